@@ -0,0 +1,33 @@
+//! Benchmark of the vector operations. Run it with and without the `simd` feature, e.g.
+//! `cargo bench --bench vec_ops --no-default-features --features simd`, to compare the
+//! vectorized and the scalar fallbacks.
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use sprsolve::vecalg::{axpy, dot, norm2, scale};
+
+fn vec_ops(c: &mut Criterion) {
+    let n = 100_000;
+    let x: Vec<f64> = (0..n).map(|i| (i % 17) as f64 * 0.1 - 0.8).collect();
+    let mut y: Vec<f64> = (0..n).map(|i| (i % 13) as f64 * 0.2 - 1.2).collect();
+
+    c.bench_function(&format!("dot-f64-{}", n), |b| {
+        b.iter(|| dot(black_box(x.as_slice()), black_box(y.as_slice())))
+    });
+    c.bench_function(&format!("norm2-f64-{}", n), |b| {
+        b.iter(|| norm2(black_box(x.as_slice())))
+    });
+    c.bench_function(&format!("axpy-f64-{}", n), |b| {
+        b.iter(|| axpy(black_box(1E-3), x.as_slice(), y.as_mut_slice()))
+    });
+    c.bench_function(&format!("scale-f64-{}", n), |b| {
+        b.iter(|| scale(black_box(1.), y.as_mut_slice()))
+    });
+
+    let xs: Vec<f32> = x.iter().map(|&v| v as f32).collect();
+    let ys: Vec<f32> = y.iter().map(|&v| v as f32).collect();
+    c.bench_function(&format!("dot-f32-{}", n), |b| {
+        b.iter(|| dot(black_box(xs.as_slice()), black_box(ys.as_slice())))
+    });
+}
+
+criterion_group!(benches, vec_ops);
+criterion_main!(benches);
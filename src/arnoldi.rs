@@ -0,0 +1,237 @@
+//! The Arnoldi process, shared by the solvers for non-symmetric systems.
+
+use super::{precond::Precond, solver::check_size, vecalg::*, MatVecMul};
+use cauchy::Scalar;
+use num_traits::{One, Zero};
+use std::{intrinsics::likely, slice::from_raw_parts_mut};
+
+/// When to orthogonalize a new Krylov vector a second time against the basis.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReorthKind {
+    /// A single pass of modified Gram-Schmidt.
+    Never,
+    /// Always a second pass, which doubles the inner products.
+    Always,
+    /// A second pass when the projection shrinks the norm of the vector by more than a
+    /// factor $\sqrt 2$, i.e. when cancellation has made it unreliable. This is the
+    /// criterion of Daniel, Gragg, Kaufman and Stewart (DGKS).
+    IfNeeded,
+}
+
+/// The DGKS criterion: reorthogonalize when `|w| < DGKS_ETA * |w_0|` after the projection.
+const DGKS_ETA: f64 = std::f64::consts::FRAC_1_SQRT_2;
+
+/// The Arnoldi process for general operators.
+///
+/// Starting from $v_1 = v / |v|$, each [`step`](Self::step) multiplies the last basis vector
+/// by $A$ and orthogonalizes the product against all the previous ones with modified
+/// Gram-Schmidt, which builds an orthonormal basis $V_{k+1}$ of the Krylov subspace and the
+/// $(k+1) \times k$ upper Hessenberg matrix $\bar H_k$ such that $AV_k = V_{k+1}\bar H_k$.
+///
+/// Up to `max_dim` steps are stored, so the workspace holds `max_dim + 1` vectors. The
+/// Hessenberg matrix is stored column by column, and a solver may transform its columns in
+/// place, e.g. GMRES applies its Givens rotations to them.
+///
+/// In finite precision, modified Gram-Schmidt loses orthogonality when the Krylov subspace
+/// becomes nearly invariant, e.g. for clustered eigenvalues. One step of reorthogonalization
+/// restores it to the working precision, see [`ReorthKind`]. By default, it is done only
+/// when needed.
+#[allow(non_snake_case)]
+pub struct Arnoldi<'data, T: Scalar, M: MatVecMul<T>, V: VecOps<T> = CpuVecOps> {
+    A: &'data M,
+    /// The basis vectors $v_1 \dots v_{k+1}$, one after another
+    basis: Vec<T>,
+    /// The `(max_dim + 1) x max_dim` Hessenberg matrix, stored column by column
+    hessenberg: Vec<T>,
+    size: usize,
+    max_dim: usize,
+    steps: usize,
+    reorth: ReorthKind,
+    ops: V,
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>> Arnoldi<'data, T, M> {
+    /// Create the process for the operator `A` of size `size`, storing up to `max_dim`
+    /// steps.
+    ///
+    /// **Panics** if `A` provides its [`shape`](MatVecMul::shape) and it differs from
+    /// `size x size`.
+    #[allow(non_snake_case)]
+    pub fn new(A: &'data M, size: usize, max_dim: usize) -> Self {
+        Self::with_vec_ops(A, size, max_dim, CpuVecOps)
+    }
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>, V: VecOps<T>> Arnoldi<'data, T, M, V> {
+    /// Create the process performing its vector operations through `ops`.
+    ///
+    /// **Panics** if `A` provides its [`shape`](MatVecMul::shape) and it differs from
+    /// `size x size`.
+    #[allow(non_snake_case)]
+    pub fn with_vec_ops(A: &'data M, size: usize, max_dim: usize, ops: V) -> Self {
+        check_size(A, size);
+        Arnoldi {
+            A,
+            basis: vec![T::zero(); size * (max_dim + 1)],
+            hessenberg: vec![T::zero(); (max_dim + 1) * max_dim],
+            size,
+            max_dim,
+            steps: 0,
+            reorth: ReorthKind::IfNeeded,
+            ops,
+        }
+    }
+
+    /// Set when a new vector is orthogonalized a second time against the basis. The default
+    /// is [`ReorthKind::IfNeeded`].
+    pub fn set_reorth(&mut self, reorth: ReorthKind) {
+        self.reorth = reorth;
+    }
+
+    pub fn reorth(&self) -> ReorthKind {
+        self.reorth
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub(crate) fn ops(&self) -> &V {
+        &self.ops
+    }
+
+    /// The maximal number of steps before the process must be restarted.
+    pub fn max_dim(&self) -> usize {
+        self.max_dim
+    }
+
+    /// The number $k$ of steps since the last [`start`](Self::start).
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    /// Restart the process from the vector `v`, which becomes $v_1 = v / |v|$, and return
+    /// $|v|$.
+    ///
+    /// When $|v|$ vanishes, there is no basis to build and the next steps are meaningless.
+    ///
+    /// **Panics** if the length of `v` differs from the operator size.
+    pub fn start(&mut self, v: &[T]) -> T::Real {
+        assert_eq!(v.len(), self.size, "Dimension mismatch");
+        self.steps = 0;
+        let v1 = &mut self.basis[..self.size];
+        v1.copy_from_slice(v);
+        let nrm = self.ops.norm2(&*v1);
+        if likely(nrm > T::Real::zero()) {
+            self.ops.rscale(T::Real::one() / nrm, v1);
+        }
+        nrm
+    }
+
+    /// Compute $v_{k+2}$ from $Av_{k+1}$ and the column $k$ of the Hessenberg matrix, and
+    /// return its subdiagonal entry $h_{k+1,k} = |w|$, where $w$ is $Av_{k+1}$ once
+    /// orthogonalized. Columns and vectors are counted from 0.
+    ///
+    /// A vanishing $h_{k+1,k}$ means that the Krylov subspace is invariant under $A$; $w$
+    /// is then stored without normalization.
+    ///
+    /// **Panics** if [`max_dim`](Self::max_dim) steps were already done since the last
+    /// [`start`](Self::start).
+    pub fn step(&mut self) -> T::Real {
+        assert!(self.steps < self.max_dim, "The Arnoldi basis is full");
+        let (n, k) = (self.size, self.steps);
+        let (v, w) = self.basis.split_at_mut((k + 1) * n);
+        unsafe {
+            self.A.mul_vec_unchecked(&v[k * n..], &mut w[..n]); // w = A v_k
+        }
+        self.orthogonalize()
+    }
+
+    /// The same as [`step`](Self::step), but with $AM^{-1}v_{k+1}$ in place of $Av_{k+1}$,
+    /// where $M^{-1}$ is applied by `precond`. The preconditioned vector $z = M^{-1}v_{k+1}$
+    /// is stored in `z`.
+    ///
+    /// Since $M$ may change from one step to the next, the relation becomes
+    /// $AZ_k = V_{k+1}\bar H_k$, and the caller keeps the vectors $z$, like
+    /// [`FGMRES`](crate::FGMRES) does.
+    ///
+    /// **Panics** if the basis is full, or if the length of `z` differs from the size.
+    pub fn step_precond<P: Precond<T> + ?Sized>(&mut self, precond: &P, z: &mut [T]) -> T::Real {
+        assert!(self.steps < self.max_dim, "The Arnoldi basis is full");
+        assert_eq!(z.len(), self.size, "Dimension mismatch");
+        let (n, k) = (self.size, self.steps);
+        let (v, w) = self.basis.split_at_mut((k + 1) * n);
+        precond.apply(&v[k * n..], &mut *z); // z = M^{-1} v_k
+        unsafe {
+            self.A.mul_vec_unchecked(&*z, &mut w[..n]); // w = A z
+        }
+        self.orthogonalize()
+    }
+
+    /// Orthogonalize the new vector $w$, stored after $v_{k+1}$, against the basis and
+    /// complete the step.
+    fn orthogonalize(&mut self) -> T::Real {
+        let (n, k) = (self.size, self.steps);
+        let ptr = self.basis.as_mut_ptr();
+        let vec = |i: usize| unsafe { from_raw_parts_mut(ptr.add(i * n), n) };
+        let w = vec(k + 1);
+        let h = &mut self.hessenberg[k * (self.max_dim + 1)..(k + 1) * (self.max_dim + 1)];
+
+        let w_norm = match self.reorth {
+            ReorthKind::IfNeeded => self.ops.norm2(&*w),
+            _ => T::Real::zero(),
+        };
+        // modified Gram-Schmidt
+        for (i, hi) in h.iter_mut().enumerate().take(k + 1) {
+            *hi = self.ops.conj_dot(&*vec(i), &*w);
+            self.ops.axpy(-*hi, &*vec(i), &mut *w);
+        }
+        let mut nrm = self.ops.norm2(&*w);
+        let second_pass = match self.reorth {
+            ReorthKind::Never => false,
+            ReorthKind::Always => true,
+            ReorthKind::IfNeeded => nrm < T::real(DGKS_ETA) * w_norm,
+        };
+        if second_pass {
+            for (i, hi) in h.iter_mut().enumerate().take(k + 1) {
+                let corr = self.ops.conj_dot(&*vec(i), &*w);
+                self.ops.axpy(-corr, &*vec(i), &mut *w);
+                *hi += corr;
+            }
+            nrm = self.ops.norm2(&*w);
+        }
+        h[k + 1] = T::from_real(nrm);
+        if likely(nrm > T::Real::zero()) {
+            self.ops.rscale(T::Real::one() / nrm, w);
+        }
+        self.steps += 1;
+        nrm
+    }
+
+    /// The basis vector $v_i$, counted from 0, for `i` up to [`steps`](Self::steps).
+    ///
+    /// **Panics** if `i` is larger than the number of steps.
+    pub fn vector(&self, i: usize) -> &[T] {
+        assert!(i <= self.steps, "The basis vector is not computed yet");
+        &self.basis[i * self.size..(i + 1) * self.size]
+    }
+
+    /// The column `j` of the Hessenberg matrix, i.e. its `j + 2` entries from the first row
+    /// down to the subdiagonal, for `j` below [`steps`](Self::steps).
+    ///
+    /// **Panics** if the column is not computed yet.
+    pub fn hessenberg_column(&self, j: usize) -> &[T] {
+        assert!(j < self.steps, "The Hessenberg column is not computed yet");
+        let offset = j * (self.max_dim + 1);
+        &self.hessenberg[offset..offset + j + 2]
+    }
+
+    /// The column `j` of the Hessenberg matrix, which can be transformed in place.
+    ///
+    /// **Panics** if the column is not computed yet.
+    pub fn hessenberg_column_mut(&mut self, j: usize) -> &mut [T] {
+        assert!(j < self.steps, "The Hessenberg column is not computed yet");
+        let offset = j * (self.max_dim + 1);
+        &mut self.hessenberg[offset..offset + j + 2]
+    }
+}
@@ -0,0 +1,222 @@
+//! An impl of the BiConjugate Gradient algorithm for linear sparse solve.
+
+use super::{
+    config::{Deadline, SolveConfig},
+    error::*,
+    report::*,
+    solver::{l2_rel_tol, replaces_residual, square_size, IterativeSolver},
+    vecalg::*,
+    MatVecMul,
+};
+use cauchy::Scalar;
+use num_traits::float::*;
+use std::{
+    intrinsics::unlikely, ops::ControlFlow, ptr::copy_nonoverlapping, slice::from_raw_parts_mut,
+};
+
+/// Implementation of the (unstabilized) BiCG algorithm for solving non-symmetric sparse
+/// linear systems.
+///
+/// Besides $A$, it applies $A^T$ to the shadow residual $\tilde r$, hence the operator must
+/// provide [`MatVecMul::mul_vec_trans`]; otherwise its error is returned by the first
+/// iteration. The two residuals are bi-orthogonal with respect to the bilinear form
+/// $\tilde r^T r$, without conjugation, which also covers complex-valued systems.
+///
+/// Its residual is not monotone, but it has no stabilizing polynomial step, which makes it a
+/// useful alternative to [`BiCGStab`](crate::BiCGStab) on problems where that step stagnates.
+#[allow(non_snake_case)]
+pub struct BiCG<'data, T: Scalar, M: MatVecMul<T>, V: VecOps<T> = CpuVecOps> {
+    A: &'data M,
+    workspace: Vec<T>,
+    size: usize,
+    ops: V,
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>> BiCG<'data, T, M> {
+    #[allow(non_snake_case)]
+    pub fn new(A: &'data M, size: usize) -> Self {
+        Self::with_vec_ops(A, size, CpuVecOps)
+    }
+
+    /// Create a solver for the operator `A`, whose size is given by its
+    /// [`shape`](MatVecMul::shape).
+    ///
+    /// Return [`SolverError::IncompatibleMatrixFormat`] if `A` is not square or doesn't provide
+    /// its shape.
+    #[allow(non_snake_case)]
+    pub fn from_op(A: &'data M) -> SolveResult<Self> {
+        Ok(Self::with_vec_ops(A, square_size(A)?, CpuVecOps))
+    }
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>, V: VecOps<T>> BiCG<'data, T, M, V> {
+    /// Create a solver performing its vector operations through `ops`.
+    #[allow(non_snake_case)]
+    pub fn with_vec_ops(A: &'data M, size: usize, ops: V) -> Self {
+        BiCG {
+            A,
+            workspace: vec![T::zero(); size * 6],
+            size,
+            ops,
+        }
+    }
+
+    /// Solves Ax = b, without preconditioner
+    ///
+    /// It returns [`SolverError::BreakDown`] when $\tilde r^T r$ or $\tilde p^T A p$ becomes
+    /// negligible compared with the norms of the vectors involved.
+    pub fn solve(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<(usize, T::Real)> {
+        self.solve_impl(rhs, x, max_iter, tol, 0, |_, _| ControlFlow::Continue(()))
+    }
+
+    /// The BiCG iterations. `monitor` is called with the iteration number and the relative
+    /// residual used by the convergence test, starting from the initial residual. The
+    /// iterations stop when it returns [`ControlFlow::Break`].
+    #[allow(clippy::many_single_char_names)]
+    fn solve_impl<F: FnMut(usize, T::Real) -> ControlFlow<()>>(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+        recompute_every: usize,
+        mut monitor: F,
+    ) -> SolveResult<(usize, T::Real)> {
+        let n = rhs.len();
+        // check the format
+        if n != self.size {
+            return Err(SolverError::IncompatibleMatrixFormat(String::from(
+                "Input vec dimension doesn't match the matrix size",
+            )));
+        }
+        if n != x.len() {
+            return Err(SolverError::IncompatibleMatrixFormat(String::from(
+                "Input and output vec dimension do not match",
+            )));
+        }
+
+        let rhs_norm = self.ops.norm2(rhs);
+        if unlikely(rhs_norm <= T::Real::epsilon()) {
+            // when rhs = 0, x is set to zero.
+            x.iter_mut().for_each(|v| *v = T::zero());
+            return Ok((0, rhs_norm));
+        }
+        let threshold = tol * rhs_norm;
+
+        // set vectors using preallocated memeory
+        let ptr = self.workspace.as_mut_ptr();
+        let r = unsafe { from_raw_parts_mut(ptr, n) }; // &mut [T]
+        let rt = unsafe { from_raw_parts_mut(ptr.add(n), n) };
+        let p = unsafe { from_raw_parts_mut(ptr.add(2 * n), n) };
+        let pt = unsafe { from_raw_parts_mut(ptr.add(3 * n), n) };
+        let q = unsafe { from_raw_parts_mut(ptr.add(4 * n), n) };
+        let qt = unsafe { from_raw_parts_mut(ptr.add(5 * n), n) };
+
+        unsafe {
+            self.A.mul_vec_unchecked(x, &mut *q); // q = A * x
+        }
+        self.ops.waxpy(-T::one(), &*q, rhs, &mut *r); // r = rhs - A*x
+        let res_norm = self.ops.norm2(&*r);
+        if unlikely(monitor(0, res_norm / rhs_norm).is_break() || res_norm <= threshold) {
+            return Ok((0, res_norm / rhs_norm));
+        }
+        unsafe {
+            copy_nonoverlapping(r.as_ptr(), rt.as_mut_ptr(), n); // r_tilde = r
+            copy_nonoverlapping(r.as_ptr(), p.as_mut_ptr(), n); // p = r
+            copy_nonoverlapping(r.as_ptr(), pt.as_mut_ptr(), n); // p_tilde = r_tilde
+        }
+        let mut rho = self.ops.dot(&*rt, &*r); // r_tilde^T r
+        if unlikely(rho.abs() <= T::Real::epsilon() * res_norm * res_norm) {
+            return Err(SolverError::BreakDown(0));
+        }
+
+        for its in 0..max_iter {
+            unsafe {
+                self.A.mul_vec_unchecked(&*p, &mut *q); // q = A * p
+            }
+            self.A.mul_vec_trans(&*pt, &mut *qt)?; // q_tilde = A^T * p_tilde
+            let sigma = self.ops.dot(&*pt, &*q); // p_tilde^T A p
+            if unlikely(sigma.abs() <= T::Real::epsilon() * T::Real::epsilon() * rho.abs()) {
+                return Err(SolverError::BreakDown(its));
+            }
+            let alpha = rho / sigma;
+            self.ops.axpy(alpha, &*p, &mut *x); // x += alpha*p
+            self.ops.axpy(-alpha, &*q, &mut *r); // r -= alpha*q
+            self.ops.axpy(-alpha, &*qt, &mut *rt); // r_tilde -= alpha*q_tilde
+
+            let mut res_norm = self.ops.norm2(&*r);
+            if replaces_residual(recompute_every, its + 1, res_norm <= threshold) {
+                unsafe {
+                    self.A.mul_vec_unchecked(x, &mut *q); // q = A * x
+                }
+                self.ops.waxpy(-T::one(), &*q, rhs, &mut *r); // r = rhs - A*x
+                res_norm = self.ops.norm2(&*r);
+            }
+            if unlikely(!res_norm.is_finite()) {
+                return Err(SolverError::Diverged(its + 1));
+            }
+            if monitor(its + 1, res_norm / rhs_norm).is_break() || res_norm <= threshold {
+                return Ok((its + 1, res_norm / rhs_norm));
+            }
+
+            let rho_new = self.ops.dot(&*rt, &*r);
+            if unlikely(rho_new.abs() <= T::Real::epsilon() * self.ops.norm2(&*rt) * res_norm) {
+                return Err(SolverError::BreakDown(its + 1));
+            }
+            let beta = rho_new / rho;
+            self.ops.axpby(T::one(), &*r, beta, &mut *p); // p = r + beta*p
+            self.ops.axpby(T::one(), &*rt, beta, &mut *pt); // p_tilde = r_tilde + beta*p_tilde
+            rho = rho_new;
+        }
+
+        Err(SolverError::InsufficientIterNum(max_iter))
+    }
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>, V: VecOps<T>> IterativeSolver<T> for BiCG<'data, T, M, V> {
+    fn solve(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<(usize, T::Real)> {
+        BiCG::solve(self, rhs, x, max_iter, tol)
+    }
+
+    fn solve_with_config(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        config: &SolveConfig<T::Real>,
+    ) -> SolveResult<(usize, T::Real)> {
+        let tol = l2_rel_tol(rhs, config)?;
+        let mut deadline = Deadline::new(config.time_limit);
+        let ret = self.solve_impl(
+            rhs,
+            x,
+            config.max_iter,
+            tol,
+            config.recompute_residual_every,
+            |its, res| deadline.monitor(its, res),
+        );
+        deadline.finish(ret)
+    }
+
+    fn solve_report(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<SolveReport<T::Real>> {
+        let ret = BiCG::solve(self, rhs, x, max_iter, tol);
+        report_outcome(self.A, &self.ops, rhs, x, &mut self.workspace, ret)
+    }
+}
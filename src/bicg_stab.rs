@@ -1,10 +1,21 @@
 //! An impl of BiCGSTAB solver.
 
-use super::{error::*, vecalg::*, MatVecMul};
+use super::{
+    config::*,
+    error::*,
+    operator::LeftPrecondOp,
+    precond::Precond,
+    report::*,
+    solver::{replaces_residual, solve_columns, square_size, IterativeSolver},
+    vecalg::*,
+    workspace::Workspace,
+    MatVecMul,
+};
 use cauchy::Scalar;
-use num_traits::{float::*, Zero};
+use num_traits::{float::*, ToPrimitive, Zero};
 use std::{
     intrinsics::{likely, unlikely},
+    ops::ControlFlow,
     ptr::copy_nonoverlapping,
     slice::from_raw_parts_mut,
 };
@@ -14,30 +25,314 @@ use std::{
 ///
 /// Note: consider to turn on `mkl` feature for improved performance.
 #[allow(non_snake_case, non_camel_case_types)]
-pub struct BiCGStab<'data, T: Scalar + Send + Sync, M: MatVecMul<T>> {
+pub struct BiCGStab<'data, T: Scalar + Send + Sync, M: MatVecMul<T>, V: VecOps<T> = CpuVecOps> {
     A: &'data M,
     workspace: Vec<T>,
     size: usize,
+    ops: V,
+    breakdown_tol: T::Real,
+    stagnation: Option<Stagnation<T::Real>>,
 }
 
 impl<'data, T: Scalar + Send + Sync, M: MatVecMul<T>> BiCGStab<'data, T, M> {
     #[allow(non_snake_case)]
     pub fn new(A: &'data M, size: usize) -> Self {
+        Self::with_vec_ops(A, size, CpuVecOps)
+    }
+
+    /// Create a solver for the operator `A`, whose size is given by its
+    /// [`shape`](MatVecMul::shape).
+    ///
+    /// Return [`SolverError::IncompatibleMatrixFormat`] if `A` is not square or doesn't provide
+    /// its shape.
+    #[allow(non_snake_case)]
+    pub fn from_op(A: &'data M) -> SolveResult<Self> {
+        Ok(Self::with_vec_ops(A, square_size(A)?, CpuVecOps))
+    }
+}
+
+impl<'data, T: Scalar + Send + Sync, M: MatVecMul<T>, V: VecOps<T>> BiCGStab<'data, T, M, V> {
+    /// Create a solver performing its vector operations through `ops`.
+    #[allow(non_snake_case)]
+    pub fn with_vec_ops(A: &'data M, size: usize, ops: V) -> Self {
         BiCGStab {
             A,
-            workspace: vec![T::zero(); size * 7],
+            workspace: vec![T::zero(); Self::workspace_len(size)],
             size,
+            ops,
+            breakdown_tol: T::Real::epsilon(),
+            stagnation: None,
         }
     }
 
+    /// The length of the [`Workspace`] needed to solve a system of `size` unknowns.
+    pub fn workspace_len(size: usize) -> usize {
+        size * 7
+    }
+
+    /// Set the relative tolerance of the breakdown checks. The default is the machine epsilon.
+    ///
+    /// - The shadow residual $\hat r_0$ is reset to the current residual when
+    ///   $|\rho| = |\hat r_0^H r| < \text{tol}\,|\hat r_0|\,|r|$.
+    /// - The solver reports [`SolverError::BreakDown`] when
+    ///   $|\hat r_0^H v| \le \text{tol}\,|\hat r_0|\,|v|$, i.e. when the step length would
+    ///   blow up.
+    ///
+    /// Both compare the cosine of the angle between the vectors with `tol`, so they don't
+    /// depend on the scaling of $A$ or $b$.
+    ///
+    /// Raising it restarts the shadow residual earlier, which trades a few extra
+    /// matrix-vector products for robustness on systems close to breakdown, and stops
+    /// the iterations sooner when no progress is possible.
+    pub fn set_breakdown_tol(&mut self, tol: T::Real) {
+        self.breakdown_tol = tol;
+    }
+
+    /// The relative tolerance of the breakdown checks. See [`set_breakdown_tol`](Self::set_breakdown_tol).
+    pub fn breakdown_tol(&self) -> T::Real {
+        self.breakdown_tol
+    }
+
+    /// Stop with [`SolverError::Stagnation`] when the residual stagnates according to
+    /// `stagnation`. `None`, the default, iterates until convergence or `max_iter`.
+    pub fn set_stagnation(&mut self, stagnation: Option<Stagnation<T::Real>>) {
+        self.stagnation = stagnation;
+    }
+
+    /// The stagnation criterion. See [`set_stagnation`](Self::set_stagnation).
+    pub fn stagnation(&self) -> Option<Stagnation<T::Real>> {
+        self.stagnation
+    }
+
     /// Solves Ax = b, without preconditioner
-    #[allow(clippy::many_single_char_names)]
     pub fn solve(
         &mut self,
         rhs: &[T],
         x: &mut [T],
         max_iter: usize,
         tol: T::Real,
+    ) -> SolveResult<(usize, T::Real)> {
+        self.solve_impl(
+            rhs,
+            x,
+            false,
+            max_iter,
+            tol,
+            T::Real::zero(),
+            NormKind::L2,
+            0,
+            |_, _| ControlFlow::Continue(()),
+        )
+    }
+
+    /// Solves Ax = b, without preconditioner, from the zero initial guess. The input
+    /// content of `x` is ignored, and `x` is overwritten by the solution.
+    ///
+    /// It gives the same result as [`solve`](Self::solve) with a zeroed `x`, but saves the
+    /// matrix-vector product computing the initial residual.
+    pub fn solve_zero_guess(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<(usize, T::Real)> {
+        self.solve_impl(
+            rhs,
+            x,
+            true,
+            max_iter,
+            tol,
+            T::Real::zero(),
+            NormKind::L2,
+            0,
+            |_, _| ControlFlow::Continue(()),
+        )
+    }
+
+    /// Solves $AX = B$ for the `n_rhs` columns of $B$, without preconditioner.
+    ///
+    /// `rhs` and `x` are column-major `size x n_rhs` arrays, and each column of `x` holds the
+    /// initial guess of its column. The columns are solved one after another, sharing this
+    /// solver and its workspace; the first failing column aborts the solve. It returns the
+    /// number of iterations and the relative residual of every column.
+    pub fn solve_multi(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        n_rhs: usize,
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<Vec<(usize, T::Real)>> {
+        solve_columns(self.size, rhs, x, n_rhs, |b, x| {
+            self.solve(b, x, max_iter, tol)
+        })
+    }
+
+    /// Solves Ax = b, without preconditioner, using the scratch memory in `workspace`
+    /// instead of the one owned by this solver.
+    ///
+    /// It borrows the solver immutably, so a solver, and its operator, can be shared by
+    /// several threads, each of them solving with its own workspace. The length of
+    /// `workspace` must be at least [`workspace_len(size)`](Self::workspace_len).
+    pub fn solve_with_workspace(
+        &self,
+        workspace: &mut Workspace<T>,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<(usize, T::Real)> {
+        if workspace.len() < Self::workspace_len(self.size) {
+            return Err(SolverError::InvalidParameter(String::from(
+                "The workspace is too short for the matrix size",
+            )));
+        }
+        self.iterate(
+            workspace.as_mut_slice(),
+            rhs,
+            x,
+            false,
+            max_iter,
+            tol,
+            T::Real::zero(),
+            NormKind::L2,
+            0,
+            |_, _| ControlFlow::Continue(()),
+        )
+    }
+
+    /// Solves Ax = b, without preconditioner, calling `callback` with the iteration number
+    /// and the relative residual used by the convergence test, starting from the initial
+    /// residual at iteration 0.
+    ///
+    /// When `callback` returns [`ControlFlow::Break`], the iterations stop and the current
+    /// iteration number and relative residual are returned; `x` holds the current iterate.
+    /// This allows custom stopping policies, e.g. stagnation detection or wall-clock limits.
+    pub fn solve_with_callback<F: FnMut(usize, T::Real) -> ControlFlow<()>>(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+        callback: F,
+    ) -> SolveResult<(usize, T::Real)> {
+        self.solve_impl(
+            rhs,
+            x,
+            false,
+            max_iter,
+            tol,
+            T::Real::zero(),
+            NormKind::L2,
+            0,
+            callback,
+        )
+    }
+
+    /// Solves Ax = b, without preconditioner, and returns the relative residual of every
+    /// iteration together with the iteration number and the final relative residual.
+    ///
+    /// The history starts with the initial relative residual, and its last entry is the
+    /// returned relative residual.
+    pub fn solve_with_history(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<(usize, T::Real, Vec<T::Real>)> {
+        let mut history = Vec::new();
+        let (its, res) = self.solve_impl(
+            rhs,
+            x,
+            false,
+            max_iter,
+            tol,
+            T::Real::zero(),
+            NormKind::L2,
+            0,
+            |_, r| {
+                history.push(r);
+                ControlFlow::Continue(())
+            },
+        )?;
+        Ok((its, res, history))
+    }
+
+    /// Solves Ax = b, without preconditioner, testing the convergence as
+    /// $|b - Ax| \le \text{tol}\,|b|$ in the norm `tol_norm`.
+    ///
+    /// The returned relative residual is measured in the same norm.
+    pub fn solve_with_norm(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+        tol_norm: NormKind,
+    ) -> SolveResult<(usize, T::Real)> {
+        self.solve_impl(
+            rhs,
+            x,
+            false,
+            max_iter,
+            tol,
+            T::Real::zero(),
+            tol_norm,
+            0,
+            |_, _| ControlFlow::Continue(()),
+        )
+    }
+
+    /// Run [`iterate`](Self::iterate) in the workspace owned by this solver.
+    #[allow(clippy::too_many_arguments)]
+    fn solve_impl<F: FnMut(usize, T::Real) -> ControlFlow<()>>(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        zero_guess: bool,
+        max_iter: usize,
+        tol: T::Real,
+        abs_tol: T::Real,
+        tol_norm: NormKind,
+        recompute_every: usize,
+        monitor: F,
+    ) -> SolveResult<(usize, T::Real)> {
+        let mut workspace = std::mem::take(&mut self.workspace);
+        let ret = self.iterate(
+            &mut workspace,
+            rhs,
+            x,
+            zero_guess,
+            max_iter,
+            tol,
+            abs_tol,
+            tol_norm,
+            recompute_every,
+            monitor,
+        );
+        self.workspace = workspace;
+        ret
+    }
+
+    /// The BiCGStab iterations, using `workspace` as scratch memory. `monitor` is called
+    /// with the iteration number and the relative residual used by the convergence test,
+    /// starting from the initial residual. The iterations stop when it returns
+    /// [`ControlFlow::Break`]. With `zero_guess`, `x` is zeroed instead of being read.
+    #[allow(clippy::many_single_char_names, clippy::too_many_arguments)]
+    fn iterate<F: FnMut(usize, T::Real) -> ControlFlow<()>>(
+        &self,
+        workspace: &mut [T],
+        rhs: &[T],
+        x: &mut [T],
+        zero_guess: bool,
+        max_iter: usize,
+        tol: T::Real,
+        abs_tol: T::Real,
+        tol_norm: NormKind,
+        recompute_every: usize,
+        mut monitor: F,
     ) -> SolveResult<(usize, T::Real)> {
         let n = rhs.len();
         // check the format
@@ -52,16 +347,21 @@ impl<'data, T: Scalar + Send + Sync, M: MatVecMul<T>> BiCGStab<'data, T, M> {
             )));
         }
 
-        let rhs_norm = norm2(rhs);
-        if unlikely(rhs_norm <= T::Real::epsilon()) {
+        let rhs_norm = tol_norm.norm(&self.ops, rhs);
+        // with an absolute tolerance, a tiny but nonzero rhs is still solved from x
+        if unlikely(
+            rhs_norm <= T::Real::epsilon()
+                && (abs_tol <= T::Real::zero() || rhs_norm == T::Real::zero()),
+        ) {
             // when rhs = 0, x is set to zero.
-            x.iter_mut().for_each(|v| *v = T::zero());
+            zero(&mut *x);
+            let _ = monitor(0, rhs_norm);
             return Ok((0, rhs_norm));
         }
-        let tol2 = tol * rhs_norm;
+        let tol2 = Float::max(tol * rhs_norm, abs_tol);
 
         // Here is the internal memeory layout
-        let ptr = self.workspace.as_mut_ptr();
+        let ptr = workspace.as_mut_ptr();
         let r = unsafe { from_raw_parts_mut(ptr, n) }; // &mut [T]
         let r0 = unsafe { from_raw_parts_mut(ptr.add(n), n) };
         let y = unsafe { from_raw_parts_mut(ptr.add(2 * n), n) };
@@ -69,23 +369,35 @@ impl<'data, T: Scalar + Send + Sync, M: MatVecMul<T>> BiCGStab<'data, T, M> {
         let t = unsafe { from_raw_parts_mut(ptr.add(4 * n), n) };
         // Without precond. we don't need s/z here.
         // let s_z = unsafe { from_raw_parts_mut(ptr.add(5 * n), n) }; // s / z
-        unsafe {
-            self.A.mul_vec_unchecked(x, &mut *r);
+        // The workspace is scratch memory: every vector is written before being read, so
+        // none of them needs to be zeroed.
+        if zero_guess {
+            // A*x vanishes, skip the product
+            zero(&mut *x);
+            r.iter_mut().zip(rhs.iter()).for_each(|(v, &b)| *v = -b); // r = -rhs
+        } else {
+            unsafe {
+                self.A.mul_vec_unchecked(x, &mut *r);
+            }
+            self.ops.axpy(-T::one(), rhs, &mut *r); // r = A*x - rhs
         }
-        axpy(-T::one(), rhs, &mut *r); // r = A*x - rhs
         unsafe {
             // r0 = r
             copy_nonoverlapping(r.as_ptr(), r0.as_mut_ptr(), n);
         }
-        let r0_norm = norm2(&*r0);
-        if unlikely(r0_norm <= tol2) {
-            return Ok((0, r0_norm / rhs_norm));
+        let r0_res = tol_norm.norm(&self.ops, &*r0);
+        if unlikely(!r0_res.is_finite()) {
+            return Err(SolverError::Diverged(0));
+        }
+        if unlikely(monitor(0, r0_res / rhs_norm).is_break() || r0_res <= tol2) {
+            return Ok((0, r0_res / rhs_norm));
         }
-        let mut r0_norm_tol = r0_norm * T::Real::epsilon();
-        r0_norm_tol = r0_norm_tol * r0_norm_tol;
+        let mut stagnation = self.stagnation.map(|s| s.detector(r0_res));
+        let r0_norm_sq = self.ops.norm2_sq(&*r0);
+        let mut r0_norm = Float::sqrt(r0_norm_sq);
 
         // unroll the first iteration to initialize variables
-        let mut rho = T::from_real(r0_norm * r0_norm); // rho != 0
+        let mut rho = T::from_real(r0_norm_sq); // rho != 0
         unsafe {
             // - y = r
             copy_nonoverlapping(r.as_ptr(), y.as_mut_ptr(), n);
@@ -93,55 +405,59 @@ impl<'data, T: Scalar + Send + Sync, M: MatVecMul<T>> BiCGStab<'data, T, M> {
             self.A.mul_vec_unchecked(&*y, &mut *v);
         }
         // alpha = rho / r0.v
-        let mut alpha = rho / conj_dot(&*r0, &*v);
+        let mut alpha = rho / self.ops.conj_dot(&*r0, &*v);
 
         // - r = r - alpha * v
         // Now r is the s in the algorithm
-        axpy(-alpha, &*v, &mut *r);
+        self.ops.axpy(-alpha, &*v, &mut *r);
 
         // - t = A * z ==> t = A * r
         unsafe {
             self.A.mul_vec_unchecked(&*r, &mut *t);
         }
         // tmp = t.t
-        let tmp = conj_dot(&*t, &*t);
+        let tmp = self.ops.conj_dot(&*t, &*t);
         let mut w = if likely(tmp.re() > T::Real::zero()) {
             // w = t.s/tmp ==> w = t.r/tmp
-            conj_dot(&*t, &*r) / tmp
+            self.ops.conj_dot(&*t, &*r) / tmp
         } else {
             T::zero()
         };
         // x = x - alpha*y - w*z
-        axpy(-alpha, &*y, &mut *x);
+        self.ops.axpy(-alpha, &*y, &mut *x);
         // Without precond: s_z (\hat s) is s, which is r
-        axpy(-w, &*r, &mut *x);
+        self.ops.axpy(-w, &*r, &mut *x);
         // r = s - w * t
         // now because r is the s, we have r = r - w*t
-        axpy(-w, &*t, &mut *r);
+        self.ops.axpy(-w, &*t, &mut *r);
 
         for its in 1..max_iter {
-            let r_norm = norm2(&*r);
-            if r_norm <= tol2 {
-                return Ok((its, r_norm / rhs_norm));
+            let mut r_norm = tol_norm.norm(&self.ops, &*r);
+            if replaces_residual(recompute_every, its, r_norm <= tol2) {
+                self.true_residual(rhs, x, &mut *r);
+                r_norm = tol_norm.norm(&self.ops, &*r);
+            }
+            if let Some(ret) =
+                check_residual_norm(its, r_norm, rhs_norm, tol2, &mut stagnation, &mut monitor)
+            {
+                return ret;
             }
             let rho_old = rho;
-            rho = conj_dot(&*r0, &*r);
+            rho = self.ops.conj_dot(&*r0, &*r);
 
-            // Here r0_norm_tol has been squared
-            if unlikely(rho.abs() < r0_norm_tol) {
-                // r = A*x
-                unsafe {
-                    self.A.mul_vec_unchecked(x, &mut *r);
-                }
-                // r = A*x - rhs
-                axpy(-T::one(), rhs, &mut *r);
+            let r_norm2 = if tol_norm == NormKind::L2 {
+                r_norm
+            } else {
+                self.ops.norm2(&*r)
+            };
+            if unlikely(rho.abs() < self.breakdown_tol * r0_norm * r_norm2) {
+                self.true_residual(rhs, x, &mut *r);
                 // r0 = r
                 unsafe {
                     copy_nonoverlapping(r.as_ptr(), r0.as_mut_ptr(), n);
                 }
-                let r_norm = norm2(&*r);
-                rho = T::from_real(r_norm * r_norm);
-                r0_norm_tol = rho.re() * T::Real::epsilon() * T::Real::epsilon();
+                rho = T::from_real(self.ops.norm2_sq(&*r));
+                r0_norm = Float::sqrt(rho.re());
             }
             let beta = (rho / rho_old) * (alpha / w);
 
@@ -152,16 +468,16 @@ impl<'data, T: Scalar + Send + Sync, M: MatVecMul<T>> BiCGStab<'data, T, M> {
             */
             // On multi-processor machine with iomp5 on, the following
             // code (using MKL's `axpby` extension) is faster.
-            axpby(-beta * w, &*v, beta, &mut *y); // beta * (y - w*v)
-            axpy(T::one(), &*r, &mut *y); // y = r + beta * (y - w*v)
+            self.ops.axpby(-beta * w, &*v, beta, &mut *y); // beta * (y - w*v)
+            self.ops.axpy(T::one(), &*r, &mut *y); // y = r + beta * (y - w*v)
 
             unsafe {
                 // - v = A*y
                 self.A.mul_vec_unchecked(&*y, &mut *v);
             }
             // alpha = rho / r0.v
-            let tmp = conj_dot(&*r0, &*v);
-            if unlikely(tmp.abs() <= T::Real::zero()) {
+            let tmp = self.ops.conj_dot(&*r0, &*v);
+            if unlikely(tmp.abs() <= self.breakdown_tol * r0_norm * self.ops.norm2(&*v)) {
                 //println!("{}", tmp);
                 return Err(SolverError::BreakDown(its));
             }
@@ -169,39 +485,142 @@ impl<'data, T: Scalar + Send + Sync, M: MatVecMul<T>> BiCGStab<'data, T, M> {
             alpha = rho / tmp;
             // - r = r - alpha * v
             // Now r is the s in the algorithm
-            axpy(-alpha, &*v, &mut *r);
+            self.ops.axpy(-alpha, &*v, &mut *r);
             // - t = A * z ==> t = A * r
             unsafe {
                 self.A.mul_vec_unchecked(&*r, &mut *t);
             }
             // tmp = t.t
-            let tmp = conj_dot(&*t, &*t);
+            let tmp = self.ops.conj_dot(&*t, &*t);
             w = if likely(tmp.re() > T::Real::zero()) {
                 // NOTE: Here we could have used `mul_vec_dot` to combine t.r with A*r above.
                 // But this won't be applicable for precond. BiCGStab method. So we don't do it for now.
                 // w = t.s/tmp ==> w = t.r/tmp
-                conj_dot(&*t, &*r) / tmp
+                self.ops.conj_dot(&*t, &*r) / tmp
             } else {
                 T::zero()
             };
             // x = x - alpha*y - w*z
-            axpy(-alpha, &*y, &mut *x); // x - alpha * y
+            self.ops.axpy(-alpha, &*y, &mut *x); // x - alpha * y
 
             // without precond: s_z (\hat s) is s, which is r
-            axpy(-w, &*r, &mut *x); // x - alpha*y - w*r
+            self.ops.axpy(-w, &*r, &mut *x); // x - alpha*y - w*r
 
             //axpy(-w, &*s_z, &mut *x); // x - alpha*y - w*z
             // r = s - w * t
             // now because r is the s, we have r = r - w*t
-            axpy(-w, &*t, &mut *r);
+            self.ops.axpy(-w, &*t, &mut *r);
         }
 
         Err(SolverError::InsufficientIterNum(max_iter))
     }
 
-    /// Solves Ax = b, with a preconditioner
+    /// r = A*x - rhs, the negated true residual, as the recursive one in the iterations
+    #[inline]
+    fn true_residual(&self, rhs: &[T], x: &[T], r: &mut [T]) {
+        unsafe {
+            self.A.mul_vec_unchecked(x, &mut *r);
+        }
+        self.ops.axpy(-T::one(), rhs, &mut *r);
+    }
+
+    /// Solves Ax = b, without preconditioner, and compares the reported residual with the
+    /// true residual $|b - Ax| / |b|$ of the solution in the returned report.
+    ///
+    /// It costs one extra matrix-vector product.
+    pub fn solve_checked(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<SolveReport<T::Real>> {
+        let ret = self.solve(rhs, x, max_iter, tol)?;
+        Ok(check_residual(
+            self.A,
+            &self.ops,
+            rhs,
+            x,
+            &mut self.workspace,
+            ret,
+        ))
+    }
+
+    /// Solves Ax = b with the preconditioner `precond`, as
+    /// [`precond_solve`](Self::precond_solve), and compares the reported residual with the
+    /// true residual $|b - Ax| / |b|$ of the solution in the returned report.
+    ///
+    /// It costs one extra matrix-vector product.
+    pub fn precond_solve_checked<P: Precond<T> + ?Sized>(
+        &mut self,
+        precond: &P,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<SolveReport<T::Real>> {
+        let ret = self.precond_solve(precond, rhs, x, max_iter, tol)?;
+        Ok(check_residual(
+            self.A,
+            &self.ops,
+            rhs,
+            x,
+            &mut self.workspace,
+            ret,
+        ))
+    }
+
+    /// Solves Ax = b, with the preconditioner applied on the given `side`.
+    ///
+    /// [`Side::Right`] is the same as [`precond_solve`](Self::precond_solve). With
+    /// [`Side::Left`], the solver runs on $M^{-1}Ax = M^{-1}b$, so the convergence test and the
+    /// returned residual use the preconditioned residual $|M^{-1}(b - Ax)| / |M^{-1}b|$. It
+    /// runs in the workspace and with the settings of this solver, and allocates $M^{-1}b$
+    /// and the scratch vector of the product $M^{-1}Av$.
+    pub fn precond_solve_side<P: Precond<T> + ?Sized>(
+        &mut self,
+        precond: &P,
+        side: Side,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<(usize, T::Real)> {
+        if side == Side::Right {
+            return self.precond_solve(precond, rhs, x, max_iter, tol);
+        }
+        let n = rhs.len();
+        if n != self.size {
+            return Err(SolverError::IncompatibleMatrixFormat(String::from(
+                "Input vec dimension doesn't match the matrix size",
+            )));
+        }
+        let mut prhs = vec![T::zero(); n];
+        precond.apply(rhs, prhs.as_mut_slice()); // M^{-1} b
+        let op = LeftPrecondOp::new(self.A, precond, n);
+        let mut left = BiCGStab {
+            A: &op,
+            workspace: std::mem::take(&mut self.workspace),
+            size: n,
+            ops: &self.ops,
+            breakdown_tol: self.breakdown_tol,
+            stagnation: self.stagnation,
+        };
+        let ret = left.solve(prhs.as_slice(), x, max_iter, tol);
+        self.workspace = left.workspace;
+        ret
+    }
+
+    /// Solves Ax = b, with a right preconditioner
+    ///
+    /// `precond` applies $M^{-1}$ to the search direction $p$ and to the intermediate
+    /// residual $s$, following the standard preconditioned BiCGStab algorithm. The
+    /// convergence test still uses the unpreconditioned residual $|b - Ax| / |b|$, so `tol`
+    /// has the same meaning as in [`solve`](Self::solve). As there, a non-finite residual
+    /// returns [`SolverError::Diverged`], and the [`stagnation`](Self::set_stagnation)
+    /// criterion applies.
     #[allow(clippy::many_single_char_names)]
-    pub fn precond_solve<P: MatVecMul<T>>(
+    pub fn precond_solve<P: Precond<T> + ?Sized>(
         &mut self,
         precond: &P,
         rhs: &[T],
@@ -222,10 +641,10 @@ impl<'data, T: Scalar + Send + Sync, M: MatVecMul<T>> BiCGStab<'data, T, M> {
             )));
         }
 
-        let rhs_norm = norm2(rhs);
+        let rhs_norm = self.ops.norm2(rhs);
         if unlikely(rhs_norm <= T::Real::epsilon()) {
             // when rhs = 0, x is set to zero.
-            x.iter_mut().for_each(|v| *v = T::zero());
+            zero(&mut *x);
             return Ok((0, rhs_norm));
         }
         let tol2 = tol * rhs_norm;
@@ -243,94 +662,96 @@ impl<'data, T: Scalar + Send + Sync, M: MatVecMul<T>> BiCGStab<'data, T, M> {
         unsafe {
             self.A.mul_vec_unchecked(x, &mut *r);
         }
-        axpy(-T::one(), rhs, &mut *r); // r = A*x - rhs
+        self.ops.axpy(-T::one(), rhs, &mut *r); // r = A*x - rhs
         unsafe {
             // r0 = r
             copy_nonoverlapping(r.as_ptr(), r0.as_mut_ptr(), n);
         }
-        let r0_norm = norm2(&*r0);
+        let r0_norm_sq = self.ops.norm2_sq(&*r0);
+        let mut r0_norm = Float::sqrt(r0_norm_sq);
+        if unlikely(!r0_norm.is_finite()) {
+            return Err(SolverError::Diverged(0));
+        }
         if unlikely(r0_norm <= tol2) {
             return Ok((0, r0_norm / rhs_norm));
         }
-        let mut r0_norm_tol = r0_norm * T::Real::epsilon();
-        r0_norm_tol = r0_norm_tol * r0_norm_tol;
+        let mut stagnation = self.stagnation.map(|s| s.detector(r0_norm));
 
         // unroll the first iteration to initialize variables
-        let mut rho = T::from_real(r0_norm * r0_norm); // rho != 0
+        let mut rho = T::from_real(r0_norm_sq); // rho != 0
         unsafe {
             copy_nonoverlapping(r.as_ptr(), p.as_mut_ptr(), n); // - p = r
-            precond.mul_vec_unchecked(&*p, &mut *y); // - y = M^{-1}p
+        }
+        precond.apply(&*p, &mut *y); // - y = M^{-1}p
+        unsafe {
             self.A.mul_vec_unchecked(&*y, &mut *v); // - v = A*y
         }
         // alpha = rho / r0.v
-        let mut alpha = rho / conj_dot(&*r0, &*v);
+        let mut alpha = rho / self.ops.conj_dot(&*r0, &*v);
         // - r = r - alpha * v
         // Now r is the s in the algorithm
-        axpy(-alpha, &*v, &mut *r);
+        self.ops.axpy(-alpha, &*v, &mut *r);
 
+        // - z = M^{-1} r
+        precond.apply(&*r, &mut *z);
         unsafe {
-            // - z = M^{-1} r
-            precond.mul_vec_unchecked(&*r, &mut *z);
             // - t = A * z
             self.A.mul_vec_unchecked(&*z, &mut *t);
         }
         // tmp = t.t
-        let tmp = conj_dot(&*t, &*t);
+        let tmp = self.ops.conj_dot(&*t, &*t);
         let mut w = if likely(tmp.re() > T::Real::zero()) {
             // w = t.s/tmp ==> w = t.r/tmp
-            conj_dot(&*t, &*r) / tmp
+            self.ops.conj_dot(&*t, &*r) / tmp
         } else {
             T::zero()
         };
 
         // x = x - alpha*y - w*z
         // x = x - alpha*y
-        axpy(-alpha, &*y, &mut *x);
+        self.ops.axpy(-alpha, &*y, &mut *x);
         // s is r: x = x - w * z
-        axpy(-w, &*z, &mut *x);
+        self.ops.axpy(-w, &*z, &mut *x);
         // r = s - w * t
         // now because r is the s, we have r = r - w*t
-        axpy(-w, &*t, &mut *r);
+        self.ops.axpy(-w, &*t, &mut *r);
 
         for its in 1..max_iter {
-            let r_norm = norm2(&*r);
-            if r_norm <= tol2 {
-                return Ok((its, r_norm / rhs_norm));
+            let r_norm = self.ops.norm2(&*r);
+            if let Some(ret) =
+                check_residual_norm(its, r_norm, rhs_norm, tol2, &mut stagnation, &mut |_, _| {
+                    ControlFlow::Continue(())
+                })
+            {
+                return ret;
             }
             let rho_old = rho;
-            rho = conj_dot(&*r0, &*r);
+            rho = self.ops.conj_dot(&*r0, &*r);
 
-            // Here r0_norm_tol has been squared
-            if unlikely(rho.abs() < r0_norm_tol) {
-                // r = A*x
-                unsafe {
-                    self.A.mul_vec_unchecked(x, &mut *r);
-                }
-                // r = A*x - rhs
-                axpy(-T::one(), rhs, &mut *r);
+            if unlikely(rho.abs() < self.breakdown_tol * r0_norm * r_norm) {
+                self.true_residual(rhs, x, &mut *r);
                 // r0 = r
                 unsafe {
                     copy_nonoverlapping(r.as_ptr(), r0.as_mut_ptr(), n);
                 }
-                let r_norm = norm2(&*r);
-                rho = T::from_real(r_norm * r_norm);
-                r0_norm_tol = rho.re() * T::Real::epsilon() * T::Real::epsilon();
+                rho = T::from_real(self.ops.norm2_sq(&*r));
+                r0_norm = Float::sqrt(rho.re());
             }
             let beta = (rho / rho_old) * (alpha / w);
 
             // p = r + beta * (p - w*v)
             // On multi-processor machine with iomp5 on, the following
             // code (using MKL's `axpby` extension) is faster.
-            axpby(-beta * w, &*v, beta, &mut *p); // beta * (p - w*v)
-            axpy(T::one(), &*r, &mut *p); // p = r + beta * (p - w*v)
+            self.ops.axpby(-beta * w, &*v, beta, &mut *p); // beta * (p - w*v)
+            self.ops.axpy(T::one(), &*r, &mut *p); // p = r + beta * (p - w*v)
 
+            precond.apply(&*p, &mut *y); // - y = M^{-1}p
             unsafe {
-                precond.mul_vec_unchecked(&*p, &mut *y); // - y = M^{-1}p
                 self.A.mul_vec_unchecked(&*y, &mut *v); // - v = A*y
             }
             // alpha = rho / r0.v
-            let tmp = conj_dot(&*r0, &*v);
-            if unlikely(tmp.abs() <= T::Real::zero()) {
+            let tmp = self.ops.conj_dot(&*r0, &*v);
+            if unlikely(tmp.abs() <= self.breakdown_tol * r0_norm * self.ops.norm2(&*v)) {
                 //println!("{}", tmp);
                 return Err(SolverError::BreakDown(its));
             }
@@ -338,30 +759,102 @@ impl<'data, T: Scalar + Send + Sync, M: MatVecMul<T>> BiCGStab<'data, T, M> {
             alpha = rho / tmp;
             // - r = r - alpha * v
             // Now r is the s in the algorithm
-            axpy(-alpha, &*v, &mut *r);
+            self.ops.axpy(-alpha, &*v, &mut *r);
+            precond.apply(&*r, &mut *z); // - z = M^{-1} * r
             unsafe {
-                precond.mul_vec_unchecked(&*r, &mut *z); // - z = M^{-1} * r
                 self.A.mul_vec_unchecked(&*z, &mut *t); // - t = A * z
             }
             // tmp = t.t
-            let tmp = conj_dot(&*t, &*t);
+            let tmp = self.ops.conj_dot(&*t, &*t);
             w = if likely(tmp.re() > T::Real::zero()) {
-                conj_dot(&*t, &*r) / tmp
+                self.ops.conj_dot(&*t, &*r) / tmp
             } else {
                 T::zero()
             };
 
             // x = x - alpha*y - w*z
-            axpy(-alpha, &*y, &mut *x); // x - alpha * y
-                                        // s is r: x = x - w * z
-            axpy(-w, &*z, &mut *x);
+            self.ops.axpy(-alpha, &*y, &mut *x); // x - alpha * y
+                                                 // s is r: x = x - w * z
+            self.ops.axpy(-w, &*z, &mut *x);
 
             //axpy(-w, &*s_z, &mut *x); // x - alpha*y - w*z
             // r = s - w * t
             // now because r is the s, we have r = r - w*t
-            axpy(-w, &*t, &mut *r);
+            self.ops.axpy(-w, &*t, &mut *r);
         }
 
         Err(SolverError::InsufficientIterNum(max_iter))
     }
 }
+
+/// The residual check at the start of an iteration, shared by the plain and the
+/// preconditioned iterations: a non-finite residual, the convergence test or `monitor`, and
+/// the stagnation criterion. Return the outcome of the solve if the iterations stop.
+#[inline]
+fn check_residual_norm<R: Float + ToPrimitive, F: FnMut(usize, R) -> ControlFlow<()>>(
+    its: usize,
+    r_norm: R,
+    rhs_norm: R,
+    tol2: R,
+    stagnation: &mut Option<StagnationDetector<R>>,
+    monitor: &mut F,
+) -> Option<SolveResult<(usize, R)>> {
+    if unlikely(!r_norm.is_finite()) {
+        return Some(Err(SolverError::Diverged(its)));
+    }
+    if monitor(its, r_norm / rhs_norm).is_break() || r_norm <= tol2 {
+        return Some(Ok((its, r_norm / rhs_norm)));
+    }
+    if let Some(d) = stagnation.as_mut() {
+        if unlikely(d.stagnated(r_norm)) {
+            return Some(Err(stagnation_error(its, r_norm / rhs_norm)));
+        }
+    }
+    None
+}
+
+impl<'data, T: Scalar + Send + Sync, M: MatVecMul<T>, V: VecOps<T>> IterativeSolver<T>
+    for BiCGStab<'data, T, M, V>
+{
+    fn solve(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<(usize, T::Real)> {
+        BiCGStab::solve(self, rhs, x, max_iter, tol)
+    }
+
+    fn solve_report(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<SolveReport<T::Real>> {
+        let ret = BiCGStab::solve(self, rhs, x, max_iter, tol);
+        report_outcome(self.A, &self.ops, rhs, x, &mut self.workspace, ret)
+    }
+
+    fn solve_with_config(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        config: &SolveConfig<T::Real>,
+    ) -> SolveResult<(usize, T::Real)> {
+        let mut deadline = Deadline::new(config.time_limit);
+        let ret = self.solve_impl(
+            rhs,
+            x,
+            false,
+            config.max_iter,
+            config.tol,
+            config.abs_tol,
+            config.norm_kind,
+            config.recompute_residual_every,
+            |its, res| deadline.monitor(its, res),
+        );
+        deadline.finish(ret)
+    }
+}
@@ -0,0 +1,273 @@
+//! An impl of the BiCGStab(l) solver.
+
+use super::{
+    config::{Deadline, SolveConfig},
+    error::*,
+    report::*,
+    solver::{l2_rel_tol, square_size, IterativeSolver},
+    vecalg::*,
+    MatVecMul,
+};
+use cauchy::Scalar;
+use num_traits::{float::*, Zero};
+use std::{
+    intrinsics::unlikely, ops::ControlFlow, ptr::copy_nonoverlapping, slice::from_raw_parts_mut,
+};
+
+/// Implementation of the BiCGStab(l) algorithm for solving non-symmetric sparse linear
+/// systems.
+///
+/// Each cycle performs `ell` BiCG steps followed by a minimal-residual polynomial of degree
+/// `ell`, where BiCGStab uses a polynomial of degree one after every BiCG step. With `ell = 2`
+/// or `4` the convergence is much smoother than BiCGStab on advection-dominated problems,
+/// whose spectra have eigenvalues with large imaginary parts. With `ell = 1` it reduces to
+/// ordinary BiCGStab.
+///
+/// See G. L. G. Sleijpen and D. R. Fokkema, *BiCGstab(l) for linear equations involving
+/// unsymmetric matrices with complex spectrum*, ETNA, 1 (1993).
+#[allow(non_snake_case)]
+pub struct BiCGStabL<'data, T: Scalar, M: MatVecMul<T>, V: VecOps<T> = CpuVecOps> {
+    A: &'data M,
+    workspace: Vec<T>,
+    size: usize,
+    ell: usize,
+    ops: V,
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>> BiCGStabL<'data, T, M> {
+    #[allow(non_snake_case)]
+    pub fn new(A: &'data M, size: usize, ell: usize) -> Self {
+        Self::with_vec_ops(A, size, ell, CpuVecOps)
+    }
+
+    /// Create a solver for the operator `A`, whose size is given by its
+    /// [`shape`](MatVecMul::shape).
+    ///
+    /// Return [`SolverError::IncompatibleMatrixFormat`] if `A` is not square or doesn't provide
+    /// its shape.
+    #[allow(non_snake_case)]
+    pub fn from_op(A: &'data M, ell: usize) -> SolveResult<Self> {
+        Ok(Self::with_vec_ops(A, square_size(A)?, ell, CpuVecOps))
+    }
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>, V: VecOps<T>> BiCGStabL<'data, T, M, V> {
+    /// Create a solver performing its vector operations through `ops`.
+    #[allow(non_snake_case)]
+    pub fn with_vec_ops(A: &'data M, size: usize, ell: usize, ops: V) -> Self {
+        BiCGStabL {
+            A,
+            // shadow residual, ell+1 residuals and ell+1 search directions
+            workspace: vec![T::zero(); size * (2 * ell + 3)],
+            size,
+            ell,
+            ops,
+        }
+    }
+
+    /// Solves Ax = b, without preconditioner
+    ///
+    /// The returned iteration number counts the BiCG steps, i.e. `ell` per cycle, so that it
+    /// is comparable with the one of [`BiCGStab`](crate::BiCGStab). The convergence is
+    /// checked at the end of every cycle.
+    pub fn solve(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<(usize, T::Real)> {
+        self.solve_impl(rhs, x, max_iter, tol, |_, _| ControlFlow::Continue(()))
+    }
+
+    /// The BiCGStab(l) iterations. `monitor` is called with the iteration number and the relative
+    /// residual used by the convergence test, starting from the initial residual. The
+    /// iterations stop when it returns [`ControlFlow::Break`].
+    #[allow(clippy::many_single_char_names)]
+    fn solve_impl<F: FnMut(usize, T::Real) -> ControlFlow<()>>(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+        mut monitor: F,
+    ) -> SolveResult<(usize, T::Real)> {
+        let n = rhs.len();
+        let ell = self.ell;
+        // check the format
+        if n != self.size {
+            return Err(SolverError::IncompatibleMatrixFormat(String::from(
+                "Input vec dimension doesn't match the matrix size",
+            )));
+        }
+        if n != x.len() {
+            return Err(SolverError::IncompatibleMatrixFormat(String::from(
+                "Input and output vec dimension do not match",
+            )));
+        }
+        if ell == 0 {
+            return Err(SolverError::InvalidParameter(String::from(
+                "The degree ell of the polynomial must be positive",
+            )));
+        }
+
+        let rhs_norm = self.ops.norm2(rhs);
+        if unlikely(rhs_norm <= T::Real::epsilon()) {
+            // when rhs = 0, x is set to zero.
+            x.iter_mut().for_each(|v| *v = T::zero());
+            return Ok((0, rhs_norm));
+        }
+        let threshold = tol * rhs_norm;
+
+        // Here is the internal memeory layout:
+        // [ r_tilde | r_0 ... r_ell | u_0 ... u_ell ]
+        let ptr = self.workspace.as_mut_ptr();
+        let vec = |k: usize| unsafe { from_raw_parts_mut(ptr.add(k * n), n) };
+        let rt = vec(0);
+        let r = |i: usize| vec(1 + i);
+        let u = |i: usize| vec(ell + 2 + i);
+
+        unsafe {
+            copy_nonoverlapping(rhs.as_ptr(), r(0).as_mut_ptr(), n); // r_0 = rhs
+            self.A.mul_vec_unchecked(x, u(0)); // u_0 = A * x
+        }
+        self.ops.axpy(-T::one(), &*u(0), r(0)); // r_0 = rhs - A*x
+        unsafe {
+            copy_nonoverlapping(r(0).as_ptr(), rt.as_mut_ptr(), n); // r_tilde = r_0
+        }
+        u(0).iter_mut().for_each(|v| *v = T::zero());
+
+        // the minimal-residual part works on small dense arrays
+        let mut tau = vec![T::zero(); (ell + 1) * (ell + 1)];
+        let mut sigma = vec![T::Real::zero(); ell + 1];
+        let mut gamma = vec![T::zero(); ell + 1];
+        let mut gamma1 = vec![T::zero(); ell + 1];
+        let mut gamma2 = vec![T::zero(); ell + 1];
+
+        let mut rho0 = T::one();
+        let mut alpha = T::zero();
+        let mut omega = T::one();
+        let breakdown_tol = T::Real::epsilon() * T::Real::epsilon();
+        let rt_norm = self.ops.norm2(&*rt);
+        let mut its = 0;
+        loop {
+            let res_norm = self.ops.norm2(&*r(0));
+            if unlikely(!res_norm.is_finite()) {
+                return Err(SolverError::Diverged(its));
+            }
+            if monitor(its, res_norm / rhs_norm).is_break() || res_norm <= threshold {
+                return Ok((its, res_norm / rhs_norm));
+            }
+            if its >= max_iter {
+                return Err(SolverError::InsufficientIterNum(max_iter));
+            }
+
+            // The BiCG part
+            rho0 = -omega * rho0;
+            for j in 0..ell {
+                let rho1 = self.ops.conj_dot(&*rt, &*r(j));
+                // r_j is (nearly) orthogonal to the shadow residual, relative to their norms
+                let rj_norm = self.ops.norm2(&*r(j));
+                if unlikely(rho1.abs() <= T::Real::epsilon() * rt_norm * rj_norm) {
+                    return Err(SolverError::BreakDown(its + j));
+                }
+                let beta = alpha * rho1 / rho0;
+                rho0 = rho1;
+                for i in 0..=j {
+                    // u_i = r_i - beta * u_i
+                    self.ops.axpby(T::one(), &*r(i), -beta, u(i));
+                }
+                unsafe {
+                    self.A.mul_vec_unchecked(&*u(j), u(j + 1));
+                }
+                let g = self.ops.conj_dot(&*rt, &*u(j + 1));
+                if unlikely(g.abs() <= breakdown_tol * rho0.abs()) {
+                    return Err(SolverError::BreakDown(its + j));
+                }
+                alpha = rho0 / g;
+                for i in 0..=j {
+                    self.ops.axpy(-alpha, &*u(i + 1), r(i)); // r_i -= alpha * u_{i+1}
+                }
+                unsafe {
+                    self.A.mul_vec_unchecked(&*r(j), r(j + 1));
+                }
+                self.ops.axpy(alpha, &*u(0), x); // x += alpha * u_0
+            }
+
+            // The minimal-residual part: modified Gram-Schmidt on r_1 ... r_ell
+            for j in 1..=ell {
+                for i in 1..j {
+                    let t = self.ops.conj_dot(&*r(i), &*r(j)) / T::from_real(sigma[i]);
+                    tau[i * (ell + 1) + j] = t;
+                    self.ops.axpy(-t, &*r(i), r(j));
+                }
+                sigma[j] = self.ops.conj_dot(&*r(j), &*r(j)).re();
+                if unlikely(sigma[j] <= T::Real::zero()) {
+                    return Err(SolverError::BreakDown(its + ell));
+                }
+                gamma1[j] = self.ops.conj_dot(&*r(j), &*r(0)) / T::from_real(sigma[j]);
+            }
+            gamma[ell] = gamma1[ell];
+            omega = gamma[ell];
+            for j in (1..ell).rev() {
+                gamma[j] = gamma1[j]
+                    - (j + 1..=ell).fold(T::zero(), |s, i| s + tau[j * (ell + 1) + i] * gamma[i]);
+            }
+            for j in 1..ell {
+                gamma2[j] = gamma[j + 1]
+                    + (j + 1..ell)
+                        .fold(T::zero(), |s, i| s + tau[j * (ell + 1) + i] * gamma[i + 1]);
+            }
+
+            // Update the iterate, the residual and the search direction
+            self.ops.axpy(gamma[1], &*r(0), x);
+            self.ops.axpy(-gamma1[ell], &*r(ell), r(0));
+            self.ops.axpy(-gamma[ell], &*u(ell), u(0));
+            for j in 1..ell {
+                self.ops.axpy(-gamma[j], &*u(j), u(0));
+                self.ops.axpy(gamma2[j], &*r(j), x);
+                self.ops.axpy(-gamma1[j], &*r(j), r(0));
+            }
+            its += ell;
+        }
+    }
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>, V: VecOps<T>> IterativeSolver<T>
+    for BiCGStabL<'data, T, M, V>
+{
+    fn solve(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<(usize, T::Real)> {
+        BiCGStabL::solve(self, rhs, x, max_iter, tol)
+    }
+
+    fn solve_with_config(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        config: &SolveConfig<T::Real>,
+    ) -> SolveResult<(usize, T::Real)> {
+        let tol = l2_rel_tol(rhs, config)?;
+        let mut deadline = Deadline::new(config.time_limit);
+        let ret = self.solve_impl(rhs, x, config.max_iter, tol, |its, res| {
+            deadline.monitor(its, res)
+        });
+        deadline.finish(ret)
+    }
+
+    fn solve_report(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<SolveReport<T::Real>> {
+        let ret = BiCGStabL::solve(self, rhs, x, max_iter, tol);
+        report_outcome(self.A, &self.ops, rhs, x, &mut self.workspace, ret)
+    }
+}
@@ -0,0 +1,360 @@
+//! An impl of the Conjugate Gradient algorithm for linear sparse solve.
+
+use super::{
+    config::{Deadline, ResidualKind, SolveConfig},
+    error::*,
+    precond::Precond,
+    report::*,
+    solver::{l2_rel_tol, replaces_residual, solve_columns, square_size, IterativeSolver},
+    vecalg::*,
+    MatVecMul,
+};
+use cauchy::Scalar;
+use num_traits::{float::*, Zero};
+use std::{
+    intrinsics::unlikely, ops::ControlFlow, ptr::copy_nonoverlapping, slice::from_raw_parts_mut,
+};
+
+/// **NOTE:** This CG solver works only for real-valued symmetric positive definite systems or
+/// complex-valued Hermitian positive definite systems.
+///
+/// **Note:** This class won't check if the input matrix is hermitian and positive definite.
+#[allow(non_snake_case)]
+pub struct CG<'data, T: Scalar, M: MatVecMul<T>, V: VecOps<T> = CpuVecOps> {
+    A: &'data M,
+    workspace: Vec<T>,
+    size: usize,
+    residual: ResidualKind,
+    ops: V,
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>> CG<'data, T, M> {
+    #[allow(non_snake_case)]
+    pub fn new(A: &'data M, size: usize) -> Self {
+        Self::with_vec_ops(A, size, CpuVecOps)
+    }
+
+    /// Create a solver for the operator `A`, whose size is given by its
+    /// [`shape`](MatVecMul::shape).
+    ///
+    /// Return [`SolverError::IncompatibleMatrixFormat`] if `A` is not square or doesn't provide
+    /// its shape.
+    #[allow(non_snake_case)]
+    pub fn from_op(A: &'data M) -> SolveResult<Self> {
+        Ok(Self::with_vec_ops(A, square_size(A)?, CpuVecOps))
+    }
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>, V: VecOps<T>> CG<'data, T, M, V> {
+    /// Create a solver performing its vector operations through `ops`.
+    #[allow(non_snake_case)]
+    pub fn with_vec_ops(A: &'data M, size: usize, ops: V) -> Self {
+        CG {
+            A,
+            workspace: vec![T::zero(); size * 4],
+            size,
+            residual: ResidualKind::True,
+            ops,
+        }
+    }
+
+    /// Set the residual compared against the tolerance by
+    /// [`precond_solve`](Self::precond_solve), which also returns it. The default is
+    /// [`ResidualKind::True`]. The preconditioned residual $z = M^{-1}r$ is computed by the
+    /// iterations anyway, so [`ResidualKind::Preconditioned`] only costs one more
+    /// application of $M^{-1}$, to $b$.
+    pub fn set_residual_kind(&mut self, residual: ResidualKind) {
+        self.residual = residual;
+    }
+
+    pub fn residual_kind(&self) -> ResidualKind {
+        self.residual
+    }
+
+    /// Solves Ax = b, without preconditioner
+    pub fn solve(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<(usize, T::Real)> {
+        self.solve_impl(None::<&M>, rhs, x, max_iter, tol, 0, |_, _, _| {
+            ControlFlow::Continue(())
+        })
+    }
+
+    /// Solves $AX = B$ for the `n_rhs` columns of $B$, without preconditioner.
+    ///
+    /// `rhs` and `x` are column-major `size x n_rhs` arrays, and each column of `x` holds the
+    /// initial guess of its column. The columns are solved one after another, sharing this
+    /// solver and its workspace; the first failing column aborts the solve. It returns the
+    /// number of iterations and the relative residual of every column.
+    pub fn solve_multi(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        n_rhs: usize,
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<Vec<(usize, T::Real)>> {
+        solve_columns(self.size, rhs, x, n_rhs, |b, x| {
+            self.solve(b, x, max_iter, tol)
+        })
+    }
+
+    /// Solves Ax = b, without preconditioner, and compares the reported residual with the
+    /// true residual $|b - Ax| / |b|$ of the solution in the returned report.
+    ///
+    /// It costs one extra matrix-vector product.
+    pub fn solve_checked(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<SolveReport<T::Real>> {
+        let ret = self.solve(rhs, x, max_iter, tol)?;
+        Ok(check_residual(
+            self.A,
+            &self.ops,
+            rhs,
+            x,
+            &mut self.workspace,
+            ret,
+        ))
+    }
+
+    /// Solves Ax = b with the preconditioner `precond`, as
+    /// [`precond_solve`](Self::precond_solve), and compares the reported residual with the
+    /// true residual $|b - Ax| / |b|$ of the solution in the returned report. With
+    /// [`ResidualKind::Preconditioned`], the gap also reflects the change of norm.
+    ///
+    /// It costs one extra matrix-vector product.
+    pub fn precond_solve_checked<P: Precond<T> + ?Sized>(
+        &mut self,
+        precond: &P,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<SolveReport<T::Real>> {
+        let ret = self.precond_solve(precond, rhs, x, max_iter, tol)?;
+        Ok(check_residual(
+            self.A,
+            &self.ops,
+            rhs,
+            x,
+            &mut self.workspace,
+            ret,
+        ))
+    }
+
+    /// Solves Ax = b, with a preconditioner
+    ///
+    /// **NOTE:** The preconditioner $M$ must be Hermitian positive definite. It is applied
+    /// symmetrically, as $M^{-1/2}AM^{-1/2}$, so there is no choice of [`Side`](crate::Side).
+    ///
+    /// The convergence test and the returned residual use the true residual
+    /// $|b - Ax| / |b|$, or $|M^{-1}(b - Ax)| / |M^{-1}b|$ after
+    /// [`set_residual_kind`](Self::set_residual_kind).
+    pub fn precond_solve<P: Precond<T> + ?Sized>(
+        &mut self,
+        precond: &P,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<(usize, T::Real)> {
+        self.solve_impl(Some(precond), rhs, x, max_iter, tol, 0, |_, _, _| {
+            ControlFlow::Continue(())
+        })
+    }
+
+    /// Solves Ax = b, without preconditioner, and returns a copy of the iterate `x` taken
+    /// after every `sample_interval` iterations, together with the iteration number and
+    /// the relative residual.
+    ///
+    /// **Note:** Each sample clones the full solution vector, so the memory cost grows as
+    /// `size * iterations / sample_interval`.
+    pub fn solve_sampling(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+        sample_interval: usize,
+    ) -> SolveResult<(usize, T::Real, Vec<Vec<T>>)> {
+        if sample_interval == 0 {
+            return Err(SolverError::InvalidParameter(String::from(
+                "The sample interval must be positive",
+            )));
+        }
+        let mut samples = Vec::new();
+        let (its, res) = self.solve_impl(None::<&M>, rhs, x, max_iter, tol, 0, |k, x, _| {
+            if k > 0 && k % sample_interval == 0 {
+                samples.push(x.to_vec());
+            }
+            ControlFlow::Continue(())
+        })?;
+        Ok((its, res, samples))
+    }
+
+    /// The CG iterations. `iterate_hook` is called with the number of completed iterations,
+    /// the current iterate $x_k$ and the relative residual used by the convergence test,
+    /// starting from the initial guess. The iterations stop when it returns
+    /// [`ControlFlow::Break`].
+    /// With a positive `recompute_every`, the residual is replaced by the true one as
+    /// described in [`SolveConfig::recompute_residual_every`].
+    #[allow(clippy::many_single_char_names, clippy::too_many_arguments)]
+    fn solve_impl<P: Precond<T> + ?Sized, F: FnMut(usize, &[T], T::Real) -> ControlFlow<()>>(
+        &mut self,
+        precond: Option<&P>,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+        recompute_every: usize,
+        mut iterate_hook: F,
+    ) -> SolveResult<(usize, T::Real)> {
+        let n = rhs.len();
+        // check the format
+        if n != self.size {
+            return Err(SolverError::IncompatibleMatrixFormat(String::from(
+                "Input vec dimension doesn't match the matrix size",
+            )));
+        }
+        if n != x.len() {
+            return Err(SolverError::IncompatibleMatrixFormat(String::from(
+                "Input and output vec dimension do not match",
+            )));
+        }
+
+        let mut rhs_norm = self.ops.norm2(rhs);
+        if unlikely(rhs_norm <= T::Real::epsilon()) {
+            // when rhs = 0, x is set to zero.
+            x.iter_mut().for_each(|v| *v = T::zero());
+            return Ok((0, rhs_norm));
+        }
+
+        // set vectors using preallocated memeory
+        let ptr = self.workspace.as_mut_ptr();
+        let r = unsafe { from_raw_parts_mut(ptr, n) }; // &mut [T]
+        let z = unsafe { from_raw_parts_mut(ptr.add(n), n) };
+        let p = unsafe { from_raw_parts_mut(ptr.add(2 * n), n) };
+        let q = unsafe { from_raw_parts_mut(ptr.add(3 * n), n) };
+
+        // with the preconditioned residual, z = M^{-1} r is computed before the
+        // convergence test instead of after it
+        let test_z = precond.is_some() && self.residual == ResidualKind::Preconditioned;
+        let apply_precond = |r: &[T], z: &mut [T]| match precond {
+            Some(pc) => pc.apply(r, z),
+            None => unsafe { copy_nonoverlapping(r.as_ptr(), z.as_mut_ptr(), n) },
+        };
+        if test_z {
+            apply_precond(rhs, &mut *z);
+            rhs_norm = self.ops.norm2(&*z); // |M^{-1} b|
+        }
+        let threshold = tol * rhs_norm;
+        // the norm of the residual used by the convergence test
+        let residual_norm = |r: &[T], z: &mut [T]| {
+            if test_z {
+                apply_precond(r, &mut *z);
+                self.ops.norm2(z)
+            } else {
+                self.ops.norm2(r)
+            }
+        };
+
+        unsafe {
+            self.A.mul_vec_unchecked(x, &mut *q); // q = A * x
+        }
+        self.ops.waxpy(-T::one(), &*q, rhs, &mut *r); // r = rhs - A*x
+        apply_precond(&*r, &mut *z); // z = M^{-1} r
+        let res_norm = self.ops.norm2(if test_z { &*z } else { &*r });
+        if unlikely(iterate_hook(0, x, res_norm / rhs_norm).is_break() || res_norm <= threshold) {
+            return Ok((0, res_norm / rhs_norm));
+        }
+        unsafe {
+            copy_nonoverlapping(z.as_ptr(), p.as_mut_ptr(), n); // p = z
+        }
+        let mut rz = self.ops.conj_dot(&*r, &*z).re(); // r^H M^{-1} r
+
+        for its in 0..max_iter {
+            // q = A*p, pq = p^H A p
+            let pq = unsafe { self.A.mul_vec_rdot_unchecked(&*p, &mut *q) };
+            if unlikely(pq <= T::Real::zero()) {
+                // A is not positive definite along p
+                return Err(SolverError::BreakDown(its));
+            }
+            let alpha = T::from_real(rz / pq);
+            self.ops.axpy(alpha, &*p, &mut *x); // x += alpha*p
+            self.ops.axpy(-alpha, &*q, &mut *r); // r -= alpha*q
+
+            let mut res_norm = residual_norm(&*r, &mut *z);
+            if replaces_residual(recompute_every, its + 1, res_norm <= threshold) {
+                unsafe {
+                    self.A.mul_vec_unchecked(x, &mut *q); // q = A * x
+                }
+                self.ops.waxpy(-T::one(), &*q, rhs, &mut *r); // r = rhs - A*x
+                res_norm = residual_norm(&*r, &mut *z);
+            }
+            if iterate_hook(its + 1, &*x, res_norm / rhs_norm).is_break() || res_norm <= threshold {
+                return Ok((its + 1, res_norm / rhs_norm));
+            }
+
+            if !test_z {
+                apply_precond(&*r, &mut *z); // z = M^{-1} r
+            }
+            let rz_new = self.ops.conj_dot(&*r, &*z).re();
+            let beta = T::from_real(rz_new / rz);
+            self.ops.axpby(T::one(), &*z, beta, &mut *p); // p = z + beta*p
+            rz = rz_new;
+        }
+
+        Err(SolverError::InsufficientIterNum(max_iter))
+    }
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>, V: VecOps<T>> IterativeSolver<T> for CG<'data, T, M, V> {
+    fn solve(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<(usize, T::Real)> {
+        CG::solve(self, rhs, x, max_iter, tol)
+    }
+
+    fn solve_with_config(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        config: &SolveConfig<T::Real>,
+    ) -> SolveResult<(usize, T::Real)> {
+        let tol = l2_rel_tol(rhs, config)?;
+        let mut deadline = Deadline::new(config.time_limit);
+        let ret = self.solve_impl(
+            None::<&M>,
+            rhs,
+            x,
+            config.max_iter,
+            tol,
+            config.recompute_residual_every,
+            |its, _, res| deadline.monitor(its, res),
+        );
+        deadline.finish(ret)
+    }
+
+    fn solve_report(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<SolveReport<T::Real>> {
+        let ret = CG::solve(self, rhs, x, max_iter, tol);
+        report_outcome(self.A, &self.ops, rhs, x, &mut self.workspace, ret)
+    }
+}
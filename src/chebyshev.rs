@@ -0,0 +1,266 @@
+//! An impl of the Chebyshev semi-iterative method for linear sparse solve.
+
+use super::{
+    error::*, estimate::lanczos_eigen_bounds, report::*, solver::square_size, vecalg::*, MatVecMul,
+    CG,
+};
+use cauchy::Scalar;
+use num_traits::{float::*, One, Zero};
+use std::{intrinsics::unlikely, ptr::copy_nonoverlapping, slice::from_raw_parts_mut};
+
+/// The iteration is considered divergent once the residual grows by this factor.
+const DIVERGENCE_FACTOR: f64 = 1E4;
+/// Number of Lanczos steps used by [`auto_chebyshev`] to estimate the spectrum.
+const AUTO_LANCZOS_STEPS: usize = 20;
+/// Relative widening of the estimated spectrum in [`auto_chebyshev`].
+const AUTO_SAFETY_FACTOR: f64 = 0.1;
+
+/// Chebyshev iteration for real-valued symmetric positive definite systems or complex-valued
+/// Hermitian positive definite systems, whose spectrum lies in a known interval
+/// $[\lambda_{min}, \lambda_{max}]$.
+///
+/// It does not need any inner product to build the iterates, which makes it attractive
+/// when reductions are expensive.
+///
+/// **Note:** This class won't check if the input matrix is hermitian and positive definite.
+#[allow(non_snake_case)]
+pub struct Chebyshev<'data, T: Scalar, M: MatVecMul<T>, V: VecOps<T> = CpuVecOps> {
+    A: &'data M,
+    workspace: Vec<T>,
+    size: usize,
+    ops: V,
+    check_interval: usize,
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>> Chebyshev<'data, T, M> {
+    #[allow(non_snake_case)]
+    pub fn new(A: &'data M, size: usize) -> Self {
+        Self::with_vec_ops(A, size, CpuVecOps)
+    }
+
+    /// Create a solver for the operator `A`, whose size is given by its
+    /// [`shape`](MatVecMul::shape).
+    ///
+    /// Return [`SolverError::IncompatibleMatrixFormat`] if `A` is not square or doesn't provide
+    /// its shape.
+    #[allow(non_snake_case)]
+    pub fn from_op(A: &'data M) -> SolveResult<Self> {
+        Ok(Self::with_vec_ops(A, square_size(A)?, CpuVecOps))
+    }
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>, V: VecOps<T>> Chebyshev<'data, T, M, V> {
+    /// Create a solver performing its vector operations through `ops`.
+    #[allow(non_snake_case)]
+    pub fn with_vec_ops(A: &'data M, size: usize, ops: V) -> Self {
+        Chebyshev {
+            A,
+            workspace: vec![T::zero(); size * 3],
+            size,
+            ops,
+            check_interval: 1,
+        }
+    }
+
+    /// Compute the residual norm, which is the only reduction of the iterations, every
+    /// `interval` iterations only. The default is 1.
+    ///
+    /// The convergence and divergence tests are done at those iterations, and at the last
+    /// one, so the returned iteration number is a multiple of `interval` unless `max_iter`
+    /// is reached. An interval of 0 is treated as 1.
+    pub fn set_check_interval(&mut self, interval: usize) {
+        self.check_interval = interval.max(1);
+    }
+
+    /// The number of iterations between two residual checks. See
+    /// [`set_check_interval`](Self::set_check_interval).
+    pub fn check_interval(&self) -> usize {
+        self.check_interval
+    }
+
+    /// Solves Ax = b, where the eigenvalues of A lie in `[lambda_min, lambda_max]`.
+    ///
+    /// It returns [`SolverError::BreakDown`] if the residual diverges, which happens when
+    /// `lambda_max` underestimates the largest eigenvalue of A.
+    #[allow(clippy::many_single_char_names)]
+    pub fn solve(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        lambda_min: T::Real,
+        lambda_max: T::Real,
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<(usize, T::Real)> {
+        let n = rhs.len();
+        // check the format
+        if n != self.size {
+            return Err(SolverError::IncompatibleMatrixFormat(String::from(
+                "Input vec dimension doesn't match the matrix size",
+            )));
+        }
+        if n != x.len() {
+            return Err(SolverError::IncompatibleMatrixFormat(String::from(
+                "Input and output vec dimension do not match",
+            )));
+        }
+        if !(lambda_min > T::Real::zero() && lambda_min < lambda_max) {
+            return Err(SolverError::InvalidParameter(String::from(
+                "Spectral bounds must satisfy 0 < lambda_min < lambda_max",
+            )));
+        }
+
+        let rhs_norm = self.ops.norm2(rhs);
+        if unlikely(rhs_norm <= T::Real::epsilon()) {
+            // when rhs = 0, x is set to zero.
+            x.iter_mut().for_each(|v| *v = T::zero());
+            return Ok((0, rhs_norm));
+        }
+        let threshold = tol * rhs_norm;
+
+        // set vectors using preallocated memeory
+        let ptr = self.workspace.as_mut_ptr();
+        let r = unsafe { from_raw_parts_mut(ptr, n) }; // &mut [T]
+        let d = unsafe { from_raw_parts_mut(ptr.add(n), n) };
+        let q = unsafe { from_raw_parts_mut(ptr.add(2 * n), n) };
+
+        unsafe {
+            self.A.mul_vec_unchecked(x, &mut *q); // q = A * x
+        }
+        self.ops.waxpy(-T::one(), &*q, rhs, &mut *r); // r = rhs - A*x
+        let r0_norm = self.ops.norm2(&*r);
+        if unlikely(r0_norm <= threshold) {
+            return Ok((0, r0_norm / rhs_norm));
+        }
+        let divergence = r0_norm * T::real(DIVERGENCE_FACTOR);
+
+        // See Algorithm 12.1 of Y. Saad, Iterative Methods for Sparse Linear Systems, Ed.2
+        let two = T::Real::one() + T::Real::one();
+        let theta = (lambda_max + lambda_min) / two;
+        let delta = (lambda_max - lambda_min) / two;
+        let sigma = theta / delta;
+        let mut rho = T::Real::one() / sigma;
+
+        unsafe {
+            copy_nonoverlapping(r.as_ptr(), d.as_mut_ptr(), n); // d = r
+        }
+        self.ops.rscale(T::Real::one() / theta, &mut *d); // d = r / theta
+
+        for its in 0..max_iter {
+            self.ops.axpy(T::one(), &*d, &mut *x); // x += d
+            unsafe {
+                self.A.mul_vec_unchecked(&*d, &mut *q); // q = A * d
+            }
+            self.ops.axpy(-T::one(), &*q, &mut *r); // r -= A * d
+
+            if (its + 1) % self.check_interval == 0 || its + 1 == max_iter {
+                let res_norm = self.ops.norm2(&*r);
+                if res_norm <= threshold {
+                    return Ok((its + 1, res_norm / rhs_norm));
+                }
+                if unlikely(res_norm > divergence || res_norm.is_nan()) {
+                    return Err(SolverError::BreakDown(its + 1));
+                }
+            }
+
+            // d = rho_new * rho * d + 2 * rho_new / delta * r
+            let rho_new = T::Real::one() / (two * sigma - rho);
+            self.ops.axpby(
+                T::from_real(two * rho_new / delta),
+                &*r,
+                T::from_real(rho_new * rho),
+                &mut *d,
+            );
+            rho = rho_new;
+        }
+
+        Err(SolverError::InsufficientIterNum(max_iter))
+    }
+
+    /// The same as [`solve`](Self::solve), and compares the reported residual with the
+    /// true residual $|b - Ax| / |b|$ of the solution in the returned report.
+    ///
+    /// It costs one extra matrix-vector product.
+    pub fn solve_checked(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        lambda_min: T::Real,
+        lambda_max: T::Real,
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<SolveReport<T::Real>> {
+        let ret = self.solve(rhs, x, lambda_min, lambda_max, max_iter, tol)?;
+        Ok(check_residual(
+            self.A,
+            &self.ops,
+            rhs,
+            x,
+            &mut self.workspace,
+            ret,
+        ))
+    }
+}
+
+/// Solves the Hermitian positive definite system Ax = b with Chebyshev iteration, without
+/// requiring the spectral bounds of A.
+///
+/// A short Lanczos run first estimates $[\lambda_{min}, \lambda_{max}]$. The estimated
+/// interval is widened by a safety factor, and the Chebyshev solver is run with it.
+///
+/// # Failure mode
+///
+/// Lanczos Ritz values lie inside the spectrum, so the estimate of $\lambda_{max}$ may be
+/// too small if the Lanczos run is too short to resolve the top of the spectrum. The error
+/// components outside the interval are then amplified and Chebyshev iteration diverges.
+/// When that happens, or when Chebyshev iteration does not converge within `max_iter`
+/// iterations, `x` is reset to the initial guess and the system is solved by [`CG`] instead.
+///
+/// The returned iteration number counts the Lanczos steps, the Chebyshev iterations and,
+/// if needed, the CG iterations.
+///
+/// `A` need not provide its [`shape`](MatVecMul::shape); if it does, a shape other than
+/// `size x size` is reported as [`SolverError::IncompatibleMatrixFormat`].
+#[allow(non_snake_case)]
+pub fn auto_chebyshev<T: Scalar, M: MatVecMul<T>>(
+    A: &M,
+    size: usize,
+    rhs: &[T],
+    x: &mut [T],
+    max_iter: usize,
+    tol: T::Real,
+) -> SolveResult<(usize, T::Real)> {
+    if rhs.len() != size || x.len() != size {
+        return Err(SolverError::IncompatibleMatrixFormat(String::from(
+            "Input vec dimension doesn't match the matrix size",
+        )));
+    }
+    if A.shape().is_some_and(|shape| shape != (size, size)) {
+        return Err(SolverError::IncompatibleMatrixFormat(String::from(
+            "The size doesn't match the operator",
+        )));
+    }
+    let steps = AUTO_LANCZOS_STEPS.min(size);
+    let (lambda_min, lambda_max) = lanczos_eigen_bounds(A, size, steps);
+    let safety = T::real(AUTO_SAFETY_FACTOR);
+    let lambda_max = lambda_max * (T::Real::one() + safety);
+    let lambda_min = lambda_min / (T::Real::one() + safety);
+
+    let x0 = x.to_vec();
+    let cheb_its = if lambda_min > T::Real::zero() && lambda_min < lambda_max {
+        match Chebyshev::new(A, size).solve(rhs, x, lambda_min, lambda_max, max_iter, tol) {
+            Ok((its, res)) => return Ok((steps + its, res)),
+            Err(SolverError::BreakDown(its)) => its,
+            Err(SolverError::InsufficientIterNum(its)) => its,
+            Err(e) => return Err(e),
+        }
+    } else {
+        // the estimate does not describe a positive definite operator
+        0
+    };
+
+    // fall back to CG
+    x.copy_from_slice(&x0);
+    let (its, res) = CG::new(A, size).solve(rhs, x, max_iter, tol)?;
+    Ok((steps + cheb_its + its, res))
+}
@@ -0,0 +1,286 @@
+//! Options shared by the solvers.
+
+use super::{
+    error::{SolveResult, SolverError},
+    vecalg::VecOps,
+};
+use cauchy::Scalar;
+use num_traits::{Float, ToPrimitive};
+use std::{
+    ops::ControlFlow,
+    time::{Duration, Instant},
+};
+
+/// The norm used by the convergence test of a solver.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NormKind {
+    /// The 2-norm $|r|_2$.
+    #[default]
+    L2,
+    /// The infinity-norm $|r|_\infty = \max_i |r_i|$, which is robust against a few large
+    /// residual entries.
+    LInf,
+}
+
+impl NormKind {
+    /// Compute the norm of `x` with `ops`.
+    #[inline]
+    pub(crate) fn norm<T: Scalar, V: VecOps<T>>(self, ops: &V, x: &[T]) -> T::Real {
+        match self {
+            NormKind::L2 => ops.norm2(x),
+            NormKind::LInf => ops.norm_inf(x),
+        }
+    }
+}
+
+/// The side on which a preconditioner $M$ is applied.
+///
+/// The solvers taking a side are:
+/// - [`BiCGStab::precond_solve_side`](crate::BiCGStab::precond_solve_side), which supports
+///   both sides;
+/// - [`FGMRES::precond_solve_side`](crate::FGMRES::precond_solve_side) and
+///   [`GCR::precond_solve_side`](crate::GCR::precond_solve_side), which support
+///   [`Side::Right`] only and reject [`Side::Left`] with
+///   [`SolverError::InvalidParameter`](crate::error::SolverError::InvalidParameter).
+///
+/// [`CG`](crate::CG) and [`MinRes`](crate::MinRes) apply their preconditioner symmetrically
+/// and take no side.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Side {
+    /// Solve $M^{-1}Ax = M^{-1}b$. The residual seen by the solver is the preconditioned
+    /// residual $M^{-1}(b - Ax)$.
+    Left,
+    /// Solve $AM^{-1}y = b$ and set $x = M^{-1}y$. The residual seen by the solver is the
+    /// true residual $b - Ax$.
+    Right,
+}
+
+/// The residual compared against the tolerance by a preconditioned solve.
+///
+/// The relative residuals $|r| / |b|$ and $|M^{-1}r| / |M^{-1}b|$ may differ by up to the
+/// condition number of $M$, in either direction. A tolerance on the true residual bounds the
+/// backward error of $x$ whatever the preconditioner, while a tolerance on the
+/// preconditioned one follows the quantity that the preconditioned iterations actually
+/// reduce, and may stop too early with an ill-conditioned $M$ or too late with a scaling
+/// one.
+///
+/// It is set on the solvers that compute $M^{-1}r$ anyway, [`CG`](crate::CG) and
+/// [`GCR`](crate::GCR). With [`Side::Left`],
+/// [`BiCGStab::precond_solve_side`](crate::BiCGStab::precond_solve_side) always tests the
+/// preconditioned residual.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ResidualKind {
+    /// The true residual $r = b - Ax$.
+    #[default]
+    True,
+    /// The preconditioned residual $z = M^{-1}r$, relative to $|M^{-1}b|$.
+    Preconditioned,
+}
+
+/// A stopping criterion detecting the stagnation of the residual.
+///
+/// The residual stagnates when $|r_k| / |r_{k-1}| > 1 - \text{tol}$ for `window` consecutive
+/// iterations. The solvers then return [`SolverError::Stagnation`](crate::error::SolverError)
+/// instead of iterating until `max_iter`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Stagnation<R> {
+    /// The minimal relative decrease of the residual per iteration.
+    pub tol: R,
+    /// The number of consecutive iterations without enough decrease.
+    pub window: usize,
+}
+
+impl<R: Float> Stagnation<R> {
+    pub fn new(tol: R, window: usize) -> Self {
+        Stagnation { tol, window }
+    }
+
+    /// Start tracking the residuals of a solve whose initial residual is `res0`.
+    pub(crate) fn detector(self, res0: R) -> StagnationDetector<R> {
+        StagnationDetector {
+            criterion: self,
+            last: res0,
+            count: 0,
+        }
+    }
+}
+
+/// Track the residuals of a solve against a [`Stagnation`] criterion.
+pub(crate) struct StagnationDetector<R> {
+    criterion: Stagnation<R>,
+    last: R,
+    count: usize,
+}
+
+impl<R: Float> StagnationDetector<R> {
+    /// Record the residual of the current iteration, and return `true` if the residual has
+    /// stagnated for `window` iterations.
+    pub(crate) fn stagnated(&mut self, res: R) -> bool {
+        if res > (R::one() - self.criterion.tol) * self.last {
+            self.count += 1;
+        } else {
+            self.count = 0;
+        }
+        self.last = res;
+        self.criterion.window > 0 && self.count >= self.criterion.window
+    }
+}
+
+/// The number of iterations between two readings of the clock by a [`Deadline`].
+const TIME_CHECK_INTERVAL: usize = 8;
+
+/// Track the wall-clock time of a solve against [`SolveConfig::time_limit`].
+///
+/// Its [`monitor`](Self::monitor) is passed as the callback of a solver, and stops the
+/// iterations once the time limit is exceeded. The clock is read only every
+/// [`TIME_CHECK_INTERVAL`] iterations, which keeps the overhead negligible.
+pub(crate) struct Deadline {
+    start: Instant,
+    limit: Option<Duration>,
+    /// The iteration and the relative residual when the limit was exceeded
+    exceeded: Option<(usize, f64)>,
+}
+
+impl Deadline {
+    /// Start the clock of a solve limited to `limit`, if any.
+    pub(crate) fn new(limit: Option<Duration>) -> Self {
+        Deadline {
+            start: Instant::now(),
+            limit,
+            exceeded: None,
+        }
+    }
+
+    /// Record the relative residual `res` of the iteration `its`, and break once the time
+    /// limit is exceeded.
+    pub(crate) fn monitor<R: ToPrimitive>(&mut self, its: usize, res: R) -> ControlFlow<()> {
+        if let Some(limit) = self.limit {
+            if its.is_multiple_of(TIME_CHECK_INTERVAL) && self.start.elapsed() > limit {
+                self.exceeded = Some((its, res.to_f64().unwrap_or(f64::NAN)));
+                return ControlFlow::Break(());
+            }
+        }
+        ControlFlow::Continue(())
+    }
+
+    /// Turn the result of a solve stopped by [`monitor`](Self::monitor) into
+    /// [`SolverError::TimeLimit`].
+    pub(crate) fn finish<R>(&self, ret: SolveResult<(usize, R)>) -> SolveResult<(usize, R)> {
+        match self.exceeded {
+            Some((iter, resid)) => Err(SolverError::TimeLimit { iter, resid }),
+            None => ret,
+        }
+    }
+}
+
+/// The parameters of a solve, set with builder methods.
+///
+/// ```
+/// # use sprsolve::{NormKind, SolveConfig};
+/// let config = SolveConfig::<f64>::default()
+///     .max_iter(500)
+///     .tol(1E-10)
+///     .norm_kind(NormKind::LInf);
+/// assert_eq!(config.max_iter, 500);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SolveConfig<R> {
+    /// The maximal number of iterations. The default is 1000.
+    pub max_iter: usize,
+    /// The tolerance on the relative residual $|b - Ax| / |b|$. The default is `1E-8`.
+    pub tol: R,
+    /// The tolerance on the absolute residual $|b - Ax|$. The default is zero, i.e. only
+    /// `tol` is used.
+    ///
+    /// The iterations stop when $|b - Ax| \le \max(\text{tol}\,|b|, \text{abs\_tol})$. The
+    /// solvers return $x = 0$ right away when $|b|$ is below the machine epsilon; with a
+    /// positive `abs_tol`, [`BiCGStab`](crate::BiCGStab) and [`MinRes`](crate::MinRes) iterate
+    /// from the initial guess instead, unless $b$ is exactly zero. The returned residual stays
+    /// relative to $|b|$.
+    pub abs_tol: R,
+    /// The norm used by the convergence test. The default is [`NormKind::L2`].
+    pub norm_kind: NormKind,
+    /// The restart length of the restarted methods. It is ignored by the other solvers.
+    /// The default is 30.
+    pub restart: usize,
+    /// The wall-clock time limit of a solve. The default is `None`, i.e. no limit.
+    ///
+    /// The clock is read every few iterations, and the solvers return
+    /// [`SolverError::TimeLimit`] once it is exceeded, with `x` holding the last iterate.
+    /// Every [`IterativeSolver`](crate::IterativeSolver) of this crate supports it; a solver
+    /// relying on the default `solve_with_config` returns [`SolverError::InvalidParameter`].
+    pub time_limit: Option<Duration>,
+    /// Replace the recursively updated residual by the true residual $b - Ax$ every
+    /// `recompute_residual_every` iterations, and before accepting the convergence. The
+    /// default is `0`, i.e. never.
+    ///
+    /// Over many iterations, the recursive residual drifts from the true one, and may report
+    /// the convergence while $|b - Ax|$ is still above the tolerance, e.g. on ill-conditioned
+    /// systems. Each replacement costs one extra matrix-vector product. It is honoured by
+    /// [`CG`](crate::CG), [`CR`](crate::CR), [`BiCG`](crate::BiCG),
+    /// [`BiCGStab`](crate::BiCGStab) and [`GCR`](crate::GCR), and ignored by the other solvers.
+    pub recompute_residual_every: usize,
+}
+
+impl<R: Float> Default for SolveConfig<R> {
+    fn default() -> Self {
+        SolveConfig {
+            max_iter: 1000,
+            tol: R::from(1E-8).unwrap(),
+            abs_tol: R::zero(),
+            norm_kind: NormKind::L2,
+            restart: 30,
+            time_limit: None,
+            recompute_residual_every: 0,
+        }
+    }
+}
+
+impl<R: Float> SolveConfig<R> {
+    pub fn max_iter(mut self, max_iter: usize) -> Self {
+        self.max_iter = max_iter;
+        self
+    }
+
+    pub fn tol(mut self, tol: R) -> Self {
+        self.tol = tol;
+        self
+    }
+
+    pub fn abs_tol(mut self, abs_tol: R) -> Self {
+        self.abs_tol = abs_tol;
+        self
+    }
+
+    pub fn norm_kind(mut self, norm_kind: NormKind) -> Self {
+        self.norm_kind = norm_kind;
+        self
+    }
+
+    pub fn restart(mut self, restart: usize) -> Self {
+        self.restart = restart;
+        self
+    }
+
+    pub fn time_limit(mut self, time_limit: Duration) -> Self {
+        self.time_limit = Some(time_limit);
+        self
+    }
+
+    pub fn recompute_residual_every(mut self, k: usize) -> Self {
+        self.recompute_residual_every = k;
+        self
+    }
+
+    /// The relative tolerance enforcing both `tol` and `abs_tol`, for a right-hand side of
+    /// norm `rhs_norm`.
+    pub(crate) fn rel_tol(&self, rhs_norm: R) -> R {
+        if self.abs_tol > R::zero() && rhs_norm > R::zero() {
+            self.tol.max(self.abs_tol / rhs_norm)
+        } else {
+            self.tol
+        }
+    }
+}
@@ -0,0 +1,219 @@
+//! An impl of the Conjugate Residual algorithm for linear sparse solve.
+
+use super::{
+    config::{Deadline, SolveConfig},
+    error::*,
+    report::*,
+    solver::{l2_rel_tol, replaces_residual, square_size, IterativeSolver},
+    vecalg::*,
+    MatVecMul,
+};
+use cauchy::Scalar;
+use num_traits::float::*;
+use std::{
+    intrinsics::unlikely, ops::ControlFlow, ptr::copy_nonoverlapping, slice::from_raw_parts_mut,
+};
+
+/// Implementation of the Conjugate Residual algorithm for solving real-valued symmetric or
+/// complex-valued Hermitian, possibly indefinite, sparse linear systems.
+///
+/// Like [`MinRes`](crate::MinRes), it minimizes the residual over the Krylov subspace, so the
+/// residual norm decreases monotonically, but it needs only four vectors and one
+/// matrix-vector product per iteration. Unlike MINRES, it breaks down when $r^H A r$
+/// vanishes, which may happen for indefinite matrices.
+///
+/// **Note:** This class won't check if the input matrix is Hermitian.
+#[allow(non_snake_case)]
+pub struct CR<'data, T: Scalar, M: MatVecMul<T>, V: VecOps<T> = CpuVecOps> {
+    A: &'data M,
+    workspace: Vec<T>,
+    size: usize,
+    ops: V,
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>> CR<'data, T, M> {
+    #[allow(non_snake_case)]
+    pub fn new(A: &'data M, size: usize) -> Self {
+        Self::with_vec_ops(A, size, CpuVecOps)
+    }
+
+    /// Create a solver for the operator `A`, whose size is given by its
+    /// [`shape`](MatVecMul::shape).
+    ///
+    /// Return [`SolverError::IncompatibleMatrixFormat`] if `A` is not square or doesn't provide
+    /// its shape.
+    #[allow(non_snake_case)]
+    pub fn from_op(A: &'data M) -> SolveResult<Self> {
+        Ok(Self::with_vec_ops(A, square_size(A)?, CpuVecOps))
+    }
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>, V: VecOps<T>> CR<'data, T, M, V> {
+    /// Create a solver performing its vector operations through `ops`.
+    #[allow(non_snake_case)]
+    pub fn with_vec_ops(A: &'data M, size: usize, ops: V) -> Self {
+        CR {
+            A,
+            workspace: vec![T::zero(); size * 4],
+            size,
+            ops,
+        }
+    }
+
+    /// Solves Ax = b, without preconditioner
+    ///
+    /// It returns [`SolverError::BreakDown`] when $r^H A r$ or $|Ap|$ becomes negligible
+    /// compared with the norms of the vectors involved.
+    pub fn solve(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<(usize, T::Real)> {
+        self.solve_impl(rhs, x, max_iter, tol, 0, |_, _| ControlFlow::Continue(()))
+    }
+
+    /// The CR iterations. `monitor` is called with the iteration number and the relative
+    /// residual used by the convergence test, starting from the initial residual. The
+    /// iterations stop when it returns [`ControlFlow::Break`].
+    #[allow(clippy::many_single_char_names)]
+    fn solve_impl<F: FnMut(usize, T::Real) -> ControlFlow<()>>(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+        recompute_every: usize,
+        mut monitor: F,
+    ) -> SolveResult<(usize, T::Real)> {
+        let n = rhs.len();
+        // check the format
+        if n != self.size {
+            return Err(SolverError::IncompatibleMatrixFormat(String::from(
+                "Input vec dimension doesn't match the matrix size",
+            )));
+        }
+        if n != x.len() {
+            return Err(SolverError::IncompatibleMatrixFormat(String::from(
+                "Input and output vec dimension do not match",
+            )));
+        }
+
+        let rhs_norm = self.ops.norm2(rhs);
+        if unlikely(rhs_norm <= T::Real::epsilon()) {
+            // when rhs = 0, x is set to zero.
+            x.iter_mut().for_each(|v| *v = T::zero());
+            return Ok((0, rhs_norm));
+        }
+        let threshold = tol * rhs_norm;
+
+        // set vectors using preallocated memeory
+        let ptr = self.workspace.as_mut_ptr();
+        let r = unsafe { from_raw_parts_mut(ptr, n) }; // &mut [T]
+        let p = unsafe { from_raw_parts_mut(ptr.add(n), n) };
+        let ar = unsafe { from_raw_parts_mut(ptr.add(2 * n), n) };
+        let ap = unsafe { from_raw_parts_mut(ptr.add(3 * n), n) };
+
+        unsafe {
+            self.A.mul_vec_unchecked(x, &mut *ar); // ar = A * x
+        }
+        self.ops.waxpy(-T::one(), &*ar, rhs, &mut *r); // r = rhs - A*x
+        let res_norm = self.ops.norm2(&*r);
+        if unlikely(monitor(0, res_norm / rhs_norm).is_break() || res_norm <= threshold) {
+            return Ok((0, res_norm / rhs_norm));
+        }
+        let mut rho = unsafe { self.A.mul_vec_dot_unchecked(&*r, &mut *ar) }; // r^H A r
+        let mut ar_norm = self.ops.norm2(&*ar);
+        if unlikely(rho.abs() <= T::Real::epsilon() * res_norm * ar_norm) {
+            return Err(SolverError::BreakDown(0));
+        }
+        unsafe {
+            copy_nonoverlapping(r.as_ptr(), p.as_mut_ptr(), n); // p = r
+            copy_nonoverlapping(ar.as_ptr(), ap.as_mut_ptr(), n); // Ap = Ar
+        }
+
+        for its in 0..max_iter {
+            let ap_norm_sq = self.ops.norm2_sq(&*ap);
+            if unlikely(!ap_norm_sq.is_finite()) {
+                return Err(SolverError::Diverged(its));
+            }
+            let eps_ar = T::Real::epsilon() * ar_norm;
+            if unlikely(ap_norm_sq <= eps_ar * eps_ar) {
+                return Err(SolverError::BreakDown(its));
+            }
+            let alpha = rho / T::from_real(ap_norm_sq);
+            self.ops.axpy(alpha, &*p, &mut *x); // x += alpha*p
+            self.ops.axpy(-alpha, &*ap, &mut *r); // r -= alpha*Ap
+
+            let mut res_norm = self.ops.norm2(&*r);
+            if replaces_residual(recompute_every, its + 1, res_norm <= threshold) {
+                unsafe {
+                    self.A.mul_vec_unchecked(x, &mut *ar); // ar = A * x
+                }
+                self.ops.waxpy(-T::one(), &*ar, rhs, &mut *r); // r = rhs - A*x
+                res_norm = self.ops.norm2(&*r);
+            }
+            if unlikely(!res_norm.is_finite()) {
+                return Err(SolverError::Diverged(its + 1));
+            }
+            if monitor(its + 1, res_norm / rhs_norm).is_break() || res_norm <= threshold {
+                return Ok((its + 1, res_norm / rhs_norm));
+            }
+
+            let rho_new = unsafe { self.A.mul_vec_dot_unchecked(&*r, &mut *ar) };
+            ar_norm = self.ops.norm2(&*ar);
+            if unlikely(rho_new.abs() <= T::Real::epsilon() * res_norm * ar_norm) {
+                return Err(SolverError::BreakDown(its + 1));
+            }
+            let beta = rho_new / rho;
+            self.ops.axpby(T::one(), &*r, beta, &mut *p); // p = r + beta*p
+            self.ops.axpby(T::one(), &*ar, beta, &mut *ap); // Ap = Ar + beta*Ap
+            rho = rho_new;
+        }
+
+        Err(SolverError::InsufficientIterNum(max_iter))
+    }
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>, V: VecOps<T>> IterativeSolver<T> for CR<'data, T, M, V> {
+    fn solve(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<(usize, T::Real)> {
+        CR::solve(self, rhs, x, max_iter, tol)
+    }
+
+    fn solve_with_config(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        config: &SolveConfig<T::Real>,
+    ) -> SolveResult<(usize, T::Real)> {
+        let tol = l2_rel_tol(rhs, config)?;
+        let mut deadline = Deadline::new(config.time_limit);
+        let ret = self.solve_impl(
+            rhs,
+            x,
+            config.max_iter,
+            tol,
+            config.recompute_residual_every,
+            |its, res| deadline.monitor(its, res),
+        );
+        deadline.finish(ret)
+    }
+
+    fn solve_report(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<SolveReport<T::Real>> {
+        let ret = CR::solve(self, rhs, x, max_iter, tol);
+        report_outcome(self.A, &self.ops, rhs, x, &mut self.workspace, ret)
+    }
+}
@@ -1,37 +1,115 @@
 //! An impl of MINRES algorithm for complex symmetric system
 
-use super::{error::*, vecalg::*, MatVecMul};
+use super::{
+    config::{Deadline, SolveConfig},
+    error::*,
+    report::*,
+    solver::{l2_rel_tol, square_size, IterativeSolver},
+    vecalg::*,
+    MatVecMul,
+};
 use cauchy::Scalar;
 use num_traits::{float::*, One, Zero};
-use std::{intrinsics::unlikely, ptr::copy_nonoverlapping, slice::from_raw_parts_mut};
+use std::{
+    intrinsics::unlikely, ops::ControlFlow, ptr::copy_nonoverlapping, slice::from_raw_parts_mut,
+};
 
 /// This solver solves a complex symmetric system, using the Saunders process
 /// (instead of Lanczos process)
+///
+/// The matrix must be complex symmetric, $A^T = A$, and need not be Hermitian: in general
+/// $A^H \ne A$, so [`MinRes`](crate::MinRes) does not apply. The Saunders process
+/// $\beta_kq_{k+1} = A\bar q_k - \alpha_kq_k - \beta_{k-1}q_{k-1}$ multiplies $A$ with the
+/// conjugated basis vector, and $\alpha_k = q_k^HA\bar q_k$ is computed as
+/// `conj_dot(q_k, A * conj(q_k))`. It is the symmetry $A^T = A$, not $A^H = A$, that makes this
+/// recurrence short and keeps the basis vectors $q_k$ orthonormal in the usual inner product
+/// $x^Hy$.
+///
+/// For real symmetric or complex Hermitian systems, use [`MinRes`](crate::MinRes) instead.
+///
+/// **Note:** This class won't check if the input matrix is complex symmetric.
 #[allow(non_snake_case)]
-pub struct CSMinRes<'data, T: Scalar, M: MatVecMul<T>> {
+pub struct CSMinRes<'data, T: Scalar, M: MatVecMul<T>, V: VecOps<T> = CpuVecOps> {
     A: &'data M,
     workspace: Vec<T>,
     size: usize,
+    ops: V,
 }
 
 impl<'data, T: Scalar, M: MatVecMul<T>> CSMinRes<'data, T, M> {
+    /// Create a solver for the `size`-by-`size` complex symmetric matrix `A`, like
+    /// [`MinRes::new`](crate::MinRes::new). It preallocates a workspace of `7 * size` entries.
     #[allow(non_snake_case)]
     pub fn new(A: &'data M, size: usize) -> Self {
+        Self::with_vec_ops(A, size, CpuVecOps)
+    }
+
+    /// Create a solver for the operator `A`, whose size is given by its
+    /// [`shape`](MatVecMul::shape).
+    ///
+    /// Return [`SolverError::IncompatibleMatrixFormat`] if `A` is not square or doesn't provide
+    /// its shape.
+    #[allow(non_snake_case)]
+    pub fn from_op(A: &'data M) -> SolveResult<Self> {
+        Ok(Self::with_vec_ops(A, square_size(A)?, CpuVecOps))
+    }
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>, V: VecOps<T>> CSMinRes<'data, T, M, V> {
+    /// Create a solver performing its vector operations through `ops`.
+    #[allow(non_snake_case)]
+    pub fn with_vec_ops(A: &'data M, size: usize, ops: V) -> Self {
         CSMinRes {
             A,
             workspace: vec![T::zero(); size * 7],
             size,
+            ops,
         }
     }
 
-    /// Solves Ax = b, without preconditioner
-    #[allow(clippy::many_single_char_names)]
+    /// Solves Ax = b, without preconditioner, and returns the iteration number and the
+    /// relative residual $|b - Ax| / |b|$ estimated by the recurrence.
     pub fn solve(
         &mut self,
         rhs: &[T],
         x: &mut [T],
         max_iter: usize,
         tol: T::Real,
+    ) -> SolveResult<(usize, T::Real)> {
+        self.solve_impl(rhs, x, max_iter, tol, |_, _| ControlFlow::Continue(()))
+    }
+
+    /// Solves Ax = b, without preconditioner, and returns the relative residual of every
+    /// iteration together with the iteration number and the final relative residual.
+    ///
+    /// The history starts with the initial relative residual, and its last entry is the
+    /// returned relative residual.
+    pub fn solve_with_history(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<(usize, T::Real, Vec<T::Real>)> {
+        let mut history = Vec::new();
+        let (its, res) = self.solve_impl(rhs, x, max_iter, tol, |_, r| {
+            history.push(r);
+            ControlFlow::Continue(())
+        })?;
+        Ok((its, res, history))
+    }
+
+    /// The MINRES iterations. `monitor` is called with the number of completed iterations
+    /// and the relative residual estimated by the recurrence, starting from the initial
+    /// residual. The iterations stop when it returns [`ControlFlow::Break`].
+    #[allow(clippy::many_single_char_names)]
+    fn solve_impl<F: FnMut(usize, T::Real) -> ControlFlow<()>>(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+        mut monitor: F,
     ) -> SolveResult<(usize, T::Real)> {
         let n = rhs.len();
         // check the format
@@ -46,10 +124,11 @@ impl<'data, T: Scalar, M: MatVecMul<T>> CSMinRes<'data, T, M> {
             )));
         }
 
-        let rhs_norm = norm2(rhs);
+        let rhs_norm = self.ops.norm2(rhs);
         if unlikely(rhs_norm <= T::Real::epsilon()) {
             // when rhs = 0, x is set to zero.
             x.iter_mut().for_each(|v| *v = T::zero());
+            let _ = monitor(0, rhs_norm);
             return Ok((0, rhs_norm));
         }
         let threshold = tol * rhs_norm;
@@ -76,11 +155,14 @@ impl<'data, T: Scalar, M: MatVecMul<T>> CSMinRes<'data, T, M> {
             copy_nonoverlapping(rhs.as_ptr(), v_new.as_mut_ptr(), n); // v_new = rhs
             self.A.mul_vec_unchecked(x, &mut *v_old); // v_old = A * x
         }
-        axpy(-T::one(), &*v_old, &mut *v_new); // v_new = rhs - A*x >>> r_1
-        let mut res_norm = norm2(&*v_new);
+        self.ops.axpy(-T::one(), &*v_old, &mut *v_new); // v_new = rhs - A*x >>> r_1
+        let mut res_norm = self.ops.norm2(&*v_new);
+        if monitor(0, res_norm / rhs_norm).is_break() {
+            return Ok((0, res_norm / rhs_norm));
+        }
         let mut beta_new = res_norm;
         let beta_one = beta_new;
-        rscale(T::Real::one() / beta_new, &mut *v_new); // >>> v_new is now q_1
+        self.ops.rscale(T::Real::one() / beta_new, &mut *v_new); // >>> v_new is now q_1
 
         v.iter_mut().for_each(|t| *t = T::zero()); // v = zero
         p_old.iter_mut().for_each(|t| *t = T::zero()); // p_old = zero
@@ -96,15 +178,15 @@ impl<'data, T: Scalar, M: MatVecMul<T>> CSMinRes<'data, T, M> {
 
             // comptue v_new = A * conj(v)    >>> v is q_k
             //         alpha = conj(v).v_new
-            conj(&*v, &mut *tvec); // tvec = conj(q_k)
+            self.ops.conj(&*v, &mut *tvec); // tvec = conj(q_k)
             unsafe {
                 self.A.mul_vec_unchecked(tvec, &mut *v_new); // v_new = A * conj(q_k)
             }
-            let alpha = conj_dot(&*v, &*v_new);
-            axpy(T::from_real(-beta), &*v_old, &mut *v_new); // >>> A*conj(q_k) - beta_{k-1} q_{k-1}
-            axpy(-alpha, &*v, &mut *v_new); // v_new = A*conj(q_k) - beta_{k-1}q_{k-1} - alpha*q_k  >>> v_new is now r_k
-            beta_new = norm2(&*v_new); // beta_new = |v_new|    >>> beta_new is beta_k
-            rscale(T::Real::one() / beta_new, &mut *v_new); // >>> v_new is now q_{k+1}
+            let alpha = self.ops.conj_dot(&*v, &*v_new);
+            self.ops.axpy(T::from_real(-beta), &*v_old, &mut *v_new); // >>> A*conj(q_k) - beta_{k-1} q_{k-1}
+            self.ops.axpy(-alpha, &*v, &mut *v_new); // v_new = A*conj(q_k) - beta_{k-1}q_{k-1} - alpha*q_k  >>> v_new is now r_k
+            beta_new = self.ops.norm2(&*v_new); // beta_new = |v_new|    >>> beta_new is beta_k
+            self.ops.rscale(T::Real::one() / beta_new, &mut *v_new); // >>> v_new is now q_{k+1}
 
             // --- Givens rotation ---
             // NOTE: s is always real number
@@ -141,19 +223,83 @@ impl<'data, T: Scalar, M: MatVecMul<T>> CSMinRes<'data, T, M> {
             unsafe {
                 copy_nonoverlapping(tvec.as_ptr(), p.as_mut_ptr(), n); // p = tvec = conj(q_k)
             }
-            axpy(-r2, &*p_old, &mut *p); // p = conj(q_k) - r2*p_old
-            axpy(T::from_real(-r3), &*p_oold, &mut *p); // p = conj(q_k) - r2*p_old - r3*p_oold
-            rscale(r1_inv, &mut *p);
+            self.ops.axpy(-r2, &*p_old, &mut *p); // p = conj(q_k) - r2*p_old
+            self.ops.axpy(T::from_real(-r3), &*p_oold, &mut *p); // p = conj(q_k) - r2*p_old - r3*p_oold
+            self.ops.rscale(r1_inv, &mut *p);
 
-            axpy((c * eta).mul_real(beta_one), &*p, &mut *x); //  x += beta_one*c*eta*p
+            self.ops.axpy((c * eta).mul_real(beta_one), &*p, &mut *x); //  x += beta_one*c*eta*p
 
             res_norm *= num_traits::Float::abs(s);
+            if monitor(its + 1, res_norm / rhs_norm).is_break() {
+                return Ok((its + 1, res_norm / rhs_norm));
+            }
             if res_norm < threshold {
-                return Ok((its, res_norm / rhs_norm));
+                return Ok((its + 1, res_norm / rhs_norm));
             }
             eta = eta.mul_real(-s);
         }
 
         Err(SolverError::InsufficientIterNum(max_iter))
     }
+
+    /// Solves Ax = b, without preconditioner, and compares the reported residual with the
+    /// true residual $|b - Ax| / |b|$ of the solution in the returned report.
+    ///
+    /// It costs one extra matrix-vector product.
+    pub fn solve_checked(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<SolveReport<T::Real>> {
+        let ret = self.solve(rhs, x, max_iter, tol)?;
+        Ok(check_residual(
+            self.A,
+            &self.ops,
+            rhs,
+            x,
+            &mut self.workspace,
+            ret,
+        ))
+    }
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>, V: VecOps<T>> IterativeSolver<T>
+    for CSMinRes<'data, T, M, V>
+{
+    fn solve(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<(usize, T::Real)> {
+        CSMinRes::solve(self, rhs, x, max_iter, tol)
+    }
+
+    fn solve_with_config(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        config: &SolveConfig<T::Real>,
+    ) -> SolveResult<(usize, T::Real)> {
+        let tol = l2_rel_tol(rhs, config)?;
+        let mut deadline = Deadline::new(config.time_limit);
+        let ret = self.solve_impl(rhs, x, config.max_iter, tol, |its, res| {
+            deadline.monitor(its, res)
+        });
+        deadline.finish(ret)
+    }
+
+    fn solve_report(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<SolveReport<T::Real>> {
+        let ret = CSMinRes::solve(self, rhs, x, max_iter, tol);
+        report_outcome(self.A, &self.ops, rhs, x, &mut self.workspace, ret)
+    }
 }
@@ -0,0 +1,465 @@
+//! Spectral information carried across a sequence of solves.
+//!
+//! When a sequence of systems with slowly varying matrices is solved (e.g., a homotopy or a
+//! parametric sweep), the eigenvectors associated with the eigenvalues of smallest magnitude
+//! change little from one system to the next. These are the components that slow down
+//! Krylov solvers the most. A [`SpectralCache`] keeps approximations (Ritz vectors) of them,
+//! populated at the end of a solve and consumed by the next one to deflate the initial
+//! guess.
+
+use super::{vecalg::*, MatVecMul};
+use cauchy::Scalar;
+use num_traits::{float::*, One, Zero};
+
+/// A cache of Ritz vectors shared by a sequence of solves.
+///
+/// The cache is populated by [`MinRes::solve_with_cache`](crate::MinRes::solve_with_cache),
+/// which records the first `max_basis` Lanczos vectors of the solve, extracts the Ritz
+/// vectors of smallest Ritz value magnitude, and merges them with the vectors already in the
+/// cache by a Rayleigh-Ritz projection. At the beginning of the next solve, the initial guess
+/// is corrected by a Galerkin projection onto the cached vectors, which removes those
+/// components from the initial residual.
+///
+/// **NOTE:** Only Hermitian (or real symmetric) matrices are supported, since the Ritz
+/// values are computed from the Lanczos tridiagonal matrix.
+pub struct SpectralCache<T: Scalar> {
+    size: usize,
+    nev: usize,
+    max_basis: usize,
+    /// Ritz vectors stored one after another, each of length `size`
+    vectors: Vec<T>,
+    values: Vec<T::Real>,
+}
+
+impl<T: Scalar> SpectralCache<T> {
+    /// Create an empty cache for systems of dimension `size`.
+    ///
+    /// At most `nev` Ritz vectors are kept, and they are extracted from the first `max_basis`
+    /// Lanczos vectors of a solve.
+    pub fn new(size: usize, nev: usize, max_basis: usize) -> Self {
+        assert!(nev > 0 && max_basis >= nev);
+        SpectralCache {
+            size,
+            nev,
+            max_basis,
+            vectors: Vec::with_capacity(size * nev),
+            values: Vec::with_capacity(nev),
+        }
+    }
+
+    /// Return the dimension of the vectors in the cache.
+    #[inline]
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Return the number of Ritz vectors currently stored.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Return the number of Lanczos vectors recorded during a solve.
+    #[inline]
+    pub fn max_basis(&self) -> usize {
+        self.max_basis
+    }
+
+    /// Return the Ritz values, sorted by increasing magnitude.
+    #[inline]
+    pub fn ritz_values(&self) -> &[T::Real] {
+        &self.values
+    }
+
+    /// Return the `i`-th Ritz vector.
+    #[inline]
+    pub fn ritz_vector(&self, i: usize) -> &[T] {
+        &self.vectors[i * self.size..(i + 1) * self.size]
+    }
+
+    /// Remove all the cached vectors.
+    pub fn clear(&mut self) {
+        self.vectors.clear();
+        self.values.clear();
+    }
+
+    /// Correct `x` by the Galerkin projection onto the cached vectors $W$:
+    /// $x \leftarrow x + W (W^H A W)^{-1} W^H (b - Ax)$.
+    ///
+    /// `work` must hold at least `size` elements.
+    #[allow(non_snake_case)]
+    pub(crate) fn deflate_initial_guess<M: MatVecMul<T>, V: VecOps<T>>(
+        &self,
+        A: &M,
+        ops: &V,
+        rhs: &[T],
+        x: &mut [T],
+        work: &mut [T],
+    ) {
+        let k = self.len();
+        if k == 0 {
+            return;
+        }
+        let n = self.size;
+        let r = &mut work[..n];
+
+        // r = b - A*x
+        unsafe {
+            A.mul_vec_unchecked(x, &mut *r);
+        }
+        ops.axpby(T::one(), rhs, -T::one(), &mut *r);
+
+        // G = W^H A W, g = W^H r
+        let mut aw = vec![T::zero(); n];
+        let mut g_mat = vec![T::zero(); k * k];
+        let mut g = vec![T::zero(); k];
+        for j in 0..k {
+            unsafe {
+                A.mul_vec_unchecked(self.ritz_vector(j), aw.as_mut_slice());
+            }
+            for i in 0..k {
+                g_mat[i * k + j] = ops.conj_dot(self.ritz_vector(i), aw.as_slice());
+            }
+            g[j] = ops.conj_dot(self.ritz_vector(j), &*r);
+        }
+        if !dense_solve(g_mat.as_mut_slice(), k, g.as_mut_slice()) {
+            return;
+        }
+        for (j, y) in g.iter().enumerate() {
+            ops.axpy(*y, self.ritz_vector(j), &mut *x);
+        }
+    }
+
+    /// Merge the Ritz vectors of the Lanczos basis `basis` (`m` vectors stored one after
+    /// another) and tridiagonal matrix (`alphas`, `betas`) with the cached vectors.
+    #[allow(non_snake_case)]
+    pub(crate) fn update<M: MatVecMul<T>, V: VecOps<T>>(
+        &mut self,
+        A: &M,
+        ops: &V,
+        basis: &[T],
+        alphas: &[T::Real],
+        betas: &[T::Real],
+    ) {
+        let n = self.size;
+        let m = alphas.len();
+        if m == 0 {
+            return;
+        }
+
+        // Ritz pairs of the tridiagonal matrix T_m
+        let mut tm = vec![T::zero(); m * m];
+        for i in 0..m {
+            tm[i * m + i] = T::from_real(alphas[i]);
+            if i + 1 < m {
+                tm[i * m + i + 1] = T::from_real(betas[i]);
+                tm[(i + 1) * m + i] = T::from_real(betas[i]);
+            }
+        }
+        let (ritz, s) = hermitian_eigen(tm.as_slice(), m);
+        let ks = ritz.len();
+
+        // candidate subspace: cached vectors followed by the new Ritz vectors
+        let nnew = self.nev.min(ks);
+        let mut candidates: Vec<T> = Vec::with_capacity((self.len() + nnew) * n);
+        candidates.extend_from_slice(&self.vectors);
+        for l in 0..nnew {
+            let st = candidates.len();
+            candidates.resize(st + n, T::zero());
+            for j in 0..m {
+                ops.axpy(
+                    s[j * ks + l],
+                    &basis[j * n..(j + 1) * n],
+                    &mut candidates[st..],
+                );
+            }
+        }
+
+        // orthonormalize the candidates with modified Gram-Schmidt
+        let mut q: Vec<T> = Vec::with_capacity(candidates.len());
+        for c in candidates.chunks_mut(n) {
+            let c_norm = ops.norm2(&*c);
+            // two passes of MGS keep the basis orthonormal to working precision
+            for _ in 0..2 {
+                for qv in q.chunks(n) {
+                    let h = ops.conj_dot(qv, &*c);
+                    ops.axpy(-h, qv, &mut *c);
+                }
+            }
+            let nrm = ops.norm2(&*c);
+            if nrm > c_norm * num_traits::Float::sqrt(T::Real::epsilon()) {
+                ops.rscale(T::Real::one() / nrm, &mut *c);
+                q.extend_from_slice(c);
+            }
+        }
+        let nq = q.len() / n;
+        if nq == 0 {
+            return;
+        }
+
+        // Rayleigh-Ritz on span(Q): H = Q^H A Q
+        let mut aq = vec![T::zero(); n];
+        let mut h = vec![T::zero(); nq * nq];
+        for j in 0..nq {
+            unsafe {
+                A.mul_vec_unchecked(&q[j * n..(j + 1) * n], aq.as_mut_slice());
+            }
+            for i in 0..nq {
+                h[i * nq + j] = ops.conj_dot(&q[i * n..(i + 1) * n], aq.as_slice());
+            }
+        }
+        let (theta, c) = hermitian_eigen(h.as_slice(), nq);
+        let kc = theta.len();
+
+        let keep = self.nev.min(kc);
+        self.vectors.clear();
+        self.vectors.resize(keep * n, T::zero());
+        self.values.clear();
+        for l in 0..keep {
+            for i in 0..nq {
+                ops.axpy(
+                    c[i * kc + l],
+                    &q[i * n..(i + 1) * n],
+                    &mut self.vectors[l * n..(l + 1) * n],
+                );
+            }
+            self.values.push(theta[l]);
+        }
+    }
+}
+
+/// Compute the eigen-decomposition of the `n`x`n` Hermitian matrix `h` (row-major).
+///
+/// It returns the eigenvalues sorted by increasing magnitude, and the corresponding
+/// eigenvectors stored column by column in a row-major matrix with one column per
+/// returned eigenvalue.
+///
+/// The complex Hermitian problem $Hz = \lambda z$ is solved through its real symmetric
+/// equivalent $[H_r, -H_i; H_i, H_r][u; w] = \lambda [u; w]$, with $z = u + iw$.
+pub(crate) fn hermitian_eigen<T: Scalar>(h: &[T], n: usize) -> (Vec<T::Real>, Vec<T>) {
+    let n2 = 2 * n;
+    let mut a = vec![T::Real::zero(); n2 * n2];
+    for i in 0..n {
+        for j in 0..n {
+            let v = h[i * n + j];
+            a[i * n2 + j] = v.re();
+            a[(i + n) * n2 + j + n] = v.re();
+            a[i * n2 + j + n] = -v.im();
+            a[(i + n) * n2 + j] = v.im();
+        }
+    }
+    let mut vecs = vec![T::Real::zero(); n2 * n2];
+    symmetric_eigen(a.as_mut_slice(), n2, vecs.as_mut_slice());
+
+    let mut order: Vec<usize> = (0..n2).collect();
+    order.sort_by(|&i, &j| {
+        let a_i = num_traits::Float::abs(a[i * n2 + i]);
+        let a_j = num_traits::Float::abs(a[j * n2 + j]);
+        a_i.partial_cmp(&a_j).unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    // Each eigenvalue of the real equivalent shows up twice, with the eigenvectors z and i*z.
+    // Keep only the ones independent from those already picked.
+    let mut values = Vec::with_capacity(n);
+    let mut picked: Vec<T> = Vec::with_capacity(n * n);
+    let mut z = vec![T::zero(); n];
+    for &l in order.iter() {
+        if values.len() == n {
+            break;
+        }
+        for (i, zi) in z.iter_mut().enumerate() {
+            *zi = complex_from_parts(vecs[i * n2 + l], vecs[(i + n) * n2 + l]);
+        }
+        for p in picked.chunks(n) {
+            let t = conj_dot(p, z.as_slice());
+            axpy(-t, p, z.as_mut_slice());
+        }
+        let nrm = norm2(z.as_slice());
+        if nrm > T::real(0.5) {
+            rscale(T::Real::one() / nrm, z.as_mut_slice());
+            picked.extend_from_slice(z.as_slice());
+            values.push(a[l * n2 + l]);
+        }
+    }
+
+    // store the eigenvectors column by column
+    let k = values.len();
+    let mut ret = vec![T::zero(); n * k];
+    for (l, p) in picked.chunks(n).enumerate() {
+        for i in 0..n {
+            ret[i * k + l] = p[i];
+        }
+    }
+    (values, ret)
+}
+
+/// Cyclic Jacobi eigenvalue algorithm for the `n`x`n` real symmetric matrix `a` (row-major).
+///
+/// On return, the diagonal of `a` holds the eigenvalues and the columns of `v` are the
+/// corresponding orthonormal eigenvectors.
+pub(crate) fn symmetric_eigen<R: Float>(a: &mut [R], n: usize, v: &mut [R]) {
+    const MAX_SWEEPS: usize = 64;
+
+    v.iter_mut().for_each(|t| *t = R::zero());
+    for i in 0..n {
+        v[i * n + i] = R::one();
+    }
+    let total = a.iter().fold(R::zero(), |acc, t| acc + (*t) * (*t));
+    let two = R::one() + R::one();
+
+    for _ in 0..MAX_SWEEPS {
+        let mut off = R::zero();
+        for p in 0..n {
+            for q in p + 1..n {
+                off = off + a[p * n + q] * a[p * n + q];
+            }
+        }
+        if off <= R::epsilon() * R::epsilon() * total {
+            break;
+        }
+
+        for p in 0..n {
+            for q in p + 1..n {
+                let apq = a[p * n + q];
+                if apq == R::zero() {
+                    continue;
+                }
+                let theta = (a[q * n + q] - a[p * n + p]) / (two * apq);
+                let t = theta.signum() / (theta.abs() + (theta * theta + R::one()).sqrt());
+                let c = R::one() / (t * t + R::one()).sqrt();
+                let s = t * c;
+
+                // A <- A * J
+                for k in 0..n {
+                    let (akp, akq) = (a[k * n + p], a[k * n + q]);
+                    a[k * n + p] = c * akp - s * akq;
+                    a[k * n + q] = s * akp + c * akq;
+                }
+                // A <- J^T * A
+                for k in 0..n {
+                    let (apk, aqk) = (a[p * n + k], a[q * n + k]);
+                    a[p * n + k] = c * apk - s * aqk;
+                    a[q * n + k] = s * apk + c * aqk;
+                }
+                // V <- V * J
+                for k in 0..n {
+                    let (vkp, vkq) = (v[k * n + p], v[k * n + q]);
+                    v[k * n + p] = c * vkp - s * vkq;
+                    v[k * n + q] = s * vkp + c * vkq;
+                }
+            }
+        }
+    }
+}
+
+/// Solve the small dense system `a * y = b` (row-major `a`) by Gaussian elimination with
+/// partial pivoting. The solution overwrites `b`.
+///
+/// Return `false` if the matrix is (numerically) singular.
+pub(crate) fn dense_solve<T: Scalar>(a: &mut [T], n: usize, b: &mut [T]) -> bool {
+    for k in 0..n {
+        let mut piv = k;
+        for i in k + 1..n {
+            if a[i * n + k].abs() > a[piv * n + k].abs() {
+                piv = i;
+            }
+        }
+        if a[piv * n + k].abs() <= T::Real::epsilon() {
+            return false;
+        }
+        if piv != k {
+            for j in 0..n {
+                a.swap(k * n + j, piv * n + j);
+            }
+            b.swap(k, piv);
+        }
+        for i in k + 1..n {
+            let f = a[i * n + k] / a[k * n + k];
+            for j in k..n {
+                let t = a[k * n + j];
+                a[i * n + j] -= f * t;
+            }
+            let t = b[k];
+            b[i] -= f * t;
+        }
+    }
+    for k in (0..n).rev() {
+        let mut s = b[k];
+        for j in k + 1..n {
+            s -= a[k * n + j] * b[j];
+        }
+        b[k] = s / a[k * n + k];
+    }
+    true
+}
+
+/// Build a scalar from its real and imaginary parts.
+/// The imaginary part is dropped if `T` is real-valued.
+#[inline]
+fn complex_from_parts<T: Scalar>(re: T::Real, im: T::Real) -> T {
+    if super::same_type::<T, T::Complex>() {
+        super::cast_as::<T::Complex, T>(&T::complex(re, im))
+    } else {
+        T::from_real(re)
+    }
+}
+
+// ---------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cauchy::c64;
+
+    #[test]
+    fn jacobi_eigen() {
+        let mut a = vec![4., 1., 0., 1., 3., 1., 0., 1., 2.];
+        let a0 = a.clone();
+        let mut v = vec![0.; 9];
+        symmetric_eigen(a.as_mut_slice(), 3, v.as_mut_slice());
+        for l in 0..3 {
+            let lambda = a[l * 3 + l];
+            for i in 0..3 {
+                let av = (0..3).fold(0., |acc, j| acc + a0[i * 3 + j] * v[j * 3 + l]);
+                approx::assert_abs_diff_eq!(av, lambda * v[i * 3 + l], epsilon = 1E-12);
+            }
+        }
+    }
+
+    #[test]
+    fn hermitian_eigen_complex() {
+        let h = vec![
+            c64::new(2., 0.),
+            c64::new(0., 1.),
+            c64::new(0., -1.),
+            c64::new(2., 0.),
+        ];
+        let (vals, vecs) = hermitian_eigen(h.as_slice(), 2);
+        assert_eq!(vals.len(), 2);
+        approx::assert_abs_diff_eq!(vals[0], 1., epsilon = 1E-12);
+        approx::assert_abs_diff_eq!(vals[1], 3., epsilon = 1E-12);
+        for l in 0..2 {
+            for i in 0..2 {
+                let hv = h[i * 2] * vecs[l] + h[i * 2 + 1] * vecs[2 + l];
+                let lv = vecs[i * 2 + l] * vals[l];
+                approx::assert_abs_diff_eq!(hv.re, lv.re, epsilon = 1E-12);
+                approx::assert_abs_diff_eq!(hv.im, lv.im, epsilon = 1E-12);
+            }
+        }
+    }
+
+    #[test]
+    fn small_dense_solve() {
+        let mut a = vec![0., 2., 1., 1., 1., 0., 3., 0., 1.];
+        let mut b = vec![3., 2., 4.];
+        assert!(dense_solve(a.as_mut_slice(), 3, b.as_mut_slice()));
+        for v in b.iter() {
+            approx::assert_abs_diff_eq!(*v, 1., epsilon = 1E-12);
+        }
+    }
+}
@@ -0,0 +1,274 @@
+//! A dense matrix usable by the solvers.
+
+use super::{error::*, MatVecMul};
+use cauchy::Scalar;
+#[cfg(feature = "mkl")]
+use mkl_sys::blas::*;
+#[cfg(feature = "mkl")]
+use std::ffi::c_void;
+
+/// The storage order of a [`DenseMat`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Layout {
+    /// The entries of a row are contiguous.
+    RowMajor,
+    /// The entries of a column are contiguous.
+    ColMajor,
+}
+
+/// A dense matrix stored in a `Vec<T>`.
+///
+/// It allows to run the solvers on small dense problems, e.g. to test an algorithm before
+/// moving to sparse matrices. The products use `cblas_?gemv` when the `mkl` feature is on.
+#[derive(Clone, Debug)]
+pub struct DenseMat<T> {
+    data: Vec<T>,
+    rows: usize,
+    cols: usize,
+    layout: Layout,
+}
+
+impl<T: Scalar> DenseMat<T> {
+    /// Create a `rows` x `cols` matrix from its entries stored in the order `layout`.
+    pub fn new(rows: usize, cols: usize, data: Vec<T>, layout: Layout) -> SolveResult<Self> {
+        if data.len() != rows * cols {
+            return Err(SolverError::IncompatibleMatrixFormat(String::from(
+                "The data length doesn't match the matrix dimensions",
+            )));
+        }
+        Ok(DenseMat {
+            data,
+            rows,
+            cols,
+            layout,
+        })
+    }
+
+    /// Create a matrix from its entries stored row by row.
+    pub fn from_row_major(rows: usize, cols: usize, data: Vec<T>) -> SolveResult<Self> {
+        Self::new(rows, cols, data, Layout::RowMajor)
+    }
+
+    /// Create a matrix from its entries stored column by column.
+    pub fn from_col_major(rows: usize, cols: usize, data: Vec<T>) -> SolveResult<Self> {
+        Self::new(rows, cols, data, Layout::ColMajor)
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    /// The entry $a_{ij}$.
+    #[inline]
+    pub fn get(&self, i: usize, j: usize) -> T {
+        match self.layout {
+            Layout::RowMajor => self.data[i * self.cols + j],
+            Layout::ColMajor => self.data[j * self.rows + i],
+        }
+    }
+
+    /// Compute `v_out = A * v_in`, or `v_out = A^T * v_in` if `trans`.
+    fn gemv(&self, trans: bool, v_in: &[T], v_out: &mut [T]) {
+        #[cfg(feature = "mkl")]
+        {
+            let layout = match self.layout {
+                Layout::RowMajor => CBLAS_LAYOUT_CblasRowMajor,
+                Layout::ColMajor => CBLAS_LAYOUT_CblasColMajor,
+            };
+            let lda = match self.layout {
+                Layout::RowMajor => self.cols.max(1),
+                Layout::ColMajor => self.rows.max(1),
+            } as i32;
+            let op = if trans {
+                CBLAS_TRANSPOSE_CblasTrans
+            } else {
+                CBLAS_TRANSPOSE_CblasNoTrans
+            };
+            macro_rules! gemv {
+                ($ty:ty, $func:ident, {}) => {
+                    if super::same_type::<T, $ty>() {
+                        unsafe {
+                            $func(
+                                layout,
+                                op,
+                                self.rows as i32,
+                                self.cols as i32,
+                                1.,
+                                self.data.as_ptr() as *const $ty,
+                                lda,
+                                v_in.as_ptr() as *const $ty,
+                                1,
+                                0.,
+                                v_out.as_mut_ptr() as *mut $ty,
+                                1,
+                            );
+                        }
+                        return;
+                    }
+                };
+                ($ty:ty, $func:ident, {complex}) => {
+                    if super::same_type::<T, num_complex::Complex<$ty>>() {
+                        let (one, zero) = (T::one(), T::zero());
+                        unsafe {
+                            $func(
+                                layout,
+                                op,
+                                self.rows as i32,
+                                self.cols as i32,
+                                &one as *const T as *const c_void,
+                                self.data.as_ptr() as *const c_void,
+                                lda,
+                                v_in.as_ptr() as *const c_void,
+                                1,
+                                &zero as *const T as *const c_void,
+                                v_out.as_mut_ptr() as *mut c_void,
+                                1,
+                            );
+                        }
+                        return;
+                    }
+                };
+            }
+            gemv! {f32, cblas_sgemv, {}};
+            gemv! {f64, cblas_dgemv, {}};
+            gemv! {f32, cblas_cgemv, {complex}};
+            gemv! {f64, cblas_zgemv, {complex}};
+        }
+        // Row-major A^T is column-major A and vice versa
+        let by_rows = (self.layout == Layout::RowMajor) != trans;
+        let (outer, inner) = if trans {
+            (self.cols, self.rows)
+        } else {
+            (self.rows, self.cols)
+        };
+        if by_rows {
+            for (k, out) in v_out.iter_mut().enumerate().take(outer) {
+                let row = &self.data[k * inner..(k + 1) * inner];
+                *out = row
+                    .iter()
+                    .zip(v_in.iter())
+                    .fold(T::zero(), |acc, (&a, &x)| acc + a * x);
+            }
+        } else {
+            v_out.iter_mut().for_each(|v| *v = T::zero());
+            for (k, &x) in v_in.iter().enumerate().take(inner) {
+                let col = &self.data[k * outer..(k + 1) * outer];
+                v_out
+                    .iter_mut()
+                    .zip(col.iter())
+                    .for_each(|(out, &a)| *out += a * x);
+            }
+        }
+    }
+}
+
+impl<T: Scalar> MatVecMul<T> for DenseMat<T> {
+    #[inline]
+    fn shape(&self) -> Option<(usize, usize)> {
+        Some((self.rows, self.cols))
+    }
+
+    #[inline]
+    fn mul_vec(&self, v_in: &[T], v_out: &mut [T]) {
+        if self.cols != v_in.len() || self.rows != v_out.len() {
+            panic!("Dimension mismatch");
+        }
+        self.gemv(false, v_in, v_out);
+    }
+
+    #[inline]
+    fn mul_vec_dot(&self, v_in: &[T], v_out: &mut [T]) -> T {
+        self.mul_vec(v_in, v_out);
+        super::vecalg::conj_dot(v_in, &*v_out)
+    }
+
+    unsafe fn mul_vec_unchecked(&self, v_in: &[T], v_out: &mut [T]) {
+        debug_assert!(self.cols == v_in.len() && self.rows == v_out.len());
+        self.gemv(false, v_in, v_out);
+    }
+
+    unsafe fn mul_vec_dot_unchecked(&self, v_in: &[T], v_out: &mut [T]) -> T {
+        self.mul_vec_unchecked(v_in, v_out);
+        super::vecalg::conj_dot(v_in, &*v_out)
+    }
+
+    fn mul_vec_trans(&self, v_in: &[T], v_out: &mut [T]) -> SolveResult<()> {
+        if self.rows != v_in.len() || self.cols != v_out.len() {
+            panic!("Dimension mismatch");
+        }
+        self.gemv(true, v_in, v_out);
+        Ok(())
+    }
+
+    fn diagonal(&self, out: &mut [T]) {
+        if self.rows != self.cols || self.rows != out.len() {
+            panic!("Dimension mismatch");
+        }
+        for (i, d) in out.iter_mut().enumerate() {
+            *d = self.get(i, i);
+        }
+    }
+}
+
+// --------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BiCGStab;
+    use num_complex::Complex64;
+
+    #[test]
+    fn dense_real_nonsymmetric() {
+        // A = [ 4 1 0 ]
+        //     [ 2 5 1 ]
+        //     [ 0 3 6 ]
+        let row = DenseMat::from_row_major(3, 3, vec![4., 1., 0., 2., 5., 1., 0., 3., 6.]).unwrap();
+        let col = DenseMat::from_col_major(3, 3, vec![4., 2., 0., 1., 5., 3., 0., 1., 6.]).unwrap();
+        let x = [1., -1., 2.];
+        let mut y = [0.; 3];
+        for a in [&row, &col].iter() {
+            a.mul_vec(&x, &mut y);
+            assert_eq!(y, [3., -1., 9.]);
+            a.mul_vec_trans(&x, &mut y).unwrap();
+            assert_eq!(y, [2., 2., 11.]);
+            a.diagonal(&mut y);
+            assert_eq!(y, [4., 5., 6.]);
+        }
+
+        let rhs = [3., -1., 9.];
+        let mut x = [0.; 3];
+        BiCGStab::new(&row, 3)
+            .solve(&rhs, &mut x, 100, 1E-12)
+            .unwrap();
+        for (u, v) in x.iter().zip([1., -1., 2.].iter()) {
+            approx::assert_abs_diff_eq!(u, v, epsilon = 1E-10);
+        }
+    }
+
+    #[test]
+    fn dense_complex() {
+        let c = |re, im| Complex64::new(re, im);
+        // A = [ 2+i   1  ]
+        //     [ -i   3-i ]
+        let a = DenseMat::from_row_major(2, 2, vec![c(2., 1.), c(1., 0.), c(0., -1.), c(3., -1.)])
+            .unwrap();
+        let x = [c(1., 1.), c(0., 2.)];
+        let mut y = [c(0., 0.); 2];
+        let d = a.mul_vec_dot(&x, &mut y);
+        assert_eq!(y, [c(1., 5.), c(3., 5.)]);
+        assert_eq!(d, x[0].conj() * y[0] + x[1].conj() * y[1]);
+        a.mul_vec_trans(&x, &mut y).unwrap();
+        assert_eq!(y, [c(3., 3.), c(3., 7.)]);
+
+        assert!(DenseMat::from_col_major(2, 2, vec![c(0., 0.); 3]).is_err());
+    }
+}
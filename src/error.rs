@@ -4,6 +4,7 @@ pub type SolveResult<T> = std::result::Result<T, SolverError>;
 
 #[non_exhaustive]
 #[derive(Debug, Error)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SolverError {
     #[error("Incompatible input matrix format: {0}")]
     IncompatibleMatrixFormat(String),
@@ -11,12 +12,56 @@ pub enum SolverError {
     #[error("Matrix has zero diagonal element at {0}")]
     ZeorDiagonalElem(usize),
 
-    #[error("Insufficient interation #: {0}")]
+    #[error("Insufficient iteration number: not converged after {0} iterations")]
     InsufficientIterNum(usize),
 
-    #[error("Solver break down: its #{0}")]
+    #[error("Solver break down at iteration {0}")]
     BreakDown(usize),
 
     #[error("Invalid preconditioner: {0}")]
     InvalidPreconditioner(String),
+
+    #[error("Invalid parameter: {0}")]
+    InvalidParameter(String),
+
+    #[error("Solver diverged: non-finite residual at iteration {0}")]
+    Diverged(usize),
+
+    #[error("Solver stagnated at iteration {iter} with relative residual {resid}")]
+    Stagnation { iter: usize, resid: f64 },
+
+    #[error("Time limit exceeded at iteration {iter} with relative residual {resid}")]
+    TimeLimit { iter: usize, resid: f64 },
+
+    /// With the `serde` feature, it is serialized as its message, and deserialized as an
+    /// error of kind [`Other`](std::io::ErrorKind::Other).
+    #[error("I/O error: {0}")]
+    Io(
+        #[from]
+        #[cfg_attr(feature = "serde", serde(with = "io_error"))]
+        std::io::Error,
+    ),
+}
+
+#[cfg(feature = "serde")]
+mod io_error {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub(super) fn serialize<S: Serializer>(e: &std::io::Error, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_str(e)
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<std::io::Error, D::Error> {
+        let msg = String::deserialize(d)?;
+        Ok(std::io::Error::other(msg))
+    }
+}
+
+/// The [`SolverError::Stagnation`] error at iteration `iter` with the relative residual
+/// `resid`.
+pub(crate) fn stagnation_error<R: num_traits::ToPrimitive>(iter: usize, resid: R) -> SolverError {
+    SolverError::Stagnation {
+        iter,
+        resid: resid.to_f64().unwrap_or(f64::NAN),
+    }
 }
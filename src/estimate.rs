@@ -0,0 +1,172 @@
+//! Cheap estimates of operator properties.
+
+use super::{
+    deflation::symmetric_eigen, error::SolveResult, lanczos::Lanczos, solver::check_size,
+    vecalg::*, MatVecMul,
+};
+use cauchy::Scalar;
+use num_traits::{float::*, One, Zero};
+
+/// Estimate $\|A\|_2$ with `iters` steps of power iteration on $A^HA$.
+///
+/// Each step computes $A^HAv$ for the normalized iterate $v$, with the product
+/// $A^Hw = \overline{A^T\bar w}$ given by [`mul_vec_trans`](MatVecMul::mul_vec_trans). The
+/// estimate is the largest $\|A^HAv\|^{1/2}$ met by the iterates, which is a **lower bound**
+/// of $\|A\|_2$ and converges to it for any operator, at a rate given by the ratio of the
+/// two largest singular values.
+///
+/// It is typically used to turn a relative tolerance into a meaningful absolute one.
+///
+/// Return the error of [`mul_vec_trans`](MatVecMul::mul_vec_trans) if `A` doesn't provide the
+/// transposed product.
+#[allow(non_snake_case)]
+pub fn operator_norm_estimate<T: Scalar, M: MatVecMul<T>>(
+    A: &M,
+    size: usize,
+    iters: usize,
+) -> SolveResult<T::Real> {
+    let mut v: Vec<T> = start_vector(size);
+    let mut w = vec![T::zero(); size];
+    let mut u = vec![T::zero(); size];
+    let mut est = T::Real::zero();
+    for _ in 0..iters {
+        unsafe {
+            A.mul_vec_unchecked(v.as_slice(), w.as_mut_slice()); // w = A v
+        }
+        // w = A^H A v = conj(A^T conj(A v))
+        conj(w.as_slice(), u.as_mut_slice());
+        A.mul_vec_trans(u.as_slice(), w.as_mut_slice())?;
+        conj(w.as_slice(), u.as_mut_slice());
+        // v is normalized, hence |A^H A v| <= |A|^2
+        let nrm = norm2(u.as_slice());
+        est = est.max(Float::sqrt(nrm));
+        if nrm <= T::Real::epsilon() {
+            // v lies in the null space
+            break;
+        }
+        std::mem::swap(&mut v, &mut u);
+        rscale(T::Real::one() / nrm, v.as_mut_slice());
+    }
+    Ok(est)
+}
+
+/// Estimate the dominant eigenvalue magnitude $|\lambda_{max}|$ of `A`, i.e. its spectral
+/// radius, and an associated eigenvector with power iteration.
+///
+/// Each iteration computes $w = Av$ for the normalized iterate $v$ and takes the Rayleigh
+/// quotient $\lambda = v^Hw$ as the eigenvalue estimate. The iteration stops once
+/// $|\lambda_k - \lambda_{k-1}| \le tol\,|\lambda_k|$, or after `max_iter` products with $A$.
+/// It returns $|\lambda|$ and the last iterate $v$, which has unit norm.
+///
+/// The convergence rate is $|\lambda_2 / \lambda_1|$, so it is slow when the two largest
+/// eigenvalue magnitudes are close, and the estimate may not converge at all when distinct
+/// eigenvalues share the largest magnitude, e.g. $\pm\lambda$. Only products with $A$ are
+/// needed, so it also applies to matrix-free operators such as the iteration matrix
+/// $I - D^{-1}A$ of a stationary method.
+///
+/// **Panics** if `A` provides its [`shape`](MatVecMul::shape) and it differs from
+/// `size x size`.
+#[allow(non_snake_case)]
+pub fn power_iteration<T: Scalar, M: MatVecMul<T>>(
+    A: &M,
+    size: usize,
+    max_iter: usize,
+    tol: T::Real,
+) -> (T::Real, Vec<T>) {
+    check_size(A, size);
+    let mut v: Vec<T> = start_vector(size);
+    let mut w = vec![T::zero(); size];
+    let mut lambda = T::zero();
+    for its in 0..max_iter {
+        unsafe {
+            A.mul_vec_unchecked(v.as_slice(), w.as_mut_slice());
+        }
+        // the Rayleigh quotient, v is normalized
+        let lambda_old = lambda;
+        lambda = conj_dot(v.as_slice(), w.as_slice());
+        if its > 0 && (lambda - lambda_old).abs() <= tol * lambda.abs() {
+            break;
+        }
+        if normalize(w.as_mut_slice()).is_zero() {
+            // v lies in the null space
+            break;
+        }
+        std::mem::swap(&mut v, &mut w);
+    }
+    (lambda.abs(), v)
+}
+
+/// Estimate the smallest and largest eigenvalues of the Hermitian operator `A` from the
+/// extreme Ritz values of `n_steps` Lanczos iterations.
+///
+/// The Ritz values are the eigenvalues of the tridiagonal matrix $T_k$ built by
+/// [`Lanczos`]. They lie inside the spectrum and move outwards as $k$ grows, so the
+/// estimates are an **inner** bound $\lambda_{min} \le lo \le hi \le \lambda_{max}$ that
+/// tightens with `n_steps`. The extreme eigenvalues are found first, and a few tens of steps
+/// usually resolve them well unless they are clustered with the rest of the spectrum. The
+/// process stops early when the Krylov subspace becomes invariant, and the estimates are
+/// then exact.
+///
+/// It is typically used to drive [`Chebyshev`](crate::Chebyshev), see
+/// [`auto_chebyshev`](crate::auto_chebyshev), which widens the interval by a safety factor.
+///
+/// **Note:** Only real-valued symmetric or complex-valued Hermitian operators are supported.
+/// For other operators, the returned values have no meaning.
+///
+/// **Panics** if `A` provides its [`shape`](MatVecMul::shape) and it differs from
+/// `size x size`.
+#[allow(non_snake_case)]
+pub fn lanczos_eigen_bounds<T: Scalar, M: MatVecMul<T>>(
+    A: &M,
+    size: usize,
+    n_steps: usize,
+) -> (T::Real, T::Real) {
+    let mut lanczos = Lanczos::new(A, size);
+    lanczos.start(start_vector::<T>(size).as_slice());
+    let mut alphas: Vec<T::Real> = Vec::with_capacity(n_steps);
+    let mut betas: Vec<T::Real> = Vec::with_capacity(n_steps);
+    for _ in 0..n_steps {
+        let (alpha, beta) = lanczos.step();
+        alphas.push(alpha);
+        if beta <= T::Real::epsilon() * Float::abs(alpha) {
+            // invariant subspace found
+            break;
+        }
+        betas.push(beta);
+    }
+
+    // Ritz values are the eigenvalues of the tridiagonal matrix
+    let m = alphas.len();
+    let mut tm = vec![T::Real::zero(); m * m];
+    for i in 0..m {
+        tm[i * m + i] = alphas[i];
+        if i + 1 < m {
+            tm[i * m + i + 1] = betas[i];
+            tm[(i + 1) * m + i] = betas[i];
+        }
+    }
+    let mut vecs = vec![T::Real::zero(); m * m];
+    symmetric_eigen(tm.as_mut_slice(), m, vecs.as_mut_slice());
+    (0..m).fold(
+        (T::Real::infinity(), T::Real::neg_infinity()),
+        |(lo, hi), i| {
+            let v = tm[i * m + i];
+            (lo.min(v), hi.max(v))
+        },
+    )
+}
+
+/// A deterministic pseudo-random vector of unit norm, used to start the iterative estimates.
+pub(crate) fn start_vector<T: Scalar>(size: usize) -> Vec<T> {
+    let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut q: Vec<T> = (0..size)
+        .map(|_| {
+            seed = seed
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+            T::from_real(T::real((seed >> 11) as f64 / (1_u64 << 53) as f64 - 0.5))
+        })
+        .collect();
+    normalize(q.as_mut_slice());
+    q
+}
@@ -0,0 +1,304 @@
+//! An impl of the Flexible GMRES algorithm for linear sparse solve.
+
+use super::{
+    arnoldi::Arnoldi,
+    config::{Deadline, Side, SolveConfig},
+    error::*,
+    precond::{Identity, Precond},
+    report::*,
+    solver::{l2_rel_tol, square_size, IterativeSolver},
+    vecalg::*,
+    MatVecMul,
+};
+use cauchy::Scalar;
+use num_traits::{float::*, Zero};
+use std::{intrinsics::unlikely, ops::ControlFlow, slice::from_raw_parts_mut};
+
+/// Implementation of the restarted Flexible GMRES algorithm for solving non-symmetric
+/// sparse linear systems with a right preconditioner that may change at every step.
+///
+/// Each step applies the preconditioner to the last [`Arnoldi`] vector, $z_k = M_k^{-1}v_k$,
+/// and orthogonalizes $Az_k$ against the basis, so that $AZ_k = V_{k+1}\bar H_k$. The
+/// least-squares problem $\min |\beta e_1 - \bar H_k y|$ is solved with Givens rotations,
+/// and the solution is built from the stored vectors, $x = x_0 + Z_ky$. Unlike GMRES, which
+/// recovers $x$ from $V_k$ with a single $M^{-1}$, this stays correct when $M_k$ is an inner
+/// iterative solve or any other nonlinear operator.
+///
+/// The Krylov subspace is restarted from the true residual every `restart` steps. On top of
+/// the Arnoldi basis, the workspace holds `restart + 1` vectors.
+///
+/// See Y. Saad, *A flexible inner-outer preconditioned GMRES algorithm*, SIAM J. Sci.
+/// Comput., 14 (1993).
+#[allow(non_snake_case)]
+pub struct FGMRES<'data, T: Scalar, M: MatVecMul<T>, V: VecOps<T> = CpuVecOps> {
+    A: &'data M,
+    arnoldi: Arnoldi<'data, T, M, V>,
+    workspace: Vec<T>,
+    /// The rotated right-hand side $\beta e_1$ of the least-squares problem
+    g: Vec<T>,
+    /// The Givens rotations applied to the Hessenberg columns
+    rotations: Vec<(T::Real, T)>,
+    size: usize,
+    restart: usize,
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>> FGMRES<'data, T, M> {
+    /// Create a solver restarting every `restart` steps.
+    ///
+    /// **Panics** if `A` provides its [`shape`](MatVecMul::shape) and it differs from
+    /// `size x size`.
+    #[allow(non_snake_case)]
+    pub fn new(A: &'data M, size: usize, restart: usize) -> Self {
+        Self::with_vec_ops(A, size, restart, CpuVecOps)
+    }
+
+    /// Create a solver for the operator `A`, whose size is given by its
+    /// [`shape`](MatVecMul::shape).
+    ///
+    /// Return [`SolverError::IncompatibleMatrixFormat`] if `A` is not square or doesn't provide
+    /// its shape.
+    #[allow(non_snake_case)]
+    pub fn from_op(A: &'data M, restart: usize) -> SolveResult<Self> {
+        Ok(Self::with_vec_ops(A, square_size(A)?, restart, CpuVecOps))
+    }
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>, V: VecOps<T>> FGMRES<'data, T, M, V> {
+    /// Create a solver performing its vector operations through `ops`.
+    ///
+    /// **Panics** if `A` provides its [`shape`](MatVecMul::shape) and it differs from
+    /// `size x size`.
+    #[allow(non_snake_case)]
+    pub fn with_vec_ops(A: &'data M, size: usize, restart: usize, ops: V) -> Self {
+        FGMRES {
+            A,
+            arnoldi: Arnoldi::with_vec_ops(A, size, restart, ops),
+            // r, and the preconditioned vectors z_0 ... z_{restart-1}
+            workspace: vec![T::zero(); size * (restart + 1)],
+            g: vec![T::zero(); restart + 1],
+            rotations: vec![(T::Real::zero(), T::zero()); restart],
+            size,
+            restart,
+        }
+    }
+
+    /// The number of steps between two restarts.
+    pub fn restart(&self) -> usize {
+        self.restart
+    }
+
+    /// Solves Ax = b, without preconditioner
+    pub fn solve(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<(usize, T::Real)> {
+        self.precond_solve(&Identity, rhs, x, max_iter, tol)
+    }
+
+    /// Solves Ax = b with the preconditioner applied on the given `side`.
+    ///
+    /// [`Side::Right`] is the same as [`precond_solve`](Self::precond_solve). The
+    /// preconditioner may change at every step only on the right, so [`Side::Left`] is
+    /// rejected with [`SolverError::InvalidParameter`].
+    pub fn precond_solve_side<P: Precond<T> + ?Sized>(
+        &mut self,
+        precond: &P,
+        side: Side,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<(usize, T::Real)> {
+        if side == Side::Left {
+            return Err(SolverError::InvalidParameter(String::from(
+                "FGMRES applies its preconditioner on the right only",
+            )));
+        }
+        self.precond_solve(precond, rhs, x, max_iter, tol)
+    }
+
+    /// Solves Ax = b with the (right) preconditioner `precond`, which may be a different
+    /// operator at every call of [`Precond::apply`], e.g. a few iterations of an inner
+    /// solver.
+    ///
+    /// [`Precond::apply`] takes `&self`, so a stateful preconditioner keeps its state behind
+    /// interior mutability, the way [`NeumannPrecond`](crate::precond::NeumannPrecond) holds
+    /// its scratch vector in a [`RefCell`](std::cell::RefCell). For instance, an inner
+    /// iterative solver kept in a `RefCell` reuses its workspace across the outer steps.
+    ///
+    /// The returned iteration number counts the Arnoldi steps, and the residual is the
+    /// least-squares estimate of $|b - Ax| / |b|$, which is exact up to rounding errors. The
+    /// true residual is recomputed at every restart. It returns [`SolverError::BreakDown`]
+    /// when the projected matrix is singular, which may happen with a singular or poor
+    /// preconditioner.
+    pub fn precond_solve<P: Precond<T> + ?Sized>(
+        &mut self,
+        precond: &P,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<(usize, T::Real)> {
+        self.solve_impl(precond, rhs, x, max_iter, tol, |_, _| {
+            ControlFlow::Continue(())
+        })
+    }
+
+    /// The FGMRES iterations. `monitor` is called with the iteration number and the relative
+    /// residual used by the convergence test, starting from the initial residual. The
+    /// iterations stop when it returns [`ControlFlow::Break`].
+    fn solve_impl<P: Precond<T> + ?Sized, F: FnMut(usize, T::Real) -> ControlFlow<()>>(
+        &mut self,
+        precond: &P,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+        mut monitor: F,
+    ) -> SolveResult<(usize, T::Real)> {
+        let n = rhs.len();
+        let m = self.restart;
+        // check the format
+        if n != self.size {
+            return Err(SolverError::IncompatibleMatrixFormat(String::from(
+                "Input vec dimension doesn't match the matrix size",
+            )));
+        }
+        if n != x.len() {
+            return Err(SolverError::IncompatibleMatrixFormat(String::from(
+                "Input and output vec dimension do not match",
+            )));
+        }
+        if m == 0 {
+            return Err(SolverError::InvalidParameter(String::from(
+                "The restart length must be positive",
+            )));
+        }
+
+        let rhs_norm = self.arnoldi.ops().norm2(rhs);
+        if unlikely(rhs_norm <= T::Real::epsilon()) {
+            // when rhs = 0, x is set to zero.
+            x.iter_mut().for_each(|v| *v = T::zero());
+            return Ok((0, rhs_norm));
+        }
+        let threshold = tol * rhs_norm;
+
+        // Here is the internal memeory layout:
+        // [ r | z_0 ... z_{m-1} ]
+        let ptr = self.workspace.as_mut_ptr();
+        let vec = |k: usize| unsafe { from_raw_parts_mut(ptr.add(k * n), n) };
+        let r = vec(0);
+        let z = |i: usize| vec(1 + i);
+
+        let mut its = 0;
+        loop {
+            unsafe {
+                self.A.mul_vec_unchecked(x, z(0)); // z_0 = A * x
+            }
+            self.arnoldi.ops().waxpy(-T::one(), &*z(0), rhs, &mut *r); // r = rhs - A*x
+            let beta = self.arnoldi.start(&*r);
+            if unlikely(!beta.is_finite()) {
+                return Err(SolverError::Diverged(its));
+            }
+            if monitor(its, beta / rhs_norm).is_break() || beta <= threshold {
+                return Ok((its, beta / rhs_norm));
+            }
+            if its >= max_iter {
+                return Err(SolverError::InsufficientIterNum(max_iter));
+            }
+
+            self.g.iter_mut().for_each(|v| *v = T::zero());
+            self.g[0] = T::from_real(beta);
+            let mut res_norm = beta;
+            let mut k = 0;
+            let mut stop = false;
+            while k < m && its < max_iter {
+                let h_next = self.arnoldi.step_precond(precond, z(k)); // z_k = M^{-1} v_k
+                its += 1;
+
+                // rotate the new column of H with the previous rotations
+                let col = self.arnoldi.hessenberg_column_mut(k);
+                for (i, &(c, s)) in self.rotations[..k].iter().enumerate() {
+                    let (a, b) = (col[i], col[i + 1]);
+                    col[i] = a.mul_real(c) + s * b;
+                    col[i + 1] = b.mul_real(c) - s.conj() * a;
+                }
+                let (diag, sub) = col.split_at_mut(k + 1);
+                let (c, s) = rotg(&mut diag[k], &mut sub[0]);
+                if unlikely(diag[k].is_zero()) {
+                    return Err(SolverError::BreakDown(its));
+                }
+                self.rotations[k] = (c, s);
+                self.g[k + 1] = -s.conj() * self.g[k];
+                self.g[k] = self.g[k].mul_real(c);
+                k += 1;
+
+                res_norm = self.g[k].abs();
+                if unlikely(!res_norm.is_finite()) {
+                    return Err(SolverError::Diverged(its));
+                }
+                stop = monitor(its, res_norm / rhs_norm).is_break();
+                if stop || res_norm <= threshold || h_next.is_zero() {
+                    break;
+                }
+            }
+
+            // solve R y = g by back substitution, and x += Z y
+            for i in (0..k).rev() {
+                let mut yi = self.g[i];
+                for j in i + 1..k {
+                    yi -= self.arnoldi.hessenberg_column(j)[i] * self.g[j];
+                }
+                self.g[i] = yi / self.arnoldi.hessenberg_column(i)[i];
+            }
+            for i in 0..k {
+                self.arnoldi.ops().axpy(self.g[i], &*z(i), &mut *x);
+            }
+            if stop || res_norm <= threshold {
+                return Ok((its, res_norm / rhs_norm));
+            }
+        }
+    }
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>, V: VecOps<T>> IterativeSolver<T>
+    for FGMRES<'data, T, M, V>
+{
+    fn solve(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<(usize, T::Real)> {
+        FGMRES::solve(self, rhs, x, max_iter, tol)
+    }
+
+    fn solve_with_config(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        config: &SolveConfig<T::Real>,
+    ) -> SolveResult<(usize, T::Real)> {
+        let tol = l2_rel_tol(rhs, config)?;
+        let mut deadline = Deadline::new(config.time_limit);
+        let ret = self.solve_impl(&Identity, rhs, x, config.max_iter, tol, |its, res| {
+            deadline.monitor(its, res)
+        });
+        deadline.finish(ret)
+    }
+
+    fn solve_report(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<SolveReport<T::Real>> {
+        let ret = FGMRES::solve(self, rhs, x, max_iter, tol);
+        report_outcome(self.A, self.arnoldi.ops(), rhs, x, &mut self.workspace, ret)
+    }
+}
@@ -1,18 +1,39 @@
-//! A naive impl of Gauss-Seidel solver.
-use super::{error::*, vecalg::*, MatVecMul};
+//! Naive impls of the stationary solvers: Gauss-Seidel, SOR, SSOR and weighted Jacobi.
+use super::{
+    config::{Deadline, SolveConfig},
+    error::*,
+    report::*,
+    solver::{l2_rel_tol, IterativeSolver},
+    vecalg::*,
+    MatVecMul,
+};
 use cauchy::Scalar;
-use num_traits::{float::*, Zero};
-use sprs::CsMatView;
+use num_traits::{float::*, One, Zero};
+use sprs::{CsMatView, CsVecView};
+use std::ops::ControlFlow;
 
+/// The order in which the unknowns are relaxed during one iteration.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Sweep {
+    /// rows 0..n, using the freshly updated values
+    Forward,
+    /// a forward sweep followed by a backward one
+    Symmetric,
+    /// all the rows, using the values of the previous iteration
+    Jacobi,
+}
+
+/// The matrix and the workspace shared by the stationary solvers.
 #[allow(non_snake_case)]
-pub struct GaussSeidel<'data, T: Scalar + PartialOrd + Send + Sync> {
+struct Stationary<'data, T: Scalar + PartialOrd + Send + Sync> {
     A: CsMatView<'data, T>,
+    // [ residual | diagonal | previous iterate (Jacobi only) ]
     workspace: Vec<T>,
 }
 
-impl<'data, T: Scalar + PartialOrd + Send + Sync> GaussSeidel<'data, T> {
+impl<'data, T: Scalar + PartialOrd + Send + Sync> Stationary<'data, T> {
     #[allow(non_snake_case)]
-    pub fn new(A: CsMatView<'data, T>) -> SolveResult<Self> {
+    fn new(A: CsMatView<'data, T>, n_vecs: usize) -> SolveResult<Self> {
         if A.rows() != A.cols() {
             return Err(SolverError::IncompatibleMatrixFormat(String::from(
                 "Not a square matrix",
@@ -24,18 +45,24 @@ impl<'data, T: Scalar + PartialOrd + Send + Sync> GaussSeidel<'data, T> {
                 "Not in CSR format",
             )));
         }
-        Ok(GaussSeidel {
+        Ok(Stationary {
             A,
-            workspace: vec![T::zero(); A.rows() * 2],
+            workspace: vec![T::zero(); A.rows() * n_vecs],
         })
     }
 
-    pub fn solve(
+    /// `monitor` is called after every sweep with the iteration count and the relative
+    /// residual, and stops the iterations when it returns [`ControlFlow::Break`].
+    #[allow(clippy::too_many_arguments)]
+    fn solve<F: FnMut(usize, T::Real) -> ControlFlow<()>>(
         &mut self,
         rhs: &[T],
         x: &mut [T],
+        omega: T::Real,
+        sweep: Sweep,
         max_iter: usize,
         eps: T::Real,
+        mut monitor: F,
     ) -> SolveResult<(usize, T::Real)> {
         // check the format
         if rhs.len() != self.A.rows() {
@@ -48,94 +75,309 @@ impl<'data, T: Scalar + PartialOrd + Send + Sync> GaussSeidel<'data, T> {
                 "Input and output vec dimension do not match",
             )));
         }
+        if !(omega > T::Real::zero() && omega < T::Real::one() + T::Real::one()) {
+            return Err(SolverError::InvalidParameter(String::from(
+                "The relaxation factor omega must lie in (0, 2)",
+            )));
+        }
 
         if max_iter == 0 {
             return Err(SolverError::InsufficientIterNum(max_iter));
         }
 
         let n_rows = rhs.len();
-        let mut b_norm: T::Real = Zero::zero();
+        let (res_vec, rest) = self.workspace.split_at_mut(n_rows);
+        let (diag, x_old) = rest.split_at_mut(n_rows);
 
-        // unroll the first iteration to compute norm of b and cache the diagonals
+        // compute norm of b and cache the diagonals
+        let mut b_norm: T::Real = Zero::zero();
         for (row_ind, vec) in self.A.outer_iterator().enumerate() {
-            let mut sigma = T::zero();
-            let mut diag = None;
-            for (col_ind, val) in vec.iter() {
-                if row_ind != col_ind {
-                    unsafe {
-                        sigma += (*val) * (*x.get_unchecked(col_ind));
-                    }
-                } else {
-                    diag = Some(val);
-                }
-            }
-            if diag == None {
-                return Err(SolverError::ZeorDiagonalElem(row_ind));
-            }
-            let diag = diag.unwrap();
-            if diag.square() < T::Real::epsilon() {
-                return Err(SolverError::ZeorDiagonalElem(row_ind));
-            }
+            let d = match vec.get(row_ind) {
+                Some(d) if d.square() >= T::Real::epsilon() => *d,
+                _ => return Err(SolverError::ZeorDiagonalElem(row_ind)),
+            };
             unsafe {
                 // store the diag elem in the cache for later use
-                *self.workspace.get_unchecked_mut(n_rows + row_ind) = *diag;
-                let rhs_v = rhs.get_unchecked(row_ind);
-                b_norm += rhs_v.square(); // accumulate 2-norm
-                *x.get_unchecked_mut(row_ind) = (*rhs_v - sigma) / *diag;
+                *diag.get_unchecked_mut(row_ind) = d;
+                b_norm += rhs.get_unchecked(row_ind).square(); // accumulate 2-norm
             }
         }
-        let tol2 = eps * num_traits::Float::sqrt(b_norm);
+        let b_norm = num_traits::Float::sqrt(b_norm);
+        let tol2 = eps * b_norm;
 
-        unsafe {
-            self.A.mul_vec_unchecked(x, &mut self.workspace[0..n_rows]);
-        }
-        // r = A*x - b
-        //self.workspace
-        //    .iter_mut()
-        //    .zip(rhs.iter())
-        //    .for_each(|(a, b)| *a -= *b);
-        axpy(-T::one(), rhs, &mut self.workspace[..n_rows]);
-        // |r|
-        //let res = self
-        //    .workspace
-        //    .iter()
-        //    .take(n_rows)
-        //    .fold(T::Real::zero(), |acc, x| acc + x.square());
-        let res = norm2(&self.workspace[..n_rows]);
-
-        if res <= tol2 {
-            return Ok((1, res));
-        }
-
-        for it in 1..max_iter {
-            for (row_ind, vec) in self.A.outer_iterator().enumerate() {
-                let mut sigma = T::zero();
-                for (col_ind, val) in vec.iter() {
-                    if row_ind != col_ind {
+        for it in 0..max_iter {
+            match sweep {
+                Sweep::Forward => {
+                    for (row_ind, vec) in self.A.outer_iterator().enumerate() {
+                        unsafe { relax_row(vec, row_ind, &*diag, rhs, x, omega) };
+                    }
+                }
+                Sweep::Symmetric => {
+                    for (row_ind, vec) in self.A.outer_iterator().enumerate() {
+                        unsafe { relax_row(vec, row_ind, &*diag, rhs, x, omega) };
+                    }
+                    for (row_ind, vec) in self.A.outer_iterator().enumerate().rev() {
+                        unsafe { relax_row(vec, row_ind, &*diag, rhs, x, omega) };
+                    }
+                }
+                Sweep::Jacobi => {
+                    x_old.copy_from_slice(x);
+                    for (row_ind, vec) in self.A.outer_iterator().enumerate() {
                         unsafe {
-                            sigma += (*val) * (*x.get_unchecked(col_ind));
+                            let v = row_solution(vec, row_ind, &*diag, rhs, &*x_old);
+                            let xi = x.get_unchecked_mut(row_ind);
+                            *xi = relax(*xi, v, omega);
                         }
                     }
                 }
-                unsafe {
-                    let diag = self.workspace.get_unchecked(n_rows + row_ind);
-                    let rhs_v = rhs.get_unchecked(row_ind);
-                    *x.get_unchecked_mut(row_ind) = (*rhs_v - sigma) / *diag;
-                }
             }
 
             unsafe {
-                self.A.mul_vec_unchecked(x, &mut self.workspace[0..n_rows]);
+                self.A.mul_vec_unchecked(x, &mut *res_vec);
             }
             // r = A*x - b
-            axpy(-T::one(), rhs, &mut self.workspace[..n_rows]);
+            axpy(-T::one(), rhs, &mut *res_vec);
             // |r|
-            let res = norm2(&self.workspace[..n_rows]);
+            let res = norm2(&*res_vec);
 
-            if res <= tol2 {
-                return Ok((it, res));
+            if monitor(it + 1, res / b_norm).is_break() || res <= tol2 {
+                // the count of the original Gauss-Seidel solver, which returned 1 after the
+                // first sweep and `it` after the sweep `it` of its loop starting at 1
+                return Ok((it.max(1), res));
             }
         }
         Err(SolverError::InsufficientIterNum(max_iter))
     }
 }
+
+/// The value $(b_i - \sum_{j \neq i} a_{ij} y_j) / a_{ii}$ solving the row `row_ind` for
+/// $x_i$, the other unknowns being taken from `y`.
+#[inline]
+unsafe fn row_solution<T: Scalar>(
+    row: CsVecView<T>,
+    row_ind: usize,
+    diag: &[T],
+    rhs: &[T],
+    y: &[T],
+) -> T {
+    let mut sigma = T::zero();
+    for (col_ind, val) in row.iter() {
+        if row_ind != col_ind {
+            sigma += (*val) * (*y.get_unchecked(col_ind));
+        }
+    }
+    (*rhs.get_unchecked(row_ind) - sigma) / *diag.get_unchecked(row_ind)
+}
+
+/// Update $x_i$ in place from the current values of the other unknowns.
+#[inline]
+unsafe fn relax_row<T: Scalar>(
+    row: CsVecView<T>,
+    row_ind: usize,
+    diag: &[T],
+    rhs: &[T],
+    x: &mut [T],
+    omega: T::Real,
+) {
+    let v = row_solution(row, row_ind, diag, rhs, x);
+    let xi = x.get_unchecked_mut(row_ind);
+    *xi = relax(*xi, v, omega);
+}
+
+/// $x + \omega (v - x)$. It returns `v` itself when $\omega = 1$, so that SOR with `omega = 1`
+/// performs exactly the same floating point operations as Gauss-Seidel.
+#[inline]
+fn relax<T: Scalar>(x: T, v: T, omega: T::Real) -> T {
+    if omega == T::Real::one() {
+        v
+    } else {
+        x + T::from_real(omega) * (v - x)
+    }
+}
+
+#[allow(non_snake_case)]
+pub struct GaussSeidel<'data, T: Scalar + PartialOrd + Send + Sync> {
+    inner: Stationary<'data, T>,
+}
+
+impl<'data, T: Scalar + PartialOrd + Send + Sync> GaussSeidel<'data, T> {
+    #[allow(non_snake_case)]
+    pub fn new(A: CsMatView<'data, T>) -> SolveResult<Self> {
+        Ok(GaussSeidel {
+            inner: Stationary::new(A, 2)?,
+        })
+    }
+
+    pub fn solve(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        eps: T::Real,
+    ) -> SolveResult<(usize, T::Real)> {
+        self.inner.solve(
+            rhs,
+            x,
+            T::Real::one(),
+            Sweep::Forward,
+            max_iter,
+            eps,
+            |_, _| ControlFlow::Continue(()),
+        )
+    }
+}
+
+impl<'data, T: Scalar + PartialOrd + Send + Sync> IterativeSolver<T> for GaussSeidel<'data, T> {
+    fn solve(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<(usize, T::Real)> {
+        GaussSeidel::solve(self, rhs, x, max_iter, tol)
+    }
+
+    fn solve_with_config(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        config: &SolveConfig<T::Real>,
+    ) -> SolveResult<(usize, T::Real)> {
+        let tol = l2_rel_tol(rhs, config)?;
+        let mut deadline = Deadline::new(config.time_limit);
+        let ret = self.inner.solve(
+            rhs,
+            x,
+            T::Real::one(),
+            Sweep::Forward,
+            config.max_iter,
+            tol,
+            |its, res| deadline.monitor(its, res),
+        );
+        deadline.finish(ret)
+    }
+
+    fn solve_report(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<SolveReport<T::Real>> {
+        let ret = GaussSeidel::solve(self, rhs, x, max_iter, tol);
+        report_outcome(
+            &self.inner.A,
+            &CpuVecOps,
+            rhs,
+            x,
+            &mut self.inner.workspace,
+            ret,
+        )
+    }
+}
+
+/// Successive over-relaxation: a Gauss-Seidel sweep whose updates are extrapolated by the
+/// relaxation factor $\omega$, $x_i \leftarrow x_i + \omega (\hat x_i - x_i)$.
+///
+/// It can only converge for $0 < \omega < 2$, and converges for any such $\omega$ when the
+/// matrix is Hermitian positive definite. `omega = 1` is Gauss-Seidel.
+#[allow(non_snake_case)]
+pub struct SOR<'data, T: Scalar + PartialOrd + Send + Sync> {
+    inner: Stationary<'data, T>,
+}
+
+impl<'data, T: Scalar + PartialOrd + Send + Sync> SOR<'data, T> {
+    #[allow(non_snake_case)]
+    pub fn new(A: CsMatView<'data, T>) -> SolveResult<Self> {
+        Ok(SOR {
+            inner: Stationary::new(A, 2)?,
+        })
+    }
+
+    /// Return [`SolverError::InvalidParameter`] if `omega` is not in $(0, 2)$.
+    pub fn solve(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        omega: T::Real,
+        max_iter: usize,
+        eps: T::Real,
+    ) -> SolveResult<(usize, T::Real)> {
+        self.inner
+            .solve(rhs, x, omega, Sweep::Forward, max_iter, eps, |_, _| {
+                ControlFlow::Continue(())
+            })
+    }
+}
+
+/// Symmetric SOR: each iteration is a forward SOR sweep followed by a backward one.
+///
+/// Its iteration matrix is similar to a Hermitian one when $A$ is Hermitian, which makes it
+/// the SOR variant to accelerate or to use as a smoother in symmetric settings. Like SOR, it
+/// requires $0 < \omega < 2$.
+/// Not to be confused with the [`SSOR`](crate::precond::SSOR) preconditioner, which applies
+/// one of its iterations.
+#[allow(non_snake_case)]
+pub struct SSOR<'data, T: Scalar + PartialOrd + Send + Sync> {
+    inner: Stationary<'data, T>,
+}
+
+impl<'data, T: Scalar + PartialOrd + Send + Sync> SSOR<'data, T> {
+    #[allow(non_snake_case)]
+    pub fn new(A: CsMatView<'data, T>) -> SolveResult<Self> {
+        Ok(SSOR {
+            inner: Stationary::new(A, 2)?,
+        })
+    }
+
+    /// Return [`SolverError::InvalidParameter`] if `omega` is not in $(0, 2)$.
+    pub fn solve(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        omega: T::Real,
+        max_iter: usize,
+        eps: T::Real,
+    ) -> SolveResult<(usize, T::Real)> {
+        self.inner
+            .solve(rhs, x, omega, Sweep::Symmetric, max_iter, eps, |_, _| {
+                ControlFlow::Continue(())
+            })
+    }
+}
+
+/// Weighted Jacobi iteration $x \leftarrow x + \omega D^{-1} (b - Ax)$.
+///
+/// All the unknowns are updated from the previous iterate. `omega` must lie in $(0, 2)$;
+/// convergence additionally requires the eigenvalues of $D^{-1}A$ to lie in
+/// $(0, 2 / \omega)$, e.g. `omega = 1` converges for strictly diagonally dominant matrices.
+/// Not to be confused with the [`Jacobi`](crate::precond::Jacobi) preconditioner.
+#[allow(non_snake_case)]
+pub struct WeightedJacobi<'data, T: Scalar + PartialOrd + Send + Sync> {
+    inner: Stationary<'data, T>,
+}
+
+impl<'data, T: Scalar + PartialOrd + Send + Sync> WeightedJacobi<'data, T> {
+    #[allow(non_snake_case)]
+    pub fn new(A: CsMatView<'data, T>) -> SolveResult<Self> {
+        Ok(WeightedJacobi {
+            inner: Stationary::new(A, 3)?,
+        })
+    }
+
+    /// Return [`SolverError::InvalidParameter`] if `omega` is not in $(0, 2)$.
+    pub fn solve(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        omega: T::Real,
+        max_iter: usize,
+        eps: T::Real,
+    ) -> SolveResult<(usize, T::Real)> {
+        self.inner
+            .solve(rhs, x, omega, Sweep::Jacobi, max_iter, eps, |_, _| {
+                ControlFlow::Continue(())
+            })
+    }
+}
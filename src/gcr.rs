@@ -0,0 +1,353 @@
+//! An impl of the Generalized Conjugate Residual algorithm with truncation.
+
+use super::{
+    config::{Deadline, ResidualKind, Side, SolveConfig},
+    error::*,
+    precond::{Identity, Precond},
+    report::*,
+    solver::{l2_rel_tol, replaces_residual, square_size, IterativeSolver},
+    vecalg::*,
+    MatVecMul,
+};
+use cauchy::Scalar;
+use num_traits::{float::*, One};
+use std::{intrinsics::unlikely, ops::ControlFlow, slice::from_raw_parts_mut};
+
+/// How [`GCR`] makes room for a new search direction once `max_krylov` of them are stored.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Truncation {
+    /// Discard the oldest direction, so that each new one is orthogonalized against the
+    /// `max_krylov` most recent ones. This is the truncated GCR, also known as ORTHOMIN.
+    KeepRecent,
+    /// Discard all the directions and restart the orthogonalization from scratch.
+    Restart,
+}
+
+/// Implementation of the Generalized Conjugate Residual algorithm for solving non-symmetric
+/// sparse linear systems.
+///
+/// Each iteration applies the preconditioner to the residual, $z = M^{-1}r$, and
+/// orthogonalizes $Az$ against the stored vectors $Ap_i$ with the same combination applied
+/// to $z$. The residual is then minimized along the new direction, so it decreases
+/// monotonically. Since the search directions are stored explicitly, the preconditioner may
+/// change from one iteration to the next, e.g. when it is an inner iterative solve, like
+/// [`FGMRES`](crate::FGMRES) does.
+///
+/// Up to `max_krylov` pairs $(p_i, Ap_i)$ are stored, see [`Truncation`] for how they are
+/// discarded. The workspace holds `2 * max_krylov + 3` vectors.
+#[allow(non_snake_case)]
+pub struct GCR<'data, T: Scalar, M: MatVecMul<T>, V: VecOps<T> = CpuVecOps> {
+    A: &'data M,
+    workspace: Vec<T>,
+    size: usize,
+    max_krylov: usize,
+    truncation: Truncation,
+    residual: ResidualKind,
+    ops: V,
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>> GCR<'data, T, M> {
+    #[allow(non_snake_case)]
+    pub fn new(A: &'data M, size: usize, max_krylov: usize) -> Self {
+        Self::with_vec_ops(A, size, max_krylov, CpuVecOps)
+    }
+
+    /// Create a solver for the operator `A`, whose size is given by its
+    /// [`shape`](MatVecMul::shape).
+    ///
+    /// Return [`SolverError::IncompatibleMatrixFormat`] if `A` is not square or doesn't provide
+    /// its shape.
+    #[allow(non_snake_case)]
+    pub fn from_op(A: &'data M, max_krylov: usize) -> SolveResult<Self> {
+        Ok(Self::with_vec_ops(
+            A,
+            square_size(A)?,
+            max_krylov,
+            CpuVecOps,
+        ))
+    }
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>, V: VecOps<T>> GCR<'data, T, M, V> {
+    /// Create a solver performing its vector operations through `ops`.
+    #[allow(non_snake_case)]
+    pub fn with_vec_ops(A: &'data M, size: usize, max_krylov: usize, ops: V) -> Self {
+        GCR {
+            A,
+            // r, and the pairs (p, Ap) with one more slot for the new direction
+            workspace: vec![T::zero(); size * (2 * max_krylov + 3)],
+            size,
+            max_krylov,
+            truncation: Truncation::KeepRecent,
+            residual: ResidualKind::True,
+            ops,
+        }
+    }
+
+    /// Set how the search directions are discarded. The default is
+    /// [`Truncation::KeepRecent`].
+    pub fn set_truncation(&mut self, truncation: Truncation) {
+        self.truncation = truncation;
+    }
+
+    pub fn truncation(&self) -> Truncation {
+        self.truncation
+    }
+
+    /// Set the residual compared against the tolerance by
+    /// [`solve_precond`](Self::solve_precond), which also returns it. The default is
+    /// [`ResidualKind::True`]. The preconditioned residual $z = M^{-1}r$ is the next search
+    /// direction before orthogonalization, so [`ResidualKind::Preconditioned`] only costs
+    /// one more application of $M^{-1}$, to $b$. With a flexible preconditioner, it is
+    /// measured with the operator of the next iteration.
+    pub fn set_residual_kind(&mut self, residual: ResidualKind) {
+        self.residual = residual;
+    }
+
+    pub fn residual_kind(&self) -> ResidualKind {
+        self.residual
+    }
+
+    pub fn max_krylov(&self) -> usize {
+        self.max_krylov
+    }
+
+    /// Solves Ax = b, without preconditioner
+    pub fn solve(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<(usize, T::Real)> {
+        self.solve_precond(rhs, x, &Identity, max_iter, tol)
+    }
+
+    /// Solves Ax = b with the preconditioner applied on the given `side`.
+    ///
+    /// [`Side::Right`] is the same as [`solve_precond`](Self::solve_precond). The
+    /// preconditioner may change at every step only on the right, so [`Side::Left`] is
+    /// rejected with [`SolverError::InvalidParameter`].
+    pub fn precond_solve_side<P: Precond<T> + ?Sized>(
+        &mut self,
+        precond: &P,
+        side: Side,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<(usize, T::Real)> {
+        if side == Side::Left {
+            return Err(SolverError::InvalidParameter(String::from(
+                "GCR applies its preconditioner on the right only",
+            )));
+        }
+        self.solve_precond(rhs, x, precond, max_iter, tol)
+    }
+
+    /// Solves Ax = b with the (right) preconditioner `precond`, which may be a different
+    /// operator at every call of [`Precond::apply`], e.g. a few iterations of an inner
+    /// solver.
+    ///
+    /// The convergence test and the returned residual use the true residual $|b - Ax| / |b|$,
+    /// or $|M^{-1}(b - Ax)| / |M^{-1}b|$ after [`set_residual_kind`](Self::set_residual_kind).
+    /// It returns
+    /// [`SolverError::BreakDown`] when the new direction $Az$ lies in the span of the stored
+    /// ones, which may happen with a singular or poor preconditioner.
+    pub fn solve_precond<P: Precond<T> + ?Sized>(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        precond: &P,
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<(usize, T::Real)> {
+        self.solve_impl(precond, rhs, x, max_iter, tol, 0, |_, _| {
+            ControlFlow::Continue(())
+        })
+    }
+
+    /// The GCR iterations. `monitor` is called with the iteration number and the relative
+    /// residual used by the convergence test, starting from the initial residual. The
+    /// iterations stop when it returns [`ControlFlow::Break`]. With a positive
+    /// `recompute_every`, the residual is replaced by the true one as described in
+    /// [`SolveConfig::recompute_residual_every`].
+    #[allow(clippy::too_many_arguments)]
+    fn solve_impl<P: Precond<T> + ?Sized, F: FnMut(usize, T::Real) -> ControlFlow<()>>(
+        &mut self,
+        precond: &P,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+        recompute_every: usize,
+        mut monitor: F,
+    ) -> SolveResult<(usize, T::Real)> {
+        let n = rhs.len();
+        let m = self.max_krylov;
+        // check the format
+        if n != self.size {
+            return Err(SolverError::IncompatibleMatrixFormat(String::from(
+                "Input vec dimension doesn't match the matrix size",
+            )));
+        }
+        if n != x.len() {
+            return Err(SolverError::IncompatibleMatrixFormat(String::from(
+                "Input and output vec dimension do not match",
+            )));
+        }
+        if m == 0 {
+            return Err(SolverError::InvalidParameter(String::from(
+                "The number of stored directions must be positive",
+            )));
+        }
+
+        let mut rhs_norm = self.ops.norm2(rhs);
+        if unlikely(rhs_norm <= T::Real::epsilon()) {
+            // when rhs = 0, x is set to zero.
+            x.iter_mut().for_each(|v| *v = T::zero());
+            return Ok((0, rhs_norm));
+        }
+
+        // Here is the internal memeory layout:
+        // [ r | p_0 ... p_m | Ap_0 ... Ap_m ]
+        let slots = m + 1;
+        let ptr = self.workspace.as_mut_ptr();
+        let vec = |k: usize| unsafe { from_raw_parts_mut(ptr.add(k * n), n) };
+        let r = vec(0);
+        let p = |i: usize| vec(1 + i);
+        let ap = |i: usize| vec(1 + slots + i);
+
+        // with the preconditioned residual, the next z = M^{-1} r is computed before the
+        // convergence test instead of after it
+        let test_z = self.residual == ResidualKind::Preconditioned;
+        if test_z {
+            precond.apply(rhs, p(0));
+            rhs_norm = self.ops.norm2(&*p(0)); // |M^{-1} b|
+        }
+        let threshold = tol * rhs_norm;
+        // the norm of the residual used by the convergence test
+        let residual_norm = |r: &[T], z: &mut [T]| {
+            if test_z {
+                precond.apply(r, &mut *z);
+                self.ops.norm2(z)
+            } else {
+                self.ops.norm2(r)
+            }
+        };
+
+        unsafe {
+            self.A.mul_vec_unchecked(x, ap(0)); // Ap_0 = A * x
+        }
+        self.ops.waxpy(-T::one(), &*ap(0), rhs, &mut *r); // r = rhs - A*x
+        let mut res_norm = if test_z {
+            precond.apply(&*r, p(0)); // z = M^{-1} r
+            self.ops.norm2(&*p(0))
+        } else {
+            self.ops.norm2(&*r)
+        };
+        if unlikely(monitor(0, res_norm / rhs_norm).is_break() || res_norm <= threshold) {
+            return Ok((0, res_norm / rhs_norm));
+        }
+
+        // the number of stored pairs, which are in the slots before `head`
+        let mut n_dirs = 0;
+        let mut head = 0;
+        for its in 0..max_iter {
+            let (z, c) = (p(head), ap(head));
+            if !test_z {
+                precond.apply(&*r, &mut *z); // z = M^{-1} r
+            }
+            unsafe {
+                self.A.mul_vec_unchecked(&*z, &mut *c); // c = A * z
+            }
+            let c_norm = self.ops.norm2(&*c);
+            if unlikely(!c_norm.is_finite()) {
+                return Err(SolverError::Diverged(its));
+            }
+            // make c orthogonal to the stored Ap_i, with |Ap_i| = 1
+            for k in 1..=n_dirs {
+                let i = (head + slots - k) % slots;
+                let beta = self.ops.conj_dot(&*ap(i), &*c);
+                self.ops.axpy(-beta, &*ap(i), &mut *c);
+                self.ops.axpy(-beta, &*p(i), &mut *z);
+            }
+            let nrm = self.ops.norm2(&*c);
+            if unlikely(nrm <= T::Real::epsilon() * c_norm) {
+                return Err(SolverError::BreakDown(its));
+            }
+            self.ops.rscale(T::Real::one() / nrm, &mut *c);
+            self.ops.rscale(T::Real::one() / nrm, &mut *z);
+
+            let alpha = self.ops.conj_dot(&*c, &*r);
+            self.ops.axpy(alpha, &*z, &mut *x); // x += alpha*p
+            self.ops.axpy(-alpha, &*c, &mut *r); // r -= alpha*Ap
+            head = (head + 1) % slots;
+            n_dirs = m.min(n_dirs + 1);
+            if self.truncation == Truncation::Restart && n_dirs == m {
+                n_dirs = 0;
+                head = 0;
+            }
+
+            res_norm = residual_norm(&*r, p(head)); // with test_z, the next z is in p_head
+            if replaces_residual(recompute_every, its + 1, res_norm <= threshold) {
+                unsafe {
+                    self.A.mul_vec_unchecked(x, ap(head)); // A * x, in the free slot
+                }
+                self.ops.waxpy(-T::one(), &*ap(head), rhs, &mut *r); // r = rhs - A*x
+                res_norm = residual_norm(&*r, p(head));
+            }
+            if unlikely(!res_norm.is_finite()) {
+                return Err(SolverError::Diverged(its + 1));
+            }
+            if monitor(its + 1, res_norm / rhs_norm).is_break() || res_norm <= threshold {
+                return Ok((its + 1, res_norm / rhs_norm));
+            }
+        }
+
+        Err(SolverError::InsufficientIterNum(max_iter))
+    }
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>, V: VecOps<T>> IterativeSolver<T> for GCR<'data, T, M, V> {
+    fn solve(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<(usize, T::Real)> {
+        GCR::solve(self, rhs, x, max_iter, tol)
+    }
+
+    fn solve_with_config(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        config: &SolveConfig<T::Real>,
+    ) -> SolveResult<(usize, T::Real)> {
+        let tol = l2_rel_tol(rhs, config)?;
+        let mut deadline = Deadline::new(config.time_limit);
+        let ret = self.solve_impl(
+            &Identity,
+            rhs,
+            x,
+            config.max_iter,
+            tol,
+            config.recompute_residual_every,
+            |its, res| deadline.monitor(its, res),
+        );
+        deadline.finish(ret)
+    }
+
+    fn solve_report(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<SolveReport<T::Real>> {
+        let ret = GCR::solve(self, rhs, x, max_iter, tol);
+        report_outcome(self.A, &self.ops, rhs, x, &mut self.workspace, ret)
+    }
+}
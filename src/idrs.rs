@@ -0,0 +1,338 @@
+//! An impl of the IDR(s) solver.
+
+use super::{
+    config::{Deadline, SolveConfig},
+    error::*,
+    report::*,
+    solver::{l2_rel_tol, square_size, IterativeSolver},
+    vecalg::*,
+    MatVecMul,
+};
+use cauchy::Scalar;
+use num_traits::{float::*, One, Zero};
+use std::{
+    intrinsics::unlikely, ops::ControlFlow, ptr::copy_nonoverlapping, slice::from_raw_parts_mut,
+};
+
+const DEFAULT_SEED: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// Implementation of the Induced Dimension Reduction method IDR(s) for solving non-symmetric
+/// sparse linear systems.
+///
+/// Each cycle performs `s + 1` matrix-vector products, forcing the residual into a sequence
+/// of nested subspaces orthogonal to `s` shadow vectors. A larger `s` usually takes fewer
+/// products to converge, at the cost of storing `3s + 3` vectors. IDR(1) is mathematically
+/// close to BiCGStab.
+///
+/// The shadow vectors are random, orthonormalized with modified Gram-Schmidt. They are
+/// generated from a seed, see [`set_seed`](Self::set_seed), so that the solves are
+/// reproducible.
+///
+/// See M. B. van Gijzen and P. Sonneveld, *Algorithm 913: An elegant IDR(s) variant that
+/// efficiently exploits biorthogonality properties*, ACM TOMS, 38 (2011).
+#[allow(non_snake_case)]
+pub struct IDRs<'data, T: Scalar, M: MatVecMul<T>, V: VecOps<T> = CpuVecOps> {
+    A: &'data M,
+    workspace: Vec<T>,
+    /// The `s x s` lower triangular matrix $P^H G$, stored column by column
+    small: Vec<T>,
+    size: usize,
+    s: usize,
+    seed: u64,
+    ops: V,
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>> IDRs<'data, T, M> {
+    #[allow(non_snake_case)]
+    pub fn new(A: &'data M, size: usize, s: usize) -> Self {
+        Self::with_vec_ops(A, size, s, CpuVecOps)
+    }
+
+    /// Create a solver for the operator `A`, whose size is given by its
+    /// [`shape`](MatVecMul::shape).
+    ///
+    /// Return [`SolverError::IncompatibleMatrixFormat`] if `A` is not square or doesn't provide
+    /// its shape.
+    #[allow(non_snake_case)]
+    pub fn from_op(A: &'data M, s: usize) -> SolveResult<Self> {
+        Ok(Self::with_vec_ops(A, square_size(A)?, s, CpuVecOps))
+    }
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>, V: VecOps<T>> IDRs<'data, T, M, V> {
+    /// Create a solver performing its vector operations through `ops`.
+    #[allow(non_snake_case)]
+    pub fn with_vec_ops(A: &'data M, size: usize, s: usize, ops: V) -> Self {
+        IDRs {
+            A,
+            // s shadow vectors, s vectors of G and U, and r, v, t
+            workspace: vec![T::zero(); size * (3 * s + 3)],
+            small: vec![T::zero(); s * s],
+            size,
+            s,
+            seed: DEFAULT_SEED,
+            ops,
+        }
+    }
+
+    /// Set the seed of the pseudo-random generator of the shadow vectors. Two solves with
+    /// the same seed give the same results.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Solves Ax = b, without preconditioner
+    ///
+    /// The returned iteration number counts the matrix-vector products, i.e. `s + 1` per
+    /// cycle, where BiCGStab performs two products per iteration. The convergence is checked
+    /// after every product.
+    pub fn solve(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<(usize, T::Real)> {
+        self.solve_impl(rhs, x, max_iter, tol, |_, _| ControlFlow::Continue(()))
+    }
+
+    /// The IDR(s) iterations. `monitor` is called with the iteration number and the relative
+    /// residual used by the convergence test, starting from the initial residual. The
+    /// iterations stop when it returns [`ControlFlow::Break`].
+    #[allow(clippy::many_single_char_names)]
+    fn solve_impl<F: FnMut(usize, T::Real) -> ControlFlow<()>>(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+        mut monitor: F,
+    ) -> SolveResult<(usize, T::Real)> {
+        let n = rhs.len();
+        let s = self.s;
+        // check the format
+        if n != self.size {
+            return Err(SolverError::IncompatibleMatrixFormat(String::from(
+                "Input vec dimension doesn't match the matrix size",
+            )));
+        }
+        if n != x.len() {
+            return Err(SolverError::IncompatibleMatrixFormat(String::from(
+                "Input and output vec dimension do not match",
+            )));
+        }
+        if s == 0 || s > n {
+            return Err(SolverError::InvalidParameter(String::from(
+                "The shadow space dimension s must be positive and at most the system size",
+            )));
+        }
+
+        let rhs_norm = self.ops.norm2(rhs);
+        if unlikely(rhs_norm <= T::Real::epsilon()) {
+            // when rhs = 0, x is set to zero.
+            x.iter_mut().for_each(|v| *v = T::zero());
+            return Ok((0, rhs_norm));
+        }
+        let threshold = tol * rhs_norm;
+
+        // Here is the internal memeory layout:
+        // [ P_0 ... P_{s-1} | G_0 ... G_{s-1} | U_0 ... U_{s-1} | r | v | t ]
+        let ptr = self.workspace.as_mut_ptr();
+        let vec = |k: usize| unsafe { from_raw_parts_mut(ptr.add(k * n), n) };
+        let p = |i: usize| vec(i);
+        let g = |i: usize| vec(s + i);
+        let u = |i: usize| vec(2 * s + i);
+        let r = vec(3 * s);
+        let v = vec(3 * s + 1);
+        let t = vec(3 * s + 2);
+
+        self.shadow_space(n)?;
+        for i in 0..s {
+            g(i).iter_mut().for_each(|v| *v = T::zero());
+            u(i).iter_mut().for_each(|v| *v = T::zero());
+        }
+        // M = I
+        let m = &mut self.small;
+        m.iter_mut().for_each(|v| *v = T::zero());
+        (0..s).for_each(|i| m[i * s + i] = T::one());
+
+        unsafe {
+            self.A.mul_vec_unchecked(x, &mut *t); // t = A * x
+        }
+        self.ops.waxpy(-T::one(), &*t, rhs, &mut *r); // r = rhs - A*x
+        let mut res_norm = self.ops.norm2(&*r);
+
+        let mut f = vec![T::zero(); s];
+        let mut c = vec![T::zero(); s];
+        let mut omega = T::one();
+        let mut its = 0;
+        loop {
+            if unlikely(!res_norm.is_finite()) {
+                return Err(SolverError::Diverged(its));
+            }
+            if monitor(its, res_norm / rhs_norm).is_break() || res_norm <= threshold {
+                return Ok((its, res_norm / rhs_norm));
+            }
+            if its >= max_iter {
+                return Err(SolverError::InsufficientIterNum(max_iter));
+            }
+
+            // f = P^H r
+            for (i, fi) in f.iter_mut().enumerate() {
+                *fi = self.ops.conj_dot(&*p(i), &*r);
+            }
+            for k in 0..s {
+                // solve the lower triangular system M[k.., k..] c = f[k..]
+                for i in k..s {
+                    let sum = (k..i).fold(T::zero(), |acc, j| acc + m[j * s + i] * c[j]);
+                    c[i] = (f[i] - sum) / m[i * s + i];
+                }
+                // v = r - G[k..] c
+                unsafe {
+                    copy_nonoverlapping(r.as_ptr(), v.as_mut_ptr(), n);
+                }
+                for (i, &ci) in c.iter().enumerate().skip(k) {
+                    self.ops.axpy(-ci, &*g(i), &mut *v);
+                }
+                // U_k = U[k..] c + omega * v
+                self.ops.axpby(omega, &*v, c[k], u(k));
+                for (i, &ci) in c.iter().enumerate().skip(k + 1) {
+                    self.ops.axpy(ci, &*u(i), u(k));
+                }
+                unsafe {
+                    self.A.mul_vec_unchecked(&*u(k), g(k)); // G_k = A * U_k
+                }
+                // make G_k orthogonal to P_0 ... P_{k-1}
+                for i in 0..k {
+                    let alpha = self.ops.conj_dot(&*p(i), &*g(k)) / m[i * s + i];
+                    self.ops.axpy(-alpha, &*g(i), g(k));
+                    self.ops.axpy(-alpha, &*u(i), u(k));
+                }
+                // the new column of M
+                for i in k..s {
+                    m[k * s + i] = self.ops.conj_dot(&*p(i), &*g(k));
+                }
+                if unlikely(m[k * s + k].is_zero()) {
+                    return Err(SolverError::BreakDown(its));
+                }
+                let beta = f[k] / m[k * s + k];
+                self.ops.axpy(-beta, &*g(k), &mut *r); // r -= beta * G_k
+                self.ops.axpy(beta, &*u(k), &mut *x); // x += beta * U_k
+                res_norm = self.ops.norm2(&*r);
+                its += 1;
+                if unlikely(!res_norm.is_finite()) {
+                    return Err(SolverError::Diverged(its));
+                }
+                if monitor(its, res_norm / rhs_norm).is_break() || res_norm <= threshold {
+                    return Ok((its, res_norm / rhs_norm));
+                }
+                if its >= max_iter {
+                    return Err(SolverError::InsufficientIterNum(max_iter));
+                }
+                for i in k + 1..s {
+                    f[i] -= beta * m[k * s + i];
+                }
+            }
+
+            // enter the next subspace with a minimal-residual step
+            unsafe {
+                copy_nonoverlapping(r.as_ptr(), v.as_mut_ptr(), n); // v = r
+                self.A.mul_vec_unchecked(&*v, &mut *t); // t = A * v
+            }
+            omega = next_omega(&self.ops, &*t, &*r);
+            if unlikely(omega.is_zero()) {
+                return Err(SolverError::BreakDown(its));
+            }
+            self.ops.axpy(-omega, &*t, &mut *r); // r -= omega * t
+            self.ops.axpy(omega, &*v, &mut *x); // x += omega * v
+            res_norm = self.ops.norm2(&*r);
+            its += 1;
+        }
+    }
+
+    /// Fill the shadow vectors with orthonormalized pseudo-random vectors drawn from the seed.
+    fn shadow_space(&mut self, n: usize) -> SolveResult<()> {
+        let mut state = self.seed;
+        let ptr = self.workspace.as_mut_ptr();
+        for k in 0..self.s {
+            let pk = unsafe { from_raw_parts_mut(ptr.add(k * n), n) };
+            for v in pk.iter_mut() {
+                state = state
+                    .wrapping_mul(6_364_136_223_846_793_005)
+                    .wrapping_add(1_442_695_040_888_963_407);
+                *v = T::from_real(T::real((state >> 11) as f64 / (1_u64 << 53) as f64 - 0.5));
+            }
+            // modified Gram-Schmidt
+            for i in 0..k {
+                let pi = unsafe { from_raw_parts_mut(ptr.add(i * n), n) };
+                let d = self.ops.conj_dot(&*pi, &*pk);
+                self.ops.axpy(-d, &*pi, &mut *pk);
+            }
+            let nrm = self.ops.norm2(&*pk);
+            if unlikely(nrm <= T::Real::epsilon()) {
+                return Err(SolverError::BreakDown(0));
+            }
+            self.ops.rscale(T::Real::one() / nrm, pk);
+        }
+        Ok(())
+    }
+}
+
+/// The step $\omega = t^H r / t^H t$ minimizing $|r - \omega t|$, enlarged when $t$ and $r$
+/// are nearly orthogonal to keep the next step from stagnating.
+fn next_omega<T: Scalar, V: VecOps<T>>(ops: &V, t: &[T], r: &[T]) -> T {
+    let kappa = T::real(0.7);
+    let (t_norm, r_norm) = (ops.norm2(t), ops.norm2(r));
+    if unlikely(t_norm.is_zero() || r_norm.is_zero()) {
+        return T::zero();
+    }
+    let tr = ops.conj_dot(t, r);
+    let omega = tr / T::from_real(t_norm * t_norm);
+    let rho = tr.abs() / (t_norm * r_norm);
+    if rho < kappa && !rho.is_zero() {
+        omega * T::from_real(kappa / rho)
+    } else {
+        omega
+    }
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>, V: VecOps<T>> IterativeSolver<T> for IDRs<'data, T, M, V> {
+    fn solve(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<(usize, T::Real)> {
+        IDRs::solve(self, rhs, x, max_iter, tol)
+    }
+
+    fn solve_with_config(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        config: &SolveConfig<T::Real>,
+    ) -> SolveResult<(usize, T::Real)> {
+        let tol = l2_rel_tol(rhs, config)?;
+        let mut deadline = Deadline::new(config.time_limit);
+        let ret = self.solve_impl(rhs, x, config.max_iter, tol, |its, res| {
+            deadline.monitor(its, res)
+        });
+        deadline.finish(ret)
+    }
+
+    fn solve_report(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<SolveReport<T::Real>> {
+        let ret = IDRs::solve(self, rhs, x, max_iter, tol);
+        report_outcome(self.A, &self.ops, rhs, x, &mut self.workspace, ret)
+    }
+}
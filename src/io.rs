@@ -0,0 +1,525 @@
+//! Reading and writing matrices stored in the [Matrix Market](https://math.nist.gov/MatrixMarket/formats.html)
+//! exchange format, e.g. the matrices of the SuiteSparse collection.
+
+use super::{cast_as, error::*, same_type};
+use cauchy::Scalar;
+use num_complex::{Complex32, Complex64};
+use num_traits::ToPrimitive;
+use sprs::{CsMatI, TriMatI};
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Write},
+    path::Path,
+};
+
+/// The symmetry qualifier in the header of a Matrix Market file.
+#[derive(Clone, Copy, PartialEq)]
+enum Symmetry {
+    General,
+    Symmetric,
+    Hermitian,
+    SkewSymmetric,
+}
+
+/// Read a sparse matrix in the Matrix Market coordinate format from the file at `path`.
+///
+/// See [`read_matrix_market_from`] for the supported formats.
+pub fn read_matrix_market<T: Scalar, P: AsRef<Path>>(path: P) -> SolveResult<CsMatI<T, i32>> {
+    let file = File::open(path)?;
+    read_matrix_market_from(BufReader::new(file))
+}
+
+/// Read a sparse matrix in the Matrix Market coordinate format from `reader`, and return it
+/// in CSR format with sorted indices, which is suitable for [`MklMat::new`].
+///
+/// `real`, `integer` and `complex` values are supported, the latter only when `T` is
+/// complex. For `symmetric`, `hermitian` and `skew-symmetric` matrices, the file stores the
+/// lower triangular part only and the upper triangular part is mirrored from it.
+///
+/// It returns [`SolverError::IncompatibleMatrixFormat`] for dense (`array`) matrices, which
+/// are read by [`read_matrix_market_vec_from`], for `pattern` matrices, which carry no
+/// usable values, and for malformed files.
+///
+/// [`MklMat::new`]: ../struct.MklMat.html#method.new
+pub fn read_matrix_market_from<T: Scalar, R: BufRead>(reader: R) -> SolveResult<CsMatI<T, i32>> {
+    let mut lines = reader.lines();
+    let header = read_header::<T, _>(&mut lines)?;
+    if header.array {
+        return Err(format_error(
+            "Only the coordinate format of Matrix Market is supported",
+        ));
+    }
+    let (is_complex, symmetry) = (header.is_complex, header.symmetry);
+
+    let mut data_lines = skip_comments(lines);
+    let size = read_size_line(&mut data_lines)?;
+    if size.len() != 3 {
+        return Err(format_error("Invalid size line"));
+    }
+    let (rows, cols, nnz) = (size[0], size[1], size[2]);
+    if symmetry != Symmetry::General && rows != cols {
+        return Err(format_error("A symmetric matrix must be square"));
+    }
+
+    // entries
+    let mut tri = TriMatI::<T, i32>::with_capacity((rows, cols), 2 * nnz);
+    let mut count = 0;
+    for line in data_lines {
+        let line = line?;
+        if count == nnz {
+            return Err(format_error("More entries than declared in the size line"));
+        }
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let n_values = if is_complex { 2 } else { 1 };
+        if tokens.len() != 2 + n_values {
+            return Err(format_error(&format!("Invalid entry '{}'", line.trim())));
+        }
+        let parse_err = || format_error(&format!("Invalid entry '{}'", line.trim()));
+        let i = tokens[0].parse::<usize>().map_err(|_| parse_err())?;
+        let j = tokens[1].parse::<usize>().map_err(|_| parse_err())?;
+        if i == 0 || i > rows || j == 0 || j > cols {
+            return Err(format_error(&format!(
+                "Entry ({}, {}) is out of the matrix bounds",
+                i, j
+            )));
+        }
+        let re = tokens[2].parse::<f64>().map_err(|_| parse_err())?;
+        let im = if is_complex {
+            tokens[3].parse::<f64>().map_err(|_| parse_err())?
+        } else {
+            0.
+        };
+        let v = make_value::<T>(re, im);
+        let (i, j) = (i - 1, j - 1);
+        tri.add_triplet(i, j, v);
+        if i != j {
+            match symmetry {
+                Symmetry::General => {}
+                Symmetry::Symmetric => tri.add_triplet(j, i, v),
+                Symmetry::Hermitian => tri.add_triplet(j, i, v.conj()),
+                Symmetry::SkewSymmetric => tri.add_triplet(j, i, -v),
+            }
+        }
+        count += 1;
+    }
+    if count != nnz {
+        return Err(format_error(&format!(
+            "Expected {} entries, but found {}",
+            nnz, count
+        )));
+    }
+
+    Ok(tri.to_csr())
+}
+
+/// Read a dense matrix in the Matrix Market array format from the file at `path`.
+///
+/// See [`read_matrix_market_vec_from`] for the supported formats.
+pub fn read_matrix_market_vec<T: Scalar, P: AsRef<Path>>(path: P) -> SolveResult<Vec<T>> {
+    let file = File::open(path)?;
+    read_matrix_market_vec_from(BufReader::new(file))
+}
+
+/// Read a dense matrix in the Matrix Market array format from `reader`, e.g. a vector
+/// written by [`write_matrix_market_vec`], and return its entries in column-major order.
+///
+/// `real`, `integer` and `complex` values are supported, the latter only when `T` is
+/// complex. Only `general` matrices are supported.
+///
+/// It returns [`SolverError::IncompatibleMatrixFormat`] for sparse (`coordinate`) matrices,
+/// which are read by [`read_matrix_market_from`], and for malformed files.
+pub fn read_matrix_market_vec_from<T: Scalar, R: BufRead>(reader: R) -> SolveResult<Vec<T>> {
+    let mut lines = reader.lines();
+    let header = read_header::<T, _>(&mut lines)?;
+    if !header.array {
+        return Err(format_error(
+            "Only the array format of Matrix Market is supported for dense matrices",
+        ));
+    }
+    if header.symmetry != Symmetry::General {
+        return Err(format_error(
+            "Only general dense matrices of Matrix Market are supported",
+        ));
+    }
+
+    let mut data_lines = skip_comments(lines);
+    let size = read_size_line(&mut data_lines)?;
+    if size.len() != 2 {
+        return Err(format_error("Invalid size line"));
+    }
+    let len = size[0] * size[1];
+
+    let n_values = if header.is_complex { 2 } else { 1 };
+    let mut v = Vec::with_capacity(len);
+    for line in data_lines {
+        let line = line?;
+        if v.len() == len {
+            return Err(format_error("More entries than declared in the size line"));
+        }
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        if tokens.len() != n_values {
+            return Err(format_error(&format!("Invalid entry '{}'", line.trim())));
+        }
+        let parse_err = || format_error(&format!("Invalid entry '{}'", line.trim()));
+        let re = tokens[0].parse::<f64>().map_err(|_| parse_err())?;
+        let im = if header.is_complex {
+            tokens[1].parse::<f64>().map_err(|_| parse_err())?
+        } else {
+            0.
+        };
+        v.push(make_value::<T>(re, im));
+    }
+    if v.len() != len {
+        return Err(format_error(&format!(
+            "Expected {} entries, but found {}",
+            len,
+            v.len()
+        )));
+    }
+    Ok(v)
+}
+
+/// The banner of a Matrix Market file.
+struct Header {
+    /// `array` (dense) rather than `coordinate` (sparse) format
+    array: bool,
+    is_complex: bool,
+    symmetry: Symmetry,
+}
+
+/// Parse the banner, the first line of the file, and check that its values fit in `T`.
+fn read_header<T: Scalar, L: Iterator<Item = std::io::Result<String>>>(
+    lines: &mut L,
+) -> SolveResult<Header> {
+    let header = match lines.next() {
+        Some(line) => line?.to_lowercase(),
+        None => return Err(format_error("Empty Matrix Market file")),
+    };
+    let tokens: Vec<&str> = header.split_whitespace().collect();
+    if tokens.len() != 5 || tokens[0] != "%%matrixmarket" || tokens[1] != "matrix" {
+        return Err(format_error("Invalid Matrix Market header"));
+    }
+    let array = match tokens[2] {
+        "coordinate" => false,
+        "array" => true,
+        format => return Err(format_error(&format!("Unknown format '{}'", format))),
+    };
+    let is_complex = match tokens[3] {
+        "real" | "double" | "integer" => false,
+        "complex" => true,
+        "pattern" => {
+            return Err(format_error(
+                "Pattern matrices carry no values and cannot be read as a numeric matrix",
+            ))
+        }
+        field => return Err(format_error(&format!("Unknown value type '{}'", field))),
+    };
+    if is_complex && !(same_type::<T, Complex32>() || same_type::<T, Complex64>()) {
+        return Err(format_error(
+            "A complex matrix cannot be read into a real-valued type",
+        ));
+    }
+    let symmetry = match tokens[4] {
+        "general" => Symmetry::General,
+        "symmetric" => Symmetry::Symmetric,
+        "hermitian" => Symmetry::Hermitian,
+        "skew-symmetric" => Symmetry::SkewSymmetric,
+        sym => return Err(format_error(&format!("Unknown symmetry '{}'", sym))),
+    };
+    Ok(Header {
+        array,
+        is_complex,
+        symmetry,
+    })
+}
+
+/// The lines after the banner, without the comments and the blank lines.
+fn skip_comments<L: Iterator<Item = std::io::Result<String>>>(
+    lines: L,
+) -> impl Iterator<Item = std::io::Result<String>> {
+    lines.filter(|line| match line {
+        Ok(l) => {
+            let l = l.trim();
+            !l.is_empty() && !l.starts_with('%')
+        }
+        Err(_) => true,
+    })
+}
+
+/// Read the size line, i.e. the first line after the comments.
+fn read_size_line<L: Iterator<Item = std::io::Result<String>>>(
+    lines: &mut L,
+) -> SolveResult<Vec<usize>> {
+    let size_line = match lines.next() {
+        Some(line) => line?,
+        None => return Err(format_error("Missing the size line")),
+    };
+    size_line
+        .split_whitespace()
+        .map(|v| v.parse::<usize>())
+        .collect::<Result<_, _>>()
+        .map_err(|_| format_error("Invalid size line"))
+}
+
+/// Write the sparse matrix `m` in the Matrix Market coordinate format.
+///
+/// The value type in the banner is `real` or `complex` according to `T`. A symmetric
+/// matrix, or a complex Hermitian one, is written with the `symmetric` or `hermitian`
+/// qualifier and only its lower triangular part is stored.
+///
+/// Values are written with enough digits to be read back exactly by [`read_matrix_market`].
+pub fn write_matrix_market_mat<T: Scalar, W: Write>(
+    mut w: W,
+    m: &CsMatI<T, i32>,
+) -> SolveResult<()> {
+    let symmetry = if m.rows() != m.cols() {
+        Symmetry::General
+    } else if m
+        .iter()
+        .all(|(v, (i, j))| m.get(j as usize, i as usize) == Some(v))
+    {
+        Symmetry::Symmetric
+    } else if m
+        .iter()
+        .all(|(v, (i, j))| m.get(j as usize, i as usize) == Some(&v.conj()))
+    {
+        Symmetry::Hermitian
+    } else {
+        Symmetry::General
+    };
+    let sym = match symmetry {
+        Symmetry::General => "general",
+        Symmetry::Symmetric => "symmetric",
+        Symmetry::Hermitian => "hermitian",
+        Symmetry::SkewSymmetric => unreachable!(),
+    };
+    let lower = symmetry != Symmetry::General;
+    let nnz = if lower {
+        m.iter().filter(|(_, (i, j))| i >= j).count()
+    } else {
+        m.nnz()
+    };
+
+    writeln!(
+        w,
+        "%%MatrixMarket matrix coordinate {} {}",
+        field_name::<T>(),
+        sym
+    )?;
+    writeln!(w, "{} {} {}", m.rows(), m.cols(), nnz)?;
+    for (v, (i, j)) in m.iter() {
+        if lower && i < j {
+            continue;
+        }
+        write!(w, "{} {} ", i + 1, j + 1)?;
+        write_value(&mut w, *v)?;
+    }
+    Ok(())
+}
+
+/// Write the vector `v` as a dense column in the Matrix Market array format, which can be
+/// loaded by MATLAB/Octave and read back by [`read_matrix_market_vec`].
+pub fn write_matrix_market_vec<T: Scalar, W: Write>(mut w: W, v: &[T]) -> SolveResult<()> {
+    writeln!(
+        w,
+        "%%MatrixMarket matrix array {} general",
+        field_name::<T>()
+    )?;
+    writeln!(w, "{} 1", v.len())?;
+    for &val in v {
+        write_value(&mut w, val)?;
+    }
+    Ok(())
+}
+
+#[inline]
+fn field_name<T: Scalar>() -> &'static str {
+    if same_type::<T, Complex32>() || same_type::<T, Complex64>() {
+        "complex"
+    } else {
+        "real"
+    }
+}
+
+/// Write one value followed by a newline. `{:e}` prints the shortest representation that
+/// parses back to the same `f64`.
+#[inline]
+fn write_value<T: Scalar, W: Write>(w: &mut W, v: T) -> std::io::Result<()> {
+    let re = v.re().to_f64().unwrap();
+    if same_type::<T, Complex32>() || same_type::<T, Complex64>() {
+        writeln!(w, "{:e} {:e}", re, v.im().to_f64().unwrap())
+    } else {
+        writeln!(w, "{:e}", re)
+    }
+}
+
+#[inline]
+fn format_error(msg: &str) -> SolverError {
+    SolverError::IncompatibleMatrixFormat(String::from(msg))
+}
+
+/// Build a value of type `T` from its real and imaginary parts. The imaginary part is
+/// dropped if `T` is real.
+#[inline]
+fn make_value<T: Scalar>(re: f64, im: f64) -> T {
+    if same_type::<T, f64>() {
+        cast_as(&re)
+    } else if same_type::<T, f32>() {
+        cast_as(&(re as f32))
+    } else if same_type::<T, Complex64>() {
+        cast_as(&Complex64::new(re, im))
+    } else if same_type::<T, Complex32>() {
+        cast_as(&Complex32::new(re as f32, im as f32))
+    } else {
+        unreachable!()
+    }
+}
+
+// --------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_symmetric_real() {
+        let mtx = "%%MatrixMarket matrix coordinate real symmetric
+% a 3x3 symmetric matrix
+%
+3 3 4
+1 1 4.0
+2 1 -1.0
+3 2 -2.5
+3 3 2
+";
+        let mat: CsMatI<f64, i32> = read_matrix_market_from(mtx.as_bytes()).unwrap();
+        assert!(mat.is_csr());
+        assert_eq!(mat.shape(), (3, 3));
+        assert_eq!(mat.indptr(), &[0, 2, 4, 6]);
+        assert_eq!(mat.indices(), &[0, 1, 0, 2, 1, 2]);
+        assert_eq!(mat.data(), &[4., -1., -1., -2.5, -2.5, 2.]);
+    }
+
+    #[test]
+    fn read_hermitian_complex() {
+        let mtx = "%%MatrixMarket matrix coordinate complex hermitian
+2 2 2
+1 1 1.0 0.0
+2 1 0.5 -2.0
+";
+        let mat: CsMatI<Complex64, i32> = read_matrix_market_from(mtx.as_bytes()).unwrap();
+        assert_eq!(mat.indices(), &[0, 1, 0]);
+        assert_eq!(
+            mat.data(),
+            &[
+                Complex64::new(1., 0.),
+                Complex64::new(0.5, 2.),
+                Complex64::new(0.5, -2.)
+            ]
+        );
+
+        // complex values cannot be read as real ones
+        let ret = read_matrix_market_from::<f64, _>(mtx.as_bytes());
+        assert!(matches!(ret, Err(SolverError::IncompatibleMatrixFormat(_))));
+    }
+
+    #[test]
+    fn read_invalid() {
+        let pattern = "%%MatrixMarket matrix coordinate pattern general
+2 2 1
+1 2
+";
+        let ret = read_matrix_market_from::<f64, _>(pattern.as_bytes());
+        assert!(matches!(ret, Err(SolverError::IncompatibleMatrixFormat(_))));
+
+        let short = "%%MatrixMarket matrix coordinate real general
+2 2 2
+1 2 1.0
+";
+        let ret = read_matrix_market_from::<f64, _>(short.as_bytes());
+        assert!(matches!(ret, Err(SolverError::IncompatibleMatrixFormat(_))));
+
+        let ret = read_matrix_market::<f64, _>("/nonexistent/matrix.mtx");
+        assert!(matches!(ret, Err(SolverError::Io(_))));
+    }
+
+    #[test]
+    fn write_round_trip() {
+        // symmetric real
+        let mut tri = TriMatI::<f64, i32>::new((3, 3));
+        tri.add_triplet(0, 0, 0.1);
+        tri.add_triplet(1, 0, -1. / 3.);
+        tri.add_triplet(0, 1, -1. / 3.);
+        tri.add_triplet(2, 2, 1E-300);
+        tri.add_triplet(2, 1, std::f64::consts::PI);
+        tri.add_triplet(1, 2, std::f64::consts::PI);
+        let mat = tri.to_csr();
+        let mut buf = Vec::new();
+        write_matrix_market_mat(&mut buf, &mat).unwrap();
+        let text = String::from_utf8(buf).unwrap();
+        assert!(text.starts_with("%%MatrixMarket matrix coordinate real symmetric\n3 3 4\n"));
+        let back: CsMatI<f64, i32> = read_matrix_market_from(text.as_bytes()).unwrap();
+        assert_eq!(back, mat);
+
+        // hermitian and general complex
+        let mut tri = TriMatI::<Complex64, i32>::new((2, 2));
+        tri.add_triplet(0, 0, Complex64::new(2., 0.));
+        tri.add_triplet(1, 0, Complex64::new(0.1, 1. / 7.));
+        tri.add_triplet(0, 1, Complex64::new(0.1, -1. / 7.));
+        let mat = tri.to_csr();
+        let mut buf = Vec::new();
+        write_matrix_market_mat(&mut buf, &mat).unwrap();
+        let text = String::from_utf8(buf).unwrap();
+        assert!(text.starts_with("%%MatrixMarket matrix coordinate complex hermitian\n"));
+        let back: CsMatI<Complex64, i32> = read_matrix_market_from(text.as_bytes()).unwrap();
+        assert_eq!(back, mat);
+
+        tri.add_triplet(1, 1, Complex64::new(-3e-5, 1E10));
+        let mat = tri.to_csr();
+        let mut buf = Vec::new();
+        write_matrix_market_mat(&mut buf, &mat).unwrap();
+        let text = String::from_utf8(buf).unwrap();
+        assert!(text.starts_with("%%MatrixMarket matrix coordinate complex general\n2 2 4\n"));
+        let back: CsMatI<Complex64, i32> = read_matrix_market_from(text.as_bytes()).unwrap();
+        assert_eq!(back, mat);
+    }
+
+    #[test]
+    fn write_vec() {
+        let mut buf = Vec::new();
+        write_matrix_market_vec(&mut buf, &[1.5, -0.25]).unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "%%MatrixMarket matrix array real general\n2 1\n1.5e0\n-2.5e-1\n"
+        );
+
+        let mut buf = Vec::new();
+        write_matrix_market_vec(&mut buf, &[Complex64::new(1., -2.)]).unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "%%MatrixMarket matrix array complex general\n1 1\n1e0 -2e0\n"
+        );
+
+        // round trip
+        let v = vec![0.1, -1. / 3., 1E-300, std::f64::consts::PI];
+        let mut buf = Vec::new();
+        write_matrix_market_vec(&mut buf, &v).unwrap();
+        let back: Vec<f64> = read_matrix_market_vec_from(buf.as_slice()).unwrap();
+        assert_eq!(back, v);
+        let v = vec![Complex64::new(0.1, 1. / 7.), Complex64::new(-3e-5, 1E10)];
+        let mut buf = Vec::new();
+        write_matrix_market_vec(&mut buf, &v).unwrap();
+        let back: Vec<Complex64> = read_matrix_market_vec_from(buf.as_slice()).unwrap();
+        assert_eq!(back, v);
+
+        // the formats are not interchangeable
+        let ret = read_matrix_market_from::<f64, _>(
+            &b"%%MatrixMarket matrix array real general\n1 1\n1\n"[..],
+        );
+        assert!(matches!(ret, Err(SolverError::IncompatibleMatrixFormat(_))));
+        let ret = read_matrix_market_vec_from::<f64, _>(
+            &b"%%MatrixMarket matrix coordinate real general\n1 1 1\n1 1 1\n"[..],
+        );
+        assert!(matches!(ret, Err(SolverError::IncompatibleMatrixFormat(_))));
+    }
+}
@@ -0,0 +1,175 @@
+//! The symmetric Lanczos process, shared by the solvers for Hermitian systems.
+
+use super::{solver::check_size, vecalg::*, MatVecMul};
+use cauchy::Scalar;
+use num_traits::{One, Zero};
+use std::{ptr::copy_nonoverlapping, slice::from_raw_parts_mut};
+
+/// The scratch memory of [`Lanczos`], either owned or borrowed from a solver workspace.
+enum Storage<'data, T> {
+    Owned(Vec<T>),
+    Borrowed(&'data mut [T]),
+}
+
+impl<'data, T> Storage<'data, T> {
+    fn as_slice(&self) -> &[T] {
+        match self {
+            Storage::Owned(v) => v.as_slice(),
+            Storage::Borrowed(v) => v,
+        }
+    }
+
+    fn as_mut_ptr(&mut self) -> *mut T {
+        match self {
+            Storage::Owned(v) => v.as_mut_ptr(),
+            Storage::Borrowed(v) => v.as_mut_ptr(),
+        }
+    }
+}
+
+/// The symmetric Lanczos process for real-valued symmetric or complex-valued Hermitian
+/// operators.
+///
+/// Starting from $q_1 = v / |v|$, each [`step`](Self::step) runs the three-term recurrence
+/// $$\beta_k q_{k+1} = A q_k - \alpha_k q_k - \beta_{k-1} q_{k-1},$$
+/// which builds an orthonormal basis $Q_k$ of the Krylov subspace together with the
+/// tridiagonal matrix $T_k = Q_k^H A Q_k$, whose diagonal is $\alpha_k$ and off-diagonal is
+/// $\beta_k$. Only the last three basis vectors are stored, so no reorthogonalization is
+/// done, and the vectors lose their orthogonality in finite precision once Ritz values
+/// converge.
+///
+/// [`MinRes`](crate::MinRes) runs on top of it. The workspace holds three vectors.
+///
+/// **Note:** This class won't check if the operator is Hermitian.
+#[allow(non_snake_case)]
+pub struct Lanczos<'data, T: Scalar, M: MatVecMul<T>, V: VecOps<T> = CpuVecOps> {
+    A: &'data M,
+    workspace: Storage<'data, T>,
+    size: usize,
+    ops: V,
+    /// The offsets of $q_{k-1}$, $q_k$ and $q_{k+1}$ in the workspace
+    slots: [usize; 3],
+    beta: T::Real,
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>> Lanczos<'data, T, M> {
+    /// Create the process for the operator `A` of size `size`.
+    ///
+    /// **Panics** if `A` provides its [`shape`](MatVecMul::shape) and it differs from
+    /// `size x size`.
+    #[allow(non_snake_case)]
+    pub fn new(A: &'data M, size: usize) -> Self {
+        Self::with_vec_ops(A, size, CpuVecOps)
+    }
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>, V: VecOps<T>> Lanczos<'data, T, M, V> {
+    /// Create the process performing its vector operations through `ops`.
+    ///
+    /// **Panics** if `A` provides its [`shape`](MatVecMul::shape) and it differs from
+    /// `size x size`.
+    #[allow(non_snake_case)]
+    pub fn with_vec_ops(A: &'data M, size: usize, ops: V) -> Self {
+        check_size(A, size);
+        let workspace = Storage::Owned(vec![T::zero(); Self::workspace_len(size)]);
+        Self::with_storage(A, size, ops, workspace)
+    }
+
+    /// Create the process in the first [`workspace_len(size)`](Self::workspace_len) scalars
+    /// of `workspace`, which a solver borrows from its own scratch memory.
+    #[allow(non_snake_case)]
+    pub(crate) fn in_workspace(
+        A: &'data M,
+        size: usize,
+        ops: V,
+        workspace: &'data mut [T],
+    ) -> Self {
+        debug_assert!(workspace.len() >= Self::workspace_len(size));
+        Self::with_storage(A, size, ops, Storage::Borrowed(workspace))
+    }
+
+    #[allow(non_snake_case)]
+    fn with_storage(A: &'data M, size: usize, ops: V, workspace: Storage<'data, T>) -> Self {
+        Lanczos {
+            A,
+            workspace,
+            size,
+            ops,
+            slots: [0, size, 2 * size],
+            beta: T::Real::zero(),
+        }
+    }
+
+    /// The length of the workspace needed for an operator of size `size`.
+    pub fn workspace_len(size: usize) -> usize {
+        size * 3
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Restart the process from the vector `v`, which becomes $q_1 = v / |v|$ after the next
+    /// [`step`](Self::step). It returns $\beta_0 = |v|$.
+    ///
+    /// When $|v|$ vanishes, there is no basis to build and the next steps are meaningless.
+    ///
+    /// **Panics** if the length of `v` differs from the operator size.
+    pub fn start(&mut self, v: &[T]) -> T::Real {
+        assert_eq!(v.len(), self.size, "Dimension mismatch");
+        let n = self.size;
+        let ptr = self.workspace.as_mut_ptr();
+        let [_, cur, next] = self.slots;
+        unsafe {
+            copy_nonoverlapping(v.as_ptr(), ptr.add(next), n); // q_1 = v
+        }
+        let q_next = unsafe { from_raw_parts_mut(ptr.add(next), n) };
+        self.beta = self.ops.norm2(&*q_next);
+        self.ops.rscale(T::Real::one() / self.beta, q_next);
+        // q_0 = 0, so that the first step has no q_{k-1} term
+        let q_cur = unsafe { from_raw_parts_mut(ptr.add(cur), n) };
+        q_cur.iter_mut().for_each(|t| *t = T::zero());
+        self.beta
+    }
+
+    /// Advance the process by one matrix-vector product, and return $(\alpha_k, \beta_k)$.
+    ///
+    /// After the call, [`vector`](Self::vector) is $q_k$, and $q_{k+1}$ is computed and
+    /// normalized. A vanishing $\beta_k$ means that the Krylov subspace is invariant under
+    /// $A$, and $q_{k+1}$ is then not defined.
+    pub fn step(&mut self) -> (T::Real, T::Real) {
+        let n = self.size;
+        // Here we just move the offsets to avoid memory copy: q_{k-1} is overwritten
+        let [old, cur, next] = self.slots;
+        self.slots = [cur, next, old];
+        let ptr = self.workspace.as_mut_ptr();
+        let q_old = unsafe { from_raw_parts_mut(ptr.add(cur), n) };
+        let q = unsafe { from_raw_parts_mut(ptr.add(next), n) };
+        let q_new = unsafe { from_raw_parts_mut(ptr.add(old), n) };
+
+        // According to the Wiki (https://en.wikipedia.org/wiki/Lanczos_algorithm)
+        // This order of computing Lanczos vectors is the most numerically stable.
+        // comptue q_new = A * q_k
+        //         alpha = conj(q_k).q_new, which is real for Hermitian A
+        let alpha = unsafe { self.A.mul_vec_rdot_unchecked(&*q, &mut *q_new) };
+        self.ops.raxpy(-self.beta, &*q_old, &mut *q_new); // A*q_k - beta_{k-1} q_{k-1}
+        self.ops.raxpy(-alpha, &*q, &mut *q_new); // ... - alpha_k q_k
+
+        self.beta = self.ops.norm2(&*q_new); // beta_k
+        self.ops.rscale(T::Real::one() / self.beta, q_new); // q_new is now q_{k+1}
+        (alpha, self.beta)
+    }
+
+    /// The current Lanczos vector $q_k$, i.e. the one whose coefficients were returned by
+    /// the last [`step`](Self::step).
+    pub fn vector(&self) -> &[T] {
+        let cur = self.slots[1];
+        &self.workspace.as_slice()[cur..cur + self.size]
+    }
+
+    /// The last computed coefficient $\beta_k$, or $\beta_0 = |v|$ right after
+    /// [`start`](Self::start).
+    pub fn beta(&self) -> T::Real {
+        self.beta
+    }
+}
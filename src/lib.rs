@@ -1,30 +1,89 @@
 //#![feature(min_const_generics)]
 #![feature(core_intrinsics)]
+#![cfg_attr(feature = "simd", feature(portable_simd))]
 
+mod arnoldi;
+mod bicg;
 mod bicg_stab;
+mod bicg_stab_l;
+mod cg;
+mod chebyshev;
+mod config;
+mod cr;
 mod cs_minres;
+mod deflation;
+mod dense;
 pub mod error;
+mod estimate;
+mod fgmres;
 mod gauss_seidel;
+mod gcr;
+mod idrs;
+pub mod io;
+mod lanczos;
+mod lsmr;
 mod mat;
 mod minres;
 #[cfg(feature = "mkl")]
 mod mkl_mat;
+#[cfg(feature = "mkl")]
+mod mkl_threads;
+#[cfg(feature = "parallel")]
+mod multi;
+mod operator;
+#[cfg(feature = "mkl")]
+mod pardiso;
 pub mod precond;
+mod qmr;
+mod report;
+mod richardson;
+#[cfg(feature = "simd")]
+mod simd;
+mod solver;
+mod symmlq;
 pub mod vecalg;
+mod workspace;
 
+pub use arnoldi::{Arnoldi, ReorthKind};
+pub use bicg::BiCG;
 pub use bicg_stab::BiCGStab;
+pub use bicg_stab_l::BiCGStabL;
+pub use cg::CG;
+pub use chebyshev::{auto_chebyshev, Chebyshev};
+pub use config::{NormKind, ResidualKind, Side, SolveConfig, Stagnation};
+pub use cr::CR;
 pub use cs_minres::CSMinRes;
+pub use deflation::SpectralCache;
+pub use dense::{DenseMat, Layout};
+pub use estimate::{lanczos_eigen_bounds, operator_norm_estimate, power_iteration};
+pub use fgmres::FGMRES;
 pub use gauss_seidel::*;
+pub use gcr::{Truncation, GCR};
+pub use idrs::IDRs;
+pub use lanczos::Lanczos;
+pub use lsmr::LSMR;
 pub use mat::MatVecMul;
 pub use minres::MinRes;
 #[cfg(feature = "mkl")]
 pub use mkl_mat::*;
-
 #[cfg(feature = "mkl")]
+pub use mkl_threads::{get_max_threads, set_num_threads, with_num_threads};
+#[cfg(feature = "parallel")]
+pub use multi::{solve_multi_parallel, MultiSolveResult};
+pub use operator::{ComplexAsReal, DeflatedOp, MatFree, ScaledOp, Shifted, SumOp, Transpose};
+#[cfg(feature = "mkl")]
+pub use pardiso::{Pardiso, PardisoMatrixType};
+pub use qmr::QMR;
+pub use report::SolveReport;
+pub use richardson::Richardson;
+pub use solver::IterativeSolver;
+pub use symmlq::SymmLQ;
+pub use vecalg::{CpuVecOps, VecOps};
+pub use workspace::Workspace;
+
 use std::any::TypeId;
 
 /// Return `true` if `A` and `B` are the same type
-#[cfg(feature = "mkl")]
 #[inline(always)]
 fn same_type<A: 'static, B: 'static>() -> bool {
     TypeId::of::<A>() == TypeId::of::<B>()
@@ -33,7 +92,6 @@ fn same_type<A: 'static, B: 'static>() -> bool {
 // Read pointer to type `A` as type `B`.
 //
 // **Panics** if `A` and `B` are not the same type
-#[cfg(feature = "mkl")]
 #[inline(always)]
 fn cast_as<A: 'static + Copy, B: 'static + Copy>(a: &A) -> B {
     debug_assert!(same_type::<A, B>());
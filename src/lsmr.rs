@@ -0,0 +1,290 @@
+//! An impl of the LSMR algorithm for sparse least-squares problems.
+
+use super::{error::*, vecalg::*, MatVecMul};
+use cauchy::Scalar;
+use num_traits::{float::*, One, Zero};
+use std::{intrinsics::unlikely, slice::from_raw_parts_mut};
+
+/// Implementation of the LSMR algorithm for solving the (damped) least-squares problem
+/// $\min |Ax - b|^2 + \lambda^2 |x|^2$ with a rectangular, possibly rank-deficient, matrix $A$.
+///
+/// It runs the Golub-Kahan bidiagonalization of $A$ started from $b$, which needs products
+/// with $A$ and with $A^H$, and it is equivalent to MINRES on the normal equations
+/// $(A^HA + \lambda^2 I)x = A^Hb$ without forming them. Hence $|A^Hr|$ decreases
+/// monotonically, where $r = b - Ax$, so the iterations can be stopped early at a good
+/// solution, which LSQR doesn't guarantee. $|r|$ decreases monotonically as well.
+///
+/// The operator must provide [`MatVecMul::mul_vec_trans`], whose error is returned
+/// otherwise. For complex entries,
+/// $A^Hu = \overline{A^T\bar u}$ is computed with it.
+///
+/// The stopping rules follow LSQR, with the tolerances `atol` and `btol` of
+/// [`solve`](Self::solve):
+/// - $|r| \le \text{btol}\,|b| + \text{atol}\,|A|\,|x|$ for a compatible system $Ax = b$;
+/// - $|A^Hr| \le \text{atol}\,|A|\,|r|$ for a least-squares problem.
+///
+/// The norms $|r|$, $|A^Hr|$ and the Frobenius norm $|A|$ are estimated by the recurrences.
+/// With $\lambda \ne 0$, $r$ stands for the residual $[b - Ax; -\lambda x]$ of the augmented
+/// system and $A$ for $[A; \lambda I]$. The workspace holds `2 * rows + 5 * cols` entries.
+///
+/// See D. C.-L. Fong and M. A. Saunders, *LSMR: An iterative algorithm for sparse
+/// least-squares problems*, SIAM J. Sci. Comput., 33 (2011).
+#[allow(non_snake_case)]
+pub struct LSMR<'data, T: Scalar, M: MatVecMul<T>, V: VecOps<T> = CpuVecOps> {
+    A: &'data M,
+    workspace: Vec<T>,
+    rows: usize,
+    cols: usize,
+    damp: T::Real,
+    ops: V,
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>> LSMR<'data, T, M> {
+    /// Create a solver for the `rows x cols` operator `A`.
+    #[allow(non_snake_case)]
+    pub fn new(A: &'data M, rows: usize, cols: usize) -> Self {
+        Self::with_vec_ops(A, rows, cols, CpuVecOps)
+    }
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>, V: VecOps<T>> LSMR<'data, T, M, V> {
+    /// Create a solver performing its vector operations through `ops`.
+    #[allow(non_snake_case)]
+    pub fn with_vec_ops(A: &'data M, rows: usize, cols: usize, ops: V) -> Self {
+        LSMR {
+            A,
+            workspace: vec![T::zero(); 2 * rows + 5 * cols],
+            rows,
+            cols,
+            damp: T::Real::zero(),
+            ops,
+        }
+    }
+
+    /// Set the damping parameter $\lambda$, which regularizes the problem. The default is
+    /// zero.
+    pub fn set_damp(&mut self, damp: T::Real) {
+        self.damp = damp;
+    }
+
+    pub fn damp(&self) -> T::Real {
+        self.damp
+    }
+
+    pub fn shape(&self) -> (usize, usize) {
+        (self.rows, self.cols)
+    }
+
+    /// Solves $\min |Ax - b|^2 + \lambda^2|x|^2$ from the initial guess `x`.
+    ///
+    /// With a nonzero initial guess, the damping applies to the correction of `x` rather
+    /// than to `x` itself. It returns the number of iterations, i.e. of products with $A$,
+    /// and the estimate of $|A^Hr| / (|A|\,|r|)$, which is zero when $r$ vanishes. The
+    /// stopping rules are given in the [type documentation](Self); the tolerances are
+    /// limited by the machine precision, and `btol` matters only for compatible systems.
+    #[allow(clippy::many_single_char_names)]
+    pub fn solve(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        atol: T::Real,
+        btol: T::Real,
+    ) -> SolveResult<(usize, T::Real)> {
+        let (m, n) = (self.rows, self.cols);
+        // check the format
+        if rhs.len() != m {
+            return Err(SolverError::IncompatibleMatrixFormat(String::from(
+                "Input vec dimension doesn't match the number of rows",
+            )));
+        }
+        if x.len() != n {
+            return Err(SolverError::IncompatibleMatrixFormat(String::from(
+                "Output vec dimension doesn't match the number of columns",
+            )));
+        }
+
+        let normb = self.ops.norm2(rhs);
+        if unlikely(normb <= T::Real::epsilon()) {
+            // when rhs = 0, x is set to zero.
+            zero(x);
+            return Ok((0, T::Real::zero()));
+        }
+
+        // Here is the internal memeory layout:
+        // [ u | t_m | v | h | hbar | t_n | s_n ]
+        let ptr = self.workspace.as_mut_ptr();
+        let u = unsafe { from_raw_parts_mut(ptr, m) }; // &mut [T]
+        let tm = unsafe { from_raw_parts_mut(ptr.add(m), m) };
+        let v = unsafe { from_raw_parts_mut(ptr.add(2 * m), n) };
+        let h = unsafe { from_raw_parts_mut(ptr.add(2 * m + n), n) };
+        let hbar = unsafe { from_raw_parts_mut(ptr.add(2 * m + 2 * n), n) };
+        let tn = unsafe { from_raw_parts_mut(ptr.add(2 * m + 3 * n), n) };
+        let sn = unsafe { from_raw_parts_mut(ptr.add(2 * m + 4 * n), n) };
+
+        // s_n = A^H u = conj(A^T t_m), with t_m = conj(u)
+        let adjoint = |tm: &[T], tn: &mut [T], sn: &mut [T]| -> SolveResult<()> {
+            self.A.mul_vec_trans(tm, &mut *tn)?;
+            self.ops.conj(&*tn, &mut *sn);
+            Ok(())
+        };
+
+        // the first bidiagonalization step: beta u = b - A x, alpha v = A^H u
+        unsafe {
+            self.A.mul_vec_unchecked(x, &mut *tm);
+        }
+        self.ops.waxpy(-T::one(), &*tm, rhs, &mut *u); // u = rhs - A*x
+        let mut beta = self.ops.norm2(&*u);
+        let mut alpha = T::Real::zero();
+        if beta > T::Real::zero() {
+            self.ops.rscale(T::Real::one() / beta, &mut *u);
+            self.ops.conj(&*u, &mut *tm);
+            adjoint(&*tm, &mut *tn, &mut *v)?;
+            alpha = self.ops.norm2(&*v);
+        } else {
+            zero(&mut *v);
+        }
+        if alpha > T::Real::zero() {
+            self.ops.rscale(T::Real::one() / alpha, &mut *v);
+        }
+        if unlikely((alpha * beta).is_zero()) {
+            // x already solves the problem
+            return Ok((0, T::Real::zero()));
+        }
+
+        let damp = self.damp;
+        let mut zetabar = alpha * beta;
+        let mut alphabar = alpha;
+        let (mut rho, mut rhobar) = (T::Real::one(), T::Real::one());
+        let (mut cbar, mut sbar) = (T::Real::one(), T::Real::zero());
+        h.copy_from_slice(&*v);
+        zero(&mut *hbar);
+
+        // the recurrences of the estimate of |r|
+        let mut betadd = beta;
+        let mut betad = T::Real::zero();
+        let mut rhodold = T::Real::one();
+        let mut tautildeold = T::Real::zero();
+        let mut thetatilde = T::Real::zero();
+        let mut zeta = T::Real::zero();
+        let mut d = T::Real::zero();
+
+        // the estimate of |A|_F
+        let mut norm_a2 = alpha * alpha;
+
+        for its in 1..=max_iter {
+            // the next bidiagonalization step: beta u = A v - alpha u, alpha v = A^H u - beta v
+            unsafe {
+                self.A.mul_vec_unchecked(&*v, &mut *tm);
+            }
+            self.ops
+                .axpby(T::one(), &*tm, T::from_real(-alpha), &mut *u);
+            beta = self.ops.norm2(&*u);
+            if beta > T::Real::zero() {
+                self.ops.rscale(T::Real::one() / beta, &mut *u);
+                self.ops.conj(&*u, &mut *tm);
+                adjoint(&*tm, &mut *tn, &mut *sn)?;
+                self.ops.axpby(T::one(), &*sn, T::from_real(-beta), &mut *v);
+                alpha = self.ops.norm2(&*v);
+                if alpha > T::Real::zero() {
+                    self.ops.rscale(T::Real::one() / alpha, &mut *v);
+                }
+            }
+
+            // the rotation eliminating the damping
+            let (chat, shat, alphahat) = sym_ortho(alphabar, damp);
+
+            // the rotation P_k turning B_k into upper bidiagonal form
+            let rhoold = rho;
+            let (c, s, rho_new) = sym_ortho(alphahat, beta);
+            rho = rho_new;
+            let thetanew = s * alpha;
+            alphabar = c * alpha;
+
+            // the rotation Pbar_k turning R_k^T into upper bidiagonal form
+            let rhobarold = rhobar;
+            let zetaold = zeta;
+            let thetabar = sbar * rho;
+            let (cb, sb, rhobar_new) = sym_ortho(cbar * rho, thetanew);
+            cbar = cb;
+            sbar = sb;
+            rhobar = rhobar_new;
+            zeta = cbar * zetabar;
+            zetabar = -sbar * zetabar;
+
+            // hbar = h - (thetabar rho / (rhoold rhobarold)) hbar, x += (zeta / (rho rhobar)) hbar,
+            // h = v - (thetanew / rho) h
+            self.ops.axpby(
+                T::one(),
+                &*h,
+                T::from_real(-thetabar * rho / (rhoold * rhobarold)),
+                &mut *hbar,
+            );
+            self.ops.raxpy(zeta / (rho * rhobar), &*hbar, &mut *x);
+            self.ops
+                .axpby(T::one(), &*v, T::from_real(-thetanew / rho), &mut *h);
+
+            // the estimate of |r|
+            let betaacute = chat * betadd;
+            let betacheck = -shat * betadd;
+            let betahat = c * betaacute;
+            betadd = -s * betaacute;
+            let thetatildeold = thetatilde;
+            let (ctildeold, stildeold, rhotildeold) = sym_ortho(rhodold, thetabar);
+            thetatilde = stildeold * rhobar;
+            rhodold = ctildeold * rhobar;
+            betad = -stildeold * betad + ctildeold * betahat;
+            tautildeold = (zetaold - thetatildeold * tautildeold) / rhotildeold;
+            let taud = (zeta - thetatilde * tautildeold) / rhodold;
+            d += betacheck * betacheck;
+            let norm_r = Float::sqrt(d + (betad - taud) * (betad - taud) + betadd * betadd);
+
+            // the estimate of |A|_F, and |A^H r| = |zetabar|
+            norm_a2 += beta * beta;
+            let norm_a = Float::sqrt(norm_a2);
+            norm_a2 += alpha * alpha;
+            let norm_ar = Float::abs(zetabar);
+            if unlikely(!norm_r.is_finite() || !norm_ar.is_finite()) {
+                return Err(SolverError::Diverged(its));
+            }
+
+            // the stopping rules
+            let norm_x = self.ops.norm2(&*x);
+            let test1 = norm_r / normb;
+            let test2 = if norm_a * norm_r > T::Real::zero() {
+                norm_ar / (norm_a * norm_r)
+            } else {
+                T::Real::zero()
+            };
+            let t1 = test1 / (T::Real::one() + norm_a * norm_x / normb);
+            let rtol = btol + atol * norm_a * norm_x / normb;
+            let converged = test1 <= rtol || test2 <= atol;
+            // the tolerances are below the machine precision
+            let one = T::Real::one();
+            let exhausted = one + t1 <= one || one + test2 <= one;
+            if converged || exhausted {
+                return Ok((its, test2));
+            }
+        }
+
+        Err(SolverError::InsufficientIterNum(max_iter))
+    }
+}
+
+/// The stable Givens rotation $(c, s, r)$ with $c a + s b = r$ and $-s a + c b = 0$ of real
+/// numbers, from S.-C. Choi's thesis.
+fn sym_ortho<R: Float>(a: R, b: R) -> (R, R, R) {
+    if b.is_zero() {
+        (a.signum(), R::zero(), a.abs())
+    } else if a.is_zero() {
+        (R::zero(), b.signum(), b.abs())
+    } else if b.abs() > a.abs() {
+        let tau = a / b;
+        let s = b.signum() / (R::one() + tau * tau).sqrt();
+        (s * tau, s, b / s)
+    } else {
+        let tau = b / a;
+        let c = a.signum() / (R::one() + tau * tau).sqrt();
+        (c, c * tau, a / c)
+    }
+}
@@ -1,3 +1,4 @@
+use super::error::*;
 use cauchy::Scalar;
 #[cfg(feature = "parallel")]
 use rayon::prelude::*;
@@ -6,9 +7,16 @@ use std::{intrinsics::likely, slice::from_raw_parts};
 
 /// An interface for the sparse matrix and dense vector multiplication.
 ///
+/// It is implemented for the `sprs` matrices [`CsMatI`] and their views [`CsMatViewI`], in
+/// either CSR or CSC storage and for real or complex entries, so a `sprs` matrix can be
+/// passed to any solver in the default build without MKL. Their `mul_vec_dot` is `mul_vec`
+/// followed by [`conj_dot`](super::vecalg::conj_dot). With the _mkl_ feature, `MklMat`
+/// provides the same products through the MKL sparse BLAS.
+///
 /// # Performance Tuning
 ///
-/// The _parallel_ feature turns on multi-thread computing in the [`mul_vec_unchecked`] using Rayon
+/// The _parallel_ feature turns on multi-thread computing in the [`mul_vec_unchecked`] and
+/// [`mul_vec_dot_unchecked`] of CSR matrices using Rayon. The serial code is used otherwise.
 pub trait MatVecMul<T: Scalar> {
     /// Multiply this matrix with the provided vector `v_in` and put the results
     /// in `v_out`.
@@ -34,6 +42,84 @@ pub trait MatVecMul<T: Scalar> {
     /// This method will not check the dimension agreement. If the dimensions don't match,
     /// they will result in *[undefined behavior](https://doc.rust-lang.org/reference/behavior-considered-undefined.html)*.
     unsafe fn mul_vec_dot_unchecked(&self, v_in: &[T], v_out: &mut [T]) -> T;
+
+    /// The same as [`mul_vec_dot_unchecked`], but returns only the real part of
+    /// $v_{in}^H A v_{in}$, which is exact for Hermitian $A$.
+    ///
+    /// The Hermitian solvers use it so that floating-point noise in the imaginary part does
+    /// not enter their recurrences. The default implementation takes the real part of
+    /// [`mul_vec_dot_unchecked`].
+    ///
+    /// # Safety
+    ///
+    /// This method will not check the dimension agreement. If the dimensions don't match,
+    /// they will result in *[undefined behavior](https://doc.rust-lang.org/reference/behavior-considered-undefined.html)*.
+    ///
+    /// [`mul_vec_dot_unchecked`]: Self::mul_vec_dot_unchecked
+    unsafe fn mul_vec_rdot_unchecked(&self, v_in: &[T], v_out: &mut [T]) -> T::Real {
+        self.mul_vec_dot_unchecked(v_in, v_out).re()
+    }
+
+    /// Compute $y = \alpha A x + \beta y$, in the manner of BLAS `gemv`.
+    ///
+    /// This method will check the dimension agreement and panick if the dimensions don't match.
+    /// The default implementation multiplies into a temporary vector, then calls [`axpby`].
+    ///
+    /// [`axpby`]: super::vecalg::axpby
+    fn mul_vec_add(&self, alpha: T, x: &[T], beta: T, y: &mut [T]) {
+        let mut ax = vec![T::zero(); y.len()];
+        self.mul_vec(x, ax.as_mut_slice());
+        super::vecalg::axpby(alpha, ax.as_slice(), beta, y);
+    }
+
+    /// Multiply the transpose $A^T$ of this matrix, without conjugation, with the provided
+    /// vector `v_in` and put the results in `v_out`.
+    ///
+    /// Operators are not required to provide it; the default implementation returns
+    /// [`SolverError::InvalidParameter`], which the solvers needing $A^T$ pass on to the
+    /// caller.
+    fn mul_vec_trans(&self, _v_in: &[T], _v_out: &mut [T]) -> SolveResult<()> {
+        Err(SolverError::InvalidParameter(String::from(
+            "The transposed product is not provided by this operator",
+        )))
+    }
+
+    /// Write the diagonal entries $a_{ii}$ of this matrix into `out`, whose length is the
+    /// matrix size. Complex entries are returned as they are, without conjugation.
+    ///
+    /// The default implementation computes $e_i^T A e_i$ with one [`mul_vec`] per entry,
+    /// which is slow but works for any operator. Implementors with access to the stored
+    /// entries should override it.
+    fn diagonal(&self, out: &mut [T]) {
+        let n = out.len();
+        let mut e = vec![T::zero(); n];
+        let mut col = vec![T::zero(); n];
+        for i in 0..n {
+            e[i] = T::one();
+            self.mul_vec(e.as_slice(), col.as_mut_slice());
+            out[i] = col[i];
+            e[i] = T::zero();
+        }
+    }
+
+    /// The numbers of rows and columns of this operator, or `None` if it doesn't know them.
+    ///
+    /// Operators are not required to provide it; the default implementation returns `None`,
+    /// and so does [`Identity`](crate::precond::Identity), which applies to vectors of any
+    /// length. The solvers constructed with an explicit size don't need it.
+    fn shape(&self) -> Option<(usize, usize)> {
+        None
+    }
+
+    /// The size of this operator, e.g. to allocate the workspace of a solver from the
+    /// operator alone, or `None` if it is not square or doesn't provide its
+    /// [`shape`](Self::shape).
+    fn size(&self) -> Option<usize> {
+        match self.shape() {
+            Some((rows, cols)) if rows == cols => Some(rows),
+            _ => None,
+        }
+    }
 }
 
 /// The trait to convert a value into usize.
@@ -45,9 +131,14 @@ pub trait AsUsize {
 
 // 'a refers to the lt of data in CSMatView
 impl<'a, T: Scalar + Send + Sync, I: SpIndex + AsUsize> MatVecMul<T> for CsMatViewI<'a, T, I> {
+    #[inline]
+    fn shape(&self) -> Option<(usize, usize)> {
+        Some((self.rows(), self.cols()))
+    }
+
     #[inline]
     fn mul_vec(&self, v_in: &[T], v_out: &mut [T]) {
-        if self.cols() != v_in.len() || v_in.len() != v_out.len() {
+        if self.cols() != v_in.len() || self.rows() != v_out.len() {
             panic!("Dimension mismatch");
         }
         unsafe {
@@ -67,7 +158,7 @@ impl<'a, T: Scalar + Send + Sync, I: SpIndex + AsUsize> MatVecMul<T> for CsMatVi
     // Here 'vec refers to the lt of data in DenseVec
     unsafe fn mul_vec_unchecked(&self, v_in: &[T], v_out: &mut [T]) {
         // compiler will turn this into memset if needed
-        debug_assert!(self.cols() == v_in.len() && v_in.len() == v_out.len());
+        debug_assert!(self.cols() == v_in.len() && self.rows() == v_out.len());
         v_out.iter_mut().for_each(|v| *v = T::zero());
 
         // We don't use `match` here because the `likely` instrinsics leads to better
@@ -145,11 +236,83 @@ impl<'a, T: Scalar + Send + Sync, I: SpIndex + AsUsize> MatVecMul<T> for CsMatVi
     unsafe fn mul_vec_dot_unchecked(&self, v_in: &[T], v_out: &mut [T]) -> T {
         use super::vecalg::conj_dot;
 
+        // When `parallel` is enabled, fuse the product and the dot product of CSR matrices:
+        // every chunk of rows computes its output entries and its partial dot product, and
+        // the partials are summed in the chunk order, so that the result doesn't depend on
+        // the thread scheduling.
+        #[cfg(feature = "parallel")]
+        {
+            if likely(self.storage() == CompressedStorage::CSR) {
+                debug_assert!(self.cols() == v_in.len() && v_in.len() == v_out.len());
+                const DOT_CHUNK_SIZE: usize = 1024;
+                let indptr = self.indptr();
+                let index_ptr = SendPtr(self.indices().as_ptr());
+                let data_ptr = SendPtr(self.data().as_ptr());
+                let partials: Vec<T> = v_out
+                    .par_chunks_mut(DOT_CHUNK_SIZE)
+                    .enumerate()
+                    .map(|(k, out_chunk)| {
+                        let row0 = k * DOT_CHUNK_SIZE;
+                        let mut partial = T::zero();
+                        for (i, row_ret) in out_chunk.iter_mut().enumerate() {
+                            let st = indptr.get_unchecked(row0 + i).as_usize();
+                            let nn = indptr.get_unchecked(row0 + i + 1).as_usize() - st;
+                            let local_idx = from_raw_parts(index_ptr.0.add(st), nn);
+                            let local_dat = from_raw_parts(data_ptr.0.add(st), nn);
+                            *row_ret = local_idx.iter().zip(local_dat.iter()).fold(
+                                T::zero(),
+                                |acc, (&lid, &ldat)| {
+                                    acc + *v_in.get_unchecked(lid.as_usize()) * ldat
+                                },
+                            );
+                            partial += v_in.get_unchecked(row0 + i).conj() * *row_ret;
+                        }
+                        partial
+                    })
+                    .collect();
+                return partials.iter().fold(T::zero(), |acc, &p| acc + p);
+            }
+        }
+
         // v_out = M * v_in
         self.mul_vec_unchecked(v_in, v_out);
         // conj(v_in) . v_out
         conj_dot(v_in, v_out)
     }
+
+    fn mul_vec_trans(&self, v_in: &[T], v_out: &mut [T]) -> SolveResult<()> {
+        if self.rows() != v_in.len() || self.cols() != v_out.len() {
+            panic!("Dimension mismatch");
+        }
+        // A^T in CSR is A in CSC and vice versa
+        if self.storage() == CompressedStorage::CSR {
+            v_out.iter_mut().for_each(|v| *v = T::zero());
+            for (row_ind, vec) in self.outer_iterator().enumerate() {
+                let multiplier = v_in[row_ind];
+                for (col_ind, &value) in vec.iter() {
+                    v_out[col_ind] += multiplier * value;
+                }
+            }
+        } else {
+            for (col_ind, vec) in self.outer_iterator().enumerate() {
+                v_out[col_ind] = vec.iter().fold(T::zero(), |acc, (row_ind, &value)| {
+                    acc + v_in[row_ind] * value
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn diagonal(&self, out: &mut [T]) {
+        if self.rows() != self.cols() || self.rows() != out.len() {
+            panic!("Dimension mismatch");
+        }
+        // the diagonal entry of an outer vector is at the inner index equal to the outer
+        // index, for both CSR and CSC
+        for (i, (vec, d)) in self.outer_iterator().zip(out.iter_mut()).enumerate() {
+            *d = vec.get(i).copied().unwrap_or_else(T::zero);
+        }
+    }
 }
 
 /// Wrap type to send the pointer across the thread
@@ -161,6 +324,11 @@ unsafe impl<T: Send> Send for SendPtr<T> {}
 unsafe impl<T: Send> Sync for SendPtr<T> {}
 
 impl<T: Scalar + Send + Sync, I: SpIndex + AsUsize> MatVecMul<T> for CsMatI<T, I> {
+    #[inline]
+    fn shape(&self) -> Option<(usize, usize)> {
+        Some((self.rows(), self.cols()))
+    }
+
     #[inline]
     fn mul_vec(&self, v_in: &[T], v_out: &mut [T]) {
         self.view().mul_vec(v_in, v_out);
@@ -180,6 +348,16 @@ impl<T: Scalar + Send + Sync, I: SpIndex + AsUsize> MatVecMul<T> for CsMatI<T, I
     unsafe fn mul_vec_dot_unchecked(&self, v_in: &[T], v_out: &mut [T]) -> T {
         self.view().mul_vec_dot_unchecked(v_in, v_out)
     }
+
+    #[inline]
+    fn mul_vec_trans(&self, v_in: &[T], v_out: &mut [T]) -> SolveResult<()> {
+        self.view().mul_vec_trans(v_in, v_out)
+    }
+
+    #[inline]
+    fn diagonal(&self, out: &mut [T]) {
+        self.view().diagonal(out)
+    }
 }
 
 macro_rules! to_usize {
@@ -254,6 +432,60 @@ mod tests {
             .all(|(x, y)| (*x - *y).abs() < epsilon));
     }
 
+    #[test]
+    fn diagonal() {
+        use num_complex::Complex64;
+        let indptr: Vec<usize> = vec![0, 2, 3, 5];
+        let indices: Vec<usize> = vec![0, 2, 0, 1, 2];
+        let data = vec![
+            Complex64::new(1., 2.),
+            Complex64::new(3., 0.),
+            Complex64::new(4., 1.),
+            Complex64::new(5., 0.),
+            Complex64::new(6., -1.),
+        ];
+        let expected = vec![
+            Complex64::new(1., 2.),
+            Complex64::new(0., 0.),
+            Complex64::new(6., -1.),
+        ];
+
+        // CSR and CSC give the diagonal of the respective matrices, which are transposes
+        let csr =
+            CsMatView::new_view(CompressedStorage::CSR, (3, 3), &indptr, &indices, &data).unwrap();
+        let mut diag = vec![Complex64::new(9., 9.); 3];
+        csr.diagonal(&mut diag);
+        assert_eq!(diag, expected);
+        let csc =
+            CsMatView::new_view(CompressedStorage::CSC, (3, 3), &indptr, &indices, &data).unwrap();
+        csc.diagonal(&mut diag);
+        assert_eq!(diag, expected);
+
+        // the default implementation agrees with the stored entries
+        struct Wrap<'a>(CsMatView<'a, Complex64>);
+        impl<'a> MatVecMul<Complex64> for Wrap<'a> {
+            fn mul_vec(&self, v_in: &[Complex64], v_out: &mut [Complex64]) {
+                self.0.mul_vec(v_in, v_out)
+            }
+            fn mul_vec_dot(&self, v_in: &[Complex64], v_out: &mut [Complex64]) -> Complex64 {
+                self.0.mul_vec_dot(v_in, v_out)
+            }
+            unsafe fn mul_vec_unchecked(&self, v_in: &[Complex64], v_out: &mut [Complex64]) {
+                self.0.mul_vec_unchecked(v_in, v_out)
+            }
+            unsafe fn mul_vec_dot_unchecked(
+                &self,
+                v_in: &[Complex64],
+                v_out: &mut [Complex64],
+            ) -> Complex64 {
+                self.0.mul_vec_dot_unchecked(v_in, v_out)
+            }
+        }
+        let mut diag = vec![Complex64::new(9., 9.); 3];
+        Wrap(csr).diagonal(&mut diag);
+        assert_eq!(diag, expected);
+    }
+
     #[test]
     fn dense_csr_mat_2() {
         let indptr: Vec<i32> = vec![0, 3, 3, 5, 6, 7];
@@ -278,4 +510,194 @@ mod tests {
             .zip(expected_output.iter())
             .all(|(x, y)| (*x - *y).abs() < epsilon));
     }
+
+    #[test]
+    fn mul_vec_add() {
+        use num_complex::Complex64;
+        let indptr: &[usize] = &[0, 3, 5, 8, 11, 13];
+        let indices: &[usize] = &[0, 1, 3, 0, 1, 2, 3, 4, 0, 2, 3, 1, 4];
+        let data: &[f64] = &[
+            1.0, -1.0, -3.0, -2.0, 5.0, 4.0, 6.0, 4.0, -4.0, 2.0, 7.0, 8.0, -5.0,
+        ];
+        let mat =
+            CsMatView::new_view(CompressedStorage::CSR, (5, 5), indptr, indices, data).unwrap();
+        let x = vec![1.0, 5.0, 1.0, 4.0, 1.0]; // A*x = [-16, 23, 32, 26, 35]
+        let mut y = vec![1.0, 2.0, 3.0, 4.0, 5.0];
+        mat.mul_vec_add(2., &x, -1., &mut y);
+        assert_eq!(y, vec![-33.0, 44.0, 61.0, 48.0, 65.0]);
+
+        let cdata: Vec<Complex64> = data.iter().map(|&v| Complex64::new(v, -v)).collect();
+        let cmat =
+            CsMatView::new_view(CompressedStorage::CSR, (5, 5), indptr, indices, &cdata).unwrap();
+        let cx: Vec<Complex64> = x.iter().map(|&v| Complex64::new(v, 0.5)).collect();
+        let mut ax = vec![Complex64::new(0., 0.); 5];
+        cmat.mul_vec(&cx, &mut ax);
+        let (alpha, beta) = (Complex64::new(0.5, 1.), Complex64::new(2., -1.));
+        let y0: Vec<Complex64> = (0..5).map(|i| Complex64::new(i as f64, 1.)).collect();
+        let mut cy = y0.clone();
+        cmat.mul_vec_add(alpha, &cx, beta, &mut cy);
+        for i in 0..5 {
+            let exp = alpha * ax[i] + beta * y0[i];
+            approx::assert_abs_diff_eq!(cy[i].re, exp.re, epsilon = 1e-12);
+            approx::assert_abs_diff_eq!(cy[i].im, exp.im, epsilon = 1e-12);
+        }
+    }
+
+    #[test]
+    fn mul_vec_trans() {
+        let indptr: &[usize] = &[0, 3, 5, 8, 11, 13];
+        let indices: &[usize] = &[0, 1, 3, 0, 1, 2, 3, 4, 0, 2, 3, 1, 4];
+        let data: &[f64] = &[
+            1.0, -1.0, -3.0, -2.0, 5.0, 4.0, 6.0, 4.0, -4.0, 2.0, 7.0, 8.0, -5.0,
+        ];
+        // the same arrays in CSC describe the transpose
+        let csr =
+            CsMatView::new_view(CompressedStorage::CSR, (5, 5), indptr, indices, data).unwrap();
+        let csc =
+            CsMatView::new_view(CompressedStorage::CSC, (5, 5), indptr, indices, data).unwrap();
+        let x = vec![1.0, 5.0, 1.0, 4.0, 1.0];
+        let mut y = vec![0.; 5];
+        let mut expected = vec![0.; 5];
+        csr.mul_vec_trans(&x, &mut y).unwrap();
+        csc.mul_vec(&x, &mut expected);
+        assert_eq!(y, expected);
+        csc.mul_vec_trans(&x, &mut y).unwrap();
+        csr.mul_vec(&x, &mut expected);
+        assert_eq!(y, expected);
+    }
+
+    #[test]
+    fn mul_vec_rdot_hermitian() {
+        use num_complex::Complex64;
+        // a dense Hermitian matrix
+        let n = 40;
+        let mut tri = sprs::TriMat::<Complex64>::new((n, n));
+        for i in 0..n {
+            tri.add_triplet(i, i, Complex64::new(1. + i as f64, 0.));
+            for j in 0..i {
+                let a = Complex64::new((i * j) as f64 * 0.37 % 1., (i + 3 * j) as f64 * 0.11 % 1.);
+                tri.add_triplet(i, j, a);
+                tri.add_triplet(j, i, a.conj());
+            }
+        }
+        let mat: CsMatI<Complex64, usize> = tri.to_csr();
+        let v: Vec<Complex64> = (0..n)
+            .map(|i| Complex64::new((i as f64 * 0.7).sin(), (i as f64 * 0.3).cos()))
+            .collect();
+        let mut out = vec![Complex64::new(0., 0.); n];
+        let d = mat.mul_vec_dot(&v, &mut out);
+        let rd = unsafe { mat.mul_vec_rdot_unchecked(&v, &mut out) };
+        assert_eq!(rd, d.re);
+        // the imaginary part of v^H A v is only rounding noise
+        assert!(d.im.abs() <= 1E-12 * d.re.abs());
+
+        // A = [2, 1+i; 1-i, 3] and v = (1, i): Av = (1+i, 1+2i), so v^H A v = 3
+        let mut tri = sprs::TriMat::<Complex64>::new((2, 2));
+        tri.add_triplet(0, 0, Complex64::new(2., 0.));
+        tri.add_triplet(0, 1, Complex64::new(1., 1.));
+        tri.add_triplet(1, 0, Complex64::new(1., -1.));
+        tri.add_triplet(1, 1, Complex64::new(3., 0.));
+        let mat: CsMatI<Complex64, usize> = tri.to_csr();
+        let v = [Complex64::new(1., 0.), Complex64::new(0., 1.)];
+        let mut out = [Complex64::new(0., 0.); 2];
+        let rd = unsafe { mat.mul_vec_rdot_unchecked(&v, &mut out) };
+        assert_eq!(out, [Complex64::new(1., 1.), Complex64::new(1., 2.)]);
+        assert_eq!(rd, 3.);
+    }
+
+    #[test]
+    fn complex_csr_mat() {
+        use crate::vecalg::conj_dot;
+        use num_complex::Complex64;
+
+        let indptr: Vec<usize> = vec![0, 3, 3, 5, 6, 7];
+        let indices: Vec<usize> = vec![1, 2, 3, 2, 3, 4, 0];
+        let data: Vec<Complex64> = (0..7)
+            .map(|k| Complex64::new(k as f64 * 0.3 - 1., 1. - k as f64 * 0.2))
+            .collect();
+        let v_in: Vec<Complex64> = (0..5)
+            .map(|i| Complex64::new((i as f64 * 0.7).sin(), (i as f64 * 0.3).cos()))
+            .collect();
+
+        // the dense reference, row by row
+        let mut expected = vec![Complex64::new(0., 0.); 5];
+        for i in 0..5 {
+            for k in indptr[i]..indptr[i + 1] {
+                expected[i] += data[k] * v_in[indices[k]];
+            }
+        }
+        let expected_dot = conj_dot(v_in.as_slice(), expected.as_slice());
+
+        let view =
+            CsMatView::new_view(CompressedStorage::CSR, (5, 5), &indptr, &indices, &data).unwrap();
+        let owned = CsMatI::new((5, 5), indptr.clone(), indices.clone(), data.clone());
+        let ops: [&dyn MatVecMul<Complex64>; 2] = [&view, &owned];
+        for mat in ops.iter() {
+            let mut v_out = vec![Complex64::new(9., 9.); 5];
+            mat.mul_vec(&v_in, &mut v_out);
+            let mut v_dot = vec![Complex64::new(9., 9.); 5];
+            let dot = mat.mul_vec_dot(&v_in, &mut v_dot);
+            let mut v_unchecked = vec![Complex64::new(9., 9.); 5];
+            let dot_unchecked = unsafe { mat.mul_vec_dot_unchecked(&v_in, &mut v_unchecked) };
+            for i in 0..5 {
+                approx::assert_abs_diff_eq!(v_out[i].re, expected[i].re, epsilon = 1e-12);
+                approx::assert_abs_diff_eq!(v_out[i].im, expected[i].im, epsilon = 1e-12);
+                assert_eq!(v_dot[i], v_out[i]);
+                assert_eq!(v_unchecked[i], v_out[i]);
+            }
+            approx::assert_abs_diff_eq!(dot.re, expected_dot.re, epsilon = 1e-12);
+            approx::assert_abs_diff_eq!(dot.im, expected_dot.im, epsilon = 1e-12);
+            assert_eq!(dot_unchecked, dot);
+        }
+    }
+
+    /// A random `n x n` CSR matrix with about `nnz_per_row` entries per row, generated by a
+    /// linear congruential generator
+    #[cfg(feature = "parallel")]
+    fn random_csr(n: usize, nnz_per_row: usize, seed: u64) -> sprs::CsMat<f64> {
+        let mut state = seed;
+        let mut next = move || {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            state >> 33
+        };
+        let mut tri = sprs::TriMat::new((n, n));
+        for i in 0..n {
+            for _ in 0..nnz_per_row {
+                let j = next() as usize % n;
+                tri.add_triplet(i, j, next() as f64 / (1u64 << 31) as f64 - 0.5);
+            }
+        }
+        tri.to_csr()
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_mul_vec_matches_serial() {
+        use crate::vecalg::conj_dot;
+
+        let n = 20_000;
+        let mat = random_csr(n, 12, 42);
+        let v_in: Vec<f64> = (0..n).map(|i| ((i * 7) % 13) as f64 - 6.).collect();
+
+        // serial reference, summing every row in the same order
+        let expected: Vec<f64> = mat
+            .outer_iterator()
+            .map(|row| row.iter().fold(0., |acc, (j, &a)| acc + v_in[j] * a))
+            .collect();
+
+        let mut v_out = vec![0.; n];
+        mat.mul_vec(&v_in, &mut v_out);
+        assert_eq!(v_out, expected);
+
+        let mut v_out = vec![0.; n];
+        let d = mat.mul_vec_dot(&v_in, &mut v_out);
+        assert_eq!(v_out, expected);
+        approx::assert_relative_eq!(d, conj_dot(&v_in[..], &expected[..]), max_relative = 1E-12);
+        // the partials are reduced in a fixed order
+        for _ in 0..4 {
+            assert_eq!(mat.mul_vec_dot(&v_in, &mut v_out).to_bits(), d.to_bits());
+        }
+    }
 }
@@ -1,39 +1,413 @@
 //! An impl of MINRES algorithm for linear sparse solve.
 
-use super::{error::*, vecalg::*, MatVecMul};
+use super::{
+    config::*,
+    error::*,
+    lanczos::Lanczos,
+    precond::Precond,
+    report::*,
+    solver::{check_size, square_size, IterativeSolver},
+    vecalg::*,
+    workspace::Workspace,
+    MatVecMul, SpectralCache,
+};
 use cauchy::Scalar;
 use num_traits::{float::*, One, Zero};
-use std::{intrinsics::unlikely, ptr::copy_nonoverlapping, slice::from_raw_parts_mut};
+use std::{
+    intrinsics::{likely, unlikely},
+    ops::ControlFlow,
+    ptr::copy_nonoverlapping,
+    slice::from_raw_parts_mut,
+};
 
 /// **NOTE:** This MINRES solver works only for real-valued symmetric systems or
 /// complex-valued Hermitian system. The system can be indefinite.
 ///
 /// **Note:** This class won't check if the input matrix is hermitian.
 #[allow(non_snake_case)]
-pub struct MinRes<'data, T: Scalar, M: MatVecMul<T>> {
+pub struct MinRes<'data, T: Scalar, M: MatVecMul<T>, V: VecOps<T> = CpuVecOps> {
     A: &'data M,
     workspace: Vec<T>,
     size: usize,
+    ops: V,
+    stagnation: Option<Stagnation<T::Real>>,
 }
 
 impl<'data, T: Scalar, M: MatVecMul<T>> MinRes<'data, T, M> {
+    /// Create a solver for the operator `A` of size `size`.
+    ///
+    /// **Panics** if `A` provides its [`shape`](MatVecMul::shape) and it differs from
+    /// `size x size`; operators without a shape are accepted as they are. Prefer
+    /// [`from_op`](Self::from_op), which takes the size from `A` and checks that it is
+    /// square.
     #[allow(non_snake_case)]
     pub fn new(A: &'data M, size: usize) -> Self {
+        Self::with_vec_ops(A, size, CpuVecOps)
+    }
+
+    /// Create a solver for the operator `A`, whose size is given by its
+    /// [`shape`](MatVecMul::shape).
+    ///
+    /// Return [`SolverError::IncompatibleMatrixFormat`] if `A` is not square or doesn't provide
+    /// its shape.
+    #[allow(non_snake_case)]
+    pub fn from_op(A: &'data M) -> SolveResult<Self> {
+        Ok(Self::with_vec_ops(A, square_size(A)?, CpuVecOps))
+    }
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>, V: VecOps<T>> MinRes<'data, T, M, V> {
+    /// Create a solver performing its vector operations through `ops`.
+    ///
+    /// **Panics** if `A` provides its [`shape`](MatVecMul::shape) and it differs from
+    /// `size x size`.
+    #[allow(non_snake_case)]
+    pub fn with_vec_ops(A: &'data M, size: usize, ops: V) -> Self {
+        check_size(A, size);
         MinRes {
             A,
-            workspace: vec![T::zero(); size * 8],
+            workspace: vec![T::zero(); Self::workspace_len(size)],
             size,
+            ops,
+            stagnation: None,
         }
     }
 
+    /// The length of the [`Workspace`] needed to solve a system of `size` unknowns.
+    pub fn workspace_len(size: usize) -> usize {
+        size * 8
+    }
+
+    /// Stop with [`SolverError::Stagnation`] when the residual stagnates according to
+    /// `stagnation`. `None`, the default, iterates until convergence or `max_iter`.
+    pub fn set_stagnation(&mut self, stagnation: Option<Stagnation<T::Real>>) {
+        self.stagnation = stagnation;
+    }
+
+    /// The stagnation criterion. See [`set_stagnation`](Self::set_stagnation).
+    pub fn stagnation(&self) -> Option<Stagnation<T::Real>> {
+        self.stagnation
+    }
+
     /// Solves Ax = b, without preconditioner
-    #[allow(clippy::many_single_char_names)]
     pub fn solve(
         &mut self,
         rhs: &[T],
         x: &mut [T],
         max_iter: usize,
         tol: T::Real,
+    ) -> SolveResult<(usize, T::Real)> {
+        self.solve_impl(
+            rhs,
+            x,
+            false,
+            max_iter,
+            tol,
+            T::Real::zero(),
+            NormKind::L2,
+            |_, _| ControlFlow::Continue(()),
+            |_, _, _| {},
+        )
+    }
+
+    /// Solves Ax = b, without preconditioner, from the zero initial guess. The input
+    /// content of `x` is ignored, and `x` is overwritten by the solution.
+    ///
+    /// It gives the same result as [`solve`](Self::solve) with a zeroed `x`, but saves the
+    /// matrix-vector product computing the initial residual.
+    pub fn solve_zero_guess(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<(usize, T::Real)> {
+        self.solve_impl(
+            rhs,
+            x,
+            true,
+            max_iter,
+            tol,
+            T::Real::zero(),
+            NormKind::L2,
+            |_, _| ControlFlow::Continue(()),
+            |_, _, _| {},
+        )
+    }
+
+    /// Solves Ax = b, without preconditioner, using the scratch memory in `workspace`
+    /// instead of the one owned by this solver.
+    ///
+    /// It borrows the solver immutably, so a solver, and its operator, can be shared by
+    /// several threads, each of them solving with its own workspace. The length of
+    /// `workspace` must be at least [`workspace_len(size)`](Self::workspace_len).
+    pub fn solve_with_workspace(
+        &self,
+        workspace: &mut Workspace<T>,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<(usize, T::Real)> {
+        if workspace.len() < Self::workspace_len(self.size) {
+            return Err(SolverError::InvalidParameter(String::from(
+                "The workspace is too short for the matrix size",
+            )));
+        }
+        self.iterate(
+            workspace.as_mut_slice(),
+            rhs,
+            x,
+            false,
+            max_iter,
+            tol,
+            T::Real::zero(),
+            NormKind::L2,
+            |_, _| ControlFlow::Continue(()),
+            |_, _, _| {},
+        )
+    }
+
+    /// Solves Ax = b, without preconditioner, calling `callback` with the iteration number
+    /// and the relative residual used by the convergence test, starting from the initial
+    /// residual at iteration 0.
+    ///
+    /// When `callback` returns [`ControlFlow::Break`], the iterations stop and the current
+    /// iteration number and relative residual are returned; `x` holds the current iterate.
+    /// This allows custom stopping policies, e.g. stagnation detection or wall-clock limits.
+    ///
+    /// **Note:** The residual passed to `callback` is the estimate given by the MINRES
+    /// recurrence, and the iteration number is the number of completed iterations.
+    pub fn solve_with_callback<F: FnMut(usize, T::Real) -> ControlFlow<()>>(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+        callback: F,
+    ) -> SolveResult<(usize, T::Real)> {
+        self.solve_impl(
+            rhs,
+            x,
+            false,
+            max_iter,
+            tol,
+            T::Real::zero(),
+            NormKind::L2,
+            callback,
+            |_, _, _| {},
+        )
+    }
+
+    /// Solves Ax = b, without preconditioner, and returns the relative residual of every
+    /// iteration together with the iteration number and the final relative residual.
+    ///
+    /// The history starts with the initial relative residual, and its last entry is the
+    /// returned relative residual.
+    pub fn solve_with_history(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<(usize, T::Real, Vec<T::Real>)> {
+        let mut history = Vec::new();
+        let (its, res) = self.solve_impl(
+            rhs,
+            x,
+            false,
+            max_iter,
+            tol,
+            T::Real::zero(),
+            NormKind::L2,
+            |_, r| {
+                history.push(r);
+                ControlFlow::Continue(())
+            },
+            |_, _, _| {},
+        )?;
+        Ok((its, res, history))
+    }
+
+    /// Solves Ax = b, without preconditioner, testing the convergence as
+    /// $|b - Ax| \le \text{tol}\,|b|$ in the norm `tol_norm`.
+    ///
+    /// The returned relative residual is measured in the same norm.
+    ///
+    /// MINRES only tracks the 2-norm of the residual. With [`NormKind::LInf`], the true
+    /// residual is computed, at the cost of one extra matrix-vector product, in the
+    /// iterations where $|r|_2 \le \sqrt{n}\,\text{tol}\,|b|_\infty$, which is necessary
+    /// for the infinity-norm test to pass.
+    pub fn solve_with_norm(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+        tol_norm: NormKind,
+    ) -> SolveResult<(usize, T::Real)> {
+        self.solve_impl(
+            rhs,
+            x,
+            false,
+            max_iter,
+            tol,
+            T::Real::zero(),
+            tol_norm,
+            |_, _| ControlFlow::Continue(()),
+            |_, _, _| {},
+        )
+    }
+
+    /// Solves Ax = b, without preconditioner, and compares the reported residual with the
+    /// true residual $|b - Ax| / |b|$ of the solution in the returned report.
+    ///
+    /// It costs one extra matrix-vector product.
+    pub fn solve_checked(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<SolveReport<T::Real>> {
+        let ret = self.solve(rhs, x, max_iter, tol)?;
+        Ok(check_residual(
+            self.A,
+            &self.ops,
+            rhs,
+            x,
+            &mut self.workspace,
+            ret,
+        ))
+    }
+
+    /// Solves Ax = b with the preconditioner `precond`, as
+    /// [`precond_solve`](Self::precond_solve), and compares the reported residual with the
+    /// true residual $|b - Ax| / |b|$ of the solution in the returned report.
+    ///
+    /// It costs one extra matrix-vector product.
+    pub fn precond_solve_checked<P: Precond<T> + ?Sized>(
+        &mut self,
+        precond: &P,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<SolveReport<T::Real>> {
+        let ret = self.precond_solve(precond, rhs, x, max_iter, tol)?;
+        Ok(check_residual(
+            self.A,
+            &self.ops,
+            rhs,
+            x,
+            &mut self.workspace,
+            ret,
+        ))
+    }
+
+    /// Solves Ax = b, without preconditioner, reusing the spectral information in `cache`.
+    ///
+    /// Before iterating, the initial guess `x` is corrected by a Galerkin projection onto the
+    /// Ritz vectors stored in `cache`. After the solve, the first `cache.max_basis()` Lanczos
+    /// vectors are used to refresh the cache for the next solve in the sequence.
+    ///
+    /// The projection costs `cache.len()` extra matrix-vector products, and the cache update
+    /// costs up to `2 * nev` more. They are not counted in the returned iteration number.
+    pub fn solve_with_cache(
+        &mut self,
+        cache: &mut SpectralCache<T>,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<(usize, T::Real)> {
+        let n = rhs.len();
+        if n != self.size || cache.size() != self.size {
+            return Err(SolverError::IncompatibleMatrixFormat(String::from(
+                "Input vec dimension doesn't match the matrix size",
+            )));
+        }
+        if n != x.len() {
+            return Err(SolverError::IncompatibleMatrixFormat(String::from(
+                "Input and output vec dimension do not match",
+            )));
+        }
+        cache.deflate_initial_guess(self.A, &self.ops, rhs, x, &mut self.workspace[..n]);
+
+        let m = cache.max_basis();
+        let mut basis: Vec<T> = Vec::with_capacity(m * n);
+        let mut alphas: Vec<T::Real> = Vec::with_capacity(m);
+        let mut betas: Vec<T::Real> = Vec::with_capacity(m);
+        let ret = self.solve_impl(
+            rhs,
+            x,
+            false,
+            max_iter,
+            tol,
+            T::Real::zero(),
+            NormKind::L2,
+            |_, _| ControlFlow::Continue(()),
+            |q, alpha, beta| {
+                if alphas.len() < m {
+                    basis.extend_from_slice(q);
+                    alphas.push(alpha.re());
+                    betas.push(beta);
+                }
+            },
+        );
+        cache.update(self.A, &self.ops, &basis, &alphas, &betas);
+        ret
+    }
+
+    /// Run [`iterate`](Self::iterate) in the workspace owned by this solver.
+    #[allow(clippy::too_many_arguments)]
+    fn solve_impl<G: FnMut(usize, T::Real) -> ControlFlow<()>, F: FnMut(&[T], T, T::Real)>(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        zero_guess: bool,
+        max_iter: usize,
+        tol: T::Real,
+        abs_tol: T::Real,
+        tol_norm: NormKind,
+        monitor: G,
+        lanczos_hook: F,
+    ) -> SolveResult<(usize, T::Real)> {
+        let mut workspace = std::mem::take(&mut self.workspace);
+        let ret = self.iterate(
+            &mut workspace,
+            rhs,
+            x,
+            zero_guess,
+            max_iter,
+            tol,
+            abs_tol,
+            tol_norm,
+            monitor,
+            lanczos_hook,
+        );
+        self.workspace = workspace;
+        ret
+    }
+
+    /// The MINRES iterations, using `workspace` as scratch memory. `monitor` is called with
+    /// the number of completed iterations and the relative residual estimated by the
+    /// recurrence, starting from the initial residual; the iterations stop when it returns [`ControlFlow::Break`]. `lanczos_hook` is called at every iteration with the current Lanczos vector
+    /// $q_k$, and the coefficients $\alpha_k$ and $\beta_k$ of the tridiagonal matrix. With
+    /// `zero_guess`, `x` is zeroed instead of being read.
+    #[allow(clippy::many_single_char_names, clippy::too_many_arguments)]
+    fn iterate<G: FnMut(usize, T::Real) -> ControlFlow<()>, F: FnMut(&[T], T, T::Real)>(
+        &self,
+        workspace: &mut [T],
+        rhs: &[T],
+        x: &mut [T],
+        zero_guess: bool,
+        max_iter: usize,
+        tol: T::Real,
+        abs_tol: T::Real,
+        tol_norm: NormKind,
+        mut monitor: G,
+        mut lanczos_hook: F,
     ) -> SolveResult<(usize, T::Real)> {
         let n = rhs.len();
         // check the format
@@ -48,13 +422,23 @@ impl<'data, T: Scalar, M: MatVecMul<T>> MinRes<'data, T, M> {
             )));
         }
 
-        let rhs_norm = norm2(rhs);
-        if unlikely(rhs_norm <= T::Real::epsilon()) {
+        let rhs_norm = tol_norm.norm(&self.ops, rhs);
+        // with an absolute tolerance, a tiny but nonzero rhs is still solved from x
+        if unlikely(
+            rhs_norm <= T::Real::epsilon()
+                && (abs_tol <= T::Real::zero() || rhs_norm == T::Real::zero()),
+        ) {
             // when rhs = 0, x is set to zero.
-            x.iter_mut().for_each(|v| *v = T::zero());
+            zero(&mut *x);
+            let _ = monitor(0, rhs_norm);
             return Ok((0, rhs_norm));
         }
-        let threshold = tol * rhs_norm;
+        let threshold = Float::max(tol * rhs_norm, abs_tol);
+        // |r|_inf <= threshold requires |r|_2 <= sqrt(n) * threshold
+        let threshold2 = match tol_norm {
+            NormKind::L2 => threshold,
+            NormKind::LInf => threshold * num_traits::Float::sqrt(T::real(n as f64)),
+        };
 
         // initialize
         let mut c = T::one();
@@ -63,62 +447,53 @@ impl<'data, T: Scalar, M: MatVecMul<T>> MinRes<'data, T, M> {
         let mut s_old = T::Real::zero();
         let mut eta = T::one();
 
-        // set vectors using preallocated memeory
-        let ptr = self.workspace.as_mut_ptr();
-        let mut v_old = unsafe { from_raw_parts_mut(ptr, n) }; // &mut [T]
-        let mut v_new = unsafe { from_raw_parts_mut(ptr.add(n), n) };
-        let mut v = unsafe { from_raw_parts_mut(ptr.add(2 * n), n) };
+        // Here is the internal memeory layout:
+        // [ Lanczos vectors (3n) | p_old | p_oold | p | r | Ax ]
+        let ptr = workspace.as_mut_ptr();
         let mut p_old = unsafe { from_raw_parts_mut(ptr.add(3 * n), n) };
         let mut p_oold = unsafe { from_raw_parts_mut(ptr.add(4 * n), n) };
         let mut p = unsafe { from_raw_parts_mut(ptr.add(5 * n), n) };
-
-        // initialize v and v_new
-        unsafe {
-            copy_nonoverlapping(rhs.as_ptr(), v_new.as_mut_ptr(), n); // v_new = rhs
-            self.A.mul_vec_unchecked(x, &mut *v_old); // v_old = A * x
+        let r = unsafe { from_raw_parts_mut(ptr.add(6 * n), n) };
+        let ax = unsafe { from_raw_parts_mut(ptr.add(7 * n), n) };
+        let mut lanczos = Lanczos::in_workspace(self.A, n, &self.ops, unsafe {
+            from_raw_parts_mut(ptr, Lanczos::<T, M, &V>::workspace_len(n))
+        });
+
+        // q_1 = r / |r|
+        let mut res_norm = if zero_guess {
+            // A*x vanishes, skip the product
+            zero(&mut *x);
+            lanczos.start(rhs)
+        } else {
+            unsafe {
+                copy_nonoverlapping(rhs.as_ptr(), r.as_mut_ptr(), n); // r = rhs
+                self.A.mul_vec_unchecked(x, &mut *ax); // ax = A * x
+            }
+            self.ops.axpy(-T::one(), &*ax, &mut *r); // r = rhs - A*x
+            lanczos.start(&*r)
+        };
+        if unlikely(!res_norm.is_finite()) {
+            return Err(SolverError::Diverged(0));
         }
-        axpy(-T::one(), &*v_old, &mut *v_new); // v_new = rhs - A*x
-        let mut res_norm = norm2(&*v_new);
+        if monitor(0, res_norm / rhs_norm).is_break() {
+            return Ok((0, res_norm / rhs_norm));
+        }
+        let mut stagnation = self.stagnation.map(|s| s.detector(res_norm));
         let mut beta_new = res_norm;
         let beta_one = beta_new;
-        rscale(T::Real::one() / beta_new, &mut *v_new);
 
-        v.iter_mut().for_each(|t| *t = T::zero()); // v = zero
-        p_old.iter_mut().for_each(|t| *t = T::zero()); // p_old = zero
-        p.iter_mut().for_each(|t| *t = T::zero()); // p = zero
+        // The workspace is scratch memory, and only the vectors read before being written
+        // are zeroed: p becomes p_old in the first iteration. p_old becomes p_oold, whose
+        // coefficient r3 vanishes in the first iteration, so its term is skipped instead.
+        zero(&mut *p);
 
         for its in 0..max_iter {
-            let beta = beta_new;
-            let v_t_ptr = v_old.as_mut_ptr();
-            // Here we just move the pointers to avoid memory copy
-            v_old = unsafe { from_raw_parts_mut(v.as_mut_ptr(), n) }; // v_old <- v
-            v = unsafe { from_raw_parts_mut(v_new.as_mut_ptr(), n) }; // v <- v_new
-            v_new = unsafe { from_raw_parts_mut(v_t_ptr, n) }; // v_new <- v_old
-
-            /*
-            unsafe {
-                self.A.mul_vec_unchecked(v, v_new); // v_new = A*v
-            }
-            // v_new = A*v - beta*v_old
-            axpy(T::from_real(-beta), &*v_old, &mut *v_new);  // >>> A*q_k - beta_{k-1} q_{k-1}
-
-            // compute the new Lanczos vector
-            // See P. 562 of Matrix Computation Ed.4
-            // alpha = (A*v - beta * v_old).v
-            // >>> beta is now beta_{k-1}
-            let alpha = conj_dot(&*v_new, &*v); // v_new . v                >>> alpha is alpha_k
-            axpy(-alpha, &*v, &mut *v_new); // v_new -= alpha * v           >>> v_new is now r_k
-            */
-            // According to the Wiki (https://en.wikipedia.org/wiki/Lanczos_algorithm)
-            // This order of computing Lanczos vectors is the most numerically stable.
-            // comptue v_new = A * v
-            //         alpha = conj(v).v_new
-            let alpha = unsafe { self.A.mul_vec_dot_unchecked(v, v_new) };
-            axpy(T::from_real(-beta), &*v_old, &mut *v_new); // >>> A*q_k - beta_{k-1} q_{k-1}
-            axpy(-alpha, &*v, &mut *v_new); // v_new = A*q_k - beta_{k-1}q_{k-1} - alpha*q_k  >>> v_new is now r_k
-
-            beta_new = norm2(&*v_new); // beta_new = |v_new|                >>> beta_new is beta_k
-            rscale(T::Real::one() / beta_new, &mut *v_new); // >>> v_new is now q_k+1
+            let beta = beta_new; // >>> beta is beta_{k-1}
+            let (alpha, b) = lanczos.step(); // >>> alpha is alpha_k
+            let alpha = T::from_real(alpha);
+            beta_new = b; // >>> beta_new is beta_k
+            let v = lanczos.vector(); // >>> v is q_k
+            lanczos_hook(v, alpha, beta_new);
 
             // --- Givens rotation ---
             // G^T_{k-1} = [ c_old  s_old ]
@@ -136,34 +511,65 @@ impl<'data, T: Scalar, M: MatVecMul<T>> MinRes<'data, T, M> {
             let r1_hat = c * alpha - tr.mul_real(s);
 
             // now need to construct Givens rotation for [r1_hat beta_k]
-            let r1_inv =
-                T::Real::one() / num_traits::Float::sqrt(r1_hat.square() + beta_new.square());
+            let (mut r1, mut b) = (r1_hat, T::from_real(beta_new));
+            let (cg, sg) = rotg(&mut r1, &mut b);
+            // r1 has the phase of r1_hat; rotate by the conjugate phase to make it positive
+            let r1_abs = r1.abs();
+            let r1_inv = T::Real::one() / r1_abs;
+            let phase = if likely(r1_abs > T::Real::zero()) {
+                r1 / T::from_real(r1_abs)
+            } else {
+                T::one()
+            };
 
             c_old = c; // store for next iteration
             s_old = s; // store for next iteration
 
             // [ c  s ]
             // [-s  c ]
-            c = r1_hat.mul_real(r1_inv); // new cosine
-            s = beta_new * r1_inv; // new sine
+            c = phase.mul_real(cg); // new cosine
+            s = (phase.conj() * sg).re(); // new sine
 
             // Update solution
             let p_t_ptr = p_oold.as_mut_ptr();
             p_oold = unsafe { from_raw_parts_mut(p_old.as_mut_ptr(), n) }; // p_oold <- p_old
             p_old = unsafe { from_raw_parts_mut(p.as_mut_ptr(), n) }; // p_old <- p
             p = unsafe { from_raw_parts_mut(p_t_ptr, n) };
-            unsafe {
-                copy_nonoverlapping(v.as_ptr(), p.as_mut_ptr(), n); // p = v
+            // p = (v - r2*p_old - r3*p_oold) / r1
+            self.ops.scaled_copy(T::from_real(r1_inv), v, &mut *p);
+            self.ops.axpy(-r2.mul_real(r1_inv), &*p_old, &mut *p);
+            if likely(its > 0) {
+                self.ops.raxpy(-r3 * r1_inv, &*p_oold, &mut *p);
             }
-            axpy(-r2, &*p_old, &mut *p); // p = v - r2*p_old
-            axpy(T::from_real(-r3), &*p_oold, &mut *p); // p = v - r2*p_old - r3*p_oold
-            rscale(r1_inv, &mut *p);
 
-            axpy((c * eta).mul_real(beta_one), &*p, &mut *x); //  x += beta_one*c*eta*p
+            self.ops.axpy((c * eta).mul_real(beta_one), &*p, &mut *x); //  x += beta_one*c*eta*p
 
             res_norm *= num_traits::Float::abs(s);
-            if res_norm < threshold {
-                return Ok((its, res_norm / rhs_norm));
+            if unlikely(!res_norm.is_finite()) {
+                return Err(SolverError::Diverged(its + 1));
+            }
+            if monitor(its + 1, res_norm / rhs_norm).is_break() {
+                return Ok((its + 1, res_norm / rhs_norm));
+            }
+            if res_norm < threshold2 {
+                match tol_norm {
+                    NormKind::L2 => return Ok((its + 1, res_norm / rhs_norm)),
+                    NormKind::LInf => {
+                        unsafe {
+                            self.A.mul_vec_unchecked(x, &mut *r); // r = A*x
+                        }
+                        self.ops.axpby(T::one(), rhs, -T::one(), &mut *r); // r = rhs - A*x
+                        let r_norm = self.ops.norm_inf(&*r);
+                        if r_norm <= threshold {
+                            return Ok((its + 1, r_norm / rhs_norm));
+                        }
+                    }
+                }
+            }
+            if let Some(d) = stagnation.as_mut() {
+                if unlikely(d.stagnated(res_norm)) {
+                    return Err(stagnation_error(its + 1, res_norm / rhs_norm));
+                }
             }
             eta = eta.mul_real(-s);
         }
@@ -173,9 +579,18 @@ impl<'data, T: Scalar, M: MatVecMul<T>> MinRes<'data, T, M> {
 
     /// Solves Ax = b, with a preconditioner
     ///
-    /// **NOTE:** The preconditioner $M$ must be able to written as $M = C^H C$.
+    /// It runs the preconditioned Lanczos process, where each step computes $z = M^{-1}v$ and
+    /// normalizes with $\beta = \sqrt{v^Hz}$, i.e. the Lanczos vectors are orthonormal in the
+    /// inner product induced by $M^{-1}$. The workspace holds the vectors $z$ next to the
+    /// Lanczos vectors. This is the standard method for preconditioned symmetric indefinite
+    /// systems, e.g. saddle point problems with a block diagonal preconditioner.
+    ///
+    /// **NOTE:** The preconditioner $M$ must be Hermitian positive definite, i.e. it must be
+    /// able to written as $M = C^H C$, even when $A$ is indefinite. It is applied
+    /// symmetrically, as $C^{-H}AC^{-1}$, so there is no choice of [`Side`](crate::Side). A
+    /// non-positive $v^Hz$ is reported as [`SolverError::InvalidPreconditioner`].
     #[allow(clippy::many_single_char_names)]
-    pub fn precond_solve<P: MatVecMul<T>>(
+    pub fn precond_solve<P: Precond<T> + ?Sized>(
         &mut self,
         precond: &P,
         rhs: &[T],
@@ -196,10 +611,10 @@ impl<'data, T: Scalar, M: MatVecMul<T>> MinRes<'data, T, M> {
             )));
         }
 
-        let rhs_norm = norm2(rhs);
+        let rhs_norm = self.ops.norm2(rhs);
         if unlikely(rhs_norm <= T::Real::epsilon()) {
             // when rhs = 0, x is set to zero.
-            x.iter_mut().for_each(|v| *v = T::zero());
+            zero(&mut *x);
             return Ok((0, rhs_norm));
         }
         let threshold = tol * rhs_norm;
@@ -227,12 +642,10 @@ impl<'data, T: Scalar, M: MatVecMul<T>> MinRes<'data, T, M> {
             copy_nonoverlapping(rhs.as_ptr(), v_new.as_mut_ptr(), n); // v_new = rhs
             self.A.mul_vec_unchecked(x, &mut *v_old); // v_old = A * x
         }
-        axpy(-T::one(), &*v_old, &mut *v_new); // v_new = rhs - A*x >>> r_1
-        let mut res_norm = norm2(&*v_new);
-        unsafe {
-            precond.mul_vec_unchecked(&*v_new, &mut *w_new); // w_new = M^{-1} r_1
-        }
-        let beta_new2 = conj_dot(&*v_new, &*w_new); // beta_1^2 = r_1^H M^{-1} r_1
+        self.ops.axpy(-T::one(), &*v_old, &mut *v_new); // v_new = rhs - A*x >>> r_1
+        let mut res_norm = self.ops.norm2(&*v_new);
+        precond.apply(&*v_new, &mut *w_new); // w_new = M^{-1} r_1
+        let beta_new2 = self.ops.conj_dot(&*v_new, &*w_new); // beta_1^2 = r_1^H M^{-1} r_1
         if unlikely(
             beta_new2.re() < T::Real::epsilon()
                 || beta_new2.im() > T::Real::epsilon() * beta_new2.re(),
@@ -246,12 +659,12 @@ impl<'data, T: Scalar, M: MatVecMul<T>> MinRes<'data, T, M> {
         let beta_one = beta_new;
 
         let ts = T::Real::one() / beta_new;
-        rscale(ts, &mut *v_new);
-        rscale(ts, &mut *w_new);
+        self.ops.rscale(ts, &mut *v_new);
+        self.ops.rscale(ts, &mut *w_new);
 
-        v.iter_mut().for_each(|t| *t = T::zero()); // v = zero
-        p_old.iter_mut().for_each(|t| *t = T::zero()); // p_old = zero
-        p.iter_mut().for_each(|t| *t = T::zero()); // p = zero
+        // only v and p are read before being written, see `iterate`
+        zero(&mut *v);
+        zero(&mut *p);
 
         for its in 0..max_iter {
             let beta = beta_new;
@@ -268,14 +681,12 @@ impl<'data, T: Scalar, M: MatVecMul<T>> MinRes<'data, T, M> {
             // This order of computing Lanczos vectors is the most numerically stable.
             // comptue v_new = A * q_k
             //         alpha = q_k^H * A * q_k
-            let alpha = unsafe { self.A.mul_vec_dot_unchecked(w, v_new) };
-            axpy(T::from_real(-beta), &*v_old, &mut *v_new); // >>> A*q_k - beta_{k-1} q_{k-1}
-            axpy(-alpha, &*v, &mut *v_new); // v_new = A*q_k - beta_{k-1}q_{k-1} - alpha*q_k  >>> v_new is now r_k
+            let alpha = T::from_real(unsafe { self.A.mul_vec_rdot_unchecked(w, v_new) });
+            self.ops.raxpy(-beta, &*v_old, &mut *v_new); // >>> A*q_k - beta_{k-1} q_{k-1}
+            self.ops.axpy(-alpha, &*v, &mut *v_new); // v_new = A*q_k - beta_{k-1}q_{k-1} - alpha*q_k  >>> v_new is now r_k
 
-            unsafe {
-                precond.mul_vec_unchecked(&*v_new, &mut *w_new); // w_new = M^-1 r_{k+1}
-            }
-            let beta_new2 = conj_dot(&*v_new, &*w_new); // beta_k^2 = r_k^H M^{-1} r_k
+            precond.apply(&*v_new, &mut *w_new); // w_new = M^-1 r_{k+1}
+            let beta_new2 = self.ops.conj_dot(&*v_new, &*w_new); // beta_k^2 = r_k^H M^{-1} r_k
             if unlikely(
                 beta_new2.re() < T::Real::epsilon()
                     || beta_new2.im() > T::Real::epsilon() * beta_new2.re(),
@@ -287,8 +698,8 @@ impl<'data, T: Scalar, M: MatVecMul<T>> MinRes<'data, T, M> {
             }
             beta_new = num_traits::Float::sqrt(beta_new2.re()); // >>> beta_new is beta_k
             let ts = T::Real::one() / beta_new;
-            rscale(ts, &mut *v_new); // >>> v_new is now q_k+1
-            rscale(ts, &mut *w_new);
+            self.ops.rscale(ts, &mut *v_new); // >>> v_new is now q_k+1
+            self.ops.rscale(ts, &mut *w_new);
 
             // --- Givens rotation ---
             // G^T_{k-1} = [ c_old  s_old ]
@@ -305,34 +716,42 @@ impl<'data, T: Scalar, M: MatVecMul<T>> MinRes<'data, T, M> {
             let r1_hat = c * alpha - tr.mul_real(s);
 
             // now need to construct Givens rotation for [r1_hat beta_k]
-            let r1_inv =
-                T::Real::one() / num_traits::Float::sqrt(r1_hat.square() + beta_new.square());
+            let (mut r1, mut b) = (r1_hat, T::from_real(beta_new));
+            let (cg, sg) = rotg(&mut r1, &mut b);
+            // r1 has the phase of r1_hat; rotate by the conjugate phase to make it positive
+            let r1_abs = r1.abs();
+            let r1_inv = T::Real::one() / r1_abs;
+            let phase = if likely(r1_abs > T::Real::zero()) {
+                r1 / T::from_real(r1_abs)
+            } else {
+                T::one()
+            };
 
             c_old = c; // store for next iteration
             s_old = s; // store for next iteration
 
             // [ c  s ]
             // [-s  c ]
-            c = r1_hat.mul_real(r1_inv); // new cosine
-            s = beta_new * r1_inv; // new sine
+            c = phase.mul_real(cg); // new cosine
+            s = (phase.conj() * sg).re(); // new sine
 
             // Update solution
             let p_t_ptr = p_oold.as_mut_ptr();
             p_oold = unsafe { from_raw_parts_mut(p_old.as_mut_ptr(), n) }; // p_oold <- p_old
             p_old = unsafe { from_raw_parts_mut(p.as_mut_ptr(), n) }; // p_old <- p
             p = unsafe { from_raw_parts_mut(p_t_ptr, n) };
-            unsafe {
-                copy_nonoverlapping(w.as_ptr(), p.as_mut_ptr(), n); // p = q_k
+            // p = (q_k - r2*p_old - r3*p_oold) / r1
+            self.ops.scaled_copy(T::from_real(r1_inv), &*w, &mut *p);
+            self.ops.axpy(-r2.mul_real(r1_inv), &*p_old, &mut *p);
+            if likely(its > 0) {
+                self.ops.raxpy(-r3 * r1_inv, &*p_oold, &mut *p);
             }
-            axpy(-r2, &*p_old, &mut *p); // p = q_k - r2*p_old
-            axpy(T::from_real(-r3), &*p_oold, &mut *p); // p = q_k - r2*p_old - r3*p_oold
-            rscale(r1_inv, &mut *p);
 
-            axpy((c * eta).mul_real(beta_one), &*p, &mut *x); //  x += beta_one*c*eta*p
+            self.ops.axpy((c * eta).mul_real(beta_one), &*p, &mut *x); //  x += beta_one*c*eta*p
 
             res_norm *= num_traits::Float::abs(s);
             if res_norm < threshold {
-                return Ok((its, res_norm / rhs_norm));
+                return Ok((its + 1, res_norm / rhs_norm));
             }
             eta = eta.mul_real(-s);
         }
@@ -340,3 +759,49 @@ impl<'data, T: Scalar, M: MatVecMul<T>> MinRes<'data, T, M> {
         Err(SolverError::InsufficientIterNum(max_iter))
     }
 }
+
+impl<'data, T: Scalar, M: MatVecMul<T>, V: VecOps<T>> IterativeSolver<T>
+    for MinRes<'data, T, M, V>
+{
+    fn solve(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<(usize, T::Real)> {
+        MinRes::solve(self, rhs, x, max_iter, tol)
+    }
+
+    fn solve_report(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<SolveReport<T::Real>> {
+        let ret = MinRes::solve(self, rhs, x, max_iter, tol);
+        report_outcome(self.A, &self.ops, rhs, x, &mut self.workspace, ret)
+    }
+
+    fn solve_with_config(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        config: &SolveConfig<T::Real>,
+    ) -> SolveResult<(usize, T::Real)> {
+        let mut deadline = Deadline::new(config.time_limit);
+        let ret = self.solve_impl(
+            rhs,
+            x,
+            false,
+            config.max_iter,
+            config.tol,
+            config.abs_tol,
+            config.norm_kind,
+            |its, res| deadline.monitor(its, res),
+            |_, _, _| {},
+        );
+        deadline.finish(ret)
+    }
+}
@@ -1,4 +1,7 @@
-use super::MatVecMul;
+use super::{
+    error::{SolveResult, SolverError},
+    Layout, MatVecMul,
+};
 use cauchy::Scalar;
 use mkl_sys::spblas as sp;
 use num_complex::{Complex32, Complex64};
@@ -6,12 +9,81 @@ use sprs::CsMatI;
 use std::{os::raw::c_int, result::Result};
 
 const DEFAULT_SPARSE_MV_CALLS: i32 = 2000;
+const NON_TRANSPOSE: sp::sparse_operation_t = sp::sparse_operation_t_SPARSE_OPERATION_NON_TRANSPOSE;
 
 const COMPLEX32_ZERO: mkl_sys::MKL_Complex8 = mkl_sys::MKL_Complex8 { real: 0., imag: 0. };
 const COMPLEX32_ONE: mkl_sys::MKL_Complex8 = mkl_sys::MKL_Complex8 { real: 1., imag: 0. };
 const COMPLEX64_ZERO: mkl_sys::MKL_Complex16 = mkl_sys::MKL_Complex16 { real: 0., imag: 0. };
 const COMPLEX64_ONE: mkl_sys::MKL_Complex16 = mkl_sys::MKL_Complex16 { real: 1., imag: 0. };
 
+#[inline]
+fn mkl_complex8(v: Complex32) -> mkl_sys::MKL_Complex8 {
+    mkl_sys::MKL_Complex8 {
+        real: v.re,
+        imag: v.im,
+    }
+}
+
+#[inline]
+fn mkl_complex16(v: Complex64) -> mkl_sys::MKL_Complex16 {
+    mkl_sys::MKL_Complex16 {
+        real: v.re,
+        imag: v.im,
+    }
+}
+
+/// The triangular part of a matrix used by [`MklMat::triangular_solve`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FillMode {
+    Lower,
+    Upper,
+}
+
+/// Whether the diagonal of a triangular matrix is read from the stored entries (`NonUnit`)
+/// or assumed to be all ones (`Unit`).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DiagType {
+    Unit,
+    NonUnit,
+}
+
+#[inline]
+fn sparse_layout(layout: Layout) -> sp::sparse_layout_t {
+    match layout {
+        Layout::RowMajor => sp::sparse_layout_t_SPARSE_LAYOUT_ROW_MAJOR,
+        Layout::ColMajor => sp::sparse_layout_t_SPARSE_LAYOUT_COLUMN_MAJOR,
+    }
+}
+
+#[inline]
+fn triangular_descr(fill: FillMode, diag: DiagType) -> sp::matrix_descr {
+    sp::matrix_descr {
+        type_: sp::sparse_matrix_type_t_SPARSE_MATRIX_TYPE_TRIANGULAR,
+        mode: match fill {
+            FillMode::Lower => sp::sparse_fill_mode_t_SPARSE_FILL_MODE_LOWER,
+            FillMode::Upper => sp::sparse_fill_mode_t_SPARSE_FILL_MODE_UPPER,
+        },
+        diag: match diag {
+            DiagType::Unit => sp::sparse_diag_type_t_SPARSE_DIAG_UNIT,
+            DiagType::NonUnit => sp::sparse_diag_type_t_SPARSE_DIAG_NON_UNIT,
+        },
+    }
+}
+
+/// A sparse matrix handle created by MKL itself, e.g. the result of `mkl_sparse_spmm`,
+/// destroyed when dropped.
+struct OwnedHandle(sp::sparse_matrix_t);
+
+impl Drop for OwnedHandle {
+    fn drop(&mut self) {
+        if !self.0.is_null() {
+            unsafe {
+                sp::mkl_sparse_destroy(self.0);
+            }
+        }
+    }
+}
+
 pub struct MklMat<T: Scalar> {
     // We have to use `u32` to be used with MKL interfaces
     _indptr: Vec<i32>,
@@ -19,6 +91,8 @@ pub struct MklMat<T: Scalar> {
     _data: Vec<T>,
     size: usize,
     sp_handle: sp::sparse_matrix_t,
+    /// How MKL interprets the stored entries
+    descr: sp::matrix_descr,
 }
 
 impl<T: Scalar> MklMat<T> {
@@ -28,10 +102,79 @@ impl<T: Scalar> MklMat<T> {
         self.size
     }
 
+    /// Return the number of stored entries.
+    #[inline(always)]
+    pub fn nnz(&self) -> usize {
+        self._indices.len()
+    }
+
     /// Create a general MKL Sparse Matrix from the privided [`CsMat`].
     pub fn new(m: CsMatI<T, i32>) -> Result<MklMat<T>, u32> {
+        Self::with_descr(
+            m,
+            sp::matrix_descr {
+                type_: sp::sparse_matrix_type_t_SPARSE_MATRIX_TYPE_GENERAL,
+                mode: sp::sparse_fill_mode_t_SPARSE_FILL_MODE_FULL,
+                diag: sp::sparse_diag_type_t_SPARSE_DIAG_NON_UNIT,
+            },
+        )
+    }
+
+    /// Create a symmetric MKL Sparse Matrix from the triangular part of `m` selected by
+    /// `fill`. Only this part needs to be stored in `m`; the entries of the other part are
+    /// ignored.
+    pub fn new_symmetric(m: CsMatI<T, i32>, fill: FillMode) -> Result<MklMat<T>, u32> {
+        let mut descr = triangular_descr(fill, DiagType::NonUnit);
+        descr.type_ = sp::sparse_matrix_type_t_SPARSE_MATRIX_TYPE_SYMMETRIC;
+        Self::with_descr(m, descr)
+    }
+
+    /// Create a Hermitian MKL Sparse Matrix from the triangular part of `m` selected by
+    /// `fill`. Only this part needs to be stored in `m`; the entries of the other part are
+    /// ignored. For real-valued matrices, it is the same as [`new_symmetric`](Self::new_symmetric).
+    pub fn new_hermitian(m: CsMatI<T, i32>, fill: FillMode) -> Result<MklMat<T>, u32> {
+        if !(super::same_type::<T, Complex32>() || super::same_type::<T, Complex64>()) {
+            return Self::new_symmetric(m, fill);
+        }
+        let mut descr = triangular_descr(fill, DiagType::NonUnit);
+        descr.type_ = sp::sparse_matrix_type_t_SPARSE_MATRIX_TYPE_HERMITIAN;
+        Self::with_descr(m, descr)
+    }
+
+    /// Create a triangular MKL Sparse Matrix from the triangular part of `m` selected by
+    /// `fill`. The products only use this part, and assume a unit diagonal if `diag` is
+    /// [`DiagType::Unit`].
+    pub fn new_triangular(
+        m: CsMatI<T, i32>,
+        fill: FillMode,
+        diag: DiagType,
+    ) -> Result<MklMat<T>, u32> {
+        Self::with_descr(m, triangular_descr(fill, diag))
+    }
+
+    /// Create a general MKL Sparse Matrix from a [`CsMat`] in the CSC format, e.g. as
+    /// assembled column by column, without converting it to CSR first.
+    pub fn new_csc(m: CsMatI<T, i32>) -> Result<MklMat<T>, u32> {
+        assert!(m.is_csc());
+        Self::from_storage(
+            m,
+            sp::matrix_descr {
+                type_: sp::sparse_matrix_type_t_SPARSE_MATRIX_TYPE_GENERAL,
+                mode: sp::sparse_fill_mode_t_SPARSE_FILL_MODE_FULL,
+                diag: sp::sparse_diag_type_t_SPARSE_DIAG_NON_UNIT,
+            },
+        )
+    }
+
+    fn with_descr(m: CsMatI<T, i32>, descr: sp::matrix_descr) -> Result<MklMat<T>, u32> {
         assert!(m.is_csr());
+        Self::from_storage(m, descr)
+    }
 
+    /// Build the MKL handle with `mkl_sparse_?_create_csr` or `mkl_sparse_?_create_csc`,
+    /// depending on the storage of `m`.
+    fn from_storage(m: CsMatI<T, i32>, descr: sp::matrix_descr) -> Result<MklMat<T>, u32> {
+        let is_csr = m.is_csr();
         let ncol = m.cols();
         let nrow = m.rows();
         assert_eq!(ncol, nrow);
@@ -41,11 +184,13 @@ impl<T: Scalar> MklMat<T> {
         let (indptr, indices, data) = m.into_raw_storage();
         let indptr_ptr = indptr.as_ptr();
         let mut sp_handle: sp::sparse_matrix_t = std::ptr::null_mut(); // *mut sparse_matrix
-        macro_rules! create_csr {
-            ($ty:ty, $func:ident, {$( $complex:ident )?}) => {
+        macro_rules! create {
+            ($ty:ty, $csr:ident, $csc:ident, {$( $complex:ident )?}) => {
                 if super::same_type::<T, $ty>() {
+                    // the CSC arrays are the CSR ones with the roles of rows and columns swapped
+                    let func = if is_csr { sp::$csr } else { sp::$csc };
                     let status = unsafe {
-                        sp::$func(
+                        func(
                             &mut sp_handle as *mut sp::sparse_matrix_t,
                             sp::sparse_index_base_t_SPARSE_INDEX_BASE_ZERO,
                             nrow as c_int,
@@ -59,16 +204,16 @@ impl<T: Scalar> MklMat<T> {
                     if status != sp::sparse_status_t_SPARSE_STATUS_SUCCESS {
                         return Err(status);
                     }
-                    let mret = MklMat { _indptr: indptr, _indices: indices, _data: data, size: nrow, sp_handle };
+                    let mret = MklMat { _indptr: indptr, _indices: indices, _data: data, size: nrow, sp_handle, descr };
                     mret.mv_hint(DEFAULT_SPARSE_MV_CALLS)?;
                     return Ok(mret);
                 }
             };
         }
-        create_csr! {f32, mkl_sparse_s_create_csr, {}};
-        create_csr! {f64, mkl_sparse_d_create_csr, {}};
-        create_csr! {Complex32, mkl_sparse_c_create_csr, {MKL_Complex8}};
-        create_csr! {Complex64, mkl_sparse_z_create_csr, {MKL_Complex16}};
+        create! {f32, mkl_sparse_s_create_csr, mkl_sparse_s_create_csc, {}};
+        create! {f64, mkl_sparse_d_create_csr, mkl_sparse_d_create_csc, {}};
+        create! {Complex32, mkl_sparse_c_create_csr, mkl_sparse_c_create_csc, {MKL_Complex8}};
+        create! {Complex64, mkl_sparse_z_create_csr, mkl_sparse_z_create_csc, {MKL_Complex16}};
 
         unreachable!();
     }
@@ -80,11 +225,7 @@ impl<T: Scalar> MklMat<T> {
     #[inline]
     pub fn mv_and_dotmv_hint(&self, ncalls: i32) -> Result<(), u32> {
         debug_assert!(ncalls > 0);
-        let descr = sp::matrix_descr {
-            type_: sp::sparse_matrix_type_t_SPARSE_MATRIX_TYPE_GENERAL,
-            mode: sp::sparse_fill_mode_t_SPARSE_FILL_MODE_FULL,
-            diag: sp::sparse_diag_type_t_SPARSE_DIAG_NON_UNIT,
-        };
+        let descr = self.descr;
         let status = unsafe {
             sp::mkl_sparse_set_mv_hint(
                 self.sp_handle,
@@ -123,11 +264,7 @@ impl<T: Scalar> MklMat<T> {
     #[inline]
     pub fn mv_hint(&self, ncalls: i32) -> Result<(), u32> {
         debug_assert!(ncalls > 0);
-        let descr = sp::matrix_descr {
-            type_: sp::sparse_matrix_type_t_SPARSE_MATRIX_TYPE_GENERAL,
-            mode: sp::sparse_fill_mode_t_SPARSE_FILL_MODE_FULL,
-            diag: sp::sparse_diag_type_t_SPARSE_DIAG_NON_UNIT,
-        };
+        let descr = self.descr;
         let status = unsafe {
             sp::mkl_sparse_set_mv_hint(
                 self.sp_handle,
@@ -146,52 +283,369 @@ impl<T: Scalar> MklMat<T> {
         }
         Ok(())
     }
-}
 
-impl<T: Scalar> MatVecMul<T> for MklMat<T> {
+    /// Set the hint for the products with dense blocks of `n_cols` columns stored in the
+    /// order `layout`, see [`mul_mat`](Self::mul_mat).
+    ///
+    /// It calls MKL routines to set `mkl_sparse_set_mm_hint`.
     #[inline]
-    fn mul_vec(&self, v_in: &[T], v_out: &mut [T]) {
-        if self.size != v_in.len() || self.size != v_out.len() {
-            panic!("Dimension mismatch");
+    pub fn mm_hint(&self, layout: Layout, n_cols: usize, ncalls: i32) -> Result<(), u32> {
+        debug_assert!(ncalls > 0);
+        let status = unsafe {
+            sp::mkl_sparse_set_mm_hint(
+                self.sp_handle,
+                sp::sparse_operation_t_SPARSE_OPERATION_NON_TRANSPOSE,
+                self.descr,
+                sparse_layout(layout),
+                n_cols as c_int,
+                ncalls,
+            )
+        };
+        if status != sp::sparse_status_t_SPARSE_STATUS_SUCCESS {
+            return Err(status);
         }
-        unsafe {
-            self.mul_vec_unchecked(v_in, v_out);
+
+        let status = unsafe { sp::mkl_sparse_optimize(self.sp_handle) };
+        if status != sp::sparse_status_t_SPARSE_STATUS_SUCCESS {
+            return Err(status);
         }
+        Ok(())
     }
 
+    /// Set the hint for triangular solves with the given triangular part of the matrix.
+    ///
+    /// It calls MKL routines to set `mkl_sparse_set_sv_hint`, e.g., for the matrices
+    /// holding the factors of an incomplete factorization.
     #[inline]
-    fn mul_vec_dot(&self, v_in: &[T], v_out: &mut [T]) -> T {
-        if self.size != v_in.len() || self.size != v_out.len() {
+    pub fn sv_hint(&self, fill: FillMode, diag: DiagType, ncalls: i32) -> Result<(), u32> {
+        debug_assert!(ncalls > 0);
+        let status = unsafe {
+            sp::mkl_sparse_set_sv_hint(
+                self.sp_handle,
+                sp::sparse_operation_t_SPARSE_OPERATION_NON_TRANSPOSE,
+                triangular_descr(fill, diag),
+                ncalls,
+            )
+        };
+        if status != sp::sparse_status_t_SPARSE_STATUS_SUCCESS {
+            return Err(status);
+        }
+
+        let status = unsafe { sp::mkl_sparse_optimize(self.sp_handle) };
+        if status != sp::sparse_status_t_SPARSE_STATUS_SUCCESS {
+            return Err(status);
+        }
+        Ok(())
+    }
+
+    /// Solve $Ty = x$, where $T$ is the triangular part of this matrix selected by `fill`.
+    /// The entries outside of this part are ignored, and so is the stored diagonal if
+    /// `diag` is [`DiagType::Unit`].
+    ///
+    /// It calls `mkl_sparse_?_trsv`, and panics if the dimensions don't match.
+    pub fn triangular_solve(
+        &self,
+        fill: FillMode,
+        diag: DiagType,
+        x: &[T],
+        y: &mut [T],
+    ) -> Result<(), u32> {
+        if self.size != x.len() || self.size != y.len() {
             panic!("Dimension mismatch");
         }
-        unsafe { self.mul_vec_dot_unchecked(v_in, v_out) }
+        let descr = triangular_descr(fill, diag);
+        macro_rules! sparse_trsv {
+            ($ty:ty, $func:ident, $one:expr, {$( $complex:ident )?}) => {
+                if super::same_type::<T, $ty>() {
+                    let status = unsafe {
+                        sp::$func(
+                            sp::sparse_operation_t_SPARSE_OPERATION_NON_TRANSPOSE,
+                            $one,
+                            self.sp_handle,
+                            descr,
+                            x.as_ptr() as *const $ty $(as *const mkl_sys::$complex)?,
+                            y.as_mut_ptr() as *mut $ty $(as *mut mkl_sys::$complex)?,
+                        )
+                    };
+                    if status != sp::sparse_status_t_SPARSE_STATUS_SUCCESS {
+                        return Err(status);
+                    }
+                    return Ok(());
+                }
+            };
+        }
+        sparse_trsv! {f32, mkl_sparse_s_trsv, 1., {}};
+        sparse_trsv! {f64, mkl_sparse_d_trsv, 1., {}};
+        sparse_trsv! {Complex32, mkl_sparse_c_trsv, COMPLEX32_ONE, {MKL_Complex8}};
+        sparse_trsv! {Complex64, mkl_sparse_z_trsv, COMPLEX64_ONE, {MKL_Complex16}};
+
+        unreachable!();
     }
 
-    unsafe fn mul_vec_unchecked(&self, v_in: &[T], v_out: &mut [T]) {
-        let descr = sp::matrix_descr {
-            type_: sp::sparse_matrix_type_t_SPARSE_MATRIX_TYPE_GENERAL,
-            mode: sp::sparse_fill_mode_t_SPARSE_FILL_MODE_FULL,
-            diag: sp::sparse_diag_type_t_SPARSE_DIAG_NON_UNIT,
+    /// Compute $X = AB$ for the dense blocks `b` and `x` of `n_cols` columns, both stored in
+    /// the order `layout`.
+    ///
+    /// It multiplies the whole block with one call of `mkl_sparse_?_mm`, which is much faster
+    /// than a product per column, and panics if the dimensions don't match.
+    pub fn mul_mat(&self, b: &[T], x: &mut [T], n_cols: usize, layout: Layout) -> Result<(), u32> {
+        if self.size * n_cols != b.len() || b.len() != x.len() {
+            panic!("Dimension mismatch");
+        }
+        if n_cols == 0 {
+            return Ok(());
+        }
+        let ld = match layout {
+            Layout::RowMajor => n_cols,
+            Layout::ColMajor => self.size,
+        } as c_int;
+        let (descr, layout) = (self.descr, sparse_layout(layout));
+        macro_rules! sparse_mm {
+            ($ty:ty, $func:ident, $one:expr, $zero:expr, {$( $complex:ident )?}) => {
+                if super::same_type::<T, $ty>() {
+                    let status = unsafe {
+                        sp::$func(
+                            sp::sparse_operation_t_SPARSE_OPERATION_NON_TRANSPOSE,
+                            $one,
+                            self.sp_handle,
+                            descr,
+                            layout,
+                            b.as_ptr() as *const $ty $(as *const mkl_sys::$complex)?,
+                            n_cols as c_int,
+                            ld,
+                            $zero,
+                            x.as_mut_ptr() as *mut $ty $(as *mut mkl_sys::$complex)?,
+                            ld,
+                        )
+                    };
+                    if status != sp::sparse_status_t_SPARSE_STATUS_SUCCESS {
+                        return Err(status);
+                    }
+                    return Ok(());
+                }
+            };
+        }
+        sparse_mm! {f32, mkl_sparse_s_mm, 1., 0., {}};
+        sparse_mm! {f64, mkl_sparse_d_mm, 1., 0., {}};
+        sparse_mm! {Complex32, mkl_sparse_c_mm, COMPLEX32_ONE, COMPLEX32_ZERO, {MKL_Complex8}};
+        sparse_mm! {Complex64, mkl_sparse_z_mm, COMPLEX64_ONE, COMPLEX64_ZERO, {MKL_Complex16}};
+
+        unreachable!();
+    }
+
+    /// Compute $A + \alpha B$, where $A$ is this matrix and $B$ is `other`, e.g. $K + \sigma M$.
+    ///
+    /// It calls `mkl_sparse_?_add`, and copies the result into a new general matrix in the
+    /// CSR format, which owns its storage and hence outlives `self` and `other`.
+    ///
+    /// MKL requires both matrices to have the same size, the same scalar type, the same index
+    /// base and the same storage (CSR or CSC). The type is enforced by `T`, and the index base
+    /// is always zero, as both are built from a [`CsMatI`] with `i32` indices; a size or
+    /// storage mismatch gives an MKL error code. Only the stored entries are added, so a
+    /// matrix created with [`new_symmetric`](Self::new_symmetric) contributes only its stored
+    /// triangle.
+    pub fn add(&self, other: &MklMat<T>, alpha: T) -> Result<MklMat<T>, u32> {
+        if self.size != other.size {
+            return Err(sp::sparse_status_t_SPARSE_STATUS_INVALID_VALUE);
+        }
+        let mut sum = OwnedHandle(std::ptr::null_mut());
+        macro_rules! sparse_add {
+            ($ty:ty, $func:ident, $alpha:expr) => {
+                if super::same_type::<T, $ty>() {
+                    // MKL computes C = alpha * op(A) + B
+                    let status = unsafe {
+                        sp::$func(
+                            sp::sparse_operation_t_SPARSE_OPERATION_NON_TRANSPOSE,
+                            other.sp_handle,
+                            $alpha,
+                            self.sp_handle,
+                            &mut sum.0 as *mut sp::sparse_matrix_t,
+                        )
+                    };
+                    if status != sp::sparse_status_t_SPARSE_STATUS_SUCCESS {
+                        return Err(status);
+                    }
+                    return Self::from_handle(&sum);
+                }
+            };
+        }
+        sparse_add! {f32, mkl_sparse_s_add, super::cast_as::<T, f32>(&alpha)};
+        sparse_add! {f64, mkl_sparse_d_add, super::cast_as::<T, f64>(&alpha)};
+        sparse_add! {Complex32, mkl_sparse_c_add, mkl_complex8(super::cast_as(&alpha))};
+        sparse_add! {Complex64, mkl_sparse_z_add, mkl_complex16(super::cast_as(&alpha))};
+
+        unreachable!();
+    }
+
+    /// Compute the product $AB$, where $A$ is this matrix and $B$ is `other`.
+    ///
+    /// It calls `mkl_sparse_spmm`, and copies the result into a new general matrix in the
+    /// CSR format, like [`add`](Self::add), with the same requirements on the two matrices.
+    pub fn spmm(&self, other: &MklMat<T>) -> Result<MklMat<T>, u32> {
+        if self.size != other.size {
+            return Err(sp::sparse_status_t_SPARSE_STATUS_INVALID_VALUE);
+        }
+        let mut prod = OwnedHandle(std::ptr::null_mut());
+        let status = unsafe {
+            sp::mkl_sparse_spmm(
+                sp::sparse_operation_t_SPARSE_OPERATION_NON_TRANSPOSE,
+                self.sp_handle,
+                other.sp_handle,
+                &mut prod.0 as *mut sp::sparse_matrix_t,
+            )
+        };
+        if status != sp::sparse_status_t_SPARSE_STATUS_SUCCESS {
+            return Err(status);
+        }
+        Self::from_handle(&prod)
+    }
+
+    /// Copy the stored entries of this matrix into a [`CsMatI`] in the CSR format, e.g. to
+    /// inspect or serialize the result of [`add`](Self::add) or [`spmm`](Self::spmm).
+    ///
+    /// It reads the arrays of the MKL handle with `mkl_sparse_?_export_csr`, converting a
+    /// matrix stored in the CSC format first. As MKL stores it, a matrix created with
+    /// [`new_symmetric`](Self::new_symmetric) gives only its stored triangle, and one
+    /// created with a unit diagonal keeps its stored diagonal. It panics if an MKL call
+    /// fails.
+    pub fn to_csmat(&self) -> CsMatI<T, i32> {
+        match Self::export_csr(self.sp_handle) {
+            Ok(m) => m,
+            Err(status) => panic!("MKL sparse matrix export failed. Code = {}", status),
+        }
+    }
+
+    /// Copy the matrix of the MKL handle `src` into the arrays of a new general matrix.
+    fn from_handle(src: &OwnedHandle) -> Result<MklMat<T>, u32> {
+        Self::new(Self::export_csr(src.0)?)
+    }
+
+    /// Copy the matrix of the MKL handle `src` into a [`CsMatI`] in the CSR format.
+    fn export_csr(src: sp::sparse_matrix_t) -> Result<CsMatI<T, i32>, u32> {
+        // sprs requires the CSR format with sorted column indices
+        let mut csr = OwnedHandle(std::ptr::null_mut());
+        let status = unsafe {
+            sp::mkl_sparse_convert_csr(
+                src,
+                sp::sparse_operation_t_SPARSE_OPERATION_NON_TRANSPOSE,
+                &mut csr.0 as *mut sp::sparse_matrix_t,
+            )
         };
+        if status != sp::sparse_status_t_SPARSE_STATUS_SUCCESS {
+            return Err(status);
+        }
+        let status = unsafe { sp::mkl_sparse_order(csr.0) };
+        if status != sp::sparse_status_t_SPARSE_STATUS_SUCCESS {
+            return Err(status);
+        }
+
+        let mut base: sp::sparse_index_base_t = sp::sparse_index_base_t_SPARSE_INDEX_BASE_ZERO;
+        let (mut rows, mut cols): (c_int, c_int) = (0, 0);
+        let mut rows_start: *mut c_int = std::ptr::null_mut();
+        let mut rows_end: *mut c_int = std::ptr::null_mut();
+        let mut col_indx: *mut c_int = std::ptr::null_mut();
+        let mut values: *const T = std::ptr::null();
+        macro_rules! export {
+            ($ty:ty, $func:ident, $ptr:ty) => {
+                if super::same_type::<T, $ty>() {
+                    let mut ptr: *mut $ptr = std::ptr::null_mut();
+                    let status = unsafe {
+                        sp::$func(
+                            csr.0,
+                            &mut base,
+                            &mut rows,
+                            &mut cols,
+                            &mut rows_start,
+                            &mut rows_end,
+                            &mut col_indx,
+                            &mut ptr,
+                        )
+                    };
+                    if status != sp::sparse_status_t_SPARSE_STATUS_SUCCESS {
+                        return Err(status);
+                    }
+                    values = ptr as *const T;
+                }
+            };
+        }
+        export! {f32, mkl_sparse_s_export_csr, f32};
+        export! {f64, mkl_sparse_d_export_csr, f64};
+        export! {Complex32, mkl_sparse_c_export_csr, mkl_sys::MKL_Complex8};
+        export! {Complex64, mkl_sparse_z_export_csr, mkl_sys::MKL_Complex16};
+        debug_assert!(!values.is_null());
+
+        // the arrays belong to the handle, copy them before it is destroyed
+        let offset = base as c_int;
+        let mut indptr = Vec::with_capacity(rows as usize + 1);
+        let mut indices = Vec::new();
+        let mut data = Vec::new();
+        indptr.push(0);
+        for i in 0..rows as usize {
+            let (st, ed) = unsafe { (*rows_start.add(i) - offset, *rows_end.add(i) - offset) };
+            for k in st as usize..ed as usize {
+                unsafe {
+                    indices.push(*col_indx.add(k) - offset);
+                    data.push(*values.add(k));
+                }
+            }
+            indptr.push(indices.len() as i32);
+        }
+        Ok(CsMatI::new(
+            (rows as usize, cols as usize),
+            indptr,
+            indices,
+            data,
+        ))
+    }
+
+    /// Multiply this matrix with `v_in` and put the results in `v_out`, like
+    /// [`mul_vec`](MatVecMul::mul_vec), but return the MKL status code instead of panicking.
+    ///
+    /// A dimension mismatch gives `SPARSE_STATUS_INVALID_VALUE`.
+    pub fn try_mul_vec(&self, v_in: &[T], v_out: &mut [T]) -> Result<(), u32> {
+        if self.size != v_in.len() || self.size != v_out.len() {
+            return Err(sp::sparse_status_t_SPARSE_STATUS_INVALID_VALUE);
+        }
+        unsafe { self.mv_unchecked(NON_TRANSPOSE, T::one(), v_in, T::zero(), v_out) }
+    }
+
+    /// The same as [`mul_vec_dot`](MatVecMul::mul_vec_dot), but return the MKL status code
+    /// instead of panicking.
+    ///
+    /// A dimension mismatch gives `SPARSE_STATUS_INVALID_VALUE`.
+    pub fn try_mul_vec_dot(&self, v_in: &[T], v_out: &mut [T]) -> Result<T, u32> {
+        if self.size != v_in.len() || self.size != v_out.len() {
+            return Err(sp::sparse_status_t_SPARSE_STATUS_INVALID_VALUE);
+        }
+        unsafe { self.dotmv_unchecked(v_in, v_out) }
+    }
+
+    /// Compute `v_out = alpha * op(A) * v_in + beta * v_out` with `mkl_sparse_?_mv`, and
+    /// return the MKL status code on failure.
+    unsafe fn mv_unchecked(
+        &self,
+        op: sp::sparse_operation_t,
+        alpha: T,
+        v_in: &[T],
+        beta: T,
+        v_out: &mut [T],
+    ) -> Result<(), u32> {
+        let descr = self.descr;
         macro_rules! sparse_mv {
             ($ty:ty, $func:ident) => {
                 if super::same_type::<T, $ty>() {
                     let status = sp::$func(
-                        sp::sparse_operation_t_SPARSE_OPERATION_NON_TRANSPOSE,
-                        1.,
+                        op,
+                        super::cast_as::<T, $ty>(&alpha),
                         self.sp_handle,
                         descr,
                         v_in.as_ptr() as *const $ty,
-                        0.,
+                        super::cast_as::<T, $ty>(&beta),
                         v_out.as_mut_ptr() as *mut $ty,
                     );
                     if status != sp::sparse_status_t_SPARSE_STATUS_SUCCESS {
-                        panic!(format!(
-                            "Cannot destroy MKL sparse matrix. Code = {}",
-                            status
-                        ));
+                        return Err(status);
                     }
-                    return;
+                    return Ok(());
                 }
             };
         }
@@ -200,51 +654,42 @@ impl<T: Scalar> MatVecMul<T> for MklMat<T> {
 
         if super::same_type::<T, Complex32>() {
             let status = sp::mkl_sparse_c_mv(
-                sp::sparse_operation_t_SPARSE_OPERATION_NON_TRANSPOSE,
-                COMPLEX32_ONE,
+                op,
+                mkl_complex8(super::cast_as(&alpha)),
                 self.sp_handle,
                 descr,
                 v_in.as_ptr() as *const Complex32 as *const mkl_sys::MKL_Complex8,
-                COMPLEX32_ZERO,
+                mkl_complex8(super::cast_as(&beta)),
                 v_out.as_mut_ptr() as *mut Complex32 as *mut mkl_sys::MKL_Complex8,
             );
             if status != sp::sparse_status_t_SPARSE_STATUS_SUCCESS {
-                panic!(format!(
-                    "Cannot destroy MKL sparse matrix. Code = {}",
-                    status
-                ));
+                return Err(status);
             }
-            return;
+            return Ok(());
         }
 
         if super::same_type::<T, Complex64>() {
             let status = sp::mkl_sparse_z_mv(
-                sp::sparse_operation_t_SPARSE_OPERATION_NON_TRANSPOSE,
-                COMPLEX64_ONE,
+                op,
+                mkl_complex16(super::cast_as(&alpha)),
                 self.sp_handle,
                 descr,
                 v_in.as_ptr() as *const Complex64 as *const mkl_sys::MKL_Complex16,
-                COMPLEX64_ZERO,
+                mkl_complex16(super::cast_as(&beta)),
                 v_out.as_mut_ptr() as *mut Complex64 as *mut mkl_sys::MKL_Complex16,
             );
             if status != sp::sparse_status_t_SPARSE_STATUS_SUCCESS {
-                panic!(format!(
-                    "Cannot destroy MKL sparse matrix. Code = {}",
-                    status
-                ));
+                return Err(status);
             }
-            return;
+            return Ok(());
         }
         unreachable!();
     }
 
-    #[inline]
-    unsafe fn mul_vec_dot_unchecked(&self, v_in: &[T], v_out: &mut [T]) -> T {
-        let descr = sp::matrix_descr {
-            type_: sp::sparse_matrix_type_t_SPARSE_MATRIX_TYPE_GENERAL,
-            mode: sp::sparse_fill_mode_t_SPARSE_FILL_MODE_FULL,
-            diag: sp::sparse_diag_type_t_SPARSE_DIAG_NON_UNIT,
-        };
+    /// Compute `v_out = A * v_in` and `conj(v_in).dot(v_out)` with `mkl_sparse_?_dotmv`, and
+    /// return the MKL status code on failure.
+    unsafe fn dotmv_unchecked(&self, v_in: &[T], v_out: &mut [T]) -> Result<T, u32> {
+        let descr = self.descr;
 
         macro_rules! sparse_dotmv {
             ($ty:ty, $func:ident) => {
@@ -261,12 +706,9 @@ impl<T: Scalar> MatVecMul<T> for MklMat<T> {
                         &mut d as *mut $ty,
                     );
                     if status != sp::sparse_status_t_SPARSE_STATUS_SUCCESS {
-                        panic!(format!(
-                            "Cannot destroy MKL sparse matrix. Code = {}",
-                            status
-                        ));
+                        return Err(status);
                     }
-                    return super::cast_as(&d);
+                    return Ok(super::cast_as(&d));
                 }
             };
         }
@@ -286,12 +728,9 @@ impl<T: Scalar> MatVecMul<T> for MklMat<T> {
                 &mut d as *mut T as *mut mkl_sys::MKL_Complex8,
             );
             if status != sp::sparse_status_t_SPARSE_STATUS_SUCCESS {
-                panic!(format!(
-                    "Cannot destroy MKL sparse matrix. Code = {}",
-                    status
-                ));
+                return Err(status);
             }
-            return super::cast_as(&d);
+            return Ok(super::cast_as(&d));
         }
 
         if super::same_type::<T, Complex64>() {
@@ -307,27 +746,108 @@ impl<T: Scalar> MatVecMul<T> for MklMat<T> {
                 &mut d as *mut T as *mut mkl_sys::MKL_Complex16,
             );
             if status != sp::sparse_status_t_SPARSE_STATUS_SUCCESS {
-                panic!(format!(
-                    "Cannot destroy MKL sparse matrix. Code = {}",
-                    status
-                ));
+                return Err(status);
             }
-            return super::cast_as(&d);
+            return Ok(super::cast_as(&d));
         }
 
         unreachable!();
     }
 }
 
+impl<T: Scalar> MatVecMul<T> for MklMat<T> {
+    #[inline]
+    fn shape(&self) -> Option<(usize, usize)> {
+        Some((self.size, self.size))
+    }
+
+    #[inline]
+    fn mul_vec(&self, v_in: &[T], v_out: &mut [T]) {
+        if self.size != v_in.len() || self.size != v_out.len() {
+            panic!("Dimension mismatch");
+        }
+        unsafe {
+            self.mul_vec_unchecked(v_in, v_out);
+        }
+    }
+
+    #[inline]
+    fn mul_vec_dot(&self, v_in: &[T], v_out: &mut [T]) -> T {
+        if self.size != v_in.len() || self.size != v_out.len() {
+            panic!("Dimension mismatch");
+        }
+        unsafe { self.mul_vec_dot_unchecked(v_in, v_out) }
+    }
+
+    #[inline]
+    unsafe fn mul_vec_unchecked(&self, v_in: &[T], v_out: &mut [T]) {
+        if let Err(status) = self.mv_unchecked(NON_TRANSPOSE, T::one(), v_in, T::zero(), v_out) {
+            panic!("MKL sparse matrix-vector product failed. Code = {}", status);
+        }
+    }
+
+    /// Pass `alpha` and `beta` straight to `mkl_sparse_?_mv`.
+    fn mul_vec_add(&self, alpha: T, x: &[T], beta: T, y: &mut [T]) {
+        if self.size != x.len() || self.size != y.len() {
+            panic!("Dimension mismatch");
+        }
+        if let Err(status) = unsafe { self.mv_unchecked(NON_TRANSPOSE, alpha, x, beta, y) } {
+            panic!("MKL sparse matrix-vector product failed. Code = {}", status);
+        }
+    }
+
+    #[inline]
+    unsafe fn mul_vec_dot_unchecked(&self, v_in: &[T], v_out: &mut [T]) -> T {
+        match self.dotmv_unchecked(v_in, v_out) {
+            Ok(d) => d,
+            Err(status) => panic!(
+                "MKL sparse matrix-vector product with dot failed. Code = {}",
+                status
+            ),
+        }
+    }
+
+    /// Compute $A^T v$ with `mkl_sparse_?_mv` and `SPARSE_OPERATION_TRANSPOSE`, which doesn't
+    /// conjugate complex entries, and return the MKL status code as
+    /// [`SolverError::InvalidParameter`] on failure.
+    fn mul_vec_trans(&self, v_in: &[T], v_out: &mut [T]) -> SolveResult<()> {
+        if self.size != v_in.len() || self.size != v_out.len() {
+            panic!("Dimension mismatch");
+        }
+        let op = sp::sparse_operation_t_SPARSE_OPERATION_TRANSPOSE;
+        unsafe { self.mv_unchecked(op, T::one(), v_in, T::zero(), v_out) }.map_err(|status| {
+            SolverError::InvalidParameter(format!(
+                "MKL sparse transposed matrix-vector product failed. Code = {}",
+                status
+            ))
+        })
+    }
+
+    /// Read the diagonal from the CSR (or CSC) arrays kept alongside the MKL handle.
+    fn diagonal(&self, out: &mut [T]) {
+        if self.size != out.len() {
+            panic!("Dimension mismatch");
+        }
+        if self.descr.diag == sp::sparse_diag_type_t_SPARSE_DIAG_UNIT {
+            out.iter_mut().for_each(|d| *d = T::one());
+            return;
+        }
+        for (i, (rng, d)) in self._indptr.windows(2).zip(out.iter_mut()).enumerate() {
+            let (st, ed) = (rng[0] as usize, rng[1] as usize);
+            *d = self._indices[st..ed]
+                .iter()
+                .position(|&j| j as usize == i)
+                .map_or_else(T::zero, |k| self._data[st + k]);
+        }
+    }
+}
+
 impl<T: Scalar> Drop for MklMat<T> {
     fn drop(&mut self) {
         // NOTE: Here we may need to ensure the handle sp_handle is dropped first
         let status = unsafe { sp::mkl_sparse_destroy(self.sp_handle) };
         if status != sp::sparse_status_t_SPARSE_STATUS_SUCCESS {
-            panic!(format!(
-                "Cannot destroy MKL sparse matrix. Code = {}",
-                status
-            ));
+            panic!("Cannot destroy MKL sparse matrix. Code = {}", status);
         }
     }
 }
@@ -364,6 +884,279 @@ mod tests {
             .all(|(x, y)| (*x - *y).abs() < epsilon));
     }
 
+    #[test]
+    fn mkl_mat_csc() {
+        let indptr: Vec<i32> = vec![0, 3, 3, 5, 6, 7];
+        let indices: Vec<i32> = vec![1, 2, 3, 2, 3, 4, 4];
+        let data = vec![0.7, 0.1, 0.3, 0.2, 0.6, 0.4, 0.5];
+        let csr = CsMatI::new((5, 5), indptr, indices, data);
+        let csc = MklMat::new_csc(csr.to_csc()).unwrap();
+        let csr = MklMat::new(csr).unwrap();
+        assert_eq!(csc.nnz(), csr.nnz());
+
+        let vector = vec![0.1, 0.2, -0.1, 0.3, 0.9];
+        let mut expected = vec![0.; 5];
+        let mut res_vec = vec![0.; 5];
+        csr.mul_vec(&vector, &mut expected);
+        csc.mul_vec(&vector, &mut res_vec);
+        for (u, v) in res_vec.iter().zip(expected.iter()) {
+            approx::assert_abs_diff_eq!(u, v, epsilon = 1e-14);
+        }
+        let d0 = csr.mul_vec_dot(&vector, &mut expected);
+        let d1 = csc.mul_vec_dot(&vector, &mut res_vec);
+        approx::assert_abs_diff_eq!(d0, d1, epsilon = 1e-14);
+
+        csc.diagonal(&mut res_vec);
+        assert_eq!(res_vec, vec![0., 0., 0.2, 0., 0.5]);
+    }
+
+    #[test]
+    fn mkl_mat_mul_mat() {
+        let indptr: Vec<i32> = vec![0, 3, 3, 5, 6, 7];
+        let indices: Vec<i32> = vec![1, 2, 3, 2, 3, 4, 4];
+        let data = vec![0.7, 0.1, 0.3, 0.2, 0.6, 0.4, 0.5];
+        let mkl_mat = MklMat::new(CsMatI::new((5, 5), indptr, indices, data)).unwrap();
+        mkl_mat.mm_hint(Layout::ColMajor, 3, 10).unwrap();
+
+        // column-major 5 x 3 block
+        let b: Vec<f64> = (0..15).map(|k| 0.1 * k as f64 - 0.5).collect();
+        let mut x = vec![0.; 15];
+        mkl_mat.mul_mat(&b, &mut x, 3, Layout::ColMajor).unwrap();
+        let mut expected = [0.; 15];
+        for (bc, ec) in b.chunks(5).zip(expected.chunks_mut(5)) {
+            mkl_mat.mul_vec(bc, ec);
+        }
+        for (u, v) in x.iter().zip(expected.iter()) {
+            approx::assert_abs_diff_eq!(u, v, epsilon = 1e-14);
+        }
+
+        // the same block stored row by row
+        let b_row: Vec<f64> = (0..15).map(|k| b[(k % 3) * 5 + k / 3]).collect();
+        let mut x_row = vec![0.; 15];
+        mkl_mat
+            .mul_mat(&b_row, &mut x_row, 3, Layout::RowMajor)
+            .unwrap();
+        for k in 0..15 {
+            approx::assert_abs_diff_eq!(x_row[k], expected[(k % 3) * 5 + k / 3], epsilon = 1e-14);
+        }
+    }
+
+    /// The dense row-major copy of `m`, read from its CSR arrays.
+    fn dense(m: &MklMat<f64>) -> Vec<f64> {
+        let n = m.size();
+        let mut d = vec![0.; n * n];
+        for (i, rng) in m._indptr.windows(2).enumerate() {
+            for k in rng[0] as usize..rng[1] as usize {
+                d[i * n + m._indices[k] as usize] += m._data[k];
+            }
+        }
+        d
+    }
+
+    #[test]
+    fn mkl_mat_add_spmm() {
+        let n = 6;
+        let mut ta = sprs::TriMatI::<f64, i32>::new((n, n));
+        let mut tb = sprs::TriMatI::<f64, i32>::new((n, n));
+        for i in 0..n {
+            for j in 0..n {
+                if (i + 2 * j) % 4 == 0 || i == j {
+                    ta.add_triplet(i, j, 1. + (i * n + j) as f64 * 0.1);
+                }
+                if (2 * i + j) % 3 == 1 {
+                    tb.add_triplet(i, j, 0.5 - (i + j) as f64 * 0.2);
+                }
+            }
+        }
+        let (a, b): (CsMatI<f64, i32>, CsMatI<f64, i32>) = (ta.to_csr(), tb.to_csr());
+        let sigma = -0.75;
+        let sum_ref = &a + &b.map(|v| sigma * v);
+        let prod_ref = &a * &b;
+
+        let (ma, mb) = (MklMat::new(a).unwrap(), MklMat::new(b).unwrap());
+        let sum = ma.add(&mb, sigma).unwrap();
+        let prod = ma.spmm(&mb).unwrap();
+        // the results own their storage
+        drop(ma);
+        drop(mb);
+
+        for (m, r) in [(&sum, &sum_ref), (&prod, &prod_ref)].iter() {
+            let d = dense(m);
+            for i in 0..n {
+                for j in 0..n {
+                    let v = r.get(i, j).copied().unwrap_or(0.);
+                    approx::assert_abs_diff_eq!(d[i * n + j], v, epsilon = 1e-12);
+                }
+            }
+            let x: Vec<f64> = (0..n).map(|i| 0.3 * i as f64 - 0.5).collect();
+            let (mut y, mut y_ref) = (vec![0.; n], vec![0.; n]);
+            m.mul_vec(&x, &mut y);
+            r.mul_vec(&x, &mut y_ref);
+            for (u, v) in y.iter().zip(y_ref.iter()) {
+                approx::assert_abs_diff_eq!(u, v, epsilon = 1e-12);
+            }
+        }
+
+        let small = MklMat::new(CsMatI::<f64, i32>::eye(3)).unwrap();
+        assert!(sum.add(&small, 1.).is_err());
+        assert!(sum.spmm(&small).is_err());
+    }
+
+    #[test]
+    fn mkl_mat_to_csmat() {
+        let indptr: Vec<i32> = vec![0, 3, 3, 5, 6, 7];
+        let indices: Vec<i32> = vec![1, 2, 3, 2, 3, 4, 4];
+        let data = vec![0.7, 0.1, 0.3, 0.2, 0.6, 0.4, 0.5];
+        let csr = CsMatI::new((5, 5), indptr, indices, data);
+        let back = MklMat::new(csr.clone()).unwrap().to_csmat();
+        assert_eq!(back, csr);
+        // a matrix stored in the CSC format comes back in CSR
+        let back = MklMat::new_csc(csr.to_csc()).unwrap().to_csmat();
+        assert_eq!(back, csr);
+
+        let mut tri = sprs::TriMatI::<Complex64, i32>::new((4, 4));
+        for i in 0..4 {
+            tri.add_triplet(i, i, Complex64::new(2., i as f64));
+            tri.add_triplet(i, (i + 1) % 4, Complex64::new(-0.5, 1.));
+        }
+        let z: CsMatI<Complex64, i32> = tri.to_csr();
+        let mz = MklMat::new(z.clone()).unwrap();
+        assert_eq!(mz.to_csmat(), z);
+
+        // a product computed by MKL, against the product of the exported matrix
+        let square = mz.spmm(&mz).unwrap().to_csmat();
+        let square_ref = &z * &z;
+        let (square, square_ref) = (square.to_dense(), square_ref.to_dense());
+        for (u, v) in square.iter().zip(square_ref.iter()) {
+            approx::assert_abs_diff_eq!(u.re, v.re, epsilon = 1e-12);
+            approx::assert_abs_diff_eq!(u.im, v.im, epsilon = 1e-12);
+        }
+    }
+
+    #[test]
+    fn mkl_mat_diagonal() {
+        let indptr: Vec<i32> = vec![0, 3, 3, 5, 6, 7];
+        let indices: Vec<i32> = vec![1, 2, 3, 2, 3, 4, 4];
+        let data = vec![0.7, 0.1, 0.3, 0.2, 0.6, 0.4, 0.5];
+        let mkl_mat = MklMat::new(CsMatI::new((5, 5), indptr, indices, data)).unwrap();
+
+        let mut diag = vec![1.; 5];
+        mkl_mat.diagonal(&mut diag);
+        assert_eq!(diag, vec![0., 0., 0.2, 0., 0.5]);
+    }
+
+    #[test]
+    fn mkl_triangular_solve() {
+        // L = [1 0 0; 2 1 0; -1 3 1], stored with a non-unit diagonal and an upper entry
+        let indptr: Vec<i32> = vec![0, 2, 4, 7];
+        let indices: Vec<i32> = vec![0, 2, 0, 1, 0, 1, 2];
+        let data = vec![5., 9., 2., 5., -1., 3., 5.];
+        let mkl_mat = MklMat::new(CsMatI::new((3, 3), indptr, indices, data)).unwrap();
+        mkl_mat
+            .sv_hint(FillMode::Lower, DiagType::Unit, 10)
+            .unwrap();
+
+        let x = vec![1., 4., 2.];
+        let mut y = vec![0.; 3];
+        mkl_mat
+            .triangular_solve(FillMode::Lower, DiagType::Unit, &x, &mut y)
+            .unwrap();
+        assert_eq!(y, vec![1., 2., -3.]);
+    }
+
+    #[test]
+    fn mkl_mat_symmetric() {
+        let mut full = sprs::TriMatI::<f64, i32>::new((5, 5));
+        let mut upper = sprs::TriMatI::<f64, i32>::new((5, 5));
+        for i in 0..5 {
+            for j in i..5 {
+                if (i + j) % 3 == 0 || i == j {
+                    let v = 1. + (i * 5 + j) as f64 * 0.1;
+                    full.add_triplet(i, j, v);
+                    upper.add_triplet(i, j, v);
+                    if i != j {
+                        full.add_triplet(j, i, v);
+                    }
+                }
+            }
+        }
+        let general = MklMat::new(full.to_csr()).unwrap();
+        let sym = MklMat::new_symmetric(upper.to_csr(), FillMode::Upper).unwrap();
+        sym.mv_and_dotmv_hint(10).unwrap();
+
+        let vector = vec![0.1, 0.2, -0.1, 0.3, 0.9];
+        let mut expected = vec![0.; 5];
+        let mut res_vec = vec![0.; 5];
+        general.mul_vec(&vector, &mut expected);
+        sym.mul_vec(&vector, &mut res_vec);
+        for (u, v) in res_vec.iter().zip(expected.iter()) {
+            approx::assert_abs_diff_eq!(u, v, epsilon = 1e-14);
+        }
+        let d0 = general.mul_vec_dot(&vector, &mut expected);
+        let d1 = sym.mul_vec_dot(&vector, &mut res_vec);
+        approx::assert_abs_diff_eq!(d0, d1, epsilon = 1e-14);
+    }
+
+    #[test]
+    fn mkl_mat_hermitian() {
+        let mut full = sprs::TriMatI::<Complex64, i32>::new((4, 4));
+        let mut lower = sprs::TriMatI::<Complex64, i32>::new((4, 4));
+        for i in 0..4 {
+            full.add_triplet(i, i, Complex64::new(2. + i as f64, 0.));
+            lower.add_triplet(i, i, Complex64::new(2. + i as f64, 0.));
+            if i > 0 {
+                let v = Complex64::new(-1., 0.5 * i as f64);
+                full.add_triplet(i, i - 1, v);
+                full.add_triplet(i - 1, i, v.conj());
+                lower.add_triplet(i, i - 1, v);
+            }
+        }
+        let general = MklMat::new(full.to_csr()).unwrap();
+        let herm = MklMat::new_hermitian(lower.to_csr(), FillMode::Lower).unwrap();
+
+        let vector: Vec<Complex64> = (0..4)
+            .map(|i| Complex64::new(0.1, -0.2 * i as f64))
+            .collect();
+        let mut expected: Vec<Complex64> = vec![Default::default(); 4];
+        let mut res_vec: Vec<Complex64> = vec![Default::default(); 4];
+        general.mul_vec(&vector, &mut expected);
+        herm.mul_vec(&vector, &mut res_vec);
+        for (u, v) in res_vec.iter().zip(expected.iter()) {
+            approx::assert_abs_diff_eq!(u.re, v.re, epsilon = 1e-14);
+            approx::assert_abs_diff_eq!(u.im, v.im, epsilon = 1e-14);
+        }
+    }
+
+    #[test]
+    fn mkl_mat_try_mul_vec() {
+        let indptr: Vec<i32> = vec![0, 3, 3, 5, 6, 7];
+        let indices: Vec<i32> = vec![1, 2, 3, 2, 3, 4, 4];
+        let data = vec![
+            0.75672424, 0.1649078, 0.30140296, 0.10358244, 0.6283315, 0.39244208, 0.57202407,
+        ];
+        let mkl_mat = MklMat::new(CsMatI::new((5, 5), indptr, indices, data)).unwrap();
+
+        let vector = vec![0.1, 0.2, -0.1, 0.3, 0.9];
+        let mut res_vec = vec![0.; 5];
+        assert_eq!(mkl_mat.try_mul_vec(&vector, &mut res_vec), Ok(()));
+        let mut expected = vec![0.; 5];
+        mkl_mat.mul_vec(&vector, &mut expected);
+        assert_eq!(res_vec, expected);
+        let d = mkl_mat.try_mul_vec_dot(&vector, &mut res_vec).unwrap();
+        approx::assert_abs_diff_eq!(d, mkl_mat.mul_vec_dot(&vector, &mut expected));
+
+        // mismatched vector length
+        let mut short = vec![0.; 4];
+        assert_eq!(
+            mkl_mat.try_mul_vec(&vector, &mut short),
+            Err(sp::sparse_status_t_SPARSE_STATUS_INVALID_VALUE)
+        );
+        assert_eq!(
+            mkl_mat.try_mul_vec_dot(&vector[..4], &mut res_vec),
+            Err(sp::sparse_status_t_SPARSE_STATUS_INVALID_VALUE)
+        );
+    }
+
     #[test]
     fn mkl_mat_vec_complex() {
         let indptr: Vec<i32> = vec![0, 3, 3, 5, 6, 7];
@@ -429,6 +1222,58 @@ mod tests {
             .all(|(x, y)| (*x - *y).abs() < epsilon));
     }
 
+    #[test]
+    fn mkl_mat_vec_add() {
+        let indptr: Vec<i32> = vec![0, 3, 5, 8, 11, 13];
+        let indices: Vec<i32> = vec![0, 1, 3, 0, 1, 2, 3, 4, 0, 2, 3, 1, 4];
+        let data = vec![
+            1.0, -1.0, -3.0, -2.0, 5.0, 4.0, 6.0, 4.0, -4.0, 2.0, 7.0, 8.0, -5.0,
+        ];
+        let mkl_mat = MklMat::new(CsMatI::new((5, 5), indptr, indices, data)).unwrap();
+
+        let vector = vec![1.0, 5.0, 1.0, 4.0, 1.0];
+        let mut res_vec = vec![1.0, 2.0, 3.0, 4.0, 5.0];
+        mkl_mat.mul_vec_add(2., &vector, -1., &mut res_vec);
+
+        let expected = [-33.0, 44.0, 61.0, 48.0, 65.0];
+        assert!(res_vec
+            .iter()
+            .zip(expected.iter())
+            .all(|(x, y)| (*x - *y).abs() < 1e-14));
+    }
+
+    #[test]
+    fn mkl_mat_vec_add_complex() {
+        let indptr: Vec<i32> = vec![0, 3, 3, 5, 6, 7];
+        let indices: Vec<i32> = vec![1, 2, 3, 2, 3, 4, 4];
+        let data = vec![
+            Complex64::new(0.75672424, 0.75672424),
+            Complex64::new(0.1649078, 0.1649078),
+            Complex64::new(0.30140296, 0.30140296),
+            Complex64::new(0.10358244, 0.10358244),
+            Complex64::new(0.6283315, 0.6283315),
+            Complex64::new(0.39244208, 0.39244208),
+            Complex64::new(0.57202407, 0.57202407),
+        ];
+        let mkl_mat = MklMat::new(CsMatI::new((5, 5), indptr, indices, data)).unwrap();
+
+        let vector: Vec<Complex64> = (0..5)
+            .map(|i| Complex64::new(0.1 * i as f64, 0.2))
+            .collect();
+        let mut ax: Vec<Complex64> = vec![Default::default(); 5];
+        mkl_mat.mul_vec(&vector, &mut ax);
+
+        let (alpha, beta) = (Complex64::new(0.5, 1.), Complex64::new(2., -1.));
+        let y0: Vec<Complex64> = (0..5).map(|i| Complex64::new(i as f64, 1.)).collect();
+        let mut res_vec = y0.clone();
+        mkl_mat.mul_vec_add(alpha, &vector, beta, &mut res_vec);
+        for i in 0..5 {
+            let exp = alpha * ax[i] + beta * y0[i];
+            approx::assert_abs_diff_eq!(res_vec[i].re, exp.re, epsilon = 1e-12);
+            approx::assert_abs_diff_eq!(res_vec[i].im, exp.im, epsilon = 1e-12);
+        }
+    }
+
     #[test]
     fn mkl_mat_vec_dot_complex() {
         let indptr: Vec<i32> = vec![0, 3, 3, 5, 6, 7];
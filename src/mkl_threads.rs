@@ -0,0 +1,48 @@
+//! The control of the number of threads used by MKL.
+
+use std::os::raw::c_int;
+
+extern "C" {
+    fn MKL_Set_Num_Threads(nt: c_int);
+    fn MKL_Get_Max_Threads() -> c_int;
+}
+
+/// Set the number of threads used by the MKL routines, e.g. the products of
+/// [`MklMat`](crate::MklMat) and the vector operations with the `mkl` feature.
+///
+/// It wraps `mkl_set_num_threads`, which takes precedence over the `MKL_NUM_THREADS` and
+/// `OMP_NUM_THREADS` environment variables. A value of zero is ignored by MKL. The setting
+/// is global to the process, so it is the place to pin one thread per MPI rank, or to make
+/// benchmarks reproducible.
+pub fn set_num_threads(n: usize) {
+    let n = n.min(c_int::MAX as usize) as c_int;
+    unsafe {
+        MKL_Set_Num_Threads(n);
+    }
+}
+
+/// The number of threads MKL uses for its parallel routines, as returned by
+/// `mkl_get_max_threads`.
+pub fn get_max_threads() -> usize {
+    unsafe { MKL_Get_Max_Threads() as usize }
+}
+
+/// Restores the thread number of MKL when dropped, even when unwinding from a panic.
+struct ThreadsGuard(usize);
+
+impl Drop for ThreadsGuard {
+    fn drop(&mut self) {
+        set_num_threads(self.0);
+    }
+}
+
+/// Run `f` with MKL using `n` threads, and restore the previous number of threads on
+/// return.
+///
+/// Like [`set_num_threads`], it changes a process-wide setting, so concurrent calls from
+/// different threads override each other.
+pub fn with_num_threads<R, F: FnOnce() -> R>(n: usize, f: F) -> R {
+    let _guard = ThreadsGuard(get_max_threads());
+    set_num_threads(n);
+    f()
+}
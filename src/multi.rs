@@ -0,0 +1,46 @@
+//! Drivers solving many right-hand sides with the same operator.
+
+use super::{error::*, BiCGStab, MatVecMul, Workspace};
+use cauchy::Scalar;
+use rayon::prelude::*;
+
+/// The solution, number of iterations and relative residual of one right-hand side.
+pub type MultiSolveResult<T> = SolveResult<(Vec<T>, usize, <T as Scalar>::Real)>;
+
+/// Solves $Ax_i = b_i$ for every right-hand side $b_i$ in `rhss` on the rayon thread pool.
+///
+/// Each right-hand side is solved independently with [`BiCGStab`], starting from a zero
+/// initial guess. The solver and the operator are shared by all the tasks, hence the operator
+/// must be `Sync`, and each task allocates its own [`Workspace`].
+///
+/// The returned vector has the same order as `rhss`. Each entry holds the solution, the
+/// number of iterations and the relative residual, or the error of that solve.
+#[allow(non_snake_case)]
+pub fn solve_multi_parallel<T, M>(
+    A: &M,
+    rhss: &[Vec<T>],
+    max_iter: usize,
+    tol: T::Real,
+) -> Vec<MultiSolveResult<T>>
+where
+    T: Scalar + Send + Sync,
+    T::Real: Send + Sync,
+    M: MatVecMul<T> + Sync,
+{
+    let n = rhss.first().map_or(0, |rhs| rhs.len());
+    let solver = BiCGStab::new(A, n);
+    rhss.par_iter()
+        .map(|rhs| {
+            let mut workspace = Workspace::new(BiCGStab::<T, M>::workspace_len(n));
+            let mut x = vec![T::zero(); rhs.len()];
+            let (its, res) = solver.solve_with_workspace(
+                &mut workspace,
+                rhs.as_slice(),
+                x.as_mut_slice(),
+                max_iter,
+                tol,
+            )?;
+            Ok((x, its, res))
+        })
+        .collect()
+}
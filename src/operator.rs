@@ -0,0 +1,573 @@
+//! Operators built on top of other [`MatVecMul`] operators.
+
+use super::{error::*, precond::Precond, vecalg::*, MatVecMul};
+use cauchy::Scalar;
+use num_traits::Zero;
+use std::{cell::RefCell, marker::PhantomData};
+
+/// The operator $PAP$ with the projector $P = I - ww^H / (w^Hw)$.
+///
+/// It deflates the operator `A` against the vector `w`, typically the null-space vector
+/// of a singular but consistent system (e.g. the constant vector of a pure-Neumann
+/// Laplacian). Solving $PAPx = Pb$ then gives the solution orthogonal to `w`.
+///
+/// $Aw$ is computed once at construction, so that each product takes only one product
+/// with `A`, and no extra memory.
+#[allow(non_snake_case)]
+pub struct DeflatedOp<'data, T: Scalar, M: MatVecMul<T>> {
+    A: &'data M,
+    /// normalized w
+    w: Vec<T>,
+    /// A * w
+    aw: Vec<T>,
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>> DeflatedOp<'data, T, M> {
+    #[allow(non_snake_case)]
+    pub fn new(A: &'data M, w: &[T]) -> SolveResult<Self> {
+        let mut w = w.to_vec();
+        if normalize(w.as_mut_slice()) == T::Real::zero() {
+            return Err(SolverError::InvalidParameter(String::from(
+                "The deflation vector must be nonzero",
+            )));
+        }
+        let mut aw = vec![T::zero(); w.len()];
+        A.mul_vec(w.as_slice(), aw.as_mut_slice());
+        Ok(DeflatedOp { A, w, aw })
+    }
+
+    /// Apply the projector in place: $v \leftarrow v - ww^Hv / (w^Hw)$.
+    ///
+    /// It is useful to make the right-hand side consistent before solving.
+    pub fn project(&self, v: &mut [T]) {
+        let c = conj_dot(self.w.as_slice(), &*v);
+        axpy(-c, self.w.as_slice(), v);
+    }
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>> MatVecMul<T> for DeflatedOp<'data, T, M> {
+    #[inline]
+    fn shape(&self) -> Option<(usize, usize)> {
+        Some((self.w.len(), self.w.len()))
+    }
+
+    #[inline]
+    fn mul_vec(&self, v_in: &[T], v_out: &mut [T]) {
+        if self.w.len() != v_in.len() || v_in.len() != v_out.len() {
+            panic!("Dimension mismatch");
+        }
+        unsafe {
+            self.mul_vec_unchecked(v_in, v_out);
+        }
+    }
+
+    #[inline]
+    fn mul_vec_dot(&self, v_in: &[T], v_out: &mut [T]) -> T {
+        if self.w.len() != v_in.len() || v_in.len() != v_out.len() {
+            panic!("Dimension mismatch");
+        }
+        unsafe { self.mul_vec_dot_unchecked(v_in, v_out) }
+    }
+
+    unsafe fn mul_vec_unchecked(&self, v_in: &[T], v_out: &mut [T]) {
+        // A P x = A x - (w^H x) A w
+        self.A.mul_vec_unchecked(v_in, v_out);
+        let c = conj_dot(self.w.as_slice(), v_in);
+        axpy(-c, self.aw.as_slice(), &mut *v_out);
+        // P A P x
+        self.project(v_out);
+    }
+
+    unsafe fn mul_vec_dot_unchecked(&self, v_in: &[T], v_out: &mut [T]) -> T {
+        self.mul_vec_unchecked(v_in, v_out);
+        conj_dot(v_in, &*v_out)
+    }
+}
+
+/// The shifted operator $A - \sigma I$.
+///
+/// It lets every solver run on $A - \sigma I$, e.g. in shift-invert eigenvalue iterations or
+/// for regularization, without forming a new matrix. Each product takes one product with
+/// `A` and one `axpy`.
+#[allow(non_snake_case)]
+pub struct Shifted<'data, T: Scalar, M: MatVecMul<T>> {
+    A: &'data M,
+    sigma: T,
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>> Shifted<'data, T, M> {
+    #[allow(non_snake_case)]
+    pub fn new(A: &'data M, sigma: T) -> Self {
+        Shifted { A, sigma }
+    }
+
+    /// The shift $\sigma$.
+    pub fn sigma(&self) -> T {
+        self.sigma
+    }
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>> MatVecMul<T> for Shifted<'data, T, M> {
+    #[inline]
+    fn shape(&self) -> Option<(usize, usize)> {
+        self.A.shape()
+    }
+
+    #[inline]
+    fn mul_vec(&self, v_in: &[T], v_out: &mut [T]) {
+        self.A.mul_vec(v_in, v_out);
+        axpy(-self.sigma, v_in, v_out);
+    }
+
+    #[inline]
+    fn mul_vec_dot(&self, v_in: &[T], v_out: &mut [T]) -> T {
+        // v^H (A - sigma I) v = v^H A v - sigma |v|^2
+        let d = self.A.mul_vec_dot(v_in, &mut *v_out);
+        axpy(-self.sigma, v_in, v_out);
+        d - self.sigma * T::from_real(norm2_sq(v_in))
+    }
+
+    unsafe fn mul_vec_unchecked(&self, v_in: &[T], v_out: &mut [T]) {
+        self.A.mul_vec_unchecked(v_in, &mut *v_out);
+        axpy(-self.sigma, v_in, v_out);
+    }
+
+    unsafe fn mul_vec_dot_unchecked(&self, v_in: &[T], v_out: &mut [T]) -> T {
+        let d = self.A.mul_vec_dot_unchecked(v_in, &mut *v_out);
+        axpy(-self.sigma, v_in, v_out);
+        d - self.sigma * T::from_real(norm2_sq(v_in))
+    }
+
+    fn mul_vec_trans(&self, v_in: &[T], v_out: &mut [T]) -> SolveResult<()> {
+        self.A.mul_vec_trans(v_in, &mut *v_out)?;
+        axpy(-self.sigma, v_in, v_out);
+        Ok(())
+    }
+
+    fn diagonal(&self, out: &mut [T]) {
+        self.A.diagonal(&mut *out);
+        out.iter_mut().for_each(|d| *d -= self.sigma);
+    }
+}
+
+/// The sum $L + R$ of two operators, applied without assembling it.
+///
+/// It holds a scratch vector for the product with `R`, hence it is not `Sync`. Combined with
+/// [`ScaledOp`], it builds linear combinations such as $\alpha K + \beta M$.
+pub struct SumOp<'data, T: Scalar, L: MatVecMul<T>, R: MatVecMul<T>> {
+    left: &'data L,
+    right: &'data R,
+    /// holds R * x
+    work: RefCell<Vec<T>>,
+}
+
+impl<'data, T: Scalar, L: MatVecMul<T>, R: MatVecMul<T>> SumOp<'data, T, L, R> {
+    /// Create the sum of `left` and `right`, which are both of size `size`.
+    pub fn new(left: &'data L, right: &'data R, size: usize) -> Self {
+        SumOp {
+            left,
+            right,
+            work: RefCell::new(vec![T::zero(); size]),
+        }
+    }
+}
+
+impl<'data, T: Scalar, L: MatVecMul<T>, R: MatVecMul<T>> MatVecMul<T> for SumOp<'data, T, L, R> {
+    #[inline]
+    fn shape(&self) -> Option<(usize, usize)> {
+        self.left.shape()
+    }
+
+    #[inline]
+    fn mul_vec(&self, v_in: &[T], v_out: &mut [T]) {
+        let n = self.work.borrow().len();
+        if n != v_in.len() || n != v_out.len() {
+            panic!("Dimension mismatch");
+        }
+        unsafe {
+            self.mul_vec_unchecked(v_in, v_out);
+        }
+    }
+
+    #[inline]
+    fn mul_vec_dot(&self, v_in: &[T], v_out: &mut [T]) -> T {
+        let n = self.work.borrow().len();
+        if n != v_in.len() || n != v_out.len() {
+            panic!("Dimension mismatch");
+        }
+        unsafe { self.mul_vec_dot_unchecked(v_in, v_out) }
+    }
+
+    unsafe fn mul_vec_unchecked(&self, v_in: &[T], v_out: &mut [T]) {
+        let mut work = self.work.borrow_mut();
+        self.left.mul_vec_unchecked(v_in, &mut *v_out);
+        self.right.mul_vec_unchecked(v_in, work.as_mut_slice());
+        axpy(T::one(), work.as_slice(), v_out);
+    }
+
+    unsafe fn mul_vec_dot_unchecked(&self, v_in: &[T], v_out: &mut [T]) -> T {
+        let mut work = self.work.borrow_mut();
+        let dl = self.left.mul_vec_dot_unchecked(v_in, &mut *v_out);
+        let dr = self.right.mul_vec_dot_unchecked(v_in, work.as_mut_slice());
+        axpy(T::one(), work.as_slice(), v_out);
+        dl + dr
+    }
+
+    fn mul_vec_trans(&self, v_in: &[T], v_out: &mut [T]) -> SolveResult<()> {
+        let mut work = self.work.borrow_mut();
+        self.left.mul_vec_trans(v_in, &mut *v_out)?;
+        self.right.mul_vec_trans(v_in, work.as_mut_slice())?;
+        axpy(T::one(), work.as_slice(), v_out);
+        Ok(())
+    }
+
+    fn diagonal(&self, out: &mut [T]) {
+        let mut work = self.work.borrow_mut();
+        self.left.diagonal(&mut *out);
+        self.right.diagonal(work.as_mut_slice());
+        axpy(T::one(), work.as_slice(), out);
+    }
+}
+
+/// The scaled operator $\alpha A$.
+#[allow(non_snake_case)]
+pub struct ScaledOp<'data, T: Scalar, M: MatVecMul<T>> {
+    A: &'data M,
+    alpha: T,
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>> ScaledOp<'data, T, M> {
+    #[allow(non_snake_case)]
+    pub fn new(A: &'data M, alpha: T) -> Self {
+        ScaledOp { A, alpha }
+    }
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>> MatVecMul<T> for ScaledOp<'data, T, M> {
+    #[inline]
+    fn shape(&self) -> Option<(usize, usize)> {
+        self.A.shape()
+    }
+
+    #[inline]
+    fn mul_vec(&self, v_in: &[T], v_out: &mut [T]) {
+        self.A.mul_vec(v_in, &mut *v_out);
+        scale(self.alpha, v_out);
+    }
+
+    #[inline]
+    fn mul_vec_dot(&self, v_in: &[T], v_out: &mut [T]) -> T {
+        let d = self.A.mul_vec_dot(v_in, &mut *v_out);
+        scale(self.alpha, v_out);
+        self.alpha * d
+    }
+
+    unsafe fn mul_vec_unchecked(&self, v_in: &[T], v_out: &mut [T]) {
+        self.A.mul_vec_unchecked(v_in, &mut *v_out);
+        scale(self.alpha, v_out);
+    }
+
+    unsafe fn mul_vec_dot_unchecked(&self, v_in: &[T], v_out: &mut [T]) -> T {
+        let d = self.A.mul_vec_dot_unchecked(v_in, &mut *v_out);
+        scale(self.alpha, v_out);
+        self.alpha * d
+    }
+
+    fn mul_vec_trans(&self, v_in: &[T], v_out: &mut [T]) -> SolveResult<()> {
+        self.A.mul_vec_trans(v_in, &mut *v_out)?;
+        scale(self.alpha, v_out);
+        Ok(())
+    }
+
+    fn diagonal(&self, out: &mut [T]) {
+        self.A.diagonal(&mut *out);
+        scale(self.alpha, out);
+    }
+}
+
+/// A matrix-free operator of size `size`, defined only by its action `f(v_in, v_out)`,
+/// which must set $v_{out} = Av_{in}$.
+///
+/// It lets every solver run on operators that are not stored as matrices, e.g. an FFT-based
+/// convolution or a stencil.
+pub struct MatFree<T: Scalar, F: Fn(&[T], &mut [T])> {
+    f: F,
+    size: usize,
+    _marker: PhantomData<T>,
+}
+
+impl<T: Scalar, F: Fn(&[T], &mut [T])> MatFree<T, F> {
+    pub fn new(size: usize, f: F) -> Self {
+        MatFree {
+            f,
+            size,
+            _marker: PhantomData,
+        }
+    }
+
+    /// The size of the operator.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+}
+
+impl<T: Scalar, F: Fn(&[T], &mut [T])> MatVecMul<T> for MatFree<T, F> {
+    #[inline]
+    fn shape(&self) -> Option<(usize, usize)> {
+        Some((self.size, self.size))
+    }
+
+    #[inline]
+    fn mul_vec(&self, v_in: &[T], v_out: &mut [T]) {
+        if self.size != v_in.len() || self.size != v_out.len() {
+            panic!("Dimension mismatch");
+        }
+        (self.f)(v_in, v_out);
+    }
+
+    #[inline]
+    fn mul_vec_dot(&self, v_in: &[T], v_out: &mut [T]) -> T {
+        self.mul_vec(v_in, v_out);
+        conj_dot(v_in, &*v_out)
+    }
+
+    unsafe fn mul_vec_unchecked(&self, v_in: &[T], v_out: &mut [T]) {
+        (self.f)(v_in, v_out);
+    }
+
+    unsafe fn mul_vec_dot_unchecked(&self, v_in: &[T], v_out: &mut [T]) -> T {
+        (self.f)(v_in, &mut *v_out);
+        conj_dot(v_in, &*v_out)
+    }
+}
+
+/// The transposed operator $A^T$, whose product calls
+/// [`mul_vec_trans`](MatVecMul::mul_vec_trans) of `A`.
+///
+/// It lets every solver run on the transposed system, e.g.
+/// `BiCGStab::new(&Transpose(&A), n)` solves $A^Tx = b$ without forming $A^T$.
+///
+/// **Panics** in the products if `A` doesn't provide
+/// [`mul_vec_trans`](MatVecMul::mul_vec_trans), since [`MatVecMul::mul_vec`] has no way to
+/// report the error.
+pub struct Transpose<'data, M>(pub &'data M);
+
+const NO_TRANS: &str = "The transposed operator needs the transposed product of A";
+
+impl<'data, T: Scalar, M: MatVecMul<T>> MatVecMul<T> for Transpose<'data, M> {
+    #[inline]
+    fn shape(&self) -> Option<(usize, usize)> {
+        self.0.shape().map(|(rows, cols)| (cols, rows))
+    }
+
+    #[inline]
+    fn mul_vec(&self, v_in: &[T], v_out: &mut [T]) {
+        self.0.mul_vec_trans(v_in, v_out).expect(NO_TRANS);
+    }
+
+    #[inline]
+    fn mul_vec_dot(&self, v_in: &[T], v_out: &mut [T]) -> T {
+        self.0.mul_vec_trans(v_in, &mut *v_out).expect(NO_TRANS);
+        conj_dot(v_in, &*v_out)
+    }
+
+    unsafe fn mul_vec_unchecked(&self, v_in: &[T], v_out: &mut [T]) {
+        self.0.mul_vec_trans(v_in, v_out).expect(NO_TRANS);
+    }
+
+    unsafe fn mul_vec_dot_unchecked(&self, v_in: &[T], v_out: &mut [T]) -> T {
+        self.0.mul_vec_trans(v_in, &mut *v_out).expect(NO_TRANS);
+        conj_dot(v_in, &*v_out)
+    }
+
+    fn mul_vec_trans(&self, v_in: &[T], v_out: &mut [T]) -> SolveResult<()> {
+        self.0.mul_vec(v_in, v_out);
+        Ok(())
+    }
+
+    fn diagonal(&self, out: &mut [T]) {
+        self.0.diagonal(out);
+    }
+}
+
+/// The equivalent real form of a complex operator $A$ of size $n$, i.e. the real operator
+/// of size $2n$
+/// $$\begin{bmatrix} \mathrm{Re} A & -\mathrm{Im} A \\ \mathrm{Im} A & \mathrm{Re} A
+/// \end{bmatrix},$$
+/// which acts on $[\mathrm{Re}\,x; \mathrm{Im}\,x]$ as $A$ acts on $x$.
+///
+/// It lets the real-only solvers and preconditioners run on complex systems: the
+/// right-hand side is split with [`pack`](Self::pack), and the solution is assembled back
+/// with [`unpack`](Self::unpack). Each product takes one complex product with `A`. The real
+/// form of a Hermitian $A$ is symmetric, with every eigenvalue of $A$ repeated twice, but
+/// that of a complex symmetric $A$ is not.
+///
+/// `T` must be a complex scalar, i.e. its own [`Scalar::Complex`]; real scalars are rejected
+/// at compile time.
+///
+/// It holds scratch vectors for the complex product, hence it is not `Sync`.
+#[allow(non_snake_case)]
+pub struct ComplexAsReal<'data, T: Scalar<Complex = T>, M: MatVecMul<T>> {
+    A: &'data M,
+    size: usize,
+    /// holds x and A * x in complex form
+    work: RefCell<Vec<T>>,
+}
+
+impl<'data, T: Scalar<Complex = T>, M: MatVecMul<T>> ComplexAsReal<'data, T, M> {
+    /// Create the real form of `A`, whose size is `size`.
+    #[allow(non_snake_case)]
+    pub fn new(A: &'data M, size: usize) -> Self {
+        ComplexAsReal {
+            A,
+            size,
+            work: RefCell::new(vec![T::zero(); 2 * size]),
+        }
+    }
+
+    /// Split the complex vector `src` of length $n$ into `dst` $= [\mathrm{Re}\,src;
+    /// \mathrm{Im}\,src]$ of length $2n$.
+    ///
+    /// **Panics** if the lengths don't match the operator size.
+    pub fn pack(&self, src: &[T], dst: &mut [T::Real]) {
+        let n = self.size;
+        assert!(src.len() == n && dst.len() == 2 * n, "Dimension mismatch");
+        let (re, im) = dst.split_at_mut(n);
+        real_part(src, re);
+        imag_part(src, im);
+    }
+
+    /// Assemble the complex vector `dst` of length $n$ from `src` $= [\mathrm{Re}\,dst;
+    /// \mathrm{Im}\,dst]$ of length $2n$, the inverse of [`pack`](Self::pack).
+    ///
+    /// **Panics** if the lengths don't match the operator size.
+    pub fn unpack(&self, src: &[T::Real], dst: &mut [T]) {
+        let n = self.size;
+        assert!(src.len() == 2 * n && dst.len() == n, "Dimension mismatch");
+        from_parts(&src[..n], &src[n..], dst);
+    }
+}
+
+impl<'data, T: Scalar<Complex = T>, M: MatVecMul<T>> MatVecMul<T::Real>
+    for ComplexAsReal<'data, T, M>
+{
+    #[inline]
+    fn shape(&self) -> Option<(usize, usize)> {
+        Some((2 * self.size, 2 * self.size))
+    }
+
+    #[inline]
+    fn mul_vec(&self, v_in: &[T::Real], v_out: &mut [T::Real]) {
+        let n = 2 * self.size;
+        if n != v_in.len() || n != v_out.len() {
+            panic!("Dimension mismatch");
+        }
+        unsafe {
+            self.mul_vec_unchecked(v_in, v_out);
+        }
+    }
+
+    #[inline]
+    fn mul_vec_dot(&self, v_in: &[T::Real], v_out: &mut [T::Real]) -> T::Real {
+        self.mul_vec(v_in, v_out);
+        dot(v_in, &*v_out)
+    }
+
+    unsafe fn mul_vec_unchecked(&self, v_in: &[T::Real], v_out: &mut [T::Real]) {
+        let n = self.size;
+        let mut work = self.work.borrow_mut();
+        let (x, y) = work.split_at_mut(n);
+        from_parts(&v_in[..n], &v_in[n..], &mut *x);
+        self.A.mul_vec_unchecked(&*x, &mut *y);
+        let (re, im) = v_out.split_at_mut(n);
+        real_part(&*y, re);
+        imag_part(&*y, im);
+    }
+
+    unsafe fn mul_vec_dot_unchecked(&self, v_in: &[T::Real], v_out: &mut [T::Real]) -> T::Real {
+        self.mul_vec_unchecked(v_in, v_out);
+        dot(v_in, &*v_out)
+    }
+
+    fn mul_vec_trans(&self, v_in: &[T::Real], v_out: &mut [T::Real]) -> SolveResult<()> {
+        // the top block row of the transpose is [Re A^T, Im A^T], and the bottom one is
+        // [-Im A^T, Re A^T], which is A^T applied to the conjugate of the input, conjugated
+        let n = self.size;
+        let mut work = self.work.borrow_mut();
+        let (x, y) = work.split_at_mut(n);
+        from_parts(&v_in[..n], &v_in[n..], &mut *x);
+        x.iter_mut().for_each(|v| *v = v.conj());
+        self.A.mul_vec_trans(&*x, &mut *y)?;
+        let (re, im) = v_out.split_at_mut(n);
+        real_part(&*y, re);
+        imag_part(&*y, &mut *im);
+        im.iter_mut().for_each(|v| *v = -*v);
+        Ok(())
+    }
+
+    fn diagonal(&self, out: &mut [T::Real]) {
+        let n = self.size;
+        let mut work = self.work.borrow_mut();
+        self.A.diagonal(&mut work[..n]);
+        let (re, re2) = out.split_at_mut(n);
+        real_part(&work[..n], &mut *re);
+        re2.copy_from_slice(re);
+    }
+}
+
+/// The left-preconditioned operator $M^{-1}A$.
+#[allow(non_snake_case)]
+pub(crate) struct LeftPrecondOp<'data, T: Scalar, M: MatVecMul<T>, P: Precond<T> + ?Sized> {
+    A: &'data M,
+    precond: &'data P,
+    /// holds A * x before the preconditioner is applied
+    work: RefCell<Vec<T>>,
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>, P: Precond<T> + ?Sized> LeftPrecondOp<'data, T, M, P> {
+    #[allow(non_snake_case)]
+    pub(crate) fn new(A: &'data M, precond: &'data P, size: usize) -> Self {
+        LeftPrecondOp {
+            A,
+            precond,
+            work: RefCell::new(vec![T::zero(); size]),
+        }
+    }
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>, P: Precond<T> + ?Sized> MatVecMul<T>
+    for LeftPrecondOp<'data, T, M, P>
+{
+    #[inline]
+    fn shape(&self) -> Option<(usize, usize)> {
+        self.A.shape()
+    }
+
+    #[inline]
+    fn mul_vec(&self, v_in: &[T], v_out: &mut [T]) {
+        let n = self.work.borrow().len();
+        if n != v_in.len() || n != v_out.len() {
+            panic!("Dimension mismatch");
+        }
+        unsafe {
+            self.mul_vec_unchecked(v_in, v_out);
+        }
+    }
+
+    #[inline]
+    fn mul_vec_dot(&self, v_in: &[T], v_out: &mut [T]) -> T {
+        self.mul_vec(v_in, v_out);
+        conj_dot(v_in, &*v_out)
+    }
+
+    unsafe fn mul_vec_unchecked(&self, v_in: &[T], v_out: &mut [T]) {
+        let mut work = self.work.borrow_mut();
+        self.A.mul_vec_unchecked(v_in, work.as_mut_slice());
+        self.precond.apply(work.as_slice(), v_out);
+    }
+
+    unsafe fn mul_vec_dot_unchecked(&self, v_in: &[T], v_out: &mut [T]) -> T {
+        self.mul_vec_unchecked(v_in, v_out);
+        conj_dot(v_in, &*v_out)
+    }
+}
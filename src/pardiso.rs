@@ -0,0 +1,215 @@
+//! A wrapper of the MKL PARDISO sparse direct solver.
+
+use cauchy::Scalar;
+use num_complex::{Complex32, Complex64};
+use sprs::CsMatI;
+use std::{ffi::c_void, os::raw::c_int, result::Result};
+
+extern "C" {
+    fn pardiso(
+        pt: *mut *mut c_void,
+        maxfct: *const c_int,
+        mnum: *const c_int,
+        mtype: *const c_int,
+        phase: *const c_int,
+        n: *const c_int,
+        a: *const c_void,
+        ia: *const c_int,
+        ja: *const c_int,
+        perm: *mut c_int,
+        nrhs: *const c_int,
+        iparm: *mut c_int,
+        msglvl: *const c_int,
+        b: *mut c_void,
+        x: *mut c_void,
+        error: *mut c_int,
+    );
+}
+
+const PHASE_FACTORIZE: c_int = 12;
+const PHASE_SOLVE: c_int = 33;
+const PHASE_RELEASE: c_int = -1;
+
+/// The structure of the matrix factorized by [`Pardiso`], which selects the PARDISO `mtype`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PardisoMatrixType {
+    /// A general matrix, factorized with $LU$.
+    General,
+    /// A symmetric indefinite matrix, i.e. $A = A^T$, factorized with $LDL^T$.
+    Symmetric,
+    /// A real symmetric or complex Hermitian positive definite matrix, factorized with
+    /// Cholesky.
+    PosDef,
+    /// A Hermitian indefinite matrix, i.e. $A = A^H$. For real-valued matrices, it is the
+    /// same as [`Symmetric`](Self::Symmetric).
+    Hermitian,
+}
+
+/// A sparse direct solver backed by MKL PARDISO.
+///
+/// The matrix is factorized once in [`new`](Self::new), and every call of
+/// [`solve`](Self::solve) reuses the factorization, e.g. to solve the coarse grid systems of
+/// a multigrid cycle.
+pub struct Pardiso<T: Scalar> {
+    // PARDISO keeps pointers to the arrays, so they live as long as the factorization
+    indptr: Vec<c_int>,
+    indices: Vec<c_int>,
+    data: Vec<T>,
+    size: usize,
+    /// The internal handle of PARDISO
+    pt: [*mut c_void; 64],
+    mtype: c_int,
+    iparm: [c_int; 64],
+}
+
+impl<T: Scalar> Pardiso<T> {
+    /// Factorize the square CSR matrix `m` with the structure `mtype`.
+    ///
+    /// For the symmetric, Hermitian and positive definite types, only the upper triangular
+    /// part of `m` is read. It returns the PARDISO error code on failure, e.g. `-4` for a
+    /// zero pivot.
+    pub fn new(m: CsMatI<T, i32>, mtype: PardisoMatrixType) -> Result<Pardiso<T>, i32> {
+        assert!(m.is_csr());
+        assert_eq!(m.rows(), m.cols());
+
+        let is_complex = super::same_type::<T, Complex32>() || super::same_type::<T, Complex64>();
+        let mtype = match (mtype, is_complex) {
+            (PardisoMatrixType::General, false) => 11,
+            (PardisoMatrixType::General, true) => 13,
+            (PardisoMatrixType::Symmetric, false) | (PardisoMatrixType::Hermitian, false) => -2,
+            (PardisoMatrixType::Symmetric, true) => 6,
+            (PardisoMatrixType::PosDef, false) => 2,
+            (PardisoMatrixType::PosDef, true) => 4,
+            (PardisoMatrixType::Hermitian, true) => -4,
+        };
+        let size = m.rows();
+        let (indptr, indices, data) = if mtype == 11 || mtype == 13 {
+            m.into_raw_storage()
+        } else {
+            upper_triangle(&m)
+        };
+
+        let mut iparm = [0 as c_int; 64];
+        iparm[0] = 1; // no default values
+        iparm[1] = 2; // nested dissection ordering from METIS
+        iparm[7] = 2; // at most two steps of iterative refinement
+        iparm[9] = if mtype == 11 || mtype == 13 { 13 } else { 8 }; // pivot perturbation 1E-13 or 1E-8
+        iparm[10] = 1; // scaling
+        iparm[12] = 1; // weighted matching
+        iparm[34] = 1; // zero-based indices
+        if super::same_type::<T, f32>() || super::same_type::<T, Complex32>() {
+            iparm[27] = 1; // single precision
+        }
+
+        let mut ret = Pardiso {
+            indptr,
+            indices,
+            data,
+            size,
+            pt: [std::ptr::null_mut(); 64],
+            mtype,
+            iparm,
+        };
+        if let Err(err) = ret.call(
+            PHASE_FACTORIZE,
+            1,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        ) {
+            ret.release();
+            return Err(err);
+        }
+        Ok(ret)
+    }
+
+    /// Return the matrix size.
+    #[inline(always)]
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Solve $Ax = b$ with the factorization, and panic if the dimensions don't match.
+    pub fn solve(&mut self, rhs: &[T], x: &mut [T]) -> Result<(), i32> {
+        if self.size != rhs.len() || self.size != x.len() {
+            panic!("Dimension mismatch");
+        }
+        // PARDISO doesn't modify b unless asked to
+        self.call(
+            PHASE_SOLVE,
+            1,
+            rhs.as_ptr() as *mut c_void,
+            x.as_mut_ptr() as *mut c_void,
+        )
+    }
+
+    fn call(
+        &mut self,
+        phase: c_int,
+        nrhs: c_int,
+        b: *mut c_void,
+        x: *mut c_void,
+    ) -> Result<(), i32> {
+        let (maxfct, mnum, msglvl) = (1 as c_int, 1 as c_int, 0 as c_int);
+        let n = self.size as c_int;
+        let mut error: c_int = 0;
+        unsafe {
+            pardiso(
+                self.pt.as_mut_ptr(),
+                &maxfct,
+                &mnum,
+                &self.mtype,
+                &phase,
+                &n,
+                self.data.as_ptr() as *const c_void,
+                self.indptr.as_ptr(),
+                self.indices.as_ptr(),
+                std::ptr::null_mut(),
+                &nrhs,
+                self.iparm.as_mut_ptr(),
+                &msglvl,
+                b,
+                x,
+                &mut error,
+            );
+        }
+        if error != 0 {
+            return Err(error);
+        }
+        Ok(())
+    }
+
+    fn release(&mut self) {
+        if self.pt.iter().any(|p| !p.is_null()) {
+            // Nothing can be done if releasing fails
+            let _ = self.call(PHASE_RELEASE, 1, std::ptr::null_mut(), std::ptr::null_mut());
+        }
+    }
+}
+
+impl<T: Scalar> Drop for Pardiso<T> {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+/// The CSR arrays of the upper triangular part of `m`, with an explicit zero for every
+/// missing diagonal entry, as PARDISO requires for the symmetric types.
+fn upper_triangle<T: Scalar>(m: &CsMatI<T, i32>) -> (Vec<c_int>, Vec<c_int>, Vec<T>) {
+    let mut indptr = Vec::with_capacity(m.rows() + 1);
+    let mut indices = Vec::with_capacity(m.nnz());
+    let mut data = Vec::with_capacity(m.nnz());
+    indptr.push(0);
+    for (i, row) in m.outer_iterator().enumerate() {
+        if row.get(i).is_none() {
+            indices.push(i as c_int);
+            data.push(T::zero());
+        }
+        // the column indices are sorted, so the diagonal comes first
+        for (j, &v) in row.iter().filter(|&(j, _)| j >= i) {
+            indices.push(j as c_int);
+            data.push(v);
+        }
+        indptr.push(indices.len() as c_int);
+    }
+    (indptr, indices, data)
+}
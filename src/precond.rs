@@ -1,8 +1,105 @@
-use super::MatVecMul;
+use super::{error::*, solver::square_size, vecalg::conj_dot, MatVecMul};
 use cauchy::Scalar;
-use std::{marker::PhantomData, ops::Mul};
+use num_traits::{Float, One, Zero};
+use sprs::{CsMat, CsMatViewI, SpIndex, TriMat};
+use std::{cell::RefCell, marker::PhantomData, ops::Mul};
+
+/// A preconditioner applying $M^{-1}$, the inverse of an approximation of the matrix.
+///
+/// Every [`MatVecMul`] is a preconditioner whose product gives $M^{-1}r$. The solvers
+/// accept `&dyn Precond<T>`, so that the preconditioner can be chosen at runtime.
+pub trait Precond<T: Scalar> {
+    /// Compute $z = M^{-1}r$.
+    fn apply(&self, r: &[T], z: &mut [T]);
+
+    /// Compute $x \leftarrow M^{-1}x$.
+    ///
+    /// The default implementation copies `x` into a temporary vector.
+    fn apply_in_place(&self, x: &mut [T]) {
+        let r = x.to_vec();
+        self.apply(r.as_slice(), x);
+    }
+
+    /// Compute $z = M^{-T}r$, which applies the preconditioner to the transposed system.
+    ///
+    /// Preconditioners are not required to provide it; the default implementation returns
+    /// [`SolverError::InvalidPreconditioner`].
+    ///
+    /// For [`Identity`], [`DiagPrecond`] and [`Jacobi`], $M$ is diagonal and the transpose is
+    /// the same as [`apply`](Self::apply). The preconditioners of this module with a
+    /// nontrivial transpose are
+    /// - [`BlockJacobi`], with the transposed block factors,
+    /// - [`GaussSeidelPrecond`] and [`SSOR`], with the transposed sweeps in reverse order,
+    /// - [`ILU0`], with the solves with $U^T$ and $L^T$,
+    /// - [`SPAI`], with the transposed approximate inverse,
+    /// - [`NeumannPrecond`], when its operator provides the transposed product.
+    ///
+    /// [`IC0`] doesn't provide it.
+    fn apply_trans(&self, _r: &[T], _z: &mut [T]) -> SolveResult<()> {
+        Err(SolverError::InvalidPreconditioner(String::from(
+            "The transposed preconditioner is not provided",
+        )))
+    }
+}
+
+impl<T: Scalar, M: MatVecMul<T>> Precond<T> for M {
+    #[inline]
+    fn apply(&self, r: &[T], z: &mut [T]) {
+        self.mul_vec(r, z);
+    }
+
+    #[inline]
+    fn apply_trans(&self, r: &[T], z: &mut [T]) -> SolveResult<()> {
+        self.mul_vec_trans(r, z)
+    }
+}
+
+/// The identity preconditioner $M = I$, which turns a preconditioned solve into an
+/// unpreconditioned one.
+///
+/// It is also a [`MatVecMul`], hence a preconditioner through the blanket implementation.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Identity;
+
+impl<T: Scalar> MatVecMul<T> for Identity {
+    /// The identity applies to vectors of any length, so it has no shape.
+    #[inline]
+    fn shape(&self) -> Option<(usize, usize)> {
+        None
+    }
+
+    #[inline]
+    fn mul_vec(&self, v_in: &[T], v_out: &mut [T]) {
+        v_out.copy_from_slice(v_in);
+    }
+
+    #[inline]
+    fn mul_vec_dot(&self, v_in: &[T], v_out: &mut [T]) -> T {
+        v_out.copy_from_slice(v_in);
+        conj_dot(v_in, v_in)
+    }
+
+    #[inline]
+    unsafe fn mul_vec_unchecked(&self, v_in: &[T], v_out: &mut [T]) {
+        self.mul_vec(v_in, v_out);
+    }
+
+    #[inline]
+    unsafe fn mul_vec_dot_unchecked(&self, v_in: &[T], v_out: &mut [T]) -> T {
+        self.mul_vec_dot(v_in, v_out)
+    }
+
+    #[inline]
+    fn mul_vec_trans(&self, v_in: &[T], v_out: &mut [T]) -> SolveResult<()> {
+        self.mul_vec(v_in, v_out);
+        Ok(())
+    }
+}
 
 /// Diagonal preconditioner
+///
+/// Being diagonal, it is its own transpose: the same object applies $M^{-T}$ when a
+/// method needs the transposed preconditioner.
 pub struct DiagPrecond<T, V>
 where
     T: Scalar + Mul<V, Output = T>,
@@ -34,6 +131,11 @@ where
     T: Scalar + Mul<V, Output = T>,
     V: Scalar,
 {
+    #[inline]
+    fn shape(&self) -> Option<(usize, usize)> {
+        Some((self.diag_inv.len(), self.diag_inv.len()))
+    }
+
     #[inline]
     fn mul_vec(&self, v_in: &[T], v_out: &mut [T]) {
         if self.diag_inv.len() != v_in.len() || self.diag_inv.len() != v_out.len() {
@@ -60,4 +162,1419 @@ where
     unsafe fn mul_vec_dot_unchecked(&self, _v_in: &[T], _v_out: &mut [T]) -> T {
         unimplemented!()
     }
+
+    #[inline]
+    fn mul_vec_trans(&self, v_in: &[T], v_out: &mut [T]) -> SolveResult<()> {
+        self.mul_vec(v_in, v_out);
+        Ok(())
+    }
+}
+
+/// Jacobi preconditioner $M = \mathrm{diag}(A)$, applied as $z_i = r_i / a_{ii}$.
+pub struct Jacobi<T: Scalar> {
+    inv_diag: Vec<T>,
+}
+
+impl<T: Scalar> Jacobi<T> {
+    /// Create the preconditioner from the diagonal of the square matrix `A`, stored either
+    /// in CSR or CSC format.
+    ///
+    /// Return [`SolverError::ZeorDiagonalElem`] if a diagonal entry is zero or missing.
+    #[allow(non_snake_case)]
+    pub fn new<I: SpIndex>(A: CsMatViewI<T, I>) -> SolveResult<Self> {
+        if A.rows() != A.cols() {
+            return Err(SolverError::IncompatibleMatrixFormat(String::from(
+                "Not a square matrix",
+            )));
+        }
+        let mut inv_diag = Vec::with_capacity(A.rows());
+        for (i, vec) in A.outer_iterator().enumerate() {
+            match vec.get(i) {
+                Some(&d) if d != T::zero() => inv_diag.push(T::one() / d),
+                _ => return Err(SolverError::ZeorDiagonalElem(i)),
+            }
+        }
+        Ok(Jacobi { inv_diag })
+    }
+}
+
+impl<T: Scalar> MatVecMul<T> for Jacobi<T> {
+    #[inline]
+    fn shape(&self) -> Option<(usize, usize)> {
+        Some((self.inv_diag.len(), self.inv_diag.len()))
+    }
+
+    #[inline]
+    fn mul_vec(&self, v_in: &[T], v_out: &mut [T]) {
+        if self.inv_diag.len() != v_in.len() || self.inv_diag.len() != v_out.len() {
+            panic!("Dimension mismatch");
+        }
+        unsafe {
+            self.mul_vec_unchecked(v_in, v_out);
+        }
+    }
+
+    #[inline]
+    unsafe fn mul_vec_unchecked(&self, v_in: &[T], v_out: &mut [T]) {
+        for (r, (v, s)) in v_out.iter_mut().zip(v_in.iter().zip(self.inv_diag.iter())) {
+            *r = (*v) * (*s);
+        }
+    }
+
+    #[inline]
+    fn mul_vec_dot(&self, v_in: &[T], v_out: &mut [T]) -> T {
+        if self.inv_diag.len() != v_in.len() || self.inv_diag.len() != v_out.len() {
+            panic!("Dimension mismatch");
+        }
+        unsafe { self.mul_vec_dot_unchecked(v_in, v_out) }
+    }
+
+    #[inline]
+    unsafe fn mul_vec_dot_unchecked(&self, v_in: &[T], v_out: &mut [T]) -> T {
+        self.mul_vec_unchecked(v_in, v_out);
+        conj_dot(v_in, v_out)
+    }
+    /// A diagonal preconditioner is its own transpose.
+    #[inline]
+    fn mul_vec_trans(&self, v_in: &[T], v_out: &mut [T]) -> SolveResult<()> {
+        self.mul_vec(v_in, v_out);
+        Ok(())
+    }
+}
+
+/// Block Jacobi preconditioner $M = \mathrm{blockdiag}(A_{11}, A_{22}, \dots)$, whose
+/// diagonal blocks are inverted exactly.
+///
+/// The unknowns are grouped into consecutive blocks of `block_size`, e.g. the degrees of
+/// freedom of a node in a vector-valued PDE, and each block is factorized by a dense LU
+/// decomposition with partial pivoting. When the size of the matrix is not a multiple of
+/// `block_size`, the last block is smaller. With `block_size = 1` it is the [`Jacobi`]
+/// preconditioner, and it captures the coupling within a block that scalar Jacobi misses.
+pub struct BlockJacobi<T: Scalar> {
+    /// The LU factors of the blocks, each stored row by row in `block_size^2` entries
+    lu: Vec<T>,
+    /// The row interchanges of the factorizations, as the LAPACK `ipiv`, local to a block
+    pivots: Vec<usize>,
+    block_size: usize,
+}
+
+impl<T: Scalar> BlockJacobi<T> {
+    /// Factorize the diagonal blocks of size `block_size` of the square matrix `A`, stored
+    /// either in CSR or CSC format.
+    ///
+    /// Return [`SolverError::InvalidParameter`] if `block_size` is zero, and
+    /// [`SolverError::BreakDown`] with the row index if a block is singular.
+    #[allow(non_snake_case)]
+    pub fn new<I: SpIndex>(A: CsMatViewI<T, I>, block_size: usize) -> SolveResult<Self> {
+        if A.rows() != A.cols() {
+            return Err(SolverError::IncompatibleMatrixFormat(String::from(
+                "Not a square matrix",
+            )));
+        }
+        if block_size == 0 {
+            return Err(SolverError::InvalidParameter(String::from(
+                "The block size must be positive",
+            )));
+        }
+        let n = A.rows();
+        let b = block_size;
+        let n_blocks = n.div_ceil(b);
+        let mut lu = vec![T::zero(); n_blocks * b * b];
+        for (outer, vec) in A.outer_iterator().enumerate() {
+            for (inner, &v) in vec.iter() {
+                let (i, j) = if A.is_csr() {
+                    (outer, inner)
+                } else {
+                    (inner, outer)
+                };
+                if i / b == j / b {
+                    lu[(i / b) * b * b + (i % b) * b + j % b] += v;
+                }
+            }
+        }
+
+        let mut pivots = vec![0; n];
+        for k in 0..n_blocks {
+            let bk = b.min(n - k * b);
+            let block = &mut lu[k * b * b..(k + 1) * b * b];
+            let ipiv = &mut pivots[k * b..k * b + bk];
+            if let Err(row) = lu_factorize(block, b, bk, ipiv) {
+                return Err(SolverError::BreakDown(k * b + row));
+            }
+        }
+        Ok(BlockJacobi {
+            lu,
+            pivots,
+            block_size,
+        })
+    }
+
+    /// The size of the diagonal blocks; the last one may be smaller.
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    /// The blocks as `(offset in the vectors, size, LU factors, pivots)`.
+    fn blocks(&self) -> impl Iterator<Item = (usize, usize, &[T], &[usize])> {
+        let b = self.block_size;
+        let n = self.pivots.len();
+        self.lu
+            .chunks(b * b)
+            .enumerate()
+            .map(move |(k, lu)| (k * b, b.min(n - k * b), lu, &self.pivots[k * b..]))
+    }
+}
+
+/// LU factorization with partial pivoting of the leading `n`x`n` part of the row-major
+/// matrix `a` with leading dimension `lda`, in place. On a zero pivot, it returns the index
+/// of the row.
+fn lu_factorize<T: Scalar>(
+    a: &mut [T],
+    lda: usize,
+    n: usize,
+    ipiv: &mut [usize],
+) -> Result<(), usize> {
+    for k in 0..n {
+        let p = (k..n)
+            .max_by(|&i, &j| {
+                let (u, v) = (a[i * lda + k].abs(), a[j * lda + k].abs());
+                u.partial_cmp(&v).unwrap_or(std::cmp::Ordering::Equal)
+            })
+            .unwrap();
+        ipiv[k] = p;
+        if a[p * lda + k] == T::zero() {
+            return Err(k);
+        }
+        if p != k {
+            for j in 0..n {
+                a.swap(k * lda + j, p * lda + j);
+            }
+        }
+        let inv = T::one() / a[k * lda + k];
+        for i in k + 1..n {
+            let l_ik = a[i * lda + k] * inv;
+            a[i * lda + k] = l_ik;
+            for j in k + 1..n {
+                let u_kj = a[k * lda + j];
+                a[i * lda + j] -= l_ik * u_kj;
+            }
+        }
+    }
+    Ok(())
+}
+
+impl<T: Scalar> MatVecMul<T> for BlockJacobi<T> {
+    #[inline]
+    fn shape(&self) -> Option<(usize, usize)> {
+        Some((self.pivots.len(), self.pivots.len()))
+    }
+
+    #[inline]
+    fn mul_vec(&self, v_in: &[T], v_out: &mut [T]) {
+        if self.pivots.len() != v_in.len() || self.pivots.len() != v_out.len() {
+            panic!("Dimension mismatch");
+        }
+        unsafe {
+            self.mul_vec_unchecked(v_in, v_out);
+        }
+    }
+
+    unsafe fn mul_vec_unchecked(&self, v_in: &[T], v_out: &mut [T]) {
+        v_out.copy_from_slice(v_in);
+        let lda = self.block_size;
+        for (offset, bk, lu, ipiv) in self.blocks() {
+            let z = &mut v_out[offset..offset + bk];
+            for (k, &p) in ipiv.iter().take(bk).enumerate() {
+                z.swap(k, p);
+            }
+            // forward solve with the unit lower triangular L
+            for i in 0..bk {
+                for k in 0..i {
+                    let z_k = z[k];
+                    z[i] -= lu[i * lda + k] * z_k;
+                }
+            }
+            // backward solve with U
+            for i in (0..bk).rev() {
+                for k in i + 1..bk {
+                    let z_k = z[k];
+                    z[i] -= lu[i * lda + k] * z_k;
+                }
+                z[i] /= lu[i * lda + i];
+            }
+        }
+    }
+
+    #[inline]
+    fn mul_vec_dot(&self, v_in: &[T], v_out: &mut [T]) -> T {
+        if self.pivots.len() != v_in.len() || self.pivots.len() != v_out.len() {
+            panic!("Dimension mismatch");
+        }
+        unsafe { self.mul_vec_dot_unchecked(v_in, v_out) }
+    }
+
+    #[inline]
+    unsafe fn mul_vec_dot_unchecked(&self, v_in: &[T], v_out: &mut [T]) -> T {
+        self.mul_vec_unchecked(v_in, v_out);
+        conj_dot(v_in, v_out)
+    }
+
+    /// Apply $M^{-T}$ block by block, with a forward solve with $U^T$, a backward solve with
+    /// $L^T$ and the row interchanges in reverse order.
+    fn mul_vec_trans(&self, v_in: &[T], v_out: &mut [T]) -> SolveResult<()> {
+        if self.pivots.len() != v_in.len() || self.pivots.len() != v_out.len() {
+            panic!("Dimension mismatch");
+        }
+        v_out.copy_from_slice(v_in);
+        let lda = self.block_size;
+        for (offset, bk, lu, ipiv) in self.blocks() {
+            let z = &mut v_out[offset..offset + bk];
+            for i in 0..bk {
+                for k in 0..i {
+                    let z_k = z[k];
+                    z[i] -= lu[k * lda + i] * z_k;
+                }
+                z[i] /= lu[i * lda + i];
+            }
+            for i in (0..bk).rev() {
+                for k in i + 1..bk {
+                    let z_k = z[k];
+                    z[i] -= lu[k * lda + i] * z_k;
+                }
+            }
+            for (k, &p) in ipiv.iter().take(bk).enumerate().rev() {
+                z.swap(k, p);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Polynomial preconditioner truncating the Neumann series of $A^{-1}$ after the Jacobi
+/// splitting $A = D - (D - A)$:
+///
+/// $$M^{-1} = \sum_{k=0}^{m} (I - D^{-1}A)^k D^{-1},$$
+///
+/// where $m$ is the degree. It is applied with $m$ products by $A$ through the Horner-style
+/// recurrence $z_0 = D^{-1}r$, $z_{k+1} = z_k + D^{-1}(r - Az_k)$, i.e. $m$ Jacobi sweeps
+/// from a zero initial guess, so that degree 0 is the [`Jacobi`] preconditioner.
+///
+/// **NOTE:** The series converges only if the spectral radius of $I - D^{-1}A$ is below one,
+/// e.g. for strictly diagonally dominant matrices. Otherwise a higher degree makes the
+/// preconditioner worse. If moreover $A$ is Hermitian with a positive diagonal, $M$ is
+/// Hermitian positive definite and may be used with [`CG`](crate::CG).
+///
+/// It holds a scratch vector for the products with $A$, hence it is not `Sync`.
+#[allow(non_snake_case)]
+pub struct NeumannPrecond<'data, T: Scalar, M: MatVecMul<T>> {
+    A: &'data M,
+    inv_diag: Vec<T>,
+    degree: usize,
+    /// holds A * z
+    work: RefCell<Vec<T>>,
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>> NeumannPrecond<'data, T, M> {
+    /// Create the preconditioner of degree `degree` from the square operator `A`, whose
+    /// diagonal is read with [`MatVecMul::diagonal`].
+    ///
+    /// Return [`SolverError::IncompatibleMatrixFormat`] if `A` is not square or doesn't
+    /// provide its [`shape`](MatVecMul::shape), and [`SolverError::ZeorDiagonalElem`] if a
+    /// diagonal entry is zero.
+    #[allow(non_snake_case)]
+    pub fn new(A: &'data M, degree: usize) -> SolveResult<Self> {
+        let n = square_size(A)?;
+        let mut inv_diag = vec![T::zero(); n];
+        A.diagonal(inv_diag.as_mut_slice());
+        for (i, d) in inv_diag.iter_mut().enumerate() {
+            if d.is_zero() {
+                return Err(SolverError::ZeorDiagonalElem(i));
+            }
+            *d = T::one() / *d;
+        }
+        Ok(NeumannPrecond {
+            A,
+            inv_diag,
+            degree,
+            work: RefCell::new(vec![T::zero(); n]),
+        })
+    }
+
+    pub fn degree(&self) -> usize {
+        self.degree
+    }
+
+    /// The Horner recurrence, with the products by $A$ or $A^T$ given by `mul`.
+    fn horner(&self, v_in: &[T], v_out: &mut [T], mut mul: impl FnMut(&[T], &mut [T])) {
+        let mut work = self.work.borrow_mut();
+        for (z, (r, s)) in v_out.iter_mut().zip(v_in.iter().zip(self.inv_diag.iter())) {
+            *z = (*r) * (*s);
+        }
+        for _ in 0..self.degree {
+            mul(&*v_out, work.as_mut_slice());
+            // z += D^{-1} (r - A z)
+            for (z, (az, (r, s))) in v_out
+                .iter_mut()
+                .zip(work.iter().zip(v_in.iter().zip(self.inv_diag.iter())))
+            {
+                *z += (*r - *az) * (*s);
+            }
+        }
+    }
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>> MatVecMul<T> for NeumannPrecond<'data, T, M> {
+    #[inline]
+    fn shape(&self) -> Option<(usize, usize)> {
+        Some((self.inv_diag.len(), self.inv_diag.len()))
+    }
+
+    #[inline]
+    fn mul_vec(&self, v_in: &[T], v_out: &mut [T]) {
+        if self.inv_diag.len() != v_in.len() || self.inv_diag.len() != v_out.len() {
+            panic!("Dimension mismatch");
+        }
+        unsafe {
+            self.mul_vec_unchecked(v_in, v_out);
+        }
+    }
+
+    #[inline]
+    unsafe fn mul_vec_unchecked(&self, v_in: &[T], v_out: &mut [T]) {
+        self.horner(v_in, v_out, |z, az| self.A.mul_vec_unchecked(z, az));
+    }
+
+    #[inline]
+    fn mul_vec_dot(&self, v_in: &[T], v_out: &mut [T]) -> T {
+        if self.inv_diag.len() != v_in.len() || self.inv_diag.len() != v_out.len() {
+            panic!("Dimension mismatch");
+        }
+        unsafe { self.mul_vec_dot_unchecked(v_in, v_out) }
+    }
+
+    #[inline]
+    unsafe fn mul_vec_dot_unchecked(&self, v_in: &[T], v_out: &mut [T]) -> T {
+        self.mul_vec_unchecked(v_in, v_out);
+        conj_dot(v_in, v_out)
+    }
+
+    /// $M^{-T} = \sum_k (I - D^{-1}A^T)^k D^{-1}$ follows the same recurrence with $A^T$.
+    fn mul_vec_trans(&self, v_in: &[T], v_out: &mut [T]) -> SolveResult<()> {
+        if self.inv_diag.len() != v_in.len() || self.inv_diag.len() != v_out.len() {
+            panic!("Dimension mismatch");
+        }
+        let mut ret = Ok(());
+        self.horner(v_in, v_out, |z, az| {
+            if ret.is_ok() {
+                ret = self.A.mul_vec_trans(z, az);
+            }
+        });
+        ret
+    }
+}
+
+/// Gauss-Seidel preconditioner, splitting the matrix as $A = L + D + U$.
+///
+/// The forward variant applies $z = (D + L)^{-1} r$, i.e. one forward Gauss-Seidel sweep
+/// from a zero initial guess. The symmetric variant (SGS) follows it with a backward sweep,
+/// which applies $z = M^{-1} r$ with $M = (D + L) D^{-1} (D + U)$.
+///
+/// **NOTE:** The forward variant is not symmetric. With [`CG`](crate::CG) and
+/// [`MinRes`](crate::MinRes), which require a Hermitian positive definite preconditioner,
+/// use the symmetric variant; for a Hermitian positive definite $A$, its $M$ is Hermitian
+/// positive definite as well. Either variant can be used with [`BiCGStab`](crate::BiCGStab).
+pub struct GaussSeidelPrecond<T: Scalar> {
+    /// Strictly lower triangular part $L$ in CSR format
+    lower: CsMat<T>,
+    /// Strictly upper triangular part $U$ in CSR format
+    upper: CsMat<T>,
+    inv_diag: Vec<T>,
+    symmetric: bool,
+}
+
+impl<T: Scalar> GaussSeidelPrecond<T> {
+    /// Create the preconditioner from the square matrix `A`, stored either in CSR or CSC
+    /// format. `symmetric` selects the symmetric (SGS) variant.
+    ///
+    /// Return [`SolverError::ZeorDiagonalElem`] if a diagonal entry is zero or missing.
+    #[allow(non_snake_case)]
+    pub fn new<I: SpIndex>(A: CsMatViewI<T, I>, symmetric: bool) -> SolveResult<Self> {
+        Self::with_relaxation(A, symmetric, T::Real::one())
+    }
+
+    /// The sweeps with the diagonal $D / \omega$ in place of $D$.
+    #[allow(non_snake_case)]
+    fn with_relaxation<I: SpIndex>(
+        A: CsMatViewI<T, I>,
+        symmetric: bool,
+        omega: T::Real,
+    ) -> SolveResult<Self> {
+        if A.rows() != A.cols() {
+            return Err(SolverError::IncompatibleMatrixFormat(String::from(
+                "Not a square matrix",
+            )));
+        }
+        let n = A.rows();
+        let mut lower = TriMat::new((n, n));
+        let mut upper = TriMat::new((n, n));
+        let mut diag = vec![T::zero(); n];
+        for (outer, vec) in A.outer_iterator().enumerate() {
+            for (inner, &v) in vec.iter() {
+                let (i, j) = if A.is_csr() {
+                    (outer, inner)
+                } else {
+                    (inner, outer)
+                };
+                if j < i {
+                    lower.add_triplet(i, j, v);
+                } else if j > i {
+                    upper.add_triplet(i, j, v);
+                } else {
+                    diag[i] += v;
+                }
+            }
+        }
+        let mut inv_diag = Vec::with_capacity(n);
+        for (i, &d) in diag.iter().enumerate() {
+            if d == T::zero() {
+                return Err(SolverError::ZeorDiagonalElem(i));
+            }
+            inv_diag.push(T::from_real(omega) / d);
+        }
+        Ok(GaussSeidelPrecond {
+            lower: lower.to_csr(),
+            upper: upper.to_csr(),
+            inv_diag,
+            symmetric,
+        })
+    }
+}
+
+impl<T: Scalar> MatVecMul<T> for GaussSeidelPrecond<T> {
+    #[inline]
+    fn shape(&self) -> Option<(usize, usize)> {
+        Some((self.inv_diag.len(), self.inv_diag.len()))
+    }
+
+    #[inline]
+    fn mul_vec(&self, v_in: &[T], v_out: &mut [T]) {
+        if self.inv_diag.len() != v_in.len() || self.inv_diag.len() != v_out.len() {
+            panic!("Dimension mismatch");
+        }
+        unsafe {
+            self.mul_vec_unchecked(v_in, v_out);
+        }
+    }
+
+    unsafe fn mul_vec_unchecked(&self, v_in: &[T], v_out: &mut [T]) {
+        // forward sweep: (D + L) z = r
+        for (i, vec) in self.lower.outer_iterator().enumerate() {
+            let mut sigma = *v_in.get_unchecked(i);
+            for (j, &a) in vec.iter() {
+                sigma -= a * *v_out.get_unchecked(j);
+            }
+            *v_out.get_unchecked_mut(i) = sigma * *self.inv_diag.get_unchecked(i);
+        }
+        if !self.symmetric {
+            return;
+        }
+        // backward sweep: (D + U) z = D z_fwd
+        for (i, vec) in self.upper.outer_iterator().enumerate().rev() {
+            let mut sigma = T::zero();
+            for (j, &a) in vec.iter() {
+                sigma += a * *v_out.get_unchecked(j);
+            }
+            *v_out.get_unchecked_mut(i) -= sigma * *self.inv_diag.get_unchecked(i);
+        }
+    }
+
+    #[inline]
+    fn mul_vec_dot(&self, v_in: &[T], v_out: &mut [T]) -> T {
+        if self.inv_diag.len() != v_in.len() || self.inv_diag.len() != v_out.len() {
+            panic!("Dimension mismatch");
+        }
+        unsafe { self.mul_vec_dot_unchecked(v_in, v_out) }
+    }
+
+    #[inline]
+    unsafe fn mul_vec_dot_unchecked(&self, v_in: &[T], v_out: &mut [T]) -> T {
+        self.mul_vec_unchecked(v_in, v_out);
+        conj_dot(v_in, v_out)
+    }
+
+    /// Apply $M^{-T}$, i.e. $(D + L^T)^{-1}$ for the forward variant and
+    /// $(D + L^T)^{-1} D (D + U^T)^{-1}$ for the symmetric one. The rows of $L$ and $U$ are
+    /// swept as the columns of their transposes.
+    fn mul_vec_trans(&self, v_in: &[T], v_out: &mut [T]) -> SolveResult<()> {
+        let n = self.inv_diag.len();
+        if n != v_in.len() || n != v_out.len() {
+            panic!("Dimension mismatch");
+        }
+        v_out.copy_from_slice(v_in);
+        if !self.symmetric {
+            // backward sweep: (D + L^T) z = r
+            for (i, vec) in self.lower.outer_iterator().enumerate().rev() {
+                let z_i = v_out[i] * self.inv_diag[i];
+                v_out[i] = z_i;
+                for (j, &a) in vec.iter() {
+                    v_out[j] -= a * z_i;
+                }
+            }
+            return Ok(());
+        }
+        // forward sweep: (D + U^T) y = r
+        for (i, vec) in self.upper.outer_iterator().enumerate() {
+            let y_i = v_out[i] * self.inv_diag[i];
+            v_out[i] = y_i;
+            for (j, &a) in vec.iter() {
+                v_out[j] -= a * y_i;
+            }
+        }
+        // backward sweep: (D + L^T) z = D y
+        for (i, vec) in self.lower.outer_iterator().enumerate().rev() {
+            let z_i = v_out[i];
+            for (j, &a) in vec.iter() {
+                v_out[j] -= a * z_i * self.inv_diag[j];
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Symmetric successive over-relaxation (SSOR) preconditioner with the relaxation factor
+/// $\omega \in (0, 2)$, splitting the matrix as $A = L + D + U$:
+///
+/// $$M = \frac{1}{2 - \omega} \left(\frac{D}{\omega} + L\right) \left(\frac{D}{\omega}\right)^{-1}
+/// \left(\frac{D}{\omega} + U\right).$$
+///
+/// It is applied with a forward sweep with $D/\omega + L$, a scaling by $D/\omega$ and a
+/// backward sweep with $D/\omega + U$, so that $z = M^{-1}r$ is one SSOR iteration from a
+/// zero initial guess, see [`SSOR`](crate::SSOR). With `omega = 1` it is the symmetric
+/// [`GaussSeidelPrecond`].
+///
+/// **NOTE:** For a Hermitian positive definite $A$ and a real $\omega \in (0, 2)$, $M$ is
+/// Hermitian positive definite as well, hence it can be used with [`CG`](crate::CG) and
+/// [`MinRes`](crate::MinRes). A good $\omega$ depends on the problem; values around 1.2–1.8
+/// usually help on discretized elliptic PDEs.
+pub struct SSOR<T: Scalar> {
+    sweeps: GaussSeidelPrecond<T>,
+    omega: T::Real,
+}
+
+impl<T: Scalar> SSOR<T> {
+    /// Create the preconditioner from the square matrix `A`, stored either in CSR or CSC
+    /// format, with the relaxation factor `omega`.
+    ///
+    /// Return [`SolverError::InvalidParameter`] if `omega` is not in $(0, 2)$, and
+    /// [`SolverError::ZeorDiagonalElem`] if a diagonal entry is zero or missing.
+    #[allow(non_snake_case)]
+    pub fn new<I: SpIndex>(A: CsMatViewI<T, I>, omega: T::Real) -> SolveResult<Self> {
+        if !(omega > T::Real::zero() && omega < T::Real::one() + T::Real::one()) {
+            return Err(SolverError::InvalidParameter(String::from(
+                "The relaxation factor omega must lie in (0, 2)",
+            )));
+        }
+        Ok(SSOR {
+            sweeps: GaussSeidelPrecond::with_relaxation(A, true, omega)?,
+            omega,
+        })
+    }
+
+    /// The relaxation parameter $\omega$.
+    pub fn omega(&self) -> T::Real {
+        self.omega
+    }
+}
+
+impl<T: Scalar> MatVecMul<T> for SSOR<T> {
+    #[inline]
+    fn shape(&self) -> Option<(usize, usize)> {
+        self.sweeps.shape()
+    }
+
+    #[inline]
+    fn mul_vec(&self, v_in: &[T], v_out: &mut [T]) {
+        let n = self.sweeps.inv_diag.len();
+        if n != v_in.len() || n != v_out.len() {
+            panic!("Dimension mismatch");
+        }
+        unsafe {
+            self.mul_vec_unchecked(v_in, v_out);
+        }
+    }
+
+    unsafe fn mul_vec_unchecked(&self, v_in: &[T], v_out: &mut [T]) {
+        // the symmetric sweeps with D / omega, then the factor 2 - omega
+        self.sweeps.mul_vec_unchecked(v_in, v_out);
+        let scale = T::Real::one() + T::Real::one() - self.omega;
+        if scale != T::Real::one() {
+            v_out.iter_mut().for_each(|v| *v = v.mul_real(scale));
+        }
+    }
+
+    #[inline]
+    fn mul_vec_dot(&self, v_in: &[T], v_out: &mut [T]) -> T {
+        let n = self.sweeps.inv_diag.len();
+        if n != v_in.len() || n != v_out.len() {
+            panic!("Dimension mismatch");
+        }
+        unsafe { self.mul_vec_dot_unchecked(v_in, v_out) }
+    }
+
+    #[inline]
+    unsafe fn mul_vec_dot_unchecked(&self, v_in: &[T], v_out: &mut [T]) -> T {
+        self.mul_vec_unchecked(v_in, v_out);
+        conj_dot(v_in, v_out)
+    }
+
+    /// Apply $M^{-T}$ with the transposed sweeps: a forward sweep with $(D/\omega + U)^T$,
+    /// the scaling by $D/\omega$ and a backward sweep with $(D/\omega + L)^T$, then the
+    /// factor $2 - \omega$.
+    fn mul_vec_trans(&self, v_in: &[T], v_out: &mut [T]) -> SolveResult<()> {
+        self.sweeps.mul_vec_trans(v_in, v_out)?;
+        let scale = T::Real::one() + T::Real::one() - self.omega;
+        if scale != T::Real::one() {
+            v_out.iter_mut().for_each(|v| *v = v.mul_real(scale));
+        }
+        Ok(())
+    }
+}
+
+/// Incomplete LU factorization with zero fill-in, ILU(0).
+///
+/// The factors $L$ (unit lower triangular) and $U$ (upper triangular) keep the sparsity
+/// pattern of the matrix, and are stored together in a copy of its CSR structure. The
+/// preconditioner applies $z = U^{-1} L^{-1} r$ with a forward and a backward triangular
+/// solve. It is mostly useful for nonsymmetric systems solved by [`BiCGStab`](crate::BiCGStab).
+#[allow(non_snake_case)]
+pub struct ILU0<T: Scalar> {
+    indptr: Vec<usize>,
+    indices: Vec<usize>,
+    /// The strictly lower part holds $L$ and the rest holds $U$
+    LU: Vec<T>,
+    /// Position of the diagonal entry of each row in `indices` and `LU`
+    diag: Vec<usize>,
+}
+
+impl<T: Scalar> ILU0<T> {
+    /// Factorize the square matrix `A`, stored either in CSR or CSC format.
+    ///
+    /// Return [`SolverError::ZeorDiagonalElem`] if a diagonal entry of `A` is missing, and
+    /// [`SolverError::BreakDown`] with the row index if a zero pivot occurs.
+    #[allow(non_snake_case)]
+    pub fn new<I: SpIndex>(A: CsMatViewI<T, I>) -> SolveResult<Self> {
+        if A.rows() != A.cols() {
+            return Err(SolverError::IncompatibleMatrixFormat(String::from(
+                "Not a square matrix",
+            )));
+        }
+        // copy the structure in CSR format
+        let n = A.rows();
+        let mut tri = TriMat::with_capacity((n, n), A.nnz());
+        for (outer, vec) in A.outer_iterator().enumerate() {
+            for (inner, &v) in vec.iter() {
+                if A.is_csr() {
+                    tri.add_triplet(outer, inner, v);
+                } else {
+                    tri.add_triplet(inner, outer, v);
+                }
+            }
+        }
+        let A: CsMat<T> = tri.to_csr();
+        let mut indptr = Vec::with_capacity(n + 1);
+        let mut indices = Vec::with_capacity(A.nnz());
+        let mut LU = Vec::with_capacity(A.nnz());
+        let mut diag = Vec::with_capacity(n);
+        indptr.push(0);
+        for (i, vec) in A.outer_iterator().enumerate() {
+            for (j, &v) in vec.iter() {
+                if j == i {
+                    diag.push(indices.len());
+                }
+                indices.push(j);
+                LU.push(v);
+            }
+            if diag.len() != i + 1 {
+                return Err(SolverError::ZeorDiagonalElem(i));
+            }
+            indptr.push(indices.len());
+        }
+
+        // IKJ variant of the Gaussian elimination restricted to the sparsity pattern
+        const NONE: usize = usize::MAX;
+        let mut pos = vec![NONE; n]; // column -> position in the current row
+        for i in 0..n {
+            let (st, ed) = (indptr[i], indptr[i + 1]);
+            for p in st..ed {
+                pos[indices[p]] = p;
+            }
+            for p in st..diag[i] {
+                let k = indices[p];
+                let l_ik = LU[p] / LU[diag[k]];
+                LU[p] = l_ik;
+                for q in diag[k] + 1..indptr[k + 1] {
+                    let pj = pos[indices[q]];
+                    if pj != NONE {
+                        let u_kj = LU[q];
+                        LU[pj] -= l_ik * u_kj;
+                    }
+                }
+            }
+            if LU[diag[i]] == T::zero() {
+                return Err(SolverError::BreakDown(i));
+            }
+            for p in st..ed {
+                pos[indices[p]] = NONE;
+            }
+        }
+
+        Ok(ILU0 {
+            indptr,
+            indices,
+            LU,
+            diag,
+        })
+    }
+}
+
+impl<T: Scalar> MatVecMul<T> for ILU0<T> {
+    #[inline]
+    fn shape(&self) -> Option<(usize, usize)> {
+        Some((self.diag.len(), self.diag.len()))
+    }
+
+    #[inline]
+    fn mul_vec(&self, v_in: &[T], v_out: &mut [T]) {
+        if self.diag.len() != v_in.len() || self.diag.len() != v_out.len() {
+            panic!("Dimension mismatch");
+        }
+        unsafe {
+            self.mul_vec_unchecked(v_in, v_out);
+        }
+    }
+
+    unsafe fn mul_vec_unchecked(&self, v_in: &[T], v_out: &mut [T]) {
+        let n = self.diag.len();
+        // forward solve: L y = r
+        for i in 0..n {
+            let mut sigma = *v_in.get_unchecked(i);
+            for p in *self.indptr.get_unchecked(i)..*self.diag.get_unchecked(i) {
+                sigma -= *self.LU.get_unchecked(p)
+                    * *v_out.get_unchecked(*self.indices.get_unchecked(p));
+            }
+            *v_out.get_unchecked_mut(i) = sigma;
+        }
+        // backward solve: U z = y
+        for i in (0..n).rev() {
+            let d = *self.diag.get_unchecked(i);
+            let mut sigma = *v_out.get_unchecked(i);
+            for p in d + 1..*self.indptr.get_unchecked(i + 1) {
+                sigma -= *self.LU.get_unchecked(p)
+                    * *v_out.get_unchecked(*self.indices.get_unchecked(p));
+            }
+            *v_out.get_unchecked_mut(i) = sigma / *self.LU.get_unchecked(d);
+        }
+    }
+
+    #[inline]
+    fn mul_vec_dot(&self, v_in: &[T], v_out: &mut [T]) -> T {
+        if self.diag.len() != v_in.len() || self.diag.len() != v_out.len() {
+            panic!("Dimension mismatch");
+        }
+        unsafe { self.mul_vec_dot_unchecked(v_in, v_out) }
+    }
+
+    #[inline]
+    unsafe fn mul_vec_dot_unchecked(&self, v_in: &[T], v_out: &mut [T]) -> T {
+        self.mul_vec_unchecked(v_in, v_out);
+        conj_dot(v_in, v_out)
+    }
+    /// Apply $M^{-T} = L^{-T} U^{-T}$ with a forward solve with $U^T$ and a backward solve
+    /// with $L^T$.
+    fn mul_vec_trans(&self, v_in: &[T], v_out: &mut [T]) -> SolveResult<()> {
+        let n = self.diag.len();
+        if n != v_in.len() || n != v_out.len() {
+            panic!("Dimension mismatch");
+        }
+        v_out.copy_from_slice(v_in);
+        // forward solve: U^T y = r, sweeping the rows of U as the columns of U^T
+        for i in 0..n {
+            let d = self.diag[i];
+            let y_i = v_out[i] / self.LU[d];
+            v_out[i] = y_i;
+            for p in d + 1..self.indptr[i + 1] {
+                v_out[self.indices[p]] -= self.LU[p] * y_i;
+            }
+        }
+        // backward solve: L^T z = y, with the unit diagonal of L
+        for i in (0..n).rev() {
+            let z_i = v_out[i];
+            for p in self.indptr[i]..self.diag[i] {
+                v_out[self.indices[p]] -= self.LU[p] * z_i;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Incomplete Cholesky factorization with zero fill-in, IC(0), of a Hermitian positive
+/// definite matrix.
+///
+/// The factor $L$ of $A \approx LL^H$ keeps the sparsity pattern of the lower triangular part
+/// of $A$, and only this part is stored in CSR format. The preconditioner applies
+/// $z = L^{-H} L^{-1} r$, which is Hermitian positive definite, so it can be used with
+/// [`CG`](crate::CG) and [`MinRes`](crate::MinRes).
+pub struct IC0<T: Scalar> {
+    indptr: Vec<usize>,
+    indices: Vec<usize>,
+    /// Entries of $L$; the diagonal entry is the last one of each row
+    data: Vec<T>,
+}
+
+impl<T: Scalar> IC0<T> {
+    /// Factorize the Hermitian matrix `A`, stored either in CSR or CSC format. Only the
+    /// lower triangular part of `A` is read.
+    ///
+    /// Return [`SolverError::ZeorDiagonalElem`] if a diagonal entry of `A` is missing, and
+    /// [`SolverError::BreakDown`] with the row index if a pivot is not positive, which means
+    /// that `A` is not positive definite.
+    #[allow(non_snake_case)]
+    pub fn new<I: SpIndex>(A: CsMatViewI<T, I>) -> SolveResult<Self> {
+        if A.rows() != A.cols() {
+            return Err(SolverError::IncompatibleMatrixFormat(String::from(
+                "Not a square matrix",
+            )));
+        }
+
+        // copy the lower triangular part in CSR format
+        let n = A.rows();
+        let mut tri = TriMat::with_capacity((n, n), A.nnz());
+        for (outer, vec) in A.outer_iterator().enumerate() {
+            for (inner, &v) in vec.iter() {
+                let (i, j) = if A.is_csr() {
+                    (outer, inner)
+                } else {
+                    (inner, outer)
+                };
+                if j <= i {
+                    tri.add_triplet(i, j, v);
+                }
+            }
+        }
+        let L: CsMat<T> = tri.to_csr();
+        let mut indptr = Vec::with_capacity(n + 1);
+        let mut indices = Vec::with_capacity(L.nnz());
+        let mut data = Vec::with_capacity(L.nnz());
+        indptr.push(0);
+        for (i, vec) in L.outer_iterator().enumerate() {
+            for (j, &v) in vec.iter() {
+                indices.push(j);
+                data.push(v);
+            }
+            if indices.last() != Some(&i) || indptr[i] == indices.len() {
+                return Err(SolverError::ZeorDiagonalElem(i));
+            }
+            indptr.push(indices.len());
+        }
+
+        // row-oriented factorization restricted to the sparsity pattern
+        const NONE: usize = usize::MAX;
+        let mut pos = vec![NONE; n]; // column -> position in the current row
+        for i in 0..n {
+            let (st, ed) = (indptr[i], indptr[i + 1]);
+            for p in st..ed {
+                pos[indices[p]] = p;
+            }
+            // l_ik = (a_ik - sum_{j<k} l_ij conj(l_kj)) / l_kk
+            for p in st..ed - 1 {
+                let k = indices[p];
+                let mut sigma = data[p];
+                for q in indptr[k]..indptr[k + 1] - 1 {
+                    let pj = pos[indices[q]];
+                    if pj != NONE {
+                        sigma -= data[pj] * data[q].conj();
+                    }
+                }
+                data[p] = sigma / data[indptr[k + 1] - 1];
+            }
+            // l_ii = sqrt(a_ii - sum_{j<i} |l_ij|^2)
+            let d = (st..ed - 1).fold(data[ed - 1].re(), |acc, p| acc - data[p].square());
+            if d <= T::Real::zero() || num_traits::Float::is_nan(d) {
+                return Err(SolverError::BreakDown(i));
+            }
+            data[ed - 1] = T::from_real(num_traits::Float::sqrt(d));
+            for p in st..ed {
+                pos[indices[p]] = NONE;
+            }
+        }
+
+        Ok(IC0 {
+            indptr,
+            indices,
+            data,
+        })
+    }
+}
+
+impl<T: Scalar> MatVecMul<T> for IC0<T> {
+    #[inline]
+    fn shape(&self) -> Option<(usize, usize)> {
+        Some((self.indptr.len() - 1, self.indptr.len() - 1))
+    }
+
+    #[inline]
+    fn mul_vec(&self, v_in: &[T], v_out: &mut [T]) {
+        let n = self.indptr.len() - 1;
+        if n != v_in.len() || n != v_out.len() {
+            panic!("Dimension mismatch");
+        }
+        unsafe {
+            self.mul_vec_unchecked(v_in, v_out);
+        }
+    }
+
+    unsafe fn mul_vec_unchecked(&self, v_in: &[T], v_out: &mut [T]) {
+        let n = self.indptr.len() - 1;
+        // forward solve: L y = r
+        for i in 0..n {
+            let ed = *self.indptr.get_unchecked(i + 1) - 1;
+            let mut sigma = *v_in.get_unchecked(i);
+            for p in *self.indptr.get_unchecked(i)..ed {
+                sigma -= *self.data.get_unchecked(p)
+                    * *v_out.get_unchecked(*self.indices.get_unchecked(p));
+            }
+            *v_out.get_unchecked_mut(i) = sigma / *self.data.get_unchecked(ed);
+        }
+        // backward solve: L^H z = y, sweeping the rows of L as the columns of L^H
+        for i in (0..n).rev() {
+            let ed = *self.indptr.get_unchecked(i + 1) - 1;
+            let z_i = *v_out.get_unchecked(i) / *self.data.get_unchecked(ed);
+            *v_out.get_unchecked_mut(i) = z_i;
+            for p in *self.indptr.get_unchecked(i)..ed {
+                *v_out.get_unchecked_mut(*self.indices.get_unchecked(p)) -=
+                    self.data.get_unchecked(p).conj() * z_i;
+            }
+        }
+    }
+
+    #[inline]
+    fn mul_vec_dot(&self, v_in: &[T], v_out: &mut [T]) -> T {
+        let n = self.indptr.len() - 1;
+        if n != v_in.len() || n != v_out.len() {
+            panic!("Dimension mismatch");
+        }
+        unsafe { self.mul_vec_dot_unchecked(v_in, v_out) }
+    }
+
+    #[inline]
+    unsafe fn mul_vec_dot_unchecked(&self, v_in: &[T], v_out: &mut [T]) -> T {
+        self.mul_vec_unchecked(v_in, v_out);
+        conj_dot(v_in, v_out)
+    }
+}
+
+/// Sparse approximate inverse (SPAI) preconditioner with a fixed sparsity pattern.
+///
+/// Each column $m_j$ of $M^{-1}$ minimizes $|Am_j - e_j|_2$ over the vectors whose nonzero
+/// entries are restricted to the pattern of the column $j$ of $A^{\text{level}}$, so that
+/// $M^{-1}$ minimizes $\|AM^{-1} - I\|_F$ on that pattern. Only the rows of $A$ touched by
+/// the pattern enter each problem, which is then a small dense least-squares problem solved
+/// by a Householder QR factorization. The columns are independent of each other, and
+/// applying the preconditioner is a plain sparse matrix-vector product without triangular
+/// solves, which makes it attractive on highly parallel hardware.
+///
+/// The pattern trades memory and setup time for quality: `level = 1` keeps the pattern of
+/// $A$, and every extra level widens it to the next power of $A$. The number of stored
+/// entries then grows roughly like the nonzeros per column to the power `level`, and the
+/// cost of each least-squares problem like the cube of the pattern size, while $M^{-1}$
+/// gets closer to $A^{-1}$. Since the inverse of a sparse matrix is dense in general, the
+/// approximation is good only when the entries of $A^{-1}$ decay away from the pattern,
+/// e.g. for diagonally dominant matrices.
+///
+/// See M. J. Grote and T. Huckle, *Parallel preconditioning with sparse approximate
+/// inverses*, SIAM J. Sci. Comput., 18 (1997), whose adaptive choice of the pattern is not
+/// implemented.
+pub struct SPAI<T: Scalar> {
+    /// The approximate inverse $M^{-1}$ in CSR format
+    inv: CsMat<T>,
+}
+
+impl<T: Scalar> SPAI<T> {
+    /// Compute the approximate inverse of the square matrix `A`, stored either in CSR or
+    /// CSC format, on the sparsity pattern of $A^{\text{level}}$.
+    ///
+    /// Return [`SolverError::InvalidParameter`] if `level` is zero, and
+    /// [`SolverError::BreakDown`] with the column index if a least-squares problem is rank
+    /// deficient, which happens when `A` is singular.
+    #[allow(non_snake_case)]
+    pub fn new<I: SpIndex>(A: CsMatViewI<T, I>, level: usize) -> SolveResult<Self> {
+        if A.rows() != A.cols() {
+            return Err(SolverError::IncompatibleMatrixFormat(String::from(
+                "Not a square matrix",
+            )));
+        }
+        if level == 0 {
+            return Err(SolverError::InvalidParameter(String::from(
+                "The level of the sparsity pattern must be positive",
+            )));
+        }
+
+        // the columns of A as lists of (row, value)
+        let n = A.rows();
+        let mut cols: Vec<Vec<(usize, T)>> = vec![Vec::new(); n];
+        for (outer, vec) in A.outer_iterator().enumerate() {
+            for (inner, &v) in vec.iter() {
+                if A.is_csr() {
+                    cols[inner].push((outer, v));
+                } else {
+                    cols[outer].push((inner, v));
+                }
+            }
+        }
+
+        // the rows of the columns in `from`, without duplicates, marked with `stamp`
+        let mut mark = vec![usize::MAX; n];
+        let mut stamp = 0;
+        let mut reach = |from: &[usize]| {
+            stamp += 1;
+            let mut rows = Vec::new();
+            for &k in from.iter() {
+                for &(i, _) in cols[k].iter() {
+                    if mark[i] != stamp {
+                        mark[i] = stamp;
+                        rows.push(i);
+                    }
+                }
+            }
+            rows.sort_unstable();
+            rows
+        };
+
+        let mut tri = TriMat::new((n, n));
+        let mut local = vec![usize::MAX; n];
+        for j in 0..n {
+            // the pattern J of the column j of A^level, and the rows I of A[:, J]
+            let mut pattern = vec![j];
+            for _ in 0..level {
+                pattern = reach(&pattern);
+            }
+            let rows = reach(&pattern);
+            for (l, &i) in rows.iter().enumerate() {
+                local[i] = l;
+            }
+
+            // the dense A[I, J] in column-major order, and e_j restricted to I
+            let (m, k) = (rows.len(), pattern.len());
+            let mut a = vec![T::zero(); m * k];
+            for (c, &col) in pattern.iter().enumerate() {
+                for &(i, v) in cols[col].iter() {
+                    a[c * m + local[i]] += v;
+                }
+            }
+            let mut b = vec![T::zero(); m];
+            if local[j] != usize::MAX {
+                b[local[j]] = T::one();
+            }
+            for &i in rows.iter() {
+                local[i] = usize::MAX;
+            }
+
+            if least_squares(&mut a, m, k, &mut b).is_err() {
+                return Err(SolverError::BreakDown(j));
+            }
+            for (&i, &v) in pattern.iter().zip(b.iter()) {
+                tri.add_triplet(i, j, v);
+            }
+        }
+        Ok(SPAI { inv: tri.to_csr() })
+    }
+
+    /// The approximate inverse $M^{-1}$ in CSR format.
+    pub fn matrix(&self) -> &CsMat<T> {
+        &self.inv
+    }
+}
+
+/// Solve the least-squares problem $\min |am - b|_2$ by a Householder QR factorization of
+/// the column-major `rows`x`cols` matrix `a`, in place. On return, the first `cols` entries
+/// of `b` hold $m$. If $a$ doesn't have full column rank, it returns the index of the
+/// column where the factorization stops.
+fn least_squares<T: Scalar>(
+    a: &mut [T],
+    rows: usize,
+    cols: usize,
+    b: &mut [T],
+) -> Result<(), usize> {
+    if rows < cols {
+        return Err(rows);
+    }
+    for k in 0..cols {
+        let (head, tail) = a.split_at_mut((k + 1) * rows);
+        let v = &mut head[k * rows + k..];
+        let norm = Float::sqrt(v.iter().fold(T::Real::zero(), |acc, x| acc + x.square()));
+        if norm == T::Real::zero() {
+            return Err(k);
+        }
+        // v = x - alpha e_1 with alpha = -phase(x_0) |x|, which avoids cancellation, and
+        // the reflector H = I - 2 vv^H / (v^H v) maps x to alpha e_1
+        let x0_abs = v[0].abs();
+        let phase = if x0_abs > T::Real::zero() {
+            v[0] / T::from_real(x0_abs)
+        } else {
+            T::one()
+        };
+        let alpha = -phase.mul_real(norm);
+        v[0] -= alpha;
+        let scale = T::Real::one() / (norm * (norm + x0_abs)); // 2 / (v^H v)
+        for c in tail.chunks_mut(rows).chain(std::iter::once(&mut *b)) {
+            let c = &mut c[k..];
+            let f = conj_dot(&*v, &*c).mul_real(scale);
+            c.iter_mut().zip(v.iter()).for_each(|(c, &v)| *c -= v * f);
+        }
+        v[0] = alpha; // the diagonal entry of R
+    }
+    // back substitution with R
+    for i in (0..cols).rev() {
+        let mut x = b[i];
+        for j in i + 1..cols {
+            x -= a[j * rows + i] * b[j];
+        }
+        b[i] = x / a[i * rows + i];
+    }
+    Ok(())
+}
+
+impl<T: Scalar> MatVecMul<T> for SPAI<T> {
+    #[inline]
+    fn shape(&self) -> Option<(usize, usize)> {
+        Some(self.inv.shape())
+    }
+
+    #[inline]
+    fn mul_vec(&self, v_in: &[T], v_out: &mut [T]) {
+        if self.inv.rows() != v_in.len() || self.inv.rows() != v_out.len() {
+            panic!("Dimension mismatch");
+        }
+        unsafe {
+            self.mul_vec_unchecked(v_in, v_out);
+        }
+    }
+
+    unsafe fn mul_vec_unchecked(&self, v_in: &[T], v_out: &mut [T]) {
+        for (out, vec) in v_out.iter_mut().zip(self.inv.outer_iterator()) {
+            *out = vec
+                .iter()
+                .fold(T::zero(), |acc, (j, &a)| acc + a * *v_in.get_unchecked(j));
+        }
+    }
+
+    #[inline]
+    fn mul_vec_dot(&self, v_in: &[T], v_out: &mut [T]) -> T {
+        if self.inv.rows() != v_in.len() || self.inv.rows() != v_out.len() {
+            panic!("Dimension mismatch");
+        }
+        unsafe { self.mul_vec_dot_unchecked(v_in, v_out) }
+    }
+
+    #[inline]
+    unsafe fn mul_vec_dot_unchecked(&self, v_in: &[T], v_out: &mut [T]) -> T {
+        self.mul_vec_unchecked(v_in, v_out);
+        conj_dot(v_in, v_out)
+    }
+
+    fn mul_vec_trans(&self, v_in: &[T], v_out: &mut [T]) -> SolveResult<()> {
+        if self.inv.rows() != v_in.len() || self.inv.rows() != v_out.len() {
+            panic!("Dimension mismatch");
+        }
+        v_out.iter_mut().for_each(|v| *v = T::zero());
+        for (&x, vec) in v_in.iter().zip(self.inv.outer_iterator()) {
+            for (j, &a) in vec.iter() {
+                v_out[j] += a * x;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The norm of the rows used by [`Equilibration`] to compute the scaling factors.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EquilibrationNorm {
+    /// The Euclidean norm $\left(\sum_j |a_{ij}|^2\right)^{1/2}$
+    Norm2,
+    /// The largest magnitude $\max_j |a_{ij}|$
+    MaxAbs,
+}
+
+/// Symmetric diagonal scaling of a badly scaled matrix, which solves $Ax = b$ as
+///
+/// $$(DAD) y = Db, \quad x = Dy,$$
+///
+/// with the positive diagonal matrix $D$. The scaling preserves the symmetry and the
+/// definiteness of $A$, so the scaled system can be solved by [`CG`](crate::CG) whenever the
+/// original one can.
+///
+/// Since it changes the operator rather than approximating $A^{-1}$, it is the matrix of the
+/// solver, not its preconditioner: it holds $DAD$ and implements [`MatVecMul`] with it.
+/// [`scale_rhs`](Self::scale_rhs) and [`unscale_solution`](Self::unscale_solution) transform
+/// the right-hand side and the solution. Other preconditioners can then be built for $DAD$.
+///
+/// A single pass takes $d_i = 1 / \sqrt{\|a_{i,:}\|}$. The rows of $DAD$ are better balanced,
+/// but not of unit norm, because the column scaling changes them again. The iteration of
+/// D. Ruiz (*A scaling algorithm to equilibrate both rows and columns norms in matrices*,
+/// 2001) repeats the pass on the scaled matrix, $d_i \leftarrow d_i / \sqrt{\|(DAD)_{i,:}\|}$;
+/// with [`EquilibrationNorm::MaxAbs`], the row norms of a symmetric matrix converge to one.
+/// A few passes are usually enough.
+pub struct Equilibration<T: Scalar> {
+    /// The scaled matrix $DAD$ in CSR format
+    mat: CsMat<T>,
+    scale: Vec<T::Real>,
+}
+
+impl<T: Scalar> Equilibration<T> {
+    /// Scale the square matrix `A`, stored either in CSR or CSC format, with `n_passes`
+    /// passes of the Ruiz iteration using the row norm `norm`.
+    ///
+    /// One pass is the plain scaling $d_i = 1 / \sqrt{\|a_{i,:}\|}$, and zero pass keeps the
+    /// matrix unchanged. An empty row keeps its scaling factor.
+    #[allow(non_snake_case)]
+    pub fn new<I: SpIndex>(
+        A: CsMatViewI<T, I>,
+        norm: EquilibrationNorm,
+        n_passes: usize,
+    ) -> SolveResult<Self> {
+        if A.rows() != A.cols() {
+            return Err(SolverError::IncompatibleMatrixFormat(String::from(
+                "Not a square matrix",
+            )));
+        }
+        let n = A.rows();
+        let mut tri = TriMat::with_capacity((n, n), A.nnz());
+        for (outer, vec) in A.outer_iterator().enumerate() {
+            for (inner, &v) in vec.iter() {
+                if A.is_csr() {
+                    tri.add_triplet(outer, inner, v);
+                } else {
+                    tri.add_triplet(inner, outer, v);
+                }
+            }
+        }
+        let mut mat: CsMat<T> = tri.to_csr();
+
+        let mut scale = vec![T::Real::one(); n];
+        let mut row_norm = vec![T::Real::zero(); n];
+        for _ in 0..n_passes {
+            for ((i, vec), r) in mat.outer_iterator().enumerate().zip(row_norm.iter_mut()) {
+                let entries = vec.iter().map(|(j, &a)| a.abs() * scale[i] * scale[j]);
+                *r = match norm {
+                    EquilibrationNorm::Norm2 => {
+                        Float::sqrt(entries.fold(T::Real::zero(), |acc, a| acc + a * a))
+                    }
+                    EquilibrationNorm::MaxAbs => entries.fold(T::Real::zero(), Float::max),
+                };
+            }
+            for (d, &r) in scale.iter_mut().zip(row_norm.iter()) {
+                if r > T::Real::zero() {
+                    *d /= Float::sqrt(r);
+                }
+            }
+        }
+
+        for (i, mut vec) in mat.outer_iterator_mut().enumerate() {
+            for (j, a) in vec.iter_mut() {
+                *a = a.mul_real(scale[i] * scale[j]);
+            }
+        }
+        Ok(Equilibration { mat, scale })
+    }
+
+    /// The diagonal of $D$.
+    pub fn scaling(&self) -> &[T::Real] {
+        self.scale.as_slice()
+    }
+
+    /// The scaled matrix $DAD$ in CSR format.
+    pub fn matrix(&self) -> &CsMat<T> {
+        &self.mat
+    }
+
+    /// Compute the right-hand side $Db$ of the scaled system.
+    pub fn scale_rhs(&self, rhs: &[T], out: &mut [T]) {
+        self.mul_scale(rhs, out);
+    }
+
+    /// Compute the solution $x = Dy$ of the original system from the solution `y` of the
+    /// scaled one.
+    pub fn unscale_solution(&self, y: &[T], x: &mut [T]) {
+        self.mul_scale(y, x);
+    }
+
+    fn mul_scale(&self, v_in: &[T], v_out: &mut [T]) {
+        if self.scale.len() != v_in.len() || self.scale.len() != v_out.len() {
+            panic!("Dimension mismatch");
+        }
+        for (r, (v, &d)) in v_out.iter_mut().zip(v_in.iter().zip(self.scale.iter())) {
+            *r = v.mul_real(d);
+        }
+    }
+}
+
+impl<T: Scalar> MatVecMul<T> for Equilibration<T> {
+    #[inline]
+    fn shape(&self) -> Option<(usize, usize)> {
+        Some((self.scale.len(), self.scale.len()))
+    }
+
+    #[inline]
+    fn mul_vec(&self, v_in: &[T], v_out: &mut [T]) {
+        if self.scale.len() != v_in.len() || self.scale.len() != v_out.len() {
+            panic!("Dimension mismatch");
+        }
+        unsafe {
+            self.mul_vec_unchecked(v_in, v_out);
+        }
+    }
+
+    unsafe fn mul_vec_unchecked(&self, v_in: &[T], v_out: &mut [T]) {
+        for (out, vec) in v_out.iter_mut().zip(self.mat.outer_iterator()) {
+            *out = vec
+                .iter()
+                .fold(T::zero(), |acc, (j, &a)| acc + a * *v_in.get_unchecked(j));
+        }
+    }
+
+    #[inline]
+    fn mul_vec_dot(&self, v_in: &[T], v_out: &mut [T]) -> T {
+        if self.scale.len() != v_in.len() || self.scale.len() != v_out.len() {
+            panic!("Dimension mismatch");
+        }
+        unsafe { self.mul_vec_dot_unchecked(v_in, v_out) }
+    }
+
+    #[inline]
+    unsafe fn mul_vec_dot_unchecked(&self, v_in: &[T], v_out: &mut [T]) -> T {
+        self.mul_vec_unchecked(v_in, v_out);
+        conj_dot(v_in, v_out)
+    }
+
+    fn mul_vec_trans(&self, v_in: &[T], v_out: &mut [T]) -> SolveResult<()> {
+        if self.scale.len() != v_in.len() || self.scale.len() != v_out.len() {
+            panic!("Dimension mismatch");
+        }
+        v_out.iter_mut().for_each(|v| *v = T::zero());
+        for (&x, vec) in v_in.iter().zip(self.mat.outer_iterator()) {
+            for (j, &a) in vec.iter() {
+                v_out[j] += a * x;
+            }
+        }
+        Ok(())
+    }
+
+    fn diagonal(&self, out: &mut [T]) {
+        if self.scale.len() != out.len() {
+            panic!("Dimension mismatch");
+        }
+        for (i, (vec, d)) in self.mat.outer_iterator().zip(out.iter_mut()).enumerate() {
+            *d = vec.get(i).copied().unwrap_or_else(T::zero);
+        }
+    }
 }
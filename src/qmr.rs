@@ -0,0 +1,263 @@
+//! An impl of the Quasi-Minimal Residual algorithm for linear sparse solve.
+
+use super::{
+    config::{Deadline, SolveConfig},
+    error::*,
+    report::*,
+    solver::{l2_rel_tol, square_size, IterativeSolver},
+    vecalg::*,
+    MatVecMul,
+};
+use cauchy::Scalar;
+use num_traits::{float::*, One, Zero};
+use std::{
+    intrinsics::unlikely, ops::ControlFlow, ptr::copy_nonoverlapping, slice::from_raw_parts_mut,
+};
+
+/// Implementation of the Quasi-Minimal Residual (QMR) algorithm without look-ahead for
+/// solving non-symmetric sparse linear systems.
+///
+/// It builds the same Krylov subspaces as [`BiCG`](crate::BiCG) with the coupled two-term
+/// recurrences of the nonsymmetric Lanczos process, $AP_k = V_{k+1}L_k$ with a lower
+/// bidiagonal $L_k$, and minimizes $|\rho_1 e_1 - L_k z|$ with Givens rotations instead of
+/// the residual itself. This smooths the irregular convergence of BiCG. Like BiCG, it
+/// applies $A^T$ to the shadow vectors, hence the operator must provide
+/// [`MatVecMul::mul_vec_trans`], whose error is returned otherwise, and it uses the bilinear
+/// form $w^Tv$.
+///
+/// Without look-ahead, the Lanczos process breaks down when $w^Tv$ or $q^TAp$ vanishes; the
+/// solver then returns [`SolverError::BreakDown`].
+///
+/// See R. W. Freund and N. M. Nachtigal, *An implementation of the QMR method based on
+/// coupled two-term recurrences*, SIAM J. Sci. Comput., 15 (1994).
+#[allow(non_snake_case)]
+pub struct QMR<'data, T: Scalar, M: MatVecMul<T>, V: VecOps<T> = CpuVecOps> {
+    A: &'data M,
+    workspace: Vec<T>,
+    size: usize,
+    ops: V,
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>> QMR<'data, T, M> {
+    #[allow(non_snake_case)]
+    pub fn new(A: &'data M, size: usize) -> Self {
+        Self::with_vec_ops(A, size, CpuVecOps)
+    }
+
+    /// Create a solver for the operator `A`, whose size is given by its
+    /// [`shape`](MatVecMul::shape).
+    ///
+    /// Return [`SolverError::IncompatibleMatrixFormat`] if `A` is not square or doesn't provide
+    /// its shape.
+    #[allow(non_snake_case)]
+    pub fn from_op(A: &'data M) -> SolveResult<Self> {
+        Ok(Self::with_vec_ops(A, square_size(A)?, CpuVecOps))
+    }
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>, V: VecOps<T>> QMR<'data, T, M, V> {
+    /// Create a solver performing its vector operations through `ops`.
+    #[allow(non_snake_case)]
+    pub fn with_vec_ops(A: &'data M, size: usize, ops: V) -> Self {
+        QMR {
+            A,
+            workspace: vec![T::zero(); size * 9],
+            size,
+            ops,
+        }
+    }
+
+    /// Solves Ax = b, without preconditioner
+    ///
+    /// Each iteration performs one product with $A$ and one with $A^T$. The convergence test
+    /// uses the recursively updated residual.
+    pub fn solve(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<(usize, T::Real)> {
+        self.solve_impl(rhs, x, max_iter, tol, |_, _| ControlFlow::Continue(()))
+    }
+
+    /// The QMR iterations. `monitor` is called with the iteration number and the relative
+    /// residual used by the convergence test, starting from the initial residual. The
+    /// iterations stop when it returns [`ControlFlow::Break`].
+    #[allow(clippy::many_single_char_names)]
+    fn solve_impl<F: FnMut(usize, T::Real) -> ControlFlow<()>>(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+        mut monitor: F,
+    ) -> SolveResult<(usize, T::Real)> {
+        let n = rhs.len();
+        // check the format
+        if n != self.size {
+            return Err(SolverError::IncompatibleMatrixFormat(String::from(
+                "Input vec dimension doesn't match the matrix size",
+            )));
+        }
+        if n != x.len() {
+            return Err(SolverError::IncompatibleMatrixFormat(String::from(
+                "Input and output vec dimension do not match",
+            )));
+        }
+
+        let rhs_norm = self.ops.norm2(rhs);
+        if unlikely(rhs_norm <= T::Real::epsilon()) {
+            // when rhs = 0, x is set to zero.
+            x.iter_mut().for_each(|v| *v = T::zero());
+            return Ok((0, rhs_norm));
+        }
+        let threshold = tol * rhs_norm;
+
+        // set vectors using preallocated memeory
+        let ptr = self.workspace.as_mut_ptr();
+        let r = unsafe { from_raw_parts_mut(ptr, n) }; // &mut [T]
+        let v = unsafe { from_raw_parts_mut(ptr.add(n), n) };
+        let w = unsafe { from_raw_parts_mut(ptr.add(2 * n), n) };
+        let p = unsafe { from_raw_parts_mut(ptr.add(3 * n), n) };
+        let q = unsafe { from_raw_parts_mut(ptr.add(4 * n), n) };
+        let ap = unsafe { from_raw_parts_mut(ptr.add(5 * n), n) };
+        let atq = unsafe { from_raw_parts_mut(ptr.add(6 * n), n) };
+        let d = unsafe { from_raw_parts_mut(ptr.add(7 * n), n) };
+        let ad = unsafe { from_raw_parts_mut(ptr.add(8 * n), n) };
+
+        unsafe {
+            self.A.mul_vec_unchecked(x, &mut *ap); // ap = A * x
+        }
+        self.ops.waxpy(-T::one(), &*ap, rhs, &mut *r); // r = rhs - A*x
+        let res_norm = self.ops.norm2(&*r);
+        if unlikely(monitor(0, res_norm / rhs_norm).is_break() || res_norm <= threshold) {
+            return Ok((0, res_norm / rhs_norm));
+        }
+        unsafe {
+            copy_nonoverlapping(r.as_ptr(), v.as_mut_ptr(), n); // v = r
+            copy_nonoverlapping(r.as_ptr(), w.as_mut_ptr(), n); // w = r
+        }
+        d.iter_mut().for_each(|v| *v = T::zero());
+        ad.iter_mut().for_each(|v| *v = T::zero());
+        // the norms of the Lanczos vectors before normalization
+        let (mut rho, mut xi) = (res_norm, res_norm);
+        // the last entry of the rotated right-hand side rho_1 e_1
+        let mut g = T::from_real(res_norm);
+        // the previous rotation and Lanczos coefficient
+        let (mut c_prev, mut s_prev) = (T::Real::one(), T::zero());
+        let mut eps_prev = T::one();
+
+        for its in 0..max_iter {
+            if unlikely(rho.is_zero() || xi.is_zero()) {
+                return Err(SolverError::BreakDown(its));
+            }
+            self.ops.rscale(T::Real::one() / rho, &mut *v);
+            self.ops.rscale(T::Real::one() / xi, &mut *w);
+            let delta = self.ops.dot(&*w, &*v); // w^T v
+            if unlikely(delta.abs() <= T::Real::epsilon()) {
+                return Err(SolverError::BreakDown(its));
+            }
+
+            if its == 0 {
+                unsafe {
+                    copy_nonoverlapping(v.as_ptr(), p.as_mut_ptr(), n); // p = v
+                    copy_nonoverlapping(w.as_ptr(), q.as_mut_ptr(), n); // q = w
+                }
+            } else {
+                // p = v - (xi delta / eps) p, q = w - (rho delta / eps) q
+                let coef = delta / eps_prev;
+                self.ops.axpby(T::one(), &*v, -coef.mul_real(xi), &mut *p);
+                self.ops.axpby(T::one(), &*w, -coef.mul_real(rho), &mut *q);
+            }
+            unsafe {
+                self.A.mul_vec_unchecked(&*p, &mut *ap); // ap = A * p
+            }
+            let eps = self.ops.dot(&*q, &*ap); // q^T A p
+            let ap_norm = self.ops.norm2(&*ap);
+            if unlikely(!ap_norm.is_finite()) {
+                return Err(SolverError::Diverged(its));
+            }
+            if unlikely(eps.abs() <= T::Real::epsilon() * self.ops.norm2(&*q) * ap_norm) {
+                return Err(SolverError::BreakDown(its));
+            }
+            let beta = eps / delta;
+
+            // the next Lanczos vectors, A p = v_next + beta v
+            self.ops.axpby(T::one(), &*ap, -beta, &mut *v); // v = A p - beta v
+            self.A.mul_vec_trans(&*q, &mut *atq)?; // atq = A^T * q
+            self.ops.axpby(T::one(), &*atq, -beta, &mut *w); // w = A^T q - beta w
+            let rho_next = self.ops.norm2(&*v);
+            xi = self.ops.norm2(&*w);
+
+            // the column (beta, rho_next) of L, rotated by the previous rotation
+            let r_off = s_prev * beta;
+            let mut r_diag = beta.mul_real(c_prev);
+            let mut sub = T::from_real(rho_next);
+            let (c, s) = rotg(&mut r_diag, &mut sub);
+            if unlikely(r_diag.is_zero()) {
+                return Err(SolverError::BreakDown(its));
+            }
+            let tau = g.mul_real(c);
+            g = -s.conj() * g;
+
+            // d = (p - r_off d) / r_diag, and the same for A d
+            let inv = T::one() / r_diag;
+            self.ops.axpby(inv, &*p, -r_off * inv, &mut *d);
+            self.ops.axpby(inv, &*ap, -r_off * inv, &mut *ad);
+            self.ops.axpy(tau, &*d, &mut *x); // x += tau*d
+            self.ops.axpy(-tau, &*ad, &mut *r); // r -= tau*Ad
+
+            let res_norm = self.ops.norm2(&*r);
+            if unlikely(!res_norm.is_finite()) {
+                return Err(SolverError::Diverged(its + 1));
+            }
+            if monitor(its + 1, res_norm / rhs_norm).is_break() || res_norm <= threshold {
+                return Ok((its + 1, res_norm / rhs_norm));
+            }
+            rho = rho_next;
+            c_prev = c;
+            s_prev = s;
+            eps_prev = eps;
+        }
+
+        Err(SolverError::InsufficientIterNum(max_iter))
+    }
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>, V: VecOps<T>> IterativeSolver<T> for QMR<'data, T, M, V> {
+    fn solve(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<(usize, T::Real)> {
+        QMR::solve(self, rhs, x, max_iter, tol)
+    }
+
+    fn solve_with_config(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        config: &SolveConfig<T::Real>,
+    ) -> SolveResult<(usize, T::Real)> {
+        let tol = l2_rel_tol(rhs, config)?;
+        let mut deadline = Deadline::new(config.time_limit);
+        let ret = self.solve_impl(rhs, x, config.max_iter, tol, |its, res| {
+            deadline.monitor(its, res)
+        });
+        deadline.finish(ret)
+    }
+
+    fn solve_report(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<SolveReport<T::Real>> {
+        let ret = QMR::solve(self, rhs, x, max_iter, tol);
+        report_outcome(self.A, &self.ops, rhs, x, &mut self.workspace, ret)
+    }
+}
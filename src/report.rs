@@ -0,0 +1,147 @@
+//! Diagnostics reported by the solvers.
+
+use super::{error::*, vecalg::*, MatVecMul};
+use cauchy::Scalar;
+use num_traits::{float::*, Zero};
+
+/// The outcome of a solve, with diagnostics beyond the iteration number and residual.
+///
+/// Unlike the `(iterations, residual)` tuple returned by `solve`, it tells whether the
+/// iterations stopped because they converged, broke down, or ran out of iterations, see
+/// [`IterativeSolver::solve_report`](crate::IterativeSolver::solve_report).
+///
+/// A value that the solver could not compute, e.g. the true residual in the default
+/// [`solve_report`](crate::IterativeSolver::solve_report), is NaN.
+///
+/// With the `serde` feature, it can be serialized, e.g. to log the outcomes of a batch of
+/// solves as JSON.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SolveReport<R> {
+    /// Number of iterations
+    pub iterations: usize,
+    /// Relative residual reported by the solver, which is usually computed by the recurrence
+    /// of the iterations. When the solver did not converge, it is the true relative residual
+    /// $|b - Ax| / |b|$ of the returned `x`.
+    ///
+    /// The default [`solve_report`](crate::IterativeSolver::solve_report) cannot compute the
+    /// true residual, and sets it to NaN after a breakdown or the iteration limit.
+    pub rel_residual: R,
+    /// The true residual $|b - Ax|$ of the returned `x`.
+    ///
+    /// The default [`solve_report`](crate::IterativeSolver::solve_report) sets it to
+    /// `rel_residual` times $|b|$ instead, which is NaN whenever `rel_residual` is.
+    pub abs_residual: R,
+    /// $|r_{true} - r_{reported}| / r_{reported}$, where $r_{true} = |b - Ax| / |b|$ is the
+    /// true relative residual of the returned solution.
+    ///
+    /// A large gap means that the recurrence residual drifted away from the true residual,
+    /// and that the solution may be less accurate than reported. It is zero when the solver
+    /// did not converge, since `rel_residual` is then the true residual.
+    ///
+    /// The default [`solve_report`](crate::IterativeSolver::solve_report) cannot compute the
+    /// true residual, and sets it to NaN in every case.
+    pub residual_gap: R,
+    /// Whether the convergence test passed.
+    pub converged: bool,
+    /// The iteration at which the solver broke down, see [`SolverError::BreakDown`].
+    pub breakdown: Option<usize>,
+    /// The relative residual of every iteration, starting from the initial one, if it was
+    /// recorded. See [`with_history`](Self::with_history).
+    pub history: Option<Vec<R>>,
+}
+
+impl<R> SolveReport<R> {
+    /// Attach the residual `history` of the solve, e.g. the one returned by
+    /// [`MinRes::solve_with_history`](crate::MinRes::solve_with_history).
+    pub fn with_history(mut self, history: Vec<R>) -> Self {
+        self.history = Some(history);
+        self
+    }
+}
+
+/// The true residual $|b - Ax|$ and the relative residual $|b - Ax| / |b|$, using `work` as
+/// scratch memory.
+#[allow(non_snake_case)]
+fn true_residual<T: Scalar, M: MatVecMul<T>, V: VecOps<T>>(
+    A: &M,
+    ops: &V,
+    rhs: &[T],
+    x: &[T],
+    work: &mut [T],
+) -> (T::Real, T::Real) {
+    let n = rhs.len();
+    let r = &mut work[..n];
+    unsafe {
+        A.mul_vec_unchecked(x, &mut *r);
+    }
+    ops.axpby(T::one(), rhs, -T::one(), &mut *r); // r = rhs - A*x
+
+    let rhs_norm = ops.norm2(rhs);
+    let res_norm = ops.norm2(&*r);
+    if rhs_norm > T::Real::epsilon() {
+        (res_norm, res_norm / rhs_norm)
+    } else {
+        (res_norm, res_norm)
+    }
+}
+
+/// Compute the true relative residual $|b - Ax| / |b|$ using `work` as scratch memory, and
+/// build the report of a solve that reported `rel_residual` after `iterations` iterations.
+#[allow(non_snake_case)]
+pub(crate) fn check_residual<T: Scalar, M: MatVecMul<T>, V: VecOps<T>>(
+    A: &M,
+    ops: &V,
+    rhs: &[T],
+    x: &[T],
+    work: &mut [T],
+    (iterations, rel_residual): (usize, T::Real),
+) -> SolveReport<T::Real> {
+    let (abs_residual, true_residual) = true_residual(A, ops, rhs, x, work);
+    let diff = num_traits::Float::abs(true_residual - rel_residual);
+    let residual_gap = if rel_residual > T::Real::zero() {
+        diff / rel_residual
+    } else {
+        diff
+    };
+    SolveReport {
+        iterations,
+        rel_residual,
+        abs_residual,
+        residual_gap,
+        converged: true,
+        breakdown: None,
+        history: None,
+    }
+}
+
+/// Build the report of a solve that returned `ret` and left `x`, using `work` as scratch
+/// memory. A breakdown, the iteration limit and stagnation are reported with the true
+/// residual of `x`; the other errors are returned as they are.
+#[allow(non_snake_case)]
+pub(crate) fn report_outcome<T: Scalar, M: MatVecMul<T>, V: VecOps<T>>(
+    A: &M,
+    ops: &V,
+    rhs: &[T],
+    x: &[T],
+    work: &mut [T],
+    ret: SolveResult<(usize, T::Real)>,
+) -> SolveResult<SolveReport<T::Real>> {
+    let (iterations, breakdown) = match ret {
+        Ok(ret) => return Ok(check_residual(A, ops, rhs, x, work, ret)),
+        Err(SolverError::BreakDown(its)) => (its, Some(its)),
+        Err(SolverError::InsufficientIterNum(its)) => (its, None),
+        Err(SolverError::Stagnation { iter, .. }) => (iter, None),
+        Err(e) => return Err(e),
+    };
+    let (abs_residual, rel_residual) = true_residual(A, ops, rhs, x, work);
+    Ok(SolveReport {
+        iterations,
+        rel_residual,
+        abs_residual,
+        residual_gap: T::Real::zero(),
+        converged: false,
+        breakdown,
+        history: None,
+    })
+}
@@ -0,0 +1,137 @@
+//! An impl of the (preconditioned) Richardson iteration.
+
+use super::{error::*, precond::Precond, solver::square_size, vecalg::*, MatVecMul};
+use cauchy::Scalar;
+use num_traits::float::*;
+use std::{intrinsics::unlikely, slice::from_raw_parts_mut};
+
+/// The damped Richardson iteration $x \leftarrow x + \omega M^{-1}(b - Ax)$.
+///
+/// It converges when the spectral radius of $I - \omega M^{-1}A$ is below one, e.g. for
+/// Hermitian positive definite $M^{-1}A$ with $0 < \omega < 2 / \lambda_{max}$. Its main uses
+/// are teaching and smoothing in multigrid methods, see [`smooth`](Self::smooth).
+#[allow(non_snake_case)]
+pub struct Richardson<'data, T: Scalar, M: MatVecMul<T>, V: VecOps<T> = CpuVecOps> {
+    A: &'data M,
+    workspace: Vec<T>,
+    size: usize,
+    ops: V,
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>> Richardson<'data, T, M> {
+    #[allow(non_snake_case)]
+    pub fn new(A: &'data M, size: usize) -> Self {
+        Self::with_vec_ops(A, size, CpuVecOps)
+    }
+
+    /// Create a solver for the operator `A`, whose size is given by its
+    /// [`shape`](MatVecMul::shape).
+    ///
+    /// Return [`SolverError::IncompatibleMatrixFormat`] if `A` is not square or doesn't provide
+    /// its shape.
+    #[allow(non_snake_case)]
+    pub fn from_op(A: &'data M) -> SolveResult<Self> {
+        Ok(Self::with_vec_ops(A, square_size(A)?, CpuVecOps))
+    }
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>, V: VecOps<T>> Richardson<'data, T, M, V> {
+    /// Create a solver performing its vector operations through `ops`.
+    #[allow(non_snake_case)]
+    pub fn with_vec_ops(A: &'data M, size: usize, ops: V) -> Self {
+        Richardson {
+            A,
+            workspace: vec![T::zero(); size * 2],
+            size,
+            ops,
+        }
+    }
+
+    /// Solves Ax = b with the relaxation parameter `omega` and the preconditioner `precond`.
+    /// Use [`Identity`](crate::precond::Identity) for the unpreconditioned iteration.
+    ///
+    /// The convergence test uses the unpreconditioned residual $|b - Ax| / |b|$.
+    pub fn solve(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        omega: T::Real,
+        precond: &dyn Precond<T>,
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<(usize, T::Real)> {
+        self.check_dims(rhs, x)?;
+        let n = rhs.len();
+        let rhs_norm = self.ops.norm2(rhs);
+        if unlikely(rhs_norm <= T::Real::epsilon()) {
+            // when rhs = 0, x is set to zero.
+            x.iter_mut().for_each(|v| *v = T::zero());
+            return Ok((0, rhs_norm));
+        }
+        let threshold = tol * rhs_norm;
+
+        let ptr = self.workspace.as_mut_ptr();
+        let r = unsafe { from_raw_parts_mut(ptr, n) }; // &mut [T]
+        let z = unsafe { from_raw_parts_mut(ptr.add(n), n) };
+        for its in 0..=max_iter {
+            self.residual(rhs, x, &mut *r);
+            let res_norm = self.ops.norm2(&*r);
+            if unlikely(!res_norm.is_finite()) {
+                return Err(SolverError::Diverged(its));
+            }
+            if res_norm <= threshold {
+                return Ok((its, res_norm / rhs_norm));
+            }
+            if its == max_iter {
+                break;
+            }
+            precond.apply(&*r, &mut *z);
+            self.ops.axpy(T::from_real(omega), &*z, &mut *x); // x += omega * M^{-1} r
+        }
+
+        Err(SolverError::InsufficientIterNum(max_iter))
+    }
+
+    /// Perform `n_sweeps` unpreconditioned iterations $x \leftarrow x + \omega(b - Ax)$,
+    /// without any convergence test.
+    ///
+    /// It is meant to damp the high-frequency error components, as the smoother of a
+    /// multigrid cycle.
+    pub fn smooth(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        omega: T::Real,
+        n_sweeps: usize,
+    ) -> SolveResult<()> {
+        self.check_dims(rhs, x)?;
+        let r = unsafe { from_raw_parts_mut(self.workspace.as_mut_ptr(), rhs.len()) };
+        for _ in 0..n_sweeps {
+            self.residual(rhs, x, &mut *r);
+            self.ops.axpy(T::from_real(omega), &*r, &mut *x); // x += omega * r
+        }
+        Ok(())
+    }
+
+    /// r = rhs - A*x
+    fn residual(&self, rhs: &[T], x: &[T], r: &mut [T]) {
+        unsafe {
+            self.A.mul_vec_unchecked(x, &mut *r);
+        }
+        self.ops.axpby(T::one(), rhs, -T::one(), r);
+    }
+
+    fn check_dims(&self, rhs: &[T], x: &[T]) -> SolveResult<()> {
+        if rhs.len() != self.size {
+            return Err(SolverError::IncompatibleMatrixFormat(String::from(
+                "Input vec dimension doesn't match the matrix size",
+            )));
+        }
+        if rhs.len() != x.len() {
+            return Err(SolverError::IncompatibleMatrixFormat(String::from(
+                "Input and output vec dimension do not match",
+            )));
+        }
+        Ok(())
+    }
+}
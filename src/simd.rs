@@ -0,0 +1,210 @@
+//! Vectorized kernels of the vector operations on real `f32`/`f64` slices, using
+//! `std::simd`.
+//!
+//! Each function returns `None` (or `false`) when the scalar type is not handled, in which
+//! case the caller falls back to its scalar loop. The reductions accumulate lane-wise
+//! partial sums, so their results differ from the scalar loops only by reassociation.
+
+use cauchy::Scalar;
+use std::simd::prelude::*;
+
+macro_rules! kernels {
+    ($ty:ty, $simd:ty, $dot:ident, $sum_sq:ident, $axpy:ident, $scale:ident) => {
+        #[inline]
+        fn $dot(x: &[$ty], y: &[$ty]) -> $ty {
+            const LANES: usize = <$simd>::LEN;
+            let (x_chunks, x_rem) = x.as_chunks::<LANES>();
+            let (y_chunks, y_rem) = y.as_chunks::<LANES>();
+            let mut acc = <$simd>::splat(0.);
+            for (a, b) in x_chunks.iter().zip(y_chunks.iter()) {
+                acc += <$simd>::from_array(*a) * <$simd>::from_array(*b);
+            }
+            x_rem
+                .iter()
+                .zip(y_rem.iter())
+                .fold(acc.reduce_sum(), |s, (a, b)| s + a * b)
+        }
+
+        #[inline]
+        fn $sum_sq(x: &[$ty]) -> $ty {
+            const LANES: usize = <$simd>::LEN;
+            let (chunks, rem) = x.as_chunks::<LANES>();
+            let mut acc = <$simd>::splat(0.);
+            for a in chunks.iter() {
+                let v = <$simd>::from_array(*a);
+                acc += v * v;
+            }
+            rem.iter().fold(acc.reduce_sum(), |s, a| s + a * a)
+        }
+
+        #[inline]
+        fn $axpy(a: $ty, x: &[$ty], y: &mut [$ty]) {
+            const LANES: usize = <$simd>::LEN;
+            let av = <$simd>::splat(a);
+            let (x_chunks, x_rem) = x.as_chunks::<LANES>();
+            let (y_chunks, y_rem) = y.as_chunks_mut::<LANES>();
+            for (u, v) in x_chunks.iter().zip(y_chunks.iter_mut()) {
+                *v = (<$simd>::from_array(*v) + <$simd>::from_array(*u) * av).to_array();
+            }
+            y_rem
+                .iter_mut()
+                .zip(x_rem.iter())
+                .for_each(|(v, u)| *v += *u * a);
+        }
+
+        #[inline]
+        fn $scale(a: $ty, x: &mut [$ty]) {
+            const LANES: usize = <$simd>::LEN;
+            let av = <$simd>::splat(a);
+            let (chunks, rem) = x.as_chunks_mut::<LANES>();
+            for v in chunks.iter_mut() {
+                *v = (<$simd>::from_array(*v) * av).to_array();
+            }
+            rem.iter_mut().for_each(|v| *v *= a);
+        }
+    };
+}
+
+kernels! {f32, f32x16, dot_f32, sum_sq_f32, axpy_f32, scale_f32}
+kernels! {f64, f64x8, dot_f64, sum_sq_f64, axpy_f64, scale_f64}
+
+/// View a slice of `T` as a slice of `B`, `T` and `B` being the same type.
+#[inline(always)]
+fn cast_slice<T: 'static, B: 'static>(x: &[T]) -> &[B] {
+    debug_assert!(super::same_type::<T, B>());
+    unsafe { std::slice::from_raw_parts(x.as_ptr() as *const B, x.len()) }
+}
+
+#[inline(always)]
+fn cast_slice_mut<T: 'static, B: 'static>(x: &mut [T]) -> &mut [B] {
+    debug_assert!(super::same_type::<T, B>());
+    unsafe { std::slice::from_raw_parts_mut(x.as_mut_ptr() as *mut B, x.len()) }
+}
+
+/// $x^T y$ for real vectors.
+#[inline]
+pub(crate) fn dot<T: Scalar>(x: &[T], y: &[T]) -> Option<T> {
+    if super::same_type::<T, f64>() {
+        let r = dot_f64(cast_slice(x), cast_slice(y));
+        return Some(super::cast_as(&r));
+    }
+    if super::same_type::<T, f32>() {
+        let r = dot_f32(cast_slice(x), cast_slice(y));
+        return Some(super::cast_as(&r));
+    }
+    None
+}
+
+/// $\sum_i x_i^2$ for real vectors.
+#[inline]
+pub(crate) fn sum_squares<T: Scalar>(x: &[T]) -> Option<T::Real> {
+    if super::same_type::<T, f64>() {
+        let r = sum_sq_f64(cast_slice(x));
+        return Some(super::cast_as(&r));
+    }
+    if super::same_type::<T, f32>() {
+        let r = sum_sq_f32(cast_slice(x));
+        return Some(super::cast_as(&r));
+    }
+    None
+}
+
+/// y = y + a*x for real vectors, when `a` has the type of the entries.
+#[inline]
+pub(crate) fn axpy<S: Copy + 'static, T: Scalar>(a: S, x: &[T], y: &mut [T]) -> bool {
+    if super::same_type::<S, f64>() && super::same_type::<T, f64>() {
+        axpy_f64(super::cast_as(&a), cast_slice(x), cast_slice_mut(y));
+        return true;
+    }
+    if super::same_type::<S, f32>() && super::same_type::<T, f32>() {
+        axpy_f32(super::cast_as(&a), cast_slice(x), cast_slice_mut(y));
+        return true;
+    }
+    false
+}
+
+/// x = x * a for real vectors.
+#[inline]
+pub(crate) fn scale<S: Copy + 'static, T: Scalar>(a: S, x: &mut [T]) -> bool {
+    if super::same_type::<S, f64>() && super::same_type::<T, f64>() {
+        scale_f64(super::cast_as(&a), cast_slice_mut(x));
+        return true;
+    }
+    if super::same_type::<S, f32>() && super::same_type::<T, f32>() {
+        scale_f32(super::cast_as(&a), cast_slice_mut(x));
+        return true;
+    }
+    false
+}
+
+// --------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_complex::Complex64;
+
+    const LENGTHS: [usize; 12] = [0, 1, 3, 7, 8, 15, 16, 17, 31, 33, 100, 1027];
+
+    fn sample(n: usize, k: usize) -> Vec<f64> {
+        (0..n)
+            .map(|i| (((i + 1) * (k + 3)) % 11) as f64 / 3. - 1.5)
+            .collect()
+    }
+
+    #[test]
+    fn simd_matches_scalar_f64() {
+        for &n in LENGTHS.iter() {
+            let x = sample(n, 0);
+            let y = sample(n, 1);
+            let tol = 1E-12 * (n as f64 + 1.);
+
+            let expected = x.iter().zip(y.iter()).fold(0., |s, (a, b)| s + a * b);
+            approx::assert_abs_diff_eq!(dot(&x, &y).unwrap(), expected, epsilon = tol);
+            let expected = x.iter().fold(0., |s, a| s + a * a);
+            approx::assert_abs_diff_eq!(sum_squares(&x).unwrap(), expected, epsilon = tol);
+
+            let mut z = y.clone();
+            assert!(axpy(0.75, &x, &mut z));
+            for ((u, v), w) in x.iter().zip(y.iter()).zip(z.iter()) {
+                assert_eq!(*w, v + u * 0.75);
+            }
+            assert!(scale(-2.5, &mut z));
+            for ((u, v), w) in x.iter().zip(y.iter()).zip(z.iter()) {
+                assert_eq!(*w, (v + u * 0.75) * -2.5);
+            }
+        }
+    }
+
+    #[test]
+    fn simd_matches_scalar_f32() {
+        for &n in LENGTHS.iter() {
+            let x: Vec<f32> = sample(n, 2).iter().map(|&v| v as f32).collect();
+            let y: Vec<f32> = sample(n, 3).iter().map(|&v| v as f32).collect();
+            let tol = 1E-5 * (n as f32 + 1.);
+
+            let expected = x.iter().zip(y.iter()).fold(0., |s, (a, b)| s + a * b);
+            approx::assert_abs_diff_eq!(dot(&x, &y).unwrap(), expected, epsilon = tol);
+            let expected = x.iter().fold(0., |s, a| s + a * a);
+            approx::assert_abs_diff_eq!(sum_squares(&x).unwrap(), expected, epsilon = tol);
+
+            let mut z = y.clone();
+            assert!(axpy(0.75_f32, &x, &mut z));
+            for ((u, v), w) in x.iter().zip(y.iter()).zip(z.iter()) {
+                assert_eq!(*w, v + u * 0.75);
+            }
+        }
+    }
+
+    #[test]
+    fn simd_skips_other_types() {
+        let x = vec![Complex64::new(1., 2.); 20];
+        let mut y = x.clone();
+        assert!(dot(&x, &x).is_none());
+        assert!(sum_squares(&x).is_none());
+        assert!(!axpy(Complex64::new(1., 0.), &x, &mut y));
+        // a real factor for complex entries
+        assert!(!scale(2_f64, &mut y));
+        assert_eq!(x, y);
+    }
+}
@@ -0,0 +1,193 @@
+//! The interface shared by the iterative solvers.
+
+use super::{
+    config::{NormKind, SolveConfig},
+    error::*,
+    report::SolveReport,
+    vecalg::{norm2, CpuVecOps},
+    MatVecMul,
+};
+use cauchy::Scalar;
+use num_traits::{float::*, Zero};
+
+/// The common interface of the iterative solvers, which allows to select a solver at
+/// runtime, e.g. by storing a `Box<dyn IterativeSolver<f64>>`.
+///
+/// It is implemented by every solver whose `solve` method needs no other input than the
+/// right-hand side, the initial guess and the stopping criteria. [`Chebyshev`](crate::Chebyshev)
+/// needs the spectral bounds of the operator and does not implement it.
+pub trait IterativeSolver<T: Scalar> {
+    /// Solves Ax = b, without preconditioner, starting from the initial guess in `x`.
+    ///
+    /// It returns the number of iterations and the relative residual.
+    fn solve(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<(usize, T::Real)>;
+
+    /// Solves Ax = b from the zero initial guess, and returns the solution together with
+    /// the number of iterations and the relative residual.
+    ///
+    /// It saves allocating `x` for the common zero-initial-guess case; use
+    /// [`solve`](Self::solve) to start from another guess or to reuse the storage.
+    fn solve_alloc(
+        &mut self,
+        rhs: &[T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<(Vec<T>, usize, T::Real)> {
+        let mut x = vec![T::zero(); rhs.len()];
+        let (its, res) = self.solve(rhs, &mut x, max_iter, tol)?;
+        Ok((x, its, res))
+    }
+
+    /// Solves Ax = b, starting from the initial guess in `x`, with the parameters in
+    /// `config`.
+    ///
+    /// The default implementation calls [`solve`](Self::solve), enforcing `config.abs_tol`
+    /// through the relative tolerance, so a right-hand side whose norm is below the machine
+    /// epsilon still yields $x = 0$. It supports only the [`NormKind::L2`] convergence test
+    /// and no time limit, and returns [`SolverError::InvalidParameter`] otherwise. The solvers
+    /// of this crate override it to check `config.time_limit` in their iterations.
+    fn solve_with_config(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        config: &SolveConfig<T::Real>,
+    ) -> SolveResult<(usize, T::Real)> {
+        let tol = l2_rel_tol(rhs, config)?;
+        if config.time_limit.is_some() {
+            return Err(SolverError::InvalidParameter(String::from(
+                "This solver doesn't support a time limit",
+            )));
+        }
+        self.solve(rhs, x, config.max_iter, tol)
+    }
+
+    /// Solves Ax = b like [`solve`](Self::solve), and reports why the iterations stopped.
+    ///
+    /// A breakdown, the iteration limit and stagnation are reported with `converged` set
+    /// to `false` instead of an error; the other errors are returned as they are.
+    ///
+    /// The default implementation knows only the residual returned by `solve`, so the
+    /// residuals of a breakdown or of the iteration limit are NaN, and `residual_gap` is
+    /// always NaN. The solvers of this crate override it to compute the true residual of
+    /// the returned `x`, at the cost of one more product with $A$.
+    fn solve_report(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<SolveReport<T::Real>> {
+        let nan = T::Real::nan();
+        let (iterations, rel_residual, converged, breakdown) =
+            match self.solve(rhs, x, max_iter, tol) {
+                Ok((its, res)) => (its, res, true, None),
+                Err(SolverError::BreakDown(its)) => (its, nan, false, Some(its)),
+                Err(SolverError::InsufficientIterNum(its)) => (its, nan, false, None),
+                Err(SolverError::Stagnation { iter, resid }) => (iter, T::real(resid), false, None),
+                Err(e) => return Err(e),
+            };
+        let rhs_norm = norm2(rhs);
+        let abs_residual = if rhs_norm > T::Real::epsilon() {
+            rel_residual * rhs_norm
+        } else {
+            rel_residual
+        };
+        Ok(SolveReport {
+            iterations,
+            rel_residual,
+            abs_residual,
+            residual_gap: nan,
+            converged,
+            breakdown,
+            history: None,
+        })
+    }
+}
+
+/// The relative tolerance enforcing `config.tol` and `config.abs_tol` for a solver testing
+/// the convergence in the 2-norm, or [`SolverError::InvalidParameter`] if `config` asks for
+/// another norm.
+pub(crate) fn l2_rel_tol<T: Scalar>(
+    rhs: &[T],
+    config: &SolveConfig<T::Real>,
+) -> SolveResult<T::Real> {
+    if config.norm_kind != NormKind::L2 {
+        return Err(SolverError::InvalidParameter(String::from(
+            "This solver tests the convergence in the 2-norm only",
+        )));
+    }
+    Ok(config.rel_tol(NormKind::L2.norm(&CpuVecOps, rhs)))
+}
+
+/// Whether the recursive residual is replaced by the true one at iteration `its`, given
+/// [`SolveConfig::recompute_residual_every`] and whether the recursive residual meets the
+/// tolerance.
+#[inline]
+pub(crate) fn replaces_residual(every: usize, its: usize, converged: bool) -> bool {
+    every > 0 && (converged || its.is_multiple_of(every))
+}
+
+/// The size of the square operator `A`, from its [`shape`](MatVecMul::shape), or
+/// [`SolverError::IncompatibleMatrixFormat`] if it is rectangular or doesn't provide its
+/// shape.
+#[allow(non_snake_case)]
+pub(crate) fn square_size<T: Scalar, M: MatVecMul<T> + ?Sized>(A: &M) -> SolveResult<usize> {
+    match A.shape() {
+        Some((rows, cols)) if rows == cols => Ok(rows),
+        Some((rows, cols)) => Err(SolverError::IncompatibleMatrixFormat(format!(
+            "Not a square operator: {} x {}",
+            rows, cols
+        ))),
+        None => Err(SolverError::IncompatibleMatrixFormat(String::from(
+            "The operator doesn't provide its shape",
+        ))),
+    }
+}
+
+/// Check the `size` given to the constructor of a solver against the
+/// [`shape`](MatVecMul::shape) of `A`, when `A` provides it.
+///
+/// **Panics** if `A` has a shape other than `size x size`.
+#[allow(non_snake_case)]
+pub(crate) fn check_size<T: Scalar, M: MatVecMul<T> + ?Sized>(A: &M, size: usize) {
+    if let Some(shape) = A.shape() {
+        assert_eq!(shape, (size, size), "The size doesn't match the operator");
+    }
+}
+
+/// Solve every column of the column-major `size x n_rhs` arrays `rhs` and `x` with `solve`,
+/// stopping at the first failing column.
+pub(crate) fn solve_columns<T: Scalar, F>(
+    size: usize,
+    rhs: &[T],
+    x: &mut [T],
+    n_rhs: usize,
+    mut solve: F,
+) -> SolveResult<Vec<(usize, T::Real)>>
+where
+    F: FnMut(&[T], &mut [T]) -> SolveResult<(usize, T::Real)>,
+{
+    if rhs.len() != size * n_rhs {
+        return Err(SolverError::IncompatibleMatrixFormat(String::from(
+            "Input vec dimension doesn't match the matrix size times the number of columns",
+        )));
+    }
+    if rhs.len() != x.len() {
+        return Err(SolverError::IncompatibleMatrixFormat(String::from(
+            "Input and output vec dimension do not match",
+        )));
+    }
+    if size == 0 {
+        return Ok(vec![(0, T::Real::zero()); n_rhs]);
+    }
+    rhs.chunks_exact(size)
+        .zip(x.chunks_exact_mut(size))
+        .map(|(b, x)| solve(b, x))
+        .collect()
+}
@@ -0,0 +1,259 @@
+//! An impl of the SYMMLQ algorithm for linear sparse solve.
+
+use super::{
+    config::{Deadline, SolveConfig},
+    error::*,
+    report::*,
+    solver::{l2_rel_tol, square_size, IterativeSolver},
+    vecalg::*,
+    MatVecMul,
+};
+use cauchy::Scalar;
+use num_traits::{float::*, One, Zero};
+use std::{
+    intrinsics::{likely, unlikely},
+    ops::ControlFlow,
+    ptr::copy_nonoverlapping,
+    slice::from_raw_parts_mut,
+};
+
+/// Implementation of the SYMMLQ algorithm for solving real-valued symmetric or
+/// complex-valued Hermitian, possibly indefinite, sparse linear systems.
+///
+/// It runs the same Lanczos process as [`MinRes`](crate::MinRes), $AV_k = V_{k+1}\bar T_k$,
+/// but factorizes the tridiagonal matrix as $T_k = L_kQ_k$ and takes the iterate of minimal
+/// norm in the Krylov subspace whose residual is orthogonal to $V_{k-1}$. Its iterates are
+/// updated along orthonormal directions, which keeps them well-behaved on some indefinite
+/// and nearly singular problems where MINRES iterates grow. The residual is not monotone,
+/// so on return the solution moves to the CG point whenever its residual is smaller.
+///
+/// See C. C. Paige and M. A. Saunders, *Solution of sparse indefinite systems of linear
+/// equations*, SIAM J. Numer. Anal., 12 (1975).
+///
+/// **Note:** This class won't check if the input matrix is Hermitian.
+#[allow(non_snake_case)]
+pub struct SymmLQ<'data, T: Scalar, M: MatVecMul<T>, V: VecOps<T> = CpuVecOps> {
+    A: &'data M,
+    workspace: Vec<T>,
+    size: usize,
+    ops: V,
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>> SymmLQ<'data, T, M> {
+    #[allow(non_snake_case)]
+    pub fn new(A: &'data M, size: usize) -> Self {
+        Self::with_vec_ops(A, size, CpuVecOps)
+    }
+
+    /// Create a solver for the operator `A`, whose size is given by its
+    /// [`shape`](MatVecMul::shape).
+    ///
+    /// Return [`SolverError::IncompatibleMatrixFormat`] if `A` is not square or doesn't provide
+    /// its shape.
+    #[allow(non_snake_case)]
+    pub fn from_op(A: &'data M) -> SolveResult<Self> {
+        Ok(Self::with_vec_ops(A, square_size(A)?, CpuVecOps))
+    }
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>, V: VecOps<T>> SymmLQ<'data, T, M, V> {
+    /// Create a solver performing its vector operations through `ops`.
+    #[allow(non_snake_case)]
+    pub fn with_vec_ops(A: &'data M, size: usize, ops: V) -> Self {
+        SymmLQ {
+            A,
+            workspace: vec![T::zero(); size * 4],
+            size,
+            ops,
+        }
+    }
+
+    /// Solves Ax = b, without preconditioner
+    ///
+    /// The returned iteration number counts the Lanczos steps, i.e. the products with $A$,
+    /// and the residual is the estimate of $|b - Ax| / |b|$ computed from the recurrences.
+    pub fn solve(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<(usize, T::Real)> {
+        self.solve_impl(rhs, x, max_iter, tol, |_, _| ControlFlow::Continue(()))
+    }
+
+    /// The SYMMLQ iterations. `monitor` is called with the iteration number and the relative
+    /// residual used by the convergence test, starting from the initial residual. The
+    /// iterations stop when it returns [`ControlFlow::Break`].
+    #[allow(clippy::many_single_char_names)]
+    fn solve_impl<F: FnMut(usize, T::Real) -> ControlFlow<()>>(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+        mut monitor: F,
+    ) -> SolveResult<(usize, T::Real)> {
+        let n = rhs.len();
+        // check the format
+        if n != self.size {
+            return Err(SolverError::IncompatibleMatrixFormat(String::from(
+                "Input vec dimension doesn't match the matrix size",
+            )));
+        }
+        if n != x.len() {
+            return Err(SolverError::IncompatibleMatrixFormat(String::from(
+                "Input and output vec dimension do not match",
+            )));
+        }
+
+        let rhs_norm = self.ops.norm2(rhs);
+        if unlikely(rhs_norm <= T::Real::epsilon()) {
+            // when rhs = 0, x is set to zero.
+            x.iter_mut().for_each(|v| *v = T::zero());
+            return Ok((0, rhs_norm));
+        }
+        let threshold = tol * rhs_norm;
+
+        // set vectors using preallocated memeory
+        let ptr = self.workspace.as_mut_ptr();
+        let mut v_old = unsafe { from_raw_parts_mut(ptr, n) }; // &mut [T]
+        let mut v = unsafe { from_raw_parts_mut(ptr.add(n), n) };
+        let mut y = unsafe { from_raw_parts_mut(ptr.add(2 * n), n) };
+        let w = unsafe { from_raw_parts_mut(ptr.add(3 * n), n) };
+
+        unsafe {
+            self.A.mul_vec_unchecked(x, &mut *v); // v = A * x
+        }
+        self.ops.waxpy(-T::one(), &*v, rhs, &mut *y); // y = rhs - A*x
+        let beta1 = self.ops.norm2(&*y);
+        if unlikely(monitor(0, beta1 / rhs_norm).is_break() || beta1 <= threshold) {
+            return Ok((0, beta1 / rhs_norm));
+        }
+
+        // the first Lanczos step, v = r / beta1
+        let inv = T::from_real(T::Real::one() / beta1);
+        self.ops.scaled_copy(inv, &*y, &mut *v);
+        unsafe {
+            self.A.mul_vec_unchecked(&*v, &mut *y); // y = A * v
+        }
+        let alpha = self.ops.conj_dot(&*v, &*y).re();
+        self.ops.axpy(T::from_real(-alpha), &*v, &mut *y);
+        // make sure that y is orthogonal to the first Lanczos vector
+        let z = self.ops.conj_dot(&*v, &*y);
+        self.ops.axpy(-z, &*v, &mut *y);
+        let mut beta = self.ops.norm2(&*y);
+        unsafe {
+            copy_nonoverlapping(v.as_ptr(), w.as_mut_ptr(), n); // w_bar = v
+        }
+
+        let mut gbar = alpha;
+        let mut dbar = beta;
+        let (mut rhs1, mut rhs2) = (beta1, T::Real::zero());
+        // product of the sines of the rotations
+        let mut sn_prod = T::Real::one();
+        let mut t_norm2 = alpha * alpha + beta * beta;
+
+        let mut its = 1;
+        loop {
+            let diag = if likely(!gbar.is_zero()) {
+                gbar
+            } else {
+                Float::sqrt(t_norm2) * T::Real::epsilon()
+            };
+            // the residuals of the LQ point and of the CG point
+            let lq_norm = Float::sqrt(rhs1 * rhs1 + rhs2 * rhs2);
+            let cg_norm = sn_prod * beta1 * beta / Float::abs(diag);
+            if unlikely(!lq_norm.is_finite() || !cg_norm.is_finite()) {
+                return Err(SolverError::Diverged(its));
+            }
+            let converged = cg_norm.min(lq_norm) <= threshold;
+            let stop = monitor(its, cg_norm.min(lq_norm) / rhs_norm).is_break();
+            if converged || stop || its >= max_iter {
+                if cg_norm <= lq_norm {
+                    // move to the CG point
+                    self.ops.axpy(T::from_real(rhs1 / diag), &*w, &mut *x);
+                }
+                if converged || stop {
+                    return Ok((its, cg_norm.min(lq_norm) / rhs_norm));
+                }
+                return Err(SolverError::InsufficientIterNum(max_iter));
+            }
+
+            // the next Lanczos vector, y = A v - alpha v - beta_old v_old
+            let y_ptr = v_old.as_mut_ptr();
+            v_old = unsafe { from_raw_parts_mut(v.as_mut_ptr(), n) }; // v_old <- v
+            v = unsafe { from_raw_parts_mut(y.as_mut_ptr(), n) }; // v <- y
+            y = unsafe { from_raw_parts_mut(y_ptr, n) };
+            self.ops.rscale(T::Real::one() / beta, &mut *v);
+            unsafe {
+                self.A.mul_vec_unchecked(&*v, &mut *y); // y = A * v
+            }
+            self.ops.axpy(T::from_real(-beta), &*v_old, &mut *y);
+            let alpha = self.ops.conj_dot(&*v, &*y).re();
+            self.ops.axpy(T::from_real(-alpha), &*v, &mut *y);
+            let beta_old = beta;
+            beta = self.ops.norm2(&*y);
+            t_norm2 += alpha * alpha + beta_old * beta_old + beta * beta;
+
+            // the next plane rotation of Q
+            let gamma = Float::sqrt(gbar * gbar + beta_old * beta_old);
+            let (cs, sn) = (gbar / gamma, beta_old / gamma);
+            let delta = cs * dbar + sn * alpha;
+            gbar = sn * dbar - cs * alpha;
+            let epsilon = sn * beta;
+            dbar = -cs * beta;
+
+            // x += z (cs w_bar + sn v), w_bar = sn w_bar - cs v
+            let z = rhs1 / gamma;
+            self.ops.axpy(T::from_real(z * cs), &*w, &mut *x);
+            self.ops.axpy(T::from_real(z * sn), &*v, &mut *x);
+            self.ops
+                .axpby(T::from_real(-cs), &*v, T::from_real(sn), &mut *w);
+
+            sn_prod *= sn;
+            rhs1 = rhs2 - delta * z;
+            rhs2 = -epsilon * z;
+            its += 1;
+        }
+    }
+}
+
+impl<'data, T: Scalar, M: MatVecMul<T>, V: VecOps<T>> IterativeSolver<T>
+    for SymmLQ<'data, T, M, V>
+{
+    fn solve(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<(usize, T::Real)> {
+        SymmLQ::solve(self, rhs, x, max_iter, tol)
+    }
+
+    fn solve_with_config(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        config: &SolveConfig<T::Real>,
+    ) -> SolveResult<(usize, T::Real)> {
+        let tol = l2_rel_tol(rhs, config)?;
+        let mut deadline = Deadline::new(config.time_limit);
+        let ret = self.solve_impl(rhs, x, config.max_iter, tol, |its, res| {
+            deadline.monitor(its, res)
+        });
+        deadline.finish(ret)
+    }
+
+    fn solve_report(
+        &mut self,
+        rhs: &[T],
+        x: &mut [T],
+        max_iter: usize,
+        tol: T::Real,
+    ) -> SolveResult<SolveReport<T::Real>> {
+        let ret = SymmLQ::solve(self, rhs, x, max_iter, tol);
+        report_outcome(self.A, &self.ops, rhs, x, &mut self.workspace, ret)
+    }
+}
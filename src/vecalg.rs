@@ -3,7 +3,7 @@
 use cauchy::Scalar;
 #[cfg(feature = "mkl")]
 use mkl_sys::blas::*;
-use num_traits::Zero;
+use num_traits::{One, Zero};
 use std::ops::{Deref, DerefMut, Mul};
 
 #[cfg(feature = "mkl")]
@@ -15,6 +15,204 @@ const DOT_BLAS_CUTOFF: usize = 64;
 const SCALE_BLAS_CUTOFF: usize = 64;
 #[cfg(feature = "mkl")]
 const AXPY_BLAS_CUTOFF: usize = 64;
+/// max len of vector handled by a single blas call
+#[cfg(feature = "mkl")]
+const BLAS_MAX_LEN: usize = std::os::raw::c_int::MAX as usize;
+
+/// The vector operations performed by the iterative solvers.
+///
+/// The solvers call the vector operations through this trait, so that they can run on data
+/// that is not directly processed by the host, e.g. GPU-resident vectors exposed through
+/// unified memory, by providing a custom implementation together with a custom `MatVecMul`.
+/// [`CpuVecOps`] is the default implementation, which calls the functions of this module.
+pub trait VecOps<T: Scalar> {
+    /// compute $\mathbf{x}\cdot\mathbf{y} = \mathbf{x}^T\mathbf{y}$.
+    fn dot(&self, x: &[T], y: &[T]) -> T;
+    /// compute $\mathbf{x}\cdot\mathbf{y} = \mathbf{x}^H\mathbf{y}$.
+    fn conj_dot(&self, x: &[T], y: &[T]) -> T;
+    /// compute the 2-norm of `x`.
+    fn norm2(&self, x: &[T]) -> T::Real;
+    /// compute the squared 2-norm of `x`, without a square root. See [`norm2_sq`].
+    ///
+    /// The default implementation takes the real part of [`conj_dot`](Self::conj_dot).
+    fn norm2_sq(&self, x: &[T]) -> T::Real {
+        self.conj_dot(x, x).re()
+    }
+    /// compute x = x * a
+    fn scale(&self, a: T, x: &mut [T]);
+    /// compute x = x * a, where a is a real number
+    fn rscale(&self, a: T::Real, x: &mut [T]);
+    /// compute y = conj(x)
+    fn conj(&self, x: &[T], y: &mut [T]);
+    /// compute y = y + a*x
+    fn axpy(&self, a: T, x: &[T], y: &mut [T]);
+    /// compute y = y + a*x, where a is a real number
+    ///
+    /// The default implementation calls [`axpy`](Self::axpy) with `a` promoted to `T`.
+    fn raxpy(&self, a: T::Real, x: &[T], y: &mut [T]) {
+        self.axpy(T::from_real(a), x, y);
+    }
+    /// compute y = b*y + a*x
+    fn axpby(&self, a: T, x: &[T], b: T, y: &mut [T]);
+    /// compute y = a*x
+    ///
+    /// The default implementation copies `x` into `y` on the host and calls
+    /// [`scale`](Self::scale).
+    fn scaled_copy(&self, a: T, x: &[T], y: &mut [T]) {
+        y.copy_from_slice(x);
+        self.scale(a, y);
+    }
+    /// compute w = a*x + y
+    ///
+    /// The default implementation copies `y` into `w` on the host and calls
+    /// [`axpy`](Self::axpy).
+    fn waxpy(&self, a: T, x: &[T], y: &[T], w: &mut [T]) {
+        w.copy_from_slice(y);
+        self.axpy(a, x, w);
+    }
+    /// compute $\mathbf{x}^H\mathbf{y}$ and $|\mathbf{y}|_2^2$. See [`dot_and_norm2`].
+    ///
+    /// The default implementation calls [`conj_dot`](Self::conj_dot) and
+    /// [`norm2_sq`](Self::norm2_sq), scanning `y` twice.
+    fn dot_and_norm2(&self, x: &[T], y: &[T]) -> (T, T::Real) {
+        (self.conj_dot(x, y), self.norm2_sq(y))
+    }
+    /// compute the infinity-norm of `x`.
+    ///
+    /// The default implementation reads `x` on the host with [`norm_inf`].
+    fn norm_inf(&self, x: &[T]) -> T::Real {
+        norm_inf(x)
+    }
+    /// scale `x` to unit 2-norm and return its norm. See [`normalize`].
+    ///
+    /// The default implementation calls [`norm2`](Self::norm2) and [`rscale`](Self::rscale).
+    fn normalize(&self, x: &mut [T]) -> T::Real {
+        let nrm = self.norm2(x);
+        if nrm < <T::Real as num_traits::Float>::epsilon() {
+            return T::Real::zero();
+        }
+        self.rscale(T::Real::one() / nrm, x);
+        nrm
+    }
+}
+
+/// The default [`VecOps`], running on host memory with the functions of this module.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CpuVecOps;
+
+impl<T: Scalar> VecOps<T> for CpuVecOps {
+    #[inline]
+    fn dot(&self, x: &[T], y: &[T]) -> T {
+        dot(x, y)
+    }
+    #[inline]
+    fn conj_dot(&self, x: &[T], y: &[T]) -> T {
+        conj_dot(x, y)
+    }
+    #[inline]
+    fn norm2(&self, x: &[T]) -> T::Real {
+        norm2(x)
+    }
+    #[inline]
+    fn norm2_sq(&self, x: &[T]) -> T::Real {
+        norm2_sq(x)
+    }
+    #[inline]
+    fn scale(&self, a: T, x: &mut [T]) {
+        scale(a, x)
+    }
+    #[inline]
+    fn rscale(&self, a: T::Real, x: &mut [T]) {
+        rscale(a, x)
+    }
+    #[inline]
+    fn conj(&self, x: &[T], y: &mut [T]) {
+        conj(x, y)
+    }
+    #[inline]
+    fn axpy(&self, a: T, x: &[T], y: &mut [T]) {
+        axpy(a, x, y)
+    }
+    #[inline]
+    fn raxpy(&self, a: T::Real, x: &[T], y: &mut [T]) {
+        raxpy(a, x, y)
+    }
+    #[inline]
+    fn axpby(&self, a: T, x: &[T], b: T, y: &mut [T]) {
+        axpby(a, x, b, y)
+    }
+    #[inline]
+    fn scaled_copy(&self, a: T, x: &[T], y: &mut [T]) {
+        scaled_copy(a, x, y)
+    }
+    #[inline]
+    fn waxpy(&self, a: T, x: &[T], y: &[T], w: &mut [T]) {
+        waxpy(a, x, y, w)
+    }
+    #[inline]
+    fn dot_and_norm2(&self, x: &[T], y: &[T]) -> (T, T::Real) {
+        dot_and_norm2(x, y)
+    }
+}
+
+impl<T: Scalar, V: VecOps<T> + ?Sized> VecOps<T> for &V {
+    #[inline]
+    fn dot(&self, x: &[T], y: &[T]) -> T {
+        (**self).dot(x, y)
+    }
+    #[inline]
+    fn conj_dot(&self, x: &[T], y: &[T]) -> T {
+        (**self).conj_dot(x, y)
+    }
+    #[inline]
+    fn norm2(&self, x: &[T]) -> T::Real {
+        (**self).norm2(x)
+    }
+    #[inline]
+    fn norm2_sq(&self, x: &[T]) -> T::Real {
+        (**self).norm2_sq(x)
+    }
+    #[inline]
+    fn scale(&self, a: T, x: &mut [T]) {
+        (**self).scale(a, x)
+    }
+    #[inline]
+    fn rscale(&self, a: T::Real, x: &mut [T]) {
+        (**self).rscale(a, x)
+    }
+    #[inline]
+    fn conj(&self, x: &[T], y: &mut [T]) {
+        (**self).conj(x, y)
+    }
+    #[inline]
+    fn axpy(&self, a: T, x: &[T], y: &mut [T]) {
+        (**self).axpy(a, x, y)
+    }
+    #[inline]
+    fn raxpy(&self, a: T::Real, x: &[T], y: &mut [T]) {
+        (**self).raxpy(a, x, y)
+    }
+    #[inline]
+    fn axpby(&self, a: T, x: &[T], b: T, y: &mut [T]) {
+        (**self).axpby(a, x, b, y)
+    }
+    #[inline]
+    fn scaled_copy(&self, a: T, x: &[T], y: &mut [T]) {
+        (**self).scaled_copy(a, x, y)
+    }
+    #[inline]
+    fn waxpy(&self, a: T, x: &[T], y: &[T], w: &mut [T]) {
+        (**self).waxpy(a, x, y, w)
+    }
+    #[inline]
+    fn dot_and_norm2(&self, x: &[T], y: &[T]) -> (T, T::Real) {
+        (**self).dot_and_norm2(x, y)
+    }
+    #[inline]
+    fn norm_inf(&self, x: &[T]) -> T::Real {
+        (**self).norm_inf(x)
+    }
+}
 
 /// compute $\mathbf{x}\cdot\mathbf{y} = \mathbf{x}^T\mathbf{y}$.
 ///
@@ -108,7 +306,7 @@ where
 #[inline]
 pub fn axpy<S, T, IN, OUT>(a: S, vec1: IN, mut vec2: OUT)
 where
-    S: Copy,
+    S: Copy + 'static,
     T: Scalar + Mul<S, Output = T>,
     IN: Deref<Target = [T]>,
     OUT: DerefMut<Target = [T]>,
@@ -117,6 +315,32 @@ where
     axpy_fallback(a, &vec1[..], &mut vec2[..])
 }
 
+/// The `axpy` operation with a real scalar: vec2 = vec2 + a*vec1,
+/// where a is a real number
+///
+/// # Example
+///
+/// ```
+/// # use sprsolve::vecalg::raxpy;
+/// use cauchy::c64;
+///
+/// let x = vec![c64::new(1., -2.); 128];
+/// let mut y = vec![c64::new(0., 1.); 128];
+/// raxpy(0.5, x.as_slice(), y.as_mut_slice());
+/// assert!(y.iter().all(|&v| v == c64::new(0.5, 0.)));
+/// ```
+#[cfg(not(feature = "mkl"))]
+#[inline]
+pub fn raxpy<T, IN, OUT>(a: T::Real, vec1: IN, mut vec2: OUT)
+where
+    T: Scalar,
+    IN: Deref<Target = [T]>,
+    OUT: DerefMut<Target = [T]>,
+{
+    assert_eq!(vec1[..].len(), vec2[..].len());
+    raxpy_fallback(a, &vec1[..], &mut vec2[..])
+}
+
 /// The `axpby` operation as in MKL: vec2 = b*vec2 + a*vec1
 ///
 /// # Example
@@ -134,7 +358,7 @@ where
 #[inline]
 pub fn axpby<S, T, IN, OUT>(a: S, vec1: IN, b: S, mut vec2: OUT)
 where
-    S: Copy,
+    S: Copy + 'static,
     T: Scalar + Mul<S, Output = T>,
     IN: Deref<Target = [T]>,
     OUT: DerefMut<Target = [T]>,
@@ -143,418 +367,1491 @@ where
     axpby_fallback(a, &vec1[..], b, &mut vec2[..])
 }
 
-/// Dot product with CBLAS calls.
-#[cfg(feature = "mkl")]
-pub fn dot<T, IN1, IN2>(vec1: IN1, vec2: IN2) -> T
+/// The out-of-place `axpy`: out = a*vec1 + vec2, leaving `vec2` unchanged.
+///
+/// `out` is borrowed mutably, so it cannot alias `vec1` or `vec2`; use [`axpy`] to update
+/// `vec2` in place.
+///
+/// # Example
+///
+/// ```
+/// # use sprsolve::vecalg::waxpy;
+/// let x = vec![1_f64; 128];
+/// let y = vec![2_f64; 128];
+/// let mut w = vec![0_f64; 128];
+/// waxpy(3., x.as_slice(), y.as_slice(), w.as_mut_slice());
+/// assert!(w.iter().all(|&v| v == 5.));
+/// ```
+#[cfg(not(feature = "mkl"))]
+#[inline]
+pub fn waxpy<S, T, IN1, IN2, OUT>(a: S, vec1: IN1, vec2: IN2, mut out: OUT)
 where
-    T: Scalar,
+    S: Copy + 'static,
+    T: Scalar + Mul<S, Output = T>,
     IN1: Deref<Target = [T]>,
     IN2: Deref<Target = [T]>,
+    OUT: DerefMut<Target = [T]>,
 {
-    let n = vec1[..].len();
-    assert_eq!(n, vec2[..].len());
+    assert_eq!(vec1[..].len(), vec2[..].len());
+    assert_eq!(vec1[..].len(), out[..].len());
+    waxpy_fallback(a, &vec1[..], &vec2[..], &mut out[..])
+}
 
-    // Use only if the vector is large enough to be worth it
-    if n > DOT_BLAS_CUTOFF && n < std::os::raw::c_int::max_value() as usize {
-        macro_rules! dot {
-            ($ty:ty, $func:ident, {}) => {
-                if super::same_type::<T, $ty>() {
-                    let v = unsafe {
-                        $func(
-                            n as i32, // this is safe because the assert! above
-                            vec1[..].as_ptr() as *const $ty,
-                            1,
-                            vec2[..].as_ptr() as *const $ty,
-                            1,
-                        )
-                    };
-                    return super::cast_as::<$ty, T>(&v);
-                }
-            };
-            ($ty:ty, $func:ident, {complex}) => {
-                if super::same_type::<T, num_complex::Complex<$ty>>() {
-                    let mut r: num_complex::Complex<$ty> = Default::default();
-                    unsafe {
-                        $func(
-                            n as i32, // this is safe because the assert! above
-                            vec1[..].as_ptr() as *const c_void,
-                            1,
-                            vec2[..].as_ptr() as *const c_void,
-                            1,
-                            &mut r as *mut num_complex::Complex<$ty> as *mut c_void,
-                        );
-                    }
-                    return super::cast_as::<num_complex::Complex<$ty>, T>(&r);
-                }
-            };
+/// Copy `src` into `dst`.
+///
+/// # Example
+///
+/// ```
+/// # use sprsolve::vecalg::copy;
+/// let a = vec![1_f64; 128];
+/// let mut b = vec![2_f64; 128];
+/// copy(a.as_slice(), b.as_mut_slice());
+/// for i in 0..b.len() {
+///     approx::assert_abs_diff_eq!(1., b[i]);
+/// }
+/// ```
+#[cfg(not(feature = "mkl"))]
+#[inline]
+pub fn copy<T, IN, OUT>(src: IN, mut dst: OUT)
+where
+    T: Scalar,
+    IN: Deref<Target = [T]>,
+    OUT: DerefMut<Target = [T]>,
+{
+    assert_eq!(src[..].len(), dst[..].len());
+    dst[..].copy_from_slice(&src[..]);
+}
+
+/// Copy `src` scaled by `a` into `dst`: dst = a*src, in a single pass over the data.
+///
+/// # Example
+///
+/// ```
+/// # use sprsolve::vecalg::scaled_copy;
+/// let a = vec![2_f64; 128];
+/// let mut b = vec![0_f64; 128];
+/// scaled_copy(0.5, a.as_slice(), b.as_mut_slice());
+/// assert!(b.iter().all(|&v| v == 1.));
+/// ```
+#[cfg(not(feature = "mkl"))]
+#[inline]
+pub fn scaled_copy<S, T, IN, OUT>(a: S, src: IN, mut dst: OUT)
+where
+    S: Copy + 'static,
+    T: Scalar + Mul<S, Output = T>,
+    IN: Deref<Target = [T]>,
+    OUT: DerefMut<Target = [T]>,
+{
+    assert_eq!(src[..].len(), dst[..].len());
+    scaled_copy_fallback(a, &src[..], &mut dst[..])
+}
+
+/// Swap the contents of `vec1` and `vec2`.
+///
+/// # Example
+///
+/// ```
+/// # use sprsolve::vecalg::swap;
+/// let mut a = vec![1_f64; 128];
+/// let mut b = vec![2_f64; 128];
+/// swap(a.as_mut_slice(), b.as_mut_slice());
+/// for i in 0..a.len() {
+///     approx::assert_abs_diff_eq!(2., a[i]);
+///     approx::assert_abs_diff_eq!(1., b[i]);
+/// }
+/// ```
+#[cfg(not(feature = "mkl"))]
+#[inline]
+pub fn swap<T, A, B>(mut vec1: A, mut vec2: B)
+where
+    T: Scalar,
+    A: DerefMut<Target = [T]>,
+    B: DerefMut<Target = [T]>,
+{
+    assert_eq!(vec1[..].len(), vec2[..].len());
+    vec1[..].swap_with_slice(&mut vec2[..]);
+}
+
+/// Compute $\sum_i |\mathrm{Re}(x_i)| + |\mathrm{Im}(x_i)|$, which is the sum of the
+/// absolute values for real vectors.
+///
+/// **NOTE:** For complex vectors, this follows the BLAS `?asum` definition rather than
+/// summing the moduli.
+#[cfg(not(feature = "mkl"))]
+#[inline]
+pub fn asum<T, VEC>(vec: VEC) -> T::Real
+where
+    T: Scalar,
+    VEC: Deref<Target = [T]>,
+{
+    asum_fallback(&vec[..])
+}
+
+/// Return the index of the first entry with the largest magnitude $|\mathrm{Re}(x_i)| +
+/// |\mathrm{Im}(x_i)|$, as the BLAS `i?amax`. It returns 0 for an empty vector.
+#[cfg(not(feature = "mkl"))]
+#[inline]
+pub fn iamax<T, VEC>(vec: VEC) -> usize
+where
+    T: Scalar,
+    VEC: Deref<Target = [T]>,
+{
+    iamax_fallback(&vec[..])
+}
+
+/// Apply the Givens rotation generated by [`rotg`] to the vectors `x` and `y`, i.e.
+/// $x_i \leftarrow c x_i + s y_i$ and $y_i \leftarrow -\bar s x_i + c y_i$.
+///
+/// # Example
+///
+/// ```
+/// # use sprsolve::vecalg::rot;
+/// let mut x = vec![3_f64; 128];
+/// let mut y = vec![-4_f64; 128];
+/// rot(x.as_mut_slice(), y.as_mut_slice(), 0.6, -0.8);
+/// for i in 0..x.len() {
+///     approx::assert_abs_diff_eq!(5., x[i], epsilon = 1E-14);
+///     approx::assert_abs_diff_eq!(0., y[i], epsilon = 1E-14);
+/// }
+/// ```
+#[cfg(not(feature = "mkl"))]
+#[inline]
+pub fn rot<T, X, Y>(mut x: X, mut y: Y, c: T::Real, s: T)
+where
+    T: Scalar,
+    X: DerefMut<Target = [T]>,
+    Y: DerefMut<Target = [T]>,
+{
+    assert_eq!(x[..].len(), y[..].len());
+    rot_fallback(&mut x[..], &mut y[..], c, s)
+}
+
+/// Compute the squared 2-norm $|\mathbf{x}|_2^2 = \mathbf{x}^H\mathbf{x}$.
+///
+/// It never takes a square root, so comparing squared quantities with it, e.g.
+/// $|r|_2^2 \le \text{tol}^2$, saves the square root of [`norm2`] and its rounding. The
+/// squares are summed without scaling, so the result may overflow for vectors with huge
+/// entries, where [`norm2`] under `mkl` would not.
+///
+/// # Example
+///
+/// ```
+/// # use sprsolve::vecalg::norm2_sq;
+/// let a = vec![3_f64, 4.];
+/// assert_eq!(norm2_sq(a.as_slice()), 25.);
+/// ```
+#[inline]
+pub fn norm2_sq<T, VEC>(vec: VEC) -> T::Real
+where
+    T: Scalar,
+    VEC: Deref<Target = [T]>,
+{
+    #[cfg(feature = "mkl")]
+    {
+        if vec[..].len() > DOT_BLAS_CUTOFF {
+            return conj_dot(&vec[..], &vec[..]).re();
         }
+    }
+    sum_squares_fallback(&vec[..])
+}
+
+/// Compute the infinity-norm $\max_i |x_i|$, where $|x_i|$ is the modulus for complex
+/// vectors. It returns 0 for an empty vector.
+#[inline]
+pub fn norm_inf<T, VEC>(vec: VEC) -> T::Real
+where
+    T: Scalar,
+    VEC: Deref<Target = [T]>,
+{
+    vec[..].iter().fold(T::Real::zero(), |acc, x| {
+        num_traits::Float::max(acc, x.abs())
+    })
+}
 
-        dot! {f32, cblas_sdot, {}};
-        dot! {f64, cblas_ddot, {}};
-        dot! {f32, cblas_cdotu_sub, {complex} };
-        dot! {f64, cblas_zdotu_sub, {complex} };
+/// Scale `vec` to unit 2-norm in place, and return its 2-norm.
+///
+/// If the norm is below `T::Real::epsilon()`, `vec` is left untouched and zero is returned,
+/// which allows the caller to detect a breakdown.
+///
+/// # Example
+///
+/// ```
+/// # use sprsolve::vecalg::normalize;
+/// let mut a = vec![3_f64, 4.];
+/// approx::assert_abs_diff_eq!(normalize(a.as_mut_slice()), 5.);
+/// approx::assert_abs_diff_eq!(a[0], 0.6);
+/// approx::assert_abs_diff_eq!(a[1], 0.8);
+/// ```
+#[inline]
+pub fn normalize<T, VEC>(mut vec: VEC) -> T::Real
+where
+    T: Scalar,
+    VEC: DerefMut<Target = [T]>,
+{
+    let nrm = norm2(&vec[..]);
+    if nrm < <T::Real as num_traits::Float>::epsilon() {
+        return T::Real::zero();
     }
-    dot_fallback(&vec1[..], &vec2[..])
+    rscale(T::Real::one() / nrm, &mut vec[..]);
+    nrm
 }
 
-/// compute $\mathbf{x}\cdot\mathbf{y} = \mathbf{x}^H\mathbf{y}$.
+/// Compute $\mathbf{x}^H\mathbf{y}$ and $|\mathbf{y}|_2^2$ in a single pass over the
+/// data.
+///
+/// It saves one scan of `vec2` in memory-bound iterations that need both values, compared
+/// with [`conj_dot`] followed by [`norm2`]. The squares are summed without scaling, so the
+/// squared norm may overflow for vectors with huge entries, where [`norm2`] under `mkl`
+/// would not.
+///
+/// # Example
 ///
-/// **NOTE:** If the vector is complex-valued, this function is conjugate-linear to
-/// the first argument, and linear to the second argument.
-/// Here is an example to show the expected results.
 /// ```
-/// # use sprsolve::vecalg::conj_dot;
-/// use cauchy::c64;
+/// # use sprsolve::vecalg::dot_and_norm2;
+/// let x = vec![1_f64, 2.];
+/// let y = vec![3_f64, 4.];
+/// assert_eq!(dot_and_norm2(x.as_slice(), y.as_slice()), (11., 25.));
+/// ```
+#[inline]
+pub fn dot_and_norm2<T, IN1, IN2>(vec1: IN1, vec2: IN2) -> (T, T::Real)
+where
+    T: Scalar,
+    IN1: Deref<Target = [T]>,
+    IN2: Deref<Target = [T]>,
+{
+    assert_eq!(vec1[..].len(), vec2[..].len());
+    vec1[..]
+        .iter()
+        .zip(vec2[..].iter())
+        .fold((T::zero(), T::Real::zero()), |(d, s), (x, y)| {
+            (d + x.conj() * (*y), s + y.square())
+        })
+}
+
+/// Generate the Givens rotation $G = \begin{bmatrix} c & s \\ -\bar s & c \end{bmatrix}$,
+/// with a real $c \ge 0$, such that $G [a, b]^T = [r, 0]^T$. On return, `a` holds $r$,
+/// which has the phase of the input `a`, and `b` is zero. It returns $(c, s)$.
+///
+/// **NOTE:** Unlike the BLAS `?rotg`, which chooses the sign of $r$ by the larger input,
+/// the same convention is used for real and complex values, with and without the `mkl`
+/// feature. If `a` is zero, then $c = 0$, $s = 1$ and $r = b$.
+///
+/// # Example
 ///
-/// let a = vec![c64::new(4., 3.); 100];
-/// let b = vec![c64::new(2., -3.); 100];
-/// let r = conj_dot(a.as_slice(), b.as_slice());
-/// let t = a[0].conj() * b[0] * 100.;
-/// approx::assert_abs_diff_eq!(t.re, r.re); //, epsilon = f64::EPSILON);
-/// approx::assert_abs_diff_eq!(t.im, r.im);
 /// ```
-#[cfg(feature = "mkl")]
-pub fn conj_dot<T, IN1, IN2>(vec1: IN1, vec2: IN2) -> T
+/// # use sprsolve::vecalg::rotg;
+/// let (mut a, mut b) = (3_f64, -4.);
+/// let (c, s) = rotg(&mut a, &mut b);
+/// approx::assert_abs_diff_eq!(a, 5.);
+/// approx::assert_abs_diff_eq!(c, 0.6);
+/// approx::assert_abs_diff_eq!(s, -0.8);
+/// assert_eq!(b, 0.);
+/// ```
+#[inline]
+pub fn rotg<T: Scalar>(a: &mut T, b: &mut T) -> (T::Real, T) {
+    let a_abs = a.abs();
+    if a_abs == T::Real::zero() {
+        *a = *b;
+        *b = T::zero();
+        return (T::Real::zero(), T::one());
+    }
+    let norm = num_traits::Float::sqrt(a.square() + b.square());
+    let inv = T::Real::one() / norm;
+    let phase = *a / T::from_real(a_abs);
+    let s = (phase * b.conj()).mul_real(inv);
+    *a = phase.mul_real(norm);
+    *b = T::zero();
+    (a_abs * inv, s)
+}
+
+/// Compute the element-wise (Hadamard) product `out[i] = vec1[i] * vec2[i]`.
+///
+/// **NOTE:** The VML routines of MKL are not exposed by `mkl-sys`, so the same loop is used
+/// with and without the `mkl` feature.
+///
+/// # Example
+///
+/// ```
+/// # use sprsolve::vecalg::hadamard;
+/// let a = vec![1_f64, 2., 3.];
+/// let b = vec![4_f64, 5., 6.];
+/// let mut c = vec![0_f64; 3];
+/// hadamard(a.as_slice(), b.as_slice(), c.as_mut_slice());
+/// assert_eq!(c, vec![4., 10., 18.]);
+/// ```
+#[inline]
+pub fn hadamard<T, IN1, IN2, OUT>(vec1: IN1, vec2: IN2, mut out: OUT)
 where
     T: Scalar,
     IN1: Deref<Target = [T]>,
     IN2: Deref<Target = [T]>,
+    OUT: DerefMut<Target = [T]>,
 {
-    let n = vec1[..].len();
-    assert_eq!(n, vec2[..].len());
+    assert_eq!(vec1[..].len(), vec2[..].len());
+    assert_eq!(vec1[..].len(), out[..].len());
+    out[..]
+        .iter_mut()
+        .zip(vec1[..].iter().zip(vec2[..].iter()))
+        .for_each(|(z, (&x, &y))| *z = x * y);
+}
+
+/// Compute the element-wise division `out[i] = vec1[i] / vec2[i]`.
+///
+/// A division by zero follows the IEEE semantics of `T` and produces an infinity or a NaN;
+/// it does not panic.
+#[inline]
+pub fn hadamard_div<T, IN1, IN2, OUT>(vec1: IN1, vec2: IN2, mut out: OUT)
+where
+    T: Scalar,
+    IN1: Deref<Target = [T]>,
+    IN2: Deref<Target = [T]>,
+    OUT: DerefMut<Target = [T]>,
+{
+    assert_eq!(vec1[..].len(), vec2[..].len());
+    assert_eq!(vec1[..].len(), out[..].len());
+    out[..]
+        .iter_mut()
+        .zip(vec1[..].iter().zip(vec2[..].iter()))
+        .for_each(|(z, (&x, &y))| *z = x / y);
+}
+
+/// Compute the element-wise product in place, `vec1[i] *= vec2[i]`.
+#[inline]
+pub fn hadamard_assign<T, OUT, IN>(mut vec1: OUT, vec2: IN)
+where
+    T: Scalar,
+    OUT: DerefMut<Target = [T]>,
+    IN: Deref<Target = [T]>,
+{
+    assert_eq!(vec1[..].len(), vec2[..].len());
+    vec1[..]
+        .iter_mut()
+        .zip(vec2[..].iter())
+        .for_each(|(x, &y)| *x *= y);
+}
+
+/// Set all the entries of `vec` to zero.
+///
+/// It fills `vec` with `T::zero()`, which the compiler turns into a `memset` for the
+/// floating-point and complex types.
+///
+/// # Example
+///
+/// ```
+/// # use sprsolve::vecalg::zero;
+/// let mut a = vec![1_f64; 128];
+/// zero(a.as_mut_slice());
+/// assert!(a.iter().all(|&v| v == 0.));
+/// ```
+#[inline]
+pub fn zero<T, VEC>(mut vec: VEC)
+where
+    T: Scalar,
+    VEC: DerefMut<Target = [T]>,
+{
+    vec[..].fill(T::zero());
+}
+
+/// Set all the entries of `vec` to `val`.
+///
+/// # Example
+///
+/// ```
+/// # use sprsolve::vecalg::fill;
+/// let mut a = vec![0_f64; 128];
+/// fill(2., a.as_mut_slice());
+/// assert!(a.iter().all(|&v| v == 2.));
+/// ```
+#[inline]
+pub fn fill<T, VEC>(val: T, mut vec: VEC)
+where
+    T: Scalar,
+    VEC: DerefMut<Target = [T]>,
+{
+    vec[..].fill(val);
+}
+
+/// Copy the real parts of `src` into `dst`. For real-valued `T`, this is a plain copy.
+#[inline]
+pub fn real_part<T, IN, OUT>(src: IN, mut dst: OUT)
+where
+    T: Scalar,
+    IN: Deref<Target = [T]>,
+    OUT: DerefMut<Target = [T::Real]>,
+{
+    assert_eq!(src[..].len(), dst[..].len());
+    dst[..]
+        .iter_mut()
+        .zip(src[..].iter())
+        .for_each(|(y, x)| *y = x.re());
+}
+
+/// Copy the imaginary parts of `src` into `dst`. For real-valued `T`, `dst` is set to zero.
+#[inline]
+pub fn imag_part<T, IN, OUT>(src: IN, mut dst: OUT)
+where
+    T: Scalar,
+    IN: Deref<Target = [T]>,
+    OUT: DerefMut<Target = [T::Real]>,
+{
+    assert_eq!(src[..].len(), dst[..].len());
+    dst[..]
+        .iter_mut()
+        .zip(src[..].iter())
+        .for_each(|(y, x)| *y = x.im());
+}
+
+/// Assemble `dst[i] = re[i] + i im[i]`, the inverse of [`real_part`] and [`imag_part`].
+///
+/// Together, they map a complex system $Ax = b$ to the equivalent real one
+/// $$\begin{bmatrix} \mathrm{Re} A & -\mathrm{Im} A \\ \mathrm{Im} A & \mathrm{Re} A
+/// \end{bmatrix} \begin{bmatrix} \mathrm{Re}\,x \\ \mathrm{Im}\,x \end{bmatrix} =
+/// \begin{bmatrix} \mathrm{Re}\,b \\ \mathrm{Im}\,b \end{bmatrix}.$$
+///
+/// **NOTE:** For real-valued `T`, there is no imaginary part to store, and `im` is ignored.
+///
+/// # Example
+///
+/// ```
+/// # use sprsolve::vecalg::{from_parts, imag_part};
+/// use cauchy::c64;
+///
+/// let mut z = vec![c64::new(0., 0.); 2];
+/// from_parts(&[1., 2.][..], &[3., 4.][..], z.as_mut_slice());
+/// assert_eq!(z, vec![c64::new(1., 3.), c64::new(2., 4.)]);
+/// let mut im = vec![0_f64; 2];
+/// imag_part(z.as_slice(), im.as_mut_slice());
+/// assert_eq!(im, vec![3., 4.]);
+/// ```
+#[inline]
+pub fn from_parts<T, RE, IM, OUT>(re: RE, im: IM, mut dst: OUT)
+where
+    T: Scalar,
+    RE: Deref<Target = [T::Real]>,
+    IM: Deref<Target = [T::Real]>,
+    OUT: DerefMut<Target = [T]>,
+{
+    let (re, im, dst) = (&re[..], &im[..], &mut dst[..]);
+    assert_eq!(re.len(), dst.len());
+    assert_eq!(im.len(), dst.len());
+    macro_rules! assemble {
+        ($ty:ty) => {
+            if super::same_type::<T, num_complex::Complex<$ty>>() {
+                dst.iter_mut()
+                    .zip(re.iter().zip(im.iter()))
+                    .for_each(|(z, (x, y))| {
+                        let c =
+                            num_complex::Complex::<$ty>::new(super::cast_as(x), super::cast_as(y));
+                        *z = super::cast_as(&c);
+                    });
+                return;
+            }
+        };
+    }
+    assemble!(f32);
+    assemble!(f64);
+
+    // real-valued T
+    dst.iter_mut()
+        .zip(re.iter())
+        .for_each(|(z, &x)| *z = T::from_real(x));
+}
+
+/// Dot product with CBLAS calls.
+#[cfg(feature = "mkl")]
+pub fn dot<T, IN1, IN2>(vec1: IN1, vec2: IN2) -> T
+where
+    T: Scalar,
+    IN1: Deref<Target = [T]>,
+    IN2: Deref<Target = [T]>,
+{
+    let n = vec1[..].len();
+    assert_eq!(n, vec2[..].len());
+
+    // Use only if the vector is large enough to be worth it
+    if n > DOT_BLAS_CUTOFF {
+        return dot_chunked(&vec1[..], &vec2[..], BLAS_MAX_LEN);
+    }
+    dot_fallback(&vec1[..], &vec2[..])
+}
+
+/// compute $\mathbf{x}\cdot\mathbf{y} = \mathbf{x}^H\mathbf{y}$.
+///
+/// **NOTE:** If the vector is complex-valued, this function is conjugate-linear to
+/// the first argument, and linear to the second argument.
+/// Here is an example to show the expected results.
+/// ```
+/// # use sprsolve::vecalg::conj_dot;
+/// use cauchy::c64;
+///
+/// let a = vec![c64::new(4., 3.); 100];
+/// let b = vec![c64::new(2., -3.); 100];
+/// let r = conj_dot(a.as_slice(), b.as_slice());
+/// let t = a[0].conj() * b[0] * 100.;
+/// approx::assert_abs_diff_eq!(t.re, r.re); //, epsilon = f64::EPSILON);
+/// approx::assert_abs_diff_eq!(t.im, r.im);
+/// ```
+#[cfg(feature = "mkl")]
+pub fn conj_dot<T, IN1, IN2>(vec1: IN1, vec2: IN2) -> T
+where
+    T: Scalar,
+    IN1: Deref<Target = [T]>,
+    IN2: Deref<Target = [T]>,
+{
+    let n = vec1[..].len();
+    assert_eq!(n, vec2[..].len());
+
+    // Use only if the vector is large enough to be worth it
+    if n > DOT_BLAS_CUTOFF {
+        return conj_dot_chunked(&vec1[..], &vec2[..], BLAS_MAX_LEN);
+    }
+    conj_dot_fallback(&vec1[..], &vec2[..])
+}
+
+#[cfg(feature = "mkl")]
+pub fn norm2<T, VEC>(vec: VEC) -> T::Real
+where
+    T: Scalar,
+    VEC: Deref<Target = [T]>,
+{
+    let n = vec[..].len();
+
+    if n > DOT_BLAS_CUTOFF {
+        return norm2_chunked(&vec[..], BLAS_MAX_LEN);
+    }
+    norm2_fallback(&vec[..])
+}
+
+/// Compute vec = vec * a
+#[cfg(feature = "mkl")]
+pub fn scale<T, VEC>(a: T, mut vec: VEC)
+where
+    T: Scalar,
+    VEC: DerefMut<Target = [T]>,
+{
+    let n = vec[..].len();
+    if n > SCALE_BLAS_CUTOFF {
+        vec[..]
+            .chunks_mut(BLAS_MAX_LEN)
+            .for_each(|v| scale_blas(a, v));
+        return;
+    }
+    scale_fallback(a, &mut vec[..]);
+}
+
+/// Compute vec = vec * a
+#[cfg(feature = "mkl")]
+pub fn rscale<T, VEC>(a: T::Real, mut vec: VEC)
+where
+    T: Scalar,
+    VEC: DerefMut<Target = [T]>,
+{
+    let n = vec[..].len();
+    if n > SCALE_BLAS_CUTOFF {
+        vec[..]
+            .chunks_mut(BLAS_MAX_LEN)
+            .for_each(|v| rscale_blas(a, v));
+        return;
+    }
+    rscale_fallback(a, &mut vec[..]);
+}
+
+#[cfg(feature = "mkl")]
+#[inline]
+pub fn conj<T, IN, OUT>(vec_in: IN, mut vec_out: OUT)
+where
+    T: Scalar,
+    IN: Deref<Target = [T]>,
+    OUT: DerefMut<Target = [T]>,
+{
+    let n = vec_in[..].len();
+    assert_eq!(n, vec_out[..].len());
+    if n > SCALE_BLAS_CUTOFF {
+        vec_out[..]
+            .chunks_mut(BLAS_MAX_LEN)
+            .zip(vec_in[..].chunks(BLAS_MAX_LEN))
+            .for_each(|(y, x)| conj_blas(x, y));
+        return;
+    }
+    conj_fallback(&vec_in[..], &mut vec_out[..])
+}
+
+/// The standard `axpy` operation as in BLAS: vec2 = vec2 + a*vec1
+///
+/// BLAS is called only when `a` has the same type as the vector entries.
+#[cfg(feature = "mkl")]
+pub fn axpy<S, T, IN, OUT>(a: S, vec1: IN, mut vec2: OUT)
+where
+    S: Copy + 'static,
+    T: Scalar + Mul<S, Output = T>,
+    IN: Deref<Target = [T]>,
+    OUT: DerefMut<Target = [T]>,
+{
+    let n = vec1[..].len();
+    assert_eq!(n, vec2[..].len());
+    if n > AXPY_BLAS_CUTOFF && super::same_type::<S, T>() {
+        axpy_chunked(super::cast_as(&a), &vec1[..], &mut vec2[..], BLAS_MAX_LEN);
+        return;
+    }
+    axpy_fallback(a, &vec1[..], &mut vec2[..])
+}
+
+/// The `axpy` operation with a real scalar: vec2 = vec2 + a*vec1,
+/// where a is a real number
+///
+/// A complex vector is processed as a real vector of twice the length, so that
+/// `cblas_?axpy` only multiplies by the real `a`.
+///
+/// # Example
+///
+/// ```
+/// # use sprsolve::vecalg::raxpy;
+/// use cauchy::c64;
+///
+/// let x = vec![c64::new(1., -2.); 128];
+/// let mut y = vec![c64::new(0., 1.); 128];
+/// raxpy(0.5, x.as_slice(), y.as_mut_slice());
+/// assert!(y.iter().all(|&v| v == c64::new(0.5, 0.)));
+/// ```
+#[cfg(feature = "mkl")]
+pub fn raxpy<T, IN, OUT>(a: T::Real, vec1: IN, mut vec2: OUT)
+where
+    T: Scalar,
+    IN: Deref<Target = [T]>,
+    OUT: DerefMut<Target = [T]>,
+{
+    let n = vec1[..].len();
+    assert_eq!(n, vec2[..].len());
+    if n > AXPY_BLAS_CUTOFF {
+        // complex vectors are twice as long as real vectors
+        vec2[..]
+            .chunks_mut(BLAS_MAX_LEN / 2)
+            .zip(vec1[..].chunks(BLAS_MAX_LEN / 2))
+            .for_each(|(y, x)| raxpy_blas(a, x, y));
+        return;
+    }
+    raxpy_fallback(a, &vec1[..], &mut vec2[..])
+}
+
+/// The `axpby` operation as in MKL: vec2 = b*vec2 + a*vec1
+///
+/// BLAS is called only when `a` and `b` have the same type as the vector entries.
+///
+/// # Example
+///
+/// ```
+/// # use sprsolve::vecalg::axpby;
+/// let a = vec![1_f32; 128];
+/// let mut b = vec![2_f32; 128];
+/// axpby(2., a.as_slice(), -1., b.as_mut_slice());
+/// for i in 0..b.len() {
+///     approx::assert_abs_diff_eq!(0., b[i]);
+/// }
+/// ```
+#[cfg(feature = "mkl")]
+pub fn axpby<S, T, IN, OUT>(a: S, vec1: IN, b: S, mut vec2: OUT)
+where
+    S: Copy + 'static,
+    T: Scalar + Mul<S, Output = T>,
+    IN: Deref<Target = [T]>,
+    OUT: DerefMut<Target = [T]>,
+{
+    let n = vec1[..].len();
+    assert_eq!(n, vec2[..].len());
+    if n > AXPY_BLAS_CUTOFF && super::same_type::<S, T>() {
+        axpby_chunked(
+            super::cast_as(&a),
+            &vec1[..],
+            super::cast_as(&b),
+            &mut vec2[..],
+            BLAS_MAX_LEN,
+        );
+        return;
+    }
+    axpby_fallback(a, &vec1[..], b, &mut vec2[..])
+}
+
+/// The out-of-place `axpy`: out = a*vec1 + vec2, leaving `vec2` unchanged.
+///
+/// There is no BLAS routine for it, so long vectors are copied into `out` before calling
+/// `cblas_?axpy`. `out` is borrowed mutably, so it cannot alias `vec1` or `vec2`.
+///
+/// # Example
+///
+/// ```
+/// # use sprsolve::vecalg::waxpy;
+/// let x = vec![1_f64; 128];
+/// let y = vec![2_f64; 128];
+/// let mut w = vec![0_f64; 128];
+/// waxpy(3., x.as_slice(), y.as_slice(), w.as_mut_slice());
+/// assert!(w.iter().all(|&v| v == 5.));
+/// ```
+#[cfg(feature = "mkl")]
+pub fn waxpy<S, T, IN1, IN2, OUT>(a: S, vec1: IN1, vec2: IN2, mut out: OUT)
+where
+    S: Copy + 'static,
+    T: Scalar + Mul<S, Output = T>,
+    IN1: Deref<Target = [T]>,
+    IN2: Deref<Target = [T]>,
+    OUT: DerefMut<Target = [T]>,
+{
+    let n = vec1[..].len();
+    assert_eq!(n, vec2[..].len());
+    assert_eq!(n, out[..].len());
+    if n > AXPY_BLAS_CUTOFF && super::same_type::<S, T>() {
+        out[..].copy_from_slice(&vec2[..]);
+        axpy_chunked(super::cast_as(&a), &vec1[..], &mut out[..], BLAS_MAX_LEN);
+        return;
+    }
+    waxpy_fallback(a, &vec1[..], &vec2[..], &mut out[..])
+}
+
+/// Copy `src` into `dst`.
+///
+/// # Example
+///
+/// ```
+/// # use sprsolve::vecalg::copy;
+/// let a = vec![1_f64; 128];
+/// let mut b = vec![2_f64; 128];
+/// copy(a.as_slice(), b.as_mut_slice());
+/// for i in 0..b.len() {
+///     approx::assert_abs_diff_eq!(1., b[i]);
+/// }
+/// ```
+#[cfg(feature = "mkl")]
+pub fn copy<T, IN, OUT>(src: IN, mut dst: OUT)
+where
+    T: Scalar,
+    IN: Deref<Target = [T]>,
+    OUT: DerefMut<Target = [T]>,
+{
+    let n = src[..].len();
+    assert_eq!(n, dst[..].len());
+    if n > SCALE_BLAS_CUTOFF {
+        dst[..]
+            .chunks_mut(BLAS_MAX_LEN)
+            .zip(src[..].chunks(BLAS_MAX_LEN))
+            .for_each(|(y, x)| copy_blas(x, y));
+        return;
+    }
+    dst[..].copy_from_slice(&src[..]);
+}
+
+/// Copy `src` scaled by `a` into `dst`: dst = a*src.
+///
+/// Long vectors are processed by `cblas_?copy` followed by `cblas_?scal`, which is called
+/// only when `a` has the same type as the vector entries. The fallback is a single loop.
+///
+/// # Example
+///
+/// ```
+/// # use sprsolve::vecalg::scaled_copy;
+/// let a = vec![2_f64; 128];
+/// let mut b = vec![0_f64; 128];
+/// scaled_copy(0.5, a.as_slice(), b.as_mut_slice());
+/// assert!(b.iter().all(|&v| v == 1.));
+/// ```
+#[cfg(feature = "mkl")]
+pub fn scaled_copy<S, T, IN, OUT>(a: S, src: IN, mut dst: OUT)
+where
+    S: Copy + 'static,
+    T: Scalar + Mul<S, Output = T>,
+    IN: Deref<Target = [T]>,
+    OUT: DerefMut<Target = [T]>,
+{
+    let n = src[..].len();
+    assert_eq!(n, dst[..].len());
+    if n > SCALE_BLAS_CUTOFF && super::same_type::<S, T>() {
+        let a: T = super::cast_as(&a);
+        dst[..]
+            .chunks_mut(BLAS_MAX_LEN)
+            .zip(src[..].chunks(BLAS_MAX_LEN))
+            .for_each(|(y, x)| {
+                copy_blas(x, &mut *y);
+                scale_blas(a, y);
+            });
+        return;
+    }
+    scaled_copy_fallback(a, &src[..], &mut dst[..])
+}
+
+/// Swap the contents of `vec1` and `vec2`.
+///
+/// # Example
+///
+/// ```
+/// # use sprsolve::vecalg::swap;
+/// let mut a = vec![1_f64; 128];
+/// let mut b = vec![2_f64; 128];
+/// swap(a.as_mut_slice(), b.as_mut_slice());
+/// for i in 0..a.len() {
+///     approx::assert_abs_diff_eq!(2., a[i]);
+///     approx::assert_abs_diff_eq!(1., b[i]);
+/// }
+/// ```
+#[cfg(feature = "mkl")]
+pub fn swap<T, A, B>(mut vec1: A, mut vec2: B)
+where
+    T: Scalar,
+    A: DerefMut<Target = [T]>,
+    B: DerefMut<Target = [T]>,
+{
+    let n = vec1[..].len();
+    assert_eq!(n, vec2[..].len());
+    if n > SCALE_BLAS_CUTOFF {
+        vec1[..]
+            .chunks_mut(BLAS_MAX_LEN)
+            .zip(vec2[..].chunks_mut(BLAS_MAX_LEN))
+            .for_each(|(x, y)| swap_blas(x, y));
+        return;
+    }
+    vec1[..].swap_with_slice(&mut vec2[..]);
+}
+
+/// Compute $\sum_i |\mathrm{Re}(x_i)| + |\mathrm{Im}(x_i)|$, which is the sum of the
+/// absolute values for real vectors.
+///
+/// **NOTE:** For complex vectors, this follows the BLAS `?asum` definition rather than
+/// summing the moduli.
+#[cfg(feature = "mkl")]
+pub fn asum<T, VEC>(vec: VEC) -> T::Real
+where
+    T: Scalar,
+    VEC: Deref<Target = [T]>,
+{
+    let n = vec[..].len();
+    if n > DOT_BLAS_CUTOFF {
+        return vec[..]
+            .chunks(BLAS_MAX_LEN)
+            .fold(T::Real::zero(), |acc, v| acc + asum_blas(v));
+    }
+    asum_fallback(&vec[..])
+}
+
+/// Return the index of the first entry with the largest magnitude $|\mathrm{Re}(x_i)| +
+/// |\mathrm{Im}(x_i)|$, as the BLAS `i?amax`. It returns 0 for an empty vector.
+#[cfg(feature = "mkl")]
+pub fn iamax<T, VEC>(vec: VEC) -> usize
+where
+    T: Scalar,
+    VEC: Deref<Target = [T]>,
+{
+    let n = vec[..].len();
+    if n > DOT_BLAS_CUTOFF {
+        let mut imax = 0;
+        let mut vmax = T::Real::zero();
+        for (k, v) in vec[..].chunks(BLAS_MAX_LEN).enumerate() {
+            let i = iamax_blas(v);
+            let m = abs1(v[i]);
+            if k == 0 || m > vmax {
+                imax = k * BLAS_MAX_LEN + i;
+                vmax = m;
+            }
+        }
+        return imax;
+    }
+    iamax_fallback(&vec[..])
+}
+
+/// Apply the Givens rotation generated by [`rotg`] to the vectors `x` and `y`, i.e.
+/// $x_i \leftarrow c x_i + s y_i$ and $y_i \leftarrow -\bar s x_i + c y_i$.
+///
+/// **NOTE:** CBLAS has no rotation of complex vectors by a complex sine, so only real
+/// vectors call `cblas_?rot`.
+///
+/// # Example
+///
+/// ```
+/// # use sprsolve::vecalg::rot;
+/// let mut x = vec![3_f64; 128];
+/// let mut y = vec![-4_f64; 128];
+/// rot(x.as_mut_slice(), y.as_mut_slice(), 0.6, -0.8);
+/// for i in 0..x.len() {
+///     approx::assert_abs_diff_eq!(5., x[i], epsilon = 1E-14);
+///     approx::assert_abs_diff_eq!(0., y[i], epsilon = 1E-14);
+/// }
+/// ```
+#[cfg(feature = "mkl")]
+pub fn rot<T, X, Y>(mut x: X, mut y: Y, c: T::Real, s: T)
+where
+    T: Scalar,
+    X: DerefMut<Target = [T]>,
+    Y: DerefMut<Target = [T]>,
+{
+    let n = x[..].len();
+    assert_eq!(n, y[..].len());
+    if n > AXPY_BLAS_CUTOFF {
+        let done = x[..]
+            .chunks_mut(BLAS_MAX_LEN)
+            .zip(y[..].chunks_mut(BLAS_MAX_LEN))
+            .all(|(x, y)| rot_blas(x, y, c, s.re()));
+        if done {
+            return;
+        }
+    }
+    rot_fallback(&mut x[..], &mut y[..], c, s)
+}
+
+// ---------------------------------------------------------------------------------------------
+// CBLAS takes the vector length as a c_int. Vectors longer than that are split into chunks of
+// at most `chunk` entries, each handled by a single CBLAS call, and the results are accumulated.
+
+#[cfg(feature = "mkl")]
+fn dot_chunked<T: Scalar>(vec1: &[T], vec2: &[T], chunk: usize) -> T {
+    vec1.chunks(chunk)
+        .zip(vec2.chunks(chunk))
+        .fold(T::zero(), |acc, (x, y)| acc + dot_blas(x, y))
+}
+
+#[cfg(feature = "mkl")]
+fn conj_dot_chunked<T: Scalar>(vec1: &[T], vec2: &[T], chunk: usize) -> T {
+    vec1.chunks(chunk)
+        .zip(vec2.chunks(chunk))
+        .fold(T::zero(), |acc, (x, y)| acc + conj_dot_blas(x, y))
+}
+
+#[cfg(feature = "mkl")]
+fn norm2_chunked<T: Scalar>(vec: &[T], chunk: usize) -> T::Real {
+    vec.chunks(chunk).fold(T::Real::zero(), |acc, v| {
+        num_traits::Float::hypot(acc, norm2_blas(v))
+    })
+}
+
+#[cfg(feature = "mkl")]
+fn axpy_chunked<T: Scalar>(a: T, vec1: &[T], vec2: &mut [T], chunk: usize) {
+    vec2.chunks_mut(chunk)
+        .zip(vec1.chunks(chunk))
+        .for_each(|(y, x)| axpy_blas(a, x, y));
+}
 
-    // Use only if the vector is large enough to be worth it
-    if n > DOT_BLAS_CUTOFF && n < std::os::raw::c_int::max_value() as usize {
-        macro_rules! dot {
-            ($ty:ty, $func:ident, {}) => {
-                if super::same_type::<T, $ty>() {
-                    let v = unsafe {
-                        $func(
-                            n as i32, // this is safe because the assert! above
-                            vec1[..].as_ptr() as *const $ty,
-                            1,
-                            vec2[..].as_ptr() as *const $ty,
-                            1,
-                        )
-                    };
-                    return super::cast_as::<$ty, T>(&v);
-                }
-            };
-            ($ty:ty, $func:ident, {complex}) => {
-                if super::same_type::<T, num_complex::Complex<$ty>>() {
-                    let mut r: num_complex::Complex<$ty> = Default::default();
-                    unsafe {
-                        $func(
-                            n as i32, // this is safe because the assert! above
-                            vec1[..].as_ptr() as *const c_void,
-                            1,
-                            vec2[..].as_ptr() as *const c_void,
-                            1,
-                            &mut r as *mut num_complex::Complex<$ty> as *mut c_void,
-                        );
-                    }
-                    return super::cast_as::<num_complex::Complex<$ty>, T>(&r);
+#[cfg(feature = "mkl")]
+fn axpby_chunked<T: Scalar>(a: T, vec1: &[T], b: T, vec2: &mut [T], chunk: usize) {
+    vec2.chunks_mut(chunk)
+        .zip(vec1.chunks(chunk))
+        .for_each(|(y, x)| axpby_blas(a, x, b, y));
+}
+
+#[cfg(feature = "mkl")]
+fn dot_blas<T: Scalar>(vec1: &[T], vec2: &[T]) -> T {
+    let n = vec1.len();
+    debug_assert!(n <= BLAS_MAX_LEN);
+    macro_rules! dot {
+        ($ty:ty, $func:ident, {}) => {
+            if super::same_type::<T, $ty>() {
+                let v = unsafe {
+                    $func(
+                        n as i32, // this is safe because of the chunk size
+                        vec1.as_ptr() as *const $ty,
+                        1,
+                        vec2.as_ptr() as *const $ty,
+                        1,
+                    )
+                };
+                return super::cast_as::<$ty, T>(&v);
+            }
+        };
+        ($ty:ty, $func:ident, {complex}) => {
+            if super::same_type::<T, num_complex::Complex<$ty>>() {
+                let mut r: num_complex::Complex<$ty> = Default::default();
+                unsafe {
+                    $func(
+                        n as i32, // this is safe because of the chunk size
+                        vec1.as_ptr() as *const c_void,
+                        1,
+                        vec2.as_ptr() as *const c_void,
+                        1,
+                        &mut r as *mut num_complex::Complex<$ty> as *mut c_void,
+                    );
                 }
-            };
-        }
+                return super::cast_as::<num_complex::Complex<$ty>, T>(&r);
+            }
+        };
+    }
+
+    dot! {f32, cblas_sdot, {}};
+    dot! {f64, cblas_ddot, {}};
+    dot! {f32, cblas_cdotu_sub, {complex} };
+    dot! {f64, cblas_zdotu_sub, {complex} };
+    dot_fallback(vec1, vec2)
+}
 
-        dot! {f32, cblas_sdot, {}};
-        dot! {f64, cblas_ddot, {}};
-        dot! {f32, cblas_cdotc_sub, {complex} };
-        dot! {f64, cblas_zdotc_sub, {complex} };
+#[cfg(feature = "mkl")]
+fn conj_dot_blas<T: Scalar>(vec1: &[T], vec2: &[T]) -> T {
+    let n = vec1.len();
+    debug_assert!(n <= BLAS_MAX_LEN);
+    macro_rules! dot {
+        ($ty:ty, $func:ident, {}) => {
+            if super::same_type::<T, $ty>() {
+                let v = unsafe {
+                    $func(
+                        n as i32, // this is safe because of the chunk size
+                        vec1.as_ptr() as *const $ty,
+                        1,
+                        vec2.as_ptr() as *const $ty,
+                        1,
+                    )
+                };
+                return super::cast_as::<$ty, T>(&v);
+            }
+        };
+        ($ty:ty, $func:ident, {complex}) => {
+            if super::same_type::<T, num_complex::Complex<$ty>>() {
+                let mut r: num_complex::Complex<$ty> = Default::default();
+                unsafe {
+                    $func(
+                        n as i32, // this is safe because of the chunk size
+                        vec1.as_ptr() as *const c_void,
+                        1,
+                        vec2.as_ptr() as *const c_void,
+                        1,
+                        &mut r as *mut num_complex::Complex<$ty> as *mut c_void,
+                    );
+                }
+                return super::cast_as::<num_complex::Complex<$ty>, T>(&r);
+            }
+        };
     }
-    conj_dot_fallback(&vec1[..], &vec2[..])
+
+    dot! {f32, cblas_sdot, {}};
+    dot! {f64, cblas_ddot, {}};
+    dot! {f32, cblas_cdotc_sub, {complex} };
+    dot! {f64, cblas_zdotc_sub, {complex} };
+    conj_dot_fallback(vec1, vec2)
 }
 
 #[cfg(feature = "mkl")]
-pub fn norm2<T, VEC>(vec: VEC) -> T::Real
-where
-    T: Scalar,
-    VEC: Deref<Target = [T]>,
-{
-    let n = vec[..].len();
+fn norm2_blas<T: Scalar>(vec: &[T]) -> T::Real {
+    let n = vec.len();
+    debug_assert!(n <= BLAS_MAX_LEN);
+    macro_rules! nrm2 {
+        ($ty:ty, $func:ident, {}) => {
+            if super::same_type::<T, $ty>() {
+                let v = unsafe { $func(n as i32, vec.as_ptr() as *const $ty, 1) };
+                return super::cast_as::<$ty, T::Real>(&v);
+            }
+        };
+        ($ty:ty, $func:ident, {complex}) => {
+            if super::same_type::<T, num_complex::Complex<$ty>>() {
+                let v = unsafe { $func(n as i32, vec.as_ptr() as *const c_void, 1) };
+                return super::cast_as::<$ty, T::Real>(&v);
+            }
+        };
+    }
+    nrm2! {f32, cblas_snrm2, {}};
+    nrm2! {f64, cblas_dnrm2, {}};
+    nrm2! {f32, cblas_scnrm2, {complex}};
+    nrm2! {f64, cblas_dznrm2, {complex}};
+    norm2_fallback(vec)
+}
 
-    if n > DOT_BLAS_CUTOFF && n < std::os::raw::c_int::max_value() as usize {
-        macro_rules! nrm2 {
-            ($ty:ty, $func:ident, {}) => {
-                if super::same_type::<T, $ty>() {
-                    let v = unsafe { $func(n as i32, vec[..].as_ptr() as *const $ty, 1) };
-                    return super::cast_as::<$ty, T::Real>(&v);
+#[cfg(feature = "mkl")]
+fn scale_blas<T: Scalar>(a: T, vec: &mut [T]) {
+    let n = vec.len();
+    debug_assert!(n <= BLAS_MAX_LEN);
+    macro_rules! scale {
+        ($ty:ty, $func:ident, {}) => {
+            if super::same_type::<T, $ty>() {
+                unsafe {
+                    $func(
+                        n as i32, // this is safe because of the chunk size
+                        super::cast_as::<T, $ty>(&a),
+                        vec.as_mut_ptr() as *mut $ty,
+                        1,
+                    )
+                };
+                return;
+            }
+        };
+        ($ty:ty, $func:ident, {complex}) => {
+            if super::same_type::<T, num_complex::Complex<$ty>>() {
+                unsafe {
+                    $func(
+                        n as i32, // this is safe because of the chunk size
+                        &a as *const T as *const c_void,
+                        vec.as_mut_ptr() as *mut c_void,
+                        1,
+                    );
                 }
-            };
-            ($ty:ty, $func:ident, {complex}) => {
-                if super::same_type::<T, num_complex::Complex<$ty>>() {
-                    let v = unsafe { $func(n as i32, vec[..].as_ptr() as *const c_void, 1) };
-                    return super::cast_as::<$ty, T::Real>(&v);
+                return;
+            }
+        };
+    }
+    scale! {f32, cblas_sscal, {}};
+    scale! {f64, cblas_dscal, {}};
+    scale! {f32, cblas_cscal, {complex}};
+    scale! {f64, cblas_zscal, {complex}};
+    scale_fallback(a, vec);
+}
+
+#[cfg(feature = "mkl")]
+fn rscale_blas<T: Scalar>(a: T::Real, vec: &mut [T]) {
+    let n = vec.len();
+    debug_assert!(n <= BLAS_MAX_LEN);
+    macro_rules! scale {
+        ($ty:ty, $func:ident, {}) => {
+            if super::same_type::<T, $ty>() {
+                unsafe {
+                    $func(
+                        n as i32, // this is safe because of the chunk size
+                        super::cast_as::<T::Real, $ty>(&a),
+                        vec.as_mut_ptr() as *mut $ty,
+                        1,
+                    )
+                };
+                return;
+            }
+        };
+        ($ty:ty, $func:ident, {complex}) => {
+            if super::same_type::<T, num_complex::Complex<$ty>>() {
+                unsafe {
+                    $func(
+                        n as i32, // this is safe because of the chunk size
+                        super::cast_as::<T::Real, $ty>(&a),
+                        vec.as_mut_ptr() as *mut c_void,
+                        1,
+                    );
                 }
-            };
-        }
-        nrm2! {f32, cblas_snrm2, {}};
-        nrm2! {f64, cblas_dnrm2, {}};
-        nrm2! {f32, cblas_scnrm2, {complex}};
-        nrm2! {f64, cblas_dznrm2, {complex}};
+                return;
+            }
+        };
     }
-    norm2_fallback(&vec[..])
+    scale! {f32, cblas_sscal, {}};
+    scale! {f64, cblas_dscal, {}};
+    scale! {f32, cblas_csscal, {complex}};
+    scale! {f64, cblas_zdscal, {complex}};
+    rscale_fallback(a, vec);
 }
 
-/// Compute vec = vec * a
 #[cfg(feature = "mkl")]
-pub fn scale<T, VEC>(a: T, mut vec: VEC)
-where
-    T: Scalar,
-    VEC: DerefMut<Target = [T]>,
-{
-    let n = vec[..].len();
-    if n > SCALE_BLAS_CUTOFF && n < std::os::raw::c_int::max_value() as usize {
-        macro_rules! scale {
-            ($ty:ty, $func:ident, {}) => {
-                if super::same_type::<T, $ty>() {
-                    unsafe {
-                        $func(
-                            n as i32, // this is safe because the assert! above
-                            super::cast_as::<T, $ty>(&a),
-                            vec[..].as_mut_ptr() as *mut $ty,
-                            1,
-                        )
-                    };
-                    return;
+fn conj_blas<T: Scalar>(vec_in: &[T], vec_out: &mut [T]) {
+    let n = vec_in.len();
+    debug_assert!(n <= BLAS_MAX_LEN);
+    macro_rules! conj {
+        ($ty:ty, $func:ident, {}) => {
+            if super::same_type::<T, $ty>() {
+                unsafe {
+                    $func(
+                        n as i32,
+                        vec_in.as_ptr() as *const $ty,
+                        1,
+                        vec_out.as_mut_ptr() as *mut $ty,
+                        1,
+                    );
                 }
-            };
-            ($ty:ty, $func:ident, {complex}) => {
-                if super::same_type::<T, num_complex::Complex<$ty>>() {
-                    unsafe {
-                        $func(
-                            n as i32, // this is safe because the assert! above
-                            &a as *const T as *const c_void,
-                            vec[..].as_mut_ptr() as *mut c_void,
-                            1,
-                        );
-                    }
-                    return;
+                return;
+            }
+        };
+        ($ty:ty, $func:ident, {$func2:ident}) => {
+            if super::same_type::<T, num_complex::Complex<$ty>>() {
+                unsafe {
+                    $func(
+                        n as i32,
+                        vec_in.as_ptr() as *const c_void,
+                        1,
+                        vec_out.as_mut_ptr() as *mut c_void,
+                        1,
+                    );
+                    let ptr = vec_out.as_mut_ptr() as *mut $ty;
+                    $func2(n as i32, -1., ptr.add(1), 2);
                 }
-            };
-        }
-        scale! {f32, cblas_sscal, {}};
-        scale! {f64, cblas_dscal, {}};
-        scale! {f32, cblas_cscal, {complex}};
-        scale! {f64, cblas_zscal, {complex}};
+                return;
+            }
+        };
     }
-    scale_fallback(a, &mut vec[..]);
+    conj! {f32, cblas_scopy, {}};
+    conj! {f64, cblas_dcopy, {}};
+    conj! {f32, cblas_ccopy, {cblas_sscal}};
+    conj! {f64, cblas_zcopy, {cblas_dscal}};
+    conj_fallback(vec_in, vec_out)
 }
 
-/// Compute vec = vec * a
 #[cfg(feature = "mkl")]
-pub fn rscale<T, VEC>(a: T::Real, mut vec: VEC)
-where
-    T: Scalar,
-    VEC: DerefMut<Target = [T]>,
-{
-    let n = vec[..].len();
-    if n > SCALE_BLAS_CUTOFF && n < std::os::raw::c_int::max_value() as usize {
-        macro_rules! scale {
-            ($ty:ty, $func:ident, {}) => {
-                if super::same_type::<T, $ty>() {
-                    unsafe {
-                        $func(
-                            n as i32, // this is safe because the assert! above
-                            super::cast_as::<T::Real, $ty>(&a),
-                            vec[..].as_mut_ptr() as *mut $ty,
-                            1,
-                        )
-                    };
-                    return;
+fn axpy_blas<T: Scalar>(a: T, vec1: &[T], vec2: &mut [T]) {
+    let n = vec1.len();
+    debug_assert!(n <= BLAS_MAX_LEN);
+    macro_rules! axpy {
+        ($ty:ty, $func:ident, {}) => {
+            if super::same_type::<T, $ty>() {
+                unsafe {
+                    $func(
+                        n as i32,
+                        super::cast_as::<T, $ty>(&a),
+                        vec1.as_ptr() as *const $ty,
+                        1,
+                        vec2.as_mut_ptr() as *mut $ty,
+                        1,
+                    );
                 }
-            };
-            ($ty:ty, $func:ident, {complex}) => {
-                if super::same_type::<T, num_complex::Complex<$ty>>() {
-                    unsafe {
-                        $func(
-                            n as i32, // this is safe because the assert! above
-                            super::cast_as::<T::Real, $ty>(&a),
-                            vec[..].as_mut_ptr() as *mut c_void,
-                            1,
-                        );
-                    }
-                    return;
+                return;
+            }
+        };
+        ($ty:ty, $func:ident, {complex}) => {
+            if super::same_type::<T, num_complex::Complex<$ty>>() {
+                unsafe {
+                    $func(
+                        n as i32,
+                        &a as *const T as *const c_void,
+                        vec1.as_ptr() as *const c_void,
+                        1,
+                        vec2.as_mut_ptr() as *mut c_void,
+                        1,
+                    );
                 }
-            };
-        }
-        scale! {f32, cblas_sscal, {}};
-        scale! {f64, cblas_dscal, {}};
-        scale! {f32, cblas_csscal, {complex}};
-        scale! {f64, cblas_zdscal, {complex}};
+                return;
+            }
+        };
     }
-    rscale_fallback(a, &mut vec[..]);
+    axpy! {f32, cblas_saxpy, {}};
+    axpy! {f64, cblas_daxpy, {}};
+    axpy! {f32, cblas_caxpy, {complex}};
+    axpy! {f64, cblas_zaxpy, {complex}};
+    axpy_fallback(a, vec1, vec2)
 }
 
 #[cfg(feature = "mkl")]
-#[inline]
-pub fn conj<T, IN, OUT>(vec_in: IN, mut vec_out: OUT)
-where
-    T: Scalar,
-    IN: Deref<Target = [T]>,
-    OUT: DerefMut<Target = [T]>,
-{
-    let n = vec_in[..].len();
-    assert_eq!(n, vec_out[..].len());
-    if n > SCALE_BLAS_CUTOFF && n < std::os::raw::c_int::max_value() as usize {
-        macro_rules! conj {
-            ($ty:ty, $func:ident, {}) => {
-                if super::same_type::<T, $ty>() {
-                    unsafe {
-                        $func(
-                            n as i32,
-                            vec_in[..].as_ptr() as *const $ty,
-                            1,
-                            vec_out[..].as_mut_ptr() as *mut $ty,
-                            1,
-                        );
-                    }
-                    return;
+fn raxpy_blas<T: Scalar>(a: T::Real, vec1: &[T], vec2: &mut [T]) {
+    // a complex vector is a real vector of twice the length
+    let n = if super::same_type::<T, T::Real>() {
+        vec1.len()
+    } else {
+        2 * vec1.len()
+    };
+    debug_assert!(n <= BLAS_MAX_LEN);
+    macro_rules! raxpy {
+        ($ty:ty, $func:ident) => {
+            if super::same_type::<T::Real, $ty>() {
+                unsafe {
+                    $func(
+                        n as i32, // this is safe because of the chunk size
+                        super::cast_as::<T::Real, $ty>(&a),
+                        vec1.as_ptr() as *const $ty,
+                        1,
+                        vec2.as_mut_ptr() as *mut $ty,
+                        1,
+                    );
+                }
+                return;
+            }
+        };
+    }
+    raxpy! {f32, cblas_saxpy};
+    raxpy! {f64, cblas_daxpy};
+    raxpy_fallback(a, vec1, vec2);
+}
+
+#[cfg(feature = "mkl")]
+fn axpby_blas<T: Scalar>(a: T, vec1: &[T], b: T, vec2: &mut [T]) {
+    let n = vec1.len();
+    debug_assert!(n <= BLAS_MAX_LEN);
+    macro_rules! axpy {
+        ($ty:ty, $func:ident, {}) => {
+            if super::same_type::<T, $ty>() {
+                unsafe {
+                    $func(
+                        n as i32,
+                        super::cast_as::<T, $ty>(&a),
+                        vec1.as_ptr() as *const $ty,
+                        1,
+                        super::cast_as::<T, $ty>(&b),
+                        vec2.as_mut_ptr() as *mut $ty,
+                        1,
+                    );
                 }
-            };
-            ($ty:ty, $func:ident, {$func2:ident}) => {
-                if super::same_type::<T, num_complex::Complex<$ty>>() {
-                    unsafe {
-                        $func(
-                            n as i32,
-                            vec_in[..].as_ptr() as *const c_void,
-                            1,
-                            vec_out[..].as_mut_ptr() as *mut c_void,
-                            1,
-                        );
-                        let ptr = vec_out[..].as_mut_ptr() as *mut $ty;
-                        $func2(n as i32, -1., ptr.add(1), 2);
-                    }
-                    return;
+                return;
+            }
+        };
+        ($ty:ty, $func:ident, {complex}) => {
+            if super::same_type::<T, num_complex::Complex<$ty>>() {
+                unsafe {
+                    $func(
+                        n as i32,
+                        &a as *const T as *const c_void,
+                        vec1.as_ptr() as *const c_void,
+                        1,
+                        &b as *const T as *const c_void,
+                        vec2.as_mut_ptr() as *mut c_void,
+                        1,
+                    );
                 }
-            };
-        }
-        conj! {f32, cblas_scopy, {}};
-        conj! {f64, cblas_dcopy, {}};
-        conj! {f32, cblas_ccopy, {cblas_sscal}};
-        conj! {f64, cblas_zcopy, {cblas_dscal}};
+                return;
+            }
+        };
     }
-    conj_fallback(&vec_in[..], &mut vec_out[..])
+    axpy! {f32, cblas_saxpby, {}};
+    axpy! {f64, cblas_daxpby, {}};
+    axpy! {f32, cblas_caxpby, {complex}};
+    axpy! {f64, cblas_zaxpby, {complex}};
+    axpby_fallback(a, vec1, b, vec2)
 }
 
 #[cfg(feature = "mkl")]
-pub fn axpy<T, IN, OUT>(a: T, vec1: IN, mut vec2: OUT)
-where
-    T: Scalar,
-    IN: Deref<Target = [T]>,
-    OUT: DerefMut<Target = [T]>,
-{
-    let n = vec1[..].len();
-    assert_eq!(n, vec2[..].len());
-    if n > AXPY_BLAS_CUTOFF && n < std::os::raw::c_int::max_value() as usize {
-        macro_rules! axpy {
-            ($ty:ty, $func:ident, {}) => {
-                if super::same_type::<T, $ty>() {
-                    unsafe {
-                        $func(
-                            n as i32,
-                            super::cast_as::<T, $ty>(&a),
-                            vec1[..].as_ptr() as *const $ty,
-                            1,
-                            vec2[..].as_mut_ptr() as *mut $ty,
-                            1,
-                        );
-                    }
-                    return;
+fn copy_blas<T: Scalar>(src: &[T], dst: &mut [T]) {
+    let n = src.len();
+    debug_assert!(n <= BLAS_MAX_LEN);
+    macro_rules! copy {
+        ($ty:ty, $func:ident, {}) => {
+            if super::same_type::<T, $ty>() {
+                unsafe {
+                    $func(
+                        n as i32,
+                        src.as_ptr() as *const $ty,
+                        1,
+                        dst.as_mut_ptr() as *mut $ty,
+                        1,
+                    );
                 }
-            };
-            ($ty:ty, $func:ident, {complex}) => {
-                if super::same_type::<T, num_complex::Complex<$ty>>() {
-                    unsafe {
-                        $func(
-                            n as i32,
-                            &a as *const T as *const c_void,
-                            vec1[..].as_ptr() as *const c_void,
-                            1,
-                            vec2[..].as_mut_ptr() as *mut c_void,
-                            1,
-                        );
-                    }
-                    return;
+                return;
+            }
+        };
+        ($ty:ty, $func:ident, {complex}) => {
+            if super::same_type::<T, num_complex::Complex<$ty>>() {
+                unsafe {
+                    $func(
+                        n as i32,
+                        src.as_ptr() as *const c_void,
+                        1,
+                        dst.as_mut_ptr() as *mut c_void,
+                        1,
+                    );
                 }
-            };
-        }
-        axpy! {f32, cblas_saxpy, {}};
-        axpy! {f64, cblas_daxpy, {}};
-        axpy! {f32, cblas_caxpy, {complex}};
-        axpy! {f64, cblas_zaxpy, {complex}};
+                return;
+            }
+        };
     }
-    axpy_fallback(a, &vec1[..], &mut vec2[..])
+    copy! {f32, cblas_scopy, {}};
+    copy! {f64, cblas_dcopy, {}};
+    copy! {f32, cblas_ccopy, {complex}};
+    copy! {f64, cblas_zcopy, {complex}};
+    dst.copy_from_slice(src)
 }
 
-/// The `axpby` operation as in MKL: vec2 = vec2 + a*vec1
-///
-/// # Example
-///
-/// ```
-/// # use sprsolve::vecalg::axpby;
-/// let a = vec![1_f32; 128];
-/// let mut b = vec![2_f32; 128];
-/// axpby(2., a.as_slice(), -1., b.as_mut_slice());
-/// for i in 0..b.len() {
-///     approx::assert_abs_diff_eq!(0., b[i]);
-/// }
-/// ```
 #[cfg(feature = "mkl")]
-pub fn axpby<T, IN, OUT>(a: T, vec1: IN, b: T, mut vec2: OUT)
-where
-    T: Scalar,
-    IN: Deref<Target = [T]>,
-    OUT: DerefMut<Target = [T]>,
-{
-    let n = vec1[..].len();
-    assert_eq!(n, vec2[..].len());
-    if n > AXPY_BLAS_CUTOFF && n < std::os::raw::c_int::max_value() as usize {
-        macro_rules! axpy {
-            ($ty:ty, $func:ident, {}) => {
-                if super::same_type::<T, $ty>() {
-                    unsafe {
-                        $func(
-                            n as i32,
-                            super::cast_as::<T, $ty>(&a),
-                            vec1[..].as_ptr() as *const $ty,
-                            1,
-                            super::cast_as::<T, $ty>(&b),
-                            vec2[..].as_mut_ptr() as *mut $ty,
-                            1,
-                        );
-                    }
-                    return;
+fn swap_blas<T: Scalar>(vec1: &mut [T], vec2: &mut [T]) {
+    let n = vec1.len();
+    debug_assert!(n <= BLAS_MAX_LEN);
+    macro_rules! swap {
+        ($ty:ty, $func:ident, {}) => {
+            if super::same_type::<T, $ty>() {
+                unsafe {
+                    $func(
+                        n as i32,
+                        vec1.as_mut_ptr() as *mut $ty,
+                        1,
+                        vec2.as_mut_ptr() as *mut $ty,
+                        1,
+                    );
                 }
-            };
-            ($ty:ty, $func:ident, {complex}) => {
-                if super::same_type::<T, num_complex::Complex<$ty>>() {
-                    unsafe {
-                        $func(
-                            n as i32,
-                            &a as *const T as *const c_void,
-                            vec1[..].as_ptr() as *const c_void,
-                            1,
-                            &b as *const T as *const c_void,
-                            vec2[..].as_mut_ptr() as *mut c_void,
-                            1,
-                        );
-                    }
-                    return;
+                return;
+            }
+        };
+        ($ty:ty, $func:ident, {complex}) => {
+            if super::same_type::<T, num_complex::Complex<$ty>>() {
+                unsafe {
+                    $func(
+                        n as i32,
+                        vec1.as_mut_ptr() as *mut c_void,
+                        1,
+                        vec2.as_mut_ptr() as *mut c_void,
+                        1,
+                    );
                 }
-            };
-        }
-        axpy! {f32, cblas_saxpby, {}};
-        axpy! {f64, cblas_daxpby, {}};
-        axpy! {f32, cblas_caxpby, {complex}};
-        axpy! {f64, cblas_zaxpby, {complex}};
+                return;
+            }
+        };
     }
-    axpby_fallback(a, &vec1[..], b, &mut vec2[..])
+    swap! {f32, cblas_sswap, {}};
+    swap! {f64, cblas_dswap, {}};
+    swap! {f32, cblas_cswap, {complex}};
+    swap! {f64, cblas_zswap, {complex}};
+    vec1.swap_with_slice(vec2)
+}
+
+/// Rotate real vectors with `cblas_?rot`, and return `false` without touching them for
+/// complex ones.
+#[cfg(feature = "mkl")]
+fn rot_blas<T: Scalar>(x: &mut [T], y: &mut [T], c: T::Real, s: T::Real) -> bool {
+    let n = x.len();
+    debug_assert!(n <= BLAS_MAX_LEN);
+    macro_rules! rot {
+        ($ty:ty, $func:ident) => {
+            if super::same_type::<T, $ty>() {
+                unsafe {
+                    $func(
+                        n as i32,
+                        x.as_mut_ptr() as *mut $ty,
+                        1,
+                        y.as_mut_ptr() as *mut $ty,
+                        1,
+                        super::cast_as::<T::Real, $ty>(&c),
+                        super::cast_as::<T::Real, $ty>(&s),
+                    );
+                }
+                return true;
+            }
+        };
+    }
+    rot! {f32, cblas_srot};
+    rot! {f64, cblas_drot};
+    false
+}
+
+#[cfg(feature = "mkl")]
+fn asum_blas<T: Scalar>(vec: &[T]) -> T::Real {
+    let n = vec.len();
+    debug_assert!(n <= BLAS_MAX_LEN);
+    macro_rules! asum {
+        ($ty:ty, $func:ident, {}) => {
+            if super::same_type::<T, $ty>() {
+                let v = unsafe { $func(n as i32, vec.as_ptr() as *const $ty, 1) };
+                return super::cast_as::<$ty, T::Real>(&v);
+            }
+        };
+        ($ty:ty, $func:ident, {complex}) => {
+            if super::same_type::<T, num_complex::Complex<$ty>>() {
+                let v = unsafe { $func(n as i32, vec.as_ptr() as *const c_void, 1) };
+                return super::cast_as::<$ty, T::Real>(&v);
+            }
+        };
+    }
+    asum! {f32, cblas_sasum, {}};
+    asum! {f64, cblas_dasum, {}};
+    asum! {f32, cblas_scasum, {complex}};
+    asum! {f64, cblas_dzasum, {complex}};
+    asum_fallback(vec)
+}
+
+#[cfg(feature = "mkl")]
+fn iamax_blas<T: Scalar>(vec: &[T]) -> usize {
+    let n = vec.len();
+    debug_assert!(n <= BLAS_MAX_LEN);
+    macro_rules! iamax {
+        ($ty:ty, $func:ident, {}) => {
+            if super::same_type::<T, $ty>() {
+                return unsafe { $func(n as i32, vec.as_ptr() as *const $ty, 1) };
+            }
+        };
+        ($ty:ty, $func:ident, {complex}) => {
+            if super::same_type::<T, num_complex::Complex<$ty>>() {
+                return unsafe { $func(n as i32, vec.as_ptr() as *const c_void, 1) };
+            }
+        };
+    }
+    iamax! {f32, cblas_isamax, {}};
+    iamax! {f64, cblas_idamax, {}};
+    iamax! {f32, cblas_icamax, {complex}};
+    iamax! {f64, cblas_izamax, {complex}};
+    iamax_fallback(vec)
 }
 
 #[inline]
 fn dot_fallback<T: Scalar>(vec1: &[T], vec2: &[T]) -> T {
+    #[cfg(feature = "simd")]
+    {
+        if let Some(r) = super::simd::dot(vec1, vec2) {
+            return r;
+        }
+    }
     vec1.iter()
         .zip(vec2.iter())
         .fold(T::zero(), |acc, (x, y)| acc + (*x) * (*y))
@@ -562,18 +1859,48 @@ fn dot_fallback<T: Scalar>(vec1: &[T], vec2: &[T]) -> T {
 
 #[inline]
 fn conj_dot_fallback<T: Scalar>(vec1: &[T], vec2: &[T]) -> T {
+    // real vectors need no conjugate
+    #[cfg(feature = "simd")]
+    {
+        if let Some(r) = super::simd::dot(vec1, vec2) {
+            return r;
+        }
+    }
     vec1.iter()
         .zip(vec2.iter())
         .fold(T::zero(), |acc, (x, y)| acc + x.conj() * (*y))
 }
 
 #[inline]
-fn axpy_fallback<S: Copy, T: Scalar + Mul<S, Output = T>>(a: S, vec1: &[T], vec2: &mut [T]) {
+fn axpy_fallback<S: Copy + 'static, T: Scalar + Mul<S, Output = T>>(
+    a: S,
+    vec1: &[T],
+    vec2: &mut [T],
+) {
+    #[cfg(feature = "simd")]
+    {
+        if super::simd::axpy(a, vec1, vec2) {
+            return;
+        }
+    }
     vec2.iter_mut()
         .zip(vec1.iter())
         .for_each(|(y, x)| *y += *x * a);
 }
 
+#[inline]
+fn raxpy_fallback<T: Scalar>(a: T::Real, vec1: &[T], vec2: &mut [T]) {
+    #[cfg(feature = "simd")]
+    {
+        if super::simd::axpy(a, vec1, vec2) {
+            return;
+        }
+    }
+    vec2.iter_mut()
+        .zip(vec1.iter())
+        .for_each(|(y, x)| *y += x.mul_real(a));
+}
+
 #[inline]
 fn conj_fallback<T: Scalar>(vec_in: &[T], vec_out: &mut [T]) {
     vec_out
@@ -589,19 +1916,95 @@ fn axpby_fallback<S: Copy, T: Scalar + Mul<S, Output = T>>(a: S, vec1: &[T], b:
         .for_each(|(y, x)| *y = *x * a + *y * b);
 }
 
+#[inline]
+fn waxpy_fallback<S: Copy, T: Scalar + Mul<S, Output = T>>(
+    a: S,
+    vec1: &[T],
+    vec2: &[T],
+    out: &mut [T],
+) {
+    out.iter_mut()
+        .zip(vec1.iter().zip(vec2.iter()))
+        .for_each(|(w, (x, y))| *w = *x * a + *y);
+}
+
+#[inline]
+fn scaled_copy_fallback<S: Copy, T: Scalar + Mul<S, Output = T>>(a: S, src: &[T], dst: &mut [T]) {
+    dst.iter_mut()
+        .zip(src.iter())
+        .for_each(|(y, x)| *y = *x * a);
+}
+
 #[inline(always)]
 fn scale_fallback<T: Scalar>(a: T, vec: &mut [T]) {
+    #[cfg(feature = "simd")]
+    {
+        if super::simd::scale(a, vec) {
+            return;
+        }
+    }
     vec.iter_mut().for_each(|v| *v *= a);
 }
 #[inline(always)]
 fn rscale_fallback<T: Scalar>(a: T::Real, vec: &mut [T]) {
+    #[cfg(feature = "simd")]
+    {
+        if super::simd::scale(a, vec) {
+            return;
+        }
+    }
     vec.iter_mut().for_each(|v| *v = v.mul_real(a));
 }
 
 #[inline]
 fn norm2_fallback<T: Scalar>(vec: &[T]) -> T::Real {
-    let v = vec.iter().fold(T::Real::zero(), |acc, x| acc + x.square());
-    v.sqrt()
+    sum_squares_fallback(vec).sqrt()
+}
+
+#[inline]
+fn sum_squares_fallback<T: Scalar>(vec: &[T]) -> T::Real {
+    #[cfg(feature = "simd")]
+    {
+        if let Some(v) = super::simd::sum_squares(vec) {
+            return v;
+        }
+    }
+    vec.iter().fold(T::Real::zero(), |acc, x| acc + x.square())
+}
+
+/// $|\mathrm{Re}(x)| + |\mathrm{Im}(x)|$, the magnitude used by BLAS level-1 reductions.
+#[inline(always)]
+fn abs1<T: Scalar>(x: T) -> T::Real {
+    num_traits::Float::abs(x.re()) + num_traits::Float::abs(x.im())
+}
+
+#[inline]
+fn asum_fallback<T: Scalar>(vec: &[T]) -> T::Real {
+    vec.iter().fold(T::Real::zero(), |acc, x| acc + abs1(*x))
+}
+
+#[inline]
+fn rot_fallback<T: Scalar>(x: &mut [T], y: &mut [T], c: T::Real, s: T) {
+    let s_conj = s.conj();
+    for (u, v) in x.iter_mut().zip(y.iter_mut()) {
+        let (a, b) = (*u, *v);
+        *u = a.mul_real(c) + s * b;
+        *v = b.mul_real(c) - s_conj * a;
+    }
+}
+
+#[inline]
+fn iamax_fallback<T: Scalar>(vec: &[T]) -> usize {
+    let mut imax = 0;
+    let mut vmax = T::Real::zero();
+    for (i, x) in vec.iter().enumerate() {
+        let m = abs1(*x);
+        if i == 0 || m > vmax {
+            imax = i;
+            vmax = m;
+        }
+    }
+    imax
 }
 
 // ---------------------------------------------------------------------------------------------
@@ -768,6 +2171,56 @@ mod tests {
         }
     }
 
+    #[test]
+    fn copy_swap() {
+        use cauchy::c64;
+        let a: Vec<c64> = (0..100).map(|i| c64::new(i as f64, -(i as f64))).collect();
+        let mut b = vec![c64::new(0., 0.); 100];
+        copy(a.as_slice(), b.as_mut_slice());
+        assert_eq!(a, b);
+
+        let mut c = vec![c64::new(1., 2.); 100];
+        swap(b.as_mut_slice(), c.as_mut_slice());
+        assert_eq!(a, c);
+        assert!(b.iter().all(|v| *v == c64::new(1., 2.)));
+    }
+
+    #[test]
+    fn asum_iamax() {
+        let a: Vec<f64> = (0..100)
+            .map(|i| if i == 37 { -200. } else { i as f64 })
+            .collect();
+        approx::assert_abs_diff_eq!(asum(a.as_slice()), 4950. - 37. + 200.);
+        assert_eq!(iamax(a.as_slice()), 37);
+
+        use cauchy::c64;
+        let a: Vec<c64> = (0..100)
+            .map(|i| c64::new(-(i as f64), if i == 12 { 500. } else { 1. }))
+            .collect();
+        approx::assert_abs_diff_eq!(asum(a.as_slice()), 4950. + 99. + 500.);
+        assert_eq!(iamax(a.as_slice()), 12);
+        // |re| + |im| rather than the modulus: 3 + 3 > 0 + 5
+        let b = vec![c64::new(0., 5.), c64::new(3., -3.)];
+        assert_eq!(iamax(b.as_slice()), 1);
+
+        let e: Vec<c64> = Vec::new();
+        approx::assert_abs_diff_eq!(asum(e.as_slice()), 0.);
+        assert_eq!(iamax(e.as_slice()), 0);
+    }
+
+    #[test]
+    fn test_norm_inf() {
+        let a: Vec<f64> = (0..100)
+            .map(|i| if i == 3 { -120. } else { i as f64 })
+            .collect();
+        approx::assert_abs_diff_eq!(norm_inf(a.as_slice()), 120.);
+
+        use cauchy::c64;
+        let a = vec![c64::new(3., -4.), c64::new(0., 4.5)];
+        approx::assert_abs_diff_eq!(norm_inf(a.as_slice()), 5.);
+        approx::assert_abs_diff_eq!(CpuVecOps.norm_inf(a.as_slice()), 5.);
+    }
+
     #[test]
     fn axpy_f32() {
         let a = vec![1_f32; 128];
@@ -828,6 +2281,86 @@ mod tests {
             approx::assert_abs_diff_eq!(x.im, -y.im);
         }
     }
+    #[cfg(feature = "mkl")]
+    #[test]
+    fn chunked_blas() {
+        // a small chunk size mocks the c_int cutoff
+        const N: usize = 1000;
+        const CHUNK: usize = 97;
+        let a: Vec<f64> = (0..N).map(|i| (i as f64 * 0.1).sin()).collect();
+        let b: Vec<f64> = (0..N).map(|i| (i as f64 * 0.3).cos()).collect();
+        approx::assert_relative_eq!(
+            dot_chunked(&a, &b, N),
+            dot_chunked(&a, &b, CHUNK),
+            max_relative = 1E-12
+        );
+        approx::assert_relative_eq!(
+            norm2_chunked(&a, N),
+            norm2_chunked(&a, CHUNK),
+            max_relative = 1E-12
+        );
+
+        let mut y1 = b.clone();
+        let mut y2 = b.clone();
+        axpy_chunked(1.5, &a, &mut y1, N);
+        axpy_chunked(1.5, &a, &mut y2, CHUNK);
+        for (u, v) in y1.iter().zip(y2.iter()) {
+            approx::assert_abs_diff_eq!(u, v);
+        }
+        axpby_chunked(0.5, &a, -2., &mut y1, N);
+        axpby_chunked(0.5, &a, -2., &mut y2, CHUNK);
+        for (u, v) in y1.iter().zip(y2.iter()) {
+            approx::assert_abs_diff_eq!(u, v);
+        }
+
+        use cauchy::c64;
+        let a: Vec<c64> = (0..N)
+            .map(|i| c64::new((i as f64).sin(), (i as f64).cos()))
+            .collect();
+        let b: Vec<c64> = (0..N).map(|i| c64::new(1., i as f64 * 1E-3)).collect();
+        let (r1, r2) = (dot_chunked(&a, &b, N), dot_chunked(&a, &b, CHUNK));
+        approx::assert_abs_diff_eq!(r1.re, r2.re, epsilon = 1E-10);
+        approx::assert_abs_diff_eq!(r1.im, r2.im, epsilon = 1E-10);
+        let (r1, r2) = (conj_dot_chunked(&a, &b, N), conj_dot_chunked(&a, &b, CHUNK));
+        approx::assert_abs_diff_eq!(r1.re, r2.re, epsilon = 1E-10);
+        approx::assert_abs_diff_eq!(r1.im, r2.im, epsilon = 1E-10);
+        approx::assert_relative_eq!(
+            norm2_chunked(&a, N),
+            norm2_chunked(&a, CHUNK),
+            max_relative = 1E-12
+        );
+    }
+    #[test]
+    fn cpu_vec_ops() {
+        use cauchy::c64;
+        let ops = CpuVecOps;
+        let a: Vec<c64> = (0..100)
+            .map(|i| c64::new(i as f64, 1. / (i + 1) as f64))
+            .collect();
+        let b: Vec<c64> = (0..100).map(|i| c64::new(2., -(i as f64))).collect();
+        assert_eq!(ops.dot(&a, &b), dot(a.as_slice(), b.as_slice()));
+        assert_eq!(ops.conj_dot(&a, &b), conj_dot(a.as_slice(), b.as_slice()));
+        assert_eq!(ops.norm2(&a), norm2(a.as_slice()));
+
+        let s = c64::new(0.5, -1.5);
+        let (mut y1, mut y2) = (b.clone(), b.clone());
+        ops.axpy(s, &a, &mut y1);
+        axpy(s, a.as_slice(), y2.as_mut_slice());
+        assert_eq!(y1, y2);
+        ops.axpby(s, &a, -s, &mut y1);
+        axpby(s, a.as_slice(), -s, y2.as_mut_slice());
+        assert_eq!(y1, y2);
+        ops.scale(s, &mut y1);
+        scale(s, y2.as_mut_slice());
+        assert_eq!(y1, y2);
+        ops.rscale(3., &mut y1);
+        rscale(3., y2.as_mut_slice());
+        assert_eq!(y1, y2);
+        ops.conj(&a, &mut y1);
+        conj(a.as_slice(), y2.as_mut_slice());
+        assert_eq!(y1, y2);
+    }
+
     #[test]
     fn test_rscale() {
         use cauchy::c64;
@@ -839,4 +2372,354 @@ mod tests {
             approx::assert_abs_diff_eq!(x.im, 18.);
         }
     }
+
+    #[test]
+    fn axpy_real_scalar() {
+        // real coefficients on complex vectors take the same path with and without mkl
+        use cauchy::c64;
+        let a: Vec<c64> = (0..100).map(|i| c64::new(i as f64, 1.)).collect();
+        let mut b = vec![c64::new(1., -1.); 100];
+        axpy(2_f64, a.as_slice(), b.as_mut_slice());
+        for (i, v) in b.iter().enumerate() {
+            approx::assert_abs_diff_eq!(v.re, 2. * i as f64 + 1.);
+            approx::assert_abs_diff_eq!(v.im, 1.);
+        }
+        axpby(0.5_f64, a.as_slice(), -1_f64, b.as_mut_slice());
+        for (i, v) in b.iter().enumerate() {
+            approx::assert_abs_diff_eq!(v.re, -1.5 * i as f64 - 1.);
+            approx::assert_abs_diff_eq!(v.im, -0.5);
+        }
+    }
+
+    #[test]
+    fn hadamard_real() {
+        let a = [1_f64, -2., 3., 0.];
+        let b = [4_f64, 5., 0., 0.];
+        let mut c = [0_f64; 4];
+        hadamard(&a[..], &b[..], &mut c[..]);
+        assert_eq!(c, [4., -10., 0., 0.]);
+
+        hadamard_div(&a[..], &b[..], &mut c[..]);
+        assert_eq!(&c[..2], &[0.25, -0.4]);
+        assert!(c[2].is_infinite() && c[2] > 0.);
+        assert!(c[3].is_nan());
+
+        let mut d = a;
+        hadamard_assign(&mut d[..], &b[..]);
+        assert_eq!(d, [4., -10., 0., 0.]);
+    }
+
+    #[test]
+    fn hadamard_complex() {
+        use cauchy::c64;
+        let a = [c64::new(1., 2.), c64::new(0., -1.)];
+        let b = [c64::new(3., -1.), c64::new(2., 2.)];
+        let mut c = [c64::new(0., 0.); 2];
+        hadamard(&a[..], &b[..], &mut c[..]);
+        assert_eq!(c, [c64::new(5., 5.), c64::new(2., -2.)]);
+
+        let mut q = [c64::new(0., 0.); 2];
+        hadamard_div(&c[..], &b[..], &mut q[..]);
+        for (u, v) in q.iter().zip(a.iter()) {
+            approx::assert_abs_diff_eq!(u.re, v.re, epsilon = 1E-14);
+            approx::assert_abs_diff_eq!(u.im, v.im, epsilon = 1E-14);
+        }
+        let zero = [c64::new(0., 0.); 2];
+        hadamard_div(&a[..], &zero[..], &mut q[..]);
+        assert!(q.iter().all(|v| !v.re.is_finite() || !v.im.is_finite()));
+
+        let mut d = a;
+        hadamard_assign(&mut d[..], &b[..]);
+        assert_eq!(d, c);
+    }
+
+    #[test]
+    fn normalize_vec() {
+        let mut a = vec![1_f64; 16];
+        approx::assert_abs_diff_eq!(normalize(a.as_mut_slice()), 4.);
+        approx::assert_abs_diff_eq!(norm2(a.as_slice()), 1., epsilon = 1E-15);
+
+        use cauchy::c64;
+        let mut c = vec![c64::new(3., 4.); 4];
+        approx::assert_abs_diff_eq!(CpuVecOps.normalize(c.as_mut_slice()), 10.);
+        approx::assert_abs_diff_eq!(c[0].re, 0.3, epsilon = 1E-15);
+        approx::assert_abs_diff_eq!(c[0].im, 0.4, epsilon = 1E-15);
+
+        // a vector with a norm below epsilon is left untouched
+        let mut z = vec![1E-18_f64; 4];
+        assert_eq!(normalize(z.as_mut_slice()), 0.);
+        assert_eq!(z, vec![1E-18; 4]);
+        let mut z = vec![0_f64; 4];
+        assert_eq!(normalize(z.as_mut_slice()), 0.);
+        assert_eq!(z, vec![0.; 4]);
+    }
+
+    #[test]
+    fn givens_rotation() {
+        for &(a0, b0) in [(3_f64, -4_f64), (-1., 2.), (0., 5.), (2., 0.)].iter() {
+            let (mut a, mut b) = (a0, b0);
+            let (c, s) = rotg(&mut a, &mut b);
+            assert_eq!(b, 0.);
+            approx::assert_abs_diff_eq!(c * c + s * s, 1., epsilon = 1E-15);
+            approx::assert_abs_diff_eq!(a.abs(), a0.hypot(b0), epsilon = 1E-14);
+            let (mut x, mut y) = (vec![a0; 100], vec![b0; 100]);
+            rot(x.as_mut_slice(), y.as_mut_slice(), c, s);
+            for (u, v) in x.iter().zip(y.iter()) {
+                approx::assert_abs_diff_eq!(*u, a, epsilon = 1E-14);
+                approx::assert_abs_diff_eq!(*v, 0., epsilon = 1E-14);
+            }
+        }
+
+        use cauchy::c64;
+        let pairs = [
+            (c64::new(1., 2.), c64::new(-3., 0.5)),
+            (c64::new(0., 0.), c64::new(1., -1.)),
+            (c64::new(-2., 1.), c64::new(0., 0.)),
+        ];
+        for &(a0, b0) in pairs.iter() {
+            let (mut a, mut b) = (a0, b0);
+            let (c, s) = rotg(&mut a, &mut b);
+            assert_eq!(b, c64::new(0., 0.));
+            assert!(c >= 0.);
+            approx::assert_abs_diff_eq!(c * c + s.norm_sqr(), 1., epsilon = 1E-15);
+            approx::assert_abs_diff_eq!(
+                a.norm(),
+                (a0.norm_sqr() + b0.norm_sqr()).sqrt(),
+                epsilon = 1E-14
+            );
+            let (mut x, mut y) = (vec![a0; 100], vec![b0; 100]);
+            rot(x.as_mut_slice(), y.as_mut_slice(), c, s);
+            for (u, v) in x.iter().zip(y.iter()) {
+                approx::assert_abs_diff_eq!(u.re, a.re, epsilon = 1E-14);
+                approx::assert_abs_diff_eq!(u.im, a.im, epsilon = 1E-14);
+                approx::assert_abs_diff_eq!(v.norm(), 0., epsilon = 1E-14);
+            }
+        }
+    }
+
+    #[test]
+    fn waxpy_out_of_place() {
+        // both sides of the BLAS cutoff
+        for &n in [10, 200].iter() {
+            let x: Vec<f64> = (0..n).map(|i| i as f64).collect();
+            let y = vec![1_f64; n];
+            let mut w = vec![f64::NAN; n];
+            waxpy(-2., x.as_slice(), y.as_slice(), w.as_mut_slice());
+            for (i, v) in w.iter().enumerate() {
+                assert_eq!(*v, 1. - 2. * i as f64);
+            }
+            // the inputs are left unchanged
+            assert!(y.iter().all(|&v| v == 1.));
+            let mut w2 = vec![0_f64; n];
+            CpuVecOps.waxpy(-2., x.as_slice(), y.as_slice(), w2.as_mut_slice());
+            assert_eq!(w, w2);
+        }
+
+        use cauchy::c64;
+        let x: Vec<c64> = (0..100).map(|i| c64::new(i as f64, 1.)).collect();
+        let y: Vec<c64> = (0..100).map(|i| c64::new(0., i as f64)).collect();
+        let mut w = vec![c64::new(0., 0.); 100];
+        waxpy(
+            c64::new(0., 1.),
+            x.as_slice(),
+            y.as_slice(),
+            w.as_mut_slice(),
+        );
+        for (i, v) in w.iter().enumerate() {
+            // j (k + j) + k j = -1 + 2k j
+            approx::assert_abs_diff_eq!(v.re, -1., epsilon = 1E-14);
+            approx::assert_abs_diff_eq!(v.im, 2. * i as f64, epsilon = 1E-14);
+        }
+        // a real coefficient
+        waxpy(2., x.as_slice(), y.as_slice(), w.as_mut_slice());
+        for (i, v) in w.iter().enumerate() {
+            assert_eq!(*v, c64::new(2. * i as f64, 2. + i as f64));
+        }
+    }
+
+    #[test]
+    fn single_pass_dot_and_norm() {
+        let x: Vec<f64> = (0..300).map(|i| (i as f64 * 0.3).sin()).collect();
+        let y: Vec<f64> = (0..300).map(|i| (i as f64 * 0.7).cos()).collect();
+        let (d, s) = dot_and_norm2(x.as_slice(), y.as_slice());
+        approx::assert_relative_eq!(d, conj_dot(x.as_slice(), y.as_slice()), epsilon = 1E-12);
+        approx::assert_relative_eq!(s, norm2(y.as_slice()).powi(2), max_relative = 1E-12);
+        assert_eq!(CpuVecOps.dot_and_norm2(&x, &y), (d, s));
+
+        use cauchy::c64;
+        let x: Vec<c64> = (0..300)
+            .map(|i| c64::new((i as f64 * 0.3).sin(), (i as f64 * 0.1).cos()))
+            .collect();
+        let y: Vec<c64> = (0..300)
+            .map(|i| c64::new((i as f64 * 0.7).cos(), -(i as f64 * 0.2).sin()))
+            .collect();
+        let (d, s) = dot_and_norm2(x.as_slice(), y.as_slice());
+        let d_ref = conj_dot(x.as_slice(), y.as_slice());
+        approx::assert_abs_diff_eq!(d.re, d_ref.re, epsilon = 1E-12);
+        approx::assert_abs_diff_eq!(d.im, d_ref.im, epsilon = 1E-12);
+        approx::assert_relative_eq!(s, norm2(y.as_slice()).powi(2), max_relative = 1E-12);
+        // x^H x is real
+        let (d, s) = dot_and_norm2(y.as_slice(), y.as_slice());
+        assert_eq!(d.im, 0.);
+        approx::assert_relative_eq!(d.re, s, max_relative = 1E-14);
+    }
+
+    #[test]
+    fn scaled_copy_real_complex() {
+        // both sides of the BLAS cutoff
+        for &n in [10, 200].iter() {
+            let x: Vec<f64> = (0..n).map(|i| i as f64).collect();
+            let mut y = vec![f64::NAN; n];
+            scaled_copy(-0.5, x.as_slice(), y.as_mut_slice());
+            for (i, (u, v)) in x.iter().zip(y.iter()).enumerate() {
+                // x is left unchanged
+                assert_eq!(*u, i as f64);
+                assert_eq!(*v, -0.5 * i as f64);
+            }
+            let mut y2 = vec![0_f64; n];
+            CpuVecOps.scaled_copy(-0.5, x.as_slice(), y2.as_mut_slice());
+            assert_eq!(y, y2);
+        }
+
+        use cauchy::c64;
+        let x: Vec<c64> = (0..100).map(|i| c64::new(i as f64, 1.)).collect();
+        let mut y = vec![c64::new(0., 0.); 100];
+        scaled_copy(c64::new(0., 2.), x.as_slice(), y.as_mut_slice());
+        for (i, v) in y.iter().enumerate() {
+            assert_eq!(*v, c64::new(-2., 2. * i as f64));
+        }
+        // a real coefficient
+        scaled_copy(3., x.as_slice(), y.as_mut_slice());
+        for (i, (u, v)) in x.iter().zip(y.iter()).enumerate() {
+            assert_eq!(*u, c64::new(i as f64, 1.));
+            assert_eq!(*v, c64::new(3. * i as f64, 3.));
+        }
+    }
+
+    #[test]
+    fn raxpy_complex() {
+        use cauchy::{c32, c64};
+        // both sides of the BLAS cutoff
+        for &n in [10, 200].iter() {
+            let x: Vec<c64> = (0..n).map(|i| c64::new(i as f64, -1.)).collect();
+            let mut y = vec![c64::new(1., 2.); n];
+            raxpy(-0.5, x.as_slice(), y.as_mut_slice());
+            for (i, v) in y.iter().enumerate() {
+                assert_eq!(*v, c64::new(1. - 0.5 * i as f64, 2.5));
+            }
+            // the same as axpy with the scalar promoted
+            let mut y2 = vec![c64::new(1., 2.); n];
+            CpuVecOps.axpy(c64::new(-0.5, 0.), x.as_slice(), y2.as_mut_slice());
+            assert_eq!(y, y2);
+
+            let x: Vec<c32> = (0..n).map(|i| c32::new(1., i as f32)).collect();
+            let mut y = vec![c32::new(0., 0.); n];
+            CpuVecOps.raxpy(2., x.as_slice(), y.as_mut_slice());
+            for (i, v) in y.iter().enumerate() {
+                assert_eq!(*v, c32::new(2., 2. * i as f32));
+            }
+        }
+    }
+
+    #[test]
+    fn zero_and_fill() {
+        use cauchy::{c32, c64};
+        let mut a: Vec<f32> = (0..100).map(|i| i as f32 - 50.).collect();
+        zero(a.as_mut_slice());
+        assert!(a.iter().all(|v| *v == 0. && v.is_sign_positive()));
+        fill(-1.5, a.as_mut_slice());
+        assert!(a.iter().all(|&v| v == -1.5));
+
+        let val = c64::new(1.5, -2.);
+        let mut b = [c64::new(3., 4.); 100];
+        fill(val, b.as_mut_slice());
+        assert!(b.iter().all(|&v| v == val));
+        zero(b.as_mut_slice());
+        assert!(b.iter().all(|&v| v == c64::new(0., 0.)));
+
+        let mut c = [c32::new(1., 1.); 7];
+        fill(c32::new(0., 1.), &mut c[2..5]);
+        zero(&mut c[..2]);
+        assert_eq!(c[0], c32::new(0., 0.));
+        assert_eq!(c[4], c32::new(0., 1.));
+        assert_eq!(c[5], c32::new(1., 1.));
+
+        // empty vectors are fine
+        zero(&mut c[..0]);
+        fill(val, &mut b[..0]);
+    }
+
+    #[test]
+    fn complex_parts() {
+        use cauchy::{c32, c64};
+        let n = 20;
+        let a: Vec<c64> = (0..n).map(|i| c64::new(i as f64, 2. - i as f64)).collect();
+        let x: Vec<c64> = (0..n).map(|i| c64::new(1. / (i + 1) as f64, 0.5)).collect();
+        let (mut re, mut im) = (vec![0_f64; n], vec![0_f64; n]);
+        real_part(x.as_slice(), re.as_mut_slice());
+        imag_part(x.as_slice(), im.as_mut_slice());
+        let mut y = vec![c64::new(0., 0.); n];
+        from_parts(re.as_slice(), im.as_slice(), y.as_mut_slice());
+        assert_eq!(x, y);
+
+        // the equivalent real system [Re a, -Im a; Im a, Re a] [Re x; Im x]
+        for i in 0..n {
+            let ax = a[i] * x[i];
+            assert_eq!(ax.re, a[i].re * re[i] - a[i].im * im[i]);
+            assert_eq!(ax.im, a[i].im * re[i] + a[i].re * im[i]);
+        }
+
+        let x = [c32::new(1., -1.), c32::new(0., 2.)];
+        let (mut re, mut im) = ([0_f32; 2], [0_f32; 2]);
+        real_part(&x[..], &mut re[..]);
+        imag_part(&x[..], &mut im[..]);
+        assert_eq!((re, im), ([1., 0.], [-1., 2.]));
+        let mut y = [c32::new(0., 0.); 2];
+        from_parts(&re[..], &im[..], &mut y[..]);
+        assert_eq!(x, y);
+
+        // real values: a copy, zero imaginary parts, and the imaginary parts are dropped
+        let x: Vec<f64> = (0..n).map(|i| i as f64 - 3.).collect();
+        let (mut re, mut im) = (vec![0_f64; n], vec![1_f64; n]);
+        real_part(x.as_slice(), re.as_mut_slice());
+        imag_part(x.as_slice(), im.as_mut_slice());
+        assert_eq!(re, x);
+        assert!(im.iter().all(|&v| v == 0.));
+        let mut y = vec![0_f64; n];
+        from_parts(re.as_slice(), &vec![1_f64; n][..], y.as_mut_slice());
+        assert_eq!(y, x);
+
+        let x = [2.5_f32, -1.];
+        let mut im = [1_f32; 2];
+        imag_part(&x[..], &mut im[..]);
+        assert_eq!(im, [0., 0.]);
+    }
+
+    #[test]
+    fn norm2_sq_matches_norm2() {
+        use cauchy::{c32, c64};
+        // both sides of the BLAS cutoff
+        for &n in [0, 7, 200].iter() {
+            let a: Vec<f64> = (0..n).map(|i| (i as f64 * 0.37).sin() * 3.).collect();
+            let nrm = norm2(a.as_slice());
+            approx::assert_relative_eq!(norm2_sq(a.as_slice()), nrm * nrm, max_relative = 1E-14);
+            assert_eq!(CpuVecOps.norm2_sq(a.as_slice()), norm2_sq(a.as_slice()));
+
+            let a: Vec<f32> = (0..n).map(|i| i as f32 - 50.).collect();
+            let nrm = norm2(a.as_slice());
+            approx::assert_relative_eq!(norm2_sq(a.as_slice()), nrm * nrm, max_relative = 1E-6);
+
+            let a: Vec<c64> = (0..n).map(|i| c64::new(i as f64, -0.5)).collect();
+            let nrm = norm2(a.as_slice());
+            approx::assert_relative_eq!(norm2_sq(a.as_slice()), nrm * nrm, max_relative = 1E-14);
+            let (_, sq) = CpuVecOps.dot_and_norm2(a.as_slice(), a.as_slice());
+            approx::assert_relative_eq!(sq, nrm * nrm, max_relative = 1E-14);
+
+            let a: Vec<c32> = (0..n).map(|i| c32::new(1., i as f32)).collect();
+            let nrm = norm2(a.as_slice());
+            approx::assert_relative_eq!(norm2_sq(a.as_slice()), nrm * nrm, max_relative = 1E-6);
+        }
+        // no square root: exact for integer entries
+        assert_eq!(norm2_sq(&[c64::new(1., 2.), c64::new(-2., 0.)][..]), 9.);
+    }
 }
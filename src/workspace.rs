@@ -0,0 +1,40 @@
+//! Scratch memory of the solvers, held separately from the solvers.
+
+use cauchy::Scalar;
+
+/// The scratch vectors used by a solver during one solve.
+///
+/// A solver borrows only the operator, so one operator can be shared by several threads,
+/// each of them solving with its own `Workspace`. The length needed by a solver for a system
+/// of a given size is returned by its `workspace_len` associated function, e.g.
+/// [`BiCGStab::workspace_len`](crate::BiCGStab::workspace_len).
+///
+/// The solvers treat a workspace as uninitialized scratch memory, so it can be reused for
+/// any number of solves, whatever it holds from the previous ones.
+#[derive(Clone, Debug)]
+pub struct Workspace<T> {
+    data: Vec<T>,
+}
+
+impl<T: Scalar> Workspace<T> {
+    /// Allocate a zeroed workspace of `len` scalars.
+    pub fn new(len: usize) -> Self {
+        Workspace {
+            data: vec![T::zero(); len],
+        }
+    }
+
+    /// The number of scalars held by this workspace.
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Return `true` if this workspace holds no scalar.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub(crate) fn as_mut_slice(&mut self) -> &mut [T] {
+        self.data.as_mut_slice()
+    }
+}
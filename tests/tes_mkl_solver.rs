@@ -10,7 +10,13 @@ fn test_bicg_stab_with_mkl() {
         (row + col) as f64
     });
 
+    let nnz = lap.nnz();
     let mkl_mat = MklMat::new(lap).unwrap();
+    assert_eq!(mkl_mat.nnz(), nnz);
+    assert_eq!(
+        sprsolve::MatVecMul::<f64>::shape(&mkl_mat),
+        Some((rows * cols, rows * cols))
+    );
 
     // solution
     let mut x = vec![0_f64; rows * cols];
@@ -32,6 +38,142 @@ fn test_bicg_stab_with_mkl() {
     );
 }
 
+#[test]
+fn mkl_mat_matches_sprs() {
+    use num_complex::Complex64;
+    use sprsolve::MatVecMul;
+
+    // the native CSR products of sprs matrices and the MKL ones agree, for real and complex
+    // entries
+    let n = 50;
+    let mut re = sprs::TriMatI::<f64, i32>::new((n, n));
+    let mut cplx = sprs::TriMatI::<Complex64, i32>::new((n, n));
+    for i in 0..n {
+        for &j in &[i, (i * 7 + 3) % n, (i * 13 + 5) % n] {
+            let v = ((i * 31 + j * 17) % 23) as f64 / 23. - 0.5;
+            re.add_triplet(i, j, v);
+            cplx.add_triplet(i, j, Complex64::new(v, 0.5 - v * v));
+        }
+    }
+    let v_re: Vec<f64> = (0..n).map(|i| (i as f64 * 0.7).sin()).collect();
+    let v_cplx: Vec<Complex64> = v_re
+        .iter()
+        .map(|&v| Complex64::new(v, (3. * v).cos()))
+        .collect();
+
+    let csr: sprs::CsMatI<f64, i32> = re.to_csr();
+    let mkl = MklMat::new(csr.clone()).unwrap();
+    let (mut y, mut y_mkl) = (vec![0_f64; n], vec![0_f64; n]);
+    let d = csr.mul_vec_dot(&v_re, &mut y);
+    let d_mkl = mkl.mul_vec_dot(&v_re, &mut y_mkl);
+    approx::assert_relative_eq!(d, d_mkl, max_relative = 1E-12);
+    for (a, b) in y.iter().zip(y_mkl.iter()) {
+        approx::assert_abs_diff_eq!(a, b, epsilon = 1E-12);
+    }
+    csr.mul_vec_trans(&v_re, &mut y).unwrap();
+    mkl.mul_vec_trans(&v_re, &mut y_mkl).unwrap();
+    for (a, b) in y.iter().zip(y_mkl.iter()) {
+        approx::assert_abs_diff_eq!(a, b, epsilon = 1E-12);
+    }
+
+    let csr: sprs::CsMatI<Complex64, i32> = cplx.to_csr();
+    let mkl = MklMat::new(csr.clone()).unwrap();
+    let zero = Complex64::new(0., 0.);
+    let (mut y, mut y_mkl) = (vec![zero; n], vec![zero; n]);
+    let d = csr.mul_vec_dot(&v_cplx, &mut y);
+    let d_mkl = mkl.mul_vec_dot(&v_cplx, &mut y_mkl);
+    approx::assert_abs_diff_eq!((d - d_mkl).norm(), 0., epsilon = 1E-12 * d.norm());
+    for (a, b) in y.iter().zip(y_mkl.iter()) {
+        approx::assert_abs_diff_eq!((a - b).norm(), 0., epsilon = 1E-12);
+    }
+    // the transposed product doesn't conjugate the entries
+    csr.mul_vec_trans(&v_cplx, &mut y).unwrap();
+    mkl.mul_vec_trans(&v_cplx, &mut y_mkl).unwrap();
+    for (a, b) in y.iter().zip(y_mkl.iter()) {
+        approx::assert_abs_diff_eq!((a - b).norm(), 0., epsilon = 1E-12);
+    }
+}
+
+#[test]
+fn mkl_num_threads() {
+    let max = sprsolve::get_max_threads();
+    assert!(max >= 1);
+
+    sprsolve::set_num_threads(1);
+    assert_eq!(sprsolve::get_max_threads(), 1);
+
+    // the scope guard restores the previous value, also on a panic
+    let inner = sprsolve::with_num_threads(2, sprsolve::get_max_threads);
+    assert!((1..=2).contains(&inner));
+    assert_eq!(sprsolve::get_max_threads(), 1);
+    let ret = std::panic::catch_unwind(|| sprsolve::with_num_threads(2, || panic!("in scope")));
+    assert!(ret.is_err());
+    assert_eq!(sprsolve::get_max_threads(), 1);
+
+    sprsolve::set_num_threads(max);
+    assert_eq!(sprsolve::get_max_threads(), max);
+}
+
+#[test]
+fn pardiso_direct_solve() {
+    use sprsolve::{Pardiso, PardisoMatrixType};
+
+    // A = [ 4 1 0 ]
+    //     [ 2 5 1 ]
+    //     [ 0 3 6 ]
+    let mut tri = sprs::TriMatI::<f64, i32>::new((3, 3));
+    for &(i, j, v) in [(0, 0, 4.), (0, 1, 1.), (1, 0, 2.), (1, 1, 5.), (1, 2, 1.)]
+        .iter()
+        .chain([(2, 1, 3.), (2, 2, 6.)].iter())
+    {
+        tri.add_triplet(i, j, v);
+    }
+    let mut lu = Pardiso::new(tri.to_csr(), PardisoMatrixType::General).unwrap();
+    let mut x = [0.; 3];
+    lu.solve(&[3., -1., 9.], &mut x).unwrap();
+    for (u, v) in x.iter().zip([1., -1., 2.].iter()) {
+        approx::assert_abs_diff_eq!(u, v, epsilon = 1E-12);
+    }
+    // the factorization is reused
+    lu.solve(&[4., 2., 0.], &mut x).unwrap();
+    for (u, v) in x.iter().zip([1., 0., 0.].iter()) {
+        approx::assert_abs_diff_eq!(u, v, epsilon = 1E-12);
+    }
+
+    let (rows, cols) = (8, 8);
+    let lap = grid_laplacian((rows, cols));
+    let expected: Vec<f64> = (0..rows * cols).map(|k| (k % 7) as f64 - 3.).collect();
+    let mut rhs = vec![0_f64; rows * cols];
+    sprsolve::MatVecMul::mul_vec(&lap, &expected, &mut rhs);
+    let mut lu = Pardiso::new(lap, PardisoMatrixType::General).unwrap();
+    let mut x = vec![0_f64; rows * cols];
+    lu.solve(&rhs, &mut x).unwrap();
+    for (u, v) in x.iter().zip(expected.iter()) {
+        approx::assert_abs_diff_eq!(u, v, epsilon = 1E-10);
+    }
+
+    // symmetric positive definite tridiagonal matrix, given in full
+    let n = 10;
+    let mut tri = sprs::TriMatI::<f64, i32>::new((n, n));
+    for i in 0..n {
+        tri.add_triplet(i, i, 4.);
+        if i > 0 {
+            tri.add_triplet(i, i - 1, -1.);
+            tri.add_triplet(i - 1, i, -1.);
+        }
+    }
+    let spd = tri.to_csr();
+    let expected: Vec<f64> = (0..n).map(|k| k as f64 * 0.5 - 2.).collect();
+    let mut rhs = vec![0_f64; n];
+    sprsolve::MatVecMul::mul_vec(&spd, &expected, &mut rhs);
+    let mut chol = Pardiso::new(spd, PardisoMatrixType::PosDef).unwrap();
+    let mut x = vec![0_f64; n];
+    chol.solve(&rhs, &mut x).unwrap();
+    for (u, v) in x.iter().zip(expected.iter()) {
+        approx::assert_abs_diff_eq!(u, v, epsilon = 1E-12);
+    }
+}
+
 /// Determine whether the grid location at `(row, col)` is a border
 /// of the grid defined by `shape`.
 fn is_border(row: usize, col: usize, shape: (usize, usize)) -> bool {
@@ -0,0 +1,191 @@
+#[test]
+fn auto_chebyshev_spd() {
+    let (rows, cols) = (16, 16);
+    let (lap, rhs) = neg_grid_laplacian((rows, cols));
+    let n = rows * cols;
+
+    let mut x_cg = vec![0_f64; n];
+    let (its_cg, res_cg) = sprsolve::CG::new(&lap, n)
+        .solve(&rhs, &mut x_cg, 1000, 1E-12)
+        .unwrap();
+    println!("CG: {} iterations, residual {}", its_cg, res_cg);
+
+    let mut x = vec![0_f64; n];
+    let (its, res) = sprsolve::auto_chebyshev(&lap, n, &rhs, &mut x, 1000, 1E-12).unwrap();
+    println!("auto Chebyshev: {} iterations, residual {}", its, res);
+    assert!(res <= 1E-12);
+
+    let x_norm = x_cg.iter().fold(0_f64, |acc, v| acc + v * v).sqrt();
+    for (u, v) in x.iter().zip(x_cg.iter()) {
+        approx::assert_abs_diff_eq!(u, v, epsilon = 1E-9 * x_norm);
+    }
+
+    // a mismatched size is rejected
+    let mut y = vec![0_f64; n - 1];
+    assert!(matches!(
+        sprsolve::auto_chebyshev(&lap, n - 1, &rhs[1..], &mut y, 1000, 1E-12),
+        Err(sprsolve::error::SolverError::IncompatibleMatrixFormat(_))
+    ));
+}
+
+#[test]
+fn auto_chebyshev_without_shape() {
+    // a diagonal operator defined by the user, which provides only the products
+    struct UserDiag(Vec<f64>);
+    impl sprsolve::MatVecMul<f64> for UserDiag {
+        fn mul_vec(&self, v_in: &[f64], v_out: &mut [f64]) {
+            assert!(v_in.len() == self.0.len() && v_out.len() == self.0.len());
+            unsafe { self.mul_vec_unchecked(v_in, v_out) }
+        }
+        fn mul_vec_dot(&self, v_in: &[f64], v_out: &mut [f64]) -> f64 {
+            self.mul_vec(v_in, v_out);
+            v_in.iter().zip(v_out.iter()).map(|(a, b)| a * b).sum()
+        }
+        unsafe fn mul_vec_unchecked(&self, v_in: &[f64], v_out: &mut [f64]) {
+            for ((o, i), d) in v_out.iter_mut().zip(v_in.iter()).zip(self.0.iter()) {
+                *o = d * i;
+            }
+        }
+        unsafe fn mul_vec_dot_unchecked(&self, v_in: &[f64], v_out: &mut [f64]) -> f64 {
+            self.mul_vec_dot(v_in, v_out)
+        }
+    }
+
+    let n = 100;
+    let diag = UserDiag((0..n).map(|i| 1. + i as f64 / 10.).collect());
+    let rhs: Vec<f64> = (0..n).map(|i| ((i * 3) % 7) as f64 - 3.).collect();
+    let mut x = vec![0_f64; n];
+    let (its, res) = sprsolve::auto_chebyshev(&diag, n, &rhs, &mut x, 1000, 1E-12).unwrap();
+    println!("auto Chebyshev: {} iterations, residual {}", its, res);
+    for ((u, b), d) in x.iter().zip(rhs.iter()).zip(diag.0.iter()) {
+        approx::assert_abs_diff_eq!(*u, b / d, epsilon = 1E-10);
+    }
+}
+
+#[test]
+fn chebyshev_diverges_with_bad_bounds() {
+    let (rows, cols) = (8, 8);
+    let (lap, rhs) = neg_grid_laplacian((rows, cols));
+    let n = rows * cols;
+
+    // the largest eigenvalue is close to 8, so this upper bound is far too small
+    let mut x = vec![0_f64; n];
+    let ret = sprsolve::Chebyshev::new(&lap, n).solve(&rhs, &mut x, 0.1, 2., 1000, 1E-12);
+    assert!(matches!(
+        ret,
+        Err(sprsolve::error::SolverError::BreakDown(_))
+    ));
+}
+
+#[test]
+fn chebyshev_check_interval() {
+    let (rows, cols) = (16, 16);
+    let (lap, rhs) = neg_grid_laplacian((rows, cols));
+    let n = rows * cols;
+
+    // eigenvalues are 4 - 2cos(pi i / (rows + 1)) - 2cos(pi j / (cols + 1)), 1 <= i, j
+    let h = std::f64::consts::PI / (rows + 1) as f64;
+    let lambda_min = 4. - 4. * h.cos();
+    let lambda_max = 4. + 4. * h.cos();
+
+    let mut x = vec![0_f64; n];
+    let (its, res) = sprsolve::Chebyshev::new(&lap, n)
+        .solve(&rhs, &mut x, lambda_min, lambda_max, 1000, 1E-10)
+        .unwrap();
+    println!("Chebyshev: {} iterations", its);
+    assert!(res <= 1E-10);
+
+    let interval = 8;
+    let mut solver = sprsolve::Chebyshev::new(&lap, n);
+    solver.set_check_interval(interval);
+    let mut y = vec![0_f64; n];
+    let (its_k, res_k) = solver
+        .solve(&rhs, &mut y, lambda_min, lambda_max, 1000, 1E-10)
+        .unwrap();
+    println!(
+        "Chebyshev, checked every {}: {} iterations",
+        interval, its_k
+    );
+    assert!(res_k <= 1E-10);
+    assert_eq!(its_k % interval, 0);
+    assert!(its_k >= its && its_k < its + interval);
+}
+
+#[test]
+fn richardson_iteration() {
+    use sprsolve::precond::{Identity, Jacobi};
+    use sprsolve::{MatVecMul, Richardson};
+
+    let (rows, cols) = (8, 8);
+    let (lap, rhs) = neg_grid_laplacian((rows, cols));
+    let n = rows * cols;
+    let res_norm = |x: &[f64]| {
+        let mut r = vec![0_f64; n];
+        lap.mul_vec(x, &mut r);
+        r.iter()
+            .zip(rhs.iter())
+            .fold(0_f64, |acc, (a, b)| acc + (a - b) * (a - b))
+            .sqrt()
+    };
+
+    // the eigenvalues lie in (0, 8), so omega = 2 / (lambda_min + lambda_max) ~ 0.25
+    let mut solver = Richardson::new(&lap, n);
+    let mut x = vec![0_f64; n];
+    let (its, res) = solver
+        .solve(&rhs, &mut x, 0.25, &Identity, 2000, 1E-8)
+        .unwrap();
+    println!("Richardson: {} iterations", its);
+    assert!(res <= 1E-8);
+
+    // with the Jacobi preconditioner, the eigenvalues of M^{-1}A lie in (0, 2)
+    let jacobi = Jacobi::new(lap.view()).unwrap();
+    let mut y = vec![0_f64; n];
+    let (its_j, res_j) = solver.solve(&rhs, &mut y, 1., &jacobi, 2000, 1E-8).unwrap();
+    println!("Jacobi-Richardson: {} iterations", its_j);
+    assert!(res_j <= 1E-8);
+    for (a, b) in x.iter().zip(y.iter()) {
+        approx::assert_abs_diff_eq!(a, b, epsilon = 1E-6);
+    }
+
+    // a too large omega diverges
+    let mut z = vec![0_f64; n];
+    assert!(solver
+        .solve(&rhs, &mut z, 0.3, &Identity, 2000, 1E-8)
+        .is_err());
+
+    // smoothing sweeps reduce the residual
+    let mut s = vec![0_f64; n];
+    let r0 = res_norm(&s);
+    solver.smooth(&rhs, &mut s, 0.25, 5).unwrap();
+    assert!(res_norm(&s) < r0);
+}
+
+/// Negative 2D grid laplacian with Dirichlet boundary, which is symmetric positive definite.
+fn neg_grid_laplacian(shape: (usize, usize)) -> (sprs::CsMat<f64>, Vec<f64>) {
+    let (rows, cols) = shape;
+    let n = rows * cols;
+    let mut rhs = vec![0_f64; n];
+    let mut ret_a = sprs::TriMat::<f64>::new((n, n));
+
+    for i in 0..rows {
+        for j in 0..cols {
+            let vid = i * cols + j;
+            ret_a.add_triplet(vid, vid, 4.);
+            rhs[vid] = ((i + j) % 5) as f64 - 2.;
+
+            if i > 0 {
+                ret_a.add_triplet(vid, vid - cols, -1.);
+            }
+            if j > 0 {
+                ret_a.add_triplet(vid, vid - 1, -1.);
+            }
+            if i < rows - 1 {
+                ret_a.add_triplet(vid, vid + cols, -1.);
+            }
+            if j < cols - 1 {
+                ret_a.add_triplet(vid, vid + 1, -1.);
+            }
+        }
+    }
+    (ret_a.to_csr(), rhs)
+}
@@ -87,6 +87,40 @@ fn test_bicgstab_complex_1() {
     }
 }
 
+#[test]
+fn minres_hermitian() {
+    use sprsolve::MatVecMul;
+
+    // Hermitian indefinite tridiagonal matrix with complex off-diagonal entries
+    let n = 60;
+    let mut tri = sprs::TriMat::<Complex64>::new((n, n));
+    for i in 0..n {
+        tri.add_triplet(i, i, Complex64::new(i as f64 - 20.5, 0.));
+        if i + 1 < n {
+            let a = Complex64::new(1., 0.5 + (i % 3) as f64);
+            tri.add_triplet(i, i + 1, a);
+            tri.add_triplet(i + 1, i, a.conj());
+        }
+    }
+    let mat: sprs::CsMat<Complex64> = tri.to_csr();
+    let rhs: Vec<Complex64> = (0..n).map(|i| val(i % 5, i % 3)).collect();
+    let mut x = vec![Complex64::default(); n];
+    let report = sprsolve::MinRes::new(&mat, n)
+        .solve_checked(&rhs, &mut x, 500, 1E-10)
+        .unwrap();
+    println!("Hermitian MinRes: {:?}", report);
+
+    let mut r = vec![Complex64::default(); n];
+    mat.mul_vec(&x, &mut r);
+    let res = r
+        .iter()
+        .zip(rhs.iter())
+        .fold(0_f64, |acc, (a, b)| acc + (a - b).norm_sqr())
+        .sqrt();
+    let b = rhs.iter().fold(0_f64, |acc, b| acc + b.norm_sqr()).sqrt();
+    assert!(res / b <= 1E-9);
+}
+
 #[inline]
 fn val<T: num_traits::ToPrimitive>(row: T, col: T) -> Complex64 {
     Complex64::new(row.to_f64().unwrap(), col.to_f64().unwrap())
@@ -212,3 +246,116 @@ fn grid_laplacian_diag(
     } // end for
     (ret_a.to_csr(), rhs, diag)
 }
+
+#[test]
+fn cs_minres_complex_symmetric() {
+    // complex symmetric (A^T = A) but not Hermitian
+    let n = 20;
+    let mut tri = sprs::TriMat::<Complex64>::new((n, n));
+    for i in 0..n {
+        tri.add_triplet(i, i, Complex64::new(3., 1. + (i % 3) as f64));
+        if i > 0 {
+            tri.add_triplet(i, i - 1, Complex64::new(-1., 0.5));
+            tri.add_triplet(i - 1, i, Complex64::new(-1., 0.5));
+        }
+    }
+    let mat = tri.to_csr();
+    let rhs: Vec<Complex64> = (0..n)
+        .map(|i| Complex64::new(1., (i % 4) as f64 - 1.5))
+        .collect();
+    let rhs_norm = sprsolve::vecalg::norm2(rhs.as_slice());
+    let true_residual = |x: &[Complex64]| {
+        let mut r = vec![Complex64::default(); n];
+        sprsolve::MatVecMul::mul_vec(&mat, x, &mut r);
+        sprsolve::vecalg::axpby(
+            Complex64::new(1., 0.),
+            rhs.as_slice(),
+            -Complex64::new(1., 0.),
+            r.as_mut_slice(),
+        );
+        sprsolve::vecalg::norm2(r.as_slice()) / rhs_norm
+    };
+
+    // CSMinRes builds its basis with the unconjugated bilinear form x^T A y. With the
+    // Hermitian form x^H A y, it would behave like MinRes below and fail.
+    let mut x = vec![Complex64::default(); n];
+    let (_, res) = sprsolve::CSMinRes::new(&mat, n)
+        .solve(&rhs, &mut x, 200, 1E-12)
+        .unwrap();
+    assert!(res <= 1E-12);
+    assert!(true_residual(&x) <= 1E-12);
+
+    // MinRes assumes a Hermitian matrix
+    let mut x = vec![Complex64::default(); n];
+    let ret = sprsolve::MinRes::new(&mat, n).solve(&rhs, &mut x, 200, 1E-12);
+    assert!(ret.is_err() || true_residual(&x) > 1E-3);
+}
+
+#[test]
+fn complex_as_real() {
+    use sprsolve::{BiCGStab, ComplexAsReal, MatVecMul, Transpose};
+
+    // complex, and neither Hermitian nor symmetric
+    let n = 30;
+    let mut tri = sprs::TriMat::<Complex64>::new((n, n));
+    for i in 0..n {
+        tri.add_triplet(i, i, Complex64::new(4., 1. + (i % 3) as f64));
+        if i > 0 {
+            tri.add_triplet(i, i - 1, Complex64::new(-1.5, 0.5));
+        }
+        if i + 1 < n {
+            tri.add_triplet(i, i + 1, Complex64::new(-0.5, -1.));
+        }
+    }
+    let mat: sprs::CsMat<Complex64> = tri.to_csr();
+    let real = ComplexAsReal::new(&mat, n);
+    assert_eq!(real.shape(), Some((2 * n, 2 * n)));
+
+    // the products agree with the complex ones
+    let v: Vec<Complex64> = (0..n).map(|i| val(i % 4, i % 5)).collect();
+    let (mut v_real, mut y_real) = (vec![0_f64; 2 * n], vec![0_f64; 2 * n]);
+    real.pack(&v, &mut v_real);
+    let mut y = vec![Complex64::default(); n];
+    let mut y_ref = vec![Complex64::default(); n];
+    real.mul_vec(&v_real, &mut y_real);
+    real.unpack(&y_real, &mut y);
+    mat.mul_vec(&v, &mut y_ref);
+    assert_eq!(y, y_ref);
+
+    // the transpose of the real form is the real form of the conjugate transpose A^H
+    let adjoint = mat.transpose_view().to_csr().map(|a| a.conj());
+    real.mul_vec_trans(&v_real, &mut y_real).unwrap();
+    real.unpack(&y_real, &mut y);
+    adjoint.mul_vec(&v, &mut y_ref);
+    for (a, b) in y.iter().zip(y_ref.iter()) {
+        approx::assert_abs_diff_eq!(a.re, b.re, epsilon = 1E-14);
+        approx::assert_abs_diff_eq!(a.im, b.im, epsilon = 1E-14);
+    }
+    let mut y_t = vec![0_f64; 2 * n];
+    Transpose(&real).mul_vec(&v_real, &mut y_t);
+    assert_eq!(y_t, y_real);
+
+    let mut diag = vec![0_f64; 2 * n];
+    real.diagonal(&mut diag);
+    assert!(diag.iter().all(|&d| d == 4.));
+
+    // solving the real form of size 2n gives the complex solution
+    let rhs: Vec<Complex64> = (0..n).map(|i| val(1, i % 3)).collect();
+    let mut x = vec![Complex64::default(); n];
+    BiCGStab::new(&mat, n)
+        .solve(&rhs, &mut x, 500, 1E-12)
+        .unwrap();
+
+    let mut rhs_real = vec![0_f64; 2 * n];
+    real.pack(&rhs, &mut rhs_real);
+    let mut x_real = vec![0_f64; 2 * n];
+    BiCGStab::new(&real, 2 * n)
+        .solve(&rhs_real, &mut x_real, 500, 1E-12)
+        .unwrap();
+    let mut x2 = vec![Complex64::default(); n];
+    real.unpack(&x_real, &mut x2);
+    for (a, b) in x.iter().zip(x2.iter()) {
+        approx::assert_abs_diff_eq!(a.re, b.re, epsilon = 1E-10);
+        approx::assert_abs_diff_eq!(a.im, b.im, epsilon = 1E-10);
+    }
+}
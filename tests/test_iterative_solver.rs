@@ -0,0 +1,431 @@
+use sprsolve::{
+    BiCG, BiCGStab, BiCGStabL, CSMinRes, GaussSeidel, IDRs, IterativeSolver, MinRes, SymmLQ, CG,
+    CR, FGMRES, GCR, QMR,
+};
+
+#[test]
+fn boxed_solvers() {
+    let n = 64;
+    let mat = tridiagonal(n);
+    let rhs: Vec<f64> = (0..n).map(|i| ((i * 3) % 7) as f64 - 3.).collect();
+
+    let solvers: Vec<(&str, Box<dyn IterativeSolver<f64> + '_>)> = vec![
+        ("CG", Box::new(CG::new(&mat, n))),
+        ("MinRes", Box::new(MinRes::new(&mat, n))),
+        ("CSMinRes", Box::new(CSMinRes::new(&mat, n))),
+        ("BiCG", Box::new(BiCG::new(&mat, n))),
+        ("BiCGStab", Box::new(BiCGStab::new(&mat, n))),
+        ("BiCGStab(2)", Box::new(BiCGStabL::new(&mat, n, 2))),
+        (
+            "GaussSeidel",
+            Box::new(GaussSeidel::new(mat.view()).unwrap()),
+        ),
+    ];
+
+    let mut x_ref: Option<Vec<f64>> = None;
+    for (name, mut solver) in solvers {
+        let mut x = vec![0_f64; n];
+        let (its, res) = solver.solve(&rhs, &mut x, 2000, 1E-10).unwrap();
+        println!("{}: {} iterations, residual {}", name, its, res);
+        match &x_ref {
+            None => x_ref = Some(x),
+            Some(x_ref) => {
+                for (u, v) in x.iter().zip(x_ref.iter()) {
+                    approx::assert_abs_diff_eq!(u, v, epsilon = 1E-7);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn solve_alloc_matches_in_place() {
+    let n = 64;
+    let mat = tridiagonal(n);
+    let rhs: Vec<f64> = (0..n).map(|i| (i % 5) as f64 - 2.).collect();
+
+    let mut solver = BiCGStab::new(&mat, n);
+    let mut x = vec![0_f64; n];
+    let (its, res) = solver.solve(&rhs, &mut x, 500, 1E-10).unwrap();
+    let (y, its_alloc, res_alloc) = solver.solve_alloc(&rhs, 500, 1E-10).unwrap();
+    assert_eq!((its, res), (its_alloc, res_alloc));
+    assert_eq!(x, y);
+
+    let mut boxed: Box<dyn IterativeSolver<f64> + '_> = Box::new(CG::new(&mat, n));
+    let (y, _, _) = boxed.solve_alloc(&rhs, 500, 1E-10).unwrap();
+    for (u, v) in x.iter().zip(y.iter()) {
+        approx::assert_abs_diff_eq!(u, v, epsilon = 1E-7);
+    }
+    assert!(boxed.solve_alloc(&rhs[1..], 500, 1E-10).is_err());
+}
+
+#[test]
+fn config_matches_positional() {
+    use sprsolve::{error::SolverError, NormKind, SolveConfig};
+
+    let n = 64;
+    let mat = tridiagonal(n);
+    let rhs: Vec<f64> = (0..n).map(|i| ((i * 5) % 9) as f64 - 4.).collect();
+    let config = SolveConfig::default().max_iter(300).tol(1E-10);
+    assert_eq!(SolveConfig::<f64>::default().max_iter, 1000);
+
+    fn make(k: usize, mat: &sprs::CsMat<f64>) -> Box<dyn IterativeSolver<f64> + '_> {
+        let n = mat.rows();
+        match k {
+            0 => Box::new(CG::new(mat, n)),
+            1 => Box::new(MinRes::new(mat, n)),
+            2 => Box::new(BiCG::new(mat, n)),
+            _ => Box::new(BiCGStab::new(mat, n)),
+        }
+    }
+    for k in 0..4 {
+        let mut x = vec![0_f64; n];
+        let ret = make(k, &mat).solve(&rhs, &mut x, 300, 1E-10).unwrap();
+        let mut y = vec![0_f64; n];
+        let ret_config = make(k, &mat)
+            .solve_with_config(&rhs, &mut y, &config)
+            .unwrap();
+        assert_eq!(ret, ret_config);
+        assert_eq!(x, y);
+    }
+
+    // the absolute tolerance stops earlier than the relative one
+    let rhs_norm = rhs.iter().map(|v| v * v).sum::<f64>().sqrt();
+    let mut x = vec![0_f64; n];
+    let (its, res) = CG::new(&mat, n)
+        .solve_with_config(&rhs, &mut x, &config.abs_tol(1E-4))
+        .unwrap();
+    let (its_rel, _) = CG::new(&mat, n)
+        .solve(&rhs, &mut vec![0_f64; n], 300, 1E-10)
+        .unwrap();
+    assert!(its < its_rel);
+    assert!(res * rhs_norm <= 1E-4);
+
+    // the norm kind
+    let config = config.norm_kind(NormKind::LInf);
+    let mut x = vec![0_f64; n];
+    let ret = BiCGStab::new(&mat, n)
+        .solve_with_norm(&rhs, &mut x, 300, 1E-10, NormKind::LInf)
+        .unwrap();
+    let mut y = vec![0_f64; n];
+    let ret_config = BiCGStab::new(&mat, n)
+        .solve_with_config(&rhs, &mut y, &config)
+        .unwrap();
+    assert_eq!(ret, ret_config);
+    assert_eq!(x, y);
+    match CG::new(&mat, n).solve_with_config(&rhs, &mut y, &config) {
+        Err(SolverError::InvalidParameter(_)) => {}
+        r => panic!("CG has no infinity-norm test, got {:?}", r),
+    }
+}
+
+#[test]
+fn absolute_tolerance_tiny_rhs() {
+    use sprsolve::SolveConfig;
+
+    let n = 64;
+    let mat = tridiagonal(n);
+    let rhs: Vec<f64> = (0..n).map(|i| 1E-20 * ((i % 3) as f64 + 1.)).collect();
+    let abs_res = |x: &[f64]| {
+        let mut ax = vec![0_f64; n];
+        sprsolve::MatVecMul::mul_vec(&mat, x, &mut ax);
+        ax.iter()
+            .zip(rhs.iter())
+            .map(|(a, b)| (b - a) * (b - a))
+            .sum::<f64>()
+            .sqrt()
+    };
+
+    // without an absolute tolerance, x is set to zero
+    let mut x = vec![1_f64; n];
+    assert_eq!(
+        BiCGStab::new(&mat, n)
+            .solve(&rhs, &mut x, 300, 1E-8)
+            .unwrap()
+            .0,
+        0
+    );
+    assert!(x.iter().all(|&v| v == 0.));
+
+    let config = SolveConfig::default().max_iter(300).abs_tol(1E-8);
+    for k in 0..2 {
+        let mut x = vec![1_f64; n];
+        assert!(abs_res(&x) > 1E-8);
+        let (its, _) = match k {
+            0 => BiCGStab::new(&mat, n).solve_with_config(&rhs, &mut x, &config),
+            _ => MinRes::new(&mat, n).solve_with_config(&rhs, &mut x, &config),
+        }
+        .unwrap();
+        assert!(its > 0);
+        assert!(abs_res(&x) <= 1E-8);
+    }
+}
+
+#[test]
+fn config_time_limit() {
+    use sprsolve::{error::SolverError, SolveConfig};
+    use std::time::Duration;
+
+    // the 1D Laplacian, whose condition number grows like n^2, converges slowly
+    let n = 2000;
+    let mut tri = sprs::TriMat::<f64>::new((n, n));
+    for i in 0..n {
+        tri.add_triplet(i, i, 2.);
+        if i > 0 {
+            tri.add_triplet(i, i - 1, -1.);
+            tri.add_triplet(i - 1, i, -1.);
+        }
+    }
+    let mat: sprs::CsMat<f64> = tri.to_csr();
+    let rhs: Vec<f64> = (0..n).map(|i| ((i * 7) % 11) as f64 - 5.).collect();
+
+    let config = SolveConfig::default().max_iter(100_000).tol(1E-12);
+    assert_eq!(config.time_limit, None);
+    let tiny = config.time_limit(Duration::from_nanos(1));
+    assert_eq!(tiny.time_limit, Some(Duration::from_nanos(1)));
+    let solvers: Vec<(&str, Box<dyn IterativeSolver<f64> + '_>)> = vec![
+        ("CG", Box::new(CG::new(&mat, n))),
+        ("CR", Box::new(CR::new(&mat, n))),
+        ("MinRes", Box::new(MinRes::new(&mat, n))),
+        ("SymmLQ", Box::new(SymmLQ::new(&mat, n))),
+        ("CSMinRes", Box::new(CSMinRes::new(&mat, n))),
+        ("BiCG", Box::new(BiCG::new(&mat, n))),
+        ("QMR", Box::new(QMR::new(&mat, n))),
+        ("BiCGStab", Box::new(BiCGStab::new(&mat, n))),
+        ("BiCGStab(2)", Box::new(BiCGStabL::new(&mat, n, 2))),
+        ("IDR(4)", Box::new(IDRs::new(&mat, n, 4))),
+        ("FGMRES", Box::new(FGMRES::new(&mat, n, 30))),
+        ("GCR", Box::new(GCR::new(&mat, n, 30))),
+        (
+            "GaussSeidel",
+            Box::new(GaussSeidel::new(mat.view()).unwrap()),
+        ),
+    ];
+    for (name, mut solver) in solvers {
+        let mut x = vec![0_f64; n];
+        match solver.solve_with_config(&rhs, &mut x, &tiny) {
+            Err(SolverError::TimeLimit { iter, resid }) => {
+                assert!(iter < 100, "{}", name);
+                assert!(resid > 1E-12, "{}", name);
+            }
+            r => panic!("{}: expected the time limit, got {:?}", name, r),
+        }
+    }
+
+    // a generous limit doesn't change the solve
+    let generous = config.time_limit(Duration::from_secs(3600));
+    let mut x = vec![0_f64; n];
+    let ret = MinRes::new(&mat, n)
+        .solve_with_config(&rhs, &mut x, &config)
+        .unwrap();
+    let mut y = vec![0_f64; n];
+    let ret_limited = MinRes::new(&mat, n)
+        .solve_with_config(&rhs, &mut y, &generous)
+        .unwrap();
+    assert_eq!(ret, ret_limited);
+    assert_eq!(x, y);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_round_trip() {
+    use sprsolve::{error::SolverError, NormKind, SolveConfig, SolveReport};
+
+    let config = SolveConfig::default()
+        .max_iter(500)
+        .tol(1E-10)
+        .abs_tol(1E-12)
+        .norm_kind(NormKind::LInf);
+    let json = serde_json::to_string(&config).unwrap();
+    let back: SolveConfig<f64> = serde_json::from_str(&json).unwrap();
+    assert_eq!(config, back);
+
+    // a report bundling the residual history
+    let n = 64;
+    let mat = tridiagonal(n);
+    let rhs: Vec<f64> = (0..n).map(|i| ((i * 5) % 9) as f64 - 4.).collect();
+    let mut x = vec![0_f64; n];
+    let (_, _, history) = MinRes::new(&mat, n)
+        .solve_with_history(&rhs, &mut x, 300, 1E-10)
+        .unwrap();
+    let mut x = vec![0_f64; n];
+    let report = MinRes::new(&mat, n)
+        .solve_checked(&rhs, &mut x, 300, 1E-10)
+        .unwrap()
+        .with_history(history);
+    let json = serde_json::to_string(&report).unwrap();
+    let back: SolveReport<f64> = serde_json::from_str(&json).unwrap();
+    assert_eq!(report, back);
+
+    // errors are logged with their payload
+    let err = SolverError::Stagnation {
+        iter: 12,
+        resid: 0.25,
+    };
+    let back: SolverError = serde_json::from_str(&serde_json::to_string(&err).unwrap()).unwrap();
+    assert!(matches!(back, SolverError::Stagnation { iter: 12, resid } if resid == 0.25));
+    let err = SolverError::from(std::io::Error::new(std::io::ErrorKind::NotFound, "no file"));
+    let back: SolverError = serde_json::from_str(&serde_json::to_string(&err).unwrap()).unwrap();
+    assert_eq!(back.to_string(), err.to_string());
+}
+
+#[test]
+fn solve_report_termination() {
+    use sprsolve::{error::SolverError, Stagnation, CR, QMR};
+
+    let n = 64;
+    let mat = tridiagonal(n);
+    let rhs: Vec<f64> = (0..n).map(|i| ((i * 3) % 7) as f64 - 3.).collect();
+    let rhs_norm = rhs.iter().map(|v| v * v).sum::<f64>().sqrt();
+
+    // convergence, with the same outcome as `solve`
+    let mut x = vec![0_f64; n];
+    let (its, res) = CG::new(&mat, n).solve(&rhs, &mut x, 500, 1E-10).unwrap();
+    let mut y = vec![0_f64; n];
+    let report = CG::new(&mat, n)
+        .solve_report(&rhs, &mut y, 500, 1E-10)
+        .unwrap();
+    assert_eq!(x, y);
+    assert!(report.converged);
+    assert_eq!(report.breakdown, None);
+    assert_eq!((report.iterations, report.rel_residual), (its, res));
+    approx::assert_relative_eq!(report.abs_residual, res * rhs_norm, max_relative = 1E-2);
+    assert!(report.residual_gap < 1E-2);
+
+    // the iteration limit, through the trait object
+    let mut boxed: Box<dyn IterativeSolver<f64> + '_> = Box::new(MinRes::new(&mat, n));
+    let mut x = vec![0_f64; n];
+    let report = boxed.solve_report(&rhs, &mut x, 3, 1E-10).unwrap();
+    assert!(!report.converged);
+    assert_eq!(report.breakdown, None);
+    assert_eq!(report.iterations, 3);
+    assert!(report.rel_residual > 1E-10 && report.rel_residual < 1.);
+    approx::assert_relative_eq!(
+        report.abs_residual,
+        report.rel_residual * rhs_norm,
+        max_relative = 1E-12
+    );
+
+    // a breakdown of the nonsymmetric Lanczos process at the second iteration
+    let mut tri = sprs::TriMat::<f64>::new((3, 3));
+    for &(i, j, v) in [
+        (0, 0, 2.),
+        (0, 2, 1.),
+        (1, 0, 1.),
+        (1, 1, 2.),
+        (2, 1, 1.),
+        (2, 2, 2.),
+    ]
+    .iter()
+    {
+        tri.add_triplet(i, j, v);
+    }
+    let cyclic: sprs::CsMat<f64> = tri.to_csr();
+    let mut x = [0.; 3];
+    let report = QMR::new(&cyclic, 3)
+        .solve_report(&[1., 0., 0.], &mut x, 10, 1E-10)
+        .unwrap();
+    assert!(!report.converged);
+    assert_eq!(report.breakdown, Some(1));
+    assert_eq!(report.iterations, 1);
+    assert!(report.rel_residual > 0. && report.rel_residual < 1.);
+
+    // a breakdown at the start, the residual is then the one of the initial guess
+    let zero: sprs::CsMat<f64> = sprs::TriMat::new((n, n)).to_csr();
+    let mut x = vec![0_f64; n];
+    let report = CR::new(&zero, n)
+        .solve_report(&rhs, &mut x, 500, 1E-10)
+        .unwrap();
+    assert_eq!(report.breakdown, Some(0));
+    assert_eq!((report.iterations, report.rel_residual), (0, 1.));
+
+    // stagnation on a nearly singular system
+    let mut tri = sprs::TriMat::new((n, n));
+    for i in 0..n {
+        let d = if i == 0 || i + 1 == n { 1. } else { 2. };
+        tri.add_triplet(i, i, d + 1E-12);
+        if i + 1 < n {
+            tri.add_triplet(i, i + 1, -1.);
+            tri.add_triplet(i + 1, i, -1.);
+        }
+    }
+    let neumann: sprs::CsMat<f64> = tri.to_csr();
+    let rhs_stag: Vec<f64> = (0..n).map(|i| 1. + (i as f64 * 0.3).sin()).collect();
+    let mut minres = MinRes::new(&neumann, n);
+    minres.set_stagnation(Some(Stagnation::new(1E-3, 10)));
+    let mut x = vec![0_f64; n];
+    let report = minres.solve_report(&rhs_stag, &mut x, 5000, 1E-10).unwrap();
+    assert!(!report.converged);
+    assert_eq!(report.breakdown, None);
+    assert!(report.iterations < 5000);
+    assert!(report.rel_residual > 1E-10);
+
+    // other errors are still returned
+    match CG::new(&mat, n).solve_report(&rhs[1..], &mut x[1..], 500, 1E-10) {
+        Err(SolverError::IncompatibleMatrixFormat(_)) => {}
+        r => panic!("expected a dimension error, got {:?}", r),
+    }
+}
+
+#[test]
+fn solve_report_default_impl() {
+    use sprsolve::error::{SolveResult, SolverError};
+
+    /// A solver that only forwards to CG, and relies on the provided `solve_report`.
+    struct Forward<'a>(CG<'a, f64, sprs::CsMat<f64>>);
+
+    impl<'a> IterativeSolver<f64> for Forward<'a> {
+        fn solve(
+            &mut self,
+            rhs: &[f64],
+            x: &mut [f64],
+            max_iter: usize,
+            tol: f64,
+        ) -> SolveResult<(usize, f64)> {
+            self.0.solve(rhs, x, max_iter, tol)
+        }
+    }
+
+    let n = 64;
+    let mat = tridiagonal(n);
+    let rhs: Vec<f64> = (0..n).map(|i| (i % 5) as f64 - 2.).collect();
+    let rhs_norm = rhs.iter().map(|v| v * v).sum::<f64>().sqrt();
+
+    let mut solver = Forward(CG::new(&mat, n));
+    let mut x = vec![0_f64; n];
+    let report = solver.solve_report(&rhs, &mut x, 500, 1E-10).unwrap();
+    assert!(report.converged);
+    approx::assert_relative_eq!(
+        report.abs_residual,
+        report.rel_residual * rhs_norm,
+        max_relative = 1E-12
+    );
+    assert!(report.residual_gap.is_nan());
+
+    let mut x = vec![0_f64; n];
+    let report = solver.solve_report(&rhs, &mut x, 2, 1E-10).unwrap();
+    assert!(!report.converged);
+    assert_eq!(report.iterations, 2);
+    assert!(report.rel_residual.is_nan());
+    assert!(report.abs_residual.is_nan());
+    assert!(report.residual_gap.is_nan());
+    assert!(matches!(
+        solver.solve_report(&rhs[1..], &mut x[1..], 2, 1E-10),
+        Err(SolverError::IncompatibleMatrixFormat(_))
+    ));
+}
+
+/// Symmetric positive definite and diagonally dominant tridiagonal matrix.
+fn tridiagonal(n: usize) -> sprs::CsMat<f64> {
+    let mut tri = sprs::TriMat::<f64>::new((n, n));
+    for i in 0..n {
+        tri.add_triplet(i, i, 3.);
+        if i > 0 {
+            tri.add_triplet(i, i - 1, -1.);
+        }
+        if i + 1 < n {
+            tri.add_triplet(i, i + 1, -1.);
+        }
+    }
+    tri.to_csr()
+}
@@ -0,0 +1,149 @@
+use num_complex::Complex64;
+use sprsolve::{error::SolverError, MatVecMul, CG, LSMR};
+
+#[test]
+fn lsmr_least_squares() {
+    let (m, n) = (60, 20);
+    let a = tall_matrix(m, n);
+    // an inconsistent right-hand side
+    let b: Vec<f64> = (0..m).map(|i| ((i * 7) % 13) as f64 - 6.).collect();
+
+    for &damp in &[0., 0.5] {
+        let mut solver = LSMR::new(&a, m, n);
+        assert_eq!(solver.shape(), (m, n));
+        solver.set_damp(damp);
+        assert_eq!(solver.damp(), damp);
+        let mut x = vec![0_f64; n];
+        let (its, res) = solver.solve(&b, &mut x, 200, 1E-12, 1E-12).unwrap();
+        println!("LSMR, damp = {}: {} iterations, {:e}", damp, its, res);
+        assert!(res <= 1E-12);
+
+        let expected = normal_solution(&a, &b, damp);
+        let x_norm = expected.iter().fold(0_f64, |acc, v| acc + v * v).sqrt();
+        for (u, v) in x.iter().zip(expected.iter()) {
+            approx::assert_abs_diff_eq!(u, v, epsilon = 1E-9 * x_norm);
+        }
+    }
+}
+
+#[test]
+fn lsmr_monotone_normal_residual() {
+    let (m, n) = (60, 20);
+    let a = tall_matrix(m, n);
+    let b: Vec<f64> = (0..m).map(|i| ((i * 5) % 9) as f64 - 4.).collect();
+
+    // |A^T r| and |r| of the iterate after k steps, which doesn't depend on max_iter
+    let (mut ar_old, mut r_old) = (f64::INFINITY, f64::INFINITY);
+    for k in 1..=n {
+        let mut x = vec![0_f64; n];
+        let ret = LSMR::new(&a, m, n).solve(&b, &mut x, k, 0., 0.);
+        assert!(ret.is_ok() || matches!(ret, Err(SolverError::InsufficientIterNum(_))));
+        let mut r = vec![0_f64; m];
+        a.mul_vec(&x, &mut r);
+        r.iter_mut().zip(b.iter()).for_each(|(r, b)| *r = b - *r);
+        let mut ar = vec![0_f64; n];
+        a.mul_vec_trans(&r, &mut ar).unwrap();
+        let ar_norm = ar.iter().fold(0_f64, |acc, v| acc + v * v).sqrt();
+        let r_norm = r.iter().fold(0_f64, |acc, v| acc + v * v).sqrt();
+        println!("{}: |A^T r| = {:e}, |r| = {:e}", k, ar_norm, r_norm);
+        assert!(ar_norm <= ar_old * (1. + 1E-10) + 1E-12);
+        assert!(r_norm <= r_old * (1. + 1E-10) + 1E-12);
+        ar_old = ar_norm;
+        r_old = r_norm;
+    }
+    assert!(ar_old <= 1E-8);
+}
+
+#[test]
+fn lsmr_compatible_complex() {
+    // a consistent complex system, solved through A^H u = conj(A^T conj(u))
+    let (m, n) = (30, 10);
+    let mut tri = sprs::TriMat::<Complex64>::new((m, n));
+    for i in 0..m {
+        tri.add_triplet(i, i % n, Complex64::new(2. + (i % 3) as f64, 0.5));
+        tri.add_triplet(
+            i,
+            (i * 7 + 3) % n,
+            Complex64::new(-0.4, (i % 5) as f64 * 0.2),
+        );
+    }
+    let a: sprs::CsMat<Complex64> = tri.to_csr();
+    let x_true: Vec<Complex64> = (0..n)
+        .map(|i| Complex64::new((i as f64 * 0.7).sin(), (i as f64 * 0.3).cos()))
+        .collect();
+    let mut b = vec![Complex64::new(0., 0.); m];
+    a.mul_vec(&x_true, &mut b);
+
+    let mut x = vec![Complex64::new(0., 0.); n];
+    let (its, _) = LSMR::new(&a, m, n)
+        .solve(&b, &mut x, 100, 1E-14, 1E-14)
+        .unwrap();
+    println!("complex LSMR: {} iterations", its);
+    for (u, v) in x.iter().zip(x_true.iter()) {
+        approx::assert_abs_diff_eq!((u - v).norm(), 0., epsilon = 1E-10);
+    }
+}
+
+#[test]
+fn lsmr_errors() {
+    let (m, n) = (12, 4);
+    let a = tall_matrix(m, n);
+    let mut solver = LSMR::new(&a, m, n);
+
+    let mut x = vec![1_f64; n];
+    assert_eq!(
+        solver
+            .solve(&vec![0_f64; m], &mut x, 10, 1E-10, 1E-10)
+            .unwrap(),
+        (0, 0.)
+    );
+    assert_eq!(x, vec![0_f64; n]);
+    assert!(matches!(
+        solver.solve(&vec![1_f64; n], &mut x, 10, 1E-10, 1E-10),
+        Err(SolverError::IncompatibleMatrixFormat(_))
+    ));
+    let mut y = vec![0_f64; m];
+    assert!(matches!(
+        solver.solve(&vec![1_f64; m], &mut y, 10, 1E-10, 1E-10),
+        Err(SolverError::IncompatibleMatrixFormat(_))
+    ));
+}
+
+/// A `m x n` sparse matrix of full column rank, with `m >= n`.
+fn tall_matrix(m: usize, n: usize) -> sprs::CsMat<f64> {
+    let mut tri = sprs::TriMat::<f64>::new((m, n));
+    for i in 0..m {
+        tri.add_triplet(i, i % n, 2. + (i % 3) as f64);
+        tri.add_triplet(i, (i * 7 + 3) % n, ((i * 5) % 11) as f64 / 11. - 0.5);
+        tri.add_triplet(i, (i * 3 + 1) % n, 0.3);
+    }
+    tri.to_csr()
+}
+
+/// The solution of the normal equations $(A^TA + \lambda^2 I)x = A^Tb$, by CG on the
+/// explicit normal matrix.
+fn normal_solution(a: &sprs::CsMat<f64>, b: &[f64], damp: f64) -> Vec<f64> {
+    let n = a.cols();
+    let mut tri = sprs::TriMat::<f64>::new((n, n));
+    let mut col = vec![0_f64; n];
+    let mut ae = vec![0_f64; a.rows()];
+    let mut normal_col = vec![0_f64; n];
+    for j in 0..n {
+        col.iter_mut().for_each(|v| *v = 0.);
+        col[j] = 1.;
+        a.mul_vec(&col, &mut ae);
+        a.mul_vec_trans(&ae, &mut normal_col).unwrap();
+        for (i, &v) in normal_col.iter().enumerate() {
+            tri.add_triplet(i, j, v);
+        }
+        tri.add_triplet(j, j, damp * damp);
+    }
+    let normal: sprs::CsMat<f64> = tri.to_csr();
+    let mut atb = vec![0_f64; n];
+    a.mul_vec_trans(b, &mut atb).unwrap();
+    let mut x = vec![0_f64; n];
+    CG::new(&normal, n)
+        .solve(&atb, &mut x, 1000, 1E-15)
+        .unwrap();
+    x
+}
@@ -59,6 +59,645 @@ fn minres_ident() {
     }
 }
 
+#[test]
+fn minres_homotopy_cache() {
+    let (rows, cols) = (16, 16);
+    let n = rows * cols;
+    let (lap, rhs) = grid_laplacian((rows, cols));
+
+    // A(t) = L - t * I, a slowly varying family of symmetric matrices
+    let shifted = |t: f64| {
+        let mut id = sprs::TriMat::<f64>::new((n, n));
+        for i in 0..n {
+            id.add_triplet(i, i, -t);
+        }
+        &lap + &id.to_csr()
+    };
+
+    let mut cache = sprsolve::SpectralCache::<f64>::new(n, 8, 60);
+    let (mut iters_plain, mut iters_cached) = (0, 0);
+    for (step, t) in [0_f64, 0.01, 0.02].iter().enumerate() {
+        let a = shifted(*t);
+
+        let mut x = vec![0_f64; n];
+        let mut solver = sprsolve::MinRes::new(&a, n);
+        let (its, _) = solver.solve(&rhs, &mut x, 500, 1E-10).unwrap();
+        iters_plain += its;
+
+        let mut y = vec![0_f64; n];
+        let mut solver = sprsolve::MinRes::new(&a, n);
+        let (its_c, res) = solver
+            .solve_with_cache(&mut cache, &rhs, &mut y, 500, 1E-10)
+            .unwrap();
+        iters_cached += its_c;
+        println!(
+            "step {}: {} iterations without cache, {} with cache (res {})",
+            step, its, its_c, res
+        );
+        assert_eq!(cache.len(), 8);
+        assert!(res <= 1E-10);
+        if step > 0 {
+            assert!(its_c < its);
+        }
+    }
+    assert!(iters_cached < iters_plain);
+}
+
+#[test]
+fn minres_residual_gap() {
+    // the Hilbert matrix is severely ill-conditioned
+    let n = 8;
+    let mut tri = sprs::TriMat::<f64>::new((n, n));
+    for i in 0..n {
+        for j in 0..n {
+            tri.add_triplet(i, j, 1. / (i + j + 1) as f64);
+        }
+    }
+    let hilbert = tri.to_csr();
+    let rhs = vec![1_f64; n];
+
+    let mut x = vec![0_f64; n];
+    let report = sprsolve::MinRes::new(&hilbert, n)
+        .solve_checked(&rhs, &mut x, 1000, 1E-10)
+        .unwrap();
+    println!("{:?}", report);
+    assert!(report.residual_gap > 1.);
+
+    // a well-conditioned system has no gap
+    let (lap, rhs) = grid_laplacian((16, 16));
+    let mut x = vec![0_f64; 256];
+    let report = sprsolve::MinRes::new(&lap, 256)
+        .solve_checked(&rhs, &mut x, 300, 1E-8)
+        .unwrap();
+    println!("{:?}", report);
+    assert!(report.residual_gap < 1E-2);
+
+    // the preconditioned entry points are checked as well, on the positive definite -A
+    let lap = lap.map(|v| -v);
+    let rhs: Vec<f64> = rhs.iter().map(|v| -v).collect();
+    let jacobi = sprsolve::precond::Jacobi::new(lap.view()).unwrap();
+    let mut x = vec![0_f64; 256];
+    let report = sprsolve::CG::new(&lap, 256)
+        .precond_solve_checked(&jacobi, &rhs, &mut x, 300, 1E-8)
+        .unwrap();
+    assert!(report.rel_residual <= 1E-8);
+    assert!(report.residual_gap < 1E-2);
+    let mut x = vec![0_f64; 256];
+    let report = sprsolve::BiCGStab::new(&lap, 256)
+        .precond_solve_checked(&jacobi, &rhs, &mut x, 300, 1E-8)
+        .unwrap();
+    assert!(report.rel_residual <= 1E-8);
+    assert!(report.residual_gap < 1E-2);
+    let mut x = vec![0_f64; 256];
+    let report = sprsolve::MinRes::new(&lap, 256)
+        .precond_solve_checked(&jacobi, &rhs, &mut x, 300, 1E-8)
+        .unwrap();
+    assert!(report.rel_residual <= 1E-8);
+    assert!(report.residual_gap < 1E-2);
+}
+
+#[test]
+fn solve_with_inf_norm() {
+    let (lap, rhs) = grid_laplacian((16, 16));
+    let n = 256;
+    let tol = 1E-8;
+    let rhs_inf = rhs.iter().fold(0_f64, |acc, v| acc.max(v.abs()));
+    let true_inf_residual = |x: &[f64]| {
+        let mut ax = vec![0_f64; n];
+        sprsolve::MatVecMul::mul_vec(&lap, x, &mut ax);
+        ax.iter()
+            .zip(rhs.iter())
+            .fold(0_f64, |acc, (u, v)| acc.max((u - v).abs()))
+            / rhs_inf
+    };
+
+    let mut x = vec![0_f64; n];
+    let (_, res) = sprsolve::MinRes::new(&lap, n)
+        .solve_with_norm(&rhs, &mut x, 1000, tol, sprsolve::NormKind::LInf)
+        .unwrap();
+    assert!(res <= tol);
+    approx::assert_abs_diff_eq!(res, true_inf_residual(&x), epsilon = 1E-14);
+
+    let mut x = vec![0_f64; n];
+    let (_, res) = sprsolve::BiCGStab::new(&lap, n)
+        .solve_with_norm(&rhs, &mut x, 1000, tol, sprsolve::NormKind::LInf)
+        .unwrap();
+    assert!(res <= tol);
+    approx::assert_relative_eq!(res, true_inf_residual(&x), max_relative = 1E-3);
+}
+
+#[test]
+fn solve_with_history() {
+    let (lap, rhs) = grid_laplacian((12, 12));
+    let n = 144;
+    let check = |its: usize, res: f64, history: &[f64]| {
+        assert_eq!(history.len(), its + 1);
+        approx::assert_abs_diff_eq!(history[0], 1.);
+        assert_eq!(*history.last().unwrap(), res);
+        assert!(res <= 1E-10);
+    };
+
+    let mut x = vec![0_f64; n];
+    let (its, res, history) = sprsolve::MinRes::new(&lap, n)
+        .solve_with_history(&rhs, &mut x, 500, 1E-10)
+        .unwrap();
+    check(its, res, &history);
+    // the recurrence residual of MINRES never increases
+    assert!(history.windows(2).all(|w| w[1] <= w[0]));
+
+    let mut x = vec![0_f64; n];
+    let (its, res, history) = sprsolve::CSMinRes::new(&lap, n)
+        .solve_with_history(&rhs, &mut x, 500, 1E-10)
+        .unwrap();
+    check(its, res, &history);
+
+    let mut x = vec![0_f64; n];
+    let (its, res, history) = sprsolve::BiCGStab::new(&lap, n)
+        .solve_with_history(&rhs, &mut x, 500, 1E-10)
+        .unwrap();
+    check(its, res, &history);
+    assert_eq!(history.len(), its + 1);
+}
+
+#[test]
+fn solve_with_callback() {
+    use std::ops::ControlFlow;
+    let (lap, rhs) = grid_laplacian((12, 12));
+    let n = 144;
+
+    // stop after 5 iterations
+    let mut seen = Vec::new();
+    let mut x = vec![0_f64; n];
+    let (its, res) = sprsolve::MinRes::new(&lap, n)
+        .solve_with_callback(&rhs, &mut x, 500, 1E-10, |k, r| {
+            seen.push(r);
+            if k == 5 {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        })
+        .unwrap();
+    assert_eq!(its, 5);
+    assert_eq!(seen.len(), 6);
+    assert_eq!(*seen.last().unwrap(), res);
+    let mut x_hist = vec![0_f64; n];
+    let (_, _, history) = sprsolve::MinRes::new(&lap, n)
+        .solve_with_history(&rhs, &mut x_hist, 500, 1E-10)
+        .unwrap();
+    assert_eq!(&history[..6], seen.as_slice());
+
+    let mut x = vec![0_f64; n];
+    let (its, _) = sprsolve::BiCGStab::new(&lap, n)
+        .solve_with_callback(&rhs, &mut x, 500, 1E-10, |k, _| {
+            if k == 5 {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        })
+        .unwrap();
+    assert_eq!(its, 5);
+
+    // never breaking is the same as solve
+    let mut x1 = vec![0_f64; n];
+    let mut x2 = vec![0_f64; n];
+    let r1 = sprsolve::BiCGStab::new(&lap, n)
+        .solve(&rhs, &mut x1, 500, 1E-10)
+        .unwrap();
+    let r2 = sprsolve::BiCGStab::new(&lap, n)
+        .solve_with_callback(&rhs, &mut x2, 500, 1E-10, |_, _| ControlFlow::Continue(()))
+        .unwrap();
+    assert_eq!(r1, r2);
+    assert_eq!(x1, x2);
+}
+
+#[test]
+fn diverged_on_singular_matrix() {
+    use sprsolve::error::SolverError;
+    // the zero matrix is singular, and the Krylov iterations divide by zero
+    let n = 16;
+    let zero: sprs::CsMat<f64> = sprs::TriMat::new((n, n)).to_csr();
+    let rhs = vec![1_f64; n];
+
+    let mut x = vec![0_f64; n];
+    let ret = sprsolve::MinRes::new(&zero, n).solve(&rhs, &mut x, 1000, 1E-10);
+    assert!(matches!(ret, Err(SolverError::Diverged(its)) if its < 1000));
+
+    let mut x = vec![0_f64; n];
+    let ret = sprsolve::BiCGStab::new(&zero, n).solve(&rhs, &mut x, 1000, 1E-10);
+    assert!(matches!(ret, Err(SolverError::Diverged(its)) if its < 1000));
+
+    // the preconditioned iterations run the same check
+    let mut x = vec![0_f64; n];
+    let ret = sprsolve::BiCGStab::new(&zero, n).precond_solve(
+        &sprsolve::precond::Identity,
+        &rhs,
+        &mut x,
+        1000,
+        1E-10,
+    );
+    assert!(matches!(ret, Err(SolverError::Diverged(its)) if its < 1000));
+}
+
+#[test]
+fn minres_from_op() {
+    use sprsolve::MinRes;
+
+    let (rows, cols) = (8, 8);
+    let n = rows * cols;
+    let (lap, rhs) = grid_laplacian((rows, cols));
+
+    let mut x = vec![0_f64; n];
+    let ret = MinRes::new(&lap, n)
+        .solve(&rhs, &mut x, 300, 1E-10)
+        .unwrap();
+    let mut y = vec![0_f64; n];
+    let ret_op = MinRes::from_op(&lap)
+        .unwrap()
+        .solve(&rhs, &mut y, 300, 1E-10)
+        .unwrap();
+    assert_eq!(ret, ret_op);
+    assert_eq!(x, y);
+
+    // a mismatched size is rejected at construction
+    assert!(std::panic::catch_unwind(|| MinRes::new(&lap, n + 1)).is_err());
+
+    // an operator without a shape is taken at the given size
+    let mut z = vec![0_f64; n];
+    let ret_plain = MinRes::new(&ProductsOnly(&lap), n)
+        .solve(&rhs, &mut z, 300, 1E-10)
+        .unwrap();
+    assert_eq!(ret, ret_plain);
+    assert_eq!(x, z);
+    assert!(MinRes::from_op(&ProductsOnly(&lap)).is_err());
+}
+
+#[test]
+fn cr_symmetric_indefinite() {
+    use sprsolve::{error::SolverError, MinRes, CR};
+
+    // symmetric tridiagonal matrix with diagonal entries of both signs
+    let n = 40;
+    let mut tri = sprs::TriMat::<f64>::new((n, n));
+    for i in 0..n {
+        let d = 2. + (i % 3) as f64;
+        tri.add_triplet(i, i, if i % 2 == 0 { d } else { -d });
+        if i > 0 {
+            tri.add_triplet(i, i - 1, 0.5);
+            tri.add_triplet(i - 1, i, 0.5);
+        }
+    }
+    let a: sprs::CsMat<f64> = tri.to_csr();
+    let rhs: Vec<f64> = (0..n).map(|i| (i as f64 * 0.3).sin() + 1.).collect();
+
+    let mut x_cr = vec![0_f64; n];
+    let (its, res) = CR::new(&a, n).solve(&rhs, &mut x_cr, 500, 1E-10).unwrap();
+    assert!(its > 0 && res <= 1E-10);
+    let mut x_minres = vec![0_f64; n];
+    MinRes::new(&a, n)
+        .solve(&rhs, &mut x_minres, 500, 1E-10)
+        .unwrap();
+    for (u, v) in x_cr.iter().zip(x_minres.iter()) {
+        approx::assert_abs_diff_eq!(u, v, epsilon = 1E-8);
+    }
+
+    // r^H A r vanishes at the start
+    let zero: sprs::CsMat<f64> = sprs::TriMat::new((n, n)).to_csr();
+    let mut x = vec![0_f64; n];
+    let ret = CR::new(&zero, n).solve(&rhs, &mut x, 500, 1E-10);
+    assert!(matches!(ret, Err(SolverError::BreakDown(0))));
+}
+
+#[test]
+fn precond_minres_saddle_point() {
+    use sprsolve::{precond::IC0, MatVecMul, MinRes};
+
+    // the saddle point matrix [K B^T; B -C], with an ill-conditioned K
+    let (n1, n2) = (200, 100);
+    let n = n1 + n2;
+    let mut tri = sprs::TriMat::<f64>::new((n, n));
+    let mut block = sprs::TriMat::<f64>::new((n, n));
+    for i in 0..n1 {
+        tri.add_triplet(i, i, 2.01);
+        block.add_triplet(i, i, 2.01);
+        if i > 0 {
+            tri.add_triplet(i, i - 1, -1.);
+            tri.add_triplet(i - 1, i, -1.);
+            block.add_triplet(i, i - 1, -1.);
+            block.add_triplet(i - 1, i, -1.);
+        }
+    }
+    for j in 0..n2 {
+        let row = n1 + j;
+        tri.add_triplet(row, 2 * j, 1.);
+        tri.add_triplet(2 * j, row, 1.);
+        tri.add_triplet(row, 2 * j + 1, -1.);
+        tri.add_triplet(2 * j + 1, row, -1.);
+        tri.add_triplet(row, row, -0.1);
+        // C + B diag(K)^{-1} B^T, the diagonal approximation of the Schur complement
+        block.add_triplet(row, row, 0.1 + 2. / 2.01);
+    }
+    let a: sprs::CsMat<f64> = tri.to_csr();
+    let rhs: Vec<f64> = (0..n).map(|i| ((i * 7) % 11) as f64 - 5.).collect();
+
+    let mut x = vec![0_f64; n];
+    let (its_plain, _) = MinRes::new(&a, n).solve(&rhs, &mut x, 5000, 1E-10).unwrap();
+
+    // the block diagonal preconditioner is positive definite, and IC(0) of its tridiagonal
+    // block is exact
+    let block: sprs::CsMat<f64> = block.to_csr();
+    let ic = IC0::new(block.view()).unwrap();
+    let mut x = vec![0_f64; n];
+    let (its, res) = MinRes::new(&a, n)
+        .precond_solve(&ic, &rhs, &mut x, 5000, 1E-10)
+        .unwrap();
+    println!("MINRES: {} iterations, with IC(0): {}", its_plain, its);
+    assert!(res <= 1E-10);
+    assert!(4 * its < its_plain);
+
+    let mut r = vec![0_f64; n];
+    a.mul_vec(&x, &mut r);
+    let err = r
+        .iter()
+        .zip(rhs.iter())
+        .fold(0_f64, |acc, (u, v)| acc.max((u - v).abs()));
+    assert!(err <= 1E-7);
+}
+
+#[test]
+fn symmlq_symmetric_indefinite() {
+    use sprsolve::{MatVecMul, MinRes, SymmLQ};
+
+    // symmetric tridiagonal matrix with diagonal entries of both signs
+    let n = 40;
+    let mut tri = sprs::TriMat::<f64>::new((n, n));
+    for i in 0..n {
+        let d = 2. + (i % 3) as f64;
+        tri.add_triplet(i, i, if i % 2 == 0 { d } else { -d });
+        if i > 0 {
+            tri.add_triplet(i, i - 1, 0.5);
+            tri.add_triplet(i - 1, i, 0.5);
+        }
+    }
+    let a: sprs::CsMat<f64> = tri.to_csr();
+    let rhs: Vec<f64> = (0..n).map(|i| (i as f64 * 0.3).sin() + 1.).collect();
+
+    let mut x = vec![0_f64; n];
+    let (its, res) = SymmLQ::new(&a, n).solve(&rhs, &mut x, 500, 1E-10).unwrap();
+    assert!(its > 0 && res <= 1E-10);
+    let mut x_minres = vec![0_f64; n];
+    MinRes::new(&a, n)
+        .solve(&rhs, &mut x_minres, 500, 1E-10)
+        .unwrap();
+    for (u, v) in x.iter().zip(x_minres.iter()) {
+        approx::assert_abs_diff_eq!(u, v, epsilon = 1E-8);
+    }
+    // the estimated residual is the true one
+    let mut r = vec![0_f64; n];
+    a.mul_vec(&x, &mut r);
+    let true_res = r
+        .iter()
+        .zip(rhs.iter())
+        .fold(0_f64, |acc, (u, v)| acc + (u - v) * (u - v))
+        .sqrt();
+    let rhs_norm = rhs.iter().fold(0_f64, |acc, v| acc + v * v).sqrt();
+    approx::assert_relative_eq!(true_res / rhs_norm, res, max_relative = 1E-3);
+
+    // a nonzero initial guess and a complex Hermitian matrix
+    use num_complex::Complex64;
+    let mut tri = sprs::TriMat::<Complex64>::new((n, n));
+    for i in 0..n {
+        tri.add_triplet(i, i, Complex64::new(if i % 2 == 0 { 3. } else { -2. }, 0.));
+        if i > 0 {
+            tri.add_triplet(i, i - 1, Complex64::new(0.5, 0.5));
+            tri.add_triplet(i - 1, i, Complex64::new(0.5, -0.5));
+        }
+    }
+    let a: sprs::CsMat<Complex64> = tri.to_csr();
+    let x_ref: Vec<Complex64> = (0..n)
+        .map(|i| Complex64::new((i as f64).cos(), (i as f64 * 0.5).sin()))
+        .collect();
+    let mut b = vec![Complex64::new(0., 0.); n];
+    a.mul_vec(&x_ref, &mut b);
+    let mut x = vec![Complex64::new(1., -1.); n];
+    SymmLQ::new(&a, n).solve(&b, &mut x, 500, 1E-12).unwrap();
+    for (u, v) in x.iter().zip(x_ref.iter()) {
+        approx::assert_abs_diff_eq!(u.re, v.re, epsilon = 1E-9);
+        approx::assert_abs_diff_eq!(u.im, v.im, epsilon = 1E-9);
+    }
+}
+
+#[test]
+fn minres_on_lanczos_matches_reference() {
+    use num_complex::Complex64;
+
+    // symmetric indefinite tridiagonal matrix, with a nonzero initial guess
+    let n = 60;
+    let mut tri = sprs::TriMat::<f64>::new((n, n));
+    for i in 0..n {
+        tri.add_triplet(
+            i,
+            i,
+            if i % 3 == 0 {
+                -1.5
+            } else {
+                2. + (i % 5) as f64
+            },
+        );
+        if i > 0 {
+            tri.add_triplet(i, i - 1, 0.7);
+            tri.add_triplet(i - 1, i, 0.7);
+        }
+    }
+    let a: sprs::CsMat<f64> = tri.to_csr();
+    let rhs: Vec<f64> = (0..n).map(|i| (i as f64 * 0.4).cos()).collect();
+    let x0: Vec<f64> = (0..n).map(|i| (i % 4) as f64 * 0.1).collect();
+    compare_with_reference(&a, &rhs, &x0);
+
+    // complex Hermitian matrix, where the Givens rotations carry a phase
+    let mut tri = sprs::TriMat::<Complex64>::new((n, n));
+    for i in 0..n {
+        tri.add_triplet(i, i, Complex64::new(if i % 2 == 0 { 3. } else { -2. }, 0.));
+        if i > 0 {
+            tri.add_triplet(i, i - 1, Complex64::new(0.5, 0.5));
+            tri.add_triplet(i - 1, i, Complex64::new(0.5, -0.5));
+        }
+    }
+    let a: sprs::CsMat<Complex64> = tri.to_csr();
+    let rhs: Vec<Complex64> = (0..n)
+        .map(|i| Complex64::new((i as f64).sin(), 1.))
+        .collect();
+    let x0 = vec![Complex64::new(0.2, -0.1); n];
+    compare_with_reference(&a, &rhs, &x0);
+}
+
+#[test]
+fn lanczos_basis() {
+    use sprsolve::{Lanczos, MatVecMul};
+
+    let (lap, rhs) = grid_laplacian((8, 8));
+    let n = 64;
+    let mut lanczos = Lanczos::new(&lap, n);
+    let beta0 = lanczos.start(&rhs);
+    approx::assert_relative_eq!(beta0, rhs.iter().map(|v| v * v).sum::<f64>().sqrt());
+
+    let mut basis = Vec::new();
+    let mut av = vec![0_f64; n];
+    for _ in 0..10 {
+        let (alpha, beta) = lanczos.step();
+        let q = lanczos.vector().to_vec();
+        // alpha_k is the Rayleigh quotient of q_k
+        lap.mul_vec(&q, &mut av);
+        let rq: f64 = q.iter().zip(av.iter()).map(|(u, v)| u * v).sum();
+        approx::assert_relative_eq!(alpha, rq, max_relative = 1E-12);
+        assert!(beta > 0.);
+        basis.push(q);
+    }
+    // the Lanczos vectors are orthonormal
+    for (i, u) in basis.iter().enumerate() {
+        for (j, v) in basis.iter().enumerate() {
+            let d: f64 = u.iter().zip(v.iter()).map(|(a, b)| a * b).sum();
+            let expected = if i == j { 1. } else { 0. };
+            approx::assert_abs_diff_eq!(d, expected, epsilon = 1E-10);
+        }
+    }
+
+    // an operator without a shape is taken at the given size
+    let op = ProductsOnly(&lap);
+    let mut plain = Lanczos::new(&op, n);
+    plain.start(&rhs);
+    for q in basis.iter() {
+        plain.step();
+        assert_eq!(plain.vector(), q.as_slice());
+    }
+}
+
+/// Solve with `MinRes` and with the reference below, from the initial guess `x0`, and check
+/// that the residual histories and the solutions are identical.
+fn compare_with_reference<T: cauchy::Scalar, M: sprsolve::MatVecMul<T>>(
+    a: &M,
+    rhs: &[T],
+    x0: &[T],
+) {
+    let n = rhs.len();
+    let tol = T::real(1E-10);
+    let mut x = x0.to_vec();
+    let (its, res, history) = sprsolve::MinRes::new(a, n)
+        .solve_with_history(rhs, &mut x, 500, tol)
+        .unwrap();
+    let mut x_ref = x0.to_vec();
+    let (its_ref, history_ref) = reference_minres(a, rhs, &mut x_ref, 500, tol);
+    assert_eq!(its, its_ref);
+    assert_eq!(res, *history_ref.last().unwrap());
+    assert_eq!(history, history_ref);
+    assert_eq!(x, x_ref);
+}
+
+/// MINRES with the Lanczos recurrence inlined, as it was implemented before the recurrence
+/// was factored out in `Lanczos`. It returns the iteration number and the residual history.
+#[allow(clippy::many_single_char_names)]
+fn reference_minres<T: cauchy::Scalar, M: sprsolve::MatVecMul<T>>(
+    a: &M,
+    rhs: &[T],
+    x: &mut [T],
+    max_iter: usize,
+    tol: T::Real,
+) -> (usize, Vec<T::Real>) {
+    use num_traits::{Float, One, Zero};
+    use sprsolve::vecalg::rotg;
+    use sprsolve::{CpuVecOps, VecOps};
+
+    let ops = CpuVecOps;
+    let n = rhs.len();
+    let rhs_norm = ops.norm2(rhs);
+    let threshold = tol * rhs_norm;
+    let (mut c, mut c_old) = (T::one(), T::one());
+    let (mut s, mut s_old) = (T::Real::zero(), T::Real::zero());
+    let mut eta = T::one();
+    let mut v_old = vec![T::zero(); n];
+    let mut v = vec![T::zero(); n];
+    let mut v_new = rhs.to_vec();
+    let mut p_oold = vec![T::zero(); n];
+    let mut p_old = vec![T::zero(); n];
+    let mut p = vec![T::zero(); n];
+
+    a.mul_vec(x, &mut v_old);
+    ops.axpy(-T::one(), &v_old, &mut v_new);
+    let mut res_norm = ops.norm2(&v_new);
+    let mut history = vec![res_norm / rhs_norm];
+    let mut beta_new = res_norm;
+    let beta_one = beta_new;
+    ops.rscale(T::Real::one() / beta_new, &mut v_new);
+
+    for its in 0..max_iter {
+        let beta = beta_new;
+        std::mem::swap(&mut v_old, &mut v);
+        std::mem::swap(&mut v, &mut v_new);
+        let alpha = T::from_real(unsafe { a.mul_vec_rdot_unchecked(&v, &mut v_new) });
+        ops.axpy(T::from_real(-beta), &v_old, &mut v_new);
+        ops.axpy(-alpha, &v, &mut v_new);
+        beta_new = ops.norm2(&v_new);
+        ops.rscale(T::Real::one() / beta_new, &mut v_new);
+
+        let r3 = s_old * beta;
+        let tr = c_old.mul_real(beta);
+        let r2 = alpha.mul_real(s) + c * tr;
+        let r1_hat = c * alpha - tr.mul_real(s);
+        let (mut r1, mut b) = (r1_hat, T::from_real(beta_new));
+        let (cg, sg) = rotg(&mut r1, &mut b);
+        let r1_abs = r1.abs();
+        let r1_inv = T::Real::one() / r1_abs;
+        let phase = if r1_abs > T::Real::zero() {
+            r1 / T::from_real(r1_abs)
+        } else {
+            T::one()
+        };
+        c_old = c;
+        s_old = s;
+        c = phase.mul_real(cg);
+        s = (phase.conj() * sg).re();
+
+        std::mem::swap(&mut p_oold, &mut p_old);
+        std::mem::swap(&mut p_old, &mut p);
+        ops.scaled_copy(T::from_real(r1_inv), &v, &mut p);
+        ops.axpy(-r2.mul_real(r1_inv), &p_old, &mut p);
+        if its > 0 {
+            ops.axpy(T::from_real(-r3 * r1_inv), &p_oold, &mut p);
+        }
+        ops.axpy((c * eta).mul_real(beta_one), &p, x);
+
+        res_norm *= Float::abs(s);
+        history.push(res_norm / rhs_norm);
+        if res_norm < threshold {
+            return (its + 1, history);
+        }
+        eta = eta.mul_real(-s);
+    }
+    panic!("the reference MINRES did not converge");
+}
+
+/// A user operator providing only the products with the wrapped matrix, and no shape.
+struct ProductsOnly<'a>(&'a sprs::CsMat<f64>);
+
+impl<'a> sprsolve::MatVecMul<f64> for ProductsOnly<'a> {
+    fn mul_vec(&self, v_in: &[f64], v_out: &mut [f64]) {
+        sprsolve::MatVecMul::mul_vec(self.0, v_in, v_out)
+    }
+
+    fn mul_vec_dot(&self, v_in: &[f64], v_out: &mut [f64]) -> f64 {
+        sprsolve::MatVecMul::mul_vec_dot(self.0, v_in, v_out)
+    }
+
+    unsafe fn mul_vec_unchecked(&self, v_in: &[f64], v_out: &mut [f64]) {
+        sprsolve::MatVecMul::mul_vec_unchecked(self.0, v_in, v_out)
+    }
+
+    unsafe fn mul_vec_dot_unchecked(&self, v_in: &[f64], v_out: &mut [f64]) -> f64 {
+        sprsolve::MatVecMul::mul_vec_dot_unchecked(self.0, v_in, v_out)
+    }
+}
+
 fn simple(shape: (usize, usize)) -> (sprs::CsMat<f64>, Vec<f64>) {
     let (rows, cols) = shape;
     let n = rows * cols;
@@ -0,0 +1,482 @@
+#[test]
+fn deflated_neumann_laplacian() {
+    let (rows, cols) = (12, 12);
+    let n = rows * cols;
+    let lap = neumann_laplacian((rows, cols));
+
+    // a consistent right-hand side, orthogonal to the null space
+    let mut rhs: Vec<f64> = (0..n).map(|i| ((i * 7) % 11) as f64 - 5.).collect();
+    let ones = vec![1_f64; n];
+    let op = sprsolve::DeflatedOp::new(&lap, &ones).unwrap();
+    op.project(&mut rhs);
+
+    let mut x = vec![0_f64; n];
+    let (iters, res) = sprsolve::CG::new(&op, n)
+        .solve(&rhs, &mut x, 1000, 1E-12)
+        .unwrap();
+    println!(
+        "Solved system in {} iterations with residual {}",
+        iters, res
+    );
+
+    // x solves the original singular system and has zero mean
+    let mut ax = vec![0_f64; n];
+    sprsolve::MatVecMul::mul_vec(&lap, &x, &mut ax);
+    let rhs_norm = rhs.iter().fold(0_f64, |acc, v| acc + v * v).sqrt();
+    let err = ax
+        .iter()
+        .zip(rhs.iter())
+        .fold(0_f64, |acc, (u, v)| acc + (u - v) * (u - v))
+        .sqrt();
+    assert!(err <= 1E-10 * rhs_norm);
+    approx::assert_abs_diff_eq!(x.iter().sum::<f64>(), 0., epsilon = 1E-9);
+}
+
+#[test]
+fn norm_estimate_diag() {
+    let n = 40;
+    let mut tri = sprs::TriMat::<f64>::new((n, n));
+    for i in 0..n {
+        // the largest magnitude is the negative entry -2n
+        let d = if i == n / 2 {
+            -2. * n as f64
+        } else {
+            (i + 1) as f64
+        };
+        tri.add_triplet(i, i, d);
+    }
+    let mat = tri.to_csr();
+    let est = sprsolve::operator_norm_estimate(&mat, n, 50).unwrap();
+    assert!(est <= 2. * n as f64 * (1. + 1E-12));
+    approx::assert_relative_eq!(est, 2. * n as f64, max_relative = 1E-6);
+
+    // a non-normal Jordan block, whose spectral radius 1 is far below its norm
+    let mut tri = sprs::TriMat::<f64>::new((2, 2));
+    tri.add_triplet(0, 0, 1.);
+    tri.add_triplet(0, 1, 100.);
+    tri.add_triplet(1, 1, 1.);
+    let jordan: sprs::CsMat<f64> = tri.to_csr();
+    // the largest singular value of [1 c; 0 1] is (c + sqrt(c^2 + 4)) / 2
+    let norm = (100. + (100_f64 * 100. + 4.).sqrt()) / 2.;
+    let est = sprsolve::operator_norm_estimate(&jordan, 2, 50).unwrap();
+    assert!(est <= norm * (1. + 1E-12));
+    approx::assert_relative_eq!(est, norm, max_relative = 1E-10);
+}
+
+#[test]
+fn power_iteration_diag() {
+    let n = 40;
+    let k = 7;
+    let mut tri = sprs::TriMat::<f64>::new((n, n));
+    for i in 0..n {
+        // the dominant eigenvalue is the negative entry -10, well separated from the rest
+        let d = if i == k {
+            -10.
+        } else {
+            5. * (i + 1) as f64 / n as f64
+        };
+        tri.add_triplet(i, i, d);
+    }
+    let mat = tri.to_csr();
+
+    let (lambda, v) = sprsolve::power_iteration(&mat, n, 200, 1E-14);
+    approx::assert_relative_eq!(lambda, 10., max_relative = 1E-12);
+    approx::assert_abs_diff_eq!(v.iter().map(|t| t * t).sum::<f64>(), 1., epsilon = 1E-12);
+    approx::assert_abs_diff_eq!(v[k].abs(), 1., epsilon = 1E-6);
+
+    // a loose tolerance stops earlier, with a less accurate estimate
+    let (rough, _) = sprsolve::power_iteration(&mat, n, 200, 1E-2);
+    assert!((rough - 10.).abs() <= 1. && (rough - 10.).abs() > (lambda - 10.).abs());
+
+    // the complex case, with a dominant eigenvalue 3i
+    let mut tri = sprs::TriMat::<num_complex::Complex64>::new((n, n));
+    for i in 0..n {
+        let d = if i == k {
+            num_complex::Complex64::new(0., 3.)
+        } else {
+            num_complex::Complex64::new((i % 3) as f64 * 0.5, 0.)
+        };
+        tri.add_triplet(i, i, d);
+    }
+    let mat = tri.to_csr();
+    let (lambda, v) = sprsolve::power_iteration(&mat, n, 200, 1E-14);
+    approx::assert_relative_eq!(lambda, 3., max_relative = 1E-12);
+    approx::assert_abs_diff_eq!(v[k].norm(), 1., epsilon = 1E-6);
+
+    // an operator without a shape is taken at the given size
+    let diag = UserDiag((0..n).map(|i| if i == k { -10. } else { 1. }).collect());
+    let (lambda, _) = sprsolve::power_iteration(&diag, n, 200, 1E-14);
+    approx::assert_relative_eq!(lambda, 10., max_relative = 1E-12);
+}
+
+#[test]
+fn lanczos_eigen_bounds_tighten() {
+    use sprsolve::lanczos_eigen_bounds;
+
+    // 1D Dirichlet Laplacian, with eigenvalues 2 - 2 cos(k pi / (n + 1))
+    let n = 200;
+    let mut tri = sprs::TriMat::<f64>::new((n, n));
+    for i in 0..n {
+        tri.add_triplet(i, i, 2.);
+        if i + 1 < n {
+            tri.add_triplet(i, i + 1, -1.);
+            tri.add_triplet(i + 1, i, -1.);
+        }
+    }
+    let mat = tri.to_csr();
+    let eig = |k: usize| 2. - 2. * (k as f64 * std::f64::consts::PI / (n + 1) as f64).cos();
+    let (lambda_min, lambda_max) = (eig(1), eig(n));
+
+    let mut errors = (f64::INFINITY, f64::INFINITY);
+    let mut first = None;
+    for &steps in [5, 10, 20, 40, 80].iter() {
+        let (lo, hi) = lanczos_eigen_bounds(&mat, n, steps);
+        println!("{} steps: [{}, {}]", steps, lo, hi);
+        // the Ritz values lie inside the spectrum
+        assert!(lo >= lambda_min * (1. - 1E-12) && hi <= lambda_max * (1. + 1E-12));
+        let (err_lo, err_hi) = (lo - lambda_min, lambda_max - hi);
+        assert!(err_lo <= errors.0 && err_hi <= errors.1);
+        errors = (err_lo, err_hi);
+        first.get_or_insert(errors);
+    }
+    // the ends of the spectrum are clustered, hence the slow convergence
+    let first = first.unwrap();
+    assert!(errors.0 * 20. <= first.0 && errors.1 * 20. <= first.1);
+    assert!(errors.1 <= 1E-3 * lambda_max);
+
+    // the process stops on an invariant subspace with the exact bounds
+    let n = 50;
+    let mut tri = sprs::TriMat::<f64>::new((n, n));
+    for i in 0..n {
+        tri.add_triplet(i, i, (i + 1) as f64);
+    }
+    let mat = tri.to_csr();
+    let (lo, hi) = lanczos_eigen_bounds(&mat, n, n);
+    approx::assert_abs_diff_eq!(lo, 1., epsilon = 1E-8);
+    approx::assert_abs_diff_eq!(hi, n as f64, epsilon = 1E-8);
+}
+
+#[test]
+fn shifted_operator() {
+    use sprsolve::{MatVecMul, Shifted};
+
+    let (rows, cols) = (10, 10);
+    let n = rows * cols;
+    let lap = neumann_laplacian((rows, cols));
+    let sigma = -0.5;
+
+    // A - sigma I, formed explicitly
+    let mut tri = sprs::TriMat::<f64>::new((n, n));
+    for (&v, (i, j)) in lap.iter() {
+        tri.add_triplet(i, j, v);
+    }
+    for i in 0..n {
+        tri.add_triplet(i, i, -sigma);
+    }
+    let explicit: sprs::CsMat<f64> = tri.to_csr();
+    let shifted = Shifted::new(&lap, sigma);
+
+    let v: Vec<f64> = (0..n).map(|i| (i as f64 * 0.7).sin()).collect();
+    let mut y = vec![0_f64; n];
+    let mut y_ref = vec![0_f64; n];
+    let d = shifted.mul_vec_dot(&v, &mut y);
+    let d_ref = explicit.mul_vec_dot(&v, &mut y_ref);
+    approx::assert_relative_eq!(d, d_ref, max_relative = 1E-12);
+    for (a, b) in y.iter().zip(y_ref.iter()) {
+        approx::assert_abs_diff_eq!(a, b, epsilon = 1E-12);
+    }
+
+    let rhs: Vec<f64> = (0..n).map(|i| ((i * 3) % 7) as f64 - 3.).collect();
+    let mut x = vec![0_f64; n];
+    sprsolve::CG::new(&shifted, n)
+        .solve(&rhs, &mut x, 1000, 1E-12)
+        .unwrap();
+    let mut x_ref = vec![0_f64; n];
+    sprsolve::CG::new(&explicit, n)
+        .solve(&rhs, &mut x_ref, 1000, 1E-12)
+        .unwrap();
+    for (a, b) in x.iter().zip(x_ref.iter()) {
+        approx::assert_abs_diff_eq!(a, b, epsilon = 1E-9);
+    }
+}
+
+#[test]
+fn sum_of_scaled_operators() {
+    use sprsolve::{MatVecMul, ScaledOp, SumOp};
+
+    let (rows, cols) = (8, 8);
+    let n = rows * cols;
+    let k = neumann_laplacian((rows, cols));
+    let mut tri = sprs::TriMat::<f64>::new((n, n));
+    for i in 0..n {
+        tri.add_triplet(i, i, 1. + (i % 3) as f64);
+    }
+    let m: sprs::CsMat<f64> = tri.to_csr();
+    let (alpha, beta) = (2., -0.5);
+
+    // alpha * K + beta * M, assembled
+    let mut tri = sprs::TriMat::<f64>::new((n, n));
+    for (&v, (i, j)) in k.iter() {
+        tri.add_triplet(i, j, alpha * v);
+    }
+    for (&v, (i, j)) in m.iter() {
+        tri.add_triplet(i, j, beta * v);
+    }
+    let explicit: sprs::CsMat<f64> = tri.to_csr();
+
+    let sk = ScaledOp::new(&k, alpha);
+    let sm = ScaledOp::new(&m, beta);
+    let op = SumOp::new(&sk, &sm, n);
+
+    let u: Vec<f64> = (0..n).map(|i| (i as f64 * 0.3).sin()).collect();
+    let v: Vec<f64> = (0..n).map(|i| (i as f64 * 0.2).cos()).collect();
+    let mut y = vec![0_f64; n];
+    let mut y_ref = vec![0_f64; n];
+    let d = op.mul_vec_dot(&u, &mut y);
+    let d_ref = explicit.mul_vec_dot(&u, &mut y_ref);
+    approx::assert_relative_eq!(d, d_ref, max_relative = 1E-12);
+    for (a, b) in y.iter().zip(y_ref.iter()) {
+        approx::assert_abs_diff_eq!(a, b, epsilon = 1E-12);
+    }
+
+    // linearity: op(2u + 3v) = 2 op(u) + 3 op(v)
+    let w: Vec<f64> = u
+        .iter()
+        .zip(v.iter())
+        .map(|(a, b)| 2. * a + 3. * b)
+        .collect();
+    let mut ow = vec![0_f64; n];
+    let mut ou = vec![0_f64; n];
+    let mut ov = vec![0_f64; n];
+    op.mul_vec(&w, &mut ow);
+    op.mul_vec(&u, &mut ou);
+    op.mul_vec(&v, &mut ov);
+    for i in 0..n {
+        approx::assert_abs_diff_eq!(ow[i], 2. * ou[i] + 3. * ov[i], epsilon = 1E-12);
+    }
+}
+
+#[test]
+fn matrix_free_stencil() {
+    use sprsolve::{BiCGStab, MatFree, CG};
+
+    // 1D stencil [-1, 2.5, -1] with zero Dirichlet boundary
+    let n = 200;
+    let stencil = |v: &[f64], out: &mut [f64]| {
+        for i in 0..v.len() {
+            let left = if i > 0 { v[i - 1] } else { 0. };
+            let right = if i + 1 < v.len() { v[i + 1] } else { 0. };
+            out[i] = 2.5 * v[i] - left - right;
+        }
+    };
+    let op = MatFree::new(n, stencil);
+    let rhs: Vec<f64> = (0..n).map(|i| (i as f64 * 0.1).sin()).collect();
+
+    let mut x = vec![0_f64; n];
+    let (its, _) = CG::new(&op, n).solve(&rhs, &mut x, 1000, 1E-12).unwrap();
+    println!("matrix-free CG: {} iterations", its);
+    let mut r = vec![0_f64; n];
+    stencil(&x, &mut r);
+    for (a, b) in r.iter().zip(rhs.iter()) {
+        approx::assert_abs_diff_eq!(a, b, epsilon = 1E-10);
+    }
+
+    let mut y = vec![0_f64; n];
+    BiCGStab::new(&op, n)
+        .solve(&rhs, &mut y, 1000, 1E-12)
+        .unwrap();
+    for (a, b) in x.iter().zip(y.iter()) {
+        approx::assert_abs_diff_eq!(a, b, epsilon = 1E-9);
+    }
+}
+
+#[test]
+fn operator_shape() {
+    use sprsolve::precond::{Identity, Jacobi};
+    use sprsolve::{BiCGStab, DenseMat, MatFree, MatVecMul, ScaledOp, Shifted, SumOp};
+
+    // generic code sizing the solver from the operator alone
+    fn solve<M: MatVecMul<f64>>(op: &M, rhs: &[f64]) -> Vec<f64> {
+        let n = op.size().unwrap();
+        let mut x = vec![0_f64; n];
+        BiCGStab::new(op, n).solve(rhs, &mut x, 500, 1E-10).unwrap();
+        x
+    }
+
+    let (rows, cols) = (6, 5);
+    let n = rows * cols;
+    let k = neumann_laplacian((rows, cols));
+    assert_eq!(MatVecMul::<f64>::shape(&k), Some((n, n)));
+    assert_eq!(MatVecMul::<f64>::size(&k.view()), Some(n));
+
+    let shifted = Shifted::new(&k, -1.);
+    let scaled = ScaledOp::new(&k, 2.);
+    let sum = SumOp::new(&shifted, &scaled, n);
+    assert_eq!(shifted.size(), Some(n));
+    assert_eq!(scaled.size(), Some(n));
+    assert_eq!(sum.shape(), Some((n, n)));
+    assert_eq!(Jacobi::new(k.view()).unwrap().size(), Some(n));
+    assert_eq!(
+        MatFree::new(n, |v: &[f64], out: &mut [f64]| out.copy_from_slice(v)).shape(),
+        Some((n, n))
+    );
+    assert_eq!(MatVecMul::<f64>::shape(&Identity), None);
+
+    // 3 K + I is positive definite
+    let rhs: Vec<f64> = (0..n).map(|i| (i as f64 * 0.4).cos()).collect();
+    let x = solve(&sum, &rhs);
+    let mut r = vec![0_f64; n];
+    sum.mul_vec(&x, &mut r);
+    for (a, b) in r.iter().zip(rhs.iter()) {
+        approx::assert_abs_diff_eq!(a, b, epsilon = 1E-8);
+    }
+
+    let dense = DenseMat::from_row_major(2, 3, vec![1_f64; 6]).unwrap();
+    assert_eq!(dense.shape(), Some((2, 3)));
+    assert_eq!(dense.size(), None);
+}
+
+#[test]
+fn transposed_operator() {
+    use sprsolve::{BiCGStab, DenseMat, MatVecMul, Transpose};
+
+    // 1D convection-diffusion, which is not symmetric
+    let n = 50;
+    let mut tri = sprs::TriMat::<f64>::new((n, n));
+    let mut tri_t = sprs::TriMat::<f64>::new((n, n));
+    for i in 0..n {
+        let mut add = |i, j, v| {
+            tri.add_triplet(i, j, v);
+            tri_t.add_triplet(j, i, v);
+        };
+        add(i, i, 4.);
+        if i > 0 {
+            add(i, i - 1, -1.5);
+        }
+        if i < n - 1 {
+            add(i, i + 1, -0.5);
+        }
+    }
+    let a: sprs::CsMat<f64> = tri.to_csr();
+    let explicit: sprs::CsMat<f64> = tri_t.to_csr();
+    let a_t = Transpose(&a);
+
+    let v: Vec<f64> = (0..n).map(|i| (i as f64 * 0.7).sin()).collect();
+    let mut y = vec![0_f64; n];
+    let mut y_ref = vec![0_f64; n];
+    a_t.mul_vec(&v, &mut y);
+    explicit.mul_vec(&v, &mut y_ref);
+    assert_eq!(y, y_ref);
+    a_t.mul_vec_trans(&v, &mut y).unwrap();
+    a.mul_vec(&v, &mut y_ref);
+    assert_eq!(y, y_ref);
+
+    let rhs: Vec<f64> = (0..n).map(|i| ((i * 3) % 7) as f64 - 3.).collect();
+    let mut x = vec![0_f64; n];
+    BiCGStab::new(&a_t, n)
+        .solve(&rhs, &mut x, 1000, 1E-12)
+        .unwrap();
+    let mut x_ref = vec![0_f64; n];
+    BiCGStab::new(&explicit, n)
+        .solve(&rhs, &mut x_ref, 1000, 1E-12)
+        .unwrap();
+    for (a, b) in x.iter().zip(x_ref.iter()) {
+        approx::assert_abs_diff_eq!(a, b, epsilon = 1E-9);
+    }
+
+    let dense = DenseMat::from_row_major(2, 3, vec![1_f64; 6]).unwrap();
+    assert_eq!(Transpose(&dense).shape(), Some((3, 2)));
+}
+
+#[test]
+fn square_solver_from_op() {
+    use sprsolve::{error::SolverError, BiCGStab, DenseMat, MinRes, CG, GCR};
+
+    // a rectangular operator is rejected instead of slicing a mismatched workspace
+    let dense = DenseMat::from_row_major(2, 3, vec![1_f64; 6]).unwrap();
+    assert!(matches!(
+        CG::from_op(&dense),
+        Err(SolverError::IncompatibleMatrixFormat(_))
+    ));
+    assert!(matches!(
+        MinRes::from_op(&dense),
+        Err(SolverError::IncompatibleMatrixFormat(_))
+    ));
+    assert!(matches!(
+        GCR::from_op(&dense, 5),
+        Err(SolverError::IncompatibleMatrixFormat(_))
+    ));
+
+    let (rows, cols) = (6, 5);
+    let n = rows * cols;
+    let k = neumann_laplacian((rows, cols));
+    let shifted = sprsolve::Shifted::new(&k, -1.); // K + I
+    let rhs: Vec<f64> = (0..n).map(|i| (i as f64 * 0.4).cos()).collect();
+    let mut x = vec![0_f64; n];
+    let ret = CG::from_op(&shifted)
+        .unwrap()
+        .solve(&rhs, &mut x, 500, 1E-10)
+        .unwrap();
+    let mut y = vec![0_f64; n];
+    let ret_new = CG::new(&shifted, n)
+        .solve(&rhs, &mut y, 500, 1E-10)
+        .unwrap();
+    assert_eq!(ret, ret_new);
+    assert_eq!(x, y);
+    assert!(BiCGStab::from_op(&shifted).is_ok());
+}
+
+/// 2D grid laplacian with Neumann boundary, whose null space is spanned by the constant vector.
+/// A diagonal operator defined by the user, which provides only the products and no shape.
+struct UserDiag(Vec<f64>);
+
+impl sprsolve::MatVecMul<f64> for UserDiag {
+    fn mul_vec(&self, v_in: &[f64], v_out: &mut [f64]) {
+        assert!(v_in.len() == self.0.len() && v_out.len() == self.0.len());
+        unsafe { self.mul_vec_unchecked(v_in, v_out) }
+    }
+
+    fn mul_vec_dot(&self, v_in: &[f64], v_out: &mut [f64]) -> f64 {
+        self.mul_vec(v_in, v_out);
+        v_in.iter().zip(v_out.iter()).map(|(a, b)| a * b).sum()
+    }
+
+    unsafe fn mul_vec_unchecked(&self, v_in: &[f64], v_out: &mut [f64]) {
+        for ((o, i), d) in v_out.iter_mut().zip(v_in.iter()).zip(self.0.iter()) {
+            *o = d * i;
+        }
+    }
+
+    unsafe fn mul_vec_dot_unchecked(&self, v_in: &[f64], v_out: &mut [f64]) -> f64 {
+        self.mul_vec_dot(v_in, v_out)
+    }
+}
+
+fn neumann_laplacian(shape: (usize, usize)) -> sprs::CsMat<f64> {
+    let (rows, cols) = shape;
+    let n = rows * cols;
+    let mut ret_a = sprs::TriMat::<f64>::new((n, n));
+    for i in 0..rows {
+        for j in 0..cols {
+            let vid = i * cols + j;
+            let mut neighbors = Vec::with_capacity(4);
+            if i > 0 {
+                neighbors.push(vid - cols);
+            }
+            if j > 0 {
+                neighbors.push(vid - 1);
+            }
+            if i < rows - 1 {
+                neighbors.push(vid + cols);
+            }
+            if j < cols - 1 {
+                neighbors.push(vid + 1);
+            }
+            ret_a.add_triplet(vid, vid, neighbors.len() as f64);
+            for tid in neighbors {
+                ret_a.add_triplet(vid, tid, -1.);
+            }
+        }
+    }
+    ret_a.to_csr()
+}
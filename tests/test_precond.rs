@@ -0,0 +1,308 @@
+#[test]
+fn gauss_seidel_precond() {
+    use sprsolve::precond::GaussSeidelPrecond;
+
+    let (rows, cols) = (16, 16);
+    let (lap, rhs) = neg_grid_laplacian((rows, cols));
+    let n = rows * cols;
+    let check = |x: &[f64]| {
+        let mut ax = vec![0_f64; n];
+        sprsolve::MatVecMul::mul_vec(&lap, x, &mut ax);
+        let res = ax
+            .iter()
+            .zip(rhs.iter())
+            .fold(0_f64, |acc, (a, b)| acc + (a - b) * (a - b));
+        let b = rhs.iter().fold(0_f64, |acc, b| acc + b * b);
+        assert!((res / b).sqrt() <= 1E-9);
+    };
+
+    let mut x = vec![0_f64; n];
+    let (its, _) = sprsolve::CG::new(&lap, n)
+        .solve(&rhs, &mut x, 1000, 1E-10)
+        .unwrap();
+
+    let sgs = GaussSeidelPrecond::new(lap.view(), true).unwrap();
+    let mut x = vec![0_f64; n];
+    let (its_pc, _) = sprsolve::CG::new(&lap, n)
+        .precond_solve(&sgs, &rhs, &mut x, 1000, 1E-10)
+        .unwrap();
+    println!("CG: {} iterations, SGS-CG: {} iterations", its, its_pc);
+    assert!(its_pc < its);
+    check(&x);
+
+    let mut x = vec![0_f64; n];
+    let (its, _) = sprsolve::BiCGStab::new(&lap, n)
+        .solve(&rhs, &mut x, 1000, 1E-10)
+        .unwrap();
+    let gs = GaussSeidelPrecond::new(lap.view(), false).unwrap();
+    let mut x = vec![0_f64; n];
+    let (its_pc, _) = sprsolve::BiCGStab::new(&lap, n)
+        .precond_solve(&gs, &rhs, &mut x, 1000, 1E-10)
+        .unwrap();
+    println!(
+        "BiCGStab: {} iterations, GS-BiCGStab: {} iterations",
+        its, its_pc
+    );
+    assert!(its_pc < its);
+    check(&x);
+}
+
+#[test]
+fn ic0_precond() {
+    use num_complex::Complex64;
+    use sprsolve::{precond::IC0, MatVecMul};
+
+    let (rows, cols) = (16, 16);
+    let (lap, rhs) = neg_grid_laplacian((rows, cols));
+    let n = rows * cols;
+
+    let mut x = vec![0_f64; n];
+    let (its, _) = sprsolve::CG::new(&lap, n)
+        .solve(&rhs, &mut x, 1000, 1E-10)
+        .unwrap();
+    let ic = IC0::new(lap.view()).unwrap();
+    let mut x_pc = vec![0_f64; n];
+    let (its_pc, _) = sprsolve::CG::new(&lap, n)
+        .precond_solve(&ic, &rhs, &mut x_pc, 1000, 1E-10)
+        .unwrap();
+    println!("CG: {} iterations, IC(0)-CG: {} iterations", its, its_pc);
+    assert!(its_pc < its);
+    let x_norm = x.iter().fold(0_f64, |acc, v| acc + v * v).sqrt();
+    for (u, v) in x_pc.iter().zip(x.iter()) {
+        approx::assert_abs_diff_eq!(u, v, epsilon = 1E-8 * x_norm);
+    }
+
+    // IC(0) of a Hermitian tridiagonal matrix is its exact Cholesky factorization
+    let m = 30;
+    let mut tri = sprs::TriMat::<Complex64>::new((m, m));
+    for i in 0..m {
+        tri.add_triplet(i, i, Complex64::new(4., 0.));
+        if i > 0 {
+            tri.add_triplet(i, i - 1, Complex64::new(-1., 0.5));
+            tri.add_triplet(i - 1, i, Complex64::new(-1., -0.5));
+        }
+    }
+    let mat = tri.to_csr();
+    let ic = IC0::new(mat.view()).unwrap();
+    let x: Vec<Complex64> = (0..m)
+        .map(|i| Complex64::new((i as f64).sin(), (i as f64).cos()))
+        .collect();
+    let mut b = vec![Complex64::new(0., 0.); m];
+    let mut z = vec![Complex64::new(0., 0.); m];
+    mat.mul_vec(&x, &mut b);
+    ic.mul_vec(&b, &mut z);
+    for (u, v) in z.iter().zip(x.iter()) {
+        approx::assert_abs_diff_eq!(u.re, v.re, epsilon = 1E-12);
+        approx::assert_abs_diff_eq!(u.im, v.im, epsilon = 1E-12);
+    }
+
+    // the grid laplacian with the opposite sign is negative definite
+    let neg = lap.map(|v| -v);
+    assert!(matches!(
+        IC0::new(neg.view()),
+        Err(sprsolve::error::SolverError::BreakDown(0))
+    ));
+}
+
+#[test]
+fn ssor_precond() {
+    use sprsolve::precond::{GaussSeidelPrecond, SSOR};
+    use sprsolve::MatVecMul;
+
+    let (rows, cols) = (16, 16);
+    let (lap, rhs) = neg_grid_laplacian((rows, cols));
+    let n = rows * cols;
+
+    // omega = 1 is symmetric Gauss-Seidel
+    let sgs = GaussSeidelPrecond::new(lap.view(), true).unwrap();
+    let ssor = SSOR::new(lap.view(), 1.).unwrap();
+    let (mut y, mut z) = (vec![0_f64; n], vec![0_f64; n]);
+    sgs.mul_vec(&rhs, &mut y);
+    ssor.mul_vec(&rhs, &mut z);
+    assert_eq!(y, z);
+
+    // M^{-1} b is one SSOR iteration from a zero initial guess
+    let ssor = SSOR::new(lap.view(), 1.5).unwrap();
+    assert_eq!(ssor.omega(), 1.5);
+    ssor.mul_vec(&rhs, &mut z);
+    let mut x = vec![0_f64; n];
+    let _ = sprsolve::SSOR::new(lap.view())
+        .unwrap()
+        .solve(&rhs, &mut x, 1.5, 1, 1E-10);
+    for (u, v) in x.iter().zip(z.iter()) {
+        approx::assert_abs_diff_eq!(u, v, epsilon = 1E-12);
+    }
+
+    // M is symmetric
+    let v: Vec<f64> = (0..n).map(|i| (i as f64 * 0.37).sin()).collect();
+    ssor.mul_vec(&v, &mut y);
+    let (vz, by) = (
+        v.iter().zip(z.iter()).map(|(a, b)| a * b).sum::<f64>(),
+        rhs.iter().zip(y.iter()).map(|(a, b)| a * b).sum::<f64>(),
+    );
+    approx::assert_relative_eq!(vz, by, max_relative = 1E-12);
+
+    let mut x = vec![0_f64; n];
+    let (its, _) = sprsolve::CG::new(&lap, n)
+        .solve(&rhs, &mut x, 1000, 1E-10)
+        .unwrap();
+    let mut x = vec![0_f64; n];
+    let (its_pc, res) = sprsolve::CG::new(&lap, n)
+        .precond_solve(&ssor, &rhs, &mut x, 1000, 1E-10)
+        .unwrap();
+    println!(
+        "CG: {} iterations, SSOR(1.5)-CG: {} iterations",
+        its, its_pc
+    );
+    assert!(its_pc < its);
+    assert!(res <= 1E-10);
+
+    assert!(SSOR::new(lap.view(), 2.).is_err());
+    assert!(SSOR::new(lap.view(), 0.).is_err());
+}
+
+#[test]
+fn neumann_precond() {
+    use sprsolve::precond::{Jacobi, NeumannPrecond, Precond};
+
+    let (rows, cols) = (16, 16);
+    let (lap, rhs) = neg_grid_laplacian((rows, cols));
+    let n = rows * cols;
+
+    // degree 0 is the Jacobi preconditioner
+    let jacobi = Jacobi::new(lap.view()).unwrap();
+    let neumann = NeumannPrecond::new(&lap, 0).unwrap();
+    let (mut z_j, mut z_n) = (vec![0_f64; n], vec![0_f64; n]);
+    jacobi.apply(&rhs, &mut z_j);
+    neumann.apply(&rhs, &mut z_n);
+    assert_eq!(z_j, z_n);
+
+    // the diagonal is 4 and the off-diagonal row sums are at most 4 in absolute value, so
+    // the series converges and a higher degree takes fewer iterations
+    let mut x_ref = vec![0_f64; n];
+    sprsolve::CG::new(&lap, n)
+        .solve(&rhs, &mut x_ref, 1000, 1E-12)
+        .unwrap();
+    let x_norm = x_ref.iter().fold(0_f64, |acc, v| acc + v * v).sqrt();
+    let mut last_its = usize::MAX;
+    for degree in [0, 1, 3].iter() {
+        let precond = NeumannPrecond::new(&lap, *degree).unwrap();
+        let mut x = vec![0_f64; n];
+        let (its, _) = sprsolve::CG::new(&lap, n)
+            .precond_solve(&precond, &rhs, &mut x, 1000, 1E-10)
+            .unwrap();
+        println!("Neumann degree {}: {} iterations", degree, its);
+        assert!(its < last_its);
+        last_its = its;
+        for (u, v) in x.iter().zip(x_ref.iter()) {
+            approx::assert_abs_diff_eq!(u, v, epsilon = 1E-8 * x_norm);
+        }
+    }
+}
+
+#[test]
+fn equilibration() {
+    use sprsolve::{
+        precond::{Equilibration, EquilibrationNorm},
+        MatVecMul,
+    };
+
+    let (rows, cols) = (16, 16);
+    let (lap, rhs) = neg_grid_laplacian((rows, cols));
+    let n = rows * cols;
+    // S * lap * S with the scaling factors spread over six decades
+    let s: Vec<f64> = (0..n).map(|i| 10_f64.powi((i % 7) as i32 - 3)).collect();
+    let mut tri = sprs::TriMat::<f64>::new((n, n));
+    for (v, (i, j)) in lap.iter() {
+        tri.add_triplet(i, j, s[i] * v * s[j]);
+    }
+    let mat = tri.to_csc();
+    let b: Vec<f64> = rhs.iter().zip(s.iter()).map(|(v, d)| v * d).collect();
+
+    // the spread of the diagonal is a proxy of the condition number
+    let spread = |a: &dyn MatVecMul<f64>| {
+        let mut d = vec![0_f64; n];
+        a.diagonal(&mut d);
+        let max = d.iter().fold(0_f64, |acc, v| acc.max(v.abs()));
+        let min = d.iter().fold(f64::MAX, |acc, v| acc.min(v.abs()));
+        max / min
+    };
+    let plain = Equilibration::new(mat.view(), EquilibrationNorm::Norm2, 1).unwrap();
+    let ruiz = Equilibration::new(mat.view(), EquilibrationNorm::MaxAbs, 10).unwrap();
+    println!(
+        "diagonal spread: {:e}, scaled: {:e}, Ruiz: {:e}",
+        spread(&mat),
+        spread(&plain),
+        spread(&ruiz)
+    );
+    assert!(spread(&mat) > 1E11);
+    assert!(spread(&plain) < 1E6);
+    assert!(spread(&ruiz) < spread(&plain));
+    // the row norms of the Ruiz scaling are close to one
+    for vec in ruiz.matrix().outer_iterator() {
+        let m = vec.iter().fold(0_f64, |acc, (_, v)| acc.max(v.abs()));
+        approx::assert_abs_diff_eq!(m, 1., epsilon = 1E-2);
+    }
+
+    let mut x = vec![0_f64; n];
+    let its = match sprsolve::CG::new(&mat, n).solve(&b, &mut x, 10 * n, 1E-10) {
+        Ok((its, _)) => its,
+        Err(_) => 10 * n,
+    };
+    for eq in [&plain, &ruiz].iter() {
+        let mut b_eq = vec![0_f64; n];
+        eq.scale_rhs(&b, &mut b_eq);
+        let mut y = vec![0_f64; n];
+        let (its_eq, _) = sprsolve::CG::new(*eq, n)
+            .solve(&b_eq, &mut y, 10 * n, 1E-10)
+            .unwrap();
+        println!(
+            "CG: {} iterations, equilibrated CG: {} iterations",
+            its, its_eq
+        );
+        assert!(its_eq < its);
+
+        // A x = b with x = D y
+        let mut x_eq = vec![0_f64; n];
+        eq.unscale_solution(&y, &mut x_eq);
+        let mut ax = vec![0_f64; n];
+        mat.mul_vec(&x_eq, &mut ax);
+        let b_norm = b.iter().fold(0_f64, |acc, v| acc + v * v).sqrt();
+        let res = ax
+            .iter()
+            .zip(b.iter())
+            .fold(0_f64, |acc, (u, v)| acc + (u - v) * (u - v))
+            .sqrt();
+        assert!(res <= 1E-6 * b_norm);
+    }
+}
+
+/// Negative 2D grid laplacian with Dirichlet boundary, which is symmetric positive definite.
+fn neg_grid_laplacian(shape: (usize, usize)) -> (sprs::CsMat<f64>, Vec<f64>) {
+    let (rows, cols) = shape;
+    let n = rows * cols;
+    let mut rhs = vec![0_f64; n];
+    let mut ret_a = sprs::TriMat::<f64>::new((n, n));
+
+    for i in 0..rows {
+        for j in 0..cols {
+            let vid = i * cols + j;
+            ret_a.add_triplet(vid, vid, 4.);
+            rhs[vid] = ((i + j) % 5) as f64 - 2.;
+
+            if i > 0 {
+                ret_a.add_triplet(vid, vid - cols, -1.);
+            }
+            if j > 0 {
+                ret_a.add_triplet(vid, vid - 1, -1.);
+            }
+            if i < rows - 1 {
+                ret_a.add_triplet(vid, vid + cols, -1.);
+            }
+            if j < cols - 1 {
+                ret_a.add_triplet(vid, vid + 1, -1.);
+            }
+        }
+    }
+    (ret_a.to_csr(), rhs)
+}
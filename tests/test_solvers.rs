@@ -56,6 +56,1660 @@ fn test_bicg_stab() {
     );
 }
 
+#[test]
+fn bicg_stab_breakdown_tol() {
+    // With b = e_1, the first shadow inner product r0.r1 is proportional to
+    // A[0][1] * A[1][0] + A[0][2] * A[2][0], which nearly vanishes here.
+    let n = 4;
+    let mut tri = sprs::TriMat::<f64>::new((n, n));
+    for i in 0..n {
+        tri.add_triplet(i, i, 2. + 0.1 * i as f64);
+    }
+    tri.add_triplet(0, 1, 1.);
+    tri.add_triplet(1, 0, 1.);
+    tri.add_triplet(0, 2, 1.);
+    tri.add_triplet(2, 0, -1. + 1E-12);
+    for i in 1..n - 1 {
+        tri.add_triplet(i, i + 1, 0.5);
+        tri.add_triplet(i + 1, i, -0.3);
+    }
+    let mat = tri.to_csr();
+    let mut rhs = vec![0_f64; n];
+    rhs[0] = 1.;
+
+    let mut x1 = vec![0_f64; n];
+    let mut solver = sprsolve::BiCGStab::new(&mat, n);
+    approx::assert_abs_diff_eq!(solver.breakdown_tol(), f64::EPSILON);
+    let (its1, _) = solver.solve(&rhs, &mut x1, 100, 1E-10).unwrap();
+
+    // a larger tolerance restarts the shadow residual right away
+    let mut x2 = vec![0_f64; n];
+    solver.set_breakdown_tol(1E-4);
+    let (its2, _) = solver.solve(&rhs, &mut x2, 100, 1E-10).unwrap();
+    println!(
+        "{} iterations by default, {} with a larger tolerance",
+        its1, its2
+    );
+    assert!(its2 < its1);
+    for (u, v) in x1.iter().zip(x2.iter()) {
+        approx::assert_abs_diff_eq!(u, v, epsilon = 1E-9);
+    }
+
+    // the checks are relative, so a tiny scaling of A doesn't make them fire
+    let scale = 1E-40;
+    let scaled = mat.map(|v| v * scale);
+    let mut x3 = vec![0_f64; n];
+    let (its3, _) = sprsolve::BiCGStab::new(&scaled, n)
+        .solve(&rhs, &mut x3, 100, 1E-10)
+        .unwrap();
+    assert_eq!(its3, its1);
+    for (u, v) in x1.iter().zip(x3.iter()) {
+        approx::assert_relative_eq!(u / scale, v, max_relative = 1E-8);
+    }
+}
+
+/// Forwards to `CpuVecOps` and counts the calls
+#[derive(Default)]
+struct CountingOps {
+    calls: std::cell::Cell<usize>,
+}
+
+impl sprsolve::VecOps<f64> for CountingOps {
+    fn dot(&self, x: &[f64], y: &[f64]) -> f64 {
+        self.calls.set(self.calls.get() + 1);
+        sprsolve::CpuVecOps.dot(x, y)
+    }
+    fn conj_dot(&self, x: &[f64], y: &[f64]) -> f64 {
+        self.calls.set(self.calls.get() + 1);
+        sprsolve::CpuVecOps.conj_dot(x, y)
+    }
+    fn norm2(&self, x: &[f64]) -> f64 {
+        self.calls.set(self.calls.get() + 1);
+        sprsolve::CpuVecOps.norm2(x)
+    }
+    fn scale(&self, a: f64, x: &mut [f64]) {
+        self.calls.set(self.calls.get() + 1);
+        sprsolve::CpuVecOps.scale(a, x)
+    }
+    fn rscale(&self, a: f64, x: &mut [f64]) {
+        self.calls.set(self.calls.get() + 1);
+        sprsolve::CpuVecOps.rscale(a, x)
+    }
+    fn conj(&self, x: &[f64], y: &mut [f64]) {
+        self.calls.set(self.calls.get() + 1);
+        sprsolve::CpuVecOps.conj(x, y)
+    }
+    fn axpy(&self, a: f64, x: &[f64], y: &mut [f64]) {
+        self.calls.set(self.calls.get() + 1);
+        sprsolve::CpuVecOps.axpy(a, x, y)
+    }
+    fn axpby(&self, a: f64, x: &[f64], b: f64, y: &mut [f64]) {
+        self.calls.set(self.calls.get() + 1);
+        sprsolve::CpuVecOps.axpby(a, x, b, y)
+    }
+}
+
+#[test]
+fn custom_vec_ops() {
+    let (rows, cols) = (10, 10);
+    let lap = grid_laplacian((rows, cols));
+    let mut rhs = vec![0_f64; rows * cols];
+    set_boundary_condition(rhs.as_mut_slice(), (rows, cols), |row, col| {
+        (row + col) as f64
+    });
+
+    let mut x1 = vec![0_f64; rows * cols];
+    let mut x2 = vec![0_f64; rows * cols];
+    let r1 = sprsolve::BiCGStab::new(&lap, lap.cols())
+        .solve(&rhs, &mut x1, 500, 1E-10)
+        .unwrap();
+    let mut solver = sprsolve::BiCGStab::with_vec_ops(&lap, lap.cols(), CountingOps::default());
+    let r2 = solver.solve(&rhs, &mut x2, 500, 1E-10).unwrap();
+    assert_eq!(r1, r2);
+    assert_eq!(x1, x2);
+
+    // MINRES needs a symmetric system
+    let n = rows * cols;
+    let mut tri = sprs::TriMat::<f64>::new((n, n));
+    for i in 0..n {
+        tri.add_triplet(i, i, 2.);
+        if i > 0 {
+            tri.add_triplet(i, i - 1, -1.);
+            tri.add_triplet(i - 1, i, -1.);
+        }
+    }
+    let lap = tri.to_csr();
+    let mut x1 = vec![0_f64; n];
+    let mut x2 = vec![0_f64; n];
+    let r1 = sprsolve::MinRes::new(&lap, lap.cols())
+        .solve(&rhs, &mut x1, 500, 1E-10)
+        .unwrap();
+    let ops = CountingOps::default();
+    let r2 = sprsolve::MinRes::with_vec_ops(&lap, lap.cols(), &ops)
+        .solve(&rhs, &mut x2, 500, 1E-10)
+        .unwrap();
+    assert_eq!(r1, r2);
+    assert_eq!(x1, x2);
+    assert!(ops.calls.get() > r2.0);
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn solve_multi_parallel() {
+    let (rows, cols) = (10, 10);
+    let lap = grid_laplacian((rows, cols));
+    let rhss: Vec<Vec<f64>> = (0..16)
+        .map(|k| {
+            let mut rhs = vec![0_f64; rows * cols];
+            set_boundary_condition(rhs.as_mut_slice(), (rows, cols), |row, col| {
+                (row * (k + 1) + col) as f64
+            });
+            rhs
+        })
+        .collect();
+
+    let results = sprsolve::solve_multi_parallel(&lap, &rhss, 500, 1E-10);
+    assert_eq!(results.len(), rhss.len());
+    for (rhs, ret) in rhss.iter().zip(results) {
+        let (x, iters, res) = ret.unwrap();
+        let mut x_serial = vec![0_f64; rows * cols];
+        let (iters_serial, res_serial) = sprsolve::BiCGStab::new(&lap, lap.cols())
+            .solve(rhs, &mut x_serial, 500, 1E-10)
+            .unwrap();
+        assert_eq!(iters, iters_serial);
+        approx::assert_relative_eq!(res, res_serial, max_relative = 1E-8);
+        for (a, b) in x.iter().zip(x_serial.iter()) {
+            approx::assert_relative_eq!(*a, *b, max_relative = 1E-8, epsilon = 1E-12);
+        }
+    }
+}
+
+#[test]
+fn cg_solve_sampling() {
+    let (rows, cols) = (16, 16);
+    let (lap, rhs) = neg_grid_laplacian((rows, cols));
+    let n = rows * cols;
+
+    let mut x = vec![0_f64; n];
+    let (its, _, all) = sprsolve::CG::new(&lap, n)
+        .solve_sampling(&rhs, &mut x, 1000, 1E-12, 1)
+        .unwrap();
+    assert_eq!(all.len(), its);
+    assert_eq!(all.last().unwrap(), &x);
+
+    let k = 5;
+    let mut x = vec![0_f64; n];
+    let (its_k, _, samples) = sprsolve::CG::new(&lap, n)
+        .solve_sampling(&rhs, &mut x, 1000, 1E-12, k)
+        .unwrap();
+    assert_eq!(its_k, its);
+    assert_eq!(samples.len(), its / k);
+    for (i, s) in samples.iter().enumerate() {
+        assert_eq!(s, &all[(i + 1) * k - 1]);
+    }
+}
+
+#[test]
+fn cg_identity_precond() {
+    use sprsolve::precond::{Identity, Precond};
+
+    let (rows, cols) = (16, 16);
+    let (lap, rhs) = neg_grid_laplacian((rows, cols));
+    let n = rows * cols;
+
+    let mut x = vec![0_f64; n];
+    let (its, res) = sprsolve::CG::new(&lap, n)
+        .solve(&rhs, &mut x, 1000, 1E-12)
+        .unwrap();
+
+    // the preconditioner is chosen at runtime
+    let precond: Box<dyn Precond<f64>> = Box::new(Identity);
+    let mut x_pc = vec![0_f64; n];
+    let (its_pc, res_pc) = sprsolve::CG::new(&lap, n)
+        .precond_solve(precond.as_ref(), &rhs, &mut x_pc, 1000, 1E-12)
+        .unwrap();
+    assert_eq!(its_pc, its);
+    assert_eq!(res_pc.to_bits(), res.to_bits());
+    assert!(x_pc
+        .iter()
+        .zip(x.iter())
+        .all(|(a, b)| a.to_bits() == b.to_bits()));
+
+    let mut y = rhs.clone();
+    precond.apply_in_place(&mut y);
+    assert_eq!(y, rhs);
+}
+
+#[test]
+fn jacobi_precond() {
+    // diagonally dominant, with a diagonal spanning several orders of magnitude
+    let n = 200;
+    let mut tri = sprs::TriMat::<f64>::new((n, n));
+    for i in 0..n {
+        tri.add_triplet(i, i, 2. + 1E3 * (i % 7) as f64);
+        if i > 0 {
+            tri.add_triplet(i, i - 1, -1.);
+            tri.add_triplet(i - 1, i, -1.);
+        }
+    }
+    let mat = tri.to_csr();
+    let rhs: Vec<f64> = (0..n).map(|i| ((i * 3) % 5) as f64 - 2.).collect();
+    let jacobi = sprsolve::precond::Jacobi::new(mat.view()).unwrap();
+
+    let mut x = vec![0_f64; n];
+    let (its, _) = sprsolve::CG::new(&mat, n)
+        .solve(&rhs, &mut x, 1000, 1E-10)
+        .unwrap();
+    let mut x = vec![0_f64; n];
+    let (its_pc, _) = sprsolve::CG::new(&mat, n)
+        .precond_solve(&jacobi, &rhs, &mut x, 1000, 1E-10)
+        .unwrap();
+    assert!(its_pc < its);
+
+    let mut x = vec![0_f64; n];
+    let (its, _) = sprsolve::BiCGStab::new(&mat, n)
+        .solve(&rhs, &mut x, 1000, 1E-10)
+        .unwrap();
+    let mut x = vec![0_f64; n];
+    let (its_pc, _) = sprsolve::BiCGStab::new(&mat, n)
+        .precond_solve(&jacobi, &rhs, &mut x, 1000, 1E-10)
+        .unwrap();
+    assert!(its_pc < its);
+
+    // zero diagonal entry
+    let mut tri = sprs::TriMat::<f64>::new((2, 2));
+    tri.add_triplet(0, 0, 1.);
+    tri.add_triplet(1, 0, 1.);
+    let mat = tri.to_csr();
+    assert!(matches!(
+        sprsolve::precond::Jacobi::new(mat.view()),
+        Err(sprsolve::error::SolverError::ZeorDiagonalElem(1))
+    ));
+}
+
+#[test]
+fn block_jacobi_precond() {
+    use sprsolve::precond::{BlockJacobi, Jacobi};
+    use sprsolve::MatVecMul;
+
+    // 2 strongly coupled unknowns per node, and a weak coupling between the nodes; the
+    // size is odd, so the last block has a single unknown
+    let n = 201;
+    let mut tri = sprs::TriMat::<f64>::new((n, n));
+    for i in 0..n {
+        let scale = 1. + (i / 2 % 5) as f64;
+        tri.add_triplet(i, i, 4. * scale);
+        if i % 2 == 0 && i + 1 < n {
+            tri.add_triplet(i, i + 1, 3.5 * scale);
+            tri.add_triplet(i + 1, i, -3. * scale);
+        }
+        if i >= 2 {
+            tri.add_triplet(i, i - 2, -0.5);
+            tri.add_triplet(i - 2, i, -0.4);
+        }
+    }
+    let mat = tri.to_csr();
+    let rhs: Vec<f64> = (0..n).map(|i| ((i * 3) % 5) as f64 - 2.).collect();
+    let block = BlockJacobi::new(mat.view(), 2).unwrap();
+    assert_eq!(block.block_size(), 2);
+
+    // M^{-1} inverts the block diagonal part
+    let mut tri = sprs::TriMat::<f64>::new((n, n));
+    for (&v, (i, j)) in mat.iter() {
+        if i / 2 == j / 2 {
+            tri.add_triplet(i, j, v);
+        }
+    }
+    let diag_blocks: sprs::CsMat<f64> = tri.to_csr();
+    let mut y = vec![0_f64; n];
+    let mut z = vec![0_f64; n];
+    diag_blocks.mul_vec(&rhs, &mut y);
+    block.mul_vec(&y, &mut z);
+    for (u, v) in z.iter().zip(rhs.iter()) {
+        approx::assert_abs_diff_eq!(u, v, epsilon = 1E-12);
+    }
+    diag_blocks.mul_vec_trans(&rhs, &mut y).unwrap();
+    block.mul_vec_trans(&y, &mut z).unwrap();
+    for (u, v) in z.iter().zip(rhs.iter()) {
+        approx::assert_abs_diff_eq!(u, v, epsilon = 1E-12);
+    }
+    // the same on the CSC storage
+    let block_csc = BlockJacobi::new(mat.to_csc().view(), 2).unwrap();
+    let mut z_csc = vec![0_f64; n];
+    block_csc.mul_vec_trans(&y, &mut z_csc).unwrap();
+    assert_eq!(z, z_csc);
+
+    // the block preconditioner beats the scalar one
+    let jacobi = Jacobi::new(mat.view()).unwrap();
+    let mut x = vec![0_f64; n];
+    let (its_jacobi, _) = sprsolve::BiCGStab::new(&mat, n)
+        .precond_solve(&jacobi, &rhs, &mut x, 1000, 1E-10)
+        .unwrap();
+    let mut x = vec![0_f64; n];
+    let (its_block, res) = sprsolve::BiCGStab::new(&mat, n)
+        .precond_solve(&block, &rhs, &mut x, 1000, 1E-10)
+        .unwrap();
+    println!("Jacobi: {}, block Jacobi: {}", its_jacobi, its_block);
+    assert!(its_block < its_jacobi);
+    assert!(res <= 1E-10);
+
+    // a singular block
+    let mut tri = sprs::TriMat::<f64>::new((4, 4));
+    for &(i, j, v) in [
+        (0, 0, 1.),
+        (1, 1, 1.),
+        (2, 2, 1.),
+        (2, 3, 2.),
+        (3, 2, 2.),
+        (3, 3, 4.),
+    ]
+    .iter()
+    {
+        tri.add_triplet(i, j, v);
+    }
+    let mat = tri.to_csr();
+    assert!(matches!(
+        BlockJacobi::new(mat.view(), 2),
+        Err(sprsolve::error::SolverError::BreakDown(3))
+    ));
+    assert!(BlockJacobi::new(mat.view(), 0).is_err());
+}
+
+#[test]
+fn ilu0_precond() {
+    use sprsolve::precond::ILU0;
+    use sprsolve::MatVecMul;
+
+    // ILU(0) of a tridiagonal matrix has no dropped fill-in, hence it is an exact solve
+    let n = 50;
+    let mut tri = sprs::TriMat::<f64>::new((n, n));
+    for i in 0..n {
+        tri.add_triplet(i, i, 4. + (i % 3) as f64);
+        if i > 0 {
+            tri.add_triplet(i, i - 1, -1.5);
+            tri.add_triplet(i - 1, i, -0.5);
+        }
+    }
+    let mat = tri.to_csr();
+    let ilu = ILU0::new(mat.view()).unwrap();
+    let x: Vec<f64> = (0..n).map(|i| (i as f64).sin()).collect();
+    let mut b = vec![0_f64; n];
+    let mut z = vec![0_f64; n];
+    mat.mul_vec(&x, &mut b);
+    ilu.mul_vec(&b, &mut z);
+    for (u, v) in z.iter().zip(x.iter()) {
+        approx::assert_abs_diff_eq!(u, v, epsilon = 1E-12);
+    }
+    // the CSC storage gives the same factorization
+    let ilu = ILU0::new(mat.to_csc().view()).unwrap();
+    ilu.mul_vec(&b, &mut z);
+    for (u, v) in z.iter().zip(x.iter()) {
+        approx::assert_abs_diff_eq!(u, v, epsilon = 1E-12);
+    }
+
+    // nonsymmetric convection-diffusion on a 2D grid
+    let n = 20 * 20;
+    let mat = convection_diffusion(20);
+    let rhs: Vec<f64> = (0..n).map(|i| ((i * 7) % 11) as f64 - 5.).collect();
+    let ilu = ILU0::new(mat.view()).unwrap();
+
+    let mut x = vec![0_f64; n];
+    let (its, _) = sprsolve::BiCGStab::new(&mat, n)
+        .solve(&rhs, &mut x, 1000, 1E-10)
+        .unwrap();
+    let mut x = vec![0_f64; n];
+    let (its_pc, res) = sprsolve::BiCGStab::new(&mat, n)
+        .precond_solve(&ilu, &rhs, &mut x, 1000, 1E-10)
+        .unwrap();
+    println!(
+        "BiCGStab: {} iterations, ILU(0)-BiCGStab: {} iterations",
+        its, its_pc
+    );
+    assert!(its_pc < its);
+    assert!(res <= 1E-10);
+
+    // zero pivot
+    let mut tri = sprs::TriMat::<f64>::new((2, 2));
+    tri.add_triplet(0, 0, 1.);
+    tri.add_triplet(0, 1, 1.);
+    tri.add_triplet(1, 0, 1.);
+    tri.add_triplet(1, 1, 1.);
+    let mat = tri.to_csr();
+    assert!(matches!(
+        ILU0::new(mat.view()),
+        Err(sprsolve::error::SolverError::BreakDown(1))
+    ));
+}
+
+#[test]
+fn precond_side_and_transpose() {
+    use sprsolve::precond::{GaussSeidelPrecond, Jacobi, Precond, ILU0, SSOR};
+    use sprsolve::{MatVecMul, Side};
+
+    let m = 20;
+    let n = m * m;
+    let mat = convection_diffusion(m);
+    let rhs: Vec<f64> = (0..n).map(|i| ((i * 7) % 11) as f64 - 5.).collect();
+    let ilu = ILU0::new(mat.view()).unwrap();
+    let rel_residual = |x: &[f64]| {
+        let mut r = vec![0_f64; n];
+        mat.mul_vec(x, &mut r);
+        let res = r
+            .iter()
+            .zip(rhs.iter())
+            .fold(0_f64, |acc, (a, b)| acc + (a - b) * (a - b));
+        let b = rhs.iter().fold(0_f64, |acc, b| acc + b * b);
+        (res / b).sqrt()
+    };
+
+    // the left solve runs in the workspace of the solver, which is reused afterwards
+    let mut solver = sprsolve::BiCGStab::new(&mat, n);
+    for side in [Side::Left, Side::Right].iter() {
+        let mut x = vec![0_f64; n];
+        let (its, _) = solver
+            .precond_solve_side(&ilu, *side, &rhs, &mut x, 1000, 1E-10)
+            .unwrap();
+        println!("{:?} ILU(0)-BiCGStab: {} iterations", side, its);
+        assert!(rel_residual(&x) <= 1E-8);
+    }
+    let mut x = vec![0_f64; n];
+    solver.solve(&rhs, &mut x, 1000, 1E-10).unwrap();
+    assert!(rel_residual(&x) <= 1E-8);
+
+    // the flexible solvers only precondition on the right
+    let mut x = vec![0_f64; n];
+    sprsolve::FGMRES::new(&mat, n, 30)
+        .precond_solve_side(&ilu, Side::Right, &rhs, &mut x, 1000, 1E-10)
+        .unwrap();
+    assert!(rel_residual(&x) <= 1E-8);
+    assert!(matches!(
+        sprsolve::FGMRES::new(&mat, n, 30).precond_solve_side(
+            &ilu,
+            Side::Left,
+            &rhs,
+            &mut x,
+            1000,
+            1E-10
+        ),
+        Err(sprsolve::error::SolverError::InvalidParameter(_))
+    ));
+    assert!(matches!(
+        sprsolve::GCR::new(&mat, n, 10).precond_solve_side(
+            &ilu,
+            Side::Left,
+            &rhs,
+            &mut x,
+            1000,
+            1E-10
+        ),
+        Err(sprsolve::error::SolverError::InvalidParameter(_))
+    ));
+
+    // <M^{-T} r, s> = <r, M^{-1} s>
+    let jacobi = Jacobi::new(mat.view()).unwrap();
+    // the sweeps scale by the diagonal, so it must not be constant to be checked
+    let mut tri = sprs::TriMat::new((n, n));
+    for (&v, (i, j)) in mat.iter() {
+        tri.add_triplet(i, j, v * (1 + i % 3) as f64);
+    }
+    let scaled: sprs::CsMat<f64> = tri.to_csr();
+    let gs = GaussSeidelPrecond::new(scaled.view(), false).unwrap();
+    let sgs = GaussSeidelPrecond::new(scaled.view(), true).unwrap();
+    let ssor = SSOR::new(scaled.view(), 1.5).unwrap();
+    let r: Vec<f64> = (0..n).map(|i| (i as f64).sin()).collect();
+    let s: Vec<f64> = (0..n).map(|i| (i as f64 * 0.3).cos()).collect();
+    let dot = |a: &[f64], b: &[f64]| a.iter().zip(b.iter()).fold(0., |acc, (u, v)| acc + u * v);
+    let pcs: [&dyn Precond<f64>; 5] = [&ilu, &jacobi, &gs, &sgs, &ssor];
+    for pc in pcs.iter() {
+        let mut mt_r = vec![0_f64; n];
+        let mut m_s = vec![0_f64; n];
+        pc.apply_trans(&r, &mut mt_r).unwrap();
+        pc.apply(&s, &mut m_s);
+        approx::assert_relative_eq!(dot(&mt_r, &s), dot(&r, &m_s), max_relative = 1E-12);
+    }
+
+    // an operator without the transposed product is reported, not a panic
+    let op = ProductsOnly(&mat);
+    let mut mt_r = vec![0_f64; n];
+    assert!(matches!(
+        op.apply_trans(&r, &mut mt_r),
+        Err(sprsolve::error::SolverError::InvalidParameter(_))
+    ));
+    let mut x = vec![0_f64; n];
+    assert!(matches!(
+        sprsolve::BiCG::new(&op, n).solve(&rhs, &mut x, 1000, 1E-10),
+        Err(sprsolve::error::SolverError::InvalidParameter(_))
+    ));
+    assert!(matches!(
+        sprsolve::QMR::new(&op, n).solve(&rhs, &mut x, 1000, 1E-10),
+        Err(sprsolve::error::SolverError::InvalidParameter(_))
+    ));
+}
+
+#[test]
+fn bicg_stab_l() {
+    use sprsolve::{BiCGStab, BiCGStabL, MatVecMul};
+
+    let m = 20;
+    let n = m * m;
+    let mat = convection_diffusion(m);
+    let rhs: Vec<f64> = (0..n).map(|i| ((i * 7) % 11) as f64 - 5.).collect();
+
+    // ell = 1 reduces to BiCGStab
+    let mut x = vec![0_f64; n];
+    let (its, _) = BiCGStab::new(&mat, n)
+        .solve(&rhs, &mut x, 1000, 1E-10)
+        .unwrap();
+    let mut xl = vec![0_f64; n];
+    let (its_l, _) = BiCGStabL::new(&mat, n, 1)
+        .solve(&rhs, &mut xl, 1000, 1E-10)
+        .unwrap();
+    println!(
+        "BiCGStab: {} iterations, BiCGStab(1): {} iterations",
+        its, its_l
+    );
+    assert!((its as isize - its_l as isize).abs() <= 1);
+    for (a, b) in x.iter().zip(xl.iter()) {
+        approx::assert_abs_diff_eq!(*a, *b, epsilon = 1E-6);
+    }
+
+    for ell in [2, 4].iter() {
+        let mut x = vec![0_f64; n];
+        let (its, res) = BiCGStabL::new(&mat, n, *ell)
+            .solve(&rhs, &mut x, 1000, 1E-10)
+            .unwrap();
+        println!("BiCGStab({}): {} iterations", ell, its);
+        assert!(res <= 1E-10);
+        let mut r = vec![0_f64; n];
+        mat.mul_vec(&x, &mut r);
+        let err = r
+            .iter()
+            .zip(rhs.iter())
+            .fold(0_f64, |acc, (a, b)| acc.max((a - b).abs()));
+        assert!(err <= 1E-7);
+    }
+
+    // the breakdown checks don't depend on the scale of the system
+    for scale in [1E-8, 1E8, 1E12].iter() {
+        let scaled: Vec<f64> = rhs.iter().map(|b| b * scale).collect();
+        let mut xs = vec![0_f64; n];
+        let (_, res) = BiCGStabL::new(&mat, n, 2)
+            .solve(&scaled, &mut xs, 1000, 1E-10)
+            .unwrap();
+        assert!(res <= 1E-10);
+        for (a, b) in xs.iter().zip(x.iter()) {
+            approx::assert_abs_diff_eq!(*a, *b * scale, epsilon = 1E-6 * scale);
+        }
+    }
+}
+
+#[test]
+fn bicg() {
+    use sprsolve::{BiCG, MatVecMul};
+
+    let m = 10;
+    let n = m * m;
+    let mat = convection_diffusion(m);
+    let rhs: Vec<f64> = (0..n).map(|i| ((i * 7) % 11) as f64 - 5.).collect();
+    let mut x = vec![0_f64; n];
+    let (its, res) = BiCG::new(&mat, n).solve(&rhs, &mut x, 1000, 1E-10).unwrap();
+    println!("BiCG: {} iterations", its);
+    assert!(res <= 1E-10);
+    let mut r = vec![0_f64; n];
+    mat.mul_vec(&x, &mut r);
+    let err = r
+        .iter()
+        .zip(rhs.iter())
+        .fold(0_f64, |acc, (a, b)| acc.max((a - b).abs()));
+    assert!(err <= 1E-8);
+}
+
+#[test]
+fn bicg_breakdown() {
+    use sprsolve::{error::SolverError, BiCG};
+
+    // With A = [0 1; 1 0] and b = e_1, the first search direction p = e_1 satisfies
+    // p^T A p = 0, so the step length is undefined.
+    let mut tri = sprs::TriMat::<f64>::new((2, 2));
+    tri.add_triplet(0, 1, 1.);
+    tri.add_triplet(1, 0, 1.);
+    let mat: sprs::CsMat<f64> = tri.to_csr();
+    let rhs = [1., 0.];
+    let mut x = [0.; 2];
+    match BiCG::new(&mat, 2).solve(&rhs, &mut x, 10, 1E-10) {
+        Err(SolverError::BreakDown(0)) => {}
+        other => panic!("expected a breakdown, got {:?}", other),
+    }
+}
+
+#[test]
+fn qmr() {
+    use sprsolve::{BiCG, MatVecMul, QMR};
+
+    let m = 20;
+    let n = m * m;
+    let mat = convection_diffusion(m);
+    let rhs: Vec<f64> = (0..n).map(|i| ((i * 7) % 11) as f64 - 5.).collect();
+
+    let mut x_bicg = vec![0_f64; n];
+    let (its_bicg, _) = BiCG::new(&mat, n)
+        .solve(&rhs, &mut x_bicg, 1000, 1E-10)
+        .unwrap();
+    let mut x = vec![0_f64; n];
+    let (its, res) = QMR::new(&mat, n).solve(&rhs, &mut x, 1000, 1E-10).unwrap();
+    println!("QMR: {} iterations, BiCG: {} iterations", its, its_bicg);
+    assert!(res <= 1E-10);
+    // both build the same Krylov subspaces
+    assert!(its <= its_bicg + its_bicg / 10);
+
+    let mut r = vec![0_f64; n];
+    mat.mul_vec(&x, &mut r);
+    let err = r
+        .iter()
+        .zip(rhs.iter())
+        .fold(0_f64, |acc, (a, b)| acc.max((a - b).abs()));
+    assert!(err <= 1E-7);
+}
+
+#[test]
+fn qmr_breakdown() {
+    use sprsolve::{error::SolverError, QMR};
+
+    // With b = e_1, the first step gives v_2 = e_2 and w_2 = e_3, so that w_2^T v_2 = 0
+    // although neither vector vanishes.
+    let mut tri = sprs::TriMat::<f64>::new((3, 3));
+    for &(i, j, v) in [
+        (0, 0, 2.),
+        (0, 2, 1.),
+        (1, 0, 1.),
+        (1, 1, 2.),
+        (2, 1, 1.),
+        (2, 2, 2.),
+    ]
+    .iter()
+    {
+        tri.add_triplet(i, j, v);
+    }
+    let mat: sprs::CsMat<f64> = tri.to_csr();
+    let rhs = [1., 0., 0.];
+    let mut x = [0.; 3];
+    match QMR::new(&mat, 3).solve(&rhs, &mut x, 10, 1E-10) {
+        Err(SolverError::BreakDown(1)) => {}
+        other => panic!("expected a breakdown, got {:?}", other),
+    }
+}
+
+#[test]
+fn shared_solver_workspaces() {
+    use sprsolve::{BiCGStab, MatVecMul, Workspace};
+
+    let m = 12;
+    let n = m * m;
+    let mat = convection_diffusion(m);
+    let rhss: Vec<Vec<f64>> = (0..2)
+        .map(|k| (0..n).map(|i| ((i * (7 + k)) % 11) as f64 - 5.).collect())
+        .collect();
+
+    let solver = BiCGStab::new(&mat, n);
+    let solutions: Vec<Vec<f64>> = std::thread::scope(|s| {
+        let handles: Vec<_> = rhss
+            .iter()
+            .map(|rhs| {
+                let solver = &solver;
+                s.spawn(move || {
+                    let mut ws =
+                        Workspace::new(BiCGStab::<f64, sprs::CsMat<f64>>::workspace_len(n));
+                    let mut x = vec![0_f64; n];
+                    solver
+                        .solve_with_workspace(&mut ws, rhs, &mut x, 1000, 1E-10)
+                        .unwrap();
+                    x
+                })
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+
+    for (rhs, x) in rhss.iter().zip(solutions.iter()) {
+        let mut r = vec![0_f64; n];
+        mat.mul_vec(x, &mut r);
+        let err = r
+            .iter()
+            .zip(rhs.iter())
+            .fold(0_f64, |acc, (a, b)| acc.max((a - b).abs()));
+        assert!(err <= 1E-7);
+    }
+
+    let mut short = Workspace::new(n);
+    let mut x = vec![0_f64; n];
+    assert!(solver
+        .solve_with_workspace(&mut short, &rhss[0], &mut x, 1000, 1E-10)
+        .is_err());
+}
+
+#[test]
+fn bicg_stab_jacobi_true_residual() {
+    let (rows, cols) = (16, 16);
+    let lap = grid_laplacian((rows, cols));
+    let mut rhs = vec![0_f64; rows * cols];
+    set_boundary_condition(rhs.as_mut_slice(), (rows, cols), |row, col| {
+        (row + col) as f64
+    });
+    let jacobi = sprsolve::precond::Jacobi::new(lap.view()).unwrap();
+
+    let tol = 1E-9;
+    let mut x = vec![0_f64; rows * cols];
+    let (_, res) = sprsolve::BiCGStab::new(&lap, lap.cols())
+        .precond_solve(&jacobi, &rhs, &mut x, 1000, tol)
+        .unwrap();
+    assert!(res <= tol);
+
+    // the reported residual is the unpreconditioned one
+    let mut ax = vec![0_f64; rows * cols];
+    sprsolve::MatVecMul::mul_vec(&lap, &x, &mut ax);
+    let rhs_norm = rhs.iter().fold(0_f64, |acc, v| acc + v * v).sqrt();
+    let err = ax
+        .iter()
+        .zip(rhs.iter())
+        .fold(0_f64, |acc, (u, v)| acc + (u - v) * (u - v))
+        .sqrt();
+    approx::assert_relative_eq!(err / rhs_norm, res, max_relative = 1E-3);
+}
+
+#[test]
+fn precond_residual_kind() {
+    use sprsolve::{precond::Jacobi, MatVecMul, ResidualKind, CG, GCR};
+
+    // a badly scaled SPD matrix D L D, so that |M^{-1}r| / |M^{-1}b| and |r| / |b| differ
+    let m = 16;
+    let n = m * m;
+    let d: Vec<f64> = (0..n).map(|i| 10_f64.powi((i % 3) as i32 - 1)).collect();
+    let mut tri = sprs::TriMat::<f64>::new((n, n));
+    for k in 0..n {
+        tri.add_triplet(k, k, 4. * d[k] * d[k]);
+        for &l in &[k.wrapping_sub(1), k + 1, k.wrapping_sub(m), k + m] {
+            let neighbor = l < n && (l / m == k / m || l % m == k % m);
+            if neighbor {
+                tri.add_triplet(k, l, -d[k] * d[l]);
+            }
+        }
+    }
+    let mat: sprs::CsMat<f64> = tri.to_csr();
+    let rhs: Vec<f64> = (0..n).map(|i| ((i * 5) % 9) as f64 - 4.).collect();
+    let jacobi = Jacobi::new(mat.view()).unwrap();
+
+    // the true and the preconditioned relative residuals of x
+    let residuals = |x: &[f64]| {
+        let mut r = vec![0_f64; n];
+        mat.mul_vec(x, &mut r);
+        r.iter_mut().zip(rhs.iter()).for_each(|(r, b)| *r = b - *r);
+        let norm = |v: &[f64]| v.iter().fold(0_f64, |acc, v| acc + v * v).sqrt();
+        let (mut z, mut zb) = (vec![0_f64; n], vec![0_f64; n]);
+        jacobi.mul_vec(&r, &mut z);
+        jacobi.mul_vec(&rhs, &mut zb);
+        (norm(&r) / norm(&rhs), norm(&z) / norm(&zb))
+    };
+
+    let tol = 1E-8;
+    let mut cg = CG::new(&mat, n);
+    assert_eq!(cg.residual_kind(), ResidualKind::True);
+    let mut gcr = GCR::new(&mat, n, 50);
+    assert_eq!(gcr.residual_kind(), ResidualKind::True);
+    for &kind in &[ResidualKind::True, ResidualKind::Preconditioned] {
+        cg.set_residual_kind(kind);
+        gcr.set_residual_kind(kind);
+        let mut x_cg = vec![0_f64; n];
+        let (its_cg, res_cg) = cg
+            .precond_solve(&jacobi, &rhs, &mut x_cg, 5000, tol)
+            .unwrap();
+        let mut x_gcr = vec![0_f64; n];
+        let (its_gcr, res_gcr) = gcr
+            .solve_precond(&rhs, &mut x_gcr, &jacobi, 5000, tol)
+            .unwrap();
+        println!("{:?}: CG {} iterations, GCR {}", kind, its_cg, its_gcr);
+        assert!(res_cg <= tol && res_gcr <= tol);
+
+        for (x, res) in [(&x_cg, res_cg), (&x_gcr, res_gcr)].iter() {
+            let (true_res, precond_res) = residuals(x);
+            println!(
+                "  |r|/|b| = {:e}, |z|/|M^-1 b| = {:e}",
+                true_res, precond_res
+            );
+            let checked = match kind {
+                ResidualKind::True => true_res,
+                ResidualKind::Preconditioned => {
+                    // the scaling by M^{-1} hides most of the true residual
+                    assert!(true_res > 10. * tol);
+                    precond_res
+                }
+            };
+            approx::assert_relative_eq!(checked, *res, max_relative = 1E-3, epsilon = 1E-14);
+        }
+    }
+}
+
+#[test]
+fn boxed_solver_error() {
+    fn solve() -> Result<(), Box<dyn std::error::Error>> {
+        let lap = grid_laplacian((4, 4));
+        let rhs = vec![1_f64; 16];
+        let mut x = vec![0_f64; 16];
+        sprsolve::BiCGStab::new(&lap, lap.cols()).solve(&rhs, &mut x, 1, 1E-12)?;
+        Ok(())
+    }
+    let err = solve().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Insufficient iteration number: not converged after 1 iterations"
+    );
+    assert!(err.downcast_ref::<sprsolve::error::SolverError>().is_some());
+    assert_eq!(
+        sprsolve::error::SolverError::BreakDown(7).to_string(),
+        "Solver break down at iteration 7"
+    );
+}
+
+#[test]
+fn sor_ssor_jacobi() {
+    use sprsolve::{error::SolverError, GaussSeidel, WeightedJacobi, SOR, SSOR};
+
+    let (rows, cols) = (10, 10);
+    let lap = grid_laplacian((rows, cols));
+    let mut rhs = vec![0_f64; rows * cols];
+    set_boundary_condition(rhs.as_mut_slice(), (rows, cols), |row, col| {
+        (row + col) as f64
+    });
+    let n = rows * cols;
+
+    let mut x_gs = vec![0_f64; n];
+    let (its_gs, res_gs) = GaussSeidel::new(lap.view())
+        .unwrap()
+        .solve(&rhs, &mut x_gs, 1000, 1E-10)
+        .unwrap();
+
+    // omega = 1 is Gauss-Seidel, bit for bit
+    let mut sor = SOR::new(lap.view()).unwrap();
+    let mut x = vec![0_f64; n];
+    let (its, res) = sor.solve(&rhs, &mut x, 1., 1000, 1E-10).unwrap();
+    assert_eq!(its, its_gs);
+    assert_eq!(res.to_bits(), res_gs.to_bits());
+    for (u, v) in x.iter().zip(x_gs.iter()) {
+        assert_eq!(u.to_bits(), v.to_bits());
+    }
+
+    // over-relaxation speeds up the convergence
+    let mut x = vec![0_f64; n];
+    let (its_sor, _) = sor.solve(&rhs, &mut x, 1.5, 1000, 1E-10).unwrap();
+    println!("Gauss-Seidel: {} iterations, SOR(1.5): {}", its_gs, its_sor);
+    assert!(its_sor < its_gs);
+    for (u, v) in x.iter().zip(x_gs.iter()) {
+        approx::assert_abs_diff_eq!(u, v, epsilon = 1E-7);
+    }
+
+    let mut x = vec![0_f64; n];
+    let (its_ssor, _) = SSOR::new(lap.view())
+        .unwrap()
+        .solve(&rhs, &mut x, 1.5, 1000, 1E-10)
+        .unwrap();
+    println!("SSOR(1.5): {} iterations", its_ssor);
+    for (u, v) in x.iter().zip(x_gs.iter()) {
+        approx::assert_abs_diff_eq!(u, v, epsilon = 1E-7);
+    }
+
+    let mut jacobi = WeightedJacobi::new(lap.view()).unwrap();
+    let mut x = vec![0_f64; n];
+    let (its_jac, _) = jacobi.solve(&rhs, &mut x, 1., 5000, 1E-10).unwrap();
+    println!("Jacobi: {} iterations", its_jac);
+    assert!(its_jac > its_gs);
+    for (u, v) in x.iter().zip(x_gs.iter()) {
+        approx::assert_abs_diff_eq!(u, v, epsilon = 1E-7);
+    }
+
+    match jacobi.solve(&rhs, &mut x, 2., 10, 1E-10) {
+        Err(SolverError::InvalidParameter(_)) => {}
+        _ => panic!("omega = 2 should be rejected"),
+    }
+
+    // Gauss-Seidel solves [1, 1; 0, 1] x = (1, 1) in the second sweep, counted as 1
+    let mut tri = sprs::TriMat::<f64>::new((2, 2));
+    tri.add_triplet(0, 0, 1.);
+    tri.add_triplet(0, 1, 1.);
+    tri.add_triplet(1, 1, 1.);
+    let upper = tri.to_csr();
+    let mut x = vec![0_f64; 2];
+    let (its, _) = GaussSeidel::new(upper.view())
+        .unwrap()
+        .solve(&[1., 1.], &mut x, 10, 1E-10)
+        .unwrap();
+    assert_eq!(its, 1);
+    assert_eq!(x, [0., 1.]);
+}
+
+#[test]
+fn zero_initial_guess() {
+    let (rows, cols) = (12, 12);
+    let lap = grid_laplacian((rows, cols));
+    let mut rhs = vec![0_f64; rows * cols];
+    set_boundary_condition(rhs.as_mut_slice(), (rows, cols), |row, col| {
+        (row + col) as f64
+    });
+    let n = rows * cols;
+
+    let mut bicg_stab = sprsolve::BiCGStab::new(&lap, n);
+    let mut x = vec![0_f64; n];
+    let ret = bicg_stab.solve(&rhs, &mut x, 500, 1E-10).unwrap();
+    // the content of y is ignored
+    let mut y = vec![1_f64; n];
+    let ret_zero = bicg_stab
+        .solve_zero_guess(&rhs, &mut y, 500, 1E-10)
+        .unwrap();
+    assert_eq!(ret, ret_zero);
+    assert_eq!(x, y);
+
+    // MINRES needs a symmetric matrix
+    let mut tri = sprs::TriMat::new((n, n));
+    for i in 0..n {
+        tri.add_triplet(i, i, 2.5);
+        if i + 1 < n {
+            tri.add_triplet(i, i + 1, -1.);
+            tri.add_triplet(i + 1, i, -1.);
+        }
+    }
+    let mat: sprs::CsMat<f64> = tri.to_csr();
+    let mut minres = sprsolve::MinRes::new(&mat, n);
+    let mut x = vec![0_f64; n];
+    let ret = minres.solve(&rhs, &mut x, 500, 1E-10).unwrap();
+    let mut y = vec![-3_f64; n];
+    let ret_zero = minres.solve_zero_guess(&rhs, &mut y, 500, 1E-10).unwrap();
+    assert_eq!(ret, ret_zero);
+    assert_eq!(x, y);
+}
+
+#[test]
+fn stagnation_detection() {
+    use sprsolve::{error::SolverError, BiCGStab, MinRes, Stagnation};
+
+    // 1D Laplacian with Neumann boundary, nearly singular with the constant null vector
+    let n = 100;
+    let mut tri = sprs::TriMat::new((n, n));
+    for i in 0..n {
+        let d = if i == 0 || i + 1 == n { 1. } else { 2. };
+        tri.add_triplet(i, i, d + 1E-12);
+        if i + 1 < n {
+            tri.add_triplet(i, i + 1, -1.);
+            tri.add_triplet(i + 1, i, -1.);
+        }
+    }
+    let mat: sprs::CsMat<f64> = tri.to_csr();
+    // the constant part of rhs is almost not in the range
+    let rhs: Vec<f64> = (0..n).map(|i| 1. + (i as f64 * 0.3).sin()).collect();
+    let max_iter = 5000;
+
+    let mut minres = MinRes::new(&mat, n);
+    minres.set_stagnation(Some(Stagnation::new(1E-3, 10)));
+    let mut x = vec![0_f64; n];
+    match minres.solve(&rhs, &mut x, max_iter, 1E-10) {
+        Err(SolverError::Stagnation { iter, resid }) => {
+            println!(
+                "MINRES stagnated at iteration {} with residual {}",
+                iter, resid
+            );
+            assert!(iter < max_iter);
+            assert!(resid > 1E-10);
+        }
+        r => panic!("expected stagnation, got {:?}", r),
+    }
+
+    let mut bicg_stab = BiCGStab::new(&mat, n);
+    bicg_stab.set_stagnation(Some(Stagnation::new(1E-3, 10)));
+    let mut x = vec![0_f64; n];
+    match bicg_stab.solve(&rhs, &mut x, max_iter, 1E-10) {
+        Err(SolverError::Stagnation { iter, .. }) => assert!(iter < max_iter),
+        r => panic!("expected stagnation, got {:?}", r),
+    }
+
+    // a well-posed system still converges
+    let rhs: Vec<f64> = (0..n).map(|i| (i as f64 * 0.3).sin()).collect();
+    let rhs_mean = rhs.iter().sum::<f64>() / n as f64;
+    let rhs: Vec<f64> = rhs.iter().map(|v| v - rhs_mean).collect();
+    let mut x = vec![0_f64; n];
+    assert!(minres.solve(&rhs, &mut x, max_iter, 1E-8).is_ok());
+}
+
+#[test]
+fn solve_multi_columns() {
+    let (rows, cols) = (10, 10);
+    let n = rows * cols;
+    let lap = grid_laplacian((rows, cols));
+    let mut rhs = vec![0_f64; n * 3];
+    for (k, b) in rhs.chunks_exact_mut(n).enumerate() {
+        set_boundary_condition(b, (rows, cols), |row, col| (row * (k + 1) + col) as f64);
+    }
+
+    let mut x = vec![0_f64; n * 3];
+    let results = sprsolve::BiCGStab::new(&lap, n)
+        .solve_multi(&rhs, &mut x, 3, 500, 1E-10)
+        .unwrap();
+    assert_eq!(results.len(), 3);
+    for (k, ret) in results.iter().enumerate() {
+        let mut x_single = vec![0_f64; n];
+        let single = sprsolve::BiCGStab::new(&lap, n)
+            .solve(&rhs[k * n..(k + 1) * n], &mut x_single, 500, 1E-10)
+            .unwrap();
+        assert_eq!(*ret, single);
+        assert_eq!(&x[k * n..(k + 1) * n], x_single.as_slice());
+    }
+
+    // CG on a symmetric positive definite tridiagonal matrix
+    let mut tri = sprs::TriMat::new((n, n));
+    for i in 0..n {
+        tri.add_triplet(i, i, 4_f64);
+        if i > 0 {
+            tri.add_triplet(i, i - 1, -1.);
+            tri.add_triplet(i - 1, i, -1.);
+        }
+    }
+    let spd: sprs::CsMat<f64> = tri.to_csr();
+    let mut x = vec![0_f64; n * 3];
+    let results = sprsolve::CG::new(&spd, n)
+        .solve_multi(&rhs, &mut x, 3, 500, 1E-10)
+        .unwrap();
+    for (b, (x, ret)) in rhs.chunks(n).zip(x.chunks(n).zip(results)) {
+        assert!(ret.1 <= 1E-10);
+        let mut ax = vec![0_f64; n];
+        sprsolve::MatVecMul::mul_vec(&spd, x, &mut ax);
+        for (u, v) in ax.iter().zip(b.iter()) {
+            approx::assert_abs_diff_eq!(u, v, epsilon = 1E-7);
+        }
+    }
+
+    assert!(sprsolve::CG::new(&spd, n)
+        .solve_multi(&rhs[..2 * n], &mut x, 3, 500, 1E-10)
+        .is_err());
+}
+
+#[test]
+fn idrs_solve() {
+    use sprsolve::{BiCGStab, IDRs, MatVecMul};
+
+    let m = 20;
+    let n = m * m;
+    let mat = convection_diffusion(m);
+    let rhs: Vec<f64> = (0..n).map(|i| ((i * 7) % 11) as f64 - 5.).collect();
+
+    let mut x = vec![0_f64; n];
+    let (its_bicg, _) = BiCGStab::new(&mat, n)
+        .solve(&rhs, &mut x, 1000, 1E-10)
+        .unwrap();
+    for s in [1, 2, 4, 8].iter() {
+        let mut x = vec![0_f64; n];
+        let (its, res) = IDRs::new(&mat, n, *s)
+            .solve(&rhs, &mut x, 2000, 1E-10)
+            .unwrap();
+        println!(
+            "IDR({}): {} products, BiCGStab: {} products",
+            s,
+            its,
+            2 * its_bicg
+        );
+        assert!(res <= 1E-10);
+        let mut r = vec![0_f64; n];
+        mat.mul_vec(&x, &mut r);
+        let err = r
+            .iter()
+            .zip(rhs.iter())
+            .fold(0_f64, |acc, (a, b)| acc.max((a - b).abs()));
+        assert!(err <= 1E-7);
+        if *s == 1 {
+            // IDR(1) takes about as many products as BiCGStab
+            assert!(its <= 3 * its_bicg);
+        }
+    }
+}
+
+#[test]
+fn idrs_seed() {
+    use sprsolve::IDRs;
+
+    let m = 16;
+    let n = m * m;
+    let mat = convection_diffusion(m);
+    let rhs: Vec<f64> = (0..n).map(|i| (i % 5) as f64 - 2.).collect();
+
+    let solve = |seed: u64| {
+        let mut solver = IDRs::new(&mat, n, 4);
+        solver.set_seed(seed);
+        assert_eq!(solver.seed(), seed);
+        let mut x = vec![0_f64; n];
+        let ret = solver.solve(&rhs, &mut x, 1000, 1E-10).unwrap();
+        (ret, x)
+    };
+    let (ret0, x0) = solve(7);
+    let (ret1, x1) = solve(7);
+    assert_eq!(ret0, ret1);
+    assert_eq!(x0, x1);
+
+    // a solver gives the same results when solving again
+    let mut solver = IDRs::new(&mat, n, 4);
+    solver.set_seed(7);
+    let mut x = vec![0_f64; n];
+    solver.solve(&rhs, &mut x, 1000, 1E-10).unwrap();
+    let mut x = vec![0_f64; n];
+    assert_eq!(solver.solve(&rhs, &mut x, 1000, 1E-10).unwrap(), ret0);
+    assert_eq!(x, x0);
+
+    let mut x = vec![0_f64; n];
+    assert!(IDRs::new(&mat, n, 0)
+        .solve(&rhs, &mut x, 1000, 1E-10)
+        .is_err());
+}
+
+#[test]
+fn reuse_workspace_across_rhs() {
+    use sprsolve::{BiCGStab, MinRes};
+
+    let (rows, cols) = (10, 10);
+    let n = rows * cols;
+    let lap = grid_laplacian((rows, cols));
+    let mut rhs1 = vec![0_f64; n];
+    set_boundary_condition(&mut rhs1, (rows, cols), |row, col| (row + col) as f64);
+    let rhs2: Vec<f64> = (0..n).map(|i| ((i * 3) % 7) as f64 - 3.).collect();
+
+    // the second solve must not depend on what the first one left in the workspace
+    let mut solver = BiCGStab::new(&lap, n);
+    let mut x = vec![0_f64; n];
+    solver.solve(&rhs1, &mut x, 500, 1E-10).unwrap();
+    let mut x2 = vec![0_f64; n];
+    let ret2 = solver.solve_zero_guess(&rhs2, &mut x2, 500, 1E-10).unwrap();
+    let mut x_fresh = vec![0_f64; n];
+    let ret_fresh = BiCGStab::new(&lap, n)
+        .solve_zero_guess(&rhs2, &mut x_fresh, 500, 1E-10)
+        .unwrap();
+    assert_eq!(ret2, ret_fresh);
+    assert_eq!(x2, x_fresh);
+
+    let mut tri = sprs::TriMat::new((n, n));
+    for i in 0..n {
+        tri.add_triplet(i, i, 4_f64);
+        if i > 0 {
+            tri.add_triplet(i, i - 1, -1.);
+            tri.add_triplet(i - 1, i, -1.);
+        }
+    }
+    let spd: sprs::CsMat<f64> = tri.to_csr();
+    let mut solver = MinRes::new(&spd, n);
+    let mut x = vec![0_f64; n];
+    solver.solve(&rhs1, &mut x, 500, 1E-10).unwrap();
+    let mut x2 = vec![0_f64; n];
+    let ret2 = solver.solve(&rhs2, &mut x2, 500, 1E-10).unwrap();
+    let mut x_fresh = vec![0_f64; n];
+    let ret_fresh = MinRes::new(&spd, n)
+        .solve(&rhs2, &mut x_fresh, 500, 1E-10)
+        .unwrap();
+    assert_eq!(ret2, ret_fresh);
+    assert_eq!(x2, x_fresh);
+    assert!(ret2.1 <= 1E-10);
+}
+
+#[test]
+fn recompute_residual() {
+    use sprsolve::{BiCGStab, IterativeSolver, MatVecMul, SolveConfig, GCR};
+
+    // ill-conditioned upper bidiagonal matrix, with a far-off initial guess whose large
+    // initial residual spoils the accuracy of the recursive residual
+    let n = 30;
+    let mut tri = sprs::TriMat::new((n, n));
+    for i in 0..n {
+        let d = 10_f64.powf(-6. * i as f64 / (n - 1) as f64);
+        tri.add_triplet(i, i, d);
+        if i + 1 < n {
+            tri.add_triplet(i, i + 1, 0.01 * d);
+        }
+    }
+    let a: sprs::CsMat<f64> = tri.to_csr();
+    let rhs = vec![1_f64; n];
+    let x0: Vec<f64> = (0..n).map(|i| 1E10 * (((i * 7) % 5) as f64 - 2.)).collect();
+    let true_res = |x: &[f64]| {
+        let mut ax = vec![0_f64; n];
+        a.mul_vec(x, &mut ax);
+        let r2: f64 = ax
+            .iter()
+            .zip(rhs.iter())
+            .map(|(u, v)| (u - v) * (u - v))
+            .sum();
+        r2.sqrt() / (n as f64).sqrt()
+    };
+
+    // The gap between the true and the reported residual, relative to the latter. The
+    // assertions only compare gaps, which differ by orders of magnitude, so that they don't
+    // depend on the rounding of the reductions, e.g. in the reassociated `simd` kernels.
+    let gap = |res: f64, x: &[f64]| (true_res(x) - res).abs() / res;
+
+    let recursive = SolveConfig::default().max_iter(5000).tol(1E-10);
+    let config = recursive.recompute_residual_every(10);
+    assert_eq!(config.recompute_residual_every, 10);
+
+    // the recursive residual declares convergence too early
+    let mut x = x0.clone();
+    let (_, res) = BiCGStab::new(&a, n)
+        .solve_with_config(&rhs, &mut x, &recursive)
+        .unwrap();
+    let gap_recursive = gap(res, &x);
+    assert!(gap_recursive > 10.);
+
+    let mut x = x0.clone();
+    let (_, res) = BiCGStab::new(&a, n)
+        .solve_with_config(&rhs, &mut x, &config)
+        .unwrap();
+    assert!(res <= 1E-10);
+    assert!(gap(res, &x) < 1E-6 * gap_recursive);
+
+    // the same knob in another solver updating its residual recursively
+    let mut x = x0.clone();
+    let (_, res) = GCR::new(&a, n, 20)
+        .solve_with_config(&rhs, &mut x, &recursive)
+        .unwrap();
+    let gap_recursive = gap(res, &x);
+    assert!(gap_recursive > 10.);
+
+    let mut x = x0.clone();
+    let (_, res) = GCR::new(&a, n, 20)
+        .solve_with_config(&rhs, &mut x, &config)
+        .unwrap();
+    assert!(res <= 1E-10);
+    assert!(gap(res, &x) < 1E-6 * gap_recursive);
+}
+
+#[test]
+fn gcr_flexible_precond() {
+    use sprsolve::{precond::Precond, BiCGStab, MatVecMul, Truncation, GCR};
+
+    // an inner BiCGStab solve to a loose tolerance, which is a different operator for every
+    // right-hand side
+    struct InnerSolve<'a> {
+        mat: &'a sprs::CsMat<f64>,
+        max_iter: usize,
+    }
+    impl<'a> Precond<f64> for InnerSolve<'a> {
+        fn apply(&self, r: &[f64], z: &mut [f64]) {
+            z.iter_mut().for_each(|v| *v = 0.);
+            // not converging to 1E-2 within max_iter is fine
+            let _ = BiCGStab::new(self.mat, r.len()).solve(r, z, self.max_iter, 1E-2);
+        }
+    }
+
+    let m = 20;
+    let n = m * m;
+    let mat = convection_diffusion(m);
+    let rhs: Vec<f64> = (0..n).map(|i| ((i * 7) % 11) as f64 - 5.).collect();
+    let check = |x: &[f64]| {
+        let mut r = vec![0_f64; n];
+        mat.mul_vec(x, &mut r);
+        let err = r
+            .iter()
+            .zip(rhs.iter())
+            .fold(0_f64, |acc, (a, b)| acc.max((a - b).abs()));
+        assert!(err <= 1E-7);
+    };
+
+    let mut its_plain = 0;
+    for truncation in [Truncation::KeepRecent, Truncation::Restart].iter() {
+        let mut solver = GCR::new(&mat, n, 10);
+        solver.set_truncation(*truncation);
+        assert_eq!(solver.truncation(), *truncation);
+        let mut x = vec![0_f64; n];
+        let (its, res) = solver.solve(&rhs, &mut x, 1000, 1E-10).unwrap();
+        println!("GCR(10) {:?}: {} iterations", truncation, its);
+        assert!(res <= 1E-10);
+        check(&x);
+        its_plain = its_plain.max(its);
+    }
+
+    let inner = InnerSolve {
+        mat: &mat,
+        max_iter: 3,
+    };
+    let mut x = vec![0_f64; n];
+    let (its, res) = GCR::new(&mat, n, 10)
+        .solve_precond(&rhs, &mut x, &inner, 1000, 1E-10)
+        .unwrap();
+    println!("flexible GCR(10): {} iterations", its);
+    assert!(res <= 1E-10);
+    assert!(2 * its < its_plain);
+    check(&x);
+
+    let mut x = vec![0_f64; n];
+    assert!(matches!(
+        GCR::new(&mat, n, 0).solve(&rhs, &mut x, 10, 1E-10),
+        Err(sprsolve::error::SolverError::InvalidParameter(_))
+    ));
+}
+
+#[test]
+fn fgmres_flexible_precond() {
+    use sprsolve::{precond::Precond, BiCGStab, MatVecMul, FGMRES};
+    use std::cell::{Cell, RefCell};
+
+    // an inner BiCGStab solve to a loose tolerance, which makes FGMRES(10) converge in far
+    // fewer outer steps than the unpreconditioned one. The inner solver and its workspace
+    // are kept across the outer steps.
+    struct InnerSolve<'a> {
+        solver: RefCell<BiCGStab<'a, f64, sprs::CsMat<f64>>>,
+        max_iter: usize,
+        applies: Cell<usize>,
+    }
+    impl<'a> Precond<f64> for InnerSolve<'a> {
+        fn apply(&self, r: &[f64], z: &mut [f64]) {
+            z.iter_mut().for_each(|v| *v = 0.);
+            let _ = self.solver.borrow_mut().solve(r, z, self.max_iter, 1E-2);
+            self.applies.set(self.applies.get() + 1);
+        }
+    }
+
+    let m = 20;
+    let n = m * m;
+    let mat = convection_diffusion(m);
+    let rhs: Vec<f64> = (0..n).map(|i| ((i * 7) % 11) as f64 - 5.).collect();
+    let true_res = |x: &[f64]| {
+        let mut r = vec![0_f64; n];
+        mat.mul_vec(x, &mut r);
+        let nrm = r
+            .iter()
+            .zip(rhs.iter())
+            .fold(0_f64, |acc, (a, b)| acc + (a - b) * (a - b));
+        nrm.sqrt() / rhs.iter().fold(0_f64, |acc, b| acc + b * b).sqrt()
+    };
+
+    let mut solver = FGMRES::new(&mat, n, 10);
+    assert_eq!(solver.restart(), 10);
+    let mut x = vec![0_f64; n];
+    let (its_plain, res) = solver.solve(&rhs, &mut x, 2000, 1E-10).unwrap();
+    println!("FGMRES(10): {} iterations", its_plain);
+    assert!(res <= 1E-10);
+    assert!(true_res(&x) <= 1E-9);
+
+    let inner = InnerSolve {
+        solver: RefCell::new(BiCGStab::new(&mat, n)),
+        max_iter: 3,
+        applies: Cell::new(0),
+    };
+    let mut x = vec![0_f64; n];
+    let (its, res) = solver
+        .precond_solve(&inner, &rhs, &mut x, 2000, 1E-10)
+        .unwrap();
+    println!("flexible FGMRES(10): {} iterations", its);
+    assert!(res <= 1E-10);
+    assert!(2 * its < its_plain);
+    // one inner solve per outer step, all with the same solver
+    assert_eq!(inner.applies.get(), its);
+    approx::assert_relative_eq!(true_res(&x), res, max_relative = 1E-3, epsilon = 1E-13);
+
+    let mut x = vec![0_f64; n];
+    assert!(matches!(
+        FGMRES::new(&mat, n, 0).solve(&rhs, &mut x, 10, 1E-10),
+        Err(sprsolve::error::SolverError::InvalidParameter(_))
+    ));
+}
+
+#[test]
+fn spai_precond() {
+    use sprsolve::{error::SolverError, precond::SPAI, MatVecMul, FGMRES};
+
+    let m = 20;
+    let n = m * m;
+    let mat = convection_diffusion(m);
+    let rhs: Vec<f64> = (0..n).map(|i| ((i * 7) % 11) as f64 - 5.).collect();
+
+    // |AM - I|_F, which the wider pattern makes smaller
+    let frobenius = |spai: &SPAI<f64>| {
+        let (mut col, mut prod) = (vec![0_f64; n], vec![0_f64; n]);
+        let mut err = 0_f64;
+        for j in 0..n {
+            col.iter_mut().for_each(|v| *v = 0.);
+            col[j] = 1.;
+            let mut e = vec![0_f64; n];
+            spai.mul_vec(&col, &mut e); // the column j of M
+            mat.mul_vec(&e, &mut prod);
+            prod[j] -= 1.;
+            err += prod.iter().fold(0_f64, |acc, v| acc + v * v);
+        }
+        err.sqrt()
+    };
+    let spai1 = SPAI::new(mat.view(), 1).unwrap();
+    let spai2 = SPAI::new(mat.view(), 2).unwrap();
+    assert_eq!(spai1.matrix().nnz(), mat.nnz());
+    assert!(spai2.matrix().nnz() > spai1.matrix().nnz());
+    let (err1, err2) = (frobenius(&spai1), frobenius(&spai2));
+    println!("|AM - I|_F: {} with A, {} with A^2", err1, err2);
+    assert!(err2 < err1);
+
+    let mut solver = FGMRES::new(&mat, n, 20);
+    let mut x = vec![0_f64; n];
+    let (its_plain, res) = solver.solve(&rhs, &mut x, 2000, 1E-10).unwrap();
+    assert!(res <= 1E-10);
+    let mut its = Vec::new();
+    for spai in [&spai1, &spai2].iter() {
+        let mut x = vec![0_f64; n];
+        let (it, res) = solver
+            .precond_solve(*spai, &rhs, &mut x, 2000, 1E-10)
+            .unwrap();
+        assert!(res <= 1E-10);
+        let mut r = vec![0_f64; n];
+        mat.mul_vec(&x, &mut r);
+        let err = r
+            .iter()
+            .zip(rhs.iter())
+            .fold(0_f64, |acc, (a, b)| acc.max((a - b).abs()));
+        assert!(err <= 1E-8);
+        its.push(it);
+    }
+    println!("GMRES(20): {} iterations, with SPAI: {:?}", its_plain, its);
+    assert!(its[0] < its_plain);
+    assert!(its[1] < its[0]);
+
+    assert!(matches!(
+        SPAI::new(mat.view(), 0),
+        Err(SolverError::InvalidParameter(_))
+    ));
+}
+
+#[test]
+fn arnoldi_relation() {
+    use sprsolve::{Arnoldi, MatVecMul, ReorthKind};
+
+    let m = 10;
+    let n = m * m;
+    let a = convection_diffusion(m);
+    let v0: Vec<f64> = (0..n).map(|i| (i as f64 * 0.7).sin() + 0.5).collect();
+    let k = 20;
+    for &reorth in &[ReorthKind::Never, ReorthKind::Always, ReorthKind::IfNeeded] {
+        let mut arnoldi = Arnoldi::new(&a, n, k);
+        arnoldi.set_reorth(reorth);
+        assert!(arnoldi.start(&v0) > 0.);
+        for _ in 0..k {
+            assert!(arnoldi.step() > 0.);
+        }
+        assert_eq!(arnoldi.steps(), k);
+
+        // V_{k+1} is orthonormal
+        for i in 0..=k {
+            for j in 0..=k {
+                let d: f64 = arnoldi
+                    .vector(i)
+                    .iter()
+                    .zip(arnoldi.vector(j).iter())
+                    .map(|(u, v)| u * v)
+                    .sum();
+                let expected = if i == j { 1. } else { 0. };
+                approx::assert_abs_diff_eq!(d, expected, epsilon = 1E-10);
+            }
+        }
+        // A V_k = V_{k+1} H
+        let mut av = vec![0_f64; n];
+        for j in 0..k {
+            a.mul_vec(arnoldi.vector(j), &mut av);
+            for (i, &h) in arnoldi.hessenberg_column(j).iter().enumerate() {
+                av.iter_mut()
+                    .zip(arnoldi.vector(i).iter())
+                    .for_each(|(u, v)| *u -= h * v);
+            }
+            let err = av.iter().fold(0_f64, |acc, v| acc.max(v.abs()));
+            assert!(err <= 1E-12, "column {}: {}", j, err);
+        }
+    }
+
+    // an operator without a shape is taken at the given size
+    let op = ProductsOnly(&a);
+    let mut plain = Arnoldi::new(&op, n, k);
+    let mut arnoldi = Arnoldi::new(&a, n, k);
+    assert_eq!(plain.start(&v0), arnoldi.start(&v0));
+    for _ in 0..k {
+        assert_eq!(plain.step(), arnoldi.step());
+    }
+    let mut x = vec![0_f64; n];
+    sprsolve::FGMRES::new(&op, n, k)
+        .solve(&v0, &mut x, 500, 1E-10)
+        .unwrap();
+}
+
+#[test]
+fn arnoldi_reorthogonalization() {
+    use sprsolve::{Arnoldi, ReorthKind};
+
+    // three tight clusters of eigenvalues, so the Krylov subspace is nearly invariant after
+    // three steps and the projections cancel
+    let n = 90;
+    let mut tri = sprs::TriMat::<f64>::new((n, n));
+    for i in 0..n {
+        let c = (i % 3) as f64 + 1.;
+        tri.add_triplet(i, i, c * (1. + 1E-12 * i as f64));
+        if i + 1 < n {
+            tri.add_triplet(i, i + 1, 1E-9);
+        }
+    }
+    let a: sprs::CsMat<f64> = tri.to_csr();
+    let v0: Vec<f64> = (0..n).map(|i| (i * 7 % 13) as f64 / 13. + 0.1).collect();
+    let k = 20;
+    // the largest entry of |V^T V - I|
+    let orthogonality = |reorth: ReorthKind| {
+        let mut arnoldi = Arnoldi::new(&a, n, k);
+        arnoldi.set_reorth(reorth);
+        arnoldi.start(&v0);
+        (0..k).for_each(|_| {
+            arnoldi.step();
+        });
+        let mut err = 0_f64;
+        for i in 0..=k {
+            for j in 0..=k {
+                let d: f64 = arnoldi
+                    .vector(i)
+                    .iter()
+                    .zip(arnoldi.vector(j).iter())
+                    .map(|(u, v)| u * v)
+                    .sum();
+                err = err.max((d - if i == j { 1. } else { 0. }).abs());
+            }
+        }
+        err
+    };
+    assert!(orthogonality(ReorthKind::Never) > 1E-2);
+    assert!(orthogonality(ReorthKind::IfNeeded) < 1E-13);
+    assert!(orthogonality(ReorthKind::Always) < 1E-13);
+
+    // FGMRES builds its basis with the default IfNeeded, and its residual estimate matches
+    // the true residual
+    let mut x = vec![0_f64; n];
+    let (_, res) = sprsolve::FGMRES::new(&a, n, k)
+        .solve(&v0, &mut x, 200, 1E-12)
+        .unwrap();
+    assert!(res <= 1E-12);
+    let mut ax = vec![0_f64; n];
+    sprsolve::MatVecMul::mul_vec(&a, &x, &mut ax);
+    let rhs_norm = v0.iter().fold(0_f64, |acc, v| acc + v * v).sqrt();
+    let err = ax
+        .iter()
+        .zip(v0.iter())
+        .fold(0_f64, |acc, (u, v)| acc + (u - v) * (u - v))
+        .sqrt();
+    assert!(err / rhs_norm <= 1E-11);
+}
+
+/// 2D convection-diffusion operator on an `m x m` grid, which is nonsymmetric.
+/// A user operator providing only the products with the wrapped matrix, and no shape.
+struct ProductsOnly<'a>(&'a sprs::CsMat<f64>);
+
+impl<'a> sprsolve::MatVecMul<f64> for ProductsOnly<'a> {
+    fn mul_vec(&self, v_in: &[f64], v_out: &mut [f64]) {
+        sprsolve::MatVecMul::mul_vec(self.0, v_in, v_out)
+    }
+
+    fn mul_vec_dot(&self, v_in: &[f64], v_out: &mut [f64]) -> f64 {
+        sprsolve::MatVecMul::mul_vec_dot(self.0, v_in, v_out)
+    }
+
+    unsafe fn mul_vec_unchecked(&self, v_in: &[f64], v_out: &mut [f64]) {
+        sprsolve::MatVecMul::mul_vec_unchecked(self.0, v_in, v_out)
+    }
+
+    unsafe fn mul_vec_dot_unchecked(&self, v_in: &[f64], v_out: &mut [f64]) -> f64 {
+        sprsolve::MatVecMul::mul_vec_dot_unchecked(self.0, v_in, v_out)
+    }
+}
+
+fn convection_diffusion(m: usize) -> sprs::CsMat<f64> {
+    let n = m * m;
+    let mut tri = sprs::TriMat::<f64>::new((n, n));
+    for i in 0..m {
+        for j in 0..m {
+            let k = i * m + j;
+            tri.add_triplet(k, k, 4.);
+            if j > 0 {
+                tri.add_triplet(k, k - 1, -1.4);
+            }
+            if j + 1 < m {
+                tri.add_triplet(k, k + 1, -0.6);
+            }
+            if i > 0 {
+                tri.add_triplet(k, k - m, -1.2);
+            }
+            if i + 1 < m {
+                tri.add_triplet(k, k + m, -0.8);
+            }
+        }
+    }
+    tri.to_csr()
+}
+
+/// Negative 2D grid laplacian with Dirichlet boundary, which is symmetric positive definite.
+fn neg_grid_laplacian(shape: (usize, usize)) -> (sprs::CsMat<f64>, Vec<f64>) {
+    let (rows, cols) = shape;
+    let n = rows * cols;
+    let mut rhs = vec![0_f64; n];
+    let mut ret_a = sprs::TriMat::<f64>::new((n, n));
+
+    for i in 0..rows {
+        for j in 0..cols {
+            let vid = i * cols + j;
+            ret_a.add_triplet(vid, vid, 4.);
+            rhs[vid] = ((i + j) % 5) as f64 - 2.;
+
+            if i > 0 {
+                ret_a.add_triplet(vid, vid - cols, -1.);
+            }
+            if j > 0 {
+                ret_a.add_triplet(vid, vid - 1, -1.);
+            }
+            if i < rows - 1 {
+                ret_a.add_triplet(vid, vid + cols, -1.);
+            }
+            if j < cols - 1 {
+                ret_a.add_triplet(vid, vid + 1, -1.);
+            }
+        }
+    }
+    (ret_a.to_csr(), rhs)
+}
+
 /// Determine whether the grid location at `(row, col)` is a border
 /// of the grid defined by `shape`.
 fn is_border(row: usize, col: usize, shape: (usize, usize)) -> bool {